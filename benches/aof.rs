@@ -0,0 +1,100 @@
+// AOF write throughput through the background writer: a batch of operations is queued
+// and timed until sync() confirms it reached disk, under each fsync policy and with a
+// group commit window sharing fsyncs between operations.
+//
+//   cargo bench --bench aof
+
+use std::{
+    path::PathBuf,
+    time::{Duration, Instant},
+};
+
+use blazekvdb::{
+    config::FsyncPolicy,
+    storage::persistence::aof::{AppendOnlyFile, Operation},
+};
+use criterion::{BenchmarkId, Criterion, Throughput, criterion_group, criterion_main};
+
+const BATCH: u64 = 1_000;
+const VALUE_SIZE: usize = 128;
+
+struct ScratchDir(PathBuf);
+
+impl ScratchDir {
+    fn new(name: &str) -> Self {
+        let path =
+            std::env::temp_dir().join(format!("blazekvdb-bench-{}-{}", name, std::process::id()));
+        std::fs::create_dir_all(&path).unwrap();
+        Self(path)
+    }
+}
+
+impl Drop for ScratchDir {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_dir_all(&self.0);
+    }
+}
+
+fn aof_writes(c: &mut Criterion) {
+    let runtime = tokio::runtime::Builder::new_multi_thread()
+        .worker_threads(2)
+        .enable_all()
+        .build()
+        .unwrap();
+
+    let mut group = c.benchmark_group("aof_write");
+    group.throughput(Throughput::Elements(BATCH));
+    group.measurement_time(Duration::from_secs(10));
+    group.sample_size(10);
+
+    for (name, policy, window) in [
+        ("never", FsyncPolicy::Never, Duration::ZERO),
+        ("every_100", FsyncPolicy::EveryN(100), Duration::ZERO),
+        ("always", FsyncPolicy::Always, Duration::ZERO),
+        (
+            "always_group_1ms",
+            FsyncPolicy::Always,
+            Duration::from_millis(1),
+        ),
+    ] {
+        let scratch = ScratchDir::new(name);
+        let aof = runtime.block_on(async {
+            let mut aof = AppendOnlyFile::new(scratch.0.join("bench.aof"))
+                .await
+                .unwrap();
+            aof.fsync_every = policy.fsync_every();
+            aof.group_commit_window = window;
+            aof.start_background_writer().await;
+            aof
+        });
+
+        group.bench_with_input(BenchmarkId::from_parameter(name), &aof, |b, aof| {
+            b.iter_custom(|iters| {
+                runtime.block_on(async {
+                    let start = Instant::now();
+                    for batch in 0..iters {
+                        for i in 0..BATCH {
+                            aof.log_operation(Operation::Put {
+                                key: format!("key:{}", (batch * BATCH + i) % 10_000),
+                                value: vec![b'x'; VALUE_SIZE],
+                            })
+                            .await
+                            .unwrap();
+                        }
+                        aof.sync().await.unwrap();
+                    }
+                    start.elapsed()
+                })
+            });
+        });
+
+        // Drain the writer before its directory goes away
+        runtime.block_on(aof.sync()).unwrap();
+        drop(aof);
+    }
+
+    group.finish();
+}
+
+criterion_group!(benches, aof_writes);
+criterion_main!(benches);
@@ -0,0 +1,92 @@
+// Commands per second through one connection: a client pipelines a batch of SETs and
+// GETs over loopback and waits for every reply, for several initial line buffer sizes.
+//
+//   cargo bench --bench connection
+
+use std::{
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+use blazekvdb::{
+    commands::CommandDispatcher,
+    server::connection::{ConnectionHandler, ConnectionLimits},
+    storage::{StorageConfig, StorageEngine, engine::memory::MemoryEngine},
+};
+use criterion::{BenchmarkId, Criterion, Throughput, criterion_group, criterion_main};
+use tokio::{
+    io::{AsyncBufReadExt, AsyncWriteExt, BufReader},
+    net::{TcpListener, TcpStream},
+};
+
+const BATCH: u64 = 1_000;
+
+fn pipelined_commands(c: &mut Criterion) {
+    let runtime = tokio::runtime::Builder::new_multi_thread()
+        .worker_threads(2)
+        .enable_all()
+        .build()
+        .unwrap();
+
+    // Half SETs, half GETs of the keys just written
+    let mut batch = String::new();
+    for i in 0..BATCH / 2 {
+        batch.push_str(&format!("SET key:{} \"value {}\"\n", i, i));
+        batch.push_str(&format!("GET key:{}\n", i));
+    }
+
+    let mut group = c.benchmark_group("connection_commands");
+    group.throughput(Throughput::Elements(BATCH));
+    group.measurement_time(Duration::from_secs(5));
+
+    for read_buffer_size in [64usize, 4096, 65536] {
+        let (mut reader, mut writer) = runtime.block_on(async {
+            let storage =
+                Arc::new(MemoryEngine::new(StorageConfig::default())) as Arc<dyn StorageEngine>;
+            let handler = ConnectionHandler::new(Arc::new(CommandDispatcher::new(storage)))
+                .with_limits(ConnectionLimits {
+                    read_buffer_size,
+                    ..ConnectionLimits::default()
+                });
+
+            let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+            let client = TcpStream::connect(listener.local_addr().unwrap())
+                .await
+                .unwrap();
+            let (stream, peer) = listener.accept().await.unwrap();
+            // As the server sets it, otherwise delayed ACKs dominate the timings
+            client.set_nodelay(true).unwrap();
+            stream.set_nodelay(true).unwrap();
+            tokio::spawn(async move { handler.handle_connection(stream, peer).await });
+
+            let (read_half, write_half) = client.into_split();
+            (BufReader::new(read_half), write_half)
+        });
+
+        group.bench_with_input(
+            BenchmarkId::from_parameter(read_buffer_size),
+            &batch,
+            |b, batch| {
+                b.iter_custom(|iters| {
+                    runtime.block_on(async {
+                        let mut reply = String::new();
+                        let start = Instant::now();
+                        for _ in 0..iters {
+                            writer.write_all(batch.as_bytes()).await.unwrap();
+                            for _ in 0..BATCH {
+                                reply.clear();
+                                reader.read_line(&mut reply).await.unwrap();
+                            }
+                        }
+                        start.elapsed()
+                    })
+                });
+            },
+        );
+    }
+
+    group.finish();
+}
+
+criterion_group!(benches, pipelined_commands);
+criterion_main!(benches);
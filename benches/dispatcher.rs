@@ -0,0 +1,78 @@
+// A 100-GET pipeline through CommandDispatcher: execute_batch, which runs the run of
+// reads concurrently, against executing the same commands one after another.
+//
+//   cargo bench --bench dispatcher
+
+use std::{
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+use blazekvdb::{
+    commands::{Command, CommandDispatcher, get::GetCommand},
+    storage::{StorageConfig, StorageEngine, engine::memory::MemoryEngine},
+};
+use criterion::{BenchmarkId, Criterion, Throughput, criterion_group, criterion_main};
+
+const PIPELINE: u64 = 100;
+
+fn pipeline() -> Vec<Command> {
+    (0..PIPELINE)
+        .map(|i| Command::Get(GetCommand::new(format!("key:{}", i))))
+        .collect()
+}
+
+fn get_pipeline(c: &mut Criterion) {
+    let runtime = tokio::runtime::Builder::new_multi_thread()
+        .worker_threads(2)
+        .enable_all()
+        .build()
+        .unwrap();
+
+    let dispatcher = runtime.block_on(async {
+        let storage =
+            Arc::new(MemoryEngine::new(StorageConfig::default())) as Arc<dyn StorageEngine>;
+        for i in 0..PIPELINE {
+            storage
+                .set(&format!("key:{}", i), format!("value {}", i).into_bytes())
+                .await
+                .unwrap();
+        }
+        CommandDispatcher::new(storage)
+    });
+
+    let mut group = c.benchmark_group("dispatcher_100_get");
+    group.throughput(Throughput::Elements(PIPELINE));
+    group.measurement_time(Duration::from_secs(5));
+
+    group.bench_function(BenchmarkId::from_parameter("execute_batch"), |b| {
+        b.iter_custom(|iters| {
+            runtime.block_on(async {
+                let start = Instant::now();
+                for _ in 0..iters {
+                    dispatcher.execute_batch(pipeline()).await;
+                }
+                start.elapsed()
+            })
+        });
+    });
+
+    group.bench_function(BenchmarkId::from_parameter("sequential"), |b| {
+        b.iter_custom(|iters| {
+            runtime.block_on(async {
+                let start = Instant::now();
+                for _ in 0..iters {
+                    for command in pipeline() {
+                        dispatcher.execute(command).await;
+                    }
+                }
+                start.elapsed()
+            })
+        });
+    });
+
+    group.finish();
+}
+
+criterion_group!(benches, get_pipeline);
+criterion_main!(benches);
@@ -0,0 +1,295 @@
+// Baseline MemoryEngine throughput: single-key get/set, batched versus looped gets, a
+// mixed workload spread across shards, a workload contending on one shard, a prefix
+// scan over 100k keys, and miss-heavy lookups with and without the bloom filter.
+// Keys come from a fixed-seed generator so every run replays the same access pattern.
+//
+//   cargo bench --bench engine
+
+use std::{
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+use blazekvdb::storage::{StorageConfig, StorageEngine, engine::memory::MemoryEngine};
+use criterion::{BenchmarkId, Criterion, Throughput, criterion_group, criterion_main};
+use futures_util::TryStreamExt;
+
+const KEY_SPACE: u64 = 10_000;
+const SCAN_KEYS: usize = 100_000;
+const TASKS: usize = 4;
+const OPS_PER_TASK: u64 = 1_000;
+
+// xorshift64*, enough to spread keys without pulling in a rand dependency
+struct KeyGen(u64);
+
+impl KeyGen {
+    fn new(seed: u64) -> Self {
+        Self(seed.max(1))
+    }
+
+    fn next(&mut self) -> u64 {
+        self.0 ^= self.0 >> 12;
+        self.0 ^= self.0 << 25;
+        self.0 ^= self.0 >> 27;
+        self.0.wrapping_mul(0x2545_F491_4F6C_DD1D)
+    }
+
+    fn key(&mut self) -> String {
+        format!("key:{}", self.next() % KEY_SPACE)
+    }
+}
+
+fn runtime() -> tokio::runtime::Runtime {
+    tokio::runtime::Builder::new_multi_thread()
+        .worker_threads(TASKS)
+        .build()
+        .unwrap()
+}
+
+fn engine(shard_count: usize) -> Arc<MemoryEngine> {
+    Arc::new(MemoryEngine::new(StorageConfig {
+        shard_count,
+        max_memory: usize::MAX,
+        ..StorageConfig::default()
+    }))
+}
+
+async fn populate(engine: &MemoryEngine, keys: u64) {
+    for i in 0..keys {
+        engine
+            .set(&format!("key:{}", i), b"value".to_vec())
+            .await
+            .unwrap();
+    }
+}
+
+fn single_key(c: &mut Criterion) {
+    let runtime = runtime();
+    let engine = engine(StorageConfig::default().shard_count);
+    runtime.block_on(populate(&engine, KEY_SPACE));
+
+    let mut group = c.benchmark_group("single_key");
+    group.throughput(Throughput::Elements(1));
+
+    group.bench_function("get", |b| {
+        let mut keys = KeyGen::new(1);
+        b.iter(|| {
+            let key = keys.key();
+            std::hint::black_box(runtime.block_on(engine.get(&key)).unwrap())
+        });
+    });
+
+    group.bench_function("set", |b| {
+        let mut keys = KeyGen::new(2);
+        b.iter(|| {
+            let key = keys.key();
+            runtime
+                .block_on(engine.set(&key, b"value".to_vec()))
+                .unwrap()
+        });
+    });
+
+    group.finish();
+}
+
+// One get_many call against the same keys fetched by a loop of get calls
+fn batch_get(c: &mut Criterion) {
+    let runtime = runtime();
+    let engine = engine(StorageConfig::default().shard_count);
+    runtime.block_on(populate(&engine, KEY_SPACE));
+
+    let mut group = c.benchmark_group("batch_get");
+
+    for batch in [10, 100, 1000] {
+        let mut keygen = KeyGen::new(3);
+        let keys: Vec<String> = (0..batch).map(|_| keygen.key()).collect();
+        let keys: Vec<&str> = keys.iter().map(String::as_str).collect();
+        group.throughput(Throughput::Elements(batch as u64));
+
+        group.bench_with_input(BenchmarkId::new("get_many", batch), &keys, |b, keys| {
+            b.iter(|| std::hint::black_box(runtime.block_on(engine.get_many(keys)).unwrap()));
+        });
+
+        group.bench_with_input(BenchmarkId::new("looped_get", batch), &keys, |b, keys| {
+            b.iter(|| {
+                runtime.block_on(async {
+                    for key in keys {
+                        std::hint::black_box(engine.get(key).await.unwrap());
+                    }
+                })
+            });
+        });
+    }
+
+    group.finish();
+}
+
+// TASKS tasks each run OPS_PER_TASK operations, one in four of them a write
+async fn mixed_workload(engine: Arc<MemoryEngine>, key_of: fn(&mut KeyGen) -> String) {
+    let tasks: Vec<_> = (0..TASKS)
+        .map(|task| {
+            let engine = engine.clone();
+            tokio::spawn(async move {
+                let mut keys = KeyGen::new(task as u64 + 1);
+                for op in 0..OPS_PER_TASK {
+                    let key = key_of(&mut keys);
+                    if op % 4 == 0 {
+                        engine.set(&key, b"value".to_vec()).await.unwrap();
+                    } else {
+                        std::hint::black_box(engine.get(&key).await.unwrap());
+                    }
+                }
+            })
+        })
+        .collect();
+
+    for task in tasks {
+        task.await.unwrap();
+    }
+}
+
+fn concurrent_mixed(c: &mut Criterion) {
+    let runtime = runtime();
+
+    let mut group = c.benchmark_group("concurrent_mixed");
+    group.throughput(Throughput::Elements(TASKS as u64 * OPS_PER_TASK));
+
+    // Same workload, keys spread over every shard versus all behind one lock
+    for (name, shard_count) in [
+        ("spread", StorageConfig::default().shard_count),
+        ("contended", 1),
+    ] {
+        let engine = engine(shard_count);
+        runtime.block_on(populate(&engine, KEY_SPACE));
+
+        group.bench_with_input(BenchmarkId::from_parameter(name), &engine, |b, engine| {
+            b.iter(|| runtime.block_on(mixed_workload(engine.clone(), KeyGen::key)));
+        });
+    }
+
+    // Default sharding, but every task hammers the same handful of keys
+    let engine = engine(StorageConfig::default().shard_count);
+    runtime.block_on(populate(&engine, KEY_SPACE));
+    group.bench_function("hot_keys", |b| {
+        b.iter(|| {
+            runtime.block_on(mixed_workload(engine.clone(), |keys| {
+                format!("key:{}", keys.next() % 4)
+            }))
+        });
+    });
+
+    group.finish();
+}
+
+// Nine in ten lookups miss, half of them GET and half EXISTS, while one op in eight writes
+// an existing key so the shard locks are contended
+async fn miss_heavy_workload(engine: Arc<MemoryEngine>) {
+    let tasks: Vec<_> = (0..TASKS)
+        .map(|task| {
+            let engine = engine.clone();
+            tokio::spawn(async move {
+                let mut keys = KeyGen::new(task as u64 + 1);
+                for op in 0..OPS_PER_TASK {
+                    let n = keys.next();
+                    let key = if n.is_multiple_of(10) {
+                        format!("key:{}", n % KEY_SPACE)
+                    } else {
+                        format!("absent:{}", n)
+                    };
+
+                    if op % 8 == 0 {
+                        let key = format!("key:{}", n % KEY_SPACE);
+                        engine.set(&key, b"value".to_vec()).await.unwrap();
+                    } else if op % 2 == 0 {
+                        std::hint::black_box(engine.get(&key).await.unwrap());
+                    } else {
+                        std::hint::black_box(engine.exists(&key).await.unwrap());
+                    }
+                }
+            })
+        })
+        .collect();
+
+    for task in tasks {
+        task.await.unwrap();
+    }
+}
+
+fn miss_heavy(c: &mut Criterion) {
+    let runtime = runtime();
+
+    let mut group = c.benchmark_group("miss_heavy");
+    group.throughput(Throughput::Elements(TASKS as u64 * OPS_PER_TASK));
+
+    for bloom_filter in [false, true] {
+        let engine = Arc::new(MemoryEngine::new(StorageConfig {
+            max_memory: usize::MAX,
+            bloom_filter,
+            bloom_filter_capacity: KEY_SPACE as usize,
+            ..StorageConfig::default()
+        }));
+        runtime.block_on(populate(&engine, KEY_SPACE));
+
+        let name = if bloom_filter { "bloom" } else { "no_bloom" };
+        group.bench_with_input(BenchmarkId::from_parameter(name), &engine, |b, engine| {
+            b.iter(|| runtime.block_on(miss_heavy_workload(engine.clone())));
+        });
+    }
+
+    group.finish();
+}
+
+fn scan(c: &mut Criterion) {
+    let runtime = runtime();
+    let engine = engine(StorageConfig::default().shard_count);
+    runtime.block_on(async {
+        for i in 0..SCAN_KEYS {
+            let prefix = if i % 10 == 0 { "match" } else { "other" };
+            engine
+                .set(&format!("{}:{}", prefix, i), b"value".to_vec())
+                .await
+                .unwrap();
+        }
+    });
+
+    let mut group = c.benchmark_group("scan_100k");
+    group.measurement_time(Duration::from_secs(10));
+    group.sample_size(20);
+
+    for prefix in ["match:", ""] {
+        group.bench_with_input(
+            BenchmarkId::from_parameter(if prefix.is_empty() { "all" } else { "10pct" }),
+            &prefix,
+            |b, prefix| {
+                b.iter_custom(|iters| {
+                    runtime.block_on(async {
+                        let start = Instant::now();
+                        for _ in 0..iters {
+                            let keys: Vec<String> = engine
+                                .scan(prefix)
+                                .await
+                                .unwrap()
+                                .try_collect()
+                                .await
+                                .unwrap();
+                            std::hint::black_box(keys);
+                        }
+                        start.elapsed()
+                    })
+                });
+            },
+        );
+    }
+
+    group.finish();
+}
+
+criterion_group!(
+    benches,
+    single_key,
+    batch_get,
+    concurrent_mixed,
+    miss_heavy,
+    scan
+);
+criterion_main!(benches);
@@ -0,0 +1,103 @@
+// Skewed workload: readers hammer one large hot key while writers update other keys
+// that live in the same shard. Measures how long the writes take under that read load.
+//
+//   cargo bench --bench hot_key
+
+use std::{
+    sync::{
+        Arc,
+        atomic::{AtomicBool, Ordering},
+    },
+    time::{Duration, Instant},
+};
+
+use blazekvdb::storage::{StorageConfig, StorageEngine, engine::memory::MemoryEngine};
+use criterion::{BenchmarkId, Criterion, criterion_group, criterion_main};
+
+const HOT_VALUE_SIZE: usize = 64 * 1024;
+
+fn engine() -> Arc<MemoryEngine> {
+    // A single shard puts the hot key and every written key behind the same lock
+    Arc::new(MemoryEngine::new(StorageConfig {
+        shard_count: 1,
+        max_memory: usize::MAX,
+        ..StorageConfig::default()
+    }))
+}
+
+fn writes_beside_hot_key(c: &mut Criterion) {
+    let runtime = tokio::runtime::Builder::new_multi_thread()
+        .worker_threads(4)
+        .build()
+        .unwrap();
+
+    let mut group = c.benchmark_group("writes_beside_hot_key");
+    group.measurement_time(Duration::from_secs(5));
+
+    for readers in [0usize, 2, 4] {
+        group.bench_with_input(
+            BenchmarkId::from_parameter(readers),
+            &readers,
+            |b, &readers| {
+                b.iter_custom(|iters| {
+                    runtime.block_on(async {
+                        let engine = engine();
+                        engine.set("hot", vec![7u8; HOT_VALUE_SIZE]).await.unwrap();
+
+                        let stop = Arc::new(AtomicBool::new(false));
+                        let reader_tasks: Vec<_> = (0..readers)
+                            .map(|_| {
+                                let engine = engine.clone();
+                                let stop = stop.clone();
+                                tokio::spawn(async move {
+                                    while !stop.load(Ordering::Relaxed) {
+                                        let value = engine.get("hot").await.unwrap();
+                                        std::hint::black_box(value);
+                                        tokio::task::yield_now().await;
+                                    }
+                                })
+                            })
+                            .collect();
+
+                        let start = Instant::now();
+                        for i in 0..iters {
+                            engine
+                                .set(&format!("cold:{}", i % 1024), b"value".to_vec())
+                                .await
+                                .unwrap();
+                        }
+                        let elapsed = start.elapsed();
+
+                        stop.store(true, Ordering::Relaxed);
+                        for task in reader_tasks {
+                            task.await.unwrap();
+                        }
+
+                        elapsed
+                    })
+                });
+            },
+        );
+    }
+
+    group.finish();
+}
+
+fn hot_key_reads(c: &mut Criterion) {
+    let runtime = tokio::runtime::Builder::new_multi_thread()
+        .worker_threads(4)
+        .build()
+        .unwrap();
+
+    let engine = engine();
+    runtime.block_on(async {
+        engine.set("hot", vec![7u8; HOT_VALUE_SIZE]).await.unwrap();
+    });
+
+    c.bench_function("hot_key_read", |b| {
+        b.iter(|| std::hint::black_box(runtime.block_on(engine.get("hot")).unwrap()));
+    });
+}
+
+criterion_group!(benches, writes_beside_hot_key, hot_key_reads);
+criterion_main!(benches);
@@ -0,0 +1,14 @@
+#![no_main]
+
+// cargo fuzz run parse_command
+use blazekvdb::{commands::proto::ProtocolMode, protocol::parser::ProtocolParser};
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|data: &[u8]| {
+    // The connection decodes lines lossily before parsing, do the same here
+    let message = String::from_utf8_lossy(data);
+
+    let _ = ProtocolParser::parse_command(&message);
+    let _ = ProtocolParser::parse_command_in(&message, ProtocolMode::Json);
+    let _ = ProtocolParser::parse_commands(&message);
+});
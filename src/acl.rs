@@ -0,0 +1,175 @@
+use std::{
+    collections::{HashMap, HashSet},
+    sync::Arc,
+};
+
+use crate::{
+    commands::CommandHandler,
+    config::{SecurityConfig, UserConfig},
+    protocol::parser::handler_name,
+};
+
+// User that plain `AUTH password` logs in as, and that unauthenticated connections
+// run as when authentication isn't required
+pub const DEFAULT_USER: &str = "default";
+
+// Command categories usable in a user's allow-list next to plain command names
+pub const CATEGORIES: &[&str] = &["@all", "@read", "@write", "@admin"];
+
+// A named user and the commands it may run
+#[derive(Debug, Clone, PartialEq)]
+pub struct AclUser {
+    name: String,
+    password: Option<String>, // None can't be logged into with AUTH
+    all: bool,
+    read: bool,
+    write: bool,
+    admin: bool,
+    commands: HashSet<String>, // Upper-cased CommandHandler::name() values
+}
+
+impl AclUser {
+    // A user allowed to run everything
+    pub fn unrestricted(name: impl Into<String>, password: Option<String>) -> Self {
+        Self {
+            name: name.into(),
+            password,
+            all: true,
+            read: false,
+            write: false,
+            admin: false,
+            commands: HashSet::new(),
+        }
+    }
+
+    pub fn from_config(config: &UserConfig) -> Self {
+        let mut user = Self {
+            name: config.name.clone(),
+            password: Some(config.password.clone()),
+            all: false,
+            read: false,
+            write: false,
+            admin: false,
+            commands: HashSet::new(),
+        };
+
+        for entry in &config.commands {
+            match entry.to_lowercase().as_str() {
+                "@all" => user.all = true,
+                "@read" => user.read = true,
+                "@write" => user.write = true,
+                "@admin" => user.admin = true,
+                // Unknown names are refused by config validation
+                _ => {
+                    let name =
+                        handler_name(entry).map_or_else(|| entry.to_uppercase(), str::to_string);
+                    user.commands.insert(name);
+                }
+            }
+        }
+
+        user
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    // Whether this user may run the command; AUTH and RESET are always allowed so users
+    // can switch or log out, HELLO so clients can always identify the server
+    pub fn permits(&self, handler: &dyn CommandHandler) -> bool {
+        let name = handler.name();
+        if self.all || matches!(name, "AUTH" | "RESET" | "HELLO") || self.commands.contains(name) {
+            return true;
+        }
+
+        if handler.is_admin() {
+            self.admin
+        } else if handler.is_read_only() {
+            self.read
+        } else {
+            self.write
+        }
+    }
+
+    fn check_password(&self, password: &str) -> bool {
+        match self.password {
+            // Compare every byte so the match length isn't leaked through timing
+            Some(ref expected) => {
+                expected.len() == password.len()
+                    && expected
+                        .bytes()
+                        .zip(password.bytes())
+                        .fold(0, |diff, (a, b)| diff | (a ^ b))
+                        == 0
+            }
+            None => false,
+        }
+    }
+}
+
+// Users a server knows about, built from the security config
+#[derive(Debug, Clone)]
+pub struct Acl {
+    users: HashMap<String, Arc<AclUser>>,
+    require_auth: bool,
+}
+
+impl Default for Acl {
+    // No authentication, everyone runs as an unrestricted default user
+    fn default() -> Self {
+        Self::from_config(&SecurityConfig::default())
+    }
+}
+
+impl Acl {
+    pub fn from_config(config: &SecurityConfig) -> Self {
+        let mut users: HashMap<String, Arc<AclUser>> = config
+            .users
+            .iter()
+            .map(|user| (user.name.clone(), Arc::new(AclUser::from_config(user))))
+            .collect();
+
+        // `auth_password` keeps working as the default user's password
+        users.entry(DEFAULT_USER.to_string()).or_insert_with(|| {
+            Arc::new(AclUser::unrestricted(
+                DEFAULT_USER,
+                config.auth_password.clone(),
+            ))
+        });
+
+        Self {
+            users,
+            require_auth: config.require_auth,
+        }
+    }
+
+    // Whether connections must AUTH before running anything else
+    pub fn requires_auth(&self) -> bool {
+        self.require_auth
+    }
+
+    // Whether any user can be logged into at all
+    pub fn has_passwords(&self) -> bool {
+        self.users.values().any(|user| user.password.is_some())
+    }
+
+    pub fn user(&self, name: &str) -> Option<Arc<AclUser>> {
+        self.users.get(name).cloned()
+    }
+
+    // The user a new connection starts as, None when it has to AUTH first
+    pub fn initial_user(&self) -> Option<Arc<AclUser>> {
+        if self.require_auth {
+            None
+        } else {
+            self.user(DEFAULT_USER)
+        }
+    }
+
+    // Check credentials, `None` as the username means the default user
+    pub fn authenticate(&self, username: Option<&str>, password: &str) -> Option<Arc<AclUser>> {
+        let user = self.users.get(username.unwrap_or(DEFAULT_USER))?;
+        user.check_password(password).then(|| user.clone())
+    }
+}
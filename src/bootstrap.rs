@@ -1,67 +1,162 @@
-use std::sync::Arc;
+use std::{
+    collections::HashMap,
+    sync::{Arc, OnceLock, Weak},
+    time::Duration,
+};
 
-use tracing::{debug, info, instrument};
+use futures_util::{Stream, StreamExt, stream};
+use tokio::{task::JoinHandle, time::MissedTickBehavior};
+use tokio_util::sync::CancellationToken;
+use tracing::{debug, error, info, instrument, warn};
 
 use crate::{
-    commands::{Command, CommandDispatcher, CommandResponse},
-    config::BlazeServerConfig,
+    acl::Acl,
+    commands::{Command, CommandDispatcher, CommandResponse, KeyLimits},
+    config::{BlazeServerConfig, SharedConfig},
+    protocol::parser::ProtocolParser,
+    pubsub::{KeyspaceNotifier, PubSub},
     storage::{
         StorageEngine, StorageResult,
-        engine::memory::MemoryEngine,
+        engine::memory::{MemoryBudget, MemoryEngine},
         persistence::{
-            aof::Operation,
+            aof::{Operation, OperationQueue},
             manager::{PersistenceManager, PersistenceStats},
         },
     },
 };
 
+// Outcome of BlazeKVDB::run_script
+#[derive(Debug, Default)]
+pub struct ScriptReport {
+    pub executed: usize,          // Commands run, failed ones included
+    pub errors: Vec<ScriptError>, // Lines that didn't parse or whose command failed
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ScriptError {
+    pub line: usize, // 1-based
+    pub message: String,
+}
+
+// Handed to every in-memory database of a server
+#[derive(Default)]
+struct SharedEngineState {
+    memory: Arc<MemoryBudget>, // The max_memory budget they share
+    evictions: Arc<OnceLock<OperationQueue>>, // Where evictions are logged, set once recovered
+}
+
 pub struct BlazeKVDB {
-    storage: Arc<dyn StorageEngine>,
+    config: SharedConfig,
+    storage: Arc<dyn StorageEngine>,        // Database 0
+    databases: Vec<Arc<dyn StorageEngine>>, // Every logical database, indexed by number
     persistence: Option<Arc<PersistenceManager>>,
     dispatcher: Arc<CommandDispatcher>,
+    shutdown: CancellationToken,
+    background: parking_lot::Mutex<Vec<JoinHandle<()>>>,
 }
 
 impl BlazeKVDB {
-    /// Create new KV store with configuration
+    /// Create new KV store with configuration, backed by the in-memory engine
     #[instrument(skip(config))]
     pub async fn new(config: BlazeServerConfig) -> StorageResult<Self> {
-        info!("Initializing KV Store...");
-
         // 1. Initialize storage engine
         info!("Creating storage engine...");
-        let storage = Arc::new(MemoryEngine::new(config.storage.clone())) as Arc<dyn StorageEngine>;
+        let pubsub = PubSub::new();
+        let shared = SharedEngineState::default();
+        let storage = Self::memory_engine(&config, 0, &pubsub, &shared);
+
+        Self::build(config, storage, pubsub, shared).await
+    }
+
+    /// Create new KV store on top of a caller-provided storage engine
+    /// The engine serves database 0, the other configured databases are in-memory engines
+    /// Keyspace events are only published for the in-memory databases, and max_memory
+    /// covers those together while the caller's engine enforces its own limit
+    #[instrument(skip(config, storage))]
+    pub async fn with_storage(
+        config: BlazeServerConfig,
+        storage: Arc<dyn StorageEngine>,
+    ) -> StorageResult<Self> {
+        Self::build(config, storage, PubSub::new(), SharedEngineState::default()).await
+    }
+
+    async fn build(
+        config: BlazeServerConfig,
+        storage: Arc<dyn StorageEngine>,
+        pubsub: PubSub,
+        shared: SharedEngineState,
+    ) -> StorageResult<Self> {
+        info!("Initializing KV Store...");
+
+        let mut databases = Vec::with_capacity(config.storage.databases.max(1));
+        databases.push(storage.clone());
+        for database in 1..config.storage.databases {
+            databases.push(Self::memory_engine(&config, database, &pubsub, &shared));
+        }
 
         // 2. Initialize persistence (if enabled)
         let persistence = if config.persistence.enabled {
             info!("Initializing persistence layer...");
 
-            let persistence = Arc::new(
-                PersistenceManager::new(config.persistence.clone(), storage.clone()).await?,
-            );
-
-            // 3. Recover from persistence
-            info!("Recovering database state...");
-            persistence.recover().await?;
-
-            Some(persistence)
+            Some(Arc::new(
+                PersistenceManager::new(config.persistence.clone(), storage.clone())
+                    .await?
+                    .with_databases(databases.clone()),
+            ))
         } else {
             info!("Persistence disabled");
             None
         };
 
-        // 4. Initialize command dispatcher
-        let dispatcher = Arc::new(CommandDispatcher::new(storage.clone()));
+        // 3. Initialize command dispatcher (logs writes to AOF when persistence is on)
+        let limits = KeyLimits::from(&config.storage);
+        let trace_sample_rate = config.observability.trace_sample_rate;
+        let acl = Acl::from_config(&config.security);
+        let config: SharedConfig = Arc::new(parking_lot::RwLock::new(config));
+        let mut dispatcher = CommandDispatcher::new(storage.clone())
+            .with_databases(databases.clone())
+            .with_limits(limits)
+            .with_trace_sample_rate(trace_sample_rate)
+            .with_config(config.clone())
+            .with_acl(acl)
+            .with_pubsub(pubsub);
+        if let Some(ref persistence) = persistence {
+            dispatcher = dispatcher.with_persistence(persistence.clone());
+        }
+        let dispatcher = Arc::new(dispatcher);
 
+        // 4. Recover from persistence, commands are refused until it completes
+        if let Some(ref persistence) = persistence {
+            info!("Recovering database state...");
+            dispatcher.set_recovering(true);
+            persistence.recover().await?;
+            // Evictions during replay aren't logged, the same entries are replayed next time
+            if let Some(queue) = persistence.operation_queue().await {
+                let _ = shared.evictions.set(queue);
+            }
+            dispatcher.set_recovering(false);
+        }
+
+        let active_expire_interval = config.read().storage.active_expire_interval_ms;
         let store = Self {
+            config,
             storage,
+            databases,
             persistence,
             dispatcher,
+            shutdown: CancellationToken::new(),
+            background: parking_lot::Mutex::new(Vec::new()),
         };
 
         // 5. Start background tasks
         if let Some(ref persistence) = store.persistence {
             info!("Starting background snapshot task...");
             persistence.clone().start_background_snapshots();
+            persistence.clone().start_rotation_compaction().await;
+            persistence.clone().start_growth_compaction().await;
+        }
+        if active_expire_interval > 0 {
+            store.start_active_expire(Duration::from_millis(active_expire_interval));
         }
 
         info!("✅ KV Store initialized successfully");
@@ -69,41 +164,172 @@ impl BlazeKVDB {
         Ok(store)
     }
 
+    // In-memory engine for one logical database, publishing keyspace events when enabled,
+    // counting its memory against the budget shared by all databases and logging evictions
+    fn memory_engine(
+        config: &BlazeServerConfig,
+        database: usize,
+        pubsub: &PubSub,
+        shared: &SharedEngineState,
+    ) -> Arc<dyn StorageEngine> {
+        let evictions = shared.evictions.clone();
+        let mut engine = MemoryEngine::new(config.storage.clone())
+            .with_memory_budget(shared.memory.clone())
+            .with_eviction_listener(Arc::new(move |key: &str| {
+                let Some(queue) = evictions.get() else {
+                    return;
+                };
+                let operation = Operation::Delete {
+                    key: key.to_string(),
+                };
+                if let Err(e) = queue.push(operation.in_database(database)) {
+                    warn!("Eviction of {} not logged to the AOF: {}", key, e);
+                }
+            }));
+        if config.server.notify_keyspace_events {
+            engine = engine.with_notifier(KeyspaceNotifier::new(database, pubsub.clone()));
+        }
+        let engine = Arc::new(engine);
+        shared.memory.register(&engine);
+        engine
+    }
+
+    // Purge expired keys in the background so keys nobody reads again still free memory
+    // The task holds only Weak references, so it never keeps the storage alive
+    fn start_active_expire(&self, interval: Duration) {
+        info!("Starting active expiry (interval: {:?})", interval);
+
+        let dispatcher = Arc::downgrade(&self.dispatcher);
+        let shutdown = self.shutdown.clone();
+
+        let handle = tokio::spawn(async move {
+            let mut timer = tokio::time::interval(interval);
+            timer.set_missed_tick_behavior(MissedTickBehavior::Skip);
+
+            loop {
+                tokio::select! {
+                    _ = shutdown.cancelled() => return,
+                    _ = timer.tick() => {}
+                }
+
+                let Some(dispatcher) = Weak::upgrade(&dispatcher) else {
+                    return; // Store dropped
+                };
+                // Paused by DEBUG SET-ACTIVE-EXPIRE 0, and nothing may change while recovering
+                if !dispatcher.is_active_expire() || dispatcher.is_recovering() {
+                    continue;
+                }
+
+                for storage in dispatcher.databases() {
+                    if let Err(e) = storage.purge_expired().await {
+                        warn!("Active expiry failed: {}", e);
+                    }
+                }
+            }
+        });
+
+        self.background.lock().push(handle);
+    }
+
     /// Execute command with persistence
     #[instrument(skip(self))]
     pub async fn execute(&self, command: Command) -> CommandResponse {
         debug!("Executing command: {:?}", command);
 
-        // Log write operations to AOF before execution
-        if let Some(ref persistence) = self.persistence {
-            match &command {
-                Command::Set(cmd) => {
-                    if let Err(e) = persistence
-                        .log_operation(Operation::Put {
-                            key: cmd.key.clone(),
-                            value: cmd.value.clone(),
-                        })
-                        .await
-                    {
-                        return CommandResponse::Error(format!("Persistence error: {}", e));
-                    }
+        // The dispatcher logs each write to the AOF once it has been applied
+        self.dispatcher.execute(command).await
+    }
+
+    /// Run a script of protocol lines against database 0, as if a client had pipelined them
+    /// Blank lines and lines starting with `#` are skipped. A script with a line that
+    /// doesn't parse runs nothing, so a typo can't leave a migration half-applied
+    pub async fn run_script(&self, script: &str) -> ScriptReport {
+        // Comments become blank lines, which parse_commands skips like empty ones
+        let script: Vec<&str> = script
+            .lines()
+            .map(|line| {
+                if line.trim_start().starts_with('#') {
+                    ""
+                } else {
+                    line
                 }
-                Command::Delete(cmd) => {
-                    if let Err(e) = persistence
-                        .log_operation(Operation::Delete {
-                            key: cmd.key.clone(),
-                        })
-                        .await
-                    {
-                        return CommandResponse::Error(format!("Persistence error: {}", e));
-                    }
+            })
+            .collect();
+        let script = script.join("\n");
+
+        // 1-based numbers of the lines parse_commands returns a result for
+        let line_numbers = script
+            .lines()
+            .enumerate()
+            .filter(|(_, line)| !line.trim().is_empty())
+            .map(|(index, _)| index + 1);
+
+        let mut report = ScriptReport::default();
+        let mut commands = Vec::new();
+        let mut command_lines = Vec::new();
+        for (line, parsed) in line_numbers.zip(ProtocolParser::parse_commands(&script)) {
+            match parsed {
+                Ok(command) => {
+                    commands.push(command);
+                    command_lines.push(line);
                 }
-                _ => {} // Read-only commands don't need persistence
+                Err(e) => report.errors.push(ScriptError {
+                    line,
+                    message: e.to_string(),
+                }),
             }
         }
+        if !report.errors.is_empty() {
+            return report;
+        }
 
-        // Execute command
-        self.dispatcher.execute(command).await
+        let responses = self.dispatcher.execute_batch(commands).await;
+        report.executed = responses.len();
+        for (line, response) in command_lines.into_iter().zip(responses) {
+            if let CommandResponse::Error(message) = response {
+                report.errors.push(ScriptError { line, message });
+            }
+        }
+
+        report
+    }
+
+    /// Fetch several keys of database 0 at once, missing and expired keys are left out
+    /// Each shard is locked once for all of its keys, rather than once per key
+    pub async fn get_many(&self, keys: &[&str]) -> StorageResult<HashMap<String, Vec<u8>>> {
+        self.storage.get_many(keys).await
+    }
+
+    /// Lazily stream the key-value pairs of database 0 whose key starts with `prefix`
+    /// Keys written or deleted while the stream is consumed may or may not show up
+    pub fn scan_stream(
+        &self,
+        prefix: &str,
+    ) -> impl Stream<Item = StorageResult<(String, Vec<u8>)>> + Send + 'static {
+        let storage = self.storage.clone();
+        let prefix = prefix.to_string();
+
+        stream::once(async move { storage.scan_entries(&prefix).await }).flat_map(|entries| {
+            match entries {
+                Ok(entries) => entries.left_stream(),
+                Err(e) => stream::iter([Err(e)]).right_stream(),
+            }
+        })
+    }
+
+    /// Toggle read-only mode: while enabled, every write command is refused
+    pub fn set_read_only(&self, enabled: bool) {
+        self.dispatcher.set_read_only(enabled);
+    }
+
+    /// Whether the server is currently in read-only mode
+    pub fn is_read_only(&self) -> bool {
+        self.dispatcher.is_read_only()
+    }
+
+    /// Whether persisted state is still being replayed (commands are refused meanwhile)
+    pub fn is_recovering(&self) -> bool {
+        self.dispatcher.is_recovering()
     }
 
     /// Create manual snapshot
@@ -117,6 +343,49 @@ impl BlazeKVDB {
         }
     }
 
+    /// Stop persistence background tasks and flush the AOF
+    /// Afterwards nothing but this instance keeps the storage engine alive
+    pub async fn shutdown(&self) -> StorageResult<()> {
+        self.shutdown.cancel();
+        let handles: Vec<_> = std::mem::take(&mut *self.background.lock());
+        for handle in handles {
+            if let Err(e) = handle.await
+                && !e.is_cancelled()
+            {
+                error!("Background task failed: {}", e);
+            }
+        }
+
+        if let Some(ref persistence) = self.persistence {
+            persistence.stop().await?;
+        }
+
+        info!("KV Store shut down");
+        Ok(())
+    }
+
+    /// Flush the AOF from synchronous code, waiting at most `timeout` (panic hooks)
+    /// Best effort only, `shutdown` remains the way to stop cleanly
+    pub fn flush_blocking(&self, timeout: std::time::Duration) -> StorageResult<()> {
+        match self.persistence {
+            Some(ref persistence) => persistence.flush_blocking(timeout),
+            None => Ok(()),
+        }
+    }
+
+    /// Check storage and persistence health
+    pub async fn health_check(&self) -> StorageResult<()> {
+        for storage in &self.databases {
+            storage.health_check().await?;
+        }
+
+        if let Some(ref persistence) = self.persistence {
+            persistence.health_check().await?;
+        }
+
+        Ok(())
+    }
+
     /// Get storage statistics
     pub async fn storage_stats(&self) -> StorageResult<crate::storage::StorageStats> {
         self.storage.stats().await
@@ -131,6 +400,11 @@ impl BlazeKVDB {
         }
     }
 
+    /// Get the running configuration (CONFIG SET changes are reflected here)
+    pub fn config(&self) -> SharedConfig {
+        self.config.clone()
+    }
+
     /// Get command dispatcher (for server integration)
     pub fn dispatcher(&self) -> Arc<CommandDispatcher> {
         self.dispatcher.clone()
@@ -140,4 +414,9 @@ impl BlazeKVDB {
     pub fn storage(&self) -> Arc<dyn StorageEngine> {
         self.storage.clone()
     }
+
+    /// Get the storage engine of a logical database (None if out of range)
+    pub fn database(&self, index: usize) -> Option<Arc<dyn StorageEngine>> {
+        self.databases.get(index).cloned()
+    }
 }
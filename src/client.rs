@@ -0,0 +1,413 @@
+use std::{net::SocketAddr, time::Duration};
+
+use thiserror::Error;
+use tokio::{
+    io::{AsyncBufReadExt, AsyncWriteExt, BufReader},
+    net::{
+        TcpStream,
+        tcp::{OwnedReadHalf, OwnedWriteHalf},
+    },
+    sync::Mutex,
+};
+use tracing::{debug, instrument, warn};
+
+use crate::{
+    commands::{
+        Command, CommandResponse, delete::DeleteCommand, echo::EchoCommand, exist::ExistCommand,
+        get::GetCommand, hello::HelloCommand, scan::ScanCommand, set::SetCommand,
+    },
+    protocol::parser::{KEEPALIVE, ProtocolError, ProtocolParser},
+};
+
+#[derive(Debug, Error)]
+pub enum ClientError {
+    #[error("I/O error: {0}")]
+    Io(#[from] std::io::Error),
+
+    #[error("Protocol error: {0}")]
+    Protocol(#[from] ProtocolError),
+
+    #[error("Server error: {0}")]
+    Server(String),
+
+    #[error("Unexpected response: {0:?}")]
+    UnexpectedResponse(CommandResponse),
+
+    #[error("Connection closed by server")]
+    ConnectionClosed,
+}
+
+pub type ClientResult<T> = Result<T, ClientError>;
+
+#[derive(Debug, Clone)]
+pub struct ClientConfig {
+    pub connect_timeout: Duration,
+    pub reconnect_attempts: u32, // Extra attempts for read-only batches on a dead connection
+    pub reconnect_delay: Duration,
+}
+
+impl Default for ClientConfig {
+    fn default() -> Self {
+        Self {
+            connect_timeout: Duration::from_secs(5),
+            reconnect_attempts: 3,
+            reconnect_delay: Duration::from_millis(100),
+        }
+    }
+}
+
+struct Connection {
+    reader: BufReader<OwnedReadHalf>,
+    writer: OwnedWriteHalf,
+}
+
+// Async client for the text protocol, sharing command and reply encoding with the server
+pub struct BlazeClient {
+    addr: SocketAddr,
+    config: ClientConfig,
+
+    // Established lazily and replaced after I/O failures; requests are serialized over it
+    connection: Mutex<Option<Connection>>,
+}
+
+impl BlazeClient {
+    // Connect to a server, failing fast if it isn't reachable
+    pub async fn connect(addr: SocketAddr) -> ClientResult<Self> {
+        Self::connect_with(addr, ClientConfig::default()).await
+    }
+
+    pub async fn connect_with(addr: SocketAddr, config: ClientConfig) -> ClientResult<Self> {
+        let client = Self {
+            addr,
+            config,
+            connection: Mutex::new(None),
+        };
+
+        let connection = client.open().await?;
+        *client.connection.lock().await = Some(connection);
+
+        Ok(client)
+    }
+
+    pub fn addr(&self) -> SocketAddr {
+        self.addr
+    }
+
+    // Fetch a value, None if the key doesn't exist
+    pub async fn get(&self, key: &str) -> ClientResult<Option<Vec<u8>>> {
+        match self
+            .execute(Command::Get(GetCommand::new(key.to_string())))
+            .await?
+        {
+            CommandResponse::Value(value) => Ok(Some(value)),
+            CommandResponse::Nil => Ok(None),
+            CommandResponse::Error(msg) if msg == "Key not found" => Ok(None),
+            other => Err(Self::unexpected(other)),
+        }
+    }
+
+    pub async fn set(&self, key: &str, value: impl Into<Vec<u8>>) -> ClientResult<()> {
+        let command = Command::Set(SetCommand::new(key.to_string(), value.into()));
+        self.expect_ok(command).await
+    }
+
+    // Set with a TTL, rounded down to whole seconds
+    pub async fn set_ex(
+        &self,
+        key: &str,
+        value: impl Into<Vec<u8>>,
+        ttl: Duration,
+    ) -> ClientResult<()> {
+        let command =
+            Command::Set(SetCommand::new(key.to_string(), value.into()).with_ttl(ttl.as_secs()));
+        self.expect_ok(command).await
+    }
+
+    // Returns whether the key existed
+    pub async fn delete(&self, key: &str) -> ClientResult<bool> {
+        match self
+            .execute(Command::Delete(DeleteCommand::new(key.to_string())))
+            .await?
+        {
+            CommandResponse::Integer(deleted) => Ok(deleted > 0),
+            other => Err(Self::unexpected(other)),
+        }
+    }
+
+    pub async fn exists(&self, key: &str) -> ClientResult<bool> {
+        match self
+            .execute(Command::Exist(ExistCommand::new(key.to_string())))
+            .await?
+        {
+            CommandResponse::Bool(found) => Ok(found),
+            other => Err(Self::unexpected(other)),
+        }
+    }
+
+    // Keys starting with `prefix`, an empty prefix lists every key
+    pub async fn scan(&self, prefix: &str) -> ClientResult<Vec<String>> {
+        match self
+            .execute(Command::Scan(ScanCommand::new(prefix.to_string())))
+            .await?
+        {
+            CommandResponse::Keys(keys) => Ok(keys),
+            other => Err(Self::unexpected(other)),
+        }
+    }
+
+    pub async fn ping(&self) -> ClientResult<()> {
+        match self.execute(Command::Ping).await? {
+            CommandResponse::Pong => Ok(()),
+            other => Err(Self::unexpected(other)),
+        }
+    }
+
+    // Round trip `message` through the server, e.g. to check a pooled connection
+    pub async fn echo(&self, message: impl Into<Vec<u8>>) -> ClientResult<Vec<u8>> {
+        match self
+            .execute(Command::Echo(EchoCommand::new(message.into())))
+            .await?
+        {
+            CommandResponse::Value(message) => Ok(message),
+            other => Err(Self::unexpected(other)),
+        }
+    }
+
+    // Server name, version and protocols, as `field value` pairs in the server's order
+    pub async fn hello(&self) -> ClientResult<Vec<(String, String)>> {
+        match self
+            .execute(Command::Hello(HelloCommand::new(None)))
+            .await?
+        {
+            CommandResponse::Map(fields) => Ok(fields),
+            other => Err(Self::unexpected(other)),
+        }
+    }
+
+    // Send one command and return the server's reply as-is (errors included)
+    pub async fn execute(&self, command: Command) -> ClientResult<CommandResponse> {
+        let mut responses = self.pipeline(&[command]).await?;
+        responses.pop().ok_or(ClientError::ConnectionClosed)
+    }
+
+    // Send several commands in one write and read back their replies in order
+    #[instrument(skip(self, commands), fields(addr = %self.addr, count = commands.len()))]
+    pub async fn pipeline(&self, commands: &[Command]) -> ClientResult<Vec<CommandResponse>> {
+        // Encoding errors are the caller's, catch them before touching the socket
+        let mut request = String::new();
+        for command in commands {
+            request.push_str(&ProtocolParser::serialize_command(command)?);
+        }
+
+        // Connection settings would change the reply framing under the client, a selected
+        // database or user would silently reset on reconnect, and pushed messages would be
+        // taken for replies
+        if commands.iter().any(|command| {
+            matches!(
+                command,
+                Command::Compress(_)
+                    | Command::Proto(_)
+                    | Command::Encoding(_)
+                    | Command::Select(_)
+                    | Command::Auth(_)
+                    | Command::Subscribe(_)
+                    | Command::Unsubscribe(_)
+            )
+        }) {
+            return Err(ProtocolError::InvalidFormat(
+                "Connection settings, AUTH and SUBSCRIBE are not supported by the client"
+                    .to_string(),
+            )
+            .into());
+        }
+
+        let mut guard = self.connection.lock().await;
+        let mut attempt = 0;
+
+        loop {
+            if guard.is_none() {
+                *guard = Some(self.open().await?);
+            }
+            let connection = guard.as_mut().expect("connection was just opened");
+
+            match Self::round_trip(connection, &request, commands.len()).await {
+                Ok(responses) => return Ok(responses),
+                // Nothing was answered, but the server may still have run the batch before
+                // the connection went: only reads are safe to send again
+                Err(RoundTripError::Stale(e))
+                    if attempt < self.config.reconnect_attempts && Self::is_read_only(commands) =>
+                {
+                    attempt += 1;
+                    warn!(
+                        "Connection lost ({}), reconnecting (attempt {})",
+                        e, attempt
+                    );
+                    *guard = None;
+                    tokio::time::sleep(self.config.reconnect_delay).await;
+                }
+                Err(RoundTripError::Stale(e)) | Err(RoundTripError::Failed(e)) => {
+                    // The stream may be mid-reply, it can't be reused
+                    *guard = None;
+                    return Err(e);
+                }
+            }
+        }
+    }
+
+    // Send one line as typed, for interactive use. Lines the client can't parse still go out,
+    // the server may know them under a renamed command. Never retried: AUTH and SELECT only
+    // last as long as the connection and a fresh one would silently drop them
+    pub async fn execute_line(&self, line: &str) -> ClientResult<CommandResponse> {
+        let line = line.trim();
+        if line.is_empty() || line.contains(['\n', '\r']) {
+            return Err(
+                ProtocolError::InvalidFormat("Expected a single command line".to_string()).into(),
+            );
+        }
+
+        // Framing changes and pushed messages would confuse the reader, and SETCHUNK expects
+        // raw payload bytes after the line
+        if let Ok(command) = ProtocolParser::parse_command(line)
+            && matches!(
+                command,
+                Command::Compress(_)
+                    | Command::Proto(_)
+                    | Command::Encoding(_)
+                    | Command::Subscribe(_)
+                    | Command::Unsubscribe(_)
+                    | Command::SetChunk(_)
+            )
+        {
+            return Err(ProtocolError::InvalidFormat(
+                "Connection settings, SETCHUNK and SUBSCRIBE are not supported by the client"
+                    .to_string(),
+            )
+            .into());
+        }
+
+        let mut guard = self.connection.lock().await;
+        if guard.is_none() {
+            *guard = Some(self.open().await?);
+        }
+        let connection = guard.as_mut().expect("connection was just opened");
+
+        match Self::round_trip(connection, &format!("{}\n", line), 1).await {
+            Ok(mut responses) => responses.pop().ok_or(ClientError::ConnectionClosed),
+            Err(RoundTripError::Stale(e)) | Err(RoundTripError::Failed(e)) => {
+                *guard = None;
+                Err(e)
+            }
+        }
+    }
+
+    // Cloned into handlers only once a connection was lost, not on every request
+    fn is_read_only(commands: &[Command]) -> bool {
+        commands
+            .iter()
+            .all(|command| command.clone().into_handler().is_read_only())
+    }
+
+    async fn round_trip(
+        connection: &mut Connection,
+        request: &str,
+        count: usize,
+    ) -> Result<Vec<CommandResponse>, RoundTripError> {
+        let written = async {
+            connection.writer.write_all(request.as_bytes()).await?;
+            connection.writer.flush().await
+        };
+        written.await.map_err(|e| RoundTripError::Stale(e.into()))?;
+
+        let mut responses = Vec::with_capacity(count);
+        while responses.len() < count {
+            let response = Self::read_response(&mut connection.reader).await;
+            match response {
+                Ok(response) => responses.push(response),
+                Err(e) if responses.is_empty() && Self::is_disconnect(&e) => {
+                    return Err(RoundTripError::Stale(e));
+                }
+                Err(e) => return Err(RoundTripError::Failed(e)),
+            }
+        }
+
+        debug!("Received {} responses", responses.len());
+        Ok(responses)
+    }
+
+    // Read one reply: a header line plus the body lines it announces
+    // Server keepalive probes in between are skipped, the request itself answered them
+    async fn read_response(reader: &mut BufReader<OwnedReadHalf>) -> ClientResult<CommandResponse> {
+        let mut reply = String::new();
+        loop {
+            if reader.read_line(&mut reply).await? == 0 {
+                return Err(ClientError::ConnectionClosed);
+            }
+            if reply.trim_end() != KEEPALIVE {
+                break;
+            }
+            reply.clear();
+        }
+
+        for _ in 0..ProtocolParser::response_body_lines(&reply)? {
+            if reader.read_line(&mut reply).await? == 0 {
+                return Err(ClientError::ConnectionClosed);
+            }
+        }
+
+        Ok(ProtocolParser::parse_response(&reply)?)
+    }
+
+    async fn open(&self) -> ClientResult<Connection> {
+        debug!("Connecting to {}", self.addr);
+
+        let stream =
+            tokio::time::timeout(self.config.connect_timeout, TcpStream::connect(self.addr))
+                .await
+                .map_err(|_| {
+                    std::io::Error::new(
+                        std::io::ErrorKind::TimedOut,
+                        format!("connect to {} timed out", self.addr),
+                    )
+                })??;
+        stream.set_nodelay(true)?;
+
+        let (reader, writer) = stream.into_split();
+        Ok(Connection {
+            reader: BufReader::new(reader),
+            writer,
+        })
+    }
+
+    async fn expect_ok(&self, command: Command) -> ClientResult<()> {
+        match self.execute(command).await? {
+            CommandResponse::Ok => Ok(()),
+            other => Err(Self::unexpected(other)),
+        }
+    }
+
+    fn unexpected(response: CommandResponse) -> ClientError {
+        match response {
+            CommandResponse::Error(msg) => ClientError::Server(msg),
+            other => ClientError::UnexpectedResponse(other),
+        }
+    }
+
+    fn is_disconnect(error: &ClientError) -> bool {
+        match error {
+            ClientError::ConnectionClosed => true,
+            ClientError::Io(e) => matches!(
+                e.kind(),
+                std::io::ErrorKind::ConnectionReset
+                    | std::io::ErrorKind::ConnectionAborted
+                    | std::io::ErrorKind::BrokenPipe
+            ),
+            _ => false,
+        }
+    }
+}
+
+// Whether a failed round trip can be retried on a fresh connection
+enum RoundTripError {
+    Stale(ClientError),  // Failed before any reply arrived
+    Failed(ClientError), // Failed mid-batch or on a bad reply
+}
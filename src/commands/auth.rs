@@ -0,0 +1,85 @@
+use std::fmt;
+
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    commands::{CommandContext, CommandError, CommandHandler, CommandResponse},
+    storage::StorageEngine,
+};
+
+// Log the connection in as a user, the default one when no username is given
+// The dispatcher only checks the credentials, the connection keeps the user once acknowledged
+#[derive(Clone, PartialEq, Serialize, Deserialize)]
+pub struct AuthCommand {
+    pub username: Option<String>,
+    pub password: String,
+}
+
+impl AuthCommand {
+    pub fn new(password: String) -> Self {
+        Self {
+            username: None,
+            password,
+        }
+    }
+
+    pub fn with_username(username: String, password: String) -> Self {
+        Self {
+            username: Some(username),
+            password,
+        }
+    }
+}
+
+// Parsed commands are logged at debug level, keep the password out of them
+impl fmt::Debug for AuthCommand {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("AuthCommand")
+            .field("username", &self.username)
+            .field("password", &"<redacted>")
+            .finish()
+    }
+}
+
+#[async_trait]
+impl CommandHandler for AuthCommand {
+    async fn execute(&self, _storage: &dyn StorageEngine) -> CommandResponse {
+        CommandResponse::Error("AUTH requires a dispatcher".to_string())
+    }
+
+    async fn execute_in(&self, ctx: &CommandContext<'_>) -> CommandResponse {
+        let Some(acl) = ctx.acl else {
+            return CommandResponse::Error("AUTH requires a dispatcher".to_string());
+        };
+
+        if !acl.has_passwords() {
+            return CommandResponse::Error(
+                "AUTH called without any password configured".to_string(),
+            );
+        }
+
+        match acl.authenticate(self.username.as_deref(), &self.password) {
+            Some(_) => CommandResponse::Ok,
+            None => CommandResponse::Error("WRONGPASS invalid username-password pair".to_string()),
+        }
+    }
+
+    fn name(&self) -> &'static str {
+        "AUTH"
+    }
+
+    fn validate(&self) -> Result<(), CommandError> {
+        if self.username.as_deref() == Some("") {
+            return Err(CommandError::InvalidParameter(
+                "Username can't be empty".to_string(),
+            ));
+        }
+        Ok(())
+    }
+
+    // Must stay runnable while writes are refused
+    fn is_read_only(&self) -> bool {
+        true
+    }
+}
@@ -0,0 +1,52 @@
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use tracing::{debug, instrument};
+
+use crate::{
+    commands::{CommandContext, CommandError, CommandHandler, CommandResponse},
+    storage::StorageEngine,
+};
+
+// Compact the AOF in the background without waiting for the next snapshot
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BgRewriteAofCommand;
+
+#[async_trait]
+impl CommandHandler for BgRewriteAofCommand {
+    async fn execute(&self, _storage: &dyn StorageEngine) -> CommandResponse {
+        CommandResponse::Error("BGREWRITEAOF requires persistence".to_string())
+    }
+
+    #[instrument(skip(self, ctx))]
+    async fn execute_in(&self, ctx: &CommandContext<'_>) -> CommandResponse {
+        let Some(persistence) = ctx.persistence else {
+            return self.execute(ctx.storage).await;
+        };
+
+        // Progress and failures are reported through the persistence stats
+        match persistence.start_aof_rewrite() {
+            Ok(()) => CommandResponse::Ok,
+            Err(e) => {
+                debug!("Failed to start AOF rewrite: {}", e);
+                CommandResponse::Error(e.to_string())
+            }
+        }
+    }
+
+    fn name(&self) -> &'static str {
+        "BGREWRITEAOF"
+    }
+
+    fn validate(&self) -> Result<(), CommandError> {
+        Ok(())
+    }
+
+    // Rewrites the log, not the dataset, so it stays available in read-only mode
+    fn is_read_only(&self) -> bool {
+        true
+    }
+
+    fn is_admin(&self) -> bool {
+        true
+    }
+}
@@ -0,0 +1,61 @@
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use tracing::{debug, instrument};
+
+use crate::{
+    commands::{CommandError, CommandHandler, CommandResponse, KeyLimits},
+    storage::StorageEngine,
+};
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BitCountCommand {
+    pub key: String,
+}
+
+impl BitCountCommand {
+    pub fn new(key: String) -> Self {
+        Self { key }
+    }
+}
+
+#[async_trait]
+impl CommandHandler for BitCountCommand {
+    #[instrument(skip(self, storage), fields(key = %self.key))]
+    async fn execute(&self, storage: &dyn StorageEngine) -> CommandResponse {
+        debug!("Executing BITCOUNT command");
+
+        // Missing keys count as an empty value
+        match storage.get_range(&self.key, 0, -1).await {
+            Ok(bytes) => {
+                let count: u32 = bytes.iter().map(|b| b.count_ones()).sum();
+                CommandResponse::Integer(i64::from(count))
+            }
+            Err(e) => {
+                debug!("Failed to count bits: {}", e);
+                CommandResponse::Error(e.to_string())
+            }
+        }
+    }
+
+    fn name(&self) -> &'static str {
+        "BITCOUNT"
+    }
+
+    fn validate(&self) -> Result<(), CommandError> {
+        self.validate_with(&KeyLimits::default())
+    }
+
+    fn validate_with(&self, limits: &KeyLimits) -> Result<(), CommandError> {
+        if self.key.is_empty() {
+            return Err(CommandError::InvalidParameter(
+                "Key cannot be empty".to_string(),
+            ));
+        }
+
+        limits.check_key(&self.key)
+    }
+
+    fn is_read_only(&self) -> bool {
+        true
+    }
+}
@@ -0,0 +1,95 @@
+use std::time::Duration;
+
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use tracing::{debug, instrument};
+
+use crate::{
+    commands::{CommandContext, CommandError, CommandHandler, CommandResponse, KeyLimits},
+    storage::{StorageEngine, persistence::aof::Operation},
+};
+
+// Pop the first item of a list, waiting up to `timeout` (zero = forever) for one to be
+// pushed. The waiting is done by the dispatcher, each attempt here never blocks
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BLPopCommand {
+    pub key: String,
+    pub timeout: Duration,
+}
+
+impl BLPopCommand {
+    pub fn new(key: String, timeout: Duration) -> Self {
+        Self { key, timeout }
+    }
+}
+
+#[async_trait]
+impl CommandHandler for BLPopCommand {
+    #[instrument(skip(self, storage), fields(key = %self.key))]
+    async fn execute(&self, storage: &dyn StorageEngine) -> CommandResponse {
+        self.execute_in(&CommandContext::for_storage(storage)).await
+    }
+
+    // Only an actual pop is logged, an attempt that finds the list empty changes nothing
+    #[instrument(skip(self, ctx), fields(key = %self.key))]
+    async fn execute_in(&self, ctx: &CommandContext<'_>) -> CommandResponse {
+        debug!("Executing BLPOP command");
+
+        let item = match ctx.storage.pop_front(&self.key).await {
+            Ok(Some(item)) => item,
+            Ok(None) => return CommandResponse::Nil,
+            Err(e) => {
+                debug!("Failed to pop item: {}", e);
+                return CommandResponse::Error(e.to_string());
+            }
+        };
+
+        if let Some(persistence) = ctx.persistence
+            && let Err(e) = persistence
+                .log_operation(
+                    Operation::ListPop {
+                        key: self.key.clone(),
+                    }
+                    .in_database(ctx.database),
+                )
+                .await
+        {
+            return CommandResponse::Error(format!("Persistence error: {}", e));
+        }
+
+        CommandResponse::Value(item)
+    }
+
+    fn name(&self) -> &'static str {
+        "BLPOP"
+    }
+
+    fn validate(&self) -> Result<(), CommandError> {
+        self.validate_with(&KeyLimits::default())
+    }
+
+    fn validate_with(&self, limits: &KeyLimits) -> Result<(), CommandError> {
+        if self.key.is_empty() {
+            return Err(CommandError::InvalidParameter(
+                "Key cannot be empty".to_string(),
+            ));
+        }
+
+        limits.check_key(&self.key)?;
+        limits.check_writable(&self.key)?;
+
+        Ok(())
+    }
+
+    fn is_read_only(&self) -> bool {
+        false
+    }
+
+    fn write_keys(&self) -> Option<Vec<&str>> {
+        Some(vec![&self.key])
+    }
+
+    fn blocks_on(&self) -> Option<(&str, Duration)> {
+        Some((&self.key, self.timeout))
+    }
+}
@@ -0,0 +1,66 @@
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    commands::{CommandError, CommandHandler, CommandResponse},
+    storage::StorageEngine,
+};
+
+// Default minimum VALUE payload size (bytes) worth compressing
+pub const DEFAULT_COMPRESS_THRESHOLD: usize = 1024;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum CompressionAlgorithm {
+    Gzip,
+}
+
+impl CompressionAlgorithm {
+    pub fn name(&self) -> &'static str {
+        match self {
+            CompressionAlgorithm::Gzip => "gzip",
+        }
+    }
+}
+
+// Connection-level response compression toggle
+// The storage is untouched; the connection applies the setting after a successful reply
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CompressCommand {
+    pub algorithm: Option<CompressionAlgorithm>, // None = compression off
+    pub threshold: usize,
+}
+
+impl CompressCommand {
+    pub fn on(algorithm: CompressionAlgorithm, threshold: usize) -> Self {
+        Self {
+            algorithm: Some(algorithm),
+            threshold,
+        }
+    }
+
+    pub fn off() -> Self {
+        Self {
+            algorithm: None,
+            threshold: DEFAULT_COMPRESS_THRESHOLD,
+        }
+    }
+}
+
+#[async_trait]
+impl CommandHandler for CompressCommand {
+    async fn execute(&self, _storage: &dyn StorageEngine) -> CommandResponse {
+        CommandResponse::Ok
+    }
+
+    fn name(&self) -> &'static str {
+        "COMPRESS"
+    }
+
+    fn validate(&self) -> Result<(), CommandError> {
+        Ok(())
+    }
+
+    fn is_read_only(&self) -> bool {
+        true
+    }
+}
@@ -0,0 +1,197 @@
+use std::sync::{Arc, atomic::Ordering};
+
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use tracing::{debug, info, instrument};
+
+use crate::{
+    commands::{CommandContext, CommandError, CommandHandler, CommandResponse},
+    config::FsyncPolicy,
+    storage::StorageEngine,
+};
+
+// Parameters visible through CONFIG GET
+pub const CONFIG_PARAMS: &[&str] = &[
+    "max_memory",
+    "fsync_policy",
+    "log_level",
+    "snapshot_interval",
+    "shard_count",
+    "readonly",
+];
+
+// CONFIG subcommands
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum ConfigSubcommand {
+    Get { param: String },
+    Set { param: String, value: String },
+}
+
+// Runtime configuration introspection and tuning
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ConfigCommand {
+    pub subcommand: ConfigSubcommand,
+}
+
+impl ConfigCommand {
+    pub fn get(param: String) -> Self {
+        Self {
+            subcommand: ConfigSubcommand::Get { param },
+        }
+    }
+
+    pub fn set(param: String, value: String) -> Self {
+        Self {
+            subcommand: ConfigSubcommand::Set { param, value },
+        }
+    }
+
+    fn get_param(ctx: &CommandContext<'_>, param: &str) -> Result<String, String> {
+        if param == "readonly" {
+            let read_only = ctx.read_only.ok_or("CONFIG requires a dispatcher")?;
+            return Ok(yes_no(read_only.load(Ordering::Acquire)).to_string());
+        }
+
+        let config = ctx
+            .config
+            .ok_or("CONFIG requires server configuration")?
+            .read();
+        match param {
+            "max_memory" => Ok(config.storage.max_memory.to_string()),
+            "fsync_policy" => Ok(config.persistence.fsync_policy.to_string()),
+            "log_level" => Ok(config.observability.log_level.clone()),
+            "snapshot_interval" => Ok(config.persistence.snapshot_interval.to_string()),
+            "shard_count" => Ok(config.storage.shard_count.to_string()),
+            other => Err(format!("Unknown config parameter '{}'", other)),
+        }
+    }
+
+    async fn set_param(ctx: &CommandContext<'_>, param: &str, value: &str) -> Result<(), String> {
+        match param {
+            "readonly" => {
+                let enabled = match value.to_lowercase().as_str() {
+                    "yes" => true,
+                    "no" => false,
+                    _ => return Err(format!("Invalid value for readonly: {}", value)),
+                };
+                let read_only = ctx.read_only.ok_or("CONFIG requires a dispatcher")?;
+                read_only.store(enabled, Ordering::Release);
+            }
+
+            "fsync_policy" => {
+                let policy = value.parse::<FsyncPolicy>().map_err(|e| e.to_string())?;
+                let config = ctx.config.ok_or("CONFIG requires server configuration")?;
+                let persistence = ctx.persistence.ok_or("Persistence not enabled")?;
+
+                persistence
+                    .set_fsync_policy(&policy)
+                    .await
+                    .map_err(|e| e.to_string())?;
+                config.write().persistence.fsync_policy = policy;
+            }
+
+            "snapshot_interval" => {
+                let seconds = value
+                    .parse::<u64>()
+                    .map_err(|_| format!("Invalid value for snapshot_interval: {}", value))?;
+                let config = ctx.config.ok_or("CONFIG requires server configuration")?;
+                let persistence = ctx.persistence.ok_or("Persistence not enabled")?;
+
+                persistence
+                    .set_snapshot_interval(seconds)
+                    .map_err(|e| e.to_string())?;
+                config.write().persistence.snapshot_interval = seconds;
+            }
+
+            // Every database is resized online, keys move to the new shards in the background
+            "shard_count" => {
+                let shard_count = value
+                    .parse::<usize>()
+                    .ok()
+                    .filter(|count| *count > 0)
+                    .ok_or_else(|| format!("Invalid value for shard_count: {}", value))?;
+                let config = ctx.config.ok_or("CONFIG requires server configuration")?;
+
+                let databases: Vec<&dyn StorageEngine> = if ctx.databases.is_empty() {
+                    vec![ctx.storage]
+                } else {
+                    ctx.databases.iter().map(Arc::as_ref).collect()
+                };
+                for storage in databases {
+                    storage
+                        .resize_shards(shard_count)
+                        .await
+                        .map_err(|e| e.to_string())?;
+                }
+                config.write().storage.shard_count = shard_count;
+            }
+
+            // Baked into components at startup
+            "max_memory" | "log_level" => {
+                return Err(format!("'{}' cannot be changed at runtime", param));
+            }
+
+            other => return Err(format!("Unknown config parameter '{}'", other)),
+        }
+
+        info!("CONFIG SET {} = {}", param, value);
+        Ok(())
+    }
+}
+
+fn yes_no(value: bool) -> &'static str {
+    if value { "yes" } else { "no" }
+}
+
+#[async_trait]
+impl CommandHandler for ConfigCommand {
+    async fn execute(&self, _storage: &dyn StorageEngine) -> CommandResponse {
+        CommandResponse::Error("CONFIG requires a dispatcher".to_string())
+    }
+
+    #[instrument(skip(self, ctx), fields(subcommand = ?self.subcommand))]
+    async fn execute_in(&self, ctx: &CommandContext<'_>) -> CommandResponse {
+        debug!("Executing CONFIG command");
+
+        match &self.subcommand {
+            ConfigSubcommand::Get { param } => match Self::get_param(ctx, param) {
+                Ok(value) => CommandResponse::Value(value.into_bytes()),
+                Err(e) => CommandResponse::Error(e),
+            },
+            ConfigSubcommand::Set { param, value } => {
+                match Self::set_param(ctx, param, value).await {
+                    Ok(()) => CommandResponse::Ok,
+                    Err(e) => CommandResponse::Error(e),
+                }
+            }
+        }
+    }
+
+    fn name(&self) -> &'static str {
+        "CONFIG"
+    }
+
+    fn validate(&self) -> Result<(), CommandError> {
+        let param = match &self.subcommand {
+            ConfigSubcommand::Get { param } | ConfigSubcommand::Set { param, .. } => param,
+        };
+
+        if !CONFIG_PARAMS.contains(&param.as_str()) {
+            return Err(CommandError::InvalidParameter(format!(
+                "Unknown config parameter '{}'",
+                param
+            )));
+        }
+
+        Ok(())
+    }
+
+    // Touches server settings, not data, so it stays usable in read-only mode
+    fn is_read_only(&self) -> bool {
+        true
+    }
+
+    fn is_admin(&self) -> bool {
+        true
+    }
+}
@@ -0,0 +1,87 @@
+use std::sync::atomic::Ordering;
+
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use tracing::info;
+
+use crate::{
+    commands::{CommandContext, CommandError, CommandHandler, CommandResponse},
+    storage::StorageEngine,
+};
+
+// DEBUG subcommands, test hooks into server internals
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum DebugCommand {
+    // Pause (false) or resume (true) the background expiry sweeper
+    SetActiveExpire(bool),
+
+    // Round-trip the dataset through persistence, failing if the key count changes
+    // Only allowed with server.debug_commands
+    Reload,
+}
+
+#[async_trait]
+impl CommandHandler for DebugCommand {
+    async fn execute(&self, _storage: &dyn StorageEngine) -> CommandResponse {
+        CommandResponse::Error("DEBUG requires a dispatcher".to_string())
+    }
+
+    async fn execute_in(&self, ctx: &CommandContext<'_>) -> CommandResponse {
+        match *self {
+            DebugCommand::SetActiveExpire(enabled) => {
+                let Some(active_expire) = ctx.active_expire else {
+                    return CommandResponse::Error("DEBUG requires a dispatcher".to_string());
+                };
+
+                if active_expire.swap(enabled, Ordering::AcqRel) != enabled {
+                    info!(
+                        "Active expiry {}",
+                        if enabled { "enabled" } else { "disabled" }
+                    );
+                }
+
+                CommandResponse::Ok
+            }
+
+            DebugCommand::Reload => {
+                let enabled = ctx
+                    .config
+                    .is_some_and(|config| config.read().server.debug_commands);
+                if !enabled {
+                    return CommandResponse::Error(
+                        "DEBUG RELOAD is disabled, set server.debug_commands".to_string(),
+                    );
+                }
+
+                let Some(persistence) = ctx.persistence else {
+                    return CommandResponse::Error("Persistence not enabled".to_string());
+                };
+
+                match persistence.reload().await {
+                    Ok((before, after)) if before == after => CommandResponse::Ok,
+                    Ok((before, after)) => CommandResponse::Error(format!(
+                        "DEBUG RELOAD key count mismatch: {} before, {} after",
+                        before, after
+                    )),
+                    Err(e) => CommandResponse::Error(format!("Persistence error: {}", e)),
+                }
+            }
+        }
+    }
+
+    fn name(&self) -> &'static str {
+        "DEBUG"
+    }
+
+    fn validate(&self) -> Result<(), CommandError> {
+        Ok(())
+    }
+
+    fn is_read_only(&self) -> bool {
+        true
+    }
+
+    fn is_admin(&self) -> bool {
+        true
+    }
+}
@@ -3,37 +3,64 @@ use serde::{Deserialize, Serialize};
 use tracing::{debug, instrument};
 
 use crate::{
-    commands::{CommandError, CommandHandler, CommandResponse},
-    storage::StorageEngine,
+    commands::{CommandContext, CommandError, CommandHandler, CommandResponse, KeyLimits},
+    storage::{StorageEngine, persistence::aof::Operation},
 };
 
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct DeleteCommand {
-    pub key: String,
+    pub keys: Vec<String>,
 }
 
 impl DeleteCommand {
     pub fn new(key: String) -> Self {
-        Self { key }
+        Self { keys: vec![key] }
+    }
+
+    pub fn many(keys: Vec<String>) -> Self {
+        Self { keys }
     }
 }
 
 #[async_trait]
 impl CommandHandler for DeleteCommand {
-    #[instrument(skip(self, storage), fields(key = %self.key))]
+    #[instrument(skip(self, storage), fields(keys = self.keys.len()))]
     async fn execute(&self, storage: &dyn StorageEngine) -> CommandResponse {
+        self.execute_in(&CommandContext::for_storage(storage)).await
+    }
+
+    // Logged after the removal like every write, one Delete per key that actually existed
+    // rather than a single aof_operation() for the whole command
+    #[instrument(skip(self, ctx), fields(keys = self.keys.len()))]
+    async fn execute_in(&self, ctx: &CommandContext<'_>) -> CommandResponse {
         debug!("Executing DELETE command");
 
-        match storage.delete(&self.key).await {
-            Ok(deleted) => {
-                debug!("Delete operation completed, deleted: {}", deleted);
-                CommandResponse::Bool(deleted)
-            }
-            Err(e) => {
-                debug!("Failed to delete key: {}", e);
-                CommandResponse::Error(e.to_string())
+        let mut deleted = 0;
+        for key in &self.keys {
+            match ctx.storage.delete(key).await {
+                Ok(true) => {
+                    deleted += 1;
+
+                    if let Some(persistence) = ctx.persistence
+                        && let Err(e) = persistence
+                            .log_operation(
+                                Operation::Delete { key: key.clone() }.in_database(ctx.database),
+                            )
+                            .await
+                    {
+                        return CommandResponse::Error(format!("Persistence error: {}", e));
+                    }
+                }
+                Ok(false) => {}
+                Err(e) => {
+                    debug!("Failed to delete key: {}", e);
+                    return CommandResponse::Error(e.to_string());
+                }
             }
         }
+
+        debug!("Delete operation completed, deleted: {}", deleted);
+        CommandResponse::Integer(deleted)
     }
 
     fn name(&self) -> &'static str {
@@ -41,16 +68,33 @@ impl CommandHandler for DeleteCommand {
     }
 
     fn validate(&self) -> Result<(), CommandError> {
-        if self.key.is_empty() {
+        self.validate_with(&KeyLimits::default())
+    }
+
+    fn validate_with(&self, limits: &KeyLimits) -> Result<(), CommandError> {
+        if self.keys.is_empty() || self.keys.iter().any(|key| key.is_empty()) {
             return Err(CommandError::MissingParameter(
                 "Key cannot be empty".to_string(),
             ));
         }
 
+        // Internal metadata is only removed by the server itself
+        for key in &self.keys {
+            limits.check_writable(key)?;
+        }
+
         Ok(())
     }
 
     fn is_read_only(&self) -> bool {
         false
     }
+
+    fn write_keys(&self) -> Option<Vec<&str>> {
+        Some(self.keys.iter().map(String::as_str).collect())
+    }
+
+    fn complexity(&self) -> u32 {
+        self.keys.len().max(1) as u32
+    }
 }
@@ -0,0 +1,105 @@
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use tracing::{debug, instrument};
+
+use crate::{
+    commands::{CommandContext, CommandError, CommandHandler, CommandResponse, KeyLimits},
+    storage::{StorageEngine, persistence::aof::Operation},
+};
+
+// Remove every key starting with a prefix, replying with how many were removed
+// An empty prefix matches everything, so it needs `all` as an explicit opt-in
+// Keys of the reserved namespace are never matched, not even by `all`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DelPrefixCommand {
+    pub prefix: String,
+    pub all: bool,
+}
+
+impl DelPrefixCommand {
+    pub fn new(prefix: String) -> Self {
+        Self { prefix, all: false }
+    }
+
+    // Every key of the database, like FLUSHDB but logged key by key
+    pub fn all() -> Self {
+        Self {
+            prefix: String::new(),
+            all: true,
+        }
+    }
+}
+
+#[async_trait]
+impl CommandHandler for DelPrefixCommand {
+    #[instrument(skip(self, storage), fields(prefix = %self.prefix))]
+    async fn execute(&self, storage: &dyn StorageEngine) -> CommandResponse {
+        self.execute_in(&CommandContext::for_storage(storage)).await
+    }
+
+    // Like DELETE, each removed key is logged as its own Delete so recovery needs nothing new
+    #[instrument(skip(self, ctx), fields(prefix = %self.prefix))]
+    async fn execute_in(&self, ctx: &CommandContext<'_>) -> CommandResponse {
+        debug!("Executing DELPREFIX command");
+
+        let deleted = match ctx.storage.delete_prefix(&self.prefix).await {
+            Ok(deleted) => deleted,
+            Err(e) => {
+                debug!("Failed to delete by prefix: {}", e);
+                return CommandResponse::Error(e.to_string());
+            }
+        };
+
+        if let Some(persistence) = ctx.persistence {
+            for key in &deleted {
+                if let Err(e) = persistence
+                    .log_operation(Operation::Delete { key: key.clone() }.in_database(ctx.database))
+                    .await
+                {
+                    return CommandResponse::Error(format!("Persistence error: {}", e));
+                }
+            }
+        }
+
+        debug!("Prefix delete completed, deleted: {}", deleted.len());
+        CommandResponse::Integer(deleted.len() as i64)
+    }
+
+    fn name(&self) -> &'static str {
+        "DELPREFIX"
+    }
+
+    fn validate(&self) -> Result<(), CommandError> {
+        self.validate_with(&KeyLimits::default())
+    }
+
+    fn validate_with(&self, limits: &KeyLimits) -> Result<(), CommandError> {
+        if self.all {
+            if !self.prefix.is_empty() {
+                return Err(CommandError::InvalidParameter(
+                    "--all takes no prefix".to_string(),
+                ));
+            }
+            return Ok(());
+        }
+
+        if self.prefix.is_empty() {
+            return Err(CommandError::InvalidParameter(
+                "Empty prefix would delete every key, use DELPREFIX --all".to_string(),
+            ));
+        }
+
+        // Internal metadata is only removed by the server itself
+        limits.check_prefix_writable(&self.prefix)?;
+
+        Ok(())
+    }
+
+    fn is_read_only(&self) -> bool {
+        false
+    }
+
+    fn complexity(&self) -> u32 {
+        100 // Walks every key of the database
+    }
+}
@@ -0,0 +1,49 @@
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    commands::{CommandError, CommandHandler, CommandResponse, KeyLimits},
+    storage::StorageEngine,
+};
+
+// Reply with the message as given; a cheap round trip for pools validating a connection
+// without touching storage
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct EchoCommand {
+    pub message: Vec<u8>,
+}
+
+impl EchoCommand {
+    pub fn new(message: Vec<u8>) -> Self {
+        Self { message }
+    }
+}
+
+#[async_trait]
+impl CommandHandler for EchoCommand {
+    async fn execute(&self, _storage: &dyn StorageEngine) -> CommandResponse {
+        CommandResponse::Value(self.message.clone())
+    }
+
+    fn name(&self) -> &'static str {
+        "ECHO"
+    }
+
+    fn validate(&self) -> Result<(), CommandError> {
+        self.validate_with(&KeyLimits::default())
+    }
+
+    fn validate_with(&self, limits: &KeyLimits) -> Result<(), CommandError> {
+        if self.message.len() > limits.max_value_size {
+            return Err(CommandError::InvalidParameter(format!(
+                "Message too large (max {} bytes)",
+                limits.max_value_size
+            )));
+        }
+        Ok(())
+    }
+
+    fn is_read_only(&self) -> bool {
+        true
+    }
+}
@@ -0,0 +1,46 @@
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    commands::{CommandError, CommandHandler, CommandResponse},
+    storage::StorageEngine,
+};
+
+// How VALUE payloads are framed in text mode
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ValueEncoding {
+    #[default]
+    Base64, // VALUE <base64>\n
+    Raw, // VALUE <len>\r\n<bytes>, payload sent verbatim
+}
+
+// Connection-level value encoding switch, applied by the connection after a successful reply
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct EncodingCommand {
+    pub encoding: ValueEncoding,
+}
+
+impl EncodingCommand {
+    pub fn new(encoding: ValueEncoding) -> Self {
+        Self { encoding }
+    }
+}
+
+#[async_trait]
+impl CommandHandler for EncodingCommand {
+    async fn execute(&self, _storage: &dyn StorageEngine) -> CommandResponse {
+        CommandResponse::Ok
+    }
+
+    fn name(&self) -> &'static str {
+        "ENCODING"
+    }
+
+    fn validate(&self) -> Result<(), CommandError> {
+        Ok(())
+    }
+
+    fn is_read_only(&self) -> bool {
+        true
+    }
+}
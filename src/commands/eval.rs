@@ -0,0 +1,175 @@
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use tracing::{debug, instrument};
+
+use crate::{
+    commands::{CommandContext, CommandError, CommandHandler, CommandResponse, KeyLimits},
+    storage::{
+        StorageEngine, StorageError, StorageResult,
+        persistence::aof::Operation,
+        value::{check_untyped, format_float, parse_float},
+    },
+};
+
+// Names accepted by `EVAL op key args...`
+pub const ATOMIC_OPS: &[&str] = &["CAS", "ADDMAX"];
+
+// Server-side compound operations, each run atomically under the key's shard lock
+// New ops only need a variant here, its `apply` and a parser arm
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum AtomicOp {
+    Cas { expected: Vec<u8>, new: Vec<u8> }, // Replace the value if it equals `expected`
+    AddMax(f64),                             // Store the larger of the current number and this one
+}
+
+impl AtomicOp {
+    pub fn name(&self) -> &'static str {
+        match self {
+            AtomicOp::Cas { .. } => "CAS",
+            AtomicOp::AddMax(_) => "ADDMAX",
+        }
+    }
+
+    // The value to store given the current one (None = leave the key), and the reply
+    fn apply(&self, current: Option<&[u8]>) -> StorageResult<(Option<Vec<u8>>, CommandResponse)> {
+        match self {
+            AtomicOp::Cas { expected, new } => {
+                if current == Some(expected.as_slice()) {
+                    Ok((Some(new.clone()), CommandResponse::Bool(true)))
+                } else {
+                    Ok((None, CommandResponse::Bool(false)))
+                }
+            }
+
+            AtomicOp::AddMax(n) => {
+                let current = current
+                    .map(|value| parse_float(value).ok_or(StorageError::NotAFloat))
+                    .transpose()?;
+
+                match current {
+                    Some(current) if current >= *n => Ok((
+                        None,
+                        CommandResponse::Value(format_float(current).into_bytes()),
+                    )),
+                    _ => {
+                        let value = format_float(*n).into_bytes();
+                        Ok((Some(value.clone()), CommandResponse::Value(value)))
+                    }
+                }
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct EvalCommand {
+    pub key: String,
+    pub op: AtomicOp,
+}
+
+impl EvalCommand {
+    pub fn new(key: String, op: AtomicOp) -> Self {
+        Self { key, op }
+    }
+}
+
+#[async_trait]
+impl CommandHandler for EvalCommand {
+    #[instrument(skip(self, storage), fields(key = %self.key, op = self.op.name()))]
+    async fn execute(&self, storage: &dyn StorageEngine) -> CommandResponse {
+        self.execute_in(&CommandContext::for_storage(storage)).await
+    }
+
+    // Like INCRBYFLOAT, the outcome is logged as a value that keeps the key's TTL
+    #[instrument(skip(self, ctx), fields(key = %self.key, op = self.op.name()))]
+    async fn execute_in(&self, ctx: &CommandContext<'_>) -> CommandResponse {
+        debug!("Executing EVAL command");
+
+        let mut reply = None;
+        let mut written = None;
+        let result = ctx
+            .storage
+            .update(
+                &self.key,
+                Box::new(|current| {
+                    let (value, response) = self.op.apply(current)?;
+                    reply = Some(response);
+                    written.clone_from(&value);
+                    Ok(value)
+                }),
+            )
+            .await;
+
+        if let Err(e) = result {
+            debug!("Failed to evaluate {}: {}", self.op.name(), e);
+            return CommandResponse::Error(e.to_string());
+        }
+
+        if let Some(value) = written
+            && let Some(persistence) = ctx.persistence
+            && let Err(e) = persistence
+                .log_operation(
+                    Operation::Update {
+                        key: self.key.clone(),
+                        value,
+                    }
+                    .in_database(ctx.database),
+                )
+                .await
+        {
+            return CommandResponse::Error(format!("Persistence error: {}", e));
+        }
+
+        reply.unwrap_or_else(|| CommandResponse::Error("EVAL produced no reply".to_string()))
+    }
+
+    fn name(&self) -> &'static str {
+        "EVAL"
+    }
+
+    fn validate(&self) -> Result<(), CommandError> {
+        self.validate_with(&KeyLimits::default())
+    }
+
+    fn validate_with(&self, limits: &KeyLimits) -> Result<(), CommandError> {
+        if self.key.is_empty() {
+            return Err(CommandError::InvalidParameter(
+                "Key cannot be empty".to_string(),
+            ));
+        }
+
+        limits.check_key(&self.key)?;
+        limits.check_writable(&self.key)?;
+
+        match self.op {
+            AtomicOp::Cas { ref new, .. } => {
+                if new.len() > limits.max_value_size {
+                    return Err(CommandError::InvalidParameter(format!(
+                        "Value too large (max {} bytes)",
+                        limits.max_value_size
+                    )));
+                }
+
+                check_untyped(new).map_err(|e| CommandError::InvalidParameter(e.to_string()))?;
+            }
+
+            AtomicOp::AddMax(n) if !n.is_finite() => {
+                return Err(CommandError::InvalidParameter(
+                    "ADDMAX needs a finite number".to_string(),
+                ));
+            }
+
+            AtomicOp::AddMax(_) => {}
+        }
+
+        Ok(())
+    }
+
+    fn is_read_only(&self) -> bool {
+        false
+    }
+
+    fn write_keys(&self) -> Option<Vec<&str>> {
+        Some(vec![&self.key])
+    }
+}
@@ -9,31 +9,50 @@ use crate::{
 
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct ExistCommand {
-    pub key: String,
+    pub keys: Vec<String>,
+    pub count: bool, // EXISTS form: reply with how many keys exist instead of a bool
 }
 
 impl ExistCommand {
+    // Single-key EXIST, replies TRUE/FALSE
     pub fn new(key: String) -> Self {
-        Self { key }
+        Self {
+            keys: vec![key],
+            count: false,
+        }
+    }
+
+    // Redis-style EXISTS, replies with the number of listed keys that exist
+    pub fn many(keys: Vec<String>) -> Self {
+        Self { keys, count: true }
     }
 }
 
 #[async_trait]
 impl CommandHandler for ExistCommand {
-    #[instrument(skip(self, storage), fields(key = %self.key))]
+    #[instrument(skip(self, storage), fields(keys = self.keys.len()))]
     async fn execute(&self, storage: &dyn StorageEngine) -> CommandResponse {
         debug!("Executing EXIST command");
 
-        match storage.exists(&self.key).await {
-            Ok(exist) => {
-                debug!("exist operation completed, exitsted: {}", exist);
-                CommandResponse::Bool(exist)
-            }
-            Err(e) => {
-                debug!("Failed to exist key: {}", e);
-                CommandResponse::Error(e.to_string())
+        // Duplicates are counted once per occurrence, as in Redis
+        let mut found = 0;
+        for key in &self.keys {
+            match storage.exists(key).await {
+                Ok(true) => found += 1,
+                Ok(false) => {}
+                Err(e) => {
+                    debug!("Failed to exist key: {}", e);
+                    return CommandResponse::Error(e.to_string());
+                }
             }
         }
+
+        debug!("exist operation completed, found: {}", found);
+        if self.count {
+            CommandResponse::Integer(found)
+        } else {
+            CommandResponse::Bool(found > 0)
+        }
     }
 
     fn name(&self) -> &'static str {
@@ -41,7 +60,7 @@ impl CommandHandler for ExistCommand {
     }
 
     fn validate(&self) -> Result<(), CommandError> {
-        if self.key.is_empty() {
+        if self.keys.is_empty() || self.keys.iter().any(|key| key.is_empty()) {
             return Err(CommandError::MissingParameter(
                 "Key cannot be empty".to_string(),
             ));
@@ -51,6 +70,10 @@ impl CommandHandler for ExistCommand {
     }
 
     fn is_read_only(&self) -> bool {
-        false
+        true
+    }
+
+    fn complexity(&self) -> u32 {
+        self.keys.len().max(1) as u32
     }
 }
@@ -0,0 +1,122 @@
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use tracing::{debug, instrument};
+
+use crate::{
+    commands::{CommandContext, CommandError, CommandHandler, CommandResponse, KeyLimits},
+    storage::{StorageEngine, now_millis, persistence::aof::Operation},
+};
+
+// EXPIRE/PEXPIRE (relative seconds/millis) and EXPIREAT/PEXPIREAT (unix seconds/millis).
+// All four resolve to an absolute unix time in millis before reaching storage
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ExpireCommand {
+    pub key: String,
+    pub amount: u64,
+    pub millis: bool,
+    pub absolute: bool,
+}
+
+impl ExpireCommand {
+    pub fn new(key: String, amount: u64, millis: bool, absolute: bool) -> Self {
+        Self {
+            key,
+            amount,
+            millis,
+            absolute,
+        }
+    }
+
+    // The absolute expiry in unix millis, None if it doesn't fit
+    fn deadline(&self, now: u64) -> Option<u64> {
+        let millis = if self.millis {
+            self.amount
+        } else {
+            self.amount.checked_mul(1000)?
+        };
+
+        if self.absolute {
+            Some(millis)
+        } else {
+            now.checked_add(millis)
+        }
+    }
+}
+
+#[async_trait]
+impl CommandHandler for ExpireCommand {
+    #[instrument(skip(self, storage), fields(key = %self.key, amount = self.amount))]
+    async fn execute(&self, storage: &dyn StorageEngine) -> CommandResponse {
+        self.execute_in(&CommandContext::for_storage(storage)).await
+    }
+
+    // Logged with the absolute time storage settled on, max_ttl applied, so a replay
+    // neither restarts the countdown nor depends on the policy in force then
+    #[instrument(skip(self, ctx), fields(key = %self.key, amount = self.amount))]
+    async fn execute_in(&self, ctx: &CommandContext<'_>) -> CommandResponse {
+        debug!("Executing {} command", self.name());
+
+        let Some(at_millis) = self.deadline(now_millis()) else {
+            return CommandResponse::Error("Expire time out of range".to_string());
+        };
+
+        let at_millis = match ctx.storage.expire_at(&self.key, at_millis).await {
+            Ok(Some(at_millis)) => at_millis,
+            Ok(None) => return CommandResponse::Bool(false),
+            Err(e) => {
+                debug!("Failed to set expiry: {}", e);
+                return CommandResponse::Error(e.to_string());
+            }
+        };
+
+        if let Some(persistence) = ctx.persistence
+            && let Err(e) = persistence
+                .log_operation(
+                    Operation::ExpireAt {
+                        key: self.key.clone(),
+                        at_millis,
+                    }
+                    .in_database(ctx.database),
+                )
+                .await
+        {
+            return CommandResponse::Error(format!("Persistence error: {}", e));
+        }
+
+        CommandResponse::Bool(true)
+    }
+
+    fn name(&self) -> &'static str {
+        match (self.millis, self.absolute) {
+            (false, false) => "EXPIRE",
+            (true, false) => "PEXPIRE",
+            (false, true) => "EXPIREAT",
+            (true, true) => "PEXPIREAT",
+        }
+    }
+
+    fn validate(&self) -> Result<(), CommandError> {
+        self.validate_with(&KeyLimits::default())
+    }
+
+    fn validate_with(&self, limits: &KeyLimits) -> Result<(), CommandError> {
+        if self.key.is_empty() {
+            return Err(CommandError::InvalidParameter(
+                "Key cannot be empty".to_string(),
+            ));
+        }
+
+        limits.check_key(&self.key)?;
+        limits.check_writable(&self.key)?;
+
+        Ok(())
+    }
+
+    fn is_read_only(&self) -> bool {
+        false
+    }
+
+    fn write_keys(&self) -> Option<Vec<&str>> {
+        Some(vec![&self.key])
+    }
+}
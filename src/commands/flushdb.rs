@@ -0,0 +1,42 @@
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use tracing::{debug, instrument};
+
+use crate::{
+    commands::{CommandError, CommandHandler, CommandResponse},
+    storage::{StorageEngine, persistence::aof::Operation},
+};
+
+// Remove every key of the selected database, other databases are untouched
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FlushDbCommand;
+
+#[async_trait]
+impl CommandHandler for FlushDbCommand {
+    #[instrument(skip(self, storage))]
+    async fn execute(&self, storage: &dyn StorageEngine) -> CommandResponse {
+        match storage.clear().await {
+            Ok(removed) => {
+                debug!("Flushed {} keys", removed);
+                CommandResponse::Ok
+            }
+            Err(e) => CommandResponse::Error(e.to_string()),
+        }
+    }
+
+    fn aof_operation(&self) -> Option<Operation> {
+        Some(Operation::Flush)
+    }
+
+    fn name(&self) -> &'static str {
+        "FLUSHDB"
+    }
+
+    fn validate(&self) -> Result<(), CommandError> {
+        Ok(())
+    }
+
+    fn complexity(&self) -> u32 {
+        100 // Linear in database size
+    }
+}
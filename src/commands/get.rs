@@ -3,8 +3,9 @@ use serde::{Deserialize, Serialize};
 use tracing::{debug, instrument};
 
 use crate::{
-    commands::{CommandError, CommandHandler, CommandResponse},
-    storage::StorageEngine,
+    commands::{CommandContext, CommandError, CommandHandler, CommandResponse, KeyLimits},
+    config::GetMissingBehavior,
+    storage::{StorageEngine, StorageError, value::is_typed},
 };
 
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
@@ -22,16 +23,37 @@ impl GetCommand {
 impl CommandHandler for GetCommand {
     #[instrument(skip(self, storage), fields(key = %self.key))]
     async fn execute(&self, storage: &dyn StorageEngine) -> CommandResponse {
+        self.execute_in(&CommandContext::for_storage(storage)).await
+    }
+
+    // A missing key is answered as protocol.get_missing_behavior says, nil without a config
+    #[instrument(skip(self, ctx), fields(key = %self.key))]
+    async fn execute_in(&self, ctx: &CommandContext<'_>) -> CommandResponse {
         debug!("Executing GET command");
 
-        match storage.get(&self.key).await {
+        match ctx.storage.get(&self.key).await {
+            Ok(Some(value)) if is_typed(&value) => {
+                debug!("Key holds a set or list");
+                CommandResponse::Error(StorageError::WrongType.to_string())
+            }
             Ok(Some(value)) => {
                 debug!("Key found, returning value of {} bytes", value.len());
                 CommandResponse::Value(value)
             }
             Ok(None) => {
                 debug!("Key not found");
-                CommandResponse::Error("Key not found".to_string())
+                let behavior = ctx
+                    .config
+                    .map_or_else(GetMissingBehavior::default, |config| {
+                        config.read().protocol.get_missing_behavior
+                    });
+                match behavior {
+                    GetMissingBehavior::Error => {
+                        CommandResponse::Error("Key not found".to_string())
+                    }
+                    GetMissingBehavior::Nil => CommandResponse::Nil,
+                    GetMissingBehavior::Empty => CommandResponse::Value(Vec::new()),
+                }
             }
             Err(e) => {
                 debug!("Storage error: {}", e);
@@ -45,18 +67,17 @@ impl CommandHandler for GetCommand {
     }
 
     fn validate(&self) -> Result<(), CommandError> {
+        self.validate_with(&KeyLimits::default())
+    }
+
+    fn validate_with(&self, limits: &KeyLimits) -> Result<(), CommandError> {
         if self.key.is_empty() {
             return Err(CommandError::InvalidParameter(
                 "Key cannot be empty".to_string(),
             ));
         }
 
-        if self.key.len() > 512 {
-            return Err(CommandError::InvalidParameter(
-                "Key too long (max 512 bytes)".to_string(),
-            ));
-        }
-        Ok(())
+        limits.check_key(&self.key)
     }
 
     fn is_read_only(&self) -> bool {
@@ -0,0 +1,68 @@
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use tracing::{debug, instrument};
+
+use crate::{
+    commands::{CommandError, CommandHandler, CommandResponse, KeyLimits},
+    storage::StorageEngine,
+};
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct GetBitCommand {
+    pub key: String,
+    pub offset: usize, // Bit offset, bit 0 is the most significant bit of byte 0
+}
+
+impl GetBitCommand {
+    pub fn new(key: String, offset: usize) -> Self {
+        Self { key, offset }
+    }
+}
+
+#[async_trait]
+impl CommandHandler for GetBitCommand {
+    #[instrument(skip(self, storage), fields(key = %self.key, offset = self.offset))]
+    async fn execute(&self, storage: &dyn StorageEngine) -> CommandResponse {
+        debug!("Executing GETBIT command");
+
+        // Bits past the end of the value (or of a missing key) read as 0
+        let Ok(byte) = i64::try_from(self.offset / 8) else {
+            return CommandResponse::Integer(0);
+        };
+
+        match storage.get_range(&self.key, byte, byte).await {
+            Ok(bytes) => {
+                let bit = bytes
+                    .first()
+                    .is_some_and(|b| b & (0x80 >> (self.offset % 8)) != 0);
+                CommandResponse::Integer(i64::from(bit))
+            }
+            Err(e) => {
+                debug!("Failed to get bit: {}", e);
+                CommandResponse::Error(e.to_string())
+            }
+        }
+    }
+
+    fn name(&self) -> &'static str {
+        "GETBIT"
+    }
+
+    fn validate(&self) -> Result<(), CommandError> {
+        self.validate_with(&KeyLimits::default())
+    }
+
+    fn validate_with(&self, limits: &KeyLimits) -> Result<(), CommandError> {
+        if self.key.is_empty() {
+            return Err(CommandError::InvalidParameter(
+                "Key cannot be empty".to_string(),
+            ));
+        }
+
+        limits.check_key(&self.key)
+    }
+
+    fn is_read_only(&self) -> bool {
+        true
+    }
+}
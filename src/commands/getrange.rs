@@ -0,0 +1,60 @@
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use tracing::{debug, instrument};
+
+use crate::{
+    commands::{CommandError, CommandHandler, CommandResponse, KeyLimits},
+    storage::StorageEngine,
+};
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct GetRangeCommand {
+    pub key: String,
+    pub start: i64, // Inclusive, negative counts from the end
+    pub end: i64,   // Inclusive, negative counts from the end
+}
+
+impl GetRangeCommand {
+    pub fn new(key: String, start: i64, end: i64) -> Self {
+        Self { key, start, end }
+    }
+}
+
+#[async_trait]
+impl CommandHandler for GetRangeCommand {
+    #[instrument(skip(self, storage), fields(key = %self.key, start = self.start, end = self.end))]
+    async fn execute(&self, storage: &dyn StorageEngine) -> CommandResponse {
+        debug!("Executing GETRANGE command");
+
+        // Missing keys read as an empty value, like Redis
+        match storage.get_range(&self.key, self.start, self.end).await {
+            Ok(bytes) => CommandResponse::Value(bytes),
+            Err(e) => {
+                debug!("Failed to get range: {}", e);
+                CommandResponse::Error(e.to_string())
+            }
+        }
+    }
+
+    fn name(&self) -> &'static str {
+        "GETRANGE"
+    }
+
+    fn validate(&self) -> Result<(), CommandError> {
+        self.validate_with(&KeyLimits::default())
+    }
+
+    fn validate_with(&self, limits: &KeyLimits) -> Result<(), CommandError> {
+        if self.key.is_empty() {
+            return Err(CommandError::InvalidParameter(
+                "Key cannot be empty".to_string(),
+            ));
+        }
+
+        limits.check_key(&self.key)
+    }
+
+    fn is_read_only(&self) -> bool {
+        true
+    }
+}
@@ -0,0 +1,64 @@
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use tracing::{debug, instrument};
+
+use crate::{
+    commands::{CommandError, CommandHandler, CommandResponse, KeyLimits},
+    storage::{StorageEngine, StorageError, value::is_typed},
+};
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct GetVerCommand {
+    pub key: String,
+}
+
+impl GetVerCommand {
+    pub fn new(key: String) -> Self {
+        Self { key }
+    }
+}
+
+#[async_trait]
+impl CommandHandler for GetVerCommand {
+    #[instrument(skip(self, storage), fields(key = %self.key))]
+    async fn execute(&self, storage: &dyn StorageEngine) -> CommandResponse {
+        debug!("Executing GETVER command");
+
+        match storage.get_versioned(&self.key).await {
+            Ok(Some((value, _))) if is_typed(&value) => {
+                CommandResponse::Error(StorageError::WrongType.to_string())
+            }
+            Ok(Some((value, version))) => {
+                debug!("Key found at version {}", version);
+                CommandResponse::Versioned { value, version }
+            }
+            Ok(None) => CommandResponse::Error("Key not found".to_string()),
+            Err(e) => {
+                debug!("Storage error: {}", e);
+                CommandResponse::Error(e.to_string())
+            }
+        }
+    }
+
+    fn name(&self) -> &'static str {
+        "GETVER"
+    }
+
+    fn validate(&self) -> Result<(), CommandError> {
+        self.validate_with(&KeyLimits::default())
+    }
+
+    fn validate_with(&self, limits: &KeyLimits) -> Result<(), CommandError> {
+        if self.key.is_empty() {
+            return Err(CommandError::InvalidParameter(
+                "Key cannot be empty".to_string(),
+            ));
+        }
+
+        limits.check_key(&self.key)
+    }
+
+    fn is_read_only(&self) -> bool {
+        true
+    }
+}
@@ -0,0 +1,59 @@
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    commands::{CommandError, CommandHandler, CommandResponse},
+    storage::StorageEngine,
+};
+
+// Version of the line-based protocol, the only one HELLO can negotiate
+pub const PROTOCOL_VERSION: u32 = 1;
+
+// Reply formats a connection can switch between with PROTO
+pub const PROTOCOLS: &str = "text,json";
+
+// Introduce the server to a client: name, version and the protocols it speaks
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct HelloCommand {
+    pub protover: Option<u32>, // None = keep the current protocol
+}
+
+impl HelloCommand {
+    pub fn new(protover: Option<u32>) -> Self {
+        Self { protover }
+    }
+}
+
+#[async_trait]
+impl CommandHandler for HelloCommand {
+    async fn execute(&self, _storage: &dyn StorageEngine) -> CommandResponse {
+        if let Some(protover) = self.protover
+            && protover != PROTOCOL_VERSION
+        {
+            return CommandResponse::Error(format!(
+                "NOPROTO unsupported protocol version {}",
+                protover
+            ));
+        }
+
+        CommandResponse::Map(vec![
+            ("server".to_string(), "blazekvdb".to_string()),
+            ("version".to_string(), env!("CARGO_PKG_VERSION").to_string()),
+            ("proto".to_string(), PROTOCOL_VERSION.to_string()),
+            ("mode".to_string(), "standalone".to_string()),
+            ("protocols".to_string(), PROTOCOLS.to_string()),
+        ])
+    }
+
+    fn name(&self) -> &'static str {
+        "HELLO"
+    }
+
+    fn validate(&self) -> Result<(), CommandError> {
+        Ok(())
+    }
+
+    fn is_read_only(&self) -> bool {
+        true
+    }
+}
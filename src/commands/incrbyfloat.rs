@@ -0,0 +1,94 @@
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use tracing::{debug, instrument};
+
+use crate::{
+    commands::{CommandContext, CommandError, CommandHandler, CommandResponse, KeyLimits},
+    storage::{StorageEngine, persistence::aof::Operation, value::format_float},
+};
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct IncrByFloatCommand {
+    pub key: String,
+    pub delta: f64,
+}
+
+impl IncrByFloatCommand {
+    pub fn new(key: String, delta: f64) -> Self {
+        Self { key, delta }
+    }
+}
+
+#[async_trait]
+impl CommandHandler for IncrByFloatCommand {
+    #[instrument(skip(self, storage), fields(key = %self.key, delta = self.delta))]
+    async fn execute(&self, storage: &dyn StorageEngine) -> CommandResponse {
+        self.execute_in(&CommandContext::for_storage(storage)).await
+    }
+
+    // The result is logged as a value once it is known, so replay never redoes float
+    // arithmetic and can't drift from what clients were told
+    #[instrument(skip(self, ctx), fields(key = %self.key, delta = self.delta))]
+    async fn execute_in(&self, ctx: &CommandContext<'_>) -> CommandResponse {
+        debug!("Executing INCRBYFLOAT command");
+
+        let value = match ctx.storage.incr_by_float(&self.key, self.delta).await {
+            Ok(result) => format_float(result).into_bytes(),
+            Err(e) => {
+                debug!("Failed to increment float: {}", e);
+                return CommandResponse::Error(e.to_string());
+            }
+        };
+
+        if let Some(persistence) = ctx.persistence
+            && let Err(e) = persistence
+                .log_operation(
+                    Operation::Update {
+                        key: self.key.clone(),
+                        value: value.clone(),
+                    }
+                    .in_database(ctx.database),
+                )
+                .await
+        {
+            return CommandResponse::Error(format!("Persistence error: {}", e));
+        }
+
+        CommandResponse::Value(value)
+    }
+
+    fn name(&self) -> &'static str {
+        "INCRBYFLOAT"
+    }
+
+    fn validate(&self) -> Result<(), CommandError> {
+        self.validate_with(&KeyLimits::default())
+    }
+
+    fn validate_with(&self, limits: &KeyLimits) -> Result<(), CommandError> {
+        if self.key.is_empty() {
+            return Err(CommandError::InvalidParameter(
+                "Key cannot be empty".to_string(),
+            ));
+        }
+
+        limits.check_key(&self.key)?;
+        limits.check_writable(&self.key)?;
+
+        if !self.delta.is_finite() {
+            return Err(CommandError::InvalidParameter(
+                "Increment must be a finite number".to_string(),
+            ));
+        }
+
+        Ok(())
+    }
+
+    fn is_read_only(&self) -> bool {
+        false
+    }
+
+    fn write_keys(&self) -> Option<Vec<&str>> {
+        Some(vec![&self.key])
+    }
+}
@@ -0,0 +1,192 @@
+use std::sync::{
+    Arc,
+    atomic::{AtomicU64, Ordering},
+};
+
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use tracing::debug;
+
+use crate::{
+    commands::{ClientTraffic, CommandContext, CommandError, CommandHandler, CommandResponse},
+    storage::{StorageEngine, StorageStats, persistence::manager::PersistenceManager},
+};
+
+pub const SECTIONS: &[&str] = &["stats", "persistence", "keyspace"];
+
+// Server information as `field:value` lines grouped under `# Section` headers, like Redis
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct InfoCommand {
+    pub section: Option<String>, // None = every section
+}
+
+impl InfoCommand {
+    pub fn new(section: Option<String>) -> Self {
+        Self { section }
+    }
+
+    fn includes(&self, section: &str) -> bool {
+        self.section
+            .as_deref()
+            .is_none_or(|wanted| wanted.eq_ignore_ascii_case(section))
+    }
+
+    async fn respond(
+        &self,
+        databases: &[&dyn StorageEngine],
+        traffic: Option<&ClientTraffic>,
+        persistence: Option<&PersistenceManager>,
+    ) -> CommandResponse {
+        let mut stats = Vec::with_capacity(databases.len());
+        for storage in databases {
+            match storage.stats().await {
+                Ok(s) => stats.push(s),
+                Err(e) => {
+                    debug!("Failed to collect info: {}", e);
+                    return CommandResponse::Error(e.to_string());
+                }
+            }
+        }
+
+        let mut info = String::new();
+        if self.includes("stats") {
+            info.push_str("# Stats\r\n");
+            let sum = |field: fn(&StorageStats) -> u64| -> u64 { stats.iter().map(field).sum() };
+            info.push_str(&format!("keyspace_hits:{}\r\n", sum(|s| s.keyspace_hits)));
+            info.push_str(&format!(
+                "keyspace_misses:{}\r\n",
+                sum(|s| s.keyspace_misses)
+            ));
+            info.push_str(&format!("expired_keys:{}\r\n", sum(|s| s.expired_keys)));
+            info.push_str(&format!("evicted_keys:{}\r\n", sum(|s| s.evicted_keys)));
+            // Every database shares the cap, 0 = unlimited like Redis' maxmemory
+            let max_keys = stats.first().and_then(|s| s.max_keys).unwrap_or(0);
+            info.push_str(&format!("max_keys:{}\r\n", max_keys));
+            // Closed connections only, open ones are added as they close
+            if let Some(traffic) = traffic {
+                let total = |counter: &AtomicU64| counter.load(Ordering::Relaxed);
+                info.push_str(&format!(
+                    "total_connections_closed:{}\r\n",
+                    total(&traffic.connections)
+                ));
+                info.push_str(&format!(
+                    "total_commands_processed:{}\r\n",
+                    total(&traffic.commands_processed)
+                ));
+                info.push_str(&format!(
+                    "total_net_input_bytes:{}\r\n",
+                    total(&traffic.bytes_received)
+                ));
+                info.push_str(&format!(
+                    "total_net_output_bytes:{}\r\n",
+                    total(&traffic.bytes_sent)
+                ));
+                info.push_str(&format!(
+                    "reaped_connections:{}\r\n",
+                    total(&traffic.reaped_connections)
+                ));
+                info.push_str(&format!(
+                    "accept_errors:{}\r\n",
+                    total(&traffic.accept_errors)
+                ));
+                info.push_str(&format!(
+                    "accept_resource_errors:{}\r\n",
+                    total(&traffic.accept_resource_errors)
+                ));
+            }
+        }
+
+        // Only with persistence configured, like the traffic counters above
+        if self.includes("persistence")
+            && let Some(persistence) = persistence
+        {
+            if !info.is_empty() {
+                info.push_str("\r\n");
+            }
+            // Lock-free reads: stats() waits on the AOF, which a rewrite may be holding
+            info.push_str("# Persistence\r\n");
+            info.push_str(&format!(
+                "aof_enabled:{}\r\n",
+                persistence.aof.is_some() as u8
+            ));
+            info.push_str(&format!(
+                "aof_rewrite_in_progress:{}\r\n",
+                persistence.is_aof_rewrite_in_progress() as u8
+            ));
+            info.push_str(&format!(
+                "aof_rewrites_completed:{}\r\n",
+                persistence.aof_rewrites_completed()
+            ));
+            // Redis' wording; the error itself is in the log and PersistenceStats
+            let status = match persistence.aof_last_rewrite_error() {
+                Some(_) => "err",
+                None => "ok",
+            };
+            info.push_str(&format!("aof_last_rewrite_status:{}\r\n", status));
+        }
+
+        if self.includes("keyspace") {
+            if !info.is_empty() {
+                info.push_str("\r\n");
+            }
+            info.push_str("# Keyspace\r\n");
+            // Empty databases are left out
+            for (database, stats) in stats.iter().enumerate() {
+                if stats.total_keys > 0 {
+                    info.push_str(&format!(
+                        "db{}:keys={},expires={}\r\n",
+                        database, stats.total_keys, stats.expires
+                    ));
+                }
+            }
+        }
+
+        CommandResponse::Value(info.into_bytes())
+    }
+}
+
+#[async_trait]
+impl CommandHandler for InfoCommand {
+    async fn execute(&self, storage: &dyn StorageEngine) -> CommandResponse {
+        self.respond(&[storage], None, None).await
+    }
+
+    async fn execute_in(&self, ctx: &CommandContext<'_>) -> CommandResponse {
+        if ctx.databases.is_empty() {
+            return self.execute(ctx.storage).await;
+        }
+
+        let databases: Vec<&dyn StorageEngine> = ctx.databases.iter().map(Arc::as_ref).collect();
+        self.respond(
+            &databases,
+            ctx.client_traffic,
+            ctx.persistence.map(Arc::as_ref),
+        )
+        .await
+    }
+
+    fn name(&self) -> &'static str {
+        "INFO"
+    }
+
+    fn validate(&self) -> Result<(), CommandError> {
+        match self.section {
+            Some(ref section)
+                if !SECTIONS
+                    .iter()
+                    .any(|known| known.eq_ignore_ascii_case(section)) =>
+            {
+                Err(CommandError::InvalidParameter(format!(
+                    "Unknown INFO section '{}', expected one of: {}",
+                    section,
+                    SECTIONS.join(", ")
+                )))
+            }
+            _ => Ok(()),
+        }
+    }
+
+    fn is_read_only(&self) -> bool {
+        true
+    }
+}
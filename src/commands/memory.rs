@@ -0,0 +1,83 @@
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use tracing::{debug, instrument};
+
+use crate::{
+    commands::{CommandError, CommandHandler, CommandResponse},
+    storage::StorageEngine,
+};
+
+// MEMORY subcommands, for finding memory-hungry keys
+// Sizes are in the accounting max_memory uses, not what the allocator actually holds
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum MemoryCommand {
+    // Bytes one key counts for
+    Usage { key: String },
+
+    // Keyspace, overhead and per-shard totals
+    Stats,
+}
+
+#[async_trait]
+impl CommandHandler for MemoryCommand {
+    #[instrument(skip(self, storage))]
+    async fn execute(&self, storage: &dyn StorageEngine) -> CommandResponse {
+        debug!("Executing MEMORY command");
+
+        match self {
+            MemoryCommand::Usage { key } => match storage.memory_usage(key).await {
+                Ok(Some(bytes)) => CommandResponse::Integer(bytes as i64),
+                Ok(None) => CommandResponse::Nil,
+                Err(e) => {
+                    debug!("Failed to get memory usage: {}", e);
+                    CommandResponse::Error(e.to_string())
+                }
+            },
+            MemoryCommand::Stats => match storage.memory_stats().await {
+                Ok(stats) => {
+                    let total = stats.keyspace_bytes + stats.overhead_bytes;
+                    let mut fields = vec![
+                        ("keys.count".to_string(), stats.keys.to_string()),
+                        (
+                            "keyspace.bytes".to_string(),
+                            stats.keyspace_bytes.to_string(),
+                        ),
+                        (
+                            "overhead.bytes".to_string(),
+                            stats.overhead_bytes.to_string(),
+                        ),
+                        ("total.bytes".to_string(), total.to_string()),
+                    ];
+                    fields.extend(stats.shard_bytes.iter().enumerate().map(|(shard, bytes)| {
+                        (format!("shard.{}.bytes", shard), bytes.to_string())
+                    }));
+                    CommandResponse::Map(fields)
+                }
+                Err(e) => {
+                    debug!("Failed to get memory stats: {}", e);
+                    CommandResponse::Error(e.to_string())
+                }
+            },
+        }
+    }
+
+    fn name(&self) -> &'static str {
+        "MEMORY"
+    }
+
+    fn validate(&self) -> Result<(), CommandError> {
+        if let MemoryCommand::Usage { key } = self
+            && key.is_empty()
+        {
+            return Err(CommandError::InvalidParameter(
+                "Key cannot be empty".to_string(),
+            ));
+        }
+
+        Ok(())
+    }
+
+    fn is_read_only(&self) -> bool {
+        true
+    }
+}
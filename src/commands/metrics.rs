@@ -0,0 +1,124 @@
+use std::sync::{
+    Arc,
+    atomic::{AtomicU64, Ordering},
+};
+
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use tracing::debug;
+
+use crate::{
+    commands::{ClientTraffic, CommandContext, CommandError, CommandHandler, CommandResponse},
+    storage::{StorageEngine, StorageStats},
+};
+
+// Server-wide metrics as one JSON object, for scripts and dashboards that don't parse the
+// Prometheus text endpoint. Storage counters are summed over every database
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct MetricsCommand;
+
+impl MetricsCommand {
+    async fn respond(
+        &self,
+        databases: &[&dyn StorageEngine],
+        ctx: Option<&CommandContext<'_>>,
+    ) -> CommandResponse {
+        let mut stats = Vec::with_capacity(databases.len());
+        for storage in databases {
+            match storage.stats().await {
+                Ok(s) => stats.push(s),
+                Err(e) => {
+                    debug!("Failed to collect metrics: {}", e);
+                    return CommandResponse::Error(e.to_string());
+                }
+            }
+        }
+
+        let sum = |field: fn(&StorageStats) -> u64| -> u64 { stats.iter().map(field).sum() };
+        let hits = sum(|s| s.keyspace_hits);
+        let misses = sum(|s| s.keyspace_misses);
+        let hit_rate = if hits + misses > 0 {
+            hits as f64 / (hits + misses) as f64
+        } else {
+            0.0
+        };
+
+        let connected_clients = ctx
+            .and_then(|ctx| ctx.connected_clients)
+            .map(|clients| clients.load(Ordering::Relaxed));
+
+        // Closed connections only, open ones are added as they close
+        let traffic = ctx.and_then(|ctx| ctx.client_traffic);
+        let total = |counter: fn(&ClientTraffic) -> &AtomicU64| {
+            traffic.map(|traffic| counter(traffic).load(Ordering::Relaxed))
+        };
+
+        let persistence = match ctx.and_then(|ctx| ctx.persistence) {
+            Some(persistence) => {
+                let stats = persistence.stats().await;
+                let aof = stats.aof_stats.as_ref();
+                serde_json::json!({
+                    "aof_size_bytes": aof.map(|aof| aof.total_size_bytes),
+                    "aof_operations_logged": aof.map(|aof| aof.operations_logged),
+                    "aof_fsyncs": aof.map(|aof| aof.fsyncs),
+                    "aof_rewrite_in_progress": stats.aof_rewrite_in_progress,
+                    "aof_rewrites_completed": stats.aof_rewrites_completed,
+                    "snapshot_count": stats.snapshot_count,
+                })
+            }
+            None => serde_json::Value::Null,
+        };
+
+        let metrics = serde_json::json!({
+            "total_keys": sum(|s| s.total_keys as u64),
+            "memory_usage_bytes": sum(|s| s.memory_usage as u64),
+            "hit_rate": hit_rate,
+            "total_operations": sum(|s| s.total_operations),
+            "keyspace_hits": hits,
+            "keyspace_misses": misses,
+            "evicted_keys": sum(|s| s.evicted_keys),
+            "expires": sum(|s| s.expires as u64),
+            "expired_keys": sum(|s| s.expired_keys),
+            "databases": stats.len(),
+            "connected_clients": connected_clients,
+            "total_connections_closed": total(|t| &t.connections),
+            "total_commands_processed": total(|t| &t.commands_processed),
+            "total_net_input_bytes": total(|t| &t.bytes_received),
+            "total_net_output_bytes": total(|t| &t.bytes_sent),
+            "reaped_connections": total(|t| &t.reaped_connections),
+            "accept_errors": total(|t| &t.accept_errors),
+            "accept_resource_errors": total(|t| &t.accept_resource_errors),
+            "persistence": persistence,
+        });
+
+        CommandResponse::Value(metrics.to_string().into_bytes())
+    }
+}
+
+#[async_trait]
+impl CommandHandler for MetricsCommand {
+    async fn execute(&self, storage: &dyn StorageEngine) -> CommandResponse {
+        self.respond(&[storage], None).await
+    }
+
+    async fn execute_in(&self, ctx: &CommandContext<'_>) -> CommandResponse {
+        let databases: Vec<&dyn StorageEngine> = if ctx.databases.is_empty() {
+            vec![ctx.storage]
+        } else {
+            ctx.databases.iter().map(Arc::as_ref).collect()
+        };
+        self.respond(&databases, Some(ctx)).await
+    }
+
+    fn name(&self) -> &'static str {
+        "METRICS"
+    }
+
+    fn validate(&self) -> Result<(), CommandError> {
+        Ok(())
+    }
+
+    fn is_read_only(&self) -> bool {
+        true
+    }
+}
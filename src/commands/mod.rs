@@ -1,23 +1,128 @@
-use std::sync::Arc;
+use std::{
+    sync::{
+        Arc,
+        atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering},
+    },
+    time::Duration,
+};
 
 use async_trait::async_trait;
+use futures_util::future::join_all;
 use serde::{Deserialize, Serialize};
+use tokio_util::sync::CancellationToken;
+use tracing::{Metadata, subscriber::Interest};
+use tracing_subscriber::layer::{Context, Filter};
 
 use crate::{
+    acl::{Acl, AclUser},
     commands::{
-        delete::DeleteCommand, exist::ExistCommand, get::GetCommand, ping::PingCommand,
-        scan::ScanCommand, set::SetCommand, stats::StatsCommand,
+        auth::AuthCommand,
+        bgrewriteaof::BgRewriteAofCommand,
+        bitcount::BitCountCommand,
+        blpop::BLPopCommand,
+        compress::CompressCommand,
+        config::ConfigCommand,
+        debug::DebugCommand,
+        delete::DeleteCommand,
+        delprefix::DelPrefixCommand,
+        echo::EchoCommand,
+        encoding::EncodingCommand,
+        eval::EvalCommand,
+        exist::ExistCommand,
+        expire::ExpireCommand,
+        flushdb::FlushDbCommand,
+        get::GetCommand,
+        getbit::GetBitCommand,
+        getrange::GetRangeCommand,
+        getver::GetVerCommand,
+        hello::HelloCommand,
+        incrbyfloat::IncrByFloatCommand,
+        info::InfoCommand,
+        memory::MemoryCommand,
+        metrics::MetricsCommand,
+        object::ObjectCommand,
+        ping::PingCommand,
+        proto::ProtoCommand,
+        push::PushCommand,
+        readonly::ReadOnlyCommand,
+        relocate::RelocateCommand,
+        reset::ResetCommand,
+        sadd::SAddCommand,
+        scan::ScanCommand,
+        scard::SCardCommand,
+        select::SelectCommand,
+        set::SetCommand,
+        setbit::SetBitCommand,
+        setchunk::SetChunkCommand,
+        setrange::SetRangeCommand,
+        setver::SetVerCommand,
+        sismember::SIsMemberCommand,
+        smembers::SMembersCommand,
+        snapshot::SnapshotCommand,
+        srem::SRemCommand,
+        stats::StatsCommand,
+        subscribe::{SubscribeCommand, UnsubscribeCommand},
+        touch::TouchCommand,
+        ttl::TtlCommand,
+        wait::WaitCommand,
+    },
+    config::SharedConfig,
+    pubsub::PubSub,
+    storage::{
+        StorageConfig, StorageEngine,
+        persistence::{aof::Operation, manager::PersistenceManager},
     },
-    storage::StorageEngine,
 };
 
+pub mod auth;
+pub mod bgrewriteaof;
+pub mod bitcount;
+pub mod blpop;
+pub mod compress;
+pub mod config;
+pub mod debug;
 pub mod delete;
+pub mod delprefix;
+pub mod echo;
+pub mod encoding;
+pub mod eval;
 pub mod exist;
+pub mod expire;
+pub mod flushdb;
 pub mod get;
+pub mod getbit;
+pub mod getrange;
+pub mod getver;
+pub mod hello;
+pub mod incrbyfloat;
+pub mod info;
+pub mod memory;
+pub mod metrics;
+pub mod object;
 pub mod ping;
+pub mod proto;
+pub mod push;
+pub mod readonly;
+pub mod relocate;
+pub mod reset;
+pub mod sadd;
 pub mod scan;
+pub mod scard;
+pub mod select;
 pub mod set;
+pub mod setbit;
+pub mod setchunk;
+pub mod setrange;
+pub mod setver;
+pub mod sismember;
+pub mod smembers;
+pub mod snapshot;
+pub mod srem;
 pub mod stats;
+pub mod subscribe;
+pub mod touch;
+pub mod ttl;
+pub mod wait;
 
 #[derive(Debug, Clone)]
 pub struct CommandMetadata {
@@ -31,15 +136,30 @@ pub enum CommandResponse {
     Value(Vec<u8>),
     Ok,
     Bool(bool),
+    Integer(i64),
     Keys(Vec<String>),
+    Members(Vec<Vec<u8>>),
+    Map(Vec<(String, String)>), // Field-value pairs, in order (HELLO)
     Stats {
         total_keys: usize,
         memory_usage: usize,
         hit_rate: f64,
         total_operations: u64,
+        keyspace_hits: u64,
+        keyspace_misses: u64,
+        evicted_keys: u64,
     },
     Pong,
+    Nil, // Nothing to return, e.g. a blocking pop that timed out
+    Versioned {
+        value: Vec<u8>,
+        version: u64,
+    }, // A value and the version it was read at (GETVER)
     Error(String),
+    Message {
+        channel: String,
+        payload: Vec<u8>,
+    }, // Pushed to subscribers, never a reply to a command
 }
 
 #[derive(Debug, thiserror::Error)]
@@ -54,17 +174,215 @@ pub enum CommandError {
     Storage(String),
 }
 
+// Configurable key/value limits enforced during validation
+#[derive(Debug, Clone, PartialEq)]
+pub struct KeyLimits {
+    pub max_key_size: usize,
+    pub max_value_size: usize,
+    pub reserved_prefix: Option<String>,
+}
+
+impl KeyLimits {
+    // Check key length against the configured maximum
+    pub fn check_key(&self, key: &str) -> Result<(), CommandError> {
+        if key.len() > self.max_key_size {
+            return Err(CommandError::InvalidParameter(format!(
+                "Key too long (max {} bytes)",
+                self.max_key_size
+            )));
+        }
+        Ok(())
+    }
+
+    // Reject user writes into the internal namespace
+    pub fn check_writable(&self, key: &str) -> Result<(), CommandError> {
+        if let Some(ref prefix) = self.reserved_prefix
+            && key.starts_with(prefix.as_str())
+        {
+            return Err(CommandError::InvalidParameter(format!(
+                "Key prefix '{}' is reserved",
+                prefix
+            )));
+        }
+        Ok(())
+    }
+
+    // Reject a key prefix that could match keys of the internal namespace
+    pub fn check_prefix_writable(&self, prefix: &str) -> Result<(), CommandError> {
+        if let Some(ref reserved) = self.reserved_prefix
+            && reserved.starts_with(prefix)
+        {
+            return Err(CommandError::InvalidParameter(format!(
+                "Prefix '{}' overlaps reserved prefix '{}'",
+                prefix, reserved
+            )));
+        }
+        self.check_writable(prefix)
+    }
+}
+
+impl Default for KeyLimits {
+    fn default() -> Self {
+        Self::from(&StorageConfig::default())
+    }
+}
+
+impl From<&StorageConfig> for KeyLimits {
+    fn from(config: &StorageConfig) -> Self {
+        Self {
+            max_key_size: config.max_key_size,
+            max_value_size: config.max_value_size,
+            reserved_prefix: config.reserved_prefix.clone(),
+        }
+    }
+}
+
+// Decides which commands run with tracing enabled
+// Deterministic: exactly `rate` of all commands are sampled, evenly spread
+#[derive(Debug)]
+pub struct TraceSampler {
+    rate: f64,
+    seen: AtomicU64,
+}
+
+impl TraceSampler {
+    pub fn new(rate: f64) -> Self {
+        Self {
+            rate: rate.clamp(0.0, 1.0),
+            seen: AtomicU64::new(0),
+        }
+    }
+
+    pub fn rate(&self) -> f64 {
+        self.rate
+    }
+
+    // True when the next command should emit spans
+    pub fn sample(&self) -> bool {
+        if self.rate >= 1.0 {
+            return true;
+        }
+        if self.rate <= 0.0 {
+            return false;
+        }
+
+        // Sampled whenever the running total of `rate` crosses an integer
+        let n = self.seen.fetch_add(1, Ordering::Relaxed) as f64;
+        (n * self.rate).floor() != ((n + 1.0) * self.rate).floor()
+    }
+}
+
+impl Default for TraceSampler {
+    fn default() -> Self {
+        Self::new(1.0)
+    }
+}
+
+tokio::task_local! {
+    // Set while a command the sampler skipped runs
+    static UNSAMPLED: ();
+}
+
+// Per-layer filter that drops the spans of commands the sampler skipped
+// Only spans: a warning or error an unsampled command logs still goes out
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SampledSpans;
+
+impl<S> Filter<S> for SampledSpans {
+    fn enabled(&self, metadata: &Metadata<'_>, _cx: &Context<'_, S>) -> bool {
+        !metadata.is_span() || UNSAMPLED.try_with(|_| ()).is_err()
+    }
+
+    // Whether a span is wanted depends on the command it's in, so it's asked every time
+    fn callsite_enabled(&self, metadata: &'static Metadata<'static>) -> Interest {
+        if metadata.is_span() {
+            Interest::sometimes()
+        } else {
+            Interest::always()
+        }
+    }
+}
+
+// Lifetime traffic of client connections that have closed; the TCP server folds each
+// connection's counters in as it ends, so the totals survive connection churn
+#[derive(Debug, Default)]
+pub struct ClientTraffic {
+    pub connections: AtomicU64,
+    pub commands_processed: AtomicU64,
+    pub bytes_received: AtomicU64,
+    pub bytes_sent: AtomicU64,
+    pub reaped_connections: AtomicU64, // Closed by the idle reaper
+    pub accept_errors: AtomicU64,
+    pub accept_resource_errors: AtomicU64, // Accept failed for lack of file descriptors or memory
+}
+
+// Server-level state available to commands beyond the storage engine
+pub struct CommandContext<'a> {
+    pub storage: &'a dyn StorageEngine, // The selected database
+    pub database: usize,
+    pub database_count: usize,
+    pub databases: &'a [Arc<dyn StorageEngine>], // Every database, empty without a dispatcher
+    pub persistence: Option<&'a Arc<PersistenceManager>>,
+    pub read_only: Option<&'a AtomicBool>, // Server-wide maintenance mode flag
+    pub active_expire: Option<&'a AtomicBool>, // Whether the expiry sweeper runs
+    pub connected_clients: Option<&'a AtomicUsize>, // Open client connections
+    pub client_traffic: Option<&'a ClientTraffic>, // Totals of closed client connections
+    pub config: Option<&'a SharedConfig>,
+    pub acl: Option<&'a Acl>,
+}
+
+impl<'a> CommandContext<'a> {
+    // Database 0 of a server with nothing but this storage, for running a command
+    // outside the dispatcher
+    pub fn for_storage(storage: &'a dyn StorageEngine) -> Self {
+        Self {
+            storage,
+            database: 0,
+            database_count: 1,
+            databases: &[],
+            persistence: None,
+            read_only: None,
+            active_expire: None,
+            connected_clients: None,
+            client_traffic: None,
+            config: None,
+            acl: None,
+        }
+    }
+}
+
 #[async_trait]
 pub trait CommandHandler: Send + Sync {
     // Execute the command against storage
     async fn execute(&self, storage: &dyn StorageEngine) -> CommandResponse;
 
+    // Execute with server-level context; only commands needing more than storage override this
+    async fn execute_in(&self, ctx: &CommandContext<'_>) -> CommandResponse {
+        self.execute(ctx.storage).await
+    }
+
+    // Operation to append to the AOF once a write has succeeded
+    fn aof_operation(&self) -> Option<Operation> {
+        None
+    }
+
+    // Keys a write touches, ordering it against other writes to them; None orders it
+    // against every write (FLUSHDB, DELPREFIX)
+    fn write_keys(&self) -> Option<Vec<&str>> {
+        None
+    }
+
     // Get command name for logging/metrics
     fn name(&self) -> &'static str;
 
     // Validate command parameters
     fn validate(&self) -> Result<(), CommandError>;
 
+    // Validate against configured limits; commands touching keys override this
+    fn validate_with(&self, _limits: &KeyLimits) -> Result<(), CommandError> {
+        self.validate()
+    }
+
     // Get command metrics/metadata
     fn metadata(&self) -> CommandMetadata {
         CommandMetadata {
@@ -79,6 +397,29 @@ pub trait CommandHandler: Send + Sync {
         false
     }
 
+    // Whether READONLY mode lets this command run; everything that isn't read-only is
+    // refused unless it changes no data
+    fn allowed_when_read_only(&self) -> bool {
+        self.is_read_only()
+    }
+
+    // Whether a pipeline runs this command after everything queued before it instead of
+    // alongside neighbouring reads; every write does
+    fn is_ordered(&self) -> bool {
+        !self.is_read_only()
+    }
+
+    // Whether this command manages the server rather than data, kept out of @read and @write
+    fn is_admin(&self) -> bool {
+        false
+    }
+
+    // Key a command waits on while it replies Nil, and for how long (zero = forever)
+    // The dispatcher re-runs it after every push to the key until it gets something else
+    fn blocks_on(&self) -> Option<(&str, Duration)> {
+        None
+    }
+
     // Computatinal complexity estimate (for rate limiting)
     fn complexity(&self) -> u32 {
         1
@@ -89,85 +430,500 @@ pub trait CommandHandler: Send + Sync {
 pub enum Command {
     Get(GetCommand),
     Set(SetCommand),
+    GetVer(GetVerCommand),
+    SetVer(SetVerCommand),
+    SetChunk(SetChunkCommand),
+    GetRange(GetRangeCommand),
+    SetRange(SetRangeCommand),
+    SetBit(SetBitCommand),
+    GetBit(GetBitCommand),
+    IncrByFloat(IncrByFloatCommand),
+    Eval(EvalCommand),
+    BitCount(BitCountCommand),
     Delete(DeleteCommand),
+    DelPrefix(DelPrefixCommand),
+    SAdd(SAddCommand),
+    SRem(SRemCommand),
+    SIsMember(SIsMemberCommand),
+    SMembers(SMembersCommand),
+    SCard(SCardCommand),
+    Push(PushCommand),
+    BLPop(BLPopCommand),
     Scan(ScanCommand),
     Exist(ExistCommand),
+    Object(ObjectCommand),
+    Memory(MemoryCommand),
+    Touch(TouchCommand),
+    Expire(ExpireCommand),
+    Ttl(TtlCommand),
+    Compress(CompressCommand),
+    Proto(ProtoCommand),
+    Encoding(EncodingCommand),
+    ReadOnly(ReadOnlyCommand),
+    Config(ConfigCommand),
+    Snapshot(SnapshotCommand),
+    Relocate(RelocateCommand),
+    Wait(WaitCommand),
+    Select(SelectCommand),
+    Auth(AuthCommand),
+    Subscribe(SubscribeCommand),
+    Unsubscribe(UnsubscribeCommand),
+    Info(InfoCommand),
+    Debug(DebugCommand),
+    Hello(HelloCommand),
+    Echo(EchoCommand),
+    Metrics,
+    Reset,
+    FlushDb,
+    BgRewriteAof,
     Stats,
     Ping,
+    // PING with a message; a bare PING stays the unit variant, so JSON "Ping" still parses
+    PingMessage(PingCommand),
 }
 
 impl Command {
+    // Whether it can wait on other clients or the disk before replying (BLPOP, WAIT)
+    pub fn may_block(&self) -> bool {
+        matches!(self, Command::BLPop(_) | Command::Wait(_))
+    }
+
     // Convert to boxed command handler
     pub fn into_handler(self) -> Box<dyn CommandHandler> {
         match self {
             Command::Get(cmd) => Box::new(cmd),
             Command::Set(cmd) => Box::new(cmd),
+            Command::GetVer(cmd) => Box::new(cmd),
+            Command::SetVer(cmd) => Box::new(cmd),
+            Command::SetChunk(cmd) => Box::new(cmd),
+            Command::GetRange(cmd) => Box::new(cmd),
+            Command::SetRange(cmd) => Box::new(cmd),
+            Command::SetBit(cmd) => Box::new(cmd),
+            Command::GetBit(cmd) => Box::new(cmd),
+            Command::IncrByFloat(cmd) => Box::new(cmd),
+            Command::Eval(cmd) => Box::new(cmd),
+            Command::BitCount(cmd) => Box::new(cmd),
             Command::Delete(cmd) => Box::new(cmd),
+            Command::DelPrefix(cmd) => Box::new(cmd),
+            Command::SAdd(cmd) => Box::new(cmd),
+            Command::SRem(cmd) => Box::new(cmd),
+            Command::SIsMember(cmd) => Box::new(cmd),
+            Command::SMembers(cmd) => Box::new(cmd),
+            Command::SCard(cmd) => Box::new(cmd),
+            Command::Push(cmd) => Box::new(cmd),
+            Command::BLPop(cmd) => Box::new(cmd),
             Command::Scan(cmd) => Box::new(cmd),
             Command::Exist(cmd) => Box::new(cmd),
+            Command::Object(cmd) => Box::new(cmd),
+            Command::Memory(cmd) => Box::new(cmd),
+            Command::Touch(cmd) => Box::new(cmd),
+            Command::Expire(cmd) => Box::new(cmd),
+            Command::Ttl(cmd) => Box::new(cmd),
+            Command::Compress(cmd) => Box::new(cmd),
+            Command::Proto(cmd) => Box::new(cmd),
+            Command::Encoding(cmd) => Box::new(cmd),
+            Command::ReadOnly(cmd) => Box::new(cmd),
+            Command::Config(cmd) => Box::new(cmd),
+            Command::Snapshot(cmd) => Box::new(cmd),
+            Command::Relocate(cmd) => Box::new(cmd),
+            Command::Wait(cmd) => Box::new(cmd),
+            Command::Select(cmd) => Box::new(cmd),
+            Command::Auth(cmd) => Box::new(cmd),
+            Command::Subscribe(cmd) => Box::new(cmd),
+            Command::Unsubscribe(cmd) => Box::new(cmd),
+            Command::Info(cmd) => Box::new(cmd),
+            Command::Debug(cmd) => Box::new(cmd),
+            Command::Hello(cmd) => Box::new(cmd),
+            Command::Metrics => Box::new(MetricsCommand),
+            Command::Reset => Box::new(ResetCommand),
+            Command::FlushDb => Box::new(FlushDbCommand),
+            Command::BgRewriteAof => Box::new(BgRewriteAofCommand),
             Command::Stats => Box::new(StatsCommand),
-            Command::Ping => Box::new(PingCommand),
+            Command::Echo(cmd) => Box::new(cmd),
+            Command::Ping => Box::new(PingCommand::default()),
+            Command::PingMessage(cmd) => Box::new(cmd),
         }
     }
 }
 
 // Enhanced command dispatcher with middleware support
 pub struct CommandDispatcher {
-    storage: Arc<dyn StorageEngine>,
+    databases: Vec<Arc<dyn StorageEngine>>, // Indexed by database number, never empty
+    persistence: Option<Arc<PersistenceManager>>,
+    limits: KeyLimits,
+    read_only: Arc<AtomicBool>,
+    recovering: Arc<AtomicBool>,
+    active_expire: Arc<AtomicBool>,
+    connected_clients: Arc<AtomicUsize>, // Counted by the TCP servers using this dispatcher
+    client_buffer_bytes: Arc<AtomicUsize>, // Held by those connections' buffers
+    client_traffic: Arc<ClientTraffic>,  // Folded in by those servers as connections close
+    config: Option<SharedConfig>,
+    acl: Acl,
+    pubsub: PubSub,
+    sampler: TraceSampler,
     middleware: Vec<Box<dyn CommandMiddleware>>,
 }
 
 impl CommandDispatcher {
     pub fn new(storage: Arc<dyn StorageEngine>) -> Self {
         Self {
-            storage,
+            databases: vec![storage],
+            persistence: None,
+            limits: KeyLimits::default(),
+            read_only: Arc::new(AtomicBool::new(false)),
+            recovering: Arc::new(AtomicBool::new(false)),
+            active_expire: Arc::new(AtomicBool::new(true)),
+            connected_clients: Arc::new(AtomicUsize::new(0)),
+            client_buffer_bytes: Arc::new(AtomicUsize::new(0)),
+            client_traffic: Arc::new(ClientTraffic::default()),
+            config: None,
+            acl: Acl::default(),
+            pubsub: PubSub::new(),
+            sampler: TraceSampler::default(),
             middleware: Vec::new(),
         }
     }
 
+    // Serve several logical databases, `databases[0]` replaces the engine given to new()
+    // An empty list is ignored
+    pub fn with_databases(mut self, databases: Vec<Arc<dyn StorageEngine>>) -> Self {
+        if !databases.is_empty() {
+            self.databases = databases;
+        }
+        self
+    }
+
+    pub fn database_count(&self) -> usize {
+        self.databases.len()
+    }
+
+    // Expose the running configuration to CONFIG GET/SET
+    pub fn with_config(mut self, config: SharedConfig) -> Self {
+        self.config = Some(config);
+        self
+    }
+
+    // Users checked by AUTH and execute_as()
+    pub fn with_acl(mut self, acl: Acl) -> Self {
+        self.acl = acl;
+        self
+    }
+
+    pub fn acl(&self) -> &Acl {
+        &self.acl
+    }
+
+    // Hub SUBSCRIBE listens on, shared with whatever publishes (keyspace notifications)
+    pub fn with_pubsub(mut self, pubsub: PubSub) -> Self {
+        self.pubsub = pubsub;
+        self
+    }
+
+    pub fn pubsub(&self) -> &PubSub {
+        &self.pubsub
+    }
+
+    // Only let a fraction (0.0-1.0) of commands emit tracing spans and events
+    pub fn with_trace_sample_rate(mut self, rate: f64) -> Self {
+        self.sampler = TraceSampler::new(rate);
+        self
+    }
+
+    // Refuse every write while enabled (maintenance windows, backups, ...)
+    pub fn set_read_only(&self, enabled: bool) {
+        self.read_only.store(enabled, Ordering::Release);
+    }
+
+    pub fn is_read_only(&self) -> bool {
+        self.read_only.load(Ordering::Acquire)
+    }
+
+    // Refuse every command but PING while persisted state is being replayed
+    pub fn set_recovering(&self, recovering: bool) {
+        self.recovering.store(recovering, Ordering::Release);
+    }
+
+    pub fn is_recovering(&self) -> bool {
+        self.recovering.load(Ordering::Acquire)
+    }
+
+    // Pause or resume the background expiry sweeper, expired keys are then only removed
+    // when accessed
+    pub fn set_active_expire(&self, enabled: bool) {
+        self.active_expire.store(enabled, Ordering::Release);
+    }
+
+    pub fn is_active_expire(&self) -> bool {
+        self.active_expire.load(Ordering::Acquire)
+    }
+
+    // Open client connections; the TCP server keeps this count up to date
+    pub fn connected_clients(&self) -> Arc<AtomicUsize> {
+        self.connected_clients.clone()
+    }
+
+    // Bytes allocated for the read and reply buffers of every open connection
+    pub fn client_buffer_bytes(&self) -> Arc<AtomicUsize> {
+        self.client_buffer_bytes.clone()
+    }
+
+    // Commands and bytes of every client connection closed so far
+    pub fn client_traffic(&self) -> Arc<ClientTraffic> {
+        self.client_traffic.clone()
+    }
+
+    // Every logical database, indexed by number
+    pub fn databases(&self) -> &[Arc<dyn StorageEngine>] {
+        &self.databases
+    }
+
+    pub fn limits(&self) -> &KeyLimits {
+        &self.limits
+    }
+
+    // Override the default key/value limits
+    pub fn with_limits(mut self, limits: KeyLimits) -> Self {
+        self.limits = limits;
+        self
+    }
+
+    // Log writes to persistence and expose it to commands (WAIT, ...)
+    pub fn with_persistence(mut self, persistence: Arc<PersistenceManager>) -> Self {
+        self.persistence = Some(persistence);
+        self
+    }
+
     // Add middleware (rate limiting, logging, etc.)
     pub fn with_middleware(mut self, middleware: Box<dyn CommandMiddleware>) -> Self {
         self.middleware.push(middleware);
         self
     }
 
-    // Execute command with full middleware chain
+    // Execute command with full middleware chain, against database 0
     pub async fn execute(&self, command: Command) -> CommandResponse {
+        self.execute_on(0, command).await
+    }
+
+    // Execute command against the given database (the connection's SELECT)
+    pub async fn execute_on(&self, database: usize, command: Command) -> CommandResponse {
+        self.execute_handler(database, command.into_handler(), None)
+            .await
+    }
+
+    // Execute on behalf of a connection's user, None until it has authenticated
+    // execute() and execute_on() are for embedded callers and skip these checks
+    pub async fn execute_as(
+        &self,
+        database: usize,
+        user: Option<&AclUser>,
+        command: Command,
+    ) -> CommandResponse {
+        self.execute_until(database, user, command, None).await
+    }
+
+    // Like execute_as, but a blocking command (BLPOP) also stops waiting, replying Nil,
+    // once `cancel` fires, so a draining server isn't held up by idle poppers
+    pub async fn execute_until(
+        &self,
+        database: usize,
+        user: Option<&AclUser>,
+        command: Command,
+        cancel: Option<&CancellationToken>,
+    ) -> CommandResponse {
         let handler = command.into_handler();
 
+        match user {
+            // None of these grant access, so they work before authenticating
+            None if !matches!(handler.name(), "AUTH" | "RESET" | "HELLO") => {
+                return CommandResponse::Error("NOAUTH Authentication required".to_string());
+            }
+            Some(user) if !user.permits(handler.as_ref()) => {
+                return CommandResponse::Error(format!(
+                    "NOPERM User {} has no permissions to run the '{}' command",
+                    user.name(),
+                    handler.name()
+                ));
+            }
+            _ => {}
+        }
+
+        self.execute_handler(database, handler, cancel).await
+    }
+
+    // Run a command, re-running a blocking one whenever its key is pushed to
+    // The wait happens between attempts, outside the AOF write guard
+    async fn execute_handler(
+        &self,
+        database: usize,
+        handler: Box<dyn CommandHandler>,
+        cancel: Option<&CancellationToken>,
+    ) -> CommandResponse {
+        let (Some((key, timeout)), Some(storage)) =
+            (handler.blocks_on(), self.databases.get(database))
+        else {
+            return self.execute_once(database, handler.as_ref()).await;
+        };
+
+        let waiter = storage.list_waiter(key);
+        let deadline = (!timeout.is_zero()).then(|| tokio::time::Instant::now() + timeout);
+
+        loop {
+            // Registered before the attempt, so a push right after it still wakes us
+            let notified = waiter.notified();
+            tokio::pin!(notified);
+            notified.as_mut().enable();
+
+            let response = self.execute_once(database, handler.as_ref()).await;
+            if response != CommandResponse::Nil {
+                return response;
+            }
+
+            let expired = async {
+                match deadline {
+                    Some(deadline) => tokio::time::sleep_until(deadline).await,
+                    None => std::future::pending().await,
+                }
+            };
+            let cancelled = async {
+                match cancel {
+                    Some(cancel) => cancel.cancelled().await,
+                    None => std::future::pending().await,
+                }
+            };
+
+            tokio::select! {
+                _ = &mut notified => {}
+                _ = expired => return CommandResponse::Nil,
+                _ = cancelled => return CommandResponse::Nil,
+            }
+        }
+    }
+
+    async fn execute_once(&self, database: usize, handler: &dyn CommandHandler) -> CommandResponse {
+        // Partially recovered data must never be served
+        if self.is_recovering() && handler.name() != "PING" {
+            return CommandResponse::Error(
+                "LOADING Server is recovering its dataset, try again later".to_string(),
+            );
+        }
+
+        let Some(storage) = self.databases.get(database) else {
+            return CommandResponse::Error("DB index is out of range".to_string());
+        };
+
         // Validate command
-        if let Err(e) = handler.validate() {
+        if let Err(e) = handler.validate_with(&self.limits) {
             return CommandResponse::Error(e.to_string());
         }
 
+        if !handler.allowed_when_read_only() && self.is_read_only() {
+            return CommandResponse::Error(
+                "READONLY You can't write against a read only server".to_string(),
+            );
+        }
+
         // Run pre-execution middleware
         for middleware in &self.middleware {
-            if let Err(response) = middleware.before_execute(handler.as_ref()).await {
+            if let Err(response) = middleware.before_execute(handler).await {
                 return response;
             }
         }
 
+        // A write runs and reaches the AOF before the next write to its keys starts, so
+        // replay applies them in the order clients saw. Admin commands touch no data and
+        // may take the write gate themselves (RELOCATE), so they never queue here
+        let _write_order = match self.persistence {
+            Some(ref persistence) if !handler.is_read_only() && !handler.is_admin() => {
+                // Refuse before touching storage if the write can't be persisted
+                if let Err(e) = persistence.health_check().await {
+                    return CommandResponse::Error(format!("Persistence error: {}", e));
+                }
+                Some(
+                    persistence
+                        .order_writes(handler.write_keys().as_deref())
+                        .await,
+                )
+            }
+            _ => None,
+        };
+
         // Execute command
-        let response = handler.execute(self.storage.as_ref()).await;
+        let ctx = CommandContext {
+            storage: storage.as_ref(),
+            database,
+            database_count: self.databases.len(),
+            databases: &self.databases,
+            persistence: self.persistence.as_ref(),
+            read_only: Some(&self.read_only),
+            active_expire: Some(&self.active_expire),
+            connected_clients: Some(&self.connected_clients),
+            client_traffic: Some(&self.client_traffic),
+            config: self.config.as_ref(),
+            acl: Some(&self.acl),
+        };
+        // Unsampled commands run without spans where logging filters with SampledSpans
+        let response = if self.sampler.sample() {
+            handler.execute_in(&ctx).await
+        } else {
+            UNSAMPLED.scope((), handler.execute_in(&ctx)).await
+        };
+
+        // Only writes that took effect are logged; one that failed must not fail replay
+        let response = match self.persistence {
+            Some(ref persistence) if !matches!(response, CommandResponse::Error(_)) => {
+                match handler.aof_operation() {
+                    Some(operation) => match persistence
+                        .log_operation(operation.in_database(database))
+                        .await
+                    {
+                        Ok(()) => response,
+                        Err(e) => CommandResponse::Error(format!("Persistence error: {}", e)),
+                    },
+                    None => response,
+                }
+            }
+            _ => response,
+        };
 
         // Run post-execution middleware
         for middleware in &self.middleware {
-            middleware.after_execute(handler.as_ref(), &response).await;
+            middleware.after_execute(handler, &response).await;
         }
 
         response
     }
 
+    // Execute a pipeline, preserving response order
+    // Contiguous runs of unordered reads run concurrently; any ordered command flushes
+    // the pending reads first so it stays ordered relative to everything else
     pub async fn execute_batch(&self, commands: Vec<Command>) -> Vec<CommandResponse> {
         let mut responses = Vec::with_capacity(commands.len());
+        let mut pending_reads = Vec::new();
 
         for command in commands {
-            let response = self.execute(command).await;
-            responses.push(response);
+            let handler = command.into_handler();
+
+            if !handler.is_ordered() {
+                pending_reads.push(handler);
+                continue;
+            }
+
+            responses.extend(self.execute_reads(std::mem::take(&mut pending_reads)).await);
+            responses.push(self.execute_handler(0, handler, None).await);
         }
 
+        responses.extend(self.execute_reads(pending_reads).await);
         responses
     }
+
+    async fn execute_reads(&self, handlers: Vec<Box<dyn CommandHandler>>) -> Vec<CommandResponse> {
+        join_all(
+            handlers
+                .into_iter()
+                .map(|handler| self.execute_handler(0, handler, None)),
+        )
+        .await
+    }
 }
 
 // Middleware trait for cross-cutting concerns
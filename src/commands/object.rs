@@ -0,0 +1,81 @@
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use tracing::{debug, instrument};
+
+use crate::{
+    commands::{CommandError, CommandHandler, CommandResponse},
+    storage::StorageEngine,
+};
+
+// OBJECT introspection subcommands
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum ObjectSubcommand {
+    IdleTime,
+    Freq,
+    Encoding,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ObjectCommand {
+    pub subcommand: ObjectSubcommand,
+    pub key: String,
+}
+
+impl ObjectCommand {
+    pub fn new(subcommand: ObjectSubcommand, key: String) -> Self {
+        Self { subcommand, key }
+    }
+}
+
+#[async_trait]
+impl CommandHandler for ObjectCommand {
+    #[instrument(skip(self, storage), fields(key = %self.key, subcommand = ?self.subcommand))]
+    async fn execute(&self, storage: &dyn StorageEngine) -> CommandResponse {
+        debug!("Executing OBJECT command");
+
+        match self.subcommand {
+            ObjectSubcommand::IdleTime => match storage.idle_time(&self.key).await {
+                Ok(Some(seconds)) => CommandResponse::Integer(seconds as i64),
+                Ok(None) => CommandResponse::Error("Key not found".to_string()),
+                Err(e) => {
+                    debug!("Failed to get idle time: {}", e);
+                    CommandResponse::Error(e.to_string())
+                }
+            },
+            ObjectSubcommand::Freq => match storage.access_frequency(&self.key).await {
+                Ok(Some(frequency)) => CommandResponse::Integer(frequency as i64),
+                Ok(None) => CommandResponse::Error("Key not found".to_string()),
+                Err(e) => {
+                    debug!("Failed to get access frequency: {}", e);
+                    CommandResponse::Error(e.to_string())
+                }
+            },
+            ObjectSubcommand::Encoding => match storage.encoding(&self.key).await {
+                Ok(Some(encoding)) => CommandResponse::Value(encoding.as_bytes().to_vec()),
+                Ok(None) => CommandResponse::Error("Key not found".to_string()),
+                Err(e) => {
+                    debug!("Failed to get encoding: {}", e);
+                    CommandResponse::Error(e.to_string())
+                }
+            },
+        }
+    }
+
+    fn name(&self) -> &'static str {
+        "OBJECT"
+    }
+
+    fn validate(&self) -> Result<(), CommandError> {
+        if self.key.is_empty() {
+            return Err(CommandError::InvalidParameter(
+                "Key cannot be empty".to_string(),
+            ));
+        }
+
+        Ok(())
+    }
+
+    fn is_read_only(&self) -> bool {
+        true
+    }
+}
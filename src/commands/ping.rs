@@ -2,19 +2,30 @@ use async_trait::async_trait;
 use serde::{Deserialize, Serialize};
 
 use crate::{
-    commands::{CommandError, CommandHandler, CommandResponse},
+    commands::{CommandError, CommandHandler, CommandResponse, KeyLimits},
     storage::StorageEngine,
 };
 
-#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
-pub struct PingCommand;
+// Like Redis, PING only proves the connection is served and never touches storage:
+// a bare PING always pongs, PING message replies with the message
+// Storage health is reported by INFO and BlazeKVDB::health_check instead
+#[derive(Debug, Clone, PartialEq, Default, Serialize, Deserialize)]
+pub struct PingCommand {
+    pub message: Option<Vec<u8>>,
+}
+
+impl PingCommand {
+    pub fn new(message: Option<Vec<u8>>) -> Self {
+        Self { message }
+    }
+}
 
 #[async_trait]
 impl CommandHandler for PingCommand {
-    async fn execute(&self, storage: &dyn StorageEngine) -> CommandResponse {
-        match storage.health_check().await {
-            Ok(_) => CommandResponse::Pong,
-            Err(e) => CommandResponse::Error(e.to_string()),
+    async fn execute(&self, _storage: &dyn StorageEngine) -> CommandResponse {
+        match self.message {
+            Some(ref message) => CommandResponse::Value(message.clone()),
+            None => CommandResponse::Pong,
         }
     }
 
@@ -23,8 +34,21 @@ impl CommandHandler for PingCommand {
     }
 
     fn validate(&self) -> Result<(), CommandError> {
+        self.validate_with(&KeyLimits::default())
+    }
+
+    fn validate_with(&self, limits: &KeyLimits) -> Result<(), CommandError> {
+        if let Some(ref message) = self.message
+            && message.len() > limits.max_value_size
+        {
+            return Err(CommandError::InvalidParameter(format!(
+                "Message too large (max {} bytes)",
+                limits.max_value_size
+            )));
+        }
         Ok(())
     }
+
     fn is_read_only(&self) -> bool {
         true
     }
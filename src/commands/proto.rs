@@ -0,0 +1,46 @@
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    commands::{CommandError, CommandHandler, CommandResponse},
+    storage::StorageEngine,
+};
+
+// Wire format used for a connection's requests and replies
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ProtocolMode {
+    #[default]
+    Text,
+    Json, // One JSON-encoded Command per line, JSON replies
+}
+
+// Connection-level protocol switch, applied by the connection after a successful reply
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ProtoCommand {
+    pub mode: ProtocolMode,
+}
+
+impl ProtoCommand {
+    pub fn new(mode: ProtocolMode) -> Self {
+        Self { mode }
+    }
+}
+
+#[async_trait]
+impl CommandHandler for ProtoCommand {
+    async fn execute(&self, _storage: &dyn StorageEngine) -> CommandResponse {
+        CommandResponse::Ok
+    }
+
+    fn name(&self) -> &'static str {
+        "PROTO"
+    }
+
+    fn validate(&self) -> Result<(), CommandError> {
+        Ok(())
+    }
+
+    fn is_read_only(&self) -> bool {
+        true
+    }
+}
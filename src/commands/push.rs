@@ -0,0 +1,97 @@
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use tracing::{debug, instrument};
+
+use crate::{
+    commands::{CommandError, CommandHandler, CommandResponse, KeyLimits},
+    storage::{StorageEngine, persistence::aof::Operation},
+};
+
+// LPUSH (front) and RPUSH (back): items are pushed one by one, so LPUSH reverses them
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PushCommand {
+    pub key: String,
+    pub items: Vec<Vec<u8>>,
+    pub front: bool,
+}
+
+impl PushCommand {
+    pub fn new(key: String, items: Vec<Vec<u8>>, front: bool) -> Self {
+        Self { key, items, front }
+    }
+}
+
+#[async_trait]
+impl CommandHandler for PushCommand {
+    #[instrument(skip(self, storage), fields(key = %self.key, items = self.items.len(), front = self.front))]
+    async fn execute(&self, storage: &dyn StorageEngine) -> CommandResponse {
+        debug!("Executing {} command", self.name());
+
+        match storage.push_items(&self.key, &self.items, self.front).await {
+            Ok(len) => CommandResponse::Integer(len as i64),
+            Err(e) => {
+                debug!("Failed to push items: {}", e);
+                CommandResponse::Error(e.to_string())
+            }
+        }
+    }
+
+    fn name(&self) -> &'static str {
+        if self.front { "LPUSH" } else { "RPUSH" }
+    }
+
+    fn validate(&self) -> Result<(), CommandError> {
+        self.validate_with(&KeyLimits::default())
+    }
+
+    fn validate_with(&self, limits: &KeyLimits) -> Result<(), CommandError> {
+        if self.key.is_empty() {
+            return Err(CommandError::InvalidParameter(
+                "Key cannot be empty".to_string(),
+            ));
+        }
+
+        if self.items.is_empty() {
+            return Err(CommandError::MissingParameter(format!(
+                "{} requires at least one item",
+                self.name()
+            )));
+        }
+
+        limits.check_key(&self.key)?;
+        limits.check_writable(&self.key)?;
+
+        if self
+            .items
+            .iter()
+            .any(|item| item.len() > limits.max_value_size)
+        {
+            return Err(CommandError::InvalidParameter(format!(
+                "Item too large (max {} bytes)",
+                limits.max_value_size
+            )));
+        }
+
+        Ok(())
+    }
+
+    fn is_read_only(&self) -> bool {
+        false
+    }
+
+    fn write_keys(&self) -> Option<Vec<&str>> {
+        Some(vec![&self.key])
+    }
+
+    fn aof_operation(&self) -> Option<Operation> {
+        Some(Operation::ListPush {
+            key: self.key.clone(),
+            items: self.items.clone(),
+            front: self.front,
+        })
+    }
+
+    fn complexity(&self) -> u32 {
+        self.items.len().max(1) as u32
+    }
+}
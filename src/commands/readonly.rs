@@ -0,0 +1,62 @@
+use std::sync::atomic::Ordering;
+
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use tracing::info;
+
+use crate::{
+    commands::{CommandContext, CommandError, CommandHandler, CommandResponse},
+    storage::StorageEngine,
+};
+
+// Toggle server-wide maintenance mode, where every write is refused
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ReadOnlyCommand {
+    pub enabled: bool,
+}
+
+impl ReadOnlyCommand {
+    pub fn new(enabled: bool) -> Self {
+        Self { enabled }
+    }
+}
+
+#[async_trait]
+impl CommandHandler for ReadOnlyCommand {
+    async fn execute(&self, _storage: &dyn StorageEngine) -> CommandResponse {
+        CommandResponse::Error("READONLY requires a dispatcher".to_string())
+    }
+
+    async fn execute_in(&self, ctx: &CommandContext<'_>) -> CommandResponse {
+        let Some(read_only) = ctx.read_only else {
+            return CommandResponse::Error("READONLY requires a dispatcher".to_string());
+        };
+
+        let was = read_only.swap(self.enabled, Ordering::AcqRel);
+        if was != self.enabled {
+            info!(
+                "Read-only mode {}",
+                if self.enabled { "enabled" } else { "disabled" }
+            );
+        }
+
+        CommandResponse::Ok
+    }
+
+    fn name(&self) -> &'static str {
+        "READONLY"
+    }
+
+    fn validate(&self) -> Result<(), CommandError> {
+        Ok(())
+    }
+
+    // Must stay runnable while writes are refused, or the mode could never be left
+    fn is_read_only(&self) -> bool {
+        true
+    }
+
+    fn is_admin(&self) -> bool {
+        true
+    }
+}
@@ -0,0 +1,111 @@
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use tracing::{instrument, warn};
+
+use crate::{
+    commands::{CommandContext, CommandError, CommandHandler, CommandResponse},
+    storage::StorageEngine,
+};
+
+// Move the AOF and/or snapshot directory to new paths while running
+// The config file is not rewritten, the reply says so
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RelocateCommand {
+    pub aof_path: Option<String>,
+    pub snapshot_dir: Option<String>,
+    pub copy: bool, // Copy the current files over, instead of seeding the AOF by a rewrite
+}
+
+#[async_trait]
+impl CommandHandler for RelocateCommand {
+    async fn execute(&self, _storage: &dyn StorageEngine) -> CommandResponse {
+        CommandResponse::Error("RELOCATE requires persistence".to_string())
+    }
+
+    #[instrument(skip(self, ctx))]
+    async fn execute_in(&self, ctx: &CommandContext<'_>) -> CommandResponse {
+        let Some(persistence) = ctx.persistence else {
+            return self.execute(ctx.storage).await;
+        };
+
+        let relocation = persistence
+            .relocate(
+                self.aof_path.as_ref().map(Into::into),
+                self.snapshot_dir.as_ref().map(Into::into),
+                self.copy,
+            )
+            .await;
+
+        match relocation {
+            Ok(relocation) => {
+                // CONFIG GET and INFO show the new paths; the config file is not rewritten,
+                // a restart on it fails until it names them too
+                if let Some(config) = ctx.config {
+                    let mut config = config.write();
+                    if let Some(ref path) = relocation.aof_path {
+                        config.persistence.aof_path = path.clone();
+                    }
+                    if let Some(ref dir) = relocation.snapshot_dir {
+                        config.persistence.snapshot_dir = dir.clone();
+                    }
+                }
+
+                let mut report = Vec::new();
+                if let Some(ref path) = relocation.aof_path {
+                    report.push(format!("aof={}", path.display()));
+                }
+                if let Some(ref dir) = relocation.snapshot_dir {
+                    report.push(format!("snapshots={}", dir.display()));
+                }
+
+                warn!(
+                    "Persistence relocated: {}; the config file still names the old paths, \
+                     update it before restarting",
+                    report.join(" ")
+                );
+                report.push(
+                    "(config file not updated, point it at the new paths before restarting)"
+                        .to_string(),
+                );
+                CommandResponse::Value(report.join(" ").into_bytes())
+            }
+            Err(e) => {
+                warn!("Persistence relocation failed: {}", e);
+                CommandResponse::Error(format!("Relocation failed: {}", e))
+            }
+        }
+    }
+
+    fn name(&self) -> &'static str {
+        "RELOCATE"
+    }
+
+    fn validate(&self) -> Result<(), CommandError> {
+        if self.aof_path.is_none() && self.snapshot_dir.is_none() {
+            return Err(CommandError::MissingParameter(
+                "RELOCATE requires AOF or SNAPSHOTS".to_string(),
+            ));
+        }
+        Ok(())
+    }
+
+    // Not read-only, so clients never retry it after a lost connection and pipelines
+    // never run it alongside reads
+    fn is_read_only(&self) -> bool {
+        false
+    }
+
+    // Moves files, not the dataset, so it stays available in read-only mode
+    fn allowed_when_read_only(&self) -> bool {
+        true
+    }
+
+    fn is_admin(&self) -> bool {
+        true
+    }
+
+    fn complexity(&self) -> u32 {
+        // Copies or rewrites the whole persisted dataset
+        100
+    }
+}
@@ -0,0 +1,32 @@
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    commands::{CommandError, CommandHandler, CommandResponse},
+    storage::StorageEngine,
+};
+
+// Put the connection back into its initial state: database 0, default encoding and
+// protocol, no subscriptions, and logged out (re-AUTH needed when auth is required)
+// Lets pooled clients recycle a connection; the connection applies it once acknowledged
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ResetCommand;
+
+#[async_trait]
+impl CommandHandler for ResetCommand {
+    async fn execute(&self, _storage: &dyn StorageEngine) -> CommandResponse {
+        CommandResponse::Ok
+    }
+
+    fn name(&self) -> &'static str {
+        "RESET"
+    }
+
+    fn validate(&self) -> Result<(), CommandError> {
+        Ok(())
+    }
+
+    fn is_read_only(&self) -> bool {
+        true
+    }
+}
@@ -0,0 +1,89 @@
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use tracing::{debug, instrument};
+
+use crate::{
+    commands::{CommandError, CommandHandler, CommandResponse, KeyLimits},
+    storage::{StorageEngine, persistence::aof::Operation},
+};
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SAddCommand {
+    pub key: String,
+    pub members: Vec<Vec<u8>>,
+}
+
+impl SAddCommand {
+    pub fn new(key: String, members: Vec<Vec<u8>>) -> Self {
+        Self { key, members }
+    }
+}
+
+#[async_trait]
+impl CommandHandler for SAddCommand {
+    #[instrument(skip(self, storage), fields(key = %self.key, members = self.members.len()))]
+    async fn execute(&self, storage: &dyn StorageEngine) -> CommandResponse {
+        debug!("Executing SADD command");
+
+        match storage.add_members(&self.key, &self.members).await {
+            Ok(added) => CommandResponse::Integer(added as i64),
+            Err(e) => {
+                debug!("Failed to add members: {}", e);
+                CommandResponse::Error(e.to_string())
+            }
+        }
+    }
+
+    fn name(&self) -> &'static str {
+        "SADD"
+    }
+
+    fn validate(&self) -> Result<(), CommandError> {
+        self.validate_with(&KeyLimits::default())
+    }
+
+    fn validate_with(&self, limits: &KeyLimits) -> Result<(), CommandError> {
+        if self.key.is_empty() {
+            return Err(CommandError::InvalidParameter(
+                "Key cannot be empty".to_string(),
+            ));
+        }
+
+        if self.members.is_empty() {
+            return Err(CommandError::MissingParameter(
+                "SADD requires at least one member".to_string(),
+            ));
+        }
+
+        limits.check_key(&self.key)?;
+        limits.check_writable(&self.key)?;
+
+        if self.members.iter().any(|m| m.len() > limits.max_value_size) {
+            return Err(CommandError::InvalidParameter(format!(
+                "Member too large (max {} bytes)",
+                limits.max_value_size
+            )));
+        }
+
+        Ok(())
+    }
+
+    fn is_read_only(&self) -> bool {
+        false
+    }
+
+    fn write_keys(&self) -> Option<Vec<&str>> {
+        Some(vec![&self.key])
+    }
+
+    fn aof_operation(&self) -> Option<Operation> {
+        Some(Operation::SetAdd {
+            key: self.key.clone(),
+            members: self.members.clone(),
+        })
+    }
+
+    fn complexity(&self) -> u32 {
+        self.members.len().max(1) as u32
+    }
+}
@@ -1,28 +1,76 @@
+use std::collections::BinaryHeap;
+
 use async_trait::async_trait;
+use futures_util::{StreamExt, TryStreamExt};
 use serde::{Deserialize, Serialize};
 use tracing::{debug, instrument};
 
 use crate::{
     commands::{CommandError, CommandHandler, CommandResponse},
-    storage::StorageEngine,
+    storage::{StorageEngine, StorageResult},
 };
 
+// Keys returned by a SORTED scan without an explicit COUNT
+pub const SORTED_SCAN_DEFAULT_COUNT: usize = 1000;
+
+// Keys with a prefix, in shard order unless `sorted`. A sorted scan still walks every
+// matching key across all shards, but holds at most `count` of them at a time
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct ScanCommand {
     pub prefix: String,
+    pub sorted: bool,
+    pub count: Option<usize>,
 }
 
 impl ScanCommand {
     pub fn new(prefix: String) -> Self {
-        Self { prefix }
+        Self {
+            prefix,
+            sorted: false,
+            count: None,
+        }
+    }
+
+    // Return the lexicographically smallest keys, in order
+    pub fn sorted(mut self) -> Self {
+        self.sorted = true;
+        self
+    }
+
+    pub fn with_count(mut self, count: usize) -> Self {
+        self.count = Some(count);
+        self
+    }
+
+    async fn keys(&self, storage: &dyn StorageEngine) -> StorageResult<Vec<String>> {
+        let mut stream = storage.scan(&self.prefix).await?;
+
+        if !self.sorted {
+            let count = self.count.unwrap_or(usize::MAX);
+            return stream.take(count).try_collect().await;
+        }
+
+        // Max-heap of the smallest keys seen so far
+        let count = self.count.unwrap_or(SORTED_SCAN_DEFAULT_COUNT);
+        let mut smallest = BinaryHeap::with_capacity(count.min(SORTED_SCAN_DEFAULT_COUNT) + 1);
+        while let Some(key) = stream.try_next().await? {
+            if smallest.len() < count {
+                smallest.push(key);
+            } else if smallest.peek().is_some_and(|largest| key < *largest) {
+                smallest.pop();
+                smallest.push(key);
+            }
+        }
+
+        Ok(smallest.into_sorted_vec())
     }
 }
 
 #[async_trait]
 impl CommandHandler for ScanCommand {
-    #[instrument(skip(self, storage), fields(prefix = %self.prefix))]
+    #[instrument(skip(self, storage), fields(prefix = %self.prefix, sorted = self.sorted))]
     async fn execute(&self, storage: &dyn StorageEngine) -> CommandResponse {
-        match storage.scan(&self.prefix).await {
+        match self.keys(storage).await {
             Ok(keys) => {
                 debug!("Scan completed, found {} keys", keys.len());
                 CommandResponse::Keys(keys)
@@ -39,6 +87,11 @@ impl CommandHandler for ScanCommand {
     }
 
     fn validate(&self) -> Result<(), CommandError> {
+        if self.count == Some(0) {
+            return Err(CommandError::InvalidParameter(
+                "COUNT must be positive".to_string(),
+            ));
+        }
         Ok(())
     }
 
@@ -0,0 +1,62 @@
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use tracing::{debug, instrument};
+
+use crate::{
+    commands::{CommandError, CommandHandler, CommandResponse, KeyLimits},
+    storage::{StorageEngine, value::set_len},
+};
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SCardCommand {
+    pub key: String,
+}
+
+impl SCardCommand {
+    pub fn new(key: String) -> Self {
+        Self { key }
+    }
+}
+
+#[async_trait]
+impl CommandHandler for SCardCommand {
+    #[instrument(skip(self, storage), fields(key = %self.key))]
+    async fn execute(&self, storage: &dyn StorageEngine) -> CommandResponse {
+        debug!("Executing SCARD command");
+
+        let len = storage
+            .get(&self.key)
+            .await
+            .and_then(|value| value.map(|v| set_len(&v)).transpose());
+
+        match len {
+            Ok(len) => CommandResponse::Integer(len.unwrap_or(0) as i64),
+            Err(e) => {
+                debug!("Failed to read set: {}", e);
+                CommandResponse::Error(e.to_string())
+            }
+        }
+    }
+
+    fn name(&self) -> &'static str {
+        "SCARD"
+    }
+
+    fn validate(&self) -> Result<(), CommandError> {
+        self.validate_with(&KeyLimits::default())
+    }
+
+    fn validate_with(&self, limits: &KeyLimits) -> Result<(), CommandError> {
+        if self.key.is_empty() {
+            return Err(CommandError::InvalidParameter(
+                "Key cannot be empty".to_string(),
+            ));
+        }
+
+        limits.check_key(&self.key)
+    }
+
+    fn is_read_only(&self) -> bool {
+        true
+    }
+}
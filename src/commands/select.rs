@@ -0,0 +1,51 @@
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    commands::{CommandContext, CommandError, CommandHandler, CommandResponse},
+    storage::StorageEngine,
+};
+
+// Switch the connection to another logical database
+// The dispatcher only checks the index, the connection keeps the selection once acknowledged
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SelectCommand {
+    pub index: usize,
+}
+
+impl SelectCommand {
+    pub fn new(index: usize) -> Self {
+        Self { index }
+    }
+}
+
+#[async_trait]
+impl CommandHandler for SelectCommand {
+    async fn execute(&self, _storage: &dyn StorageEngine) -> CommandResponse {
+        if self.index == 0 {
+            CommandResponse::Ok
+        } else {
+            CommandResponse::Error("DB index is out of range".to_string())
+        }
+    }
+
+    async fn execute_in(&self, ctx: &CommandContext<'_>) -> CommandResponse {
+        if self.index < ctx.database_count {
+            CommandResponse::Ok
+        } else {
+            CommandResponse::Error("DB index is out of range".to_string())
+        }
+    }
+
+    fn name(&self) -> &'static str {
+        "SELECT"
+    }
+
+    fn validate(&self) -> Result<(), CommandError> {
+        Ok(())
+    }
+
+    fn is_read_only(&self) -> bool {
+        true
+    }
+}
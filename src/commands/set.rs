@@ -1,17 +1,21 @@
+use std::time::Duration;
+
 use async_trait::async_trait;
 use serde::{Deserialize, Serialize};
 use tracing::{debug, instrument};
 
 use crate::{
-    commands::{CommandError, CommandHandler, CommandResponse},
-    storage::StorageEngine,
+    commands::{CommandContext, CommandError, CommandHandler, CommandResponse, KeyLimits},
+    storage::{StorageEngine, now_millis, persistence::aof::Operation, value::check_untyped},
 };
 
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct SetCommand {
     pub key: String,
     pub value: Vec<u8>,
-    pub ttl: Option<u64>, // Future: TTl support
+    pub ttl: Option<u64>, // TTL in seconds
+    #[serde(skip)]
+    pub internal: bool, // Allows writes into the reserved namespace; never set by the protocol
 }
 
 impl SetCommand {
@@ -20,6 +24,15 @@ impl SetCommand {
             key,
             value,
             ttl: None,
+            internal: false,
+        }
+    }
+
+    // SET issued by the server itself for internal metadata
+    pub fn internal(key: String, value: Vec<u8>) -> Self {
+        Self {
+            internal: true,
+            ..Self::new(key, value)
         }
     }
 
@@ -35,12 +48,16 @@ impl CommandHandler for SetCommand {
     async fn execute(&self, storage: &dyn StorageEngine) -> CommandResponse {
         debug!("Executing SET command");
 
-        // TODO: Handle TTL in future versions
-        if self.ttl.is_some() {
-            return CommandResponse::Error("TTL not yet supported".to_string());
-        }
+        let result = match self.ttl {
+            Some(ttl) => {
+                storage
+                    .set_with_ttl(&self.key, self.value.clone(), Duration::from_secs(ttl))
+                    .await
+            }
+            None => storage.set(&self.key, self.value.clone()).await,
+        };
 
-        match storage.set(&self.key, self.value.clone()).await {
+        match result {
             Ok(_) => {
                 debug!("Key set successfully");
                 CommandResponse::Ok
@@ -52,29 +69,85 @@ impl CommandHandler for SetCommand {
         }
     }
 
+    // Any expiry the key ended up with, its own TTL or the configured default_ttl, is logged
+    // as an absolute deadline in the same entry as the value, so a replay neither drops it
+    // nor restarts the countdown
+    #[instrument(skip(self, ctx), fields(key = %self.key, size = self.value.len()))]
+    async fn execute_in(&self, ctx: &CommandContext<'_>) -> CommandResponse {
+        let response = self.execute(ctx.storage).await;
+        let Some(persistence) = ctx.persistence else {
+            return response;
+        };
+        if !matches!(response, CommandResponse::Ok) {
+            return response;
+        }
+
+        let at_millis = match ctx.storage.expires_at(&self.key).await {
+            Ok(Some(at_millis)) => at_millis,
+            // Already evicted: fall back to the deadline SET asked for
+            _ => self
+                .ttl
+                .map(|ttl| now_millis().saturating_add(ttl.saturating_mul(1000))),
+        };
+        let key = self.key.clone();
+        let value = self.value.clone();
+        let operation = match at_millis {
+            Some(at_millis) => Operation::PutExpiring {
+                key,
+                value,
+                at_millis,
+            },
+            None => Operation::Put { key, value },
+        };
+
+        match persistence
+            .log_operation(operation.in_database(ctx.database))
+            .await
+        {
+            Ok(()) => response,
+            Err(e) => CommandResponse::Error(format!("Persistence error: {}", e)),
+        }
+    }
+
     fn name(&self) -> &'static str {
         "SET"
     }
 
     fn validate(&self) -> Result<(), CommandError> {
+        self.validate_with(&KeyLimits::default())
+    }
+
+    fn validate_with(&self, limits: &KeyLimits) -> Result<(), CommandError> {
         if self.key.is_empty() {
             return Err(CommandError::InvalidParameter(
                 "Key cannot be empty".to_string(),
             ));
         }
 
-        if self.key.len() > 512 {
-            return Err(CommandError::InvalidParameter(
-                "Key too long (max 512 bytes)".to_string(),
-            ));
+        limits.check_key(&self.key)?;
+
+        if !self.internal {
+            limits.check_writable(&self.key)?;
         }
 
-        if self.value.len() > 10 * 1024 * 1024 {
+        if self.ttl == Some(0) {
             return Err(CommandError::InvalidParameter(
-                "Value too large (max 10MB)".to_string(),
+                "TTL must be > 0".to_string(),
             ));
         }
 
+        if self.value.len() > limits.max_value_size {
+            return Err(CommandError::InvalidParameter(format!(
+                "Value too large (max {} bytes)",
+                limits.max_value_size
+            )));
+        }
+
+        if !self.internal {
+            check_untyped(&self.value)
+                .map_err(|e| CommandError::InvalidParameter(e.to_string()))?;
+        }
+
         Ok(())
     }
 
@@ -82,6 +155,10 @@ impl CommandHandler for SetCommand {
         false
     }
 
+    fn write_keys(&self) -> Option<Vec<&str>> {
+        Some(vec![&self.key])
+    }
+
     fn complexity(&self) -> u32 {
         // Complexity based on value size
         (self.value.len() / 1024).max(1) as u32
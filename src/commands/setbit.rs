@@ -0,0 +1,83 @@
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use tracing::{debug, instrument};
+
+use crate::{
+    commands::{CommandError, CommandHandler, CommandResponse, KeyLimits},
+    storage::{StorageEngine, persistence::aof::Operation},
+};
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SetBitCommand {
+    pub key: String,
+    pub offset: usize, // Bit offset, bit 0 is the most significant bit of byte 0
+    pub bit: bool,
+}
+
+impl SetBitCommand {
+    pub fn new(key: String, offset: usize, bit: bool) -> Self {
+        Self { key, offset, bit }
+    }
+}
+
+#[async_trait]
+impl CommandHandler for SetBitCommand {
+    #[instrument(skip(self, storage), fields(key = %self.key, offset = self.offset, bit = self.bit))]
+    async fn execute(&self, storage: &dyn StorageEngine) -> CommandResponse {
+        debug!("Executing SETBIT command");
+
+        // Replies with the bit's previous value, like Redis
+        match storage.set_bit(&self.key, self.offset, self.bit).await {
+            Ok(previous) => CommandResponse::Integer(i64::from(previous)),
+            Err(e) => {
+                debug!("Failed to set bit: {}", e);
+                CommandResponse::Error(e.to_string())
+            }
+        }
+    }
+
+    fn name(&self) -> &'static str {
+        "SETBIT"
+    }
+
+    fn validate(&self) -> Result<(), CommandError> {
+        self.validate_with(&KeyLimits::default())
+    }
+
+    fn validate_with(&self, limits: &KeyLimits) -> Result<(), CommandError> {
+        if self.key.is_empty() {
+            return Err(CommandError::InvalidParameter(
+                "Key cannot be empty".to_string(),
+            ));
+        }
+
+        limits.check_key(&self.key)?;
+        limits.check_writable(&self.key)?;
+
+        // The value grows to cover the addressed byte
+        if self.offset / 8 >= limits.max_value_size {
+            return Err(CommandError::InvalidParameter(format!(
+                "Bit offset out of range (max {} bytes)",
+                limits.max_value_size
+            )));
+        }
+
+        Ok(())
+    }
+
+    fn is_read_only(&self) -> bool {
+        false
+    }
+
+    fn write_keys(&self) -> Option<Vec<&str>> {
+        Some(vec![&self.key])
+    }
+
+    fn aof_operation(&self) -> Option<Operation> {
+        Some(Operation::SetBit {
+            key: self.key.clone(),
+            offset: self.offset,
+            bit: self.bit,
+        })
+    }
+}
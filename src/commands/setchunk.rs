@@ -0,0 +1,73 @@
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    commands::{CommandError, CommandHandler, CommandResponse, KeyLimits, set::SetCommand},
+    storage::StorageEngine,
+};
+
+// Header of a streamed SET: `total_len` raw bytes follow the command line, in as many
+// writes as the client likes. The connection reads them straight into one pre-sized
+// buffer and runs the result as a plain SET, so there is no base64 line to hold or decode
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SetChunkCommand {
+    pub key: String,
+    pub total_len: usize,
+}
+
+impl SetChunkCommand {
+    pub fn new(key: String, total_len: usize) -> Self {
+        Self { key, total_len }
+    }
+
+    // The SET to run once the payload has been read
+    pub fn into_set(self, value: Vec<u8>) -> SetCommand {
+        SetCommand::new(self.key, value)
+    }
+}
+
+#[async_trait]
+impl CommandHandler for SetChunkCommand {
+    // Only reached when dispatched without a connection to read the payload from
+    async fn execute(&self, _storage: &dyn StorageEngine) -> CommandResponse {
+        CommandResponse::Error("SETCHUNK payload can only be sent over a connection".to_string())
+    }
+
+    fn name(&self) -> &'static str {
+        "SETCHUNK"
+    }
+
+    fn validate(&self) -> Result<(), CommandError> {
+        self.validate_with(&KeyLimits::default())
+    }
+
+    // Checked before any of the payload is read, so an overlarge upload is refused
+    // without buffering it
+    fn validate_with(&self, limits: &KeyLimits) -> Result<(), CommandError> {
+        if self.key.is_empty() {
+            return Err(CommandError::InvalidParameter(
+                "Key cannot be empty".to_string(),
+            ));
+        }
+
+        limits.check_key(&self.key)?;
+        limits.check_writable(&self.key)?;
+
+        if self.total_len > limits.max_value_size {
+            return Err(CommandError::InvalidParameter(format!(
+                "Value too large (max {} bytes)",
+                limits.max_value_size
+            )));
+        }
+
+        Ok(())
+    }
+
+    fn is_read_only(&self) -> bool {
+        false
+    }
+
+    fn write_keys(&self) -> Option<Vec<&str>> {
+        Some(vec![&self.key])
+    }
+}
@@ -0,0 +1,87 @@
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use tracing::{debug, instrument};
+
+use crate::{
+    commands::{CommandError, CommandHandler, CommandResponse, KeyLimits},
+    storage::{StorageEngine, persistence::aof::Operation},
+};
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SetRangeCommand {
+    pub key: String,
+    pub offset: usize,
+    pub value: Vec<u8>,
+}
+
+impl SetRangeCommand {
+    pub fn new(key: String, offset: usize, value: Vec<u8>) -> Self {
+        Self { key, offset, value }
+    }
+}
+
+#[async_trait]
+impl CommandHandler for SetRangeCommand {
+    #[instrument(skip(self, storage), fields(key = %self.key, offset = self.offset, size = self.value.len()))]
+    async fn execute(&self, storage: &dyn StorageEngine) -> CommandResponse {
+        debug!("Executing SETRANGE command");
+
+        match storage.set_range(&self.key, self.offset, &self.value).await {
+            Ok(len) => CommandResponse::Integer(len as i64),
+            Err(e) => {
+                debug!("Failed to set range: {}", e);
+                CommandResponse::Error(e.to_string())
+            }
+        }
+    }
+
+    fn name(&self) -> &'static str {
+        "SETRANGE"
+    }
+
+    fn validate(&self) -> Result<(), CommandError> {
+        self.validate_with(&KeyLimits::default())
+    }
+
+    fn validate_with(&self, limits: &KeyLimits) -> Result<(), CommandError> {
+        if self.key.is_empty() {
+            return Err(CommandError::InvalidParameter(
+                "Key cannot be empty".to_string(),
+            ));
+        }
+
+        limits.check_key(&self.key)?;
+        limits.check_writable(&self.key)?;
+
+        // The padded value must still fit within the value limit
+        let end = self.offset.saturating_add(self.value.len());
+        if end > limits.max_value_size {
+            return Err(CommandError::InvalidParameter(format!(
+                "Value too large (max {} bytes)",
+                limits.max_value_size
+            )));
+        }
+
+        Ok(())
+    }
+
+    fn is_read_only(&self) -> bool {
+        false
+    }
+
+    fn write_keys(&self) -> Option<Vec<&str>> {
+        Some(vec![&self.key])
+    }
+
+    fn aof_operation(&self) -> Option<Operation> {
+        Some(Operation::SetRange {
+            key: self.key.clone(),
+            offset: self.offset,
+            value: self.value.clone(),
+        })
+    }
+
+    fn complexity(&self) -> u32 {
+        (self.value.len() / 1024).max(1) as u32
+    }
+}
@@ -0,0 +1,108 @@
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use tracing::{debug, instrument};
+
+use crate::{
+    commands::{CommandContext, CommandError, CommandHandler, CommandResponse, KeyLimits},
+    storage::{StorageEngine, persistence::aof::Operation, value::check_untyped},
+};
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SetVerCommand {
+    pub key: String,
+    pub value: Vec<u8>,
+    pub expected_version: u64, // 0 = the key must not exist
+}
+
+impl SetVerCommand {
+    pub fn new(key: String, value: Vec<u8>, expected_version: u64) -> Self {
+        Self {
+            key,
+            value,
+            expected_version,
+        }
+    }
+}
+
+#[async_trait]
+impl CommandHandler for SetVerCommand {
+    #[instrument(skip(self, storage), fields(key = %self.key, expected = self.expected_version))]
+    async fn execute(&self, storage: &dyn StorageEngine) -> CommandResponse {
+        self.execute_in(&CommandContext::for_storage(storage)).await
+    }
+
+    // Logged as a plain SET once the version check passed, a refused write never reaches
+    // the AOF and replay doesn't need versions
+    #[instrument(skip(self, ctx), fields(key = %self.key, expected = self.expected_version))]
+    async fn execute_in(&self, ctx: &CommandContext<'_>) -> CommandResponse {
+        debug!("Executing SETVER command");
+
+        let version = match ctx
+            .storage
+            .set_if_version(&self.key, self.value.clone(), self.expected_version)
+            .await
+        {
+            Ok(version) => version,
+            Err(e) => {
+                debug!("Versioned set refused: {}", e);
+                return CommandResponse::Error(e.to_string());
+            }
+        };
+
+        if let Some(persistence) = ctx.persistence
+            && let Err(e) = persistence
+                .log_operation(
+                    Operation::Put {
+                        key: self.key.clone(),
+                        value: self.value.clone(),
+                    }
+                    .in_database(ctx.database),
+                )
+                .await
+        {
+            return CommandResponse::Error(format!("Persistence error: {}", e));
+        }
+
+        CommandResponse::Integer(version as i64)
+    }
+
+    fn name(&self) -> &'static str {
+        "SETVER"
+    }
+
+    fn validate(&self) -> Result<(), CommandError> {
+        self.validate_with(&KeyLimits::default())
+    }
+
+    fn validate_with(&self, limits: &KeyLimits) -> Result<(), CommandError> {
+        if self.key.is_empty() {
+            return Err(CommandError::InvalidParameter(
+                "Key cannot be empty".to_string(),
+            ));
+        }
+
+        limits.check_key(&self.key)?;
+        limits.check_writable(&self.key)?;
+
+        if self.value.len() > limits.max_value_size {
+            return Err(CommandError::InvalidParameter(format!(
+                "Value too large (max {} bytes)",
+                limits.max_value_size
+            )));
+        }
+
+        check_untyped(&self.value).map_err(|e| CommandError::InvalidParameter(e.to_string()))
+    }
+
+    fn is_read_only(&self) -> bool {
+        false
+    }
+
+    fn write_keys(&self) -> Option<Vec<&str>> {
+        Some(vec![&self.key])
+    }
+
+    fn complexity(&self) -> u32 {
+        (self.value.len() / 1024).max(1) as u32
+    }
+}
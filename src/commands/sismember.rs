@@ -0,0 +1,63 @@
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use tracing::{debug, instrument};
+
+use crate::{
+    commands::{CommandError, CommandHandler, CommandResponse, KeyLimits},
+    storage::{StorageEngine, value::set_contains},
+};
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SIsMemberCommand {
+    pub key: String,
+    pub member: Vec<u8>,
+}
+
+impl SIsMemberCommand {
+    pub fn new(key: String, member: Vec<u8>) -> Self {
+        Self { key, member }
+    }
+}
+
+#[async_trait]
+impl CommandHandler for SIsMemberCommand {
+    #[instrument(skip(self, storage), fields(key = %self.key))]
+    async fn execute(&self, storage: &dyn StorageEngine) -> CommandResponse {
+        debug!("Executing SISMEMBER command");
+
+        let found = storage
+            .get(&self.key)
+            .await
+            .and_then(|value| value.map(|v| set_contains(&v, &self.member)).transpose());
+
+        match found {
+            Ok(found) => CommandResponse::Bool(found.unwrap_or(false)),
+            Err(e) => {
+                debug!("Failed to read set: {}", e);
+                CommandResponse::Error(e.to_string())
+            }
+        }
+    }
+
+    fn name(&self) -> &'static str {
+        "SISMEMBER"
+    }
+
+    fn validate(&self) -> Result<(), CommandError> {
+        self.validate_with(&KeyLimits::default())
+    }
+
+    fn validate_with(&self, limits: &KeyLimits) -> Result<(), CommandError> {
+        if self.key.is_empty() {
+            return Err(CommandError::InvalidParameter(
+                "Key cannot be empty".to_string(),
+            ));
+        }
+
+        limits.check_key(&self.key)
+    }
+
+    fn is_read_only(&self) -> bool {
+        true
+    }
+}
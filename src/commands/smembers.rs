@@ -0,0 +1,72 @@
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use tracing::{debug, instrument};
+
+use crate::{
+    commands::{CommandError, CommandHandler, CommandResponse, KeyLimits},
+    storage::{StorageEngine, value::decode_set},
+};
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SMembersCommand {
+    pub key: String,
+}
+
+impl SMembersCommand {
+    pub fn new(key: String) -> Self {
+        Self { key }
+    }
+}
+
+#[async_trait]
+impl CommandHandler for SMembersCommand {
+    #[instrument(skip(self, storage), fields(key = %self.key))]
+    async fn execute(&self, storage: &dyn StorageEngine) -> CommandResponse {
+        debug!("Executing SMEMBERS command");
+
+        // Missing keys read as an empty set
+        let members = storage
+            .get(&self.key)
+            .await
+            .and_then(|value| value.map(|v| decode_set(&v)).transpose());
+
+        match members {
+            Ok(members) => {
+                // Sorted so replies are stable across calls
+                let mut members: Vec<Vec<u8>> = members.unwrap_or_default().into_iter().collect();
+                members.sort();
+                CommandResponse::Members(members)
+            }
+            Err(e) => {
+                debug!("Failed to read set: {}", e);
+                CommandResponse::Error(e.to_string())
+            }
+        }
+    }
+
+    fn name(&self) -> &'static str {
+        "SMEMBERS"
+    }
+
+    fn validate(&self) -> Result<(), CommandError> {
+        self.validate_with(&KeyLimits::default())
+    }
+
+    fn validate_with(&self, limits: &KeyLimits) -> Result<(), CommandError> {
+        if self.key.is_empty() {
+            return Err(CommandError::InvalidParameter(
+                "Key cannot be empty".to_string(),
+            ));
+        }
+
+        limits.check_key(&self.key)
+    }
+
+    fn is_read_only(&self) -> bool {
+        true
+    }
+
+    fn complexity(&self) -> u32 {
+        10 // Linear in set size
+    }
+}
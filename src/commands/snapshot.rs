@@ -0,0 +1,87 @@
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use tracing::{debug, instrument};
+
+use crate::{
+    commands::{CommandContext, CommandError, CommandHandler, CommandResponse},
+    storage::StorageEngine,
+};
+
+// SNAPSHOT subcommands
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum SnapshotSubcommand {
+    Verify { path: Option<String> }, // File name in the snapshot directory, None = latest
+}
+
+// Snapshot maintenance that never touches the live store
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SnapshotCommand {
+    pub subcommand: SnapshotSubcommand,
+}
+
+impl SnapshotCommand {
+    pub fn verify(path: Option<String>) -> Self {
+        Self {
+            subcommand: SnapshotSubcommand::Verify { path },
+        }
+    }
+}
+
+#[async_trait]
+impl CommandHandler for SnapshotCommand {
+    async fn execute(&self, _storage: &dyn StorageEngine) -> CommandResponse {
+        CommandResponse::Error("SNAPSHOT requires persistence".to_string())
+    }
+
+    #[instrument(skip(self, ctx), fields(subcommand = ?self.subcommand))]
+    async fn execute_in(&self, ctx: &CommandContext<'_>) -> CommandResponse {
+        let Some(persistence) = ctx.persistence else {
+            return CommandResponse::Error("SNAPSHOT requires persistence".to_string());
+        };
+
+        let SnapshotSubcommand::Verify { ref path } = self.subcommand;
+        match persistence.verify_snapshot(path.as_deref()).await {
+            Ok(report) => {
+                debug!("Snapshot verified: {:?}", report);
+                let checksum = if report.checksum_verified {
+                    "ok"
+                } else {
+                    "absent"
+                };
+                CommandResponse::Value(
+                    format!(
+                        "path={} format_version={} keys={} size={} checksum={}",
+                        report.path.display(),
+                        report.format_version,
+                        report.total_keys,
+                        report.total_size,
+                        checksum
+                    )
+                    .into_bytes(),
+                )
+            }
+            Err(e) => CommandResponse::Error(format!("Snapshot verification failed: {}", e)),
+        }
+    }
+
+    fn name(&self) -> &'static str {
+        "SNAPSHOT"
+    }
+
+    fn validate(&self) -> Result<(), CommandError> {
+        Ok(())
+    }
+
+    fn is_read_only(&self) -> bool {
+        true
+    }
+
+    fn is_admin(&self) -> bool {
+        true
+    }
+
+    fn complexity(&self) -> u32 {
+        // Reads and replays a whole snapshot file
+        100
+    }
+}
@@ -21,6 +21,9 @@ impl CommandHandler for StatsCommand {
                     memory_usage: stats.memory_usage,
                     hit_rate: stats.hit_rate,
                     total_operations: stats.total_operations,
+                    keyspace_hits: stats.keyspace_hits,
+                    keyspace_misses: stats.keyspace_misses,
+                    evicted_keys: stats.evicted_keys,
                 }
             }
             Err(e) => {
@@ -0,0 +1,87 @@
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    commands::{CommandError, CommandHandler, CommandResponse},
+    storage::StorageEngine,
+};
+
+// Listen for messages on channels, e.g. __keyevent@0__:set
+// The dispatcher only checks the channel names, the connection keeps the subscriptions
+// once acknowledged and replies with how many channels it is subscribed to
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SubscribeCommand {
+    pub channels: Vec<String>,
+}
+
+impl SubscribeCommand {
+    pub fn new(channels: Vec<String>) -> Self {
+        Self { channels }
+    }
+}
+
+#[async_trait]
+impl CommandHandler for SubscribeCommand {
+    async fn execute(&self, _storage: &dyn StorageEngine) -> CommandResponse {
+        CommandResponse::Ok
+    }
+
+    fn name(&self) -> &'static str {
+        "SUBSCRIBE"
+    }
+
+    fn validate(&self) -> Result<(), CommandError> {
+        if self.channels.is_empty() {
+            return Err(CommandError::MissingParameter("channel".to_string()));
+        }
+        validate_channels(&self.channels)
+    }
+
+    fn is_read_only(&self) -> bool {
+        true
+    }
+}
+
+// Stop listening on channels, or on every channel when none are given
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct UnsubscribeCommand {
+    pub channels: Vec<String>,
+}
+
+impl UnsubscribeCommand {
+    pub fn new(channels: Vec<String>) -> Self {
+        Self { channels }
+    }
+}
+
+#[async_trait]
+impl CommandHandler for UnsubscribeCommand {
+    async fn execute(&self, _storage: &dyn StorageEngine) -> CommandResponse {
+        CommandResponse::Ok
+    }
+
+    fn name(&self) -> &'static str {
+        "UNSUBSCRIBE"
+    }
+
+    fn validate(&self) -> Result<(), CommandError> {
+        validate_channels(&self.channels)
+    }
+
+    fn is_read_only(&self) -> bool {
+        true
+    }
+}
+
+// Channel names go out unquoted in MESSAGE pushes
+fn validate_channels(channels: &[String]) -> Result<(), CommandError> {
+    for channel in channels {
+        if channel.is_empty() || channel.chars().any(char::is_whitespace) {
+            return Err(CommandError::InvalidParameter(format!(
+                "Invalid channel name: {:?}",
+                channel
+            )));
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,62 @@
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use tracing::{debug, instrument};
+
+use crate::{
+    commands::{CommandError, CommandHandler, CommandResponse},
+    storage::StorageEngine,
+};
+
+// Mark keys as recently used without transferring their values
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TouchCommand {
+    pub keys: Vec<String>,
+}
+
+impl TouchCommand {
+    pub fn new(keys: Vec<String>) -> Self {
+        Self { keys }
+    }
+}
+
+#[async_trait]
+impl CommandHandler for TouchCommand {
+    #[instrument(skip(self, storage), fields(keys = self.keys.len()))]
+    async fn execute(&self, storage: &dyn StorageEngine) -> CommandResponse {
+        debug!("Executing TOUCH command");
+
+        match storage.touch(&self.keys).await {
+            Ok(touched) => {
+                debug!("Touched {} keys", touched);
+                CommandResponse::Integer(touched as i64)
+            }
+            Err(e) => {
+                debug!("Failed to touch keys: {}", e);
+                CommandResponse::Error(e.to_string())
+            }
+        }
+    }
+
+    fn name(&self) -> &'static str {
+        "TOUCH"
+    }
+
+    fn validate(&self) -> Result<(), CommandError> {
+        if self.keys.is_empty() || self.keys.iter().any(|key| key.is_empty()) {
+            return Err(CommandError::MissingParameter(
+                "Key cannot be empty".to_string(),
+            ));
+        }
+
+        Ok(())
+    }
+
+    // Only access metadata changes, nothing to persist
+    fn is_read_only(&self) -> bool {
+        true
+    }
+
+    fn complexity(&self) -> u32 {
+        self.keys.len().max(1) as u32
+    }
+}
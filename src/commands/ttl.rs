@@ -0,0 +1,65 @@
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use tracing::{debug, instrument};
+
+use crate::{
+    commands::{CommandError, CommandHandler, CommandResponse},
+    storage::{StorageEngine, now_millis},
+};
+
+// TTL (seconds, rounded) and PTTL (millis) of a key: -2 if it's missing, -1 if it never
+// expires
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TtlCommand {
+    pub key: String,
+    pub millis: bool,
+}
+
+impl TtlCommand {
+    pub fn new(key: String, millis: bool) -> Self {
+        Self { key, millis }
+    }
+}
+
+#[async_trait]
+impl CommandHandler for TtlCommand {
+    #[instrument(skip(self, storage), fields(key = %self.key))]
+    async fn execute(&self, storage: &dyn StorageEngine) -> CommandResponse {
+        debug!("Executing {} command", self.name());
+
+        match storage.expires_at(&self.key).await {
+            Ok(None) => CommandResponse::Integer(-2),
+            Ok(Some(None)) => CommandResponse::Integer(-1),
+            Ok(Some(Some(at_millis))) => {
+                let remaining = at_millis.saturating_sub(now_millis());
+                let remaining = if self.millis {
+                    remaining
+                } else {
+                    remaining.saturating_add(500) / 1000
+                };
+                CommandResponse::Integer(remaining as i64)
+            }
+            Err(e) => {
+                debug!("Failed to read expiry: {}", e);
+                CommandResponse::Error(e.to_string())
+            }
+        }
+    }
+
+    fn name(&self) -> &'static str {
+        if self.millis { "PTTL" } else { "TTL" }
+    }
+
+    fn validate(&self) -> Result<(), CommandError> {
+        if self.key.is_empty() {
+            return Err(CommandError::InvalidParameter(
+                "Key cannot be empty".to_string(),
+            ));
+        }
+        Ok(())
+    }
+
+    fn is_read_only(&self) -> bool {
+        true
+    }
+}
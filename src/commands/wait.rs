@@ -0,0 +1,87 @@
+use std::time::Duration;
+
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use tracing::{debug, instrument};
+
+use crate::{
+    commands::{CommandContext, CommandError, CommandHandler, CommandResponse},
+    storage::StorageEngine,
+};
+
+// Block until prior writes reach the requested durability level or the timeout elapses
+// There is no replication yet, so the only ack available is the local AOF fsync and
+// numreplicas can be at most 1
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct WaitCommand {
+    pub numreplicas: u64,
+    pub timeout_ms: u64, // 0 = wait forever
+}
+
+impl WaitCommand {
+    pub fn new(numreplicas: u64, timeout_ms: u64) -> Self {
+        Self {
+            numreplicas,
+            timeout_ms,
+        }
+    }
+}
+
+#[async_trait]
+impl CommandHandler for WaitCommand {
+    async fn execute(&self, _storage: &dyn StorageEngine) -> CommandResponse {
+        CommandResponse::Error("WAIT requires persistence".to_string())
+    }
+
+    #[instrument(skip(self, ctx), fields(numreplicas = self.numreplicas, timeout_ms = self.timeout_ms))]
+    async fn execute_in(&self, ctx: &CommandContext<'_>) -> CommandResponse {
+        let Some(persistence) = ctx.persistence else {
+            return CommandResponse::Error("WAIT requires persistence".to_string());
+        };
+
+        let sync = persistence.sync_aof();
+
+        let result = if self.timeout_ms == 0 {
+            Ok(sync.await)
+        } else {
+            tokio::time::timeout(Duration::from_millis(self.timeout_ms), sync).await
+        };
+
+        match result {
+            Ok(Ok(())) => {
+                debug!("Local AOF fsync acknowledged");
+                CommandResponse::Integer(1)
+            }
+            Ok(Err(e)) => CommandResponse::Error(e.to_string()),
+            Err(_) => {
+                debug!("WAIT timed out before fsync ack");
+                CommandResponse::Integer(0)
+            }
+        }
+    }
+
+    fn name(&self) -> &'static str {
+        "WAIT"
+    }
+
+    // The local fsync is the only ack, so asking for more could never be satisfied
+    fn validate(&self) -> Result<(), CommandError> {
+        if self.numreplicas > 1 {
+            return Err(CommandError::InvalidParameter(
+                "numreplicas above 1 needs replication, only the local fsync is counted"
+                    .to_string(),
+            ));
+        }
+        Ok(())
+    }
+
+    // Changes no data, so READONLY mode, @read and a failing AOF don't stop it
+    fn is_read_only(&self) -> bool {
+        true
+    }
+
+    // Still waits on the writes before it, so batches never run it ahead of them
+    fn is_ordered(&self) -> bool {
+        true
+    }
+}
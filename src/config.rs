@@ -1,9 +1,18 @@
-use std::{net::SocketAddr, path::PathBuf};
+use std::{
+    collections::{HashMap, HashSet},
+    net::SocketAddr,
+    path::PathBuf,
+    sync::Arc,
+};
 
 use serde::{Deserialize, Serialize};
 use thiserror::Error;
 
-use crate::storage::StorageConfig;
+use crate::{
+    acl::CATEGORIES,
+    protocol::parser::{COMMAND_NAMES, handler_name},
+    storage::{MaxMemoryPolicy, StorageConfig},
+};
 
 #[derive(Debug, Error)]
 pub enum ConfigError {
@@ -18,8 +27,14 @@ pub enum ConfigError {
 
     #[error("Validation error: {0}")]
     Validation(String),
+
+    #[error("Interpolation error: {0}")]
+    Interpolation(String),
 }
 
+// Running configuration shared with components that read or tune it at runtime
+pub type SharedConfig = Arc<parking_lot::RwLock<BlazeServerConfig>>;
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct BlazeServerConfig {
     // Server settings
@@ -37,6 +52,10 @@ pub struct BlazeServerConfig {
     // Security settings (optional)
     #[serde(default)]
     pub security: SecurityConfig,
+
+    // Wire protocol settings (optional)
+    #[serde(default)]
+    pub protocol: ProtocolConfig,
 }
 
 // Server-specific configuration
@@ -61,6 +80,76 @@ pub struct ServerConfig {
     // Number of worker threads (0 = auto)
     #[serde(default)]
     pub worker_threads: usize,
+
+    // Max commands a connection may have pipelined behind the one executing
+    #[serde(default = "default_max_pipeline_depth")]
+    pub max_pipeline_depth: usize,
+
+    // Per-connection command budget per second (0 = unlimited)
+    #[serde(default)]
+    pub max_commands_per_sec: u64,
+
+    // Close the connection instead of only rejecting commands on quota breach
+    #[serde(default)]
+    pub disconnect_on_quota: bool,
+
+    // Initial capacity in bytes of each connection's command line buffer, which grows as
+    // needed and is reused for every command of the connection
+    #[serde(default = "default_read_buffer_size")]
+    pub read_buffer_size: usize,
+
+    // Cap in bytes on the read and reply buffers of all connections together (0 = no
+    // cap); once it is exceeded, connections holding more than their share are dropped
+    #[serde(default)]
+    pub max_client_buffer_bytes: usize,
+
+    // Pending connections the kernel queues before refusing new ones
+    #[serde(default = "default_listen_backlog")]
+    pub listen_backlog: u32,
+
+    // Number of tasks accepting connections concurrently
+    #[serde(default = "default_accept_tasks")]
+    pub accept_tasks: usize,
+
+    // Disable Nagle's algorithm so small replies are sent immediately
+    #[serde(default = "default_tcp_nodelay")]
+    pub tcp_nodelay: bool,
+
+    // Seconds in-flight commands get to finish on shutdown (0 = don't wait)
+    #[serde(default = "default_shutdown_timeout")]
+    pub shutdown_timeout: u64,
+
+    // Close connections that sent no command for this many seconds (0 = never)
+    #[serde(default)]
+    pub idle_timeout: u64,
+
+    // How often the idle reaper scans connections, in seconds
+    #[serde(default = "default_idle_check_interval")]
+    pub idle_check_interval: u64,
+
+    // Send a KEEPALIVE probe to connections silent for this many seconds and close them
+    // if nothing comes back within server_keepalive_timeout (0 = never probe)
+    #[serde(default)]
+    pub server_keepalive_interval: u64,
+
+    #[serde(default = "default_server_keepalive_timeout")]
+    pub server_keepalive_timeout: u64,
+
+    // Publish set/del/expired events to __keyevent@<db>__:<event> channels
+    #[serde(default)]
+    pub notify_keyspace_events: bool,
+
+    // File of protocol lines run against database 0 before connections are accepted
+    #[serde(default)]
+    pub startup_script: Option<PathBuf>,
+
+    // Refuse to start when a line of the startup script fails instead of only logging it
+    #[serde(default)]
+    pub startup_script_abort_on_error: bool,
+
+    // Allow DEBUG subcommands that rebuild the dataset (DEBUG RELOAD)
+    #[serde(default)]
+    pub debug_commands: bool,
 }
 
 // Pesistence configuration
@@ -78,6 +167,28 @@ pub struct PersistenceConfig {
     #[serde(default = "default_fsync_policy")]
     pub fsync_policy: FsyncPolicy,
 
+    // Fsync the AOF once on graceful shutdown, whatever fsync_policy says
+    #[serde(default = "default_true")]
+    pub fsync_on_shutdown: bool,
+
+    // Rotate the AOF into a numbered segment and compact once it reaches this many bytes
+    // (0 = never rotate)
+    #[serde(default)]
+    pub max_aof_size: u64,
+
+    // Microseconds the AOF writer holds an fsync so writes arriving meanwhile share it
+    // (group commit, 0 = fsync as soon as the policy asks)
+    #[serde(default)]
+    pub group_commit_window_us: u64,
+
+    // Compact the AOF once its total size has grown this many percent past its size after
+    // the last rewrite (0 = never), provided it is at least aof_rewrite_min_size bytes
+    #[serde(default = "default_aof_rewrite_percentage")]
+    pub aof_rewrite_percentage: u64,
+
+    #[serde(default = "default_aof_rewrite_min_size")]
+    pub aof_rewrite_min_size: u64,
+
     // Enable snapshots
     #[serde(default = "default_true")]
     pub snapshot_enabled: bool,
@@ -86,9 +197,68 @@ pub struct PersistenceConfig {
     #[serde(default = "default_snapshot_interval")]
     pub snapshot_interval: u64,
 
+    // Max random delay in seconds added to each snapshot tick (0 = no jitter)
+    #[serde(default)]
+    pub snapshot_jitter: u64,
+
     // Snapshot directory
     #[serde(default = "default_snapshot_dir")]
     pub snapshot_dir: PathBuf,
+
+    // Refuse to start when the latest snapshot can't be loaded, instead of rebuilding
+    // from the AOF alone
+    #[serde(default)]
+    pub snapshot_required: bool,
+
+    // Encoding of new snapshots, loading detects it from each file
+    #[serde(default)]
+    pub snapshot_format: SnapshotEncoding,
+
+    // Start from this snapshot file instead of the latest one (point-in-time restore from
+    // an archived backup); the restored dataset then replaces the persisted one
+    #[serde(default)]
+    pub restore_from: Option<PathBuf>,
+
+    // With restore_from, skip replaying the AOF on top of the restored snapshot
+    #[serde(default)]
+    pub restore_snapshot_only: bool,
+
+    // Allow RELOCATE to move the AOF and snapshot directory while running
+    #[serde(default)]
+    pub allow_relocate: bool,
+}
+
+impl Default for PersistenceConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            aof_path: default_aof_path(),
+            fsync_policy: default_fsync_policy(),
+            fsync_on_shutdown: true,
+            max_aof_size: 0,
+            group_commit_window_us: 0,
+            aof_rewrite_percentage: default_aof_rewrite_percentage(),
+            aof_rewrite_min_size: default_aof_rewrite_min_size(),
+            snapshot_enabled: true,
+            snapshot_interval: default_snapshot_interval(),
+            snapshot_jitter: 0,
+            snapshot_dir: default_snapshot_dir(),
+            snapshot_required: false,
+            snapshot_format: SnapshotEncoding::Bincode,
+            restore_from: None,
+            restore_snapshot_only: false,
+            allow_relocate: false,
+        }
+    }
+}
+
+// Snapshot file encoding
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum SnapshotEncoding {
+    #[default]
+    Bincode, // Compact and fast
+    Json, // Human-readable, for debugging and external tools
 }
 
 // Fsync policy for AOF
@@ -100,6 +270,48 @@ pub enum FsyncPolicy {
     Never,       // OS decides (fastest, least safe)
 }
 
+impl FsyncPolicy {
+    // Operations between fsyncs as used by the AOF writer (0 = every operation)
+    pub fn fsync_every(&self) -> u64 {
+        match self {
+            FsyncPolicy::Always => 0,
+            FsyncPolicy::EveryN(n) => *n,
+            FsyncPolicy::Never => u64::MAX,
+        }
+    }
+}
+
+// Text form used by CONFIG GET/SET: always, never or everyn:N
+impl std::fmt::Display for FsyncPolicy {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            FsyncPolicy::Always => write!(f, "always"),
+            FsyncPolicy::EveryN(n) => write!(f, "everyn:{}", n),
+            FsyncPolicy::Never => write!(f, "never"),
+        }
+    }
+}
+
+impl std::str::FromStr for FsyncPolicy {
+    type Err = ConfigError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || ConfigError::Validation(format!("Invalid fsync policy: {}", s));
+
+        match s.to_lowercase().as_str() {
+            "always" => Ok(FsyncPolicy::Always),
+            "never" => Ok(FsyncPolicy::Never),
+            other => match other.strip_prefix("everyn:") {
+                Some(n) => match n.parse::<u64>() {
+                    Ok(n) if n > 0 => Ok(FsyncPolicy::EveryN(n)),
+                    _ => Err(invalid()),
+                },
+                None => Err(invalid()),
+            },
+        }
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ObservabilityConfig {
     // Enable metrics endpoint
@@ -125,6 +337,33 @@ pub struct ObservabilityConfig {
     // Log format: compact, pretty, json
     #[serde(default = "default_log_format")]
     pub log_format: String,
+
+    // Fraction of commands (0.0-1.0) that emit tracing spans
+    #[serde(default = "default_trace_sample_rate")]
+    pub trace_sample_rate: f64,
+
+    // Log auth failures, oversized requests and quota hits under the `audit` target
+    #[serde(default)]
+    pub audit_log: bool,
+}
+
+// Wire protocol configuration
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+pub struct ProtocolConfig {
+    // Reply to GET on a missing key. Servers before this setting replied with an error,
+    // clients matching on `ERROR Key not found` need `error` to keep working; `empty` can't
+    // be told apart from a stored empty value
+    #[serde(default)]
+    pub get_missing_behavior: GetMissingBehavior,
+}
+
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum GetMissingBehavior {
+    Error, // ERROR Key not found
+    #[default]
+    Nil, // NIL
+    Empty, // An empty VALUE
 }
 
 // Security configuration
@@ -145,6 +384,25 @@ pub struct SecurityConfig {
     pub require_auth: bool,
 
     pub auth_password: Option<String>,
+
+    // Named users, each limited to the commands in its allow-list
+    #[serde(default)]
+    pub users: Vec<UserConfig>,
+
+    // Command name -> name clients must use instead, "" disables the command
+    #[serde(default)]
+    pub renamed_commands: HashMap<String, String>,
+}
+
+// A user that can AUTH with a username and password
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UserConfig {
+    pub name: String,
+    pub password: String,
+
+    // Command names (aliases like DEL grant the command they run) or @all, @read, @write, @admin
+    #[serde(default)]
+    pub commands: Vec<String>,
 }
 
 // Default value functions
@@ -156,6 +414,38 @@ fn default_connection_timeout() -> u64 {
     300
 }
 
+fn default_max_pipeline_depth() -> usize {
+    1024
+}
+
+fn default_listen_backlog() -> u32 {
+    1024
+}
+
+fn default_accept_tasks() -> usize {
+    1
+}
+
+fn default_tcp_nodelay() -> bool {
+    true
+}
+
+fn default_shutdown_timeout() -> u64 {
+    30
+}
+
+fn default_idle_check_interval() -> u64 {
+    10
+}
+
+fn default_read_buffer_size() -> usize {
+    4096
+}
+
+fn default_server_keepalive_timeout() -> u64 {
+    10
+}
+
 fn default_max_connections() -> usize {
     1000
 }
@@ -180,6 +470,14 @@ fn default_snapshot_interval() -> u64 {
     3600
 }
 
+fn default_aof_rewrite_percentage() -> u64 {
+    100
+}
+
+fn default_aof_rewrite_min_size() -> u64 {
+    64 * 1024 * 1024
+}
+
 fn default_snapshot_dir() -> PathBuf {
     PathBuf::from("data/snapshots")
 }
@@ -200,6 +498,10 @@ fn default_log_format() -> String {
     "compact".to_string()
 }
 
+fn default_trace_sample_rate() -> f64 {
+    1.0
+}
+
 impl Default for BlazeServerConfig {
     fn default() -> Self {
         Self {
@@ -209,16 +511,26 @@ impl Default for BlazeServerConfig {
                 max_connections: default_max_connections(),
                 keepalive_interval: default_keepalive(),
                 worker_threads: 0,
+                max_pipeline_depth: default_max_pipeline_depth(),
+                max_commands_per_sec: 0,
+                disconnect_on_quota: false,
+                read_buffer_size: default_read_buffer_size(),
+                max_client_buffer_bytes: 0,
+                listen_backlog: default_listen_backlog(),
+                accept_tasks: default_accept_tasks(),
+                tcp_nodelay: default_tcp_nodelay(),
+                shutdown_timeout: default_shutdown_timeout(),
+                idle_timeout: 0,
+                idle_check_interval: default_idle_check_interval(),
+                server_keepalive_interval: 0,
+                server_keepalive_timeout: default_server_keepalive_timeout(),
+                notify_keyspace_events: false,
+                startup_script: None,
+                startup_script_abort_on_error: false,
+                debug_commands: false,
             },
             storage: StorageConfig::default(),
-            persistence: PersistenceConfig {
-                enabled: true,
-                aof_path: default_aof_path(),
-                fsync_policy: default_fsync_policy(),
-                snapshot_enabled: true,
-                snapshot_interval: default_snapshot_interval(),
-                snapshot_dir: default_snapshot_dir(),
-            },
+            persistence: PersistenceConfig::default(),
             observability: ObservabilityConfig {
                 metrics_enabled: true,
                 metrics_addr: default_metrics_addr(),
@@ -226,27 +538,35 @@ impl Default for BlazeServerConfig {
                 health_check_addr: default_health_addr(),
                 log_level: default_log_level(),
                 log_format: default_log_format(),
+                trace_sample_rate: default_trace_sample_rate(),
+                audit_log: false,
             },
             security: SecurityConfig::default(),
+            protocol: ProtocolConfig::default(),
         }
     }
 }
 
 impl BlazeServerConfig {
+    /// Start a layered config builder (defaults < file < env < CLI)
+    pub fn builder() -> ConfigBuilder {
+        ConfigBuilder::new()
+    }
+
     /// Load config from JSON file
     pub fn from_json_file<P: AsRef<std::path::Path>>(path: P) -> Result<Self, ConfigError> {
-        let content = std::fs::read_to_string(path)?;
-        let config: BlazeServerConfig = serde_json::from_str(&content)?;
-        config.validate()?;
-        Ok(config)
+        Self::builder()
+            .with_file_format(path, ConfigFormat::Json)
+            .build()
+            .map(|layered| layered.config)
     }
 
     /// Load config from TOML file
     pub fn from_toml_file<P: AsRef<std::path::Path>>(path: P) -> Result<Self, ConfigError> {
-        let content = std::fs::read_to_string(path)?;
-        let config: BlazeServerConfig = toml::from_str(&content)?;
-        config.validate()?;
-        Ok(config)
+        Self::builder()
+            .with_file_format(path, ConfigFormat::Toml)
+            .build()
+            .map(|layered| layered.config)
     }
 
     /// Save config to JSON file
@@ -273,6 +593,37 @@ impl BlazeServerConfig {
             ));
         }
 
+        if self.server.max_pipeline_depth == 0 {
+            return Err(ConfigError::Validation(
+                "max_pipeline_depth must be > 0".to_string(),
+            ));
+        }
+
+        if self.server.listen_backlog == 0 {
+            return Err(ConfigError::Validation(
+                "listen_backlog must be > 0".to_string(),
+            ));
+        }
+
+        if self.server.accept_tasks == 0 {
+            return Err(ConfigError::Validation(
+                "accept_tasks must be > 0".to_string(),
+            ));
+        }
+
+        if self.server.idle_timeout > 0 && self.server.idle_check_interval == 0 {
+            return Err(ConfigError::Validation(
+                "idle_check_interval must be > 0 when idle_timeout is set".to_string(),
+            ));
+        }
+
+        if self.server.server_keepalive_interval > 0 && self.server.server_keepalive_timeout == 0 {
+            return Err(ConfigError::Validation(
+                "server_keepalive_timeout must be > 0 when server_keepalive_interval is set"
+                    .to_string(),
+            ));
+        }
+
         if self.server.connection_timeout == 0 {
             return Err(ConfigError::Validation(
                 "connection_timeout must be > 0".to_string(),
@@ -286,6 +637,41 @@ impl BlazeServerConfig {
             ));
         }
 
+        // Every WAIT and AOF sync may sit out a full window
+        if self.persistence.group_commit_window_us > 1_000_000 {
+            return Err(ConfigError::Validation(
+                "group_commit_window_us must be <= 1000000".to_string(),
+            ));
+        }
+
+        // Recovery, and so any restore, only runs with persistence on
+        if self.persistence.restore_from.is_some() && !self.persistence.enabled {
+            return Err(ConfigError::Validation(
+                "restore_from requires persistence to be enabled".to_string(),
+            ));
+        }
+
+        if self.persistence.restore_snapshot_only && self.persistence.restore_from.is_none() {
+            return Err(ConfigError::Validation(
+                "restore_snapshot_only requires restore_from".to_string(),
+            ));
+        }
+
+        if self.persistence.snapshot_enabled {
+            if self.persistence.snapshot_interval == 0 {
+                return Err(ConfigError::Validation(
+                    "snapshot_interval must be > 0".to_string(),
+                ));
+            }
+
+            // Jitter at or above the interval would let snapshots drift into each other
+            if self.persistence.snapshot_jitter >= self.persistence.snapshot_interval {
+                return Err(ConfigError::Validation(
+                    "snapshot_jitter must be < snapshot_interval".to_string(),
+                ));
+            }
+        }
+
         // Validate storage config
         if self.storage.max_memory == 0 {
             return Err(ConfigError::Validation(
@@ -299,6 +685,77 @@ impl BlazeServerConfig {
             ));
         }
 
+        if self.storage.databases == 0 {
+            return Err(ConfigError::Validation("databases must be > 0".to_string()));
+        }
+
+        // Nothing would be compared, so nothing would ever be evicted
+        if self.storage.maxmemory_policy != MaxMemoryPolicy::NoEviction
+            && self.storage.eviction_sample_size == 0
+        {
+            return Err(ConfigError::Validation(
+                "eviction_sample_size must be > 0 when maxmemory_policy evicts".to_string(),
+            ));
+        }
+
+        if self.storage.bloom_filter && self.storage.bloom_filter_capacity == 0 {
+            return Err(ConfigError::Validation(
+                "bloom_filter_capacity must be > 0 when bloom_filter is enabled".to_string(),
+            ));
+        }
+
+        if self.storage.max_key_size == 0 {
+            return Err(ConfigError::Validation(
+                "max_key_size must be > 0".to_string(),
+            ));
+        }
+
+        if self.storage.max_value_size == 0 {
+            return Err(ConfigError::Validation(
+                "max_value_size must be > 0".to_string(),
+            ));
+        }
+
+        // An empty prefix would reserve the whole keyspace
+        if self.storage.reserved_prefix.as_deref() == Some("") {
+            return Err(ConfigError::Validation(
+                "reserved_prefix cannot be empty (omit it to disable)".to_string(),
+            ));
+        }
+
+        if self.storage.default_ttl == Some(0) {
+            return Err(ConfigError::Validation(
+                "default_ttl must be > 0".to_string(),
+            ));
+        }
+
+        if self.storage.max_ttl == Some(0) {
+            return Err(ConfigError::Validation("max_ttl must be > 0".to_string()));
+        }
+
+        if self.storage.max_keys == Some(0) {
+            return Err(ConfigError::Validation("max_keys must be > 0".to_string()));
+        }
+
+        if let (Some(default_ttl), Some(max_ttl)) = (self.storage.default_ttl, self.storage.max_ttl)
+            && default_ttl > max_ttl
+        {
+            return Err(ConfigError::Validation(format!(
+                "default_ttl ({}s) must be <= max_ttl ({}s)",
+                default_ttl, max_ttl
+            )));
+        }
+
+        // Also rejects NaN
+        if !(0.0..=1.0).contains(&self.observability.trace_sample_rate) {
+            return Err(ConfigError::Validation(
+                "trace_sample_rate must be between 0.0 and 1.0".to_string(),
+            ));
+        }
+
+        // Validate listener addresses don't collide
+        self.validate_bind_addrs()?;
+
         // Validate TLS config
         if self.security.tls_enabled {
             if self.security.tls_cert_path.is_none() {
@@ -313,6 +770,122 @@ impl BlazeServerConfig {
             }
         }
 
+        // Validate users
+        let mut names = HashSet::new();
+        for user in &self.security.users {
+            if user.name.is_empty() {
+                return Err(ConfigError::Validation(
+                    "security.users entries need a name".to_string(),
+                ));
+            }
+            if !names.insert(user.name.as_str()) {
+                return Err(ConfigError::Validation(format!(
+                    "security.users has duplicate user '{}'",
+                    user.name
+                )));
+            }
+            if let Some(entry) = user.commands.iter().find(|entry| {
+                entry.starts_with('@') && !CATEGORIES.contains(&entry.to_lowercase().as_str())
+            }) {
+                return Err(ConfigError::Validation(format!(
+                    "Unknown command category '{}' for user '{}'",
+                    entry, user.name
+                )));
+            }
+            // A typo would otherwise grant nothing without a word
+            if let Some(entry) = user
+                .commands
+                .iter()
+                .find(|entry| !entry.starts_with('@') && handler_name(entry).is_none())
+            {
+                return Err(ConfigError::Validation(format!(
+                    "Unknown command '{}' for user '{}'",
+                    entry, user.name
+                )));
+            }
+        }
+
+        // Renames must refer to known commands and leave every name meaning one command
+        let mut new_names = HashSet::new();
+        for (original, new_name) in &self.security.renamed_commands {
+            let original = original.to_uppercase();
+            if !COMMAND_NAMES.contains(&original.as_str()) {
+                return Err(ConfigError::Validation(format!(
+                    "security.renamed_commands has unknown command '{}'",
+                    original
+                )));
+            }
+            if new_name.is_empty() {
+                continue;
+            }
+
+            let new_name = new_name.to_uppercase();
+            if new_name.contains(char::is_whitespace) || new_name.starts_with('"') {
+                return Err(ConfigError::Validation(format!(
+                    "security.renamed_commands: '{}' is not a valid command name",
+                    new_name
+                )));
+            }
+            // Still reachable under its own name unless it was renamed too
+            let shadows = COMMAND_NAMES.contains(&new_name.as_str())
+                && !self
+                    .security
+                    .renamed_commands
+                    .keys()
+                    .any(|renamed| renamed.eq_ignore_ascii_case(&new_name));
+            if shadows || !new_names.insert(new_name.clone()) {
+                return Err(ConfigError::Validation(format!(
+                    "security.renamed_commands: '{}' is already a command name",
+                    new_name
+                )));
+            }
+        }
+
+        // Nobody could ever log in
+        if self.security.require_auth
+            && self.security.auth_password.is_none()
+            && self.security.users.is_empty()
+        {
+            return Err(ConfigError::Validation(
+                "require_auth needs auth_password or security.users".to_string(),
+            ));
+        }
+
+        Ok(())
+    }
+
+    // Reject server/metrics/health endpoints bound to the same socket
+    fn validate_bind_addrs(&self) -> Result<(), ConfigError> {
+        let mut addrs = vec![("server.bind_addr", self.server.bind_addr)];
+
+        if self.observability.metrics_enabled
+            && let Some(addr) = self.observability.metrics_addr
+        {
+            addrs.push(("observability.metrics_addr", addr));
+        }
+
+        if self.observability.health_check_enabled
+            && let Some(addr) = self.observability.health_check_addr
+        {
+            addrs.push(("observability.health_check_addr", addr));
+        }
+
+        for (i, (name_a, a)) in addrs.iter().enumerate() {
+            for (name_b, b) in &addrs[i + 1..] {
+                // Port 0 lets the OS pick, so it never collides
+                let same_port = a.port() != 0 && a.port() == b.port();
+                let overlapping_ip =
+                    a.ip() == b.ip() || a.ip().is_unspecified() || b.ip().is_unspecified();
+
+                if same_port && overlapping_ip {
+                    return Err(ConfigError::Validation(format!(
+                        "{} ({}) conflicts with {} ({})",
+                        name_a, a, name_b, b
+                    )));
+                }
+            }
+        }
+
         Ok(())
     }
 
@@ -323,45 +896,433 @@ impl BlazeServerConfig {
 
     /// Load from environment variables (override config file)
     pub fn from_env(&mut self) {
-        use std::env;
+        let mut overrides = Vec::new();
+        apply_env_layer(self, &mut overrides);
+    }
+}
 
-        // Server overrides
-        if let Ok(addr) = env::var("KVSTORE_BIND_ADDR") {
-            if let Ok(parsed) = addr.parse() {
-                self.server.bind_addr = parsed;
-            }
-        }
+// Config file format for the file layer
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ConfigFormat {
+    Json,
+    Toml,
+}
 
-        if let Ok(timeout) = env::var("KVSTORE_CONNECTION_TIMEOUT") {
-            if let Ok(parsed) = timeout.parse() {
-                self.server.connection_timeout = parsed;
-            }
+// Layer that last set a config field
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ConfigSource {
+    Default,
+    File(PathBuf),
+    Env(&'static str), // env variable name
+    Cli(&'static str), // CLI flag name
+}
+
+impl std::fmt::Display for ConfigSource {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ConfigSource::Default => write!(f, "default"),
+            ConfigSource::File(path) => write!(f, "file {}", path.display()),
+            ConfigSource::Env(var) => write!(f, "env {}", var),
+            ConfigSource::Cli(flag) => write!(f, "cli {}", flag),
         }
+    }
+}
 
-        if let Ok(max_conn) = env::var("KVSTORE_MAX_CONNECTIONS") {
-            if let Ok(parsed) = max_conn.parse() {
-                self.server.max_connections = parsed;
-            }
+// A single field overridden by a higher-precedence layer
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ConfigOverride {
+    pub field: &'static str,
+    pub source: ConfigSource,
+}
+
+// CLI-level overrides, decoupled from the binary's clap definition
+#[derive(Debug, Clone, Default)]
+pub struct CliOverrides {
+    // Bind address for TCP server
+    pub bind: Option<String>,
+
+    // Disable persistence
+    pub no_persistence: bool,
+
+    // Enable debug logging
+    pub debug: bool,
+
+    // Startup script to run
+    pub exec: Option<PathBuf>,
+
+    // Snapshot file to restore from, and whether to skip the AOF replay after it
+    pub restore_from: Option<PathBuf>,
+    pub restore_snapshot_only: bool,
+}
+
+// Final config together with where each overridden field came from
+#[derive(Debug, Clone)]
+pub struct LayeredConfig {
+    pub config: BlazeServerConfig,
+
+    // Base layer the config was built from (defaults or file)
+    pub base: ConfigSource,
+
+    // Overrides applied on top of the base, in application order
+    pub overrides: Vec<ConfigOverride>,
+}
+
+impl LayeredConfig {
+    /// Layer that last set the given field (e.g. "server.bind_addr")
+    pub fn source_of(&self, field: &str) -> &ConfigSource {
+        self.overrides
+            .iter()
+            .rev()
+            .find(|o| o.field == field)
+            .map(|o| &o.source)
+            .unwrap_or(&self.base)
+    }
+}
+
+// Layered config builder
+// Precedence is fixed regardless of call order: defaults < file < env < CLI
+#[derive(Debug, Clone, Default)]
+pub struct ConfigBuilder {
+    file: Option<(PathBuf, Option<ConfigFormat>)>,
+    env: bool,
+    cli: Option<CliOverrides>,
+}
+
+impl ConfigBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Load base config from file, detecting the format by extension
+    pub fn with_file<P: AsRef<std::path::Path>>(mut self, path: P) -> Self {
+        self.file = Some((path.as_ref().to_path_buf(), None));
+        self
+    }
+
+    /// Load base config from file with an explicit format
+    pub fn with_file_format<P: AsRef<std::path::Path>>(
+        mut self,
+        path: P,
+        format: ConfigFormat,
+    ) -> Self {
+        self.file = Some((path.as_ref().to_path_buf(), Some(format)));
+        self
+    }
+
+    /// Apply KVSTORE_* environment variable overrides
+    pub fn with_env(mut self) -> Self {
+        self.env = true;
+        self
+    }
+
+    /// Apply CLI overrides
+    pub fn with_cli(mut self, cli: CliOverrides) -> Self {
+        self.cli = Some(cli);
+        self
+    }
+
+    /// Apply all layers in precedence order and validate the result
+    pub fn build(self) -> Result<LayeredConfig, ConfigError> {
+        let (mut config, base) = match self.file {
+            Some((path, format)) => (load_file(&path, format)?, ConfigSource::File(path)),
+            None => (BlazeServerConfig::default(), ConfigSource::Default),
+        };
+
+        let mut overrides = Vec::new();
+
+        if self.env {
+            apply_env_layer(&mut config, &mut overrides);
         }
 
-        // Persistence overrides
-        if let Ok(aof_path) = env::var("KVSTORE_AOF_PATH") {
-            self.persistence.aof_path = PathBuf::from(aof_path);
+        if let Some(ref cli) = self.cli {
+            apply_cli_layer(&mut config, cli, &mut overrides)?;
         }
 
-        if let Ok(enabled) = env::var("KVSTORE_PERSISTENCE_ENABLED") {
-            self.persistence.enabled = enabled.to_lowercase() == "true";
+        config.validate()?;
+
+        Ok(LayeredConfig {
+            config,
+            base,
+            overrides,
+        })
+    }
+}
+
+// Read and parse a config file
+fn load_file(
+    path: &std::path::Path,
+    format: Option<ConfigFormat>,
+) -> Result<BlazeServerConfig, ConfigError> {
+    if !path.exists() {
+        return Err(ConfigError::Validation(format!(
+            "Config file not found: {}",
+            path.display()
+        )));
+    }
+
+    let content = std::fs::read_to_string(path)?;
+
+    let format = format.or_else(|| match path.extension().and_then(|e| e.to_str()) {
+        Some("toml") => Some(ConfigFormat::Toml),
+        Some("json") => Some(ConfigFormat::Json),
+        _ => None,
+    });
+
+    match format {
+        Some(ConfigFormat::Json) => parse_json(&content),
+        Some(ConfigFormat::Toml) => parse_toml(&content),
+        // Unknown extension: try TOML first, then JSON
+        None => match toml::from_str::<toml::Value>(&content) {
+            Ok(_) => parse_toml(&content),
+            Err(_) => parse_json(&content),
+        },
+    }
+}
+
+// Parse to a generic tree first so ${VAR} expansion only ever touches string values
+fn parse_json(content: &str) -> Result<BlazeServerConfig, ConfigError> {
+    let mut tree: serde_json::Value = serde_json::from_str(content)?;
+    interpolate_json(&mut tree)?;
+    check_legacy_eviction(serde_json::from_value(tree.clone()).unwrap_or_default())?;
+    Ok(serde_json::from_value(tree)?)
+}
+
+fn parse_toml(content: &str) -> Result<BlazeServerConfig, ConfigError> {
+    let mut tree: toml::Value = toml::from_str(content)?;
+    interpolate_toml(&mut tree)?;
+    check_legacy_eviction(tree.clone().try_into().unwrap_or_default())?;
+    Ok(tree.try_into()?)
+}
+
+// The eviction keys as written in a config file, absent ones left as None
+#[derive(Debug, Default, Deserialize)]
+struct EvictionKeys {
+    #[serde(default)]
+    storage: StorageEvictionKeys,
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct StorageEvictionKeys {
+    eviction_sample_size: Option<usize>,
+    maxmemory_policy: Option<serde::de::IgnoredAny>,
+}
+
+// Before maxmemory_policy, a non-zero eviction_sample_size alone turned on LRU eviction.
+// Such a file would now quietly stop evicting and fail writes at max_memory, so it has to
+// say which policy it wants
+fn check_legacy_eviction(keys: EvictionKeys) -> Result<(), ConfigError> {
+    match keys.storage {
+        StorageEvictionKeys {
+            eviction_sample_size: Some(size),
+            maxmemory_policy: None,
+        } if size > 0 => Err(ConfigError::Validation(
+            "storage.eviction_sample_size no longer enables eviction on its own: set \
+             storage.maxmemory_policy, \"allkeys-lru\" to keep evicting as before or \
+             \"noeviction\" to fail writes at max_memory"
+                .to_string(),
+        )),
+        _ => Ok(()),
+    }
+}
+
+fn interpolate_json(value: &mut serde_json::Value) -> Result<(), ConfigError> {
+    match value {
+        serde_json::Value::String(s) => *s = interpolate_env(s)?,
+        serde_json::Value::Array(items) => items.iter_mut().try_for_each(interpolate_json)?,
+        serde_json::Value::Object(map) => map.values_mut().try_for_each(interpolate_json)?,
+        _ => {}
+    }
+    Ok(())
+}
+
+fn interpolate_toml(value: &mut toml::Value) -> Result<(), ConfigError> {
+    match value {
+        toml::Value::String(s) => *s = interpolate_env(s)?,
+        toml::Value::Array(items) => items.iter_mut().try_for_each(interpolate_toml)?,
+        toml::Value::Table(table) => table
+            .iter_mut()
+            .try_for_each(|(_, value)| interpolate_toml(value))?,
+        _ => {}
+    }
+    Ok(())
+}
+
+/// Expand `${VAR}` and `${VAR:-default}` from the environment; `$${` is a literal `${`
+/// The default applies when VAR is unset or empty, otherwise a missing VAR is an error
+pub fn interpolate_env(input: &str) -> Result<String, ConfigError> {
+    let mut output = String::with_capacity(input.len());
+    let mut rest = input;
+
+    while let Some(dollar) = rest.find('$') {
+        output.push_str(&rest[..dollar]);
+        let after = &rest[dollar + 1..];
+
+        if let Some(escaped) = after.strip_prefix("${") {
+            output.push_str("${");
+            rest = escaped;
+            continue;
         }
 
-        // Logging overrides
-        if let Ok(log_level) = env::var("KVSTORE_LOG_LEVEL") {
-            self.observability.log_level = log_level;
+        let Some(body) = after.strip_prefix('{') else {
+            output.push('$');
+            rest = after;
+            continue;
+        };
+
+        let end = body.find('}').ok_or_else(|| {
+            ConfigError::Interpolation(format!("Unterminated '${{' in '{}'", input))
+        })?;
+
+        let (name, default) = match body[..end].split_once(":-") {
+            Some((name, default)) => (name, Some(default)),
+            None => (&body[..end], None),
+        };
+
+        if name.is_empty() {
+            return Err(ConfigError::Interpolation(format!(
+                "Empty variable name in '{}'",
+                input
+            )));
         }
 
-        // Security overrides
-        if let Ok(password) = env::var("KVSTORE_AUTH_PASSWORD") {
-            self.security.auth_password = Some(password);
-            self.security.require_auth = true;
+        match (std::env::var(name), default) {
+            (Ok(value), Some(default)) if value.is_empty() => output.push_str(default),
+            (Ok(value), _) => output.push_str(&value),
+            (Err(_), Some(default)) => output.push_str(default),
+            (Err(_), None) => {
+                return Err(ConfigError::Interpolation(format!(
+                    "Environment variable '{}' is not set",
+                    name
+                )));
+            }
         }
+
+        rest = &body[end + 1..];
+    }
+
+    output.push_str(rest);
+    Ok(output)
+}
+
+// Apply environment variable layer, recording every field it sets
+fn apply_env_layer(config: &mut BlazeServerConfig, overrides: &mut Vec<ConfigOverride>) {
+    use std::env;
+
+    let mut record = |field: &'static str, var: &'static str| {
+        overrides.push(ConfigOverride {
+            field,
+            source: ConfigSource::Env(var),
+        });
+    };
+
+    // Server overrides
+    if let Ok(addr) = env::var("KVSTORE_BIND_ADDR")
+        && let Ok(parsed) = addr.parse()
+    {
+        config.server.bind_addr = parsed;
+        record("server.bind_addr", "KVSTORE_BIND_ADDR");
+    }
+
+    if let Ok(timeout) = env::var("KVSTORE_CONNECTION_TIMEOUT")
+        && let Ok(parsed) = timeout.parse()
+    {
+        config.server.connection_timeout = parsed;
+        record("server.connection_timeout", "KVSTORE_CONNECTION_TIMEOUT");
+    }
+
+    if let Ok(max_conn) = env::var("KVSTORE_MAX_CONNECTIONS")
+        && let Ok(parsed) = max_conn.parse()
+    {
+        config.server.max_connections = parsed;
+        record("server.max_connections", "KVSTORE_MAX_CONNECTIONS");
+    }
+
+    // Persistence overrides
+    if let Ok(aof_path) = env::var("KVSTORE_AOF_PATH") {
+        config.persistence.aof_path = PathBuf::from(aof_path);
+        record("persistence.aof_path", "KVSTORE_AOF_PATH");
     }
+
+    if let Ok(enabled) = env::var("KVSTORE_PERSISTENCE_ENABLED") {
+        config.persistence.enabled = enabled.to_lowercase() == "true";
+        record("persistence.enabled", "KVSTORE_PERSISTENCE_ENABLED");
+    }
+
+    // Logging overrides
+    if let Ok(log_level) = env::var("KVSTORE_LOG_LEVEL") {
+        config.observability.log_level = log_level;
+        record("observability.log_level", "KVSTORE_LOG_LEVEL");
+    }
+
+    // Security overrides
+    if let Ok(password) = env::var("KVSTORE_AUTH_PASSWORD") {
+        config.security.auth_password = Some(password);
+        config.security.require_auth = true;
+        record("security.auth_password", "KVSTORE_AUTH_PASSWORD");
+        record("security.require_auth", "KVSTORE_AUTH_PASSWORD");
+    }
+}
+
+// Apply CLI layer, recording every field it sets
+fn apply_cli_layer(
+    config: &mut BlazeServerConfig,
+    cli: &CliOverrides,
+    overrides: &mut Vec<ConfigOverride>,
+) -> Result<(), ConfigError> {
+    // Overrides bind address
+    if let Some(ref bind_addr) = cli.bind {
+        config.server.bind_addr = bind_addr.parse().map_err(|e| {
+            ConfigError::Validation(format!("Invalid bind address '{}': {}", bind_addr, e))
+        })?;
+        overrides.push(ConfigOverride {
+            field: "server.bind_addr",
+            source: ConfigSource::Cli("--bind"),
+        });
+    }
+
+    // Override persistence
+    if cli.no_persistence {
+        config.persistence.enabled = false;
+        overrides.push(ConfigOverride {
+            field: "persistence.enabled",
+            source: ConfigSource::Cli("--no-persistence"),
+        });
+    }
+
+    // Override log level
+    if cli.debug {
+        config.observability.log_level = "debug".to_string();
+        overrides.push(ConfigOverride {
+            field: "observability.log_level",
+            source: ConfigSource::Cli("--debug"),
+        });
+    }
+
+    // Override startup script
+    if let Some(ref script) = cli.exec {
+        config.server.startup_script = Some(script.clone());
+        overrides.push(ConfigOverride {
+            field: "server.startup_script",
+            source: ConfigSource::Cli("--exec"),
+        });
+    }
+
+    // Override restore
+    if let Some(ref snapshot) = cli.restore_from {
+        config.persistence.restore_from = Some(snapshot.clone());
+        overrides.push(ConfigOverride {
+            field: "persistence.restore_from",
+            source: ConfigSource::Cli("--restore-from"),
+        });
+    }
+
+    if cli.restore_snapshot_only {
+        config.persistence.restore_snapshot_only = true;
+        overrides.push(ConfigOverride {
+            field: "persistence.restore_snapshot_only",
+            source: ConfigSource::Cli("--restore-snapshot-only"),
+        });
+    }
+
+    Ok(())
 }
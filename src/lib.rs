@@ -1,9 +1,13 @@
+pub mod acl;
 pub mod bootstrap;
+pub mod client;
 pub mod commands;
 pub mod config;
 pub mod error;
 pub mod http;
 pub mod metrics;
 pub mod protocol;
+pub mod pubsub;
+pub mod repl;
 pub mod server;
 pub mod storage;
@@ -1,15 +1,23 @@
-use std::{path::Path, sync::Arc};
+use std::{
+    path::{Path, PathBuf},
+    sync::{Arc, atomic::Ordering},
+    time::Duration,
+};
 
 use blazekvdb::{
     bootstrap::BlazeKVDB,
-    config::BlazeServerConfig,
+    commands::{ClientTraffic, SampledSpans},
+    config::{BlazeServerConfig, CliOverrides, LayeredConfig},
     error::{BlazeError, BlazeResult},
-    server::tcp::TcpServer,
+    protocol::parser::CommandRenames,
+    repl,
+    server::{connection::ConnectionLimits, tcp::TcpServer},
     storage::StorageEngine,
 };
-use clap::Parser;
-use tracing::{Level, error, info};
-use tracing_subscriber::{EnvFilter, fmt};
+use clap::{Parser, Subcommand};
+use tokio::net::TcpListener;
+use tracing::{Level, error, info, warn};
+use tracing_subscriber::{EnvFilter, Layer, fmt, layer::SubscriberExt, util::SubscriberInitExt};
 use warp::Filter;
 
 #[derive(Parser)]
@@ -47,6 +55,31 @@ struct Cli {
     // Validate config and exit
     #[arg(long)]
     validate: bool,
+
+    // Run the commands in this file before accepting connections
+    #[arg(long, value_name = "FILE")]
+    exec: Option<PathBuf>,
+
+    // Start from this snapshot file instead of the latest one
+    #[arg(long, value_name = "FILE")]
+    restore_from: Option<PathBuf>,
+
+    // With --restore-from, don't replay the AOF on top of the snapshot
+    #[arg(long, requires = "restore_from")]
+    restore_snapshot_only: bool,
+
+    #[command(subcommand)]
+    mode: Option<Mode>,
+}
+
+#[derive(Subcommand)]
+enum Mode {
+    // Open an interactive shell against a running server
+    Cli {
+        // Server address
+        #[arg(default_value = "127.0.0.1:6379")]
+        addr: String,
+    },
 }
 
 #[tokio::main]
@@ -54,27 +87,18 @@ async fn main() -> BlazeResult<()> {
     // Parse CLI arguments
     let cli = Cli::parse();
 
+    if let Some(Mode::Cli { addr }) = &cli.mode {
+        return run_cli(addr).await;
+    }
+
     // Generate example config if requested
     if let Some(output_path) = cli.generate_config {
         return generate_config_file(&output_path);
     }
 
-    // Load and prepare configuration
-    let mut config = load_configuration(&cli)?;
-
-    // Apply environment variable overrides if requested
-    if cli.use_env {
-        info!("Applying environment variable overrides");
-        config.from_env();
-    }
-
-    // Apply CLI overrides
-    apply_cli_overrides(&mut config, &cli)?;
-
-    // Validate configuration
-    config
-        .validate()
-        .map_err(|e| BlazeError::Config(format!("{}", e).into()))?;
+    // Load, layer and validate configuration
+    let layered = load_configuration(&cli)?;
+    let config = layered.config.clone();
 
     // If validate-only mode, exit here
     if cli.validate {
@@ -90,6 +114,7 @@ async fn main() -> BlazeResult<()> {
 
     // Print config summary
     print_config_summary(&config);
+    print_config_overrides(&layered);
 
     // Initialize BlazeKVDB
     info!("Initializing BlazeKVDB...");
@@ -101,24 +126,118 @@ async fn main() -> BlazeResult<()> {
         }
     };
 
+    install_panic_flush(&kvdb);
+
+    if let Some(ref path) = config.server.startup_script
+        && let Err(e) =
+            run_startup_script(&kvdb, path, config.server.startup_script_abort_on_error).await
+    {
+        error!("❌ {}", e);
+        // Keep what the script wrote before failing
+        kvdb.shutdown().await?;
+        return Err(e);
+    }
+
     let dispatcher = kvdb.dispatcher();
-    let storage = kvdb.storage();
 
     print_startup_info(&config, &kvdb).await;
 
-    start_auxiliary_services(&config, storage.clone()).await;
+    start_auxiliary_services(&config, kvdb.clone()).await;
 
     info!("Starting TCP server...");
-    let server = TcpServer::new(dispatcher, config.server.bind_addr);
+    let server = TcpServer::new(dispatcher, config.server.bind_addr)
+        .with_connection_limits(ConnectionLimits::from(&config.server))
+        .with_listen_backlog(config.server.listen_backlog)
+        .with_accept_tasks(config.server.accept_tasks)
+        .with_tcp_nodelay(config.server.tcp_nodelay)
+        .with_audit_log(config.observability.audit_log)
+        .with_renamed_commands(CommandRenames::new(&config.security.renamed_commands))
+        .with_shutdown_timeout(Duration::from_secs(config.server.shutdown_timeout))
+        .with_idle_timeout(Duration::from_secs(config.server.idle_timeout))
+        .with_idle_check_interval(Duration::from_secs(config.server.idle_check_interval));
 
     if let Err(e) = server.start().await {
         error!("❌ Server error: {}", e);
         return Err(BlazeError::Server(e.to_string()));
     }
 
+    // Connections are drained, nothing writes anymore
+    if let Err(e) = kvdb.shutdown().await {
+        error!("❌ Persistence shutdown failed: {}", e);
+        return Err(e.into());
+    }
+
+    Ok(())
+}
+
+/// On panic, try to get queued AOF writes to disk before the process goes down
+/// Best effort only, graceful shutdown (Ctrl+C / SIGTERM) is still the reliable path
+fn install_panic_flush(kvdb: &Arc<BlazeKVDB>) {
+    let kvdb = Arc::downgrade(kvdb);
+    let default_hook = std::panic::take_hook();
+
+    std::panic::set_hook(Box::new(move |info| {
+        default_hook(info);
+
+        if let Some(kvdb) = kvdb.upgrade() {
+            match kvdb.flush_blocking(Duration::from_secs(2)) {
+                Ok(()) => eprintln!("AOF flushed after panic"),
+                Err(e) => eprintln!("AOF flush after panic failed: {}", e),
+            }
+        }
+    }));
+}
+
+/// Run the startup script, logging every failed line with its number
+/// Failed lines only stop startup with startup_script_abort_on_error, a missing file always does
+async fn run_startup_script(
+    kvdb: &BlazeKVDB,
+    path: &Path,
+    abort_on_error: bool,
+) -> BlazeResult<()> {
+    info!("📜 Running startup script: {}", path.display());
+
+    let script = tokio::fs::read_to_string(path).await.map_err(|e| {
+        BlazeError::Server(format!(
+            "Failed to read startup script {}: {}",
+            path.display(),
+            e
+        ))
+    })?;
+
+    let report = kvdb.run_script(&script).await;
+    for failure in &report.errors {
+        warn!("{}:{}: {}", path.display(), failure.line, failure.message);
+    }
+
+    if abort_on_error && !report.errors.is_empty() {
+        return Err(BlazeError::Server(format!(
+            "Startup script {} failed on {} line(s)",
+            path.display(),
+            report.errors.len()
+        )));
+    }
+
+    info!(
+        "✅ Startup script ran {} commands ({} failed)",
+        report.executed,
+        report.errors.len()
+    );
     Ok(())
 }
 
+/// Connect the interactive shell, resolving host names like localhost:6379 first
+async fn run_cli(addr: &str) -> BlazeResult<()> {
+    let resolved = tokio::net::lookup_host(addr)
+        .await?
+        .next()
+        .ok_or_else(|| BlazeError::Config(format!("Could not resolve {}", addr)))?;
+
+    repl::run(resolved)
+        .await
+        .map_err(|e| BlazeError::Server(format!("{}: {}", addr, e)))
+}
+
 /// Setup logging based on configuration
 fn setup_logging(config: &BlazeServerConfig) {
     let log_level = match config.observability.log_level.to_lowercase().as_str() {
@@ -132,17 +251,22 @@ fn setup_logging(config: &BlazeServerConfig) {
 
     let env_filter = EnvFilter::from_default_env().add_directive(log_level.into());
 
-    let subscriber = fmt()
-        .with_env_filter(env_filter)
-        .with_target(false)
+    let layer = fmt::layer()
+        // Targets tell audit events apart from the rest of the log
+        .with_target(config.observability.audit_log)
         .with_thread_ids(false)
         .with_file(false);
 
-    match config.observability.log_format.as_str() {
-        "json" => subscriber.json().init(),
-        "pretty" => subscriber.pretty().init(),
-        _ => subscriber.compact().init(),
-    }
+    let layer = match config.observability.log_format.as_str() {
+        "json" => layer.json().boxed(),
+        "pretty" => layer.pretty().boxed(),
+        _ => layer.compact().boxed(),
+    };
+
+    // Spans of commands left out by trace_sample_rate are dropped, their events kept
+    tracing_subscriber::registry()
+        .with(layer.with_filter(env_filter).with_filter(SampledSpans))
+        .init();
 }
 
 fn generate_config_file(output_path: &str) -> BlazeResult<()> {
@@ -153,7 +277,7 @@ fn generate_config_file(output_path: &str) -> BlazeResult<()> {
     if output_path.ends_with(".toml") {
         config
             .to_toml_file(output_path)
-            .map_err(|e| blazekvdb::error::BlazeError::Config(format!("{}", e).into()))?;
+            .map_err(|e| blazekvdb::error::BlazeError::Config(format!("{}", e)))?;
         info!("✅ Generated TOML config: {}", output_path);
         println!("\n{}", "=".repeat(60));
         println!("Example configuration generated: {}", output_path);
@@ -165,7 +289,7 @@ fn generate_config_file(output_path: &str) -> BlazeResult<()> {
     } else {
         config
             .to_json_file(output_path)
-            .map_err(|e| blazekvdb::error::BlazeError::Config(format!("{}", e).into()))?;
+            .map_err(|e| blazekvdb::error::BlazeError::Config(format!("{}", e)))?;
         info!("✅ Generated JSON config: {}", output_path);
         println!("\n{}", "=".repeat(60));
         println!("Example configuration generated: {}", output_path);
@@ -179,61 +303,43 @@ fn generate_config_file(output_path: &str) -> BlazeResult<()> {
     Ok(())
 }
 
-// Load configuration from file or use details
-fn load_configuration(cli: &Cli) -> BlazeResult<BlazeServerConfig> {
+// Load configuration from file, layering env and CLI overrides on top
+fn load_configuration(cli: &Cli) -> BlazeResult<LayeredConfig> {
     let config_path = &cli.config;
 
     info!("📂 Loading config from: {}", config_path);
 
-    if !Path::new(config_path).exists() {
-        error!("❌ Config file not found: {}", config_path);
-        return Err(blazekvdb::error::BlazeError::Config(
-            format!("Config file not found: {}", config_path).into(),
-        ));
+    let mut builder = BlazeServerConfig::builder().with_file(config_path);
+
+    if cli.use_env {
+        info!("Applying environment variable overrides");
+        builder = builder.with_env();
     }
 
-    let config = if config_path.ends_with(".toml") {
-        BlazeServerConfig::from_toml_file(config_path)
-            .map_err(|e| blazekvdb::error::BlazeError::Config(format!("{}", e).into()))?
-    } else if config_path.ends_with(".json") {
-        BlazeServerConfig::from_json_file(config_path)
-            .map_err(|e| blazekvdb::error::BlazeError::Config(format!("{}", e).into()))?
-    } else {
-        // Try to detect format by content
-        match BlazeServerConfig::from_toml_file(config_path) {
-            Ok(cfg) => cfg,
-            Err(_) => BlazeServerConfig::from_json_file(config_path)
-                .map_err(|e| blazekvdb::error::BlazeError::Config(format!("{}", e).into()))?,
-        }
-    };
+    let layered = builder
+        .with_cli(CliOverrides {
+            bind: cli.bind.clone(),
+            no_persistence: cli.no_persistence,
+            debug: cli.debug,
+            exec: cli.exec.clone(),
+            restore_from: cli.restore_from.clone(),
+            restore_snapshot_only: cli.restore_snapshot_only,
+        })
+        .build()
+        .map_err(|e| {
+            error!("❌ {}", e);
+            BlazeError::Config(format!("{}", e))
+        })?;
 
     info!("✅ Configuration loaded successfully");
-    Ok(config)
+    Ok(layered)
 }
 
-// Apply CLI argument overrides to configuration
-fn apply_cli_overrides(config: &mut BlazeServerConfig, cli: &Cli) -> BlazeResult<()> {
-    // Overrides bind address
-    if let Some(ref bind_addr) = cli.bind {
-        info!("🔧 Overriding bind address: {}", bind_addr);
-        config.server.bind_addr = bind_addr.parse().map_err(|e| {
-            BlazeError::Config(format!("Invalid bind address '{}': {}", bind_addr, e))
-        })?;
-    }
-
-    // Override persistence
-    if cli.no_persistence {
-        info!("🔧 Disabling persistence (CLI override)");
-        config.persistence.enabled = false;
-    }
-
-    // Override log level
-    if cli.debug {
-        info!("🔧 Enabling debug logging (CLI override)");
-        config.observability.log_level = "debug".to_string();
+/// Print which layer overrode each config field
+fn print_config_overrides(layered: &LayeredConfig) {
+    for o in &layered.overrides {
+        info!("🔧 {} overridden by {}", o.field, o.source);
     }
-
-    Ok(())
 }
 
 /// Print application banner
@@ -261,10 +367,27 @@ fn print_config_summary(config: &BlazeServerConfig) {
     info!("  ┌─ Server");
     info!("  │  • Bind address: {}", config.server.bind_addr);
     info!("  │  • Max connections: {}", config.server.max_connections);
+    info!(
+        "  │  • Listen backlog: {} ({} accept tasks)",
+        config.server.listen_backlog, config.server.accept_tasks
+    );
+    info!("  │  • TCP_NODELAY: {}", config.server.tcp_nodelay);
     info!(
         "  │  • Connection timeout: {}s",
         config.server.connection_timeout
     );
+    if config.server.idle_timeout > 0 {
+        info!(
+            "  │  • Idle timeout: {}s (checked every {}s)",
+            config.server.idle_timeout, config.server.idle_check_interval
+        );
+    }
+    if config.server.notify_keyspace_events {
+        info!("  │  • Keyspace events: enabled");
+    }
+    if let Some(ref script) = config.server.startup_script {
+        info!("  │  • Startup script: {}", script.display());
+    }
     info!(
         "  │  • Worker threads: {}",
         if config.server.worker_threads == 0 {
@@ -280,12 +403,23 @@ fn print_config_summary(config: &BlazeServerConfig) {
         config.storage.max_memory / 1024 / 1024
     );
     info!("  │  • Shard count: {}", config.storage.shard_count);
+    info!("  │  • Databases: {}", config.storage.databases);
 
     info!("  ├─ Persistence");
     info!("  │  • Enabled: {}", config.persistence.enabled);
     if config.persistence.enabled {
         info!("  │  • AOF path: {}", config.persistence.aof_path.display());
         info!("  │  • Fsync policy: {:?}", config.persistence.fsync_policy);
+        info!(
+            "  │  • Fsync on shutdown: {}",
+            config.persistence.fsync_on_shutdown
+        );
+        if config.persistence.max_aof_size > 0 {
+            info!(
+                "  │  • AOF rotation: {} bytes",
+                config.persistence.max_aof_size
+            );
+        }
         info!("  │  • Snapshots: {}", config.persistence.snapshot_enabled);
         if config.persistence.snapshot_enabled {
             info!(
@@ -302,16 +436,25 @@ fn print_config_summary(config: &BlazeServerConfig) {
     info!("  ├─ Observability");
     info!("  │  • Log level: {}", config.observability.log_level);
     info!("  │  • Log format: {}", config.observability.log_format);
+    info!(
+        "  │  • Trace sample rate: {}",
+        config.observability.trace_sample_rate
+    );
+    info!("  │  • Audit log: {}", config.observability.audit_log);
     info!("  │  • Metrics: {}", config.observability.metrics_enabled);
     info!(
         "  │  • Health checks: {}",
         config.observability.health_check_enabled
     );
 
-    if config.security.tls_enabled || config.security.require_auth {
+    if config.security.tls_enabled
+        || config.security.require_auth
+        || !config.security.users.is_empty()
+    {
         info!("  └─ Security");
         info!("     • TLS: {}", config.security.tls_enabled);
         info!("     • Auth required: {}", config.security.require_auth);
+        info!("     • Users: {}", config.security.users.len());
     } else {
         info!("  └─ Security: Disabled");
     }
@@ -332,18 +475,18 @@ async fn print_startup_info(config: &BlazeServerConfig, kvdb: &BlazeKVDB) {
     }
 
     // Persistence stats
-    if let Some(persistence_stats) = kvdb.persistence_stats().await {
-        if persistence_stats.aof_enabled {
-            if let Some(ref aof_stats) = persistence_stats.aof_stats {
-                info!("💾 Persistence Statistics:");
-                info!("  • AOF operations: {}", aof_stats.operations_logged);
-                info!(
-                    "  • AOF size: {} MB",
-                    aof_stats.file_size_bytes / 1024 / 1024
-                );
-                info!("  • Snapshots: {}", persistence_stats.snapshot_count);
-            }
-        }
+    if let Some(persistence_stats) = kvdb.persistence_stats().await
+        && persistence_stats.aof_enabled
+        && let Some(ref aof_stats) = persistence_stats.aof_stats
+    {
+        info!("💾 Persistence Statistics:");
+        info!("  • AOF operations: {}", aof_stats.operations_logged);
+        info!(
+            "  • AOF size: {} MB ({} rotated segments)",
+            aof_stats.total_size_bytes / 1024 / 1024,
+            aof_stats.current_segment
+        );
+        info!("  • Snapshots: {}", persistence_stats.snapshot_count);
     }
 
     println!("\n{}", "=".repeat(70));
@@ -357,71 +500,192 @@ async fn print_startup_info(config: &BlazeServerConfig, kvdb: &BlazeKVDB) {
         config.server.bind_addr.port()
     );
 
-    if config.observability.health_check_enabled {
-        if let Some(addr) = config.observability.health_check_addr {
-            println!("  • Health check: http://{}/health", addr);
-            println!("  • Stats: http://{}/stats", addr);
-        }
+    if config.observability.health_check_enabled
+        && let Some(addr) = config.observability.health_check_addr
+    {
+        println!("  • Health check: http://{}/health", addr);
+        println!("  • Stats: http://{}/stats", addr);
     }
 
-    if config.observability.metrics_enabled {
-        if let Some(addr) = config.observability.metrics_addr {
-            println!("  • Metrics: http://{}/metrics", addr);
-        }
+    if config.observability.metrics_enabled
+        && let Some(addr) = config.observability.metrics_addr
+    {
+        println!("  • Metrics: http://{}/metrics", addr);
     }
 
     println!("\n💡 Useful Commands:");
     println!("  • SET key value    - Store a key-value pair");
+    println!("  • SETEX key s val  - Store a pair expiring after s seconds");
+    println!("  • SETCHUNK key n   - Store the n raw bytes sent after this line");
     println!("  • GET key          - Retrieve a value");
-    println!("  • DELETE key       - Remove a key");
-    println!("  • EXISTS key       - Check if key exists");
+    println!("  • GETVER key       - Retrieve a value and its version");
+    println!("  • SETVER k val ver - Store a value only if the key is still at version ver");
+    println!("  • GETRANGE k s e   - Retrieve bytes s..=e of a value");
+    println!("  • SETRANGE k o val - Overwrite bytes starting at offset o");
+    println!("  • SETBIT k o 0|1   - Set or clear bit o, returns the old bit");
+    println!("  • GETBIT k o       - Read bit o");
+    println!("  • BITCOUNT k       - Count set bits in a value");
+    println!("  • INCRBYFLOAT k d  - Add d to a float value, returns the result");
+    println!("  • EVAL CAS k old new - Replace a value only if it still equals old");
+    println!("  • EVAL ADDMAX k n  - Store the larger of the current number and n");
+    println!("  • DEL k [k ...]    - Remove keys, returns how many existed");
+    println!("  • DELPREFIX prefix - Remove every key starting with prefix (--all for every key)");
+    println!("  • EXISTS k [k ...] - Count how many keys exist");
+    println!("  • SADD k m [m ...] - Add members to a set");
+    println!("  • SREM k m [m ...] - Remove members from a set");
+    println!("  • SISMEMBER k m    - Check set membership");
+    println!("  • SMEMBERS k       - List set members");
+    println!("  • SCARD k          - Count set members");
+    println!("  • LPUSH/RPUSH k i [i ...] - Push items onto the head/tail of a list");
+    println!("  • BLPOP k secs     - Pop a list's head, waiting up to secs (0 = forever)");
     println!("  • SCAN prefix      - List keys with prefix");
+    println!("  • SCAN prefix SORTED [COUNT n] - First n keys in order (walks every match)");
+    println!("  • OBJECT IDLETIME k - Seconds since key was last accessed");
+    println!("  • OBJECT FREQ k    - Decaying access-frequency counter of a key (LFU)");
+    println!("  • OBJECT ENCODING k - Internal encoding of a key's value");
+    println!("  • MEMORY USAGE k   - Bytes a key counts for against max_memory");
+    println!("  • MEMORY STATS     - Keyspace, overhead and per-shard memory totals");
+    println!("  • TOUCH k [k ...]  - Mark keys as recently used");
+    println!("  • EXPIRE/PEXPIRE k n - Expire a key in n seconds/milliseconds");
+    println!("  • EXPIREAT/PEXPIREAT k t - Expire a key at a unix time in seconds/milliseconds");
+    println!("  • TTL/PTTL k       - Remaining time to live in seconds/milliseconds");
+    println!("  • SELECT index     - Switch the connection to another database");
+    println!("  • AUTH [user] pass - Log the connection in as a user");
+    println!("  • SUBSCRIBE ch [ch ...] - Receive messages, e.g. __keyevent@0__:set");
+    println!("  • UNSUBSCRIBE [ch ...] - Stop receiving messages (all channels by default)");
+    println!("  • RESET            - Restore the connection's initial state (db, auth, encoding)");
+    println!("  • FLUSHDB          - Remove every key of the selected database");
     println!("  • STATS            - Show database statistics");
+    println!("  • INFO [section]   - Show stats, persistence state and key counts");
+    println!("  • METRICS          - Show server metrics as a JSON object");
     println!("  • SAVE             - Trigger manual snapshot");
-    println!("  • PING             - Check server health");
+    println!("  • SNAPSHOT VERIFY [f] - Check a snapshot loads and matches its checksum");
+    println!("  • BGREWRITEAOF     - Compact the AOF in the background");
+    println!(
+        "  • RELOCATE [AOF p] [SNAPSHOTS d] [COPY] - Move persistence files (needs allow_relocate)"
+    );
+    println!("  • PROTO JSON|TEXT  - Switch connection protocol");
+    println!("  • ENCODING RAW|BASE64 - Send values as raw bytes or base64");
+    println!("  • READONLY ON|OFF  - Refuse all writes (maintenance mode)");
+    println!("  • CONFIG GET|SET p - Inspect or tune runtime settings");
+    println!("  • ID corr-id cmd   - Run cmd with corr-id attached to its log lines");
+    println!("  • DEBUG SET-ACTIVE-EXPIRE 0|1 - Pause or resume the expiry sweeper");
+    println!("  • DEBUG RELOAD - Reload the dataset through persistence (needs debug_commands)");
+    println!("  • HELLO [protover] - Show server name, version and protocols");
+    println!("  • PING [msg]       - Reply PONG, or msg when given");
+    println!("  • ECHO msg         - Reply with msg, for connection checks");
 
     println!("\n{}", "=".repeat(70));
     println!("Press Ctrl+C to shutdown gracefully\n");
 }
 
 /// Start auxiliary services (health check, metrics)
-async fn start_auxiliary_services(config: &BlazeServerConfig, storage: Arc<dyn StorageEngine>) {
-    // Start health check server
-    if config.observability.health_check_enabled {
-        if let Some(health_addr) = config.observability.health_check_addr {
-            info!("🏥 Starting health check server on http://{}", health_addr);
-            start_health_server(health_addr, storage.clone()).await;
+async fn start_auxiliary_services(config: &BlazeServerConfig, kvdb: Arc<BlazeKVDB>) {
+    // Bind every endpoint up front so failures are known before anything is served
+    let mut failed_endpoints = Vec::new();
+
+    let health_listener = if config.observability.health_check_enabled
+        && let Some(health_addr) = config.observability.health_check_addr
+    {
+        let listener = bind_auxiliary("health check", health_addr).await;
+        if listener.is_none() {
+            failed_endpoints.push("health");
         }
+        listener
+    } else {
+        None
+    };
+
+    let metrics_listener = if config.observability.metrics_enabled
+        && let Some(metrics_addr) = config.observability.metrics_addr
+    {
+        let listener = bind_auxiliary("metrics", metrics_addr).await;
+        if listener.is_none() {
+            failed_endpoints.push("metrics");
+        }
+        listener
+    } else {
+        None
+    };
+
+    // Start health check server
+    if let Some(listener) = health_listener {
+        info!(
+            "🏥 Starting health check server on http://{}",
+            listener
+                .local_addr()
+                .map_or_else(|e| e.to_string(), |a| a.to_string())
+        );
+        start_health_server(listener, kvdb.clone(), failed_endpoints.clone());
     }
 
     // Start metrics server
-    if config.observability.metrics_enabled {
-        if let Some(metrics_addr) = config.observability.metrics_addr {
-            info!(
-                "📊 Starting metrics server on http://{}/metrics",
-                metrics_addr
-            );
-            start_metrics_server(metrics_addr, storage.clone()).await;
+    if let Some(listener) = metrics_listener {
+        info!(
+            "📊 Starting metrics server on http://{}/metrics",
+            listener
+                .local_addr()
+                .map_or_else(|e| e.to_string(), |a| a.to_string())
+        );
+        start_metrics_server(listener, kvdb.storage(), kvdb.dispatcher().client_traffic());
+    }
+
+    if !failed_endpoints.is_empty() {
+        warn!(
+            "⚠️ Running degraded, unavailable endpoints: {}",
+            failed_endpoints.join(", ")
+        );
+    }
+}
+
+/// Bind an auxiliary listener, logging instead of panicking on failure
+async fn bind_auxiliary(name: &str, addr: std::net::SocketAddr) -> Option<TcpListener> {
+    match TcpListener::bind(addr).await {
+        Ok(listener) => Some(listener),
+        Err(e) => {
+            error!("❌ Failed to bind {} server on {}: {}", name, addr, e);
+            None
         }
     }
 }
 
 /// Start health check HTTP server
-async fn start_health_server(addr: std::net::SocketAddr, storage: Arc<dyn StorageEngine>) {
+fn start_health_server(
+    listener: TcpListener,
+    kvdb: Arc<BlazeKVDB>,
+    failed_endpoints: Vec<&'static str>,
+) {
     let health = warp::path("health").map(move || {
+        // Report degraded when a sibling observability endpoint failed to start
+        let status = if failed_endpoints.is_empty() {
+            "healthy"
+        } else {
+            "degraded"
+        };
+
         warp::reply::json(&serde_json::json!({
-            "status": "healthy",
+            "status": status,
+            "failed_endpoints": failed_endpoints,
             "timestamp": chrono::Utc::now().to_rfc3339(),
             "version": env!("CARGO_PKG_VERSION"),
         }))
     });
 
     let ready = warp::path("ready").and_then({
-        let storage = storage.clone();
+        let kvdb = kvdb.clone();
         move || {
-            let storage = storage.clone();
+            let kvdb = kvdb.clone();
             async move {
-                match storage.health_check().await {
+                if kvdb.is_recovering() {
+                    return Ok::<_, warp::Rejection>(warp::reply::json(&serde_json::json!({
+                        "status": "not_ready",
+                        "error": "recovery in progress",
+                        "timestamp": chrono::Utc::now().to_rfc3339(),
+                    })));
+                }
+
+                // Covers persistence too, so a failed AOF writer reports not ready
+                match kvdb.health_check().await {
                     Ok(_) => Ok::<_, warp::Rejection>(warp::reply::json(&serde_json::json!({
                         "status": "ready",
                         "timestamp": chrono::Utc::now().to_rfc3339(),
@@ -437,17 +701,35 @@ async fn start_health_server(addr: std::net::SocketAddr, storage: Arc<dyn Storag
     });
 
     let stats = warp::path("stats").and_then({
-        let storage = storage.clone();
+        let kvdb = kvdb.clone();
         move || {
-            let storage = storage.clone();
+            let kvdb = kvdb.clone();
             async move {
-                match storage.stats().await {
+                // AOF rewrite status lets operators follow a BGREWRITEAOF
+                let persistence = kvdb.persistence_stats().await.map(|stats| {
+                    serde_json::json!({
+                        "aof_size_bytes": stats.aof_stats.as_ref().map(|aof| aof.total_size_bytes),
+                        "aof_current_segment": stats.aof_stats.as_ref().map(|aof| aof.current_segment),
+                        "aof_rewrite_in_progress": stats.aof_rewrite_in_progress,
+                        "aof_rewrites_completed": stats.aof_rewrites_completed,
+                        "aof_last_rewrite_error": stats.aof_last_rewrite_error,
+                        "snapshot_count": stats.snapshot_count,
+                    })
+                });
+
+                match kvdb.storage_stats().await {
                     Ok(stats) => Ok(warp::reply::json(&serde_json::json!({
                         "total_keys": stats.total_keys,
                         "memory_usage_bytes": stats.memory_usage,
                         "memory_usage_mb": stats.memory_usage / 1024 / 1024,
                         "hit_rate": stats.hit_rate,
                         "total_operations": stats.total_operations,
+                        "keyspace_hits": stats.keyspace_hits,
+                        "keyspace_misses": stats.keyspace_misses,
+                        "evicted_keys": stats.evicted_keys,
+                        "expires": stats.expires,
+                        "expired_keys": stats.expired_keys,
+                        "persistence": persistence,
                         "timestamp": chrono::Utc::now().to_rfc3339(),
                     }))),
                     Err(_) => Err(warp::reject()),
@@ -458,15 +740,22 @@ async fn start_health_server(addr: std::net::SocketAddr, storage: Arc<dyn Storag
 
     let routes = health.or(ready).or(stats);
 
-    tokio::spawn(warp::serve(routes).run(addr));
+    tokio::spawn(warp::serve(routes).incoming(listener).run());
 }
 
 /// Start metrics HTTP server
-async fn start_metrics_server(addr: std::net::SocketAddr, storage: Arc<dyn StorageEngine>) {
+fn start_metrics_server(
+    listener: TcpListener,
+    storage: Arc<dyn StorageEngine>,
+    traffic: Arc<ClientTraffic>,
+) {
     let metrics = warp::path("metrics").and_then({
         let storage = storage.clone();
         move || {
             let storage = storage.clone();
+            let reaped = traffic.reaped_connections.load(Ordering::Relaxed);
+            let accept_errors = traffic.accept_errors.load(Ordering::Relaxed);
+            let accept_resource_errors = traffic.accept_resource_errors.load(Ordering::Relaxed);
             async move {
                 match storage.stats().await {
                     Ok(stats) => {
@@ -487,6 +776,18 @@ async fn start_metrics_server(addr: std::net::SocketAddr, storage: Arc<dyn Stora
                                  # TYPE blaze_kvdb_operations_total counter\n\
                                  blaze_kvdb_operations_total {}\n\
                                  \n\
+                                 # HELP blaze_kvdb_reaped_connections_total Idle connections closed by the reaper\n\
+                                 # TYPE blaze_kvdb_reaped_connections_total counter\n\
+                                 blaze_kvdb_reaped_connections_total {}\n\
+                                 \n\
+                                 # HELP blaze_kvdb_accept_errors_total Failed accepts of incoming connections\n\
+                                 # TYPE blaze_kvdb_accept_errors_total counter\n\
+                                 blaze_kvdb_accept_errors_total {}\n\
+                                 \n\
+                                 # HELP blaze_kvdb_accept_resource_errors_total Accepts failed for lack of file descriptors or memory\n\
+                                 # TYPE blaze_kvdb_accept_resource_errors_total counter\n\
+                                 blaze_kvdb_accept_resource_errors_total {}\n\
+                                 \n\
                                  # HELP blaze_kvdb_up Server uptime indicator\n\
                                  # TYPE blaze_kvdb_up gauge\n\
                                  blaze_kvdb_up 1\n",
@@ -494,6 +795,9 @@ async fn start_metrics_server(addr: std::net::SocketAddr, storage: Arc<dyn Stora
                             stats.memory_usage,
                             stats.hit_rate,
                             stats.total_operations,
+                            reaped,
+                            accept_errors,
+                            accept_resource_errors,
                         );
 
                         Ok::<_, warp::Rejection>(warp::reply::with_header(
@@ -512,5 +816,5 @@ async fn start_metrics_server(addr: std::net::SocketAddr, storage: Arc<dyn Stora
         }
     });
 
-    tokio::spawn(warp::serve(metrics).run(addr));
+    tokio::spawn(warp::serve(metrics).incoming(listener).run());
 }
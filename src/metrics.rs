@@ -5,6 +5,7 @@ use prometheus::{
 pub struct RespLiteMetrics {
     // Counter - total number which always increase (total number of requests)
     pub requests_total: Counter,
+    pub reaped_connections: Counter, // Closed by the idle reaper
 
     // Gauge - number which can go up and down (current number of active connections)
     pub active_connections: Gauge,
@@ -16,10 +17,21 @@ pub struct RespLiteMetrics {
     registry: Registry,
 }
 
+impl Default for RespLiteMetrics {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl RespLiteMetrics {
     pub fn new() -> Self {
         let registry = Registry::new();
         let requests_total = Counter::new("kv_requests_total", "Total requests received").unwrap();
+        let reaped_connections = Counter::new(
+            "kv_reaped_connections_total",
+            "Idle connections closed by the reaper",
+        )
+        .unwrap();
         let active_connections = Gauge::new("kv_active_connections", "Active connections").unwrap();
         let request_duration = Histogram::with_opts(
             HistogramOpts::new("kv_request_duration_seconds", "Request duration in seconds")
@@ -29,6 +41,9 @@ impl RespLiteMetrics {
         let keys_total = Gauge::new("kv_keys_total", "Total keys stored").unwrap();
 
         registry.register(Box::new(requests_total.clone())).unwrap();
+        registry
+            .register(Box::new(reaped_connections.clone()))
+            .unwrap();
         registry
             .register(Box::new(active_connections.clone()))
             .unwrap();
@@ -39,6 +54,7 @@ impl RespLiteMetrics {
 
         Self {
             requests_total,
+            reaped_connections,
             active_connections,
             request_duration,
             keys_total,
@@ -0,0 +1,16 @@
+use std::io::Write;
+
+use flate2::{Compression, write::GzEncoder};
+
+use crate::commands::compress::CompressionAlgorithm;
+
+// Compress a payload with the negotiated algorithm
+pub fn compress(algorithm: CompressionAlgorithm, data: &[u8]) -> std::io::Result<Vec<u8>> {
+    match algorithm {
+        CompressionAlgorithm::Gzip => {
+            let mut encoder = GzEncoder::new(Vec::new(), Compression::fast());
+            encoder.write_all(data)?;
+            encoder.finish()
+        }
+    }
+}
@@ -1,8 +1,58 @@
+use std::{
+    borrow::Cow,
+    collections::{HashMap, HashSet},
+};
+
 use thiserror::Error;
 
+use crate::protocol::compression;
+
 use crate::commands::{
-    Command, CommandResponse, delete::DeleteCommand, exist::ExistCommand, get::GetCommand,
-    scan::ScanCommand, set::SetCommand,
+    Command, CommandResponse, KeyLimits,
+    auth::AuthCommand,
+    bitcount::BitCountCommand,
+    blpop::BLPopCommand,
+    compress::{CompressCommand, CompressionAlgorithm, DEFAULT_COMPRESS_THRESHOLD},
+    config::{ConfigCommand, ConfigSubcommand},
+    debug::DebugCommand,
+    delete::DeleteCommand,
+    delprefix::DelPrefixCommand,
+    echo::EchoCommand,
+    encoding::{EncodingCommand, ValueEncoding},
+    eval::{ATOMIC_OPS, AtomicOp, EvalCommand},
+    exist::ExistCommand,
+    expire::ExpireCommand,
+    get::GetCommand,
+    getbit::GetBitCommand,
+    getrange::GetRangeCommand,
+    getver::GetVerCommand,
+    hello::HelloCommand,
+    incrbyfloat::IncrByFloatCommand,
+    info::InfoCommand,
+    memory::MemoryCommand,
+    object::{ObjectCommand, ObjectSubcommand},
+    ping::PingCommand,
+    proto::{ProtoCommand, ProtocolMode},
+    push::PushCommand,
+    readonly::ReadOnlyCommand,
+    relocate::RelocateCommand,
+    sadd::SAddCommand,
+    scan::ScanCommand,
+    scard::SCardCommand,
+    select::SelectCommand,
+    set::SetCommand,
+    setbit::SetBitCommand,
+    setchunk::SetChunkCommand,
+    setrange::SetRangeCommand,
+    setver::SetVerCommand,
+    sismember::SIsMemberCommand,
+    smembers::SMembersCommand,
+    snapshot::{SnapshotCommand, SnapshotSubcommand},
+    srem::SRemCommand,
+    subscribe::{SubscribeCommand, UnsubscribeCommand},
+    touch::TouchCommand,
+    ttl::TtlCommand,
+    wait::WaitCommand,
 };
 
 #[derive(Debug, Error)]
@@ -16,35 +66,309 @@ pub enum ProtocolError {
     #[error("Missing arguments for command: {0}")]
     MissingArguments(String),
 
+    #[error("syntax error, unsupported option {0}")]
+    UnsupportedOption(String),
+
+    #[error("Value too large (max {0} bytes)")]
+    ValueTooLarge(usize),
+
     #[error("Base64 decode error: {0}")]
     Base64Error(#[from] base64::DecodeError),
 
     #[error("JSON serialization error: {0}")]
     JsonError(#[from] serde_json::Error),
+
+    #[error("Compression error: {0}")]
+    Compression(#[from] std::io::Error),
+}
+
+// Redis SET options we don't implement. Left unquoted after a value they would be joined
+// into it, so `SET k v KEEPTTL` would quietly store "v KEEPTTL"
+pub const UNSUPPORTED_SET_OPTIONS: &[&str] = &[
+    "EX", "PX", "EXAT", "PXAT", "NX", "XX", "KEEPTTL", "GET", "IDLE",
+];
+
+// Line the server keepalive sends to a silent connection ({"status":"keepalive"} in JSON
+// mode). Any line from the client answers it, the same word sent back is dropped unanswered
+pub const KEEPALIVE: &str = "KEEPALIVE";
+
+// Every command name parse_command accepts, what security.renamed_commands may refer to
+pub const COMMAND_NAMES: &[&str] = &[
+    "GET",
+    "SET",
+    "SETEX",
+    "GETVER",
+    "SETVER",
+    "SETCHUNK",
+    "GETRANGE",
+    "SETRANGE",
+    "SETBIT",
+    "GETBIT",
+    "EVAL",
+    "INCRBYFLOAT",
+    "BITCOUNT",
+    "DELETE",
+    "DEL",
+    "DELPREFIX",
+    "EXIST",
+    "EXISTS",
+    "SADD",
+    "SREM",
+    "LPUSH",
+    "RPUSH",
+    "BLPOP",
+    "SISMEMBER",
+    "SMEMBERS",
+    "SCARD",
+    "SCAN",
+    "OBJECT",
+    "MEMORY",
+    "EXPIRE",
+    "PEXPIRE",
+    "EXPIREAT",
+    "PEXPIREAT",
+    "TTL",
+    "PTTL",
+    "TOUCH",
+    "COMPRESS",
+    "WAIT",
+    "PROTO",
+    "ENCODING",
+    "READONLY",
+    "CONFIG",
+    "SNAPSHOT",
+    "RELOCATE",
+    "SELECT",
+    "AUTH",
+    "SUBSCRIBE",
+    "UNSUBSCRIBE",
+    "INFO",
+    "DEBUG",
+    "HELLO",
+    "METRICS",
+    "RESET",
+    "FLUSHDB",
+    "BGREWRITEAOF",
+    "STATS",
+    "ECHO",
+    "PING",
+];
+
+// Names in COMMAND_NAMES that run a command reporting another CommandHandler::name()
+const COMMAND_ALIASES: &[(&str, &str)] =
+    &[("DEL", "DELETE"), ("EXISTS", "EXIST"), ("SETEX", "SET")];
+
+// The CommandHandler::name() of what `name` runs, None if parse_command doesn't know it
+// ACL allow-lists match handler names, so an alias there must become the command it runs
+pub fn handler_name(name: &str) -> Option<&'static str> {
+    let name = name.to_uppercase();
+    if let Some((_, handler)) = COMMAND_ALIASES.iter().find(|(alias, _)| *alias == name) {
+        return Some(handler);
+    }
+    COMMAND_NAMES.iter().find(|known| **known == name).copied()
+}
+
+// Operator renames of commands, like Redis' rename-command: incoming names mapped to the
+// command they run, and the original names that no longer run anything
+// Built from security.renamed_commands (original name -> new name, empty = disabled)
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct CommandRenames {
+    aliases: HashMap<String, String>,
+    hidden: HashSet<String>,
+}
+
+impl CommandRenames {
+    pub fn new(renamed: &HashMap<String, String>) -> Self {
+        let mut renames = Self::default();
+        for (original, new_name) in renamed {
+            let original = original.to_uppercase();
+            if !new_name.is_empty() {
+                renames
+                    .aliases
+                    .insert(new_name.to_uppercase(), original.clone());
+            }
+            renames.hidden.insert(original);
+        }
+        renames
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.hidden.is_empty()
+    }
+
+    // The command an incoming name runs, refusing original names that were renamed or disabled
+    fn resolve(&self, name: &str) -> Result<String, ProtocolError> {
+        let name = name.to_uppercase();
+        if let Some(original) = self.aliases.get(&name) {
+            return Ok(original.clone());
+        }
+        if self.hidden.contains(&name) {
+            return Err(ProtocolError::UnknownCommand(name));
+        }
+        Ok(name)
+    }
+
+    // Whether a command reporting `name` may still run, for JSON commands that have no
+    // command name to rename
+    pub fn allows(&self, name: &str) -> bool {
+        !self.hidden.contains(name)
+    }
+}
+
+// Per-connection response encoding options
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ResponseOptions {
+    // Compress VALUE payloads of at least `threshold` bytes (text mode only)
+    pub compression: Option<(CompressionAlgorithm, usize)>,
+
+    // Text or JSON framing
+    pub mode: ProtocolMode,
+
+    // VALUE framing in text mode
+    pub encoding: ValueEncoding,
 }
 
 // Simple text-based protocol parser
 // Protocol format:
-// - GET key
+// - GET key (a missing key replies as protocol.get_missing_behavior says: NIL by default)
 // - SET key value_base64
-// - DELETE key
+// - SETEX key seconds value_base64
+// - SETCHUNK key total_len, then exactly total_len raw bytes
+// - GETVER key
+// - SETVER key value_base64 expected_version (0 = key must not exist)
+// - GETRANGE key start end
+// - SETRANGE key offset value_base64
+// - SETBIT key offset 0|1
+// - GETBIT key offset
+// - BITCOUNT key
+// - INCRBYFLOAT key delta
+// - EVAL CAS key expected new | EVAL ADDMAX key n
+// - DELETE key [key ...]
+// - DELPREFIX prefix | DELPREFIX --all
 // - EXIST key
-// - SCAN prefix
+// - EXISTS key [key ...]
+// - SADD key member [member ...]
+// - SREM key member [member ...]
+// - SISMEMBER key member
+// - SMEMBERS key
+// - SCARD key
+// - LPUSH key item [item ...] | RPUSH key item [item ...]
+// - BLPOP key timeout_secs (0 waits forever)
+// - SCAN prefix [SORTED] [COUNT n]
+// - OBJECT IDLETIME key | OBJECT FREQ key | OBJECT ENCODING key
+// - MEMORY USAGE key | MEMORY STATS
+// - TOUCH key [key ...]
+// - EXPIRE key secs | PEXPIRE key millis
+// - EXPIREAT key unix_secs | PEXPIREAT key unix_millis
+// - TTL key | PTTL key
+// - COMPRESS ON gzip [min_bytes] | COMPRESS OFF
+// - WAIT numreplicas timeout_ms
+// - PROTO TEXT | PROTO JSON
+// - ENCODING RAW | ENCODING BASE64
+// - READONLY ON | READONLY OFF
+// - CONFIG GET param | CONFIG SET param value
+// - SNAPSHOT VERIFY [file]
+// - RELOCATE [AOF path] [SNAPSHOTS dir] [COPY]
+// - SELECT index
+// - AUTH [username] password
+// - SUBSCRIBE channel [channel ...]
+// - UNSUBSCRIBE [channel ...]
+// - INFO [section]
+// - DEBUG SET-ACTIVE-EXPIRE 0|1
+// - DEBUG RELOAD
+// - HELLO [protover]
+// - METRICS
+// - RESET
+// - FLUSHDB
+// - BGREWRITEAOF
 // - STATS
-// - PING
+// - ECHO message_base64
+// - PING [message_base64]
+// - ID corr_id <any command above>, tagging the request's log lines with corr_id
+//
+// Arguments are separated by whitespace. A "double quoted" argument may contain
+// whitespace, with \" and \\ as escapes, and is taken literally rather than base64
+// decoded. Quotes inside an unquoted word are ordinary characters, and the command
+// name itself is never quoted.
 
 pub struct ProtocolParser;
 
+// One argument of a command line
+struct Token<'a> {
+    text: Cow<'a, str>,
+    quoted: bool,
+}
+
+// Upper bound on words in a single command line, keeps pathological input cheap to reject
+pub const MAX_ARGUMENTS: usize = 1024 * 1024;
+
+// Longest id accepted by the `ID <correlation-id> command...` prefix
+pub const MAX_CORRELATION_ID_LEN: usize = 128;
+
 impl ProtocolParser {
+    // Split off an optional `ID <correlation-id>` prefix, which works in either protocol
+    // mode. The id is one printable word tagging the request's tracing span, the rest of
+    // the line is the command itself
+    pub fn split_correlation_id(message: &str) -> Result<(Option<&str>, &str), ProtocolError> {
+        let trimmed = message.trim_start();
+        let (name, rest) = trimmed
+            .split_once(char::is_whitespace)
+            .unwrap_or((trimmed, ""));
+        if !name.eq_ignore_ascii_case("ID") {
+            return Ok((None, message));
+        }
+
+        let rest = rest.trim_start();
+        let (id, command) = rest.split_once(char::is_whitespace).unwrap_or((rest, ""));
+        if id.is_empty() || command.trim().is_empty() {
+            return Err(ProtocolError::MissingArguments(
+                "ID requires a correlation id and a command".to_string(),
+            ));
+        }
+
+        if id.len() > MAX_CORRELATION_ID_LEN
+            || id.starts_with('"')
+            || !id.chars().all(|c| c.is_ascii_graphic())
+        {
+            return Err(ProtocolError::InvalidFormat(format!(
+                "Invalid correlation id (printable ASCII, max {} characters)",
+                MAX_CORRELATION_ID_LEN
+            )));
+        }
+
+        Ok((Some(id), command))
+    }
+
     // Parse incoming message into Command
     pub fn parse_command(message: &str) -> Result<Command, ProtocolError> {
-        let message = message.trim();
-        if message.is_empty() {
+        Self::parse_command_with(message, &KeyLimits::default())
+    }
+
+    // Parse with the server's limits, so oversized values are refused before being decoded
+    pub fn parse_command_with(message: &str, limits: &KeyLimits) -> Result<Command, ProtocolError> {
+        Self::parse_command_renamed(message, limits, &CommandRenames::default())
+    }
+
+    // Parse with the server's limits and command renames
+    pub fn parse_command_renamed(
+        message: &str,
+        limits: &KeyLimits,
+        renames: &CommandRenames,
+    ) -> Result<Command, ProtocolError> {
+        let tokens = Self::tokenize(message)?;
+        let parts: Vec<&str> = tokens.iter().map(|token| token.text.as_ref()).collect();
+        let Some(name) = parts.first() else {
             return Err(ProtocolError::InvalidFormat("Empty command".to_string()));
+        };
+
+        // A leading quote is how JSON mode recognizes a JSON string, keep the modes distinct
+        if tokens[0].quoted {
+            return Err(ProtocolError::InvalidFormat(
+                "Command name can't be quoted".to_string(),
+            ));
         }
 
-        let parts: Vec<&str> = message.split_whitespace().collect();
-        let command = parts[0].to_uppercase();
+        let command = renames.resolve(name)?;
 
         match command.as_str() {
             "GET" => {
@@ -64,34 +388,263 @@ impl ProtocolParser {
                 }
 
                 let key = parts[1].to_string();
+                Self::reject_set_options(&tokens[2..])?;
+                let value = Self::parse_value(&tokens[2..], limits)?;
 
-                // Handle value - could be base64 encoded or plain text
-                let value = if parts.len() == 3 {
-                    // Single value part - try base64 first, fallback to plain text
-                    match base64::Engine::decode(
-                        &base64::engine::general_purpose::STANDARD,
-                        parts[2],
-                    ) {
-                        Ok(decoded) => decoded,
-                        Err(_) => parts[2].as_bytes().to_vec(), // Plain text fallback
+                Ok(Command::Set(SetCommand::new(key, value)))
+            }
+
+            "SETEX" => {
+                if parts.len() < 4 {
+                    return Err(ProtocolError::MissingArguments(
+                        "SETEX requires key, seconds and value".to_string(),
+                    ));
+                }
+
+                let key = parts[1].to_string();
+                let ttl = parts[2].parse::<u64>().map_err(|_| {
+                    ProtocolError::InvalidFormat(format!("Invalid TTL seconds: {}", parts[2]))
+                })?;
+                Self::reject_set_options(&tokens[3..])?;
+                let value = Self::parse_value(&tokens[3..], limits)?;
+
+                Ok(Command::Set(SetCommand::new(key, value).with_ttl(ttl)))
+            }
+
+            "GETVER" => {
+                if parts.len() < 2 {
+                    return Err(ProtocolError::MissingArguments(
+                        "GETVER requires key".to_string(),
+                    ));
+                }
+                Ok(Command::GetVer(GetVerCommand::new(parts[1].to_string())))
+            }
+
+            "SETVER" => {
+                if parts.len() != 4 {
+                    return Err(ProtocolError::MissingArguments(
+                        "SETVER requires key, value and expected version".to_string(),
+                    ));
+                }
+
+                let value = Self::parse_value(&tokens[2..3], limits)?;
+                let expected = parts[3].parse::<u64>().map_err(|_| {
+                    ProtocolError::InvalidFormat(format!("Invalid version: {}", parts[3]))
+                })?;
+
+                Ok(Command::SetVer(SetVerCommand::new(
+                    parts[1].to_string(),
+                    value,
+                    expected,
+                )))
+            }
+
+            "SETCHUNK" => {
+                if parts.len() != 3 {
+                    return Err(ProtocolError::MissingArguments(
+                        "SETCHUNK requires key and total length".to_string(),
+                    ));
+                }
+
+                let total_len = parts[2].parse::<usize>().map_err(|_| {
+                    ProtocolError::InvalidFormat(format!("Invalid length: {}", parts[2]))
+                })?;
+
+                Ok(Command::SetChunk(SetChunkCommand::new(
+                    parts[1].to_string(),
+                    total_len,
+                )))
+            }
+
+            "GETRANGE" => {
+                if parts.len() < 4 {
+                    return Err(ProtocolError::MissingArguments(
+                        "GETRANGE requires key, start and end".to_string(),
+                    ));
+                }
+
+                let start = parts[2].parse::<i64>().map_err(|_| {
+                    ProtocolError::InvalidFormat(format!("Invalid start: {}", parts[2]))
+                })?;
+                let end = parts[3].parse::<i64>().map_err(|_| {
+                    ProtocolError::InvalidFormat(format!("Invalid end: {}", parts[3]))
+                })?;
+
+                Ok(Command::GetRange(GetRangeCommand::new(
+                    parts[1].to_string(),
+                    start,
+                    end,
+                )))
+            }
+
+            "SETRANGE" => {
+                if parts.len() < 4 {
+                    return Err(ProtocolError::MissingArguments(
+                        "SETRANGE requires key, offset and value".to_string(),
+                    ));
+                }
+
+                let offset = parts[2].parse::<usize>().map_err(|_| {
+                    ProtocolError::InvalidFormat(format!("Invalid offset: {}", parts[2]))
+                })?;
+                let value = Self::parse_value(&tokens[3..], limits)?;
+
+                Ok(Command::SetRange(SetRangeCommand::new(
+                    parts[1].to_string(),
+                    offset,
+                    value,
+                )))
+            }
+
+            "SETBIT" => {
+                if parts.len() < 4 {
+                    return Err(ProtocolError::MissingArguments(
+                        "SETBIT requires key, offset and value".to_string(),
+                    ));
+                }
+
+                let offset = parts[2].parse::<usize>().map_err(|_| {
+                    ProtocolError::InvalidFormat(format!("Invalid bit offset: {}", parts[2]))
+                })?;
+                let bit = match parts[3] {
+                    "0" => false,
+                    "1" => true,
+                    other => {
+                        return Err(ProtocolError::InvalidFormat(format!(
+                            "Bit value must be 0 or 1: {}",
+                            other
+                        )));
                     }
-                } else {
-                    // Multiple parts - join with spaces and treat as plain text
-                    let value_str = parts[2..].join(" ");
-                    value_str.as_bytes().to_vec()
                 };
 
-                Ok(Command::Set(SetCommand::new(key, value)))
+                Ok(Command::SetBit(SetBitCommand::new(
+                    parts[1].to_string(),
+                    offset,
+                    bit,
+                )))
+            }
+
+            "GETBIT" => {
+                if parts.len() < 3 {
+                    return Err(ProtocolError::MissingArguments(
+                        "GETBIT requires key and offset".to_string(),
+                    ));
+                }
+
+                let offset = parts[2].parse::<usize>().map_err(|_| {
+                    ProtocolError::InvalidFormat(format!("Invalid bit offset: {}", parts[2]))
+                })?;
+
+                Ok(Command::GetBit(GetBitCommand::new(
+                    parts[1].to_string(),
+                    offset,
+                )))
+            }
+
+            "EVAL" => {
+                let op = parts.get(1).map(|op| op.to_uppercase()).unwrap_or_default();
+                match op.as_str() {
+                    "CAS" if parts.len() == 5 => Ok(Command::Eval(EvalCommand::new(
+                        parts[2].to_string(),
+                        AtomicOp::Cas {
+                            expected: Self::parse_value(&tokens[3..4], limits)?,
+                            new: Self::parse_value(&tokens[4..5], limits)?,
+                        },
+                    ))),
+                    "CAS" => Err(ProtocolError::MissingArguments(
+                        "EVAL CAS requires key, expected and new value".to_string(),
+                    )),
+
+                    "ADDMAX" if parts.len() == 4 => {
+                        let n = parts[3]
+                            .parse::<f64>()
+                            .ok()
+                            .filter(|n| n.is_finite())
+                            .ok_or_else(|| {
+                                ProtocolError::InvalidFormat(format!(
+                                    "ADDMAX needs a valid float: {}",
+                                    parts[3]
+                                ))
+                            })?;
+                        Ok(Command::Eval(EvalCommand::new(
+                            parts[2].to_string(),
+                            AtomicOp::AddMax(n),
+                        )))
+                    }
+                    "ADDMAX" => Err(ProtocolError::MissingArguments(
+                        "EVAL ADDMAX requires key and number".to_string(),
+                    )),
+
+                    _ => Err(ProtocolError::InvalidFormat(format!(
+                        "EVAL op must be one of: {}",
+                        ATOMIC_OPS.join(", ")
+                    ))),
+                }
+            }
+
+            "INCRBYFLOAT" => {
+                if parts.len() < 3 {
+                    return Err(ProtocolError::MissingArguments(
+                        "INCRBYFLOAT requires key and increment".to_string(),
+                    ));
+                }
+
+                // f64 parsing also accepts "inf" and "NaN", which can't be stored
+                let delta = parts[2]
+                    .parse::<f64>()
+                    .ok()
+                    .filter(|delta| delta.is_finite())
+                    .ok_or_else(|| {
+                        ProtocolError::InvalidFormat(format!(
+                            "Increment is not a valid float: {}",
+                            parts[2]
+                        ))
+                    })?;
+
+                Ok(Command::IncrByFloat(IncrByFloatCommand::new(
+                    parts[1].to_string(),
+                    delta,
+                )))
+            }
+
+            "BITCOUNT" => {
+                if parts.len() < 2 {
+                    return Err(ProtocolError::MissingArguments(
+                        "BITCOUNT requires a key".to_string(),
+                    ));
+                }
+
+                Ok(Command::BitCount(BitCountCommand::new(
+                    parts[1].to_string(),
+                )))
             }
 
             "DELETE" | "DEL" => {
                 if parts.len() < 2 {
                     return Err(ProtocolError::MissingArguments(
-                        "DELETE requires key".to_string(),
+                        "DELETE requires at least one key".to_string(),
+                    ));
+                }
+
+                Ok(Command::Delete(DeleteCommand::many(
+                    parts[1..].iter().map(|key| key.to_string()).collect(),
+                )))
+            }
+
+            "DELPREFIX" => {
+                if parts.len() != 2 {
+                    return Err(ProtocolError::MissingArguments(
+                        "DELPREFIX requires a prefix or --all".to_string(),
                     ));
                 }
 
-                Ok(Command::Delete(DeleteCommand::new(parts[1].to_string())))
+                // A quoted "--all" is an ordinary prefix
+                if parts[1] == "--all" && !tokens[1].quoted {
+                    return Ok(Command::DelPrefix(DelPrefixCommand::all()));
+                }
+                Ok(Command::DelPrefix(DelPrefixCommand::new(
+                    parts[1].to_string(),
+                )))
             }
 
             "EXIST" => {
@@ -103,25 +656,672 @@ impl ProtocolParser {
                 Ok(Command::Exist(ExistCommand::new(parts[1].to_string())))
             }
 
+            "EXISTS" => {
+                if parts.len() < 2 {
+                    return Err(ProtocolError::MissingArguments(
+                        "EXISTS requires at least one key".to_string(),
+                    ));
+                }
+                Ok(Command::Exist(ExistCommand::many(
+                    parts[1..].iter().map(|key| key.to_string()).collect(),
+                )))
+            }
+
+            "SADD" | "SREM" => {
+                if parts.len() < 3 {
+                    return Err(ProtocolError::MissingArguments(format!(
+                        "{} requires key and at least one member",
+                        command
+                    )));
+                }
+
+                let key = parts[1].to_string();
+                let members = tokens[2..]
+                    .iter()
+                    .map(|member| Self::parse_value(std::slice::from_ref(member), limits))
+                    .collect::<Result<_, _>>()?;
+
+                if command == "SADD" {
+                    Ok(Command::SAdd(SAddCommand::new(key, members)))
+                } else {
+                    Ok(Command::SRem(SRemCommand::new(key, members)))
+                }
+            }
+
+            "LPUSH" | "RPUSH" => {
+                if parts.len() < 3 {
+                    return Err(ProtocolError::MissingArguments(format!(
+                        "{} requires key and at least one item",
+                        command
+                    )));
+                }
+
+                let items = tokens[2..]
+                    .iter()
+                    .map(|item| Self::parse_value(std::slice::from_ref(item), limits))
+                    .collect::<Result<_, _>>()?;
+
+                Ok(Command::Push(PushCommand::new(
+                    parts[1].to_string(),
+                    items,
+                    command == "LPUSH",
+                )))
+            }
+
+            "BLPOP" => {
+                if parts.len() != 3 {
+                    return Err(ProtocolError::MissingArguments(
+                        "BLPOP requires key and timeout".to_string(),
+                    ));
+                }
+
+                // Seconds like Redis, fractions allowed down to a millisecond
+                let timeout = parts[2]
+                    .parse::<f64>()
+                    .ok()
+                    .filter(|secs| secs.is_finite() && *secs >= 0.0)
+                    .ok_or_else(|| {
+                        ProtocolError::InvalidFormat(format!("Invalid timeout: {}", parts[2]))
+                    })?;
+
+                Ok(Command::BLPop(BLPopCommand::new(
+                    parts[1].to_string(),
+                    std::time::Duration::from_millis((timeout * 1000.0).round() as u64),
+                )))
+            }
+
+            "SISMEMBER" => {
+                if parts.len() < 3 {
+                    return Err(ProtocolError::MissingArguments(
+                        "SISMEMBER requires key and member".to_string(),
+                    ));
+                }
+                Ok(Command::SIsMember(SIsMemberCommand::new(
+                    parts[1].to_string(),
+                    Self::parse_value(&tokens[2..3], limits)?,
+                )))
+            }
+
+            "SMEMBERS" => {
+                if parts.len() < 2 {
+                    return Err(ProtocolError::MissingArguments(
+                        "SMEMBERS requires key".to_string(),
+                    ));
+                }
+                Ok(Command::SMembers(SMembersCommand::new(
+                    parts[1].to_string(),
+                )))
+            }
+
+            "SCARD" => {
+                if parts.len() < 2 {
+                    return Err(ProtocolError::MissingArguments(
+                        "SCARD requires key".to_string(),
+                    ));
+                }
+                Ok(Command::SCard(SCardCommand::new(parts[1].to_string())))
+            }
+
             "SCAN" => {
                 let prefix = if parts.len() >= 2 {
                     parts[1].to_string()
                 } else {
                     String::new() // Empty prefix = scan all
                 };
+                let mut cmd = ScanCommand::new(prefix);
+
+                // Options only follow a prefix, `SCAN "" SORTED` sorts every key
+                let mut options = parts.iter().skip(2);
+                while let Some(option) = options.next() {
+                    match option.to_uppercase().as_str() {
+                        "SORTED" => cmd = cmd.sorted(),
+                        "COUNT" => {
+                            let count = options
+                                .next()
+                                .and_then(|count| count.parse::<usize>().ok())
+                                .ok_or_else(|| {
+                                    ProtocolError::InvalidFormat(
+                                        "COUNT requires a number".to_string(),
+                                    )
+                                })?;
+                            cmd = cmd.with_count(count);
+                        }
+                        other => {
+                            return Err(ProtocolError::InvalidFormat(format!(
+                                "Unknown SCAN option: {}",
+                                other
+                            )));
+                        }
+                    }
+                }
+
+                Ok(Command::Scan(cmd))
+            }
+
+            "OBJECT" => {
+                if parts.len() < 3 {
+                    return Err(ProtocolError::MissingArguments(
+                        "OBJECT requires subcommand and key".to_string(),
+                    ));
+                }
+
+                let subcommand = match parts[1].to_uppercase().as_str() {
+                    "IDLETIME" => ObjectSubcommand::IdleTime,
+                    "FREQ" => ObjectSubcommand::Freq,
+                    "ENCODING" => ObjectSubcommand::Encoding,
+                    other => {
+                        return Err(ProtocolError::UnknownCommand(format!("OBJECT {}", other)));
+                    }
+                };
+
+                Ok(Command::Object(ObjectCommand::new(
+                    subcommand,
+                    parts[2].to_string(),
+                )))
+            }
+
+            "MEMORY" => match parts.get(1).map(|p| p.to_uppercase()).as_deref() {
+                Some("USAGE") => match parts.get(2) {
+                    Some(key) if parts.len() == 3 => Ok(Command::Memory(MemoryCommand::Usage {
+                        key: key.to_string(),
+                    })),
+                    _ => Err(ProtocolError::MissingArguments(
+                        "MEMORY USAGE requires a key".to_string(),
+                    )),
+                },
+                Some("STATS") => Ok(Command::Memory(MemoryCommand::Stats)),
+                Some(other) => Err(ProtocolError::UnknownCommand(format!("MEMORY {}", other))),
+                None => Err(ProtocolError::MissingArguments(
+                    "MEMORY requires a subcommand".to_string(),
+                )),
+            },
+
+            "EXPIRE" | "PEXPIRE" | "EXPIREAT" | "PEXPIREAT" => {
+                if parts.len() != 3 {
+                    return Err(ProtocolError::MissingArguments(format!(
+                        "{} requires key and time",
+                        command
+                    )));
+                }
+
+                let amount = parts[2].parse::<u64>().map_err(|_| {
+                    ProtocolError::InvalidFormat(format!("Invalid time: {}", parts[2]))
+                })?;
+
+                Ok(Command::Expire(ExpireCommand::new(
+                    parts[1].to_string(),
+                    amount,
+                    command.starts_with('P'),
+                    command.ends_with("AT"),
+                )))
+            }
+
+            "TTL" | "PTTL" => {
+                if parts.len() != 2 {
+                    return Err(ProtocolError::MissingArguments(format!(
+                        "{} requires a key",
+                        command
+                    )));
+                }
+                Ok(Command::Ttl(TtlCommand::new(
+                    parts[1].to_string(),
+                    command == "PTTL",
+                )))
+            }
+
+            "TOUCH" => {
+                if parts.len() < 2 {
+                    return Err(ProtocolError::MissingArguments(
+                        "TOUCH requires at least one key".to_string(),
+                    ));
+                }
+                Ok(Command::Touch(TouchCommand::new(
+                    parts[1..].iter().map(|key| key.to_string()).collect(),
+                )))
+            }
+
+            "COMPRESS" => match parts.get(1).map(|p| p.to_uppercase()).as_deref() {
+                Some("OFF") => Ok(Command::Compress(CompressCommand::off())),
+                Some("ON") => {
+                    let algorithm = match parts.get(2).map(|p| p.to_lowercase()).as_deref() {
+                        Some("gzip") | None => CompressionAlgorithm::Gzip,
+                        Some(other) => {
+                            return Err(ProtocolError::InvalidFormat(format!(
+                                "Unsupported compression: {}",
+                                other
+                            )));
+                        }
+                    };
+
+                    let threshold = match parts.get(3) {
+                        Some(raw) => raw.parse::<usize>().map_err(|_| {
+                            ProtocolError::InvalidFormat(format!("Invalid threshold: {}", raw))
+                        })?,
+                        None => DEFAULT_COMPRESS_THRESHOLD,
+                    };
+
+                    Ok(Command::Compress(CompressCommand::on(algorithm, threshold)))
+                }
+                _ => Err(ProtocolError::MissingArguments(
+                    "COMPRESS requires ON or OFF".to_string(),
+                )),
+            },
+
+            "WAIT" => {
+                if parts.len() < 3 {
+                    return Err(ProtocolError::MissingArguments(
+                        "WAIT requires numreplicas and timeout".to_string(),
+                    ));
+                }
+
+                let numreplicas = parts[1].parse::<u64>().map_err(|_| {
+                    ProtocolError::InvalidFormat(format!("Invalid numreplicas: {}", parts[1]))
+                })?;
+                let timeout_ms = parts[2].parse::<u64>().map_err(|_| {
+                    ProtocolError::InvalidFormat(format!("Invalid timeout: {}", parts[2]))
+                })?;
+
+                Ok(Command::Wait(WaitCommand::new(numreplicas, timeout_ms)))
+            }
+
+            "PROTO" => match parts.get(1).map(|mode| mode.to_uppercase()).as_deref() {
+                Some("TEXT") => Ok(Command::Proto(ProtoCommand::new(ProtocolMode::Text))),
+                Some("JSON") => Ok(Command::Proto(ProtoCommand::new(ProtocolMode::Json))),
+                Some(other) => Err(ProtocolError::InvalidFormat(format!(
+                    "Unsupported protocol: {}",
+                    other
+                ))),
+                None => Err(ProtocolError::MissingArguments(
+                    "PROTO requires TEXT or JSON".to_string(),
+                )),
+            },
+
+            "ENCODING" => match parts.get(1).map(|p| p.to_uppercase()).as_deref() {
+                Some("RAW") => Ok(Command::Encoding(EncodingCommand::new(ValueEncoding::Raw))),
+                Some("BASE64") => Ok(Command::Encoding(EncodingCommand::new(
+                    ValueEncoding::Base64,
+                ))),
+                Some(other) => Err(ProtocolError::InvalidFormat(format!(
+                    "Unsupported encoding: {}",
+                    other
+                ))),
+                None => Err(ProtocolError::MissingArguments(
+                    "ENCODING requires RAW or BASE64".to_string(),
+                )),
+            },
+
+            "READONLY" => match parts.get(1).map(|p| p.to_uppercase()).as_deref() {
+                Some("ON") => Ok(Command::ReadOnly(ReadOnlyCommand::new(true))),
+                Some("OFF") => Ok(Command::ReadOnly(ReadOnlyCommand::new(false))),
+                _ => Err(ProtocolError::MissingArguments(
+                    "READONLY requires ON or OFF".to_string(),
+                )),
+            },
+
+            "CONFIG" => match parts.get(1).map(|p| p.to_uppercase()).as_deref() {
+                Some("GET") if parts.len() >= 3 => {
+                    Ok(Command::Config(ConfigCommand::get(parts[2].to_lowercase())))
+                }
+                Some("SET") if parts.len() >= 4 => Ok(Command::Config(ConfigCommand::set(
+                    parts[2].to_lowercase(),
+                    parts[3..].join(" "),
+                ))),
+                Some("GET") | Some("SET") => Err(ProtocolError::MissingArguments(
+                    "CONFIG GET requires param, CONFIG SET requires param and value".to_string(),
+                )),
+                Some(other) => Err(ProtocolError::UnknownCommand(format!("CONFIG {}", other))),
+                None => Err(ProtocolError::MissingArguments(
+                    "CONFIG requires GET or SET".to_string(),
+                )),
+            },
+
+            "SNAPSHOT" => match parts.get(1).map(|p| p.to_uppercase()).as_deref() {
+                Some("VERIFY") => Ok(Command::Snapshot(SnapshotCommand::verify(
+                    parts.get(2).map(|path| path.to_string()),
+                ))),
+                Some(other) => Err(ProtocolError::UnknownCommand(format!("SNAPSHOT {}", other))),
+                None => Err(ProtocolError::MissingArguments(
+                    "SNAPSHOT requires VERIFY".to_string(),
+                )),
+            },
+
+            "RELOCATE" => {
+                let mut cmd = RelocateCommand {
+                    aof_path: None,
+                    snapshot_dir: None,
+                    copy: false,
+                };
+
+                let mut options = parts.iter().skip(1);
+                while let Some(option) = options.next() {
+                    match option.to_uppercase().as_str() {
+                        "AOF" => {
+                            let path = options.next().ok_or_else(|| {
+                                ProtocolError::MissingArguments("AOF requires a path".to_string())
+                            })?;
+                            cmd.aof_path = Some(path.to_string());
+                        }
+                        "SNAPSHOTS" => {
+                            let dir = options.next().ok_or_else(|| {
+                                ProtocolError::MissingArguments(
+                                    "SNAPSHOTS requires a directory".to_string(),
+                                )
+                            })?;
+                            cmd.snapshot_dir = Some(dir.to_string());
+                        }
+                        "COPY" => cmd.copy = true,
+                        other => {
+                            return Err(ProtocolError::InvalidFormat(format!(
+                                "Unknown RELOCATE option: {}",
+                                other
+                            )));
+                        }
+                    }
+                }
+
+                if cmd.aof_path.is_none() && cmd.snapshot_dir.is_none() {
+                    return Err(ProtocolError::MissingArguments(
+                        "RELOCATE requires AOF or SNAPSHOTS".to_string(),
+                    ));
+                }
+                Ok(Command::Relocate(cmd))
+            }
+
+            "SELECT" => {
+                let index = parts.get(1).ok_or_else(|| {
+                    ProtocolError::MissingArguments("SELECT requires a database index".to_string())
+                })?;
+                let index = index.parse::<usize>().map_err(|_| {
+                    ProtocolError::InvalidFormat(format!("Invalid database index: {}", index))
+                })?;
 
-                Ok(Command::Scan(ScanCommand::new(prefix)))
+                Ok(Command::Select(SelectCommand::new(index)))
             }
 
+            "AUTH" => match parts.len() {
+                2 => Ok(Command::Auth(AuthCommand::new(parts[1].to_string()))),
+                3 => Ok(Command::Auth(AuthCommand::with_username(
+                    parts[1].to_string(),
+                    parts[2].to_string(),
+                ))),
+                1 => Err(ProtocolError::MissingArguments(
+                    "AUTH requires a password".to_string(),
+                )),
+                _ => Err(ProtocolError::InvalidFormat(
+                    "AUTH takes [username] password".to_string(),
+                )),
+            },
+
+            "SUBSCRIBE" => {
+                if parts.len() < 2 {
+                    return Err(ProtocolError::MissingArguments(
+                        "SUBSCRIBE requires at least one channel".to_string(),
+                    ));
+                }
+                let channels = parts[1..].iter().map(|c| c.to_string()).collect();
+                Ok(Command::Subscribe(SubscribeCommand::new(channels)))
+            }
+
+            "UNSUBSCRIBE" => {
+                let channels = parts[1..].iter().map(|c| c.to_string()).collect();
+                Ok(Command::Unsubscribe(UnsubscribeCommand::new(channels)))
+            }
+
+            "INFO" => Ok(Command::Info(InfoCommand::new(
+                parts.get(1).map(|section| section.to_lowercase()),
+            ))),
+
+            "DEBUG" => match parts.get(1).map(|p| p.to_uppercase()).as_deref() {
+                Some("SET-ACTIVE-EXPIRE") => match parts.get(2).copied() {
+                    Some("0") => Ok(Command::Debug(DebugCommand::SetActiveExpire(false))),
+                    Some("1") => Ok(Command::Debug(DebugCommand::SetActiveExpire(true))),
+                    _ => Err(ProtocolError::MissingArguments(
+                        "DEBUG SET-ACTIVE-EXPIRE requires 0 or 1".to_string(),
+                    )),
+                },
+                Some("RELOAD") => Ok(Command::Debug(DebugCommand::Reload)),
+                Some(other) => Err(ProtocolError::UnknownCommand(format!("DEBUG {}", other))),
+                None => Err(ProtocolError::MissingArguments(
+                    "DEBUG requires a subcommand".to_string(),
+                )),
+            },
+
+            "HELLO" => match parts.get(1) {
+                Some(protover) => protover
+                    .parse::<u32>()
+                    .map(|protover| Command::Hello(HelloCommand::new(Some(protover))))
+                    .map_err(|_| {
+                        ProtocolError::InvalidFormat(format!(
+                            "Invalid protocol version: {}",
+                            protover
+                        ))
+                    }),
+                None => Ok(Command::Hello(HelloCommand::new(None))),
+            },
+
+            "METRICS" => Ok(Command::Metrics),
+
+            "RESET" => Ok(Command::Reset),
+
+            "FLUSHDB" => Ok(Command::FlushDb),
+
+            "BGREWRITEAOF" => Ok(Command::BgRewriteAof),
+
             "STATS" => Ok(Command::Stats),
 
-            "PING" => Ok(Command::Ping),
+            "ECHO" => {
+                if parts.len() < 2 {
+                    return Err(ProtocolError::MissingArguments(
+                        "ECHO requires a message".to_string(),
+                    ));
+                }
+                Ok(Command::Echo(EchoCommand::new(Self::parse_value(
+                    &tokens[1..],
+                    limits,
+                )?)))
+            }
+
+            "PING" if parts.len() < 2 => Ok(Command::Ping),
+            "PING" => Ok(Command::PingMessage(PingCommand::new(Some(
+                Self::parse_value(&tokens[1..], limits)?,
+            )))),
 
             _ => Err(ProtocolError::UnknownCommand(command)),
         }
     }
 
+    // Parse a line in the given protocol mode
+    // JSON mode still accepts plain text commands so `PROTO TEXT` can switch back
+    pub fn parse_command_in(
+        message: &str,
+        mode: ProtocolMode,
+        limits: &KeyLimits,
+        renames: &CommandRenames,
+    ) -> Result<Command, ProtocolError> {
+        let trimmed = message.trim_start();
+        if mode == ProtocolMode::Json && (trimmed.starts_with('{') || trimmed.starts_with('"')) {
+            let command = Self::parse_json_command(trimmed)?;
+
+            // Renaming must not be bypassed by naming the command in JSON instead
+            if !renames.is_empty() {
+                let name = command.clone().into_handler().name();
+                if !renames.allows(name) {
+                    return Err(ProtocolError::UnknownCommand(name.to_string()));
+                }
+            }
+            return Ok(command);
+        }
+        Self::parse_command_renamed(message, limits, renames)
+    }
+
+    // Parse a JSON-encoded Command, e.g. {"Get":{"key":"k"}} or "Ping"
+    pub fn parse_json_command(message: &str) -> Result<Command, ProtocolError> {
+        Ok(serde_json::from_str(message)?)
+    }
+
+    // Split a line into arguments on whitespace, keeping quoted arguments whole
+    fn tokenize(message: &str) -> Result<Vec<Token<'_>>, ProtocolError> {
+        let mut tokens = Vec::new();
+        let mut rest = message.trim_start();
+
+        while !rest.is_empty() {
+            if tokens.len() == MAX_ARGUMENTS {
+                return Err(ProtocolError::InvalidFormat(format!(
+                    "Too many arguments (max {})",
+                    MAX_ARGUMENTS
+                )));
+            }
+
+            let (token, remainder) = match rest.strip_prefix('"') {
+                Some(quoted) => Self::quoted_token(quoted)?,
+                None => {
+                    let end = rest.find(char::is_whitespace).unwrap_or(rest.len());
+                    let token = Token {
+                        text: Cow::Borrowed(&rest[..end]),
+                        quoted: false,
+                    };
+                    (token, &rest[end..])
+                }
+            };
+
+            tokens.push(token);
+            rest = remainder.trim_start();
+        }
+
+        Ok(tokens)
+    }
+
+    // Read a quoted argument up to its closing quote, `input` starts after the opening one
+    fn quoted_token(input: &str) -> Result<(Token<'_>, &str), ProtocolError> {
+        let mut text = String::new();
+        let mut chars = input.char_indices().peekable();
+
+        while let Some((i, c)) = chars.next() {
+            match c {
+                '"' => {
+                    let rest = &input[i + 1..];
+
+                    // `"a"b` would otherwise quietly turn into two arguments
+                    if rest.starts_with(|c: char| !c.is_whitespace()) {
+                        return Err(ProtocolError::InvalidFormat(
+                            "Closing quote must be followed by whitespace".to_string(),
+                        ));
+                    }
+
+                    let token = Token {
+                        text: Cow::Owned(text),
+                        quoted: true,
+                    };
+                    return Ok((token, rest));
+                }
+                // Only \" and \\ are escapes, any other backslash is kept as is
+                '\\' => match chars.next_if(|(_, next)| matches!(next, '"' | '\\')) {
+                    Some((_, escaped)) => text.push(escaped),
+                    None => text.push('\\'),
+                },
+                c => text.push(c),
+            }
+        }
+
+        Err(ProtocolError::InvalidFormat(
+            "Unterminated quoted argument".to_string(),
+        ))
+    }
+
+    // A value's first word is always data, later unquoted words may be a Redis option
+    fn reject_set_options(parts: &[Token]) -> Result<(), ProtocolError> {
+        for part in parts.iter().skip(1).filter(|part| !part.quoted) {
+            let option = part.text.to_uppercase();
+            if UNSUPPORTED_SET_OPTIONS.contains(&option.as_str()) {
+                return Err(ProtocolError::UnsupportedOption(option));
+            }
+        }
+        Ok(())
+    }
+
+    // Handle value - quoted text is literal, a single bare word may be base64 encoded
+    // Anything longer than max_value_size bytes would encode to is refused before decoding,
+    // so an oversized upload is never copied
+    fn parse_value(parts: &[Token], limits: &KeyLimits) -> Result<Vec<u8>, ProtocolError> {
+        let encoded_limit = limits.max_value_size.div_ceil(3).saturating_mul(4);
+        // Parts are joined with a space between each
+        let len = parts.iter().map(|part| part.text.len()).sum::<usize>() + parts.len() - 1;
+        if len > encoded_limit {
+            return Err(ProtocolError::ValueTooLarge(limits.max_value_size));
+        }
+
+        let value = match parts {
+            [part] if part.quoted => part.text.as_bytes().to_vec(),
+            [part] => {
+                // Single value part - try base64 first, fallback to plain text
+                match base64::Engine::decode(
+                    &base64::engine::general_purpose::STANDARD,
+                    &*part.text,
+                ) {
+                    Ok(decoded) => decoded,
+                    Err(_) => part.text.as_bytes().to_vec(), // Plain text fallback
+                }
+            }
+            // Multiple parts - join with spaces and treat as plain text
+            parts => parts
+                .iter()
+                .map(|part| part.text.as_ref())
+                .collect::<Vec<_>>()
+                .join(" ")
+                .into_bytes(),
+        };
+        Ok(value)
+    }
+
     pub fn serialize_response(response: &CommandResponse) -> Result<String, ProtocolError> {
+        Self::serialize_response_with(response, &ResponseOptions::default())
+    }
+
+    // Serialize a response to wire bytes honoring per-connection options
+    // Only raw VALUE framing needs bytes, everything else is the text serialization
+    pub fn serialize_response_bytes(
+        response: &CommandResponse,
+        options: &ResponseOptions,
+    ) -> Result<Vec<u8>, ProtocolError> {
+        // Compressed replies keep their VALUEZ base64 framing
+        let compressed = matches!(
+            (response, options.compression),
+            (CommandResponse::Value(data), Some((_, threshold))) if data.len() >= threshold
+        );
+
+        if let CommandResponse::Value(data) = response
+            && options.mode == ProtocolMode::Text
+            && options.encoding == ValueEncoding::Raw
+            && !compressed
+        {
+            let mut framed = format!("VALUE {}\r\n", data.len()).into_bytes();
+            framed.extend_from_slice(data);
+            return Ok(framed);
+        }
+
+        Ok(Self::serialize_response_with(response, options)?.into_bytes())
+    }
+
+    // Serialize a response honoring per-connection options
+    pub fn serialize_response_with(
+        response: &CommandResponse,
+        options: &ResponseOptions,
+    ) -> Result<String, ProtocolError> {
+        if options.mode == ProtocolMode::Json {
+            return Self::serialize_json_response(response);
+        }
+
+        // Large values: VALUEZ <algorithm> <compressed_base64>
+        if let CommandResponse::Value(data) = response
+            && let Some((algorithm, threshold)) = options.compression
+            && data.len() >= threshold
+        {
+            let compressed = compression::compress(algorithm, data)?;
+            let encoded =
+                base64::Engine::encode(&base64::engine::general_purpose::STANDARD, compressed);
+            return Ok(format!("VALUEZ {} {}\n", algorithm.name(), encoded));
+        }
+
         match response {
             CommandResponse::Value(data) => {
                 // Return base64 encoded value for binary safety
@@ -133,6 +1333,7 @@ impl ProtocolParser {
             CommandResponse::Ok => Ok("OK\n".to_string()),
             CommandResponse::Bool(true) => Ok("TRUE\n".to_string()),
             CommandResponse::Bool(false) => Ok("FALSE\n".to_string()),
+            CommandResponse::Integer(n) => Ok(format!("INTEGER {}\n", n)),
             CommandResponse::Keys(keys) => {
                 if keys.is_empty() {
                     Ok("KEYS 0\n".to_string())
@@ -144,19 +1345,123 @@ impl ProtocolParser {
                     Ok(result)
                 }
             }
+            CommandResponse::Members(members) => {
+                // One base64 member per line, like KEYS
+                let mut result = format!("MEMBERS {}\n", members.len());
+                for member in members {
+                    let encoded =
+                        base64::Engine::encode(&base64::engine::general_purpose::STANDARD, member);
+                    result.push_str(&format!("{}\n", encoded));
+                }
+                Ok(result)
+            }
+            CommandResponse::Map(fields) => {
+                // One `field value` line per pair
+                let mut result = format!("MAP {}\n", fields.len());
+                for (field, value) in fields {
+                    result.push_str(&format!("{} {}\n", field, value));
+                }
+                Ok(result)
+            }
+            // Space-separated key=value fields, in a fixed order
             CommandResponse::Stats {
                 total_keys,
                 memory_usage,
                 hit_rate,
                 total_operations,
+                keyspace_hits,
+                keyspace_misses,
+                evicted_keys,
             } => Ok(format!(
-                "STATS total_keys={} memory_usage={} hit_rate={:.3} total_operations:{}\n",
-                total_keys, memory_usage, hit_rate, total_operations
+                "STATS total_keys={} memory_usage={} hit_rate={:.3} total_operations={} \
+                 keyspace_hits={} keyspace_misses={} evicted_keys={}\n",
+                total_keys,
+                memory_usage,
+                hit_rate,
+                total_operations,
+                keyspace_hits,
+                keyspace_misses,
+                evicted_keys
             )),
             CommandResponse::Pong => Ok("PONG\n".to_string()),
+            CommandResponse::Nil => Ok("NIL\n".to_string()),
+            CommandResponse::Versioned { value, version } => Ok(format!(
+                "VERSIONED {} {}\n",
+                version,
+                base64::Engine::encode(&base64::engine::general_purpose::STANDARD, value)
+            )),
             CommandResponse::Error(msg) => Ok(format!("ERROR {}\n", msg)),
+            CommandResponse::Message { channel, payload } => Ok(format!(
+                "MESSAGE {} {}\n",
+                channel,
+                base64::Engine::encode(&base64::engine::general_purpose::STANDARD, payload)
+            )),
         }
     }
+
+    // Serialize a response as a single JSON line: {"status":"ok","value":...}
+    pub fn serialize_json_response(response: &CommandResponse) -> Result<String, ProtocolError> {
+        let json = match response {
+            CommandResponse::Value(data) => serde_json::json!({
+                "status": "ok",
+                "value": base64::Engine::encode(&base64::engine::general_purpose::STANDARD, data),
+            }),
+            CommandResponse::Ok => serde_json::json!({ "status": "ok" }),
+            CommandResponse::Bool(b) => serde_json::json!({ "status": "ok", "value": b }),
+            CommandResponse::Integer(n) => serde_json::json!({ "status": "ok", "value": n }),
+            CommandResponse::Keys(keys) => serde_json::json!({ "status": "ok", "value": keys }),
+            CommandResponse::Members(members) => serde_json::json!({
+                "status": "ok",
+                "value": members
+                    .iter()
+                    .map(|m| base64::Engine::encode(&base64::engine::general_purpose::STANDARD, m))
+                    .collect::<Vec<_>>(),
+            }),
+            CommandResponse::Map(fields) => serde_json::json!({
+                "status": "ok",
+                "value": fields
+                    .iter()
+                    .map(|(field, value)| (field.clone(), serde_json::Value::from(value.as_str())))
+                    .collect::<serde_json::Map<_, _>>(),
+            }),
+            CommandResponse::Stats {
+                total_keys,
+                memory_usage,
+                hit_rate,
+                total_operations,
+                keyspace_hits,
+                keyspace_misses,
+                evicted_keys,
+            } => serde_json::json!({
+                "status": "ok",
+                "value": {
+                    "total_keys": total_keys,
+                    "memory_usage": memory_usage,
+                    "hit_rate": hit_rate,
+                    "total_operations": total_operations,
+                    "keyspace_hits": keyspace_hits,
+                    "keyspace_misses": keyspace_misses,
+                    "evicted_keys": evicted_keys,
+                },
+            }),
+            CommandResponse::Pong => serde_json::json!({ "status": "ok", "value": "PONG" }),
+            CommandResponse::Nil => serde_json::json!({ "status": "ok", "value": null }),
+            CommandResponse::Versioned { value, version } => serde_json::json!({
+                "status": "ok",
+                "value": base64::Engine::encode(&base64::engine::general_purpose::STANDARD, value),
+                "version": version,
+            }),
+            CommandResponse::Error(msg) => serde_json::json!({ "status": "error", "error": msg }),
+            CommandResponse::Message { channel, payload } => serde_json::json!({
+                "status": "message",
+                "channel": channel,
+                "value": base64::Engine::encode(&base64::engine::general_purpose::STANDARD, payload),
+            }),
+        };
+
+        Ok(format!("{}\n", serde_json::to_string(&json)?))
+    }
+
     pub fn parse_commands(buffer: &str) -> Vec<Result<Command, ProtocolError>> {
         buffer
             .lines()
@@ -164,4 +1469,410 @@ impl ProtocolParser {
             .map(Self::parse_command)
             .collect()
     }
+
+    // Encode a command as a text protocol line, the inverse of `parse_command`
+    // Fails for arguments the whitespace-separated framing can't carry (empty or spaced keys)
+    pub fn serialize_command(command: &Command) -> Result<String, ProtocolError> {
+        let line = match command {
+            Command::Get(cmd) => format!("GET {}", Self::word(&cmd.key)?),
+            Command::GetVer(cmd) => format!("GETVER {}", Self::word(&cmd.key)?),
+            Command::SetVer(cmd) => format!(
+                "SETVER {} {} {}",
+                Self::word(&cmd.key)?,
+                Self::encode_value(&cmd.value)?,
+                cmd.expected_version
+            ),
+            Command::Set(cmd) => match cmd.ttl {
+                Some(ttl) => format!(
+                    "SETEX {} {} {}",
+                    Self::word(&cmd.key)?,
+                    ttl,
+                    Self::encode_value(&cmd.value)?
+                ),
+                None => format!(
+                    "SET {} {}",
+                    Self::word(&cmd.key)?,
+                    Self::encode_value(&cmd.value)?
+                ),
+            },
+            // Only the header, the payload is written raw after it
+            Command::SetChunk(cmd) => {
+                format!("SETCHUNK {} {}", Self::word(&cmd.key)?, cmd.total_len)
+            }
+            Command::GetRange(cmd) => format!(
+                "GETRANGE {} {} {}",
+                Self::word(&cmd.key)?,
+                cmd.start,
+                cmd.end
+            ),
+            Command::SetRange(cmd) => format!(
+                "SETRANGE {} {} {}",
+                Self::word(&cmd.key)?,
+                cmd.offset,
+                Self::encode_value(&cmd.value)?
+            ),
+            Command::SetBit(cmd) => format!(
+                "SETBIT {} {} {}",
+                Self::word(&cmd.key)?,
+                cmd.offset,
+                u8::from(cmd.bit)
+            ),
+            Command::GetBit(cmd) => format!("GETBIT {} {}", Self::word(&cmd.key)?, cmd.offset),
+            Command::IncrByFloat(cmd) => {
+                format!("INCRBYFLOAT {} {}", Self::word(&cmd.key)?, cmd.delta)
+            }
+            Command::Eval(cmd) => match &cmd.op {
+                AtomicOp::Cas { expected, new } => format!(
+                    "EVAL CAS {} {} {}",
+                    Self::word(&cmd.key)?,
+                    Self::encode_value(expected)?,
+                    Self::encode_value(new)?
+                ),
+                AtomicOp::AddMax(n) => format!("EVAL ADDMAX {} {}", Self::word(&cmd.key)?, n),
+            },
+            Command::BitCount(cmd) => format!("BITCOUNT {}", Self::word(&cmd.key)?),
+            Command::Delete(cmd) => format!("DELETE {}", Self::words(&cmd.keys)?),
+            Command::DelPrefix(cmd) if cmd.all => "DELPREFIX --all".to_string(),
+            Command::DelPrefix(cmd) if cmd.prefix == "--all" => {
+                format!("DELPREFIX {}", Self::quoted(&cmd.prefix)?)
+            }
+            Command::DelPrefix(cmd) => format!("DELPREFIX {}", Self::word(&cmd.prefix)?),
+            Command::SAdd(cmd) => format!(
+                "SADD {} {}",
+                Self::word(&cmd.key)?,
+                Self::encode_values(&cmd.members)?
+            ),
+            Command::SRem(cmd) => format!(
+                "SREM {} {}",
+                Self::word(&cmd.key)?,
+                Self::encode_values(&cmd.members)?
+            ),
+            Command::SIsMember(cmd) => format!(
+                "SISMEMBER {} {}",
+                Self::word(&cmd.key)?,
+                Self::encode_value(&cmd.member)?
+            ),
+            Command::SMembers(cmd) => format!("SMEMBERS {}", Self::word(&cmd.key)?),
+            Command::SCard(cmd) => format!("SCARD {}", Self::word(&cmd.key)?),
+            Command::Push(cmd) => format!(
+                "{} {} {}",
+                if cmd.front { "LPUSH" } else { "RPUSH" },
+                Self::word(&cmd.key)?,
+                Self::encode_values(&cmd.items)?
+            ),
+            Command::BLPop(cmd) => format!(
+                "BLPOP {} {}",
+                Self::word(&cmd.key)?,
+                cmd.timeout.as_millis() as f64 / 1000.0
+            ),
+            Command::Scan(cmd) if cmd.prefix.is_empty() && !cmd.sorted && cmd.count.is_none() => {
+                "SCAN".to_string()
+            }
+            Command::Scan(cmd) => {
+                let mut line = if cmd.prefix.is_empty() {
+                    "SCAN \"\"".to_string()
+                } else {
+                    format!("SCAN {}", Self::word(&cmd.prefix)?)
+                };
+                if cmd.sorted {
+                    line.push_str(" SORTED");
+                }
+                if let Some(count) = cmd.count {
+                    line.push_str(&format!(" COUNT {}", count));
+                }
+                line
+            }
+            Command::Exist(cmd) if cmd.count => format!("EXISTS {}", Self::words(&cmd.keys)?),
+            Command::Exist(cmd) => match cmd.keys.as_slice() {
+                [key] => format!("EXIST {}", Self::word(key)?),
+                _ => {
+                    return Err(ProtocolError::InvalidFormat(
+                        "EXIST takes exactly one key".to_string(),
+                    ));
+                }
+            },
+            Command::Object(cmd) => match cmd.subcommand {
+                ObjectSubcommand::IdleTime => format!("OBJECT IDLETIME {}", Self::word(&cmd.key)?),
+                ObjectSubcommand::Freq => format!("OBJECT FREQ {}", Self::word(&cmd.key)?),
+                ObjectSubcommand::Encoding => {
+                    format!("OBJECT ENCODING {}", Self::word(&cmd.key)?)
+                }
+            },
+            Command::Memory(MemoryCommand::Usage { key }) => {
+                format!("MEMORY USAGE {}", Self::word(key)?)
+            }
+            Command::Memory(MemoryCommand::Stats) => "MEMORY STATS".to_string(),
+            Command::Touch(cmd) => format!("TOUCH {}", Self::words(&cmd.keys)?),
+            Command::Expire(cmd) => format!(
+                "{}EXPIRE{} {} {}",
+                if cmd.millis { "P" } else { "" },
+                if cmd.absolute { "AT" } else { "" },
+                Self::word(&cmd.key)?,
+                cmd.amount
+            ),
+            Command::Ttl(cmd) => format!(
+                "{} {}",
+                if cmd.millis { "PTTL" } else { "TTL" },
+                Self::word(&cmd.key)?
+            ),
+            Command::Compress(cmd) => match cmd.algorithm {
+                Some(algorithm) => format!("COMPRESS ON {} {}", algorithm.name(), cmd.threshold),
+                None => "COMPRESS OFF".to_string(),
+            },
+            Command::Proto(cmd) => match cmd.mode {
+                ProtocolMode::Text => "PROTO TEXT".to_string(),
+                ProtocolMode::Json => "PROTO JSON".to_string(),
+            },
+            Command::Encoding(cmd) => match cmd.encoding {
+                ValueEncoding::Raw => "ENCODING RAW".to_string(),
+                ValueEncoding::Base64 => "ENCODING BASE64".to_string(),
+            },
+            Command::ReadOnly(cmd) if cmd.enabled => "READONLY ON".to_string(),
+            Command::ReadOnly(_) => "READONLY OFF".to_string(),
+            Command::Config(cmd) => match &cmd.subcommand {
+                ConfigSubcommand::Get { param } => format!("CONFIG GET {}", Self::word(param)?),
+                ConfigSubcommand::Set { param, value } => {
+                    format!("CONFIG SET {} {}", Self::word(param)?, Self::word(value)?)
+                }
+            },
+            Command::Snapshot(cmd) => match &cmd.subcommand {
+                SnapshotSubcommand::Verify { path: Some(path) } => {
+                    format!("SNAPSHOT VERIFY {}", Self::word(path)?)
+                }
+                SnapshotSubcommand::Verify { path: None } => "SNAPSHOT VERIFY".to_string(),
+            },
+            Command::Relocate(cmd) => {
+                let mut line = "RELOCATE".to_string();
+                if let Some(ref path) = cmd.aof_path {
+                    line.push_str(&format!(" AOF {}", Self::quoted(path)?));
+                }
+                if let Some(ref dir) = cmd.snapshot_dir {
+                    line.push_str(&format!(" SNAPSHOTS {}", Self::quoted(dir)?));
+                }
+                if cmd.copy {
+                    line.push_str(" COPY");
+                }
+                line
+            }
+            Command::Wait(cmd) => format!("WAIT {} {}", cmd.numreplicas, cmd.timeout_ms),
+            Command::Select(cmd) => format!("SELECT {}", cmd.index),
+            Command::Auth(cmd) => match &cmd.username {
+                Some(username) => format!(
+                    "AUTH {} {}",
+                    Self::word(username)?,
+                    Self::quoted(&cmd.password)?
+                ),
+                None => format!("AUTH {}", Self::quoted(&cmd.password)?),
+            },
+            Command::Subscribe(cmd) => format!("SUBSCRIBE {}", Self::words(&cmd.channels)?),
+            Command::Unsubscribe(cmd) if cmd.channels.is_empty() => "UNSUBSCRIBE".to_string(),
+            Command::Unsubscribe(cmd) => format!("UNSUBSCRIBE {}", Self::words(&cmd.channels)?),
+            Command::Info(cmd) => match &cmd.section {
+                Some(section) => format!("INFO {}", Self::word(section)?),
+                None => "INFO".to_string(),
+            },
+            Command::Debug(DebugCommand::SetActiveExpire(enabled)) => {
+                format!("DEBUG SET-ACTIVE-EXPIRE {}", u8::from(*enabled))
+            }
+            Command::Debug(DebugCommand::Reload) => "DEBUG RELOAD".to_string(),
+            Command::Hello(cmd) => match cmd.protover {
+                Some(protover) => format!("HELLO {}", protover),
+                None => "HELLO".to_string(),
+            },
+            Command::Metrics => "METRICS".to_string(),
+            Command::Reset => "RESET".to_string(),
+            Command::FlushDb => "FLUSHDB".to_string(),
+            Command::BgRewriteAof => "BGREWRITEAOF".to_string(),
+            Command::Stats => "STATS".to_string(),
+            Command::Echo(cmd) => format!("ECHO {}", Self::encode_value(&cmd.message)?),
+            Command::Ping => "PING".to_string(),
+            Command::PingMessage(cmd) => match cmd.message {
+                Some(ref message) => format!("PING {}", Self::encode_value(message)?),
+                None => "PING".to_string(),
+            },
+        };
+
+        Ok(format!("{}\n", line))
+    }
+
+    // Decode a complete text reply (header plus any body lines), the inverse of `serialize_response`
+    // Only base64 VALUE framing is understood, not VALUEZ or raw replies
+    pub fn parse_response(reply: &str) -> Result<CommandResponse, ProtocolError> {
+        let mut lines = reply.lines();
+        let header = lines
+            .next()
+            .ok_or_else(|| ProtocolError::InvalidFormat("Empty response".to_string()))?;
+        let (kind, rest) = header.split_once(' ').unwrap_or((header, ""));
+
+        match kind {
+            "OK" => Ok(CommandResponse::Ok),
+            "TRUE" => Ok(CommandResponse::Bool(true)),
+            "FALSE" => Ok(CommandResponse::Bool(false)),
+            "PONG" => Ok(CommandResponse::Pong),
+            "NIL" => Ok(CommandResponse::Nil),
+            "ERROR" => Ok(CommandResponse::Error(rest.to_string())),
+            "MESSAGE" => {
+                let (channel, payload) = rest.split_once(' ').ok_or_else(|| {
+                    ProtocolError::InvalidFormat("MESSAGE requires channel and payload".to_string())
+                })?;
+                Ok(CommandResponse::Message {
+                    channel: channel.to_string(),
+                    payload: base64::Engine::decode(
+                        &base64::engine::general_purpose::STANDARD,
+                        payload,
+                    )?,
+                })
+            }
+            "VALUE" => Ok(CommandResponse::Value(base64::Engine::decode(
+                &base64::engine::general_purpose::STANDARD,
+                rest,
+            )?)),
+            "VERSIONED" => {
+                let (version, value) = rest.split_once(' ').ok_or_else(|| {
+                    ProtocolError::InvalidFormat("VERSIONED requires version and value".to_string())
+                })?;
+                Ok(CommandResponse::Versioned {
+                    version: version.parse().map_err(|_| {
+                        ProtocolError::InvalidFormat(format!("Invalid version: {}", version))
+                    })?,
+                    value: base64::Engine::decode(
+                        &base64::engine::general_purpose::STANDARD,
+                        value,
+                    )?,
+                })
+            }
+            "INTEGER" => rest
+                .parse::<i64>()
+                .map(CommandResponse::Integer)
+                .map_err(|_| ProtocolError::InvalidFormat(format!("Invalid integer: {}", rest))),
+            "KEYS" => Ok(CommandResponse::Keys(
+                lines.map(|key| key.to_string()).collect(),
+            )),
+            "MEMBERS" => Ok(CommandResponse::Members(
+                lines
+                    .map(|member| {
+                        base64::Engine::decode(&base64::engine::general_purpose::STANDARD, member)
+                    })
+                    .collect::<Result<_, _>>()?,
+            )),
+            "MAP" => lines
+                .map(|line| {
+                    line.split_once(' ')
+                        .map(|(field, value)| (field.to_string(), value.to_string()))
+                        .ok_or_else(|| {
+                            ProtocolError::InvalidFormat(format!("Invalid MAP field: {}", line))
+                        })
+                })
+                .collect::<Result<_, _>>()
+                .map(CommandResponse::Map),
+            "STATS" => {
+                let mut fields = std::collections::HashMap::new();
+                for field in rest.split_whitespace() {
+                    if let Some((name, value)) = field.split_once('=') {
+                        fields.insert(name, value);
+                    }
+                }
+
+                let field = |name: &str| {
+                    fields.get(name).copied().ok_or_else(|| {
+                        ProtocolError::InvalidFormat(format!("STATS missing {}", name))
+                    })
+                };
+                let invalid =
+                    |name: &str| ProtocolError::InvalidFormat(format!("Invalid {}", name));
+
+                Ok(CommandResponse::Stats {
+                    total_keys: field("total_keys")?
+                        .parse()
+                        .map_err(|_| invalid("total_keys"))?,
+                    memory_usage: field("memory_usage")?
+                        .parse()
+                        .map_err(|_| invalid("memory_usage"))?,
+                    hit_rate: field("hit_rate")?
+                        .parse()
+                        .map_err(|_| invalid("hit_rate"))?,
+                    total_operations: field("total_operations")?
+                        .parse()
+                        .map_err(|_| invalid("total_operations"))?,
+                    keyspace_hits: field("keyspace_hits")?
+                        .parse()
+                        .map_err(|_| invalid("keyspace_hits"))?,
+                    keyspace_misses: field("keyspace_misses")?
+                        .parse()
+                        .map_err(|_| invalid("keyspace_misses"))?,
+                    evicted_keys: field("evicted_keys")?
+                        .parse()
+                        .map_err(|_| invalid("evicted_keys"))?,
+                })
+            }
+            other => Err(ProtocolError::InvalidFormat(format!(
+                "Unknown response: {}",
+                other
+            ))),
+        }
+    }
+
+    // Number of lines following a reply header (KEYS, MEMBERS and MAP carry one line per item)
+    pub fn response_body_lines(header: &str) -> Result<usize, ProtocolError> {
+        match header.trim_end().split_once(' ') {
+            Some(("KEYS" | "MEMBERS" | "MAP", count)) => count
+                .parse::<usize>()
+                .map_err(|_| ProtocolError::InvalidFormat(format!("Invalid count: {}", count))),
+            _ => Ok(0),
+        }
+    }
+
+    // A single bare protocol argument: non-empty, free of whitespace and not opening a quote
+    fn word(arg: &str) -> Result<&str, ProtocolError> {
+        if arg.is_empty() || arg.starts_with('"') || arg.chars().any(char::is_whitespace) {
+            return Err(ProtocolError::InvalidFormat(format!(
+                "Argument can't be sent over the text protocol: {:?}",
+                arg
+            )));
+        }
+        Ok(arg)
+    }
+
+    // Quote an argument taken literally, so passwords may hold whitespace and quotes
+    fn quoted(arg: &str) -> Result<String, ProtocolError> {
+        if arg.contains(['\n', '\r']) {
+            return Err(ProtocolError::InvalidFormat(format!(
+                "Argument can't be sent over the text protocol: {:?}",
+                arg
+            )));
+        }
+        Ok(format!(
+            "\"{}\"",
+            arg.replace('\\', "\\\\").replace('"', "\\\"")
+        ))
+    }
+
+    fn words(args: &[String]) -> Result<String, ProtocolError> {
+        let args = args
+            .iter()
+            .map(|arg| Self::word(arg))
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(args.join(" "))
+    }
+
+    // Values always go out as base64, which `parse_value` decodes back to the same bytes
+    fn encode_value(value: &[u8]) -> Result<String, ProtocolError> {
+        if value.is_empty() {
+            return Err(ProtocolError::InvalidFormat(
+                "Empty values can't be sent over the text protocol".to_string(),
+            ));
+        }
+        Ok(base64::Engine::encode(
+            &base64::engine::general_purpose::STANDARD,
+            value,
+        ))
+    }
+
+    fn encode_values(values: &[Vec<u8>]) -> Result<String, ProtocolError> {
+        let values = values
+            .iter()
+            .map(|value| Self::encode_value(value))
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(values.join(" "))
+    }
 }
@@ -0,0 +1,75 @@
+use std::sync::Arc;
+
+use tokio::sync::broadcast;
+
+// Messages a subscriber may fall behind by before it starts missing some
+const CHANNEL_CAPACITY: usize = 1024;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Message {
+    pub channel: String,
+    pub payload: Vec<u8>,
+}
+
+// Fans published messages out to every subscribed connection, which keep the ones for
+// their own channels
+#[derive(Debug, Clone)]
+pub struct PubSub {
+    sender: broadcast::Sender<Arc<Message>>,
+}
+
+impl Default for PubSub {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl PubSub {
+    pub fn new() -> Self {
+        let (sender, _) = broadcast::channel(CHANNEL_CAPACITY);
+        Self { sender }
+    }
+
+    // Returns how many subscribers the message was handed to
+    pub fn publish(&self, channel: String, payload: Vec<u8>) -> usize {
+        self.sender
+            .send(Arc::new(Message { channel, payload }))
+            .unwrap_or(0)
+    }
+
+    pub fn subscribe(&self) -> broadcast::Receiver<Arc<Message>> {
+        self.sender.subscribe()
+    }
+
+    pub fn has_subscribers(&self) -> bool {
+        self.sender.receiver_count() > 0
+    }
+}
+
+// Publishes the keyspace events of one logical database to `__keyevent@<db>__:<event>`,
+// with the key as the payload
+#[derive(Debug, Clone)]
+pub struct KeyspaceNotifier {
+    database: usize,
+    pubsub: PubSub,
+}
+
+impl KeyspaceNotifier {
+    pub fn new(database: usize, pubsub: PubSub) -> Self {
+        Self { database, pubsub }
+    }
+
+    pub fn channel(database: usize, event: &str) -> String {
+        format!("__keyevent@{}__:{}", database, event)
+    }
+
+    pub fn notify(&self, event: &str, key: &str) {
+        // Called on every write, skip building the message when nobody listens
+        if !self.pubsub.has_subscribers() {
+            return;
+        }
+
+        self.pubsub
+            .publish(Self::channel(self.database, event), key.as_bytes().to_vec());
+    }
+}
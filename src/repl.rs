@@ -0,0 +1,151 @@
+use std::{net::SocketAddr, path::PathBuf};
+
+use rustyline::{DefaultEditor, error::ReadlineError};
+
+use crate::{
+    client::{BlazeClient, ClientConfig, ClientError, ClientResult},
+    commands::CommandResponse,
+};
+
+const HISTORY_FILE: &str = ".blazekvdb_history";
+
+// Interactive shell: read a line, send it as typed, print the decoded reply
+// Returns when the user types quit/exit or closes stdin
+pub async fn run(addr: SocketAddr) -> ClientResult<()> {
+    // A dropped connection is reopened on the next line, not retried behind the user's back
+    let client = BlazeClient::connect_with(
+        addr,
+        ClientConfig {
+            reconnect_attempts: 0,
+            ..ClientConfig::default()
+        },
+    )
+    .await?;
+
+    let mut editor = DefaultEditor::new().map_err(readline_error)?;
+    let history = history_path();
+    if let Some(path) = &history {
+        // Missing on first run
+        let _ = editor.load_history(path);
+    }
+
+    let prompt = format!("{}> ", addr);
+    loop {
+        // rustyline blocks on the terminal, keep it off the runtime's worker
+        let line = match tokio::task::block_in_place(|| editor.readline(&prompt)) {
+            Ok(line) => line,
+            Err(ReadlineError::Interrupted) => continue,
+            Err(ReadlineError::Eof) => break,
+            Err(e) => return Err(readline_error(e)),
+        };
+
+        let line = line.trim();
+        if line.is_empty() {
+            continue;
+        }
+        let _ = editor.add_history_entry(line);
+        if line.eq_ignore_ascii_case("quit") || line.eq_ignore_ascii_case("exit") {
+            break;
+        }
+
+        match client.execute_line(line).await {
+            Ok(response) => println!("{}", format_response(&response)),
+            Err(e) => println!("(error) {}", e),
+        }
+    }
+
+    if let Some(path) = &history {
+        let _ = editor.save_history(path);
+    }
+    Ok(())
+}
+
+// Render a reply the way redis-cli does: quoted values, numbered lists, one field per line
+pub fn format_response(response: &CommandResponse) -> String {
+    match response {
+        CommandResponse::Value(value) => quote(value),
+        CommandResponse::Ok => "OK".to_string(),
+        CommandResponse::Bool(value) => format!("(integer) {}", *value as i64),
+        CommandResponse::Integer(value) => format!("(integer) {}", value),
+        CommandResponse::Keys(keys) => numbered(keys.iter().map(|key| quote(key.as_bytes()))),
+        CommandResponse::Members(members) => numbered(members.iter().map(|member| quote(member))),
+        CommandResponse::Map(fields) => {
+            if fields.is_empty() {
+                return "(empty map)".to_string();
+            }
+            let width = fields
+                .iter()
+                .map(|(field, _)| field.len() + 1)
+                .max()
+                .unwrap_or(0);
+            fields
+                .iter()
+                .map(|(field, value)| format!("{:<width$}  {}", format!("{}:", field), value))
+                .collect::<Vec<_>>()
+                .join("\n")
+        }
+        CommandResponse::Stats {
+            total_keys,
+            memory_usage,
+            hit_rate,
+            total_operations,
+            keyspace_hits,
+            keyspace_misses,
+            evicted_keys,
+        } => [
+            format!("total_keys:        {}", total_keys),
+            format!("memory_usage:      {}", memory_usage),
+            format!("hit_rate:          {:.2}%", hit_rate * 100.0),
+            format!("total_operations:  {}", total_operations),
+            format!("keyspace_hits:     {}", keyspace_hits),
+            format!("keyspace_misses:   {}", keyspace_misses),
+            format!("evicted_keys:      {}", evicted_keys),
+        ]
+        .join("\n"),
+        CommandResponse::Pong => "PONG".to_string(),
+        CommandResponse::Nil => "(nil)".to_string(),
+        CommandResponse::Versioned { value, version } => {
+            format!("1) {}\n2) (integer) {}", quote(value), version)
+        }
+        CommandResponse::Error(message) => format!("(error) {}", message),
+        CommandResponse::Message { channel, payload } => format!(
+            "1) \"message\"\n2) {}\n3) {}",
+            quote(channel.as_bytes()),
+            quote(payload)
+        ),
+    }
+}
+
+// Printable ASCII as is, everything else escaped, so binary values stay on one line
+fn quote(bytes: &[u8]) -> String {
+    let escaped: String = bytes
+        .iter()
+        .flat_map(|byte| std::ascii::escape_default(*byte))
+        .map(char::from)
+        .collect();
+    format!("\"{}\"", escaped)
+}
+
+fn numbered(items: impl ExactSizeIterator<Item = String>) -> String {
+    if items.len() == 0 {
+        return "(empty list)".to_string();
+    }
+    // Keep the items aligned once the index grows a digit
+    let width = items.len().to_string().len();
+    items
+        .enumerate()
+        .map(|(index, item)| format!("{:>width$}) {}", index + 1, item))
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+fn history_path() -> Option<PathBuf> {
+    std::env::var_os("HOME").map(|home| PathBuf::from(home).join(HISTORY_FILE))
+}
+
+fn readline_error(error: ReadlineError) -> ClientError {
+    match error {
+        ReadlineError::Io(e) => ClientError::Io(e),
+        other => ClientError::Io(std::io::Error::other(other)),
+    }
+}
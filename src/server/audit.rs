@@ -0,0 +1,86 @@
+use std::net::SocketAddr;
+
+use tracing::warn;
+
+use crate::commands::CommandResponse;
+
+// Tracing target of security audit events, so they can be filtered or routed apart from
+// the regular log (e.g. RUST_LOG=audit=warn)
+pub const AUDIT_TARGET: &str = "audit";
+
+// Longest command name recorded, so junk sent by a scanner can't flood the audit log
+const MAX_COMMAND_LEN: usize = 32;
+
+// Why a request ended up in the audit log
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AuditReason {
+    AuthFailed,       // AUTH with a wrong password or an unknown user
+    Unauthenticated,  // Command sent before logging in
+    PermissionDenied, // Command outside the user's ACL
+    Oversized,        // Key, value or argument count over the configured limits
+    RateLimited,      // Pipeline depth or command rate quota exceeded
+    InvalidCommand,   // Unknown command or malformed arguments
+}
+
+impl AuditReason {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            AuditReason::AuthFailed => "auth_failed",
+            AuditReason::Unauthenticated => "unauthenticated",
+            AuditReason::PermissionDenied => "permission_denied",
+            AuditReason::Oversized => "oversized",
+            AuditReason::RateLimited => "rate_limited",
+            AuditReason::InvalidCommand => "invalid_command",
+        }
+    }
+
+    // Classify a rejected command by its reply, None for replies that aren't suspicious
+    // (a missing key, a wrong type, ...). Any failed AUTH counts, not only a wrong password
+    pub fn of_response(auth: bool, response: &CommandResponse) -> Option<Self> {
+        let CommandResponse::Error(message) = response else {
+            return None;
+        };
+
+        if auth {
+            Some(AuditReason::AuthFailed)
+        } else if message.starts_with("NOAUTH") {
+            Some(AuditReason::Unauthenticated)
+        } else if message.starts_with("NOPERM") {
+            Some(AuditReason::PermissionDenied)
+        } else if Self::is_oversized(message) {
+            Some(AuditReason::Oversized)
+        } else {
+            None
+        }
+    }
+
+    // Classify a line that didn't parse
+    pub fn of_parse_error(message: &str) -> Self {
+        if Self::is_oversized(message) {
+            AuditReason::Oversized
+        } else {
+            AuditReason::InvalidCommand
+        }
+    }
+
+    // Every size check words its error as "... too long/large/many"
+    fn is_oversized(message: &str) -> bool {
+        let message = message.to_lowercase();
+        ["too long", "too large", "too many"]
+            .iter()
+            .any(|needle| message.contains(needle))
+    }
+}
+
+// Record one audit event: who sent what, and why it was flagged
+pub fn record(client: SocketAddr, command: &str, reason: AuditReason, detail: &str) {
+    let command: String = command.chars().take(MAX_COMMAND_LEN).collect();
+    warn!(
+        target: AUDIT_TARGET,
+        client = %client,
+        command = %command,
+        reason = reason.as_str(),
+        "{}",
+        detail
+    );
+}
@@ -1,21 +1,32 @@
 use std::{
+    collections::HashSet,
     net::SocketAddr,
     sync::{
         Arc,
-        atomic::{AtomicU64, Ordering},
+        atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering},
     },
     time::{Duration, Instant},
 };
 
+use parking_lot::Mutex;
 use tokio::{
-    io::{AsyncBufReadExt, AsyncWrite, AsyncWriteExt, BufReader},
+    io::{AsyncBufReadExt, AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt, BufReader},
     net::TcpStream,
+    sync::broadcast,
 };
-use tracing::{debug, error, info, instrument, warn};
+use tokio_util::sync::CancellationToken;
+use tracing::{Instrument, debug, error, info, info_span, instrument, warn};
 
 use crate::{
-    commands::{CommandDispatcher, CommandResponse},
-    protocol::parser::ProtocolParser,
+    acl::{AclUser, DEFAULT_USER},
+    commands::{
+        Command, CommandDispatcher, CommandHandler, CommandResponse, proto::ProtocolMode,
+        set::SetCommand, setchunk::SetChunkCommand,
+    },
+    config::ServerConfig,
+    protocol::parser::{CommandRenames, KEEPALIVE, ProtocolError, ProtocolParser, ResponseOptions},
+    pubsub::Message,
+    server::audit::{self, AuditReason},
 };
 
 #[derive(Debug, Clone)]
@@ -24,7 +35,75 @@ pub struct ConnectionStats {
     pub bytes_received: u64,
     pub bytes_sent: u64,
     pub connection_duration: Duration,
-    pub last_command_time: Option<Instant>,
+    pub last_command_time: Option<Instant>, // None until the first command is processed
+    pub idle_time: Duration,                // Since the last command, or since connect
+    pub peak_pipeline_depth: u64,
+    pub quota_rejections: u64,
+    pub write_batches: u64, // Socket writes; below commands_processed when pipelines are batched
+    pub auth_failures: u64,
+    pub buffer_bytes: usize, // Currently allocated for this connection's buffers
+}
+
+// Flush a reply batch early once it reaches this size
+const MAX_BATCH_BYTES: usize = 64 * 1024;
+
+// Starting capacity of the reply buffer, also what it shrinks back to once flushed
+const INITIAL_REPLY_BUFFER: usize = 4096;
+
+// Most of a SETCHUNK payload read at once; the value grows as the bytes actually arrive
+const PAYLOAD_READ_SIZE: usize = 64 * 1024;
+
+// Per-connection fairness limits
+#[derive(Debug, Clone, PartialEq)]
+pub struct ConnectionLimits {
+    pub max_pipeline_depth: usize,
+    pub max_commands_per_sec: u64, // 0 = unlimited
+    pub disconnect_on_quota: bool,
+    pub write_timeout: Duration, // A client not draining a reply for this long is dropped
+    pub keepalive_interval: Option<Duration>, // Silence before a KEEPALIVE probe, None = off
+    pub keepalive_timeout: Duration, // Time to answer a probe before being dropped
+    pub read_buffer_size: usize, // Initial capacity of the line buffer
+    pub max_client_buffer_bytes: usize, // Cap on all connections' buffers together, 0 = none
+}
+
+impl Default for ConnectionLimits {
+    fn default() -> Self {
+        Self {
+            max_pipeline_depth: 1024,
+            max_commands_per_sec: 0,
+            disconnect_on_quota: false,
+            write_timeout: Duration::from_secs(300),
+            keepalive_interval: None,
+            keepalive_timeout: Duration::from_secs(10),
+            read_buffer_size: 4096,
+            max_client_buffer_bytes: 0,
+        }
+    }
+}
+
+impl From<&ServerConfig> for ConnectionLimits {
+    fn from(config: &ServerConfig) -> Self {
+        Self {
+            max_pipeline_depth: config.max_pipeline_depth,
+            max_commands_per_sec: config.max_commands_per_sec,
+            disconnect_on_quota: config.disconnect_on_quota,
+            write_timeout: Duration::from_secs(config.connection_timeout),
+            keepalive_interval: (config.server_keepalive_interval > 0)
+                .then(|| Duration::from_secs(config.server_keepalive_interval)),
+            keepalive_timeout: Duration::from_secs(config.server_keepalive_timeout),
+            read_buffer_size: config.read_buffer_size,
+            max_client_buffer_bytes: config.max_client_buffer_bytes,
+        }
+    }
+}
+
+// Per-connection state set by commands, put back to its initial values by RESET
+#[derive(Debug, Clone, Default)]
+struct Session {
+    response_options: ResponseOptions, // Negotiated via COMPRESS, PROTO and ENCODING
+    database: usize,                   // Logical database commands run against (SELECT)
+    user: Option<Arc<AclUser>>,        // Set by AUTH, None until the connection authenticates
+    subscriptions: HashSet<String>,    // Channels whose messages are pushed (SUBSCRIBE)
 }
 
 // handles individual TCP connections
@@ -35,34 +114,186 @@ pub struct ConnectionHandler {
     commands_processed: AtomicU64,
     bytes_received: AtomicU64,
     bytes_sent: AtomicU64,
+    write_batches: AtomicU64,
     connection_start: Instant,
+    last_command: Mutex<Option<Instant>>,
+
+    session: Mutex<Session>,
+
+    // Quota enforcement
+    limits: ConnectionLimits,
+    rate_window: Mutex<(Instant, u64)>, // (window start, commands in window)
+    peak_pipeline_depth: AtomicU64,
+    quota_rejections: AtomicU64,
+
+    // Record rejected and suspicious requests under the audit target
+    audit_log: bool,
+    auth_failures: AtomicU64,
+
+    // Cancelled on server shutdown; checked between commands so replies are never cut off
+    shutdown: CancellationToken,
+
+    // Capacity of this connection's buffers, and the total over every connection it is
+    // counted in; given back when the handler is dropped
+    buffer_bytes: AtomicUsize,
+    client_buffer_bytes: Arc<AtomicUsize>,
+
+    // Set when a SETCHUNK payload was cut short: what follows isn't a command boundary
+    payload_lost: AtomicBool,
+
+    // Commands renamed or disabled by the operator
+    renames: Arc<CommandRenames>,
 }
 
 impl ConnectionHandler {
     pub fn new(dispatcher: Arc<CommandDispatcher>) -> Self {
+        let session = Self::initial_session(&dispatcher);
+        let client_buffer_bytes = dispatcher.client_buffer_bytes();
+
         Self {
             dispatcher,
             commands_processed: AtomicU64::new(0),
             bytes_received: AtomicU64::new(0),
             bytes_sent: AtomicU64::new(0),
+            write_batches: AtomicU64::new(0),
             connection_start: Instant::now(),
+            last_command: Mutex::new(None),
+            session: Mutex::new(session),
+            limits: ConnectionLimits::default(),
+            rate_window: Mutex::new((Instant::now(), 0)),
+            peak_pipeline_depth: AtomicU64::new(0),
+            quota_rejections: AtomicU64::new(0),
+            audit_log: false,
+            auth_failures: AtomicU64::new(0),
+            shutdown: CancellationToken::new(),
+            buffer_bytes: AtomicUsize::new(0),
+            client_buffer_bytes,
+            payload_lost: AtomicBool::new(false),
+            renames: Arc::new(CommandRenames::default()),
         }
     }
 
+    pub fn with_limits(mut self, limits: ConnectionLimits) -> Self {
+        self.limits = limits;
+        self
+    }
+
+    pub fn with_audit_log(mut self, enabled: bool) -> Self {
+        self.audit_log = enabled;
+        self
+    }
+
+    pub fn with_shutdown(mut self, shutdown: CancellationToken) -> Self {
+        self.shutdown = shutdown;
+        self
+    }
+
+    pub fn with_renamed_commands(mut self, renames: Arc<CommandRenames>) -> Self {
+        self.renames = renames;
+        self
+    }
+
     // handle a TCP connection
     #[instrument(skip(self, stream), fields(addr = %addr))]
     pub async fn handle_connection(&self, stream: TcpStream, addr: SocketAddr) {
         info!("New connection established");
 
-        let mut buffer = Vec::with_capacity(4096);
+        let mut buffer = Vec::with_capacity(self.limits.read_buffer_size);
+        let mut pending = Vec::with_capacity(INITIAL_REPLY_BUFFER); // Encoded replies not yet written
         let (read_half, mut write_half) = stream.into_split();
         let mut reader = BufReader::new(read_half);
 
+        // Only held while subscribed, so publishers skip idle connections entirely
+        let mut messages: Option<broadcast::Receiver<Arc<Message>>> = None;
+        let mut partial_line = false;
+
+        // Server keepalive: when the client was last heard from and whether a probe is out
+        let mut last_heard = Instant::now();
+        let mut probe_sent: Option<Instant> = None;
+        let mut heard_len = 0; // Bytes of a partial line already counted as activity
+
         loop {
-            buffer.clear();
+            // A message pushed mid-read leaves the start of the line in the buffer
+            if !partial_line {
+                buffer.clear();
+                heard_len = 0;
+            }
+            partial_line = false;
+
+            // Replies to a pipeline go out in one write, once no complete command is left
+            // buffered (reading further could block) or the batch grows too large
+            let more_buffered = reader.buffer().contains(&b'\n');
+            if !pending.is_empty()
+                && (!more_buffered
+                    || pending.len() >= MAX_BATCH_BYTES
+                    || self.shutdown.is_cancelled())
+                && let Err(e) = self.flush_responses(&mut write_half, &mut pending).await
+            {
+                error!("Failed to send response: {}", e);
+                break;
+            }
+
+            // Nothing left to do before waiting on the client: give back what a large
+            // command or reply grew the buffers to
+            if !more_buffered {
+                buffer.shrink_to(self.limits.read_buffer_size);
+                pending.shrink_to(INITIAL_REPLY_BUFFER);
+            }
+            self.track_buffers(buffer.capacity() + pending.capacity() + reader.buffer().len());
+
+            // Safe point: the previous reply is fully sent, pipelined commands are dropped
+            if self.shutdown.is_cancelled() {
+                debug!("Shutdown requested, closing connection");
+                break;
+            }
+
+            // Read line from client, unless shutdown arrives while idle
+            let read = tokio::select! {
+                read = reader.read_until(b'\n', &mut buffer) => read,
+                message = Self::next_message(&mut messages) => {
+                    partial_line = true;
+                    if let Some(message) = message
+                        && self.session.lock().subscriptions.contains(&message.channel)
+                    {
+                        let response = CommandResponse::Message {
+                            channel: message.channel.clone(),
+                            payload: message.payload.clone(),
+                        };
+                        if let Err(e) = self.queue_response(&mut pending, response) {
+                            error!("Failed to encode message: {}", e);
+                            break;
+                        }
+                    }
+                    continue;
+                }
+                _ = Self::keepalive_due(self.keepalive_deadline(last_heard, probe_sent)) => {
+                    partial_line = true;
 
-            // Read line from client
-            match reader.read_until(b'\n', &mut buffer).await {
+                    // Part of a line arrived meanwhile, so the client is still there
+                    if buffer.len() > heard_len {
+                        heard_len = buffer.len();
+                        last_heard = Instant::now();
+                        probe_sent = None;
+                        continue;
+                    }
+
+                    if probe_sent.is_some() {
+                        warn!("Keepalive probe unanswered, closing connection");
+                        break;
+                    }
+
+                    debug!("Connection silent, sending keepalive probe");
+                    self.queue_keepalive(&mut pending);
+                    probe_sent = Some(Instant::now());
+                    continue;
+                }
+                _ = self.shutdown.cancelled() => {
+                    debug!("Shutdown requested while idle, closing connection");
+                    break;
+                }
+            };
+
+            match read {
                 Ok(0) => {
                     debug!("Client disconnected");
                     break;
@@ -70,24 +301,90 @@ impl ConnectionHandler {
                 Ok(bytes_read) => {
                     self.bytes_received
                         .fetch_add(bytes_read as u64, Ordering::Relaxed);
+                    last_heard = Instant::now();
+                    probe_sent = None;
 
+                    // Borrows the buffer, only a line with invalid UTF-8 is copied
                     let message = String::from_utf8_lossy(&buffer);
                     let message = message.trim();
 
-                    if message.is_empty() {
+                    if message.is_empty() || message.eq_ignore_ascii_case(KEEPALIVE) {
                         continue;
                     }
 
+                    self.track_buffers(
+                        buffer.capacity() + pending.capacity() + reader.buffer().len(),
+                    );
+                    if let Err(reason) = self.check_buffers() {
+                        warn!("Client buffer limit exceeded: {}", reason);
+                        self.audit(addr, message, AuditReason::RateLimited, &reason);
+                        let _ = self.queue_response(&mut pending, CommandResponse::Error(reason));
+                        break;
+                    }
+
                     debug!("Received command: {}", message);
-                    // Process command
-                    let response = self.process_command(message).await;
-                    // Send command
-                    if let Err(e) = self.send_response(&mut write_half, response).await {
-                        error!("Failed to send response: {}", e);
+
+                    // Complete lines still buffered are commands pipelined behind this one
+                    let pipelined = reader.buffer().iter().filter(|&&b| b == b'\n').count();
+
+                    if let Err(reason) = self.check_quota(pipelined) {
+                        warn!("Connection quota exceeded: {}", reason);
+                        self.audit(addr, message, AuditReason::RateLimited, &reason);
+                        let response = CommandResponse::Error(reason);
+                        let queued = self.queue_response(&mut pending, response);
+
+                        if self.limits.disconnect_on_quota || queued.is_err() {
+                            break;
+                        }
+                        continue;
+                    }
+
+                    // Process command, inside a span carrying the client's correlation id
+                    // when one is given; untagged requests create no extra span
+                    let response = match ProtocolParser::split_correlation_id(message) {
+                        Ok((Some(id), command)) => {
+                            let span = info_span!("request", correlation_id = %id);
+                            self.process_command(
+                                command,
+                                addr,
+                                &mut reader,
+                                &mut write_half,
+                                &mut pending,
+                            )
+                            .instrument(span)
+                            .await
+                        }
+                        Ok((None, command)) => {
+                            self.process_command(
+                                command,
+                                addr,
+                                &mut reader,
+                                &mut write_half,
+                                &mut pending,
+                            )
+                            .await
+                        }
+                        Err(e) => self.parse_error(message, addr, e),
+                    };
+                    if let Err(e) = self.queue_response(&mut pending, response) {
+                        error!("Failed to encode response: {}", e);
+                        break;
+                    }
+                    if self.payload_lost.load(Ordering::Relaxed) {
+                        warn!("SETCHUNK payload not fully read, closing connection");
                         break;
                     }
 
+                    match (self.is_subscribed(), messages.is_some()) {
+                        (true, false) => messages = Some(self.dispatcher.pubsub().subscribe()),
+                        (false, true) => messages = None,
+                        _ => {}
+                    }
+
                     self.commands_processed.fetch_add(1, Ordering::Relaxed);
+                    *self.last_command.lock() = Some(Instant::now());
+                    // A long-blocking command (BLPOP, WAIT) doesn't count as silence
+                    last_heard = Instant::now();
                 }
                 Err(e) => {
                     error!("Error reading from connection: {}", e);
@@ -96,6 +393,11 @@ impl ConnectionHandler {
             }
         }
 
+        // Best effort for replies queued right before closing (quota disconnect, ...)
+        if !pending.is_empty() {
+            let _ = self.flush_responses(&mut write_half, &mut pending).await;
+        }
+
         let duration = self.connection_start.elapsed();
         let commands = self.commands_processed.load(Ordering::Relaxed);
 
@@ -105,48 +407,454 @@ impl ConnectionHandler {
         )
     }
 
-    // Process a single command
-    async fn process_command(&self, message: &str) -> CommandResponse {
-        match ProtocolParser::parse_command(message) {
+    // Enforce pipeline depth and the per-second command budget
+    fn check_quota(&self, pipelined: usize) -> Result<(), String> {
+        self.peak_pipeline_depth
+            .fetch_max(pipelined as u64, Ordering::Relaxed);
+
+        if pipelined > self.limits.max_pipeline_depth {
+            self.quota_rejections.fetch_add(1, Ordering::Relaxed);
+            return Err(format!(
+                "Pipeline depth {} exceeds limit {}",
+                pipelined, self.limits.max_pipeline_depth
+            ));
+        }
+
+        if self.limits.max_commands_per_sec > 0 {
+            let mut window = self.rate_window.lock();
+            if window.0.elapsed() >= Duration::from_secs(1) {
+                *window = (Instant::now(), 0);
+            }
+
+            if window.1 >= self.limits.max_commands_per_sec {
+                self.quota_rejections.fetch_add(1, Ordering::Relaxed);
+                return Err(format!(
+                    "Command rate exceeds {} per second",
+                    self.limits.max_commands_per_sec
+                ));
+            }
+            window.1 += 1;
+        }
+
+        Ok(())
+    }
+
+    // Record what this connection's buffers hold now, in its own and the shared total
+    fn track_buffers(&self, bytes: usize) {
+        let previous = self.buffer_bytes.swap(bytes, Ordering::Relaxed);
+        if bytes > previous {
+            self.client_buffer_bytes
+                .fetch_add(bytes - previous, Ordering::Relaxed);
+        } else {
+            self.client_buffer_bytes
+                .fetch_sub(previous - bytes, Ordering::Relaxed);
+        }
+    }
+
+    // Over the shared cap, the connections holding more than an even share of it are
+    // dropped, so one client with a huge command or pipeline can't starve the others
+    fn check_buffers(&self) -> Result<(), String> {
+        let limit = self.limits.max_client_buffer_bytes;
+        if limit == 0 || self.client_buffer_bytes.load(Ordering::Relaxed) <= limit {
+            return Ok(());
+        }
+
+        let clients = self
+            .dispatcher
+            .connected_clients()
+            .load(Ordering::Relaxed)
+            .max(1);
+        let own = self.buffer_bytes.load(Ordering::Relaxed);
+        if own > limit / clients {
+            self.quota_rejections.fetch_add(1, Ordering::Relaxed);
+            return Err(format!(
+                "Client buffers of {} bytes exceed the fair share of max_client_buffer_bytes {}",
+                own, limit
+            ));
+        }
+
+        Ok(())
+    }
+
+    // Process a single command, `reader` supplies the raw payload of a SETCHUNK
+    async fn process_command<R, W>(
+        &self,
+        message: &str,
+        addr: SocketAddr,
+        reader: &mut R,
+        writer: &mut W,
+        pending: &mut Vec<u8>,
+    ) -> CommandResponse
+    where
+        R: AsyncRead + Unpin,
+        W: AsyncWrite + Unpin + Send,
+    {
+        let mode = self.session.lock().response_options.mode;
+
+        match ProtocolParser::parse_command_in(
+            message,
+            mode,
+            self.dispatcher.limits(),
+            &self.renames,
+        ) {
             Ok(command) => {
                 debug!("Parsed command successfully: {:?}", command);
-                self.dispatcher.execute(command).await
+
+                let command = match command {
+                    Command::SetChunk(cmd) => match self.read_chunked_value(cmd, reader).await {
+                        Ok(set) => Command::Set(set),
+                        Err(e) => {
+                            self.audit(addr, message, AuditReason::of_parse_error(&e), &e);
+                            return CommandResponse::Error(e);
+                        }
+                    },
+                    command => command,
+                };
+
+                // Replies batched ahead of a command that may block go out now, not once
+                // it returns
+                if command.may_block()
+                    && !pending.is_empty()
+                    && let Err(e) = self.flush_responses(writer, pending).await
+                {
+                    return CommandResponse::Error(format!("Failed to send response: {}", e));
+                }
+
+                // Connection-level settings are applied only once acknowledged
+                let setting = match &command {
+                    Command::Compress(_)
+                    | Command::Proto(_)
+                    | Command::Encoding(_)
+                    | Command::Select(_)
+                    | Command::Auth(_)
+                    | Command::Subscribe(_)
+                    | Command::Unsubscribe(_)
+                    | Command::Reset => Some(command.clone()),
+                    _ => None,
+                };
+
+                let auth = matches!(command, Command::Auth(_));
+                let (database, user) = {
+                    let session = self.session.lock();
+                    (session.database, session.user.clone())
+                };
+                // A blocked BLPOP gives up on shutdown, so draining never waits on it
+                let response = self
+                    .dispatcher
+                    .execute_until(database, user.as_deref(), command, Some(&self.shutdown))
+                    .await;
+
+                if let Some(reason) = AuditReason::of_response(auth, &response) {
+                    if reason == AuditReason::AuthFailed {
+                        self.auth_failures.fetch_add(1, Ordering::Relaxed);
+                    }
+                    if let CommandResponse::Error(ref e) = response {
+                        self.audit(addr, message, reason, e);
+                    }
+                }
+
+                if let Some(setting) = setting
+                    && response == CommandResponse::Ok
+                {
+                    let mut session = self.session.lock();
+                    match setting {
+                        Command::Compress(cmd) => {
+                            session.response_options.compression =
+                                cmd.algorithm.map(|algorithm| (algorithm, cmd.threshold));
+                        }
+                        Command::Proto(cmd) => session.response_options.mode = cmd.mode,
+                        Command::Encoding(cmd) => session.response_options.encoding = cmd.encoding,
+                        Command::Select(cmd) => session.database = cmd.index,
+                        Command::Auth(cmd) => {
+                            let name = cmd.username.as_deref().unwrap_or(DEFAULT_USER);
+                            session.user = self.dispatcher.acl().user(name);
+                        }
+                        Command::Subscribe(cmd) => {
+                            session.subscriptions.extend(cmd.channels);
+                            return CommandResponse::Integer(session.subscriptions.len() as i64);
+                        }
+                        Command::Unsubscribe(cmd) => {
+                            if cmd.channels.is_empty() {
+                                session.subscriptions.clear();
+                            }
+                            for channel in &cmd.channels {
+                                session.subscriptions.remove(channel);
+                            }
+                            return CommandResponse::Integer(session.subscriptions.len() as i64);
+                        }
+                        Command::Reset => *session = Self::initial_session(&self.dispatcher),
+                        _ => {}
+                    }
+                }
+
+                response
+            }
+            Err(e) => self.parse_error(message, addr, e),
+        }
+    }
+
+    fn parse_error(&self, message: &str, addr: SocketAddr, e: ProtocolError) -> CommandResponse {
+        warn!("Failed to parse command '{}': {}", message, e);
+        // Worded like Redis so migrating clients recognize it
+        let reply = match e {
+            ProtocolError::UnsupportedOption(_) => e.to_string(),
+            _ => format!("Parse error: {}", e),
+        };
+        let e = e.to_string();
+        self.audit(addr, message, AuditReason::of_parse_error(&e), &e);
+        CommandResponse::Error(reply)
+    }
+
+    // Read the payload announced by a SETCHUNK into a buffer sized for it up front
+    // An upload over the limits is drained unbuffered, keeping the commands behind it framed
+    async fn read_chunked_value<R>(
+        &self,
+        cmd: SetChunkCommand,
+        reader: &mut R,
+    ) -> Result<SetCommand, String>
+    where
+        R: AsyncRead + Unpin,
+    {
+        if let Err(e) = cmd.validate_with(self.dispatcher.limits()) {
+            self.drain_payload(reader, cmd.total_len).await?;
+            return Err(e.to_string());
+        }
+
+        // Counted on top of what the connection's other buffers held for this command
+        let held = self.buffer_bytes.load(Ordering::Relaxed);
+        let mut value = Vec::new();
+        while value.len() < cmd.total_len {
+            let start = value.len();
+            value.resize(start + (cmd.total_len - start).min(PAYLOAD_READ_SIZE), 0);
+            let read = self.read_payload(reader, &mut value[start..]).await?;
+            value.truncate(start + read);
+
+            self.track_buffers(held + value.capacity());
+            if let Err(reason) = self.check_buffers() {
+                warn!("Client buffer limit exceeded: {}", reason);
+                self.payload_lost.store(true, Ordering::Relaxed);
+                return Err(reason);
+            }
+        }
+
+        Ok(cmd.into_set(value))
+    }
+
+    // Skip a payload that won't be stored, keeping the stream at a command boundary
+    async fn drain_payload<R>(&self, reader: &mut R, mut remaining: usize) -> Result<(), String>
+    where
+        R: AsyncRead + Unpin,
+    {
+        let mut scratch = vec![0; remaining.min(PAYLOAD_READ_SIZE)];
+        while remaining > 0 {
+            let len = remaining.min(scratch.len());
+            remaining -= self.read_payload(reader, &mut scratch[..len]).await?;
+        }
+        Ok(())
+    }
+
+    // One read of payload bytes, given up on shutdown or once the client sends nothing
+    // for connection_timeout; either way the rest of the payload is lost
+    async fn read_payload<R>(&self, reader: &mut R, buf: &mut [u8]) -> Result<usize, String>
+    where
+        R: AsyncRead + Unpin,
+    {
+        let read = tokio::select! {
+            read = reader.read(buf) => {
+                read.map_err(|e| format!("Failed to read SETCHUNK payload: {}", e))
+            }
+            _ = tokio::time::sleep(self.limits.write_timeout) => {
+                Err("Timed out reading SETCHUNK payload".to_string())
+            }
+            _ = self.shutdown.cancelled() => {
+                Err("Server shutting down".to_string())
+            }
+        };
+
+        match read {
+            Ok(0) => Err("SETCHUNK payload ended early".to_string()),
+            Ok(read) => {
+                self.bytes_received
+                    .fetch_add(read as u64, Ordering::Relaxed);
+                Ok(read)
             }
             Err(e) => {
-                warn!("Failed to parse command '{}': {}", message, e);
-                CommandResponse::Error(format!("Parse error: {}", e))
+                self.payload_lost.store(true, Ordering::Relaxed);
+                Err(e)
             }
         }
     }
 
-    async fn send_response<W>(
+    // Send a rejected request to the audit log, when enabled
+    // Only the command name is recorded: arguments may hold passwords or user data
+    fn audit(&self, addr: SocketAddr, message: &str, reason: AuditReason, detail: &str) {
+        if !self.audit_log {
+            return;
+        }
+
+        let command = message.split_whitespace().next().unwrap_or_default();
+        let command = command.to_uppercase();
+        if reason == AuditReason::AuthFailed {
+            let attempts = self.auth_failures.load(Ordering::Relaxed);
+            let detail = format!("{} (attempt {} on this connection)", detail, attempts);
+            audit::record(addr, &command, reason, &detail);
+        } else {
+            audit::record(addr, &command, reason, detail);
+        }
+    }
+
+    // When the keepalive next acts: a probe after `keepalive_interval` of silence, then
+    // closing once `keepalive_timeout` passes without an answer
+    fn keepalive_deadline(
         &self,
-        writer: &mut W,
+        last_heard: Instant,
+        probe_sent: Option<Instant>,
+    ) -> Option<Instant> {
+        let interval = self.limits.keepalive_interval?;
+        Some(match probe_sent {
+            Some(sent) => sent + self.limits.keepalive_timeout,
+            None => last_heard + interval,
+        })
+    }
+
+    async fn keepalive_due(deadline: Option<Instant>) {
+        match deadline {
+            Some(deadline) => tokio::time::sleep_until(deadline.into()).await,
+            None => std::future::pending().await,
+        }
+    }
+
+    fn queue_keepalive(&self, pending: &mut Vec<u8>) {
+        match self.session.lock().response_options.mode {
+            ProtocolMode::Text => pending.extend_from_slice(format!("{}\n", KEEPALIVE).as_bytes()),
+            ProtocolMode::Json => pending.extend_from_slice(b"{\"status\":\"keepalive\"}\n"),
+        }
+    }
+
+    // Next published message, never resolves while unsubscribed
+    // None when messages were dropped because this connection fell behind
+    async fn next_message(
+        messages: &mut Option<broadcast::Receiver<Arc<Message>>>,
+    ) -> Option<Arc<Message>> {
+        let Some(receiver) = messages else {
+            return std::future::pending().await;
+        };
+
+        match receiver.recv().await {
+            Ok(message) => Some(message),
+            Err(broadcast::error::RecvError::Lagged(skipped)) => {
+                warn!("Subscriber fell behind, dropped {} messages", skipped);
+                None
+            }
+            // The hub lives as long as the dispatcher, which outlives every connection
+            Err(broadcast::error::RecvError::Closed) => std::future::pending().await,
+        }
+    }
+
+    // State of a fresh connection: default encoding, database 0, no subscriptions, and
+    // logged in as the default user unless authentication is required
+    fn initial_session(dispatcher: &CommandDispatcher) -> Session {
+        Session {
+            user: dispatcher.acl().initial_user(),
+            ..Default::default()
+        }
+    }
+
+    // Encode a reply into the pending batch using the connection's current options
+    fn queue_response(
+        &self,
+        pending: &mut Vec<u8>,
         response: CommandResponse,
+    ) -> Result<(), ProtocolError> {
+        let options = self.session.lock().response_options.clone();
+        let encoded = ProtocolParser::serialize_response_bytes(&response, &options)?;
+        pending.extend_from_slice(&encoded);
+        Ok(())
+    }
+
+    // Write out every pending reply with a single write + flush
+    // The batch is discarded even on failure: a peer that can't take it won't take a retry
+    async fn flush_responses<W>(
+        &self,
+        writer: &mut W,
+        pending: &mut Vec<u8>,
     ) -> Result<(), Box<dyn std::error::Error + Send + Sync>>
     where
         W: AsyncWrite + Unpin + Send,
     {
-        let response_str = ProtocolParser::serialize_response(&response)?;
+        let sent = pending.len();
+        let write = async {
+            writer.write_all(pending).await?;
+            writer.flush().await
+        };
 
-        writer.write_all(response_str.as_bytes()).await?;
-        writer.flush().await?;
+        // A half-open peer (gone, or no longer reading) must not pin the task forever
+        let result = tokio::time::timeout(self.limits.write_timeout, write).await;
+        pending.clear();
 
-        self.bytes_sent
-            .fetch_add(response_str.len() as u64, Ordering::Relaxed);
+        match result {
+            Ok(written) => written?,
+            Err(_) => {
+                return Err(format!(
+                    "write timed out after {:?}, peer is not reading",
+                    self.limits.write_timeout
+                )
+                .into());
+            }
+        }
 
-        debug!("Response sent: {} bytes", response_str.len());
+        self.bytes_sent.fetch_add(sent as u64, Ordering::Relaxed);
+        self.write_batches.fetch_add(1, Ordering::Relaxed);
+
+        debug!("Responses sent: {} bytes", sent);
         Ok(())
     }
 
+    // Time since the last processed command, or since connect if there was none
+    pub fn idle_time(&self) -> Duration {
+        self.last_command
+            .lock()
+            .unwrap_or(self.connection_start)
+            .elapsed()
+    }
+
+    // Ask the connection to close at its next safe point, same as a server shutdown
+    pub fn close(&self) {
+        self.shutdown.cancel();
+    }
+
+    pub fn is_closing(&self) -> bool {
+        self.shutdown.is_cancelled()
+    }
+
+    // Subscribed connections legitimately stay quiet, the idle reaper leaves them alone
+    pub fn is_subscribed(&self) -> bool {
+        !self.session.lock().subscriptions.is_empty()
+    }
+
     // Get connection statistics
     pub fn stats(&self) -> ConnectionStats {
+        let last_command_time = *self.last_command.lock();
+
         ConnectionStats {
             commands_processed: self.commands_processed.load(Ordering::Relaxed),
             bytes_received: self.bytes_received.load(Ordering::Relaxed),
             bytes_sent: self.bytes_sent.load(Ordering::Relaxed),
             connection_duration: self.connection_start.elapsed(),
-            last_command_time: Some(Instant::now()),
+            last_command_time,
+            idle_time: last_command_time.unwrap_or(self.connection_start).elapsed(),
+            peak_pipeline_depth: self.peak_pipeline_depth.load(Ordering::Relaxed),
+            quota_rejections: self.quota_rejections.load(Ordering::Relaxed),
+            auth_failures: self.auth_failures.load(Ordering::Relaxed),
+            buffer_bytes: self.buffer_bytes.load(Ordering::Relaxed),
+            write_batches: self.write_batches.load(Ordering::Relaxed),
         }
     }
 }
+
+impl Drop for ConnectionHandler {
+    // Give this connection's share back to the shared buffer total
+    fn drop(&mut self) {
+        self.track_buffers(0);
+    }
+}
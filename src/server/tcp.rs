@@ -1,19 +1,54 @@
 use std::{
+    collections::HashMap,
     net::SocketAddr,
     sync::{
         Arc,
-        atomic::{AtomicUsize, Ordering},
+        atomic::{AtomicU64, AtomicUsize, Ordering},
     },
+    time::Duration,
 };
 
-use tokio::{net::TcpListener, signal};
-use tracing::{error, info, instrument};
+use parking_lot::Mutex;
+use tokio::{
+    net::{TcpListener, TcpSocket},
+    signal,
+    task::JoinSet,
+};
+use tokio_util::{sync::CancellationToken, task::TaskTracker};
+use tracing::{error, info, instrument, warn};
 
-use crate::{commands::CommandDispatcher, server::connection::ConnectionHandler};
+use crate::{
+    commands::{ClientTraffic, CommandDispatcher},
+    protocol::parser::CommandRenames,
+    server::connection::{ConnectionHandler, ConnectionLimits},
+};
 
 pub struct TcpServer {
-    dispatcher: Arc<CommandDispatcher>,
     bind_addr: SocketAddr,
+    listen_backlog: u32,
+    accept_tasks: usize,
+    shutdown_timeout: Duration,
+    acceptor: Acceptor,
+}
+
+// State shared by every accept loop
+#[derive(Clone)]
+struct Acceptor {
+    dispatcher: Arc<CommandDispatcher>,
+    connection_limits: ConnectionLimits,
+    tcp_nodelay: bool,
+    audit_log: bool,
+    renames: Arc<CommandRenames>,
+
+    // Graceful shutdown: stops accept loops, connections get a child token each
+    shutdown: CancellationToken,
+    connections: TaskTracker,
+
+    // Open connections by id, scanned by the idle reaper
+    registry: Arc<Mutex<HashMap<u64, Arc<ConnectionHandler>>>>,
+    next_connection_id: Arc<AtomicU64>,
+    idle_timeout: Option<Duration>, // None = idle connections are kept
+    idle_check_interval: Duration,
 
     // Server metrics
     total_connections: Arc<AtomicUsize>,
@@ -24,25 +59,124 @@ pub struct TcpServer {
 pub struct ServerStats {
     pub total_connections: usize,
     pub active_connections: usize,
+    pub accept_errors: usize,
+    pub accept_resource_errors: usize, // Out of file descriptors or memory, accepts paused
+    pub reaped_connections: usize,     // Closed by the idle reaper
+    pub client_buffer_bytes: usize,    // Allocated for the open connections' buffers
+
+    // Lifetime traffic, closed connections included
+    pub total_commands_processed: u64,
+    pub total_bytes_received: u64,
+    pub total_bytes_sent: u64,
 }
 
 impl TcpServer {
     // Create new TCP server
     pub fn new(dispatcher: Arc<CommandDispatcher>, bind_addr: SocketAddr) -> Self {
+        // Shared with the dispatcher so METRICS can report it
+        let active_connections = dispatcher.connected_clients();
         Self {
-            dispatcher,
             bind_addr,
-            total_connections: AtomicUsize::new(0).into(),
-            active_connections: AtomicUsize::new(0).into(),
+            listen_backlog: 1024,
+            accept_tasks: 1,
+            shutdown_timeout: Duration::from_secs(30),
+            acceptor: Acceptor {
+                dispatcher,
+                connection_limits: ConnectionLimits::default(),
+                tcp_nodelay: true,
+                audit_log: false,
+                renames: Arc::new(CommandRenames::default()),
+                shutdown: CancellationToken::new(),
+                connections: TaskTracker::new(),
+                registry: Arc::new(Mutex::new(HashMap::new())),
+                next_connection_id: AtomicU64::new(0).into(),
+                idle_timeout: None,
+                idle_check_interval: Duration::from_secs(10),
+                total_connections: AtomicUsize::new(0).into(),
+                active_connections,
+            },
         }
     }
 
+    // Per-connection pipeline depth and command rate limits
+    pub fn with_connection_limits(mut self, limits: ConnectionLimits) -> Self {
+        self.acceptor.connection_limits = limits;
+        self
+    }
+
+    // Log rejected requests (auth failures, quota hits, ...) under the audit target
+    pub fn with_audit_log(mut self, enabled: bool) -> Self {
+        self.acceptor.audit_log = enabled;
+        self
+    }
+
+    // Run commands under operator-chosen names, or not at all (security.renamed_commands)
+    pub fn with_renamed_commands(mut self, renames: CommandRenames) -> Self {
+        self.acceptor.renames = Arc::new(renames);
+        self
+    }
+
+    // Kernel queue size for connections not yet accepted
+    pub fn with_listen_backlog(mut self, backlog: u32) -> Self {
+        self.listen_backlog = backlog;
+        self
+    }
+
+    // Number of concurrent accept loops
+    pub fn with_accept_tasks(mut self, tasks: usize) -> Self {
+        self.accept_tasks = tasks.max(1);
+        self
+    }
+
+    // Set TCP_NODELAY on accepted sockets
+    pub fn with_tcp_nodelay(mut self, enabled: bool) -> Self {
+        self.acceptor.tcp_nodelay = enabled;
+        self
+    }
+
+    // How long shutdown waits for in-flight commands before giving up
+    pub fn with_shutdown_timeout(mut self, timeout: Duration) -> Self {
+        self.shutdown_timeout = timeout;
+        self
+    }
+
+    // Close connections that sent no command for `timeout` (zero disables the reaper)
+    pub fn with_idle_timeout(mut self, timeout: Duration) -> Self {
+        self.acceptor.idle_timeout = (!timeout.is_zero()).then_some(timeout);
+        self
+    }
+
+    // How often the reaper looks for idle connections
+    pub fn with_idle_check_interval(mut self, interval: Duration) -> Self {
+        self.acceptor.idle_check_interval = interval.max(Duration::from_millis(1));
+        self
+    }
+
+    // Bind the listening socket with the configured backlog
+    pub fn bind(&self) -> std::io::Result<TcpListener> {
+        let socket = if self.bind_addr.is_ipv4() {
+            TcpSocket::new_v4()?
+        } else {
+            TcpSocket::new_v6()?
+        };
+
+        // Same as TcpListener::bind, so restarts don't wait out TIME_WAIT
+        #[cfg(unix)]
+        socket.set_reuseaddr(true)?;
+
+        socket.bind(self.bind_addr)?;
+        socket.listen(self.listen_backlog)
+    }
+
     // Start the TCP server
     #[instrument(skip(self))]
     pub async fn start(&self) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
-        let listener = TcpListener::bind(self.bind_addr).await?;
+        let listener = self.bind()?;
 
-        info!("KV Store TCP server listening on {}", self.bind_addr);
+        info!(
+            "KV Store TCP server listening on {} (backlog: {})",
+            self.bind_addr, self.listen_backlog
+        );
 
         // setup graceful shutdown
         let shutdown = self.setup_graceful_shutdown();
@@ -59,46 +193,57 @@ impl TcpServer {
         }
 
         info!("Server shutting down...");
+        self.drain().await;
 
         Ok(())
     }
 
-    // Accept incoming connections
+    // Stop accepting, let every connection finish its current command, then wait for
+    // them to close, at most `shutdown_timeout`
+    pub async fn drain(&self) {
+        self.acceptor.shutdown.cancel();
+        self.acceptor.connections.close();
+
+        let active = self.acceptor.active_connections.load(Ordering::Relaxed);
+        info!("Draining {} connections...", active);
+
+        if tokio::time::timeout(self.shutdown_timeout, self.acceptor.connections.wait())
+            .await
+            .is_err()
+        {
+            warn!(
+                "Shutdown deadline reached with {} connections still open",
+                self.acceptor.active_connections.load(Ordering::Relaxed)
+            );
+        } else {
+            info!("All connections drained");
+        }
+    }
+
+    // Accept incoming connections on `accept_tasks` concurrent loops
+    // Returns once drain() is called; dropping the returned future also stops every loop
     pub async fn accept_connections(
         &self,
         listener: TcpListener,
     ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
-        loop {
-            match listener.accept().await {
-                Ok((stream, addr)) => {
-                    let total = self.total_connections.fetch_add(1, Ordering::Relaxed) + 1;
-                    let active = self.active_connections.fetch_add(1, Ordering::Relaxed) + 1;
-
-                    info!(
-                        "Accepted connection {} from {} (active: {})",
-                        total, addr, active
-                    );
+        let listener = Arc::new(listener);
+        let mut loops = JoinSet::new();
 
-                    // Spawn task to handle connection
-                    let dispatcher = self.dispatcher.clone();
-                    let active_connections = self.active_connections.clone();
-
-                    tokio::spawn(async move {
-                        let handler = ConnectionHandler::new(dispatcher);
-                        handler.handle_connection(stream, addr).await;
+        for _ in 0..self.accept_tasks {
+            loops.spawn(self.acceptor.clone().run(listener.clone()));
+        }
 
-                        // Decrement active connection count
-                        active_connections.fetch_sub(1, Ordering::Relaxed);
-                    });
-                }
+        if let Some(timeout) = self.acceptor.idle_timeout {
+            loops.spawn(self.acceptor.clone().reap_idle(timeout));
+        }
 
-                Err(e) => {
-                    error!("failed to accept connection: {}", e);
-                    // Small delay to prevent tight error loops
-                    tokio::time::sleep(tokio::time::Duration::from_millis(100)).await;
-                }
+        while let Some(result) = loops.join_next().await {
+            if let Err(e) = result {
+                error!("Accept loop terminated: {}", e);
             }
         }
+
+        Ok(())
     }
 
     async fn setup_graceful_shutdown(&self) {
@@ -130,9 +275,196 @@ impl TcpServer {
     }
 
     pub fn stats(&self) -> ServerStats {
+        // Closing connections fold their counters in under the registry lock, so each one
+        // is counted either here or in the totals, never both or neither
+        let traffic = self.acceptor.dispatcher.client_traffic();
+        let (commands, received, sent) = {
+            let registry = self.acceptor.registry.lock();
+            registry.values().map(|handler| handler.stats()).fold(
+                (
+                    traffic.commands_processed.load(Ordering::Relaxed),
+                    traffic.bytes_received.load(Ordering::Relaxed),
+                    traffic.bytes_sent.load(Ordering::Relaxed),
+                ),
+                |(commands, received, sent), stats| {
+                    (
+                        commands + stats.commands_processed,
+                        received + stats.bytes_received,
+                        sent + stats.bytes_sent,
+                    )
+                },
+            )
+        };
+
         ServerStats {
-            total_connections: self.total_connections.load(Ordering::Relaxed),
-            active_connections: self.active_connections.load(Ordering::Relaxed),
+            total_connections: self.acceptor.total_connections.load(Ordering::Relaxed),
+            active_connections: self.acceptor.active_connections.load(Ordering::Relaxed),
+            accept_errors: traffic.accept_errors.load(Ordering::Relaxed) as usize,
+            accept_resource_errors: traffic.accept_resource_errors.load(Ordering::Relaxed) as usize,
+            reaped_connections: traffic.reaped_connections.load(Ordering::Relaxed) as usize,
+            client_buffer_bytes: self
+                .acceptor
+                .dispatcher
+                .client_buffer_bytes()
+                .load(Ordering::Relaxed),
+            total_commands_processed: commands,
+            total_bytes_received: received,
+            total_bytes_sent: sent,
         }
     }
 }
+
+impl Acceptor {
+    async fn run(self, listener: Arc<TcpListener>) {
+        loop {
+            let accepted = tokio::select! {
+                accepted = listener.accept() => accepted,
+                _ = self.shutdown.cancelled() => return,
+            };
+
+            match accepted {
+                Ok((stream, addr)) => {
+                    let total = self.total_connections.fetch_add(1, Ordering::Relaxed) + 1;
+                    let active = self.active_connections.fetch_add(1, Ordering::Relaxed) + 1;
+
+                    info!(
+                        "Accepted connection {} from {} (active: {})",
+                        total, addr, active
+                    );
+
+                    if let Err(e) = stream.set_nodelay(self.tcp_nodelay) {
+                        warn!("Failed to set TCP_NODELAY for {}: {}", addr, e);
+                    }
+
+                    let handler = Arc::new(
+                        ConnectionHandler::new(self.dispatcher.clone())
+                            .with_limits(self.connection_limits.clone())
+                            .with_audit_log(self.audit_log)
+                            .with_renamed_commands(self.renames.clone())
+                            .with_shutdown(self.shutdown.child_token()),
+                    );
+
+                    let id = self.next_connection_id.fetch_add(1, Ordering::Relaxed);
+                    self.registry.lock().insert(id, handler.clone());
+                    let active = ActiveConnection {
+                        id,
+                        active: self.active_connections.clone(),
+                        registry: self.registry.clone(),
+                        traffic: self.dispatcher.client_traffic(),
+                    };
+
+                    // Spawn task to handle connection
+                    self.connections.spawn(async move {
+                        // Moved in so the count drops however the task ends (panic, abort, ...)
+                        let _active = active;
+
+                        handler.handle_connection(stream, addr).await;
+                    });
+                }
+
+                // The peer gave up before we got to it, only that connection is affected
+                Err(e) if is_connection_error(&e) => {
+                    self.dispatcher
+                        .client_traffic()
+                        .accept_errors
+                        .fetch_add(1, Ordering::Relaxed);
+                    warn!("Connection dropped before accept: {}", e);
+                }
+
+                Err(e) => {
+                    // Typically out of file descriptors or memory: nothing can be accepted,
+                    // so pending connections queue up in the backlog until it overflows
+                    let traffic = self.dispatcher.client_traffic();
+                    traffic.accept_errors.fetch_add(1, Ordering::Relaxed);
+                    traffic
+                        .accept_resource_errors
+                        .fetch_add(1, Ordering::Relaxed);
+                    error!("failed to accept connection: {}", e);
+
+                    // Small delay to prevent tight error loops
+                    tokio::time::sleep(tokio::time::Duration::from_millis(100)).await;
+                }
+            }
+        }
+    }
+
+    // Periodically close connections idle past `timeout`, until shutdown
+    async fn reap_idle(self, timeout: Duration) {
+        let mut ticker = tokio::time::interval(self.idle_check_interval);
+        ticker.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+
+        loop {
+            tokio::select! {
+                _ = ticker.tick() => {}
+                _ = self.shutdown.cancelled() => return,
+            }
+
+            // Collected first so no handler is closed while the registry is locked
+            // Already closing ones were counted the first time around
+            let idle: Vec<(u64, Arc<ConnectionHandler>)> = self
+                .registry
+                .lock()
+                .iter()
+                .filter(|(_, handler)| {
+                    !handler.is_closing()
+                        && !handler.is_subscribed()
+                        && handler.idle_time() >= timeout
+                })
+                .map(|(id, handler)| (*id, handler.clone()))
+                .collect();
+
+            for (id, handler) in idle {
+                info!(
+                    "Closing connection {} idle for {:?}",
+                    id,
+                    handler.idle_time()
+                );
+                handler.close();
+                self.dispatcher
+                    .client_traffic()
+                    .reaped_connections
+                    .fetch_add(1, Ordering::Relaxed);
+            }
+        }
+    }
+}
+
+// Holds one slot of the active connection count and the registry, released exactly once on drop
+// The connection's traffic is folded into the server totals at the same time
+struct ActiveConnection {
+    id: u64,
+    active: Arc<AtomicUsize>,
+    registry: Arc<Mutex<HashMap<u64, Arc<ConnectionHandler>>>>,
+    traffic: Arc<ClientTraffic>,
+}
+
+impl Drop for ActiveConnection {
+    fn drop(&mut self) {
+        let mut registry = self.registry.lock();
+        if let Some(handler) = registry.remove(&self.id) {
+            let stats = handler.stats();
+            self.traffic.connections.fetch_add(1, Ordering::Relaxed);
+            self.traffic
+                .commands_processed
+                .fetch_add(stats.commands_processed, Ordering::Relaxed);
+            self.traffic
+                .bytes_received
+                .fetch_add(stats.bytes_received, Ordering::Relaxed);
+            self.traffic
+                .bytes_sent
+                .fetch_add(stats.bytes_sent, Ordering::Relaxed);
+        }
+        drop(registry);
+        self.active.fetch_sub(1, Ordering::Relaxed);
+    }
+}
+
+// Accept errors that concern a single connection rather than the listener
+fn is_connection_error(e: &std::io::Error) -> bool {
+    matches!(
+        e.kind(),
+        std::io::ErrorKind::ConnectionAborted
+            | std::io::ErrorKind::ConnectionReset
+            | std::io::ErrorKind::ConnectionRefused
+    )
+}
@@ -0,0 +1,97 @@
+use std::{
+    fmt,
+    hash::{DefaultHasher, Hash, Hasher},
+    sync::atomic::{AtomicU64, AtomicUsize, Ordering},
+};
+
+// Bits per key the filter is sized for and bits set per key; blocked as below, together
+// about 1-2% false positives
+pub const BITS_PER_KEY: usize = 10;
+const PROBES: u32 = 7;
+
+// Hashed ahead of the key so probe positions don't correlate with the shard it lands in
+const SEED: u64 = 0x626c_617a_652d_6266;
+
+// Below this many deleted keys a rebuild isn't worth the pass over the shard
+const MIN_STALE_KEYS: usize = 1024;
+
+// Bloom filter over one shard's keys, read and written without the shard lock
+// A miss is definite. A hit only means "maybe": the bits may belong to other keys, or to
+// a key deleted since, as deletes never clear bits. Every hit still needs the real lookup
+pub struct BloomFilter {
+    words: Box<[AtomicU64]>,
+    inserted: AtomicUsize, // Keys added since the last rebuild, deleted ones included
+}
+
+impl BloomFilter {
+    // Sized for `capacity` keys; a fuller filter still works, with more false positives
+    pub fn new(capacity: usize) -> Self {
+        let words = (capacity.saturating_mul(BITS_PER_KEY) / 64).max(1);
+        Self {
+            words: (0..words).map(|_| AtomicU64::new(0)).collect(),
+            inserted: AtomicUsize::new(0),
+        }
+    }
+
+    // Called before the key becomes visible, so a reader that can find it also sees its bits
+    pub fn insert(&self, key: &str) {
+        let (word, mask) = self.probe(key);
+        self.words[word].fetch_or(mask, Ordering::Release);
+        self.inserted.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn may_contain(&self, key: &str) -> bool {
+        let (word, mask) = self.probe(key);
+        self.words[word].load(Ordering::Acquire) & mask == mask
+    }
+
+    // Whether deleted keys now outnumber the `live` ones, so their leftover bits drive
+    // false positives well above the sizing target
+    pub fn is_stale(&self, live: usize) -> bool {
+        let deleted = self.inserted.load(Ordering::Relaxed).saturating_sub(live);
+        deleted > live.max(MIN_STALE_KEYS)
+    }
+
+    // Reset the bits to exactly those of `keys`, the shard's full key set
+    // Callers hold the shard write lock, so no insert races this. Words are swapped one
+    // store at a time and a live key's bits are set in both the old and the new word,
+    // so a concurrent lookup never misses a key that exists
+    pub fn rebuild<'a>(&self, keys: impl Iterator<Item = &'a String>) {
+        let mut words = vec![0u64; self.words.len()];
+        let mut count = 0;
+        for key in keys {
+            let (word, mask) = self.probe(key);
+            words[word] |= mask;
+            count += 1;
+        }
+
+        for (word, bits) in self.words.iter().zip(words) {
+            word.store(bits, Ordering::Release);
+        }
+        self.inserted.store(count, Ordering::Relaxed);
+    }
+
+    // A key's bits all sit in one word, so a lookup costs one hash and one load
+    // (a blocked filter: a few more false positives than spreading them over the array)
+    fn probe(&self, key: &str) -> (usize, u64) {
+        let mut hasher = DefaultHasher::new();
+        SEED.hash(&mut hasher);
+        key.hash(&mut hasher);
+        let hash = hasher.finish();
+
+        // High bits pick the word, the remixed hash supplies 6 bits per probe
+        let word = ((hash as u128 * self.words.len() as u128) >> 64) as usize;
+        let bits = hash.wrapping_mul(0x9e37_79b9_7f4a_7c15);
+        let mask = (0..PROBES).fold(0u64, |mask, i| mask | 1 << ((bits >> (i * 6)) & 63));
+        (word, mask)
+    }
+}
+
+impl fmt::Debug for BloomFilter {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("BloomFilter")
+            .field("bits", &(self.words.len() * 64))
+            .field("inserted", &self.inserted.load(Ordering::Relaxed))
+            .finish()
+    }
+}
@@ -1,94 +1,432 @@
 use std::{
-    collections::HashMap,
-    hash::{DefaultHasher, Hash, Hasher},
+    collections::{HashMap, HashSet, VecDeque},
+    hash::{BuildHasher, DefaultHasher, Hash, Hasher, RandomState},
     sync::{
-        Arc,
+        Arc, Weak,
         atomic::{AtomicU64, AtomicUsize, Ordering},
     },
+    time::Duration,
 };
 
-use parking_lot::RwLock;
+use futures_util::{StreamExt, TryStreamExt, stream};
+use parking_lot::{Mutex, RwLock, RwLockReadGuard, RwLockWriteGuard};
+use tokio::sync::Notify;
 use tracing::{debug, info, instrument};
 
-use crate::storage::{StorageConfig, StorageEngine, StorageError, StorageResult, StorageStats};
+use crate::{
+    pubsub::KeyspaceNotifier,
+    storage::{
+        EntryStream, EvictionListener, ExpiringEntryStream, KeyStream, MaxMemoryPolicy,
+        MemoryStats, StorageConfig, StorageEngine, StorageError, StorageResult, StorageStats,
+        TtlOverflowPolicy, UpdateFn,
+        engine::bloom::BloomFilter,
+        now_millis,
+        value::{
+            decode_list, decode_set, encode_list, encode_packed_list, encode_packed_set,
+            encode_set, encoding, format_float, is_typed, parse_float, typed_after_write,
+        },
+    },
+};
+
+// Access frequency counter kept like Redis' LFU: an access increments it with probability
+// 1 / ((counter - LFU_INIT) * LFU_LOG_FACTOR + 1), so it grows logarithmically and
+// saturates at 255, and it loses one for every LFU_DECAY_MINUTES without access
+const LFU_INIT: u64 = 5; // New keys start here, so they get a chance to be read again
+const LFU_LOG_FACTOR: f64 = 10.0;
+const LFU_DECAY_MINUTES: u64 = 1;
+
+// Rough per-key cost of the entry and hash map slot, on top of key and value bytes
+const ENTRY_OVERHEAD: usize = 64;
+
+// Stored value plus its expiry and access metadata
+#[derive(Debug)]
+struct Entry {
+    // Shared so readers clone the pointer under the shard lock and copy the bytes after
+    // releasing it; writers to a shared value copy-on-write via Arc::make_mut
+    value: Arc<Vec<u8>>,
+    expires_at: Option<u64>, // Unix timestamp in millis, None = never expires
+    last_access: AtomicU64,  // Unix timestamp in coarse seconds, updated under read lock
+    frequency: AtomicU64,    // LFU counter (low 8 bits) and the minute of its last access
+    version: u64,            // Drawn from the engine's counter by every write to the value
+}
+
+impl Entry {
+    fn new(value: Vec<u8>, expires_at: Option<u64>, version: u64) -> Self {
+        let now = now_millis();
+        Self {
+            value: Arc::new(value),
+            expires_at,
+            version,
+            last_access: AtomicU64::new(now / 1000),
+            frequency: AtomicU64::new((now / 60_000) << 8 | LFU_INIT),
+        }
+    }
+
+    fn is_expired(&self, now: u64) -> bool {
+        self.expires_at.is_some_and(|at| at <= now)
+    }
+
+    fn touch(&self) {
+        let now = now_millis();
+        self.last_access.store(now / 1000, Ordering::Relaxed);
+
+        // Racing readers may lose an increment, the counter is an estimate anyway
+        let mut counter = self.frequency_at(now);
+        if counter < 255 {
+            let p = 1.0 / ((counter.saturating_sub(LFU_INIT)) as f64 * LFU_LOG_FACTOR + 1.0);
+            if counter <= LFU_INIT || random_fraction() < p {
+                counter += 1;
+            }
+        }
+        self.frequency
+            .store((now / 60_000) << 8 | counter, Ordering::Relaxed);
+    }
+
+    // The LFU counter, decayed for the time since the last access
+    fn frequency_at(&self, now: u64) -> u64 {
+        let packed = self.frequency.load(Ordering::Relaxed);
+        let idle_minutes = (now / 60_000).saturating_sub(packed >> 8);
+        (packed & 0xff).saturating_sub(idle_minutes / LFU_DECAY_MINUTES)
+    }
+}
 
 // Shard for reduce lock contention
 // why using shard rwlock? because this is easier to implement, predictable perf, lock-free between shards (diferrent shards = zero contention)
 // but the tradeoff is still blocking within shard, uneven distribution of keys - some shards might be hotter, expensive range ops - must check all shards
 #[derive(Debug)]
 struct Shard {
-    data: RwLock<HashMap<String, Vec<u8>>>,
+    data: RwLock<HashMap<String, Entry>>,
     size: AtomicUsize, // Track memory usage per shard
+
+    // Lets lookups of absent keys skip the lock, None unless storage.bloom_filter is set
+    bloom: Option<BloomFilter>,
 }
 
 impl Shard {
-    fn new() -> Self {
+    // `bloom_capacity` keys for the filter, 0 = no filter
+    fn new(bloom_capacity: usize) -> Self {
         Self {
             data: RwLock::new(HashMap::new()),
             size: AtomicUsize::new(0),
+            bloom: (bloom_capacity > 0).then(|| BloomFilter::new(bloom_capacity)),
         }
     }
 
     fn estimate_size(key: &str, value: &[u8]) -> usize {
-        key.len() + value.len() + 64 // rough overhead estimate
+        key.len() + value.len() + ENTRY_OVERHEAD
+    }
+
+    // Record a key about to be inserted; callers hold the shard write lock
+    fn remember(&self, key: &str) {
+        if let Some(ref bloom) = self.bloom {
+            bloom.insert(key);
+        }
+    }
+
+    // False only for a key that is certainly absent, the lookup can then skip the lock
+    fn may_contain(&self, key: &str) -> bool {
+        self.bloom
+            .as_ref()
+            .is_none_or(|bloom| bloom.may_contain(key))
+    }
+
+    // Drop the bits of deleted keys once they outnumber the live ones, or always when
+    // `force` is set; callers hold the shard write lock
+    fn refresh_filter(&self, data: &HashMap<String, Entry>, force: bool) {
+        if let Some(ref bloom) = self.bloom
+            && (force || bloom.is_stale(data.len()))
+        {
+            bloom.rebuild(data.keys());
+            debug!("Rebuilt shard bloom filter over {} keys", data.len());
+        }
+    }
+}
+
+// Which shard owns a key. While a resize runs keys are routed to the previous shard set
+// until their shard has been migrated
+struct ShardLayout {
+    shards: Vec<Arc<Shard>>,
+    resize: Option<Resize>,
+}
+
+// An online resize in progress: `from` is the previous shard set, the ones below
+// `migrated` have been emptied into the new set and no longer own any key
+struct Resize {
+    from: Vec<Arc<Shard>>,
+    migrated: usize,
+}
+
+impl ShardLayout {
+    fn new(shard_count: usize, bloom_capacity: usize) -> Self {
+        Self {
+            shards: (0..shard_count)
+                .map(|_| Arc::new(Shard::new(bloom_capacity)))
+                .collect(),
+            resize: None,
+        }
+    }
+
+    // Shards still to be migrated, they come first in lock order
+    fn pending(&self) -> &[Arc<Shard>] {
+        self.resize
+            .as_ref()
+            .map_or(&[], |resize| &resize.from[resize.migrated..])
+    }
+
+    // Number of shards that may hold keys
+    fn len(&self) -> usize {
+        self.pending().len() + self.shards.len()
+    }
+
+    // Lock order position of the shard owning `key`
+    fn position(&self, key: &str) -> usize {
+        if let Some(ref resize) = self.resize {
+            let index = shard_index(key, resize.from.len());
+            if index >= resize.migrated {
+                return index - resize.migrated;
+            }
+        }
+        self.pending().len() + shard_index(key, self.shards.len())
+    }
+
+    fn shard_at(&self, position: usize) -> &Arc<Shard> {
+        let pending = self.pending();
+        pending
+            .get(position)
+            .unwrap_or_else(|| &self.shards[position - pending.len()])
+    }
+
+    fn shard(&self, key: &str) -> &Arc<Shard> {
+        self.shard_at(self.position(key))
+    }
+
+    // Every shard that may hold keys, in lock order
+    fn all(&self) -> impl Iterator<Item = &Arc<Shard>> {
+        self.pending().iter().chain(self.shards.iter())
+    }
+
+    // Move the next shard of a running resize into the new set, true once none is left
+    // Runs under the layout write lock, so no command sees a shard half-moved
+    fn migrate_next(&mut self) -> bool {
+        let Some(ref mut resize) = self.resize else {
+            return true;
+        };
+
+        let source = &resize.from[resize.migrated];
+        let entries = std::mem::take(&mut *source.data.write());
+        source.size.store(0, Ordering::Relaxed);
+
+        // Keys of an unmigrated shard are only ever written there, so none of them
+        // exists in the new set yet
+        for (key, entry) in entries {
+            let size = Shard::estimate_size(&key, &entry.value);
+            let target = &self.shards[shard_index(&key, self.shards.len())];
+            let mut guard = target.data.write();
+            target.remember(&key);
+            guard.insert(key, entry);
+            target.size.fetch_add(size, Ordering::Relaxed);
+        }
+
+        resize.migrated += 1;
+        if resize.migrated < resize.from.len() {
+            return false;
+        }
+
+        self.resize = None;
+        true
+    }
+}
+
+// Counts a live scan stream; migration waits while any exists so streams see every key once
+struct ScanGuard(Arc<AtomicUsize>);
+
+impl ScanGuard {
+    fn new(scans: &Arc<AtomicUsize>) -> Self {
+        scans.fetch_add(1, Ordering::Relaxed);
+        Self(scans.clone())
+    }
+}
+
+impl Drop for ScanGuard {
+    fn drop(&mut self) {
+        self.0.fetch_sub(1, Ordering::Relaxed);
+    }
+}
+
+// max_memory budget shared by several engines (a server's databases): their bytes count
+// together, and a write short of room may evict from any of them
+#[derive(Default)]
+pub struct MemoryBudget {
+    used: AtomicUsize,
+    engines: Mutex<Vec<Weak<MemoryEngine>>>,
+}
+
+impl MemoryBudget {
+    // Let writes to the other engines on this budget evict from `engine`
+    pub fn register(&self, engine: &Arc<MemoryEngine>) {
+        let mut engines = self.engines.lock();
+        engines.retain(|engine| engine.strong_count() > 0);
+        engines.push(Arc::downgrade(engine));
+    }
+
+    // Evict from the engines other than `from` until `additional_size` more bytes fit
+    fn evict_elsewhere(&self, from: &MemoryEngine, additional_size: usize) {
+        let engines: Vec<Arc<MemoryEngine>> = self
+            .engines
+            .lock()
+            .iter()
+            .filter_map(Weak::upgrade)
+            .collect();
+        for engine in engines {
+            if !std::ptr::eq(engine.as_ref(), from) && !engine.fits(additional_size) {
+                engine.evict(additional_size);
+            }
+        }
     }
 }
 
 // High-performance in-memory storage engine
 // Uses sharding RwLock HashMap to reduce contention
 pub struct MemoryEngine {
-    shards: Vec<Arc<Shard>>,
-    shard_count: usize,
+    // Commands hold a read lock for as long as they use a shard; only resize and its
+    // migration steps take the write lock
+    layout: Arc<RwLock<ShardLayout>>,
+    scans: Arc<AtomicUsize>, // Live scan streams, see ScanGuard
     config: StorageConfig,
 
     // metrics
     pub total_operations: AtomicU64,
     pub hit_count: AtomicU64,
     pub miss_count: AtomicU64,
+    pub evicted_keys: AtomicU64,
+    pub expired_keys: AtomicU64,
 
     // memory tracking
     pub total_memory: AtomicUsize,
+    // Bytes counted against max_memory: this engine's alone, or shared by every database of
+    // a server so that together they stay under the limit
+    memory_budget: Arc<MemoryBudget>,
+
+    // Keys held, expired ones not yet purged included; what max_keys caps and stats reports
+    key_count: AtomicUsize,
+    volatile_count: AtomicUsize, // Those of them with a TTL
+
+    // Last version handed out; engine-wide so a deleted and recreated key never reuses one
+    last_version: AtomicU64,
+
+    // Next shard purge_expired() sweeps
+    expire_cursor: AtomicUsize,
+
+    // Keyspace events (set, del, expired), None when notifications are off
+    notifier: Option<KeyspaceNotifier>,
+
+    // Told about every evicted key, e.g. to log its removal to the AOF
+    eviction_listener: Option<EvictionListener>,
+
+    // Clients blocked on a list key; entries die with the last waiter
+    list_waiters: Mutex<HashMap<String, Weak<Notify>>>,
 }
 
 impl MemoryEngine {
     // Create new memory engine with configuration
     pub fn new(config: StorageConfig) -> Self {
         let shard_count = config.shard_count;
-        let mut shards = Vec::with_capacity(shard_count);
-
-        for _ in 0..shard_count {
-            shards.push(Arc::new(Shard::new()));
-        }
 
         info!("MemoryEngine initialized with {} shards", shard_count);
 
         Self {
-            shards,
-            shard_count,
+            layout: Arc::new(RwLock::new(ShardLayout::new(
+                shard_count,
+                bloom_capacity(&config, shard_count),
+            ))),
+            scans: Arc::new(AtomicUsize::new(0)),
             config,
             total_operations: AtomicU64::new(0),
             hit_count: AtomicU64::new(0),
             miss_count: AtomicU64::new(0),
+            evicted_keys: AtomicU64::new(0),
+            expired_keys: AtomicU64::new(0),
+            expire_cursor: AtomicUsize::new(0),
             total_memory: AtomicUsize::new(0),
+            memory_budget: Arc::new(MemoryBudget::default()),
+            key_count: AtomicUsize::new(0),
+            volatile_count: AtomicUsize::new(0),
+            last_version: AtomicU64::new(0),
+            notifier: None,
+            eviction_listener: None,
+            list_waiters: Mutex::new(HashMap::new()),
+        }
+    }
+
+    // Count this engine's memory against `budget` alongside other engines sharing it
+    // Once the engine is in an Arc, MemoryBudget::register lets the others evict from it
+    pub fn with_memory_budget(mut self, budget: Arc<MemoryBudget>) -> Self {
+        self.memory_budget = budget;
+        self
+    }
+
+    pub fn with_eviction_listener(mut self, listener: EvictionListener) -> Self {
+        self.eviction_listener = Some(listener);
+        self
+    }
+
+    // Publish keyspace events for this engine's writes
+    pub fn with_notifier(mut self, notifier: KeyspaceNotifier) -> Self {
+        self.notifier = Some(notifier);
+        self
+    }
+
+    fn notify(&self, event: &str, key: &str) {
+        if let Some(ref notifier) = self.notifier {
+            notifier.notify(event, key);
         }
     }
 
-    // Get shard index for a key using hash
-    fn get_shard_index(&self, key: &str) -> usize {
-        let mut hasher = DefaultHasher::new();
-        key.hash(&mut hasher);
-        (hasher.finish() as usize) % self.shard_count
+    // Current shard layout. Recursive so a command can re-enter (e.g. through eviction)
+    // while a resize is waiting for the write lock
+    fn layout(&self) -> RwLockReadGuard<'_, ShardLayout> {
+        self.layout.read_recursive()
+    }
+
+    pub fn shard_count(&self) -> usize {
+        self.layout().shards.len()
+    }
+
+    pub fn is_resizing(&self) -> bool {
+        self.layout().resize.is_some()
     }
 
-    // Get shard by key
-    fn get_shard(&self, key: &str) -> &Arc<Shard> {
-        let index = self.get_shard_index(key);
-        &self.shards[index]
+    // Write-lock every shard owning one of `keys`, always in ascending lock order
+    // All multi-shard mutations must go through here so no two callers can lock in opposite order
+    fn lock_shards<'a>(&'a self, layout: &'a ShardLayout, keys: &[&str]) -> LockedShards<'a> {
+        let mut positions: Vec<usize> = keys.iter().map(|key| layout.position(key)).collect();
+        positions.sort_unstable();
+        positions.dedup();
+
+        let guards = positions
+            .into_iter()
+            .map(|position| (position, layout.shard_at(position).data.write()))
+            .collect();
+
+        LockedShards {
+            engine: self,
+            layout,
+            guards,
+        }
     }
 
-    // Check memory limits
+    // Check memory limits, evicting keys first when eviction is enabled
     fn check_memory_limit(&self, additional_size: usize) -> StorageResult<()> {
-        let current = self.total_memory.load(Ordering::Relaxed);
+        let mut current = self.memory_budget.used.load(Ordering::Relaxed);
+        if current + additional_size > self.config.max_memory {
+            self.evict(additional_size);
+            // This engine alone may not hold enough, e.g. a write to an empty database
+            if !self.fits(additional_size) {
+                self.memory_budget.evict_elsewhere(self, additional_size);
+            }
+            current = self.memory_budget.used.load(Ordering::Relaxed);
+        }
+
         if current + additional_size > self.config.max_memory {
             return Err(StorageError::Persistence(format!(
                 "Memory limit exceeded: {} + {} > {}",
@@ -98,64 +436,213 @@ impl MemoryEngine {
         Ok(())
     }
 
+    // Whether `additional_size` more bytes stay within max_memory
+    fn fits(&self, additional_size: usize) -> bool {
+        self.memory_budget.used.load(Ordering::Relaxed) + additional_size <= self.config.max_memory
+    }
+
+    // Evict keys picked by maxmemory_policy until `additional_size` more bytes fit. The LRU,
+    // LFU and TTL policies are approximated: they compare eviction_sample_size keys of a random
+    // shard and drop the best candidate
+    // Shards are only ever try-locked here, callers may already hold one (or several)
+    fn evict(&self, additional_size: usize) {
+        let policy = self.config.maxmemory_policy;
+        let sample_size = match policy {
+            MaxMemoryPolicy::AllkeysRandom => 1,
+            _ => self.config.eviction_sample_size,
+        };
+        // Don't drop keys for a value that wouldn't fit into an empty engine either
+        if policy == MaxMemoryPolicy::NoEviction
+            || sample_size == 0
+            || additional_size > self.config.max_memory
+        {
+            return;
+        }
+
+        // Bounded so a keyspace that is empty, contended or too small to make room
+        // gives up instead of spinning
+        let layout = self.layout();
+        let mut attempts = layout.len() * 4;
+        while attempts > 0 && !self.fits(additional_size) {
+            attempts -= 1;
+
+            let shard = layout.shard_at(random_index(layout.len()));
+            let Some(mut guard) = shard.data.try_write() else {
+                continue;
+            };
+            if guard.is_empty() {
+                continue;
+            }
+
+            // Start the sample at a random position, wrapping around the map's end
+            let now = now_millis();
+            let start = random_index(guard.len());
+            let victim = guard
+                .iter()
+                .skip(start)
+                .chain(guard.iter().take(start))
+                .filter(|(_, entry)| !policy.is_volatile() || entry.expires_at.is_some())
+                .take(sample_size)
+                .min_by_key(|(_, entry)| match policy {
+                    MaxMemoryPolicy::VolatileTtl => entry.expires_at.unwrap_or(u64::MAX),
+                    // Expired keys go first
+                    _ if entry.is_expired(now) => 0,
+                    MaxMemoryPolicy::AllkeysLfu => entry.frequency_at(now),
+                    _ => entry.last_access.load(Ordering::Relaxed),
+                })
+                .map(|(key, _)| key.clone());
+
+            let Some(key) = victim else {
+                continue;
+            };
+            if let Some(old) = guard.remove(&key) {
+                let size = Shard::estimate_size(&key, &old.value);
+                self.update_memory(-(size as isize));
+                shard.size.fetch_sub(size, Ordering::Relaxed);
+                self.release_key(&old);
+                // Before unlocking, so a later write to the key is always seen after it
+                if let Some(ref listener) = self.eviction_listener {
+                    listener(&key);
+                }
+                drop(guard);
+
+                self.evicted_keys.fetch_add(1, Ordering::Relaxed);
+                self.notify("evicted", &key);
+                debug!("Evicted key to free {} bytes", size);
+            }
+        }
+    }
+
+    // Version for a write about to land; callers hold the key's shard lock, so versions
+    // only grow per key
+    fn next_version(&self) -> u64 {
+        self.last_version.fetch_add(1, Ordering::Relaxed) + 1
+    }
+
+    // Claim the slot of a key about to be created, failing once max_keys are held
+    // Callers hold the key's shard lock, so the key can't appear in between
+    fn reserve_key(&self, expires_at: Option<u64>) -> StorageResult<()> {
+        match self.config.max_keys {
+            None => {
+                self.key_count.fetch_add(1, Ordering::Relaxed);
+            }
+            Some(max_keys) => {
+                self.key_count
+                    .fetch_update(Ordering::Relaxed, Ordering::Relaxed, |count| {
+                        (count < max_keys).then_some(count + 1)
+                    })
+                    .map_err(|_| StorageError::KeyLimit(max_keys))?;
+            }
+        }
+
+        self.track_ttl(None, expires_at);
+        Ok(())
+    }
+
+    // Give back the slot of a removed entry
+    fn release_key(&self, entry: &Entry) {
+        self.key_count.fetch_sub(1, Ordering::Relaxed);
+        self.track_ttl(entry.expires_at, None);
+    }
+
+    // Keep volatile_count in step with a key's expiry changing from `before` to `after`
+    fn track_ttl(&self, before: Option<u64>, after: Option<u64>) {
+        match (before.is_some(), after.is_some()) {
+            (false, true) => {
+                self.volatile_count.fetch_add(1, Ordering::Relaxed);
+            }
+            (true, false) => {
+                self.volatile_count.fetch_sub(1, Ordering::Relaxed);
+            }
+            _ => {}
+        }
+    }
+
     // Update memory tracking
     fn update_memory(&self, delta: isize) {
         if delta > 0 {
             self.total_memory
                 .fetch_add(delta as usize, Ordering::Relaxed);
+            self.memory_budget
+                .used
+                .fetch_add(delta as usize, Ordering::Relaxed);
         } else {
             self.total_memory
                 .fetch_sub((-delta) as usize, Ordering::Relaxed);
+            self.memory_budget
+                .used
+                .fetch_sub((-delta) as usize, Ordering::Relaxed);
         }
     }
-}
-
-#[async_trait::async_trait]
-impl StorageEngine for MemoryEngine {
-    #[instrument(skip(self), fields(key = %key))]
-    async fn get(&self, key: &str) -> StorageResult<Option<Vec<u8>>> {
-        debug!("getting key from memory engine");
-
-        self.total_operations.fetch_add(1, Ordering::Relaxed);
 
-        let shard = self.get_shard(key);
-        let guard = shard.data.read();
-
-        match guard.get(key) {
-            Some(value) => {
-                self.hit_count.fetch_add(1, Ordering::Relaxed);
-                debug!("Key found in memory");
-                Ok(Some(value.clone()))
-            }
-            None => {
-                self.miss_count.fetch_add(1, Ordering::Relaxed);
-                debug!("Key not found in memory");
-                Ok(None)
-            }
+    // Apply max_ttl policy to a client-requested TTL
+    fn apply_ttl_policy(&self, ttl: Duration) -> StorageResult<Duration> {
+        match self.config.max_ttl.map(Duration::from_secs) {
+            Some(max) if ttl > max => match self.config.ttl_overflow {
+                TtlOverflowPolicy::Clamp => {
+                    debug!("Clamping TTL {:?} to max {:?}", ttl, max);
+                    Ok(max)
+                }
+                TtlOverflowPolicy::Reject => Err(StorageError::InvalidTtl(format!(
+                    "TTL {}s exceeds max_ttl {}s",
+                    ttl.as_secs(),
+                    max.as_secs()
+                ))),
+            },
+            _ => Ok(ttl),
         }
     }
 
-    #[instrument(skip(self, value), fields(key = %key, size = value.len()))]
-    async fn set(&self, key: &str, value: Vec<u8>) -> StorageResult<()> {
-        debug!("Setting key in memory engine");
-
+    // Insert a value with an optional TTL
+    // With `expected` set, the write only goes through while the key is at that version
+    // (0 = missing or expired); returns the version written
+    fn insert(
+        &self,
+        key: &str,
+        value: Vec<u8>,
+        ttl: Option<Duration>,
+        expected: Option<u64>,
+    ) -> StorageResult<u64> {
         let size = Shard::estimate_size(key, &value);
 
         // Check memory limit before allocating
         self.check_memory_limit(size)?;
 
-        let shard = self.get_shard(key);
+        let expires_at = ttl.map(|ttl| now_millis().saturating_add(ttl.as_millis() as u64));
+
+        let layout = self.layout();
+        let shard = layout.shard(key);
         let mut guard = shard.data.write();
 
-        // Check if key exists (for memory tracking)
-        let old_size = if let Some(old_value) = guard.get(key) {
-            Shard::estimate_size(key, old_value)
+        if let Some(expected) = expected {
+            let actual = guard
+                .get(key)
+                .filter(|entry| !entry.is_expired(now_millis()))
+                .map_or(0, |entry| entry.version);
+            if actual != expected {
+                return Err(StorageError::VersionMismatch { expected, actual });
+            }
+        }
+
+        // Check if key exists (for memory tracking); an overwrite counts as an access, so the
+        // key keeps its frequency
+        let version = self.next_version();
+        let entry = Entry::new(value, expires_at, version);
+        let old_size = if let Some(old) = guard.get(key) {
+            entry
+                .frequency
+                .store(old.frequency.load(Ordering::Relaxed), Ordering::Relaxed);
+            entry.touch();
+            self.track_ttl(old.expires_at, expires_at);
+            Shard::estimate_size(key, &old.value)
         } else {
+            self.reserve_key(expires_at)?;
+            shard.remember(key);
             0
         };
 
         // Insert new value
-        guard.insert(key.to_string(), value);
+        guard.insert(key.to_string(), entry);
 
         // Update memory tracking
         let memory_delta = size as isize - old_size as isize;
@@ -165,95 +652,1304 @@ impl StorageEngine for MemoryEngine {
             shard.size.fetch_sub(old_size, Ordering::Relaxed);
         }
 
-        self.total_operations.fetch_add(1, Ordering::Relaxed);
+        drop(guard);
+        self.notify("set", key);
+
         debug!("Key stored in memory, memory delta: {}", memory_delta);
 
-        Ok(())
+        Ok(version)
     }
 
-    #[instrument(skip(self), fields(key = %key))]
-    async fn delete(&self, key: &str) -> StorageResult<bool> {
-        debug!("Deleting key from memory engine");
+    // Read-modify-write the set at key under its shard lock
+    // `update` returns how many members it changed; nothing is written when that is 0
+    fn update_set(
+        &self,
+        key: &str,
+        update: impl FnOnce(&mut HashSet<Vec<u8>>) -> usize,
+    ) -> StorageResult<usize> {
+        self.purge_if_expired(key);
 
-        let shard = self.get_shard(key);
+        let layout = self.layout();
+        let shard = layout.shard(key);
         let mut guard = shard.data.write();
 
-        match guard.remove(key) {
-            Some(old_value) => {
-                let size = Shard::estimate_size(key, &old_value);
-                self.update_memory(-(size as isize));
-                shard.size.fetch_sub(size, Ordering::Relaxed);
+        let mut members = match guard.get(key) {
+            Some(entry) => decode_set(&entry.value)?,
+            None => HashSet::new(),
+        };
 
-                self.total_operations.fetch_add(1, Ordering::Relaxed);
-                debug!("Key deleted from memory");
-                Ok(true)
+        let changed = update(&mut members);
+        if changed == 0 {
+            return Ok(0);
+        }
+
+        let old_size = guard
+            .get(key)
+            .map_or(0, |entry| Shard::estimate_size(key, &entry.value));
+
+        // Sets never exist empty
+        if members.is_empty() {
+            if let Some(old) = guard.remove(key) {
+                self.release_key(&old);
+            }
+            self.update_memory(-(old_size as isize));
+            shard.size.fetch_sub(old_size, Ordering::Relaxed);
+            return Ok(changed);
+        }
+
+        // The encoding follows the thresholds on every write, so sets convert both ways
+        let packed = members.len() <= self.config.set_max_packed_entries
+            && members
+                .iter()
+                .all(|member| member.len() <= self.config.max_packed_value);
+        let value = if packed {
+            encode_packed_set(&members)
+        } else {
+            encode_set(&members)?
+        };
+        let new_size = Shard::estimate_size(key, &value);
+        if new_size > old_size {
+            self.check_memory_limit(new_size - old_size)?;
+        }
+
+        match guard.get_mut(key) {
+            Some(entry) => {
+                entry.value = Arc::new(value);
+                entry.version = self.next_version();
+                entry.touch();
             }
             None => {
-                debug!("Key not found in memory");
-                Ok(false)
+                let ttl = self.config.default_ttl.map(Duration::from_secs);
+                let expires_at = ttl.map(|ttl| now_millis().saturating_add(ttl.as_millis() as u64));
+                self.reserve_key(expires_at)?;
+                shard.remember(key);
+                guard.insert(
+                    key.to_string(),
+                    Entry::new(value, expires_at, self.next_version()),
+                );
             }
         }
-    }
 
-    async fn exists(&self, key: &str) -> StorageResult<bool> {
-        let shard = self.get_shard(key);
-        let guard = shard.data.read();
-        Ok(guard.contains_key(key))
+        self.update_memory(new_size as isize - old_size as isize);
+        shard.size.fetch_add(new_size, Ordering::Relaxed);
+        shard.size.fetch_sub(old_size, Ordering::Relaxed);
+
+        Ok(changed)
     }
 
-    #[instrument(skip(self), fields(prefix = %prefix))]
-    async fn scan(&self, prefix: &str) -> StorageResult<Vec<String>> {
-        debug!("Scanning keys with prefix");
+    // Read-modify-write the list at key under its shard lock, like update_set
+    // Nothing is written when `update` leaves the length unchanged
+    fn update_list<R>(
+        &self,
+        key: &str,
+        update: impl FnOnce(&mut VecDeque<Vec<u8>>) -> R,
+    ) -> StorageResult<R> {
+        self.purge_if_expired(key);
 
-        let mut results = Vec::new();
+        let layout = self.layout();
+        let shard = layout.shard(key);
+        let mut guard = shard.data.write();
 
-        // Scan all shards
-        for shard in &self.shards {
-            let guard = shard.data.read();
-            for key in guard.keys() {
-                if key.starts_with(prefix) {
-                    results.push(key.clone());
-                }
+        let mut items = match guard.get(key) {
+            Some(entry) => decode_list(&entry.value)?,
+            None => VecDeque::new(),
+        };
+
+        let len = items.len();
+        let result = update(&mut items);
+        if items.len() == len {
+            return Ok(result);
+        }
+
+        let old_size = guard
+            .get(key)
+            .map_or(0, |entry| Shard::estimate_size(key, &entry.value));
+
+        // Lists never exist empty
+        if items.is_empty() {
+            if let Some(old) = guard.remove(key) {
+                self.release_key(&old);
+            }
+            self.update_memory(-(old_size as isize));
+            shard.size.fetch_sub(old_size, Ordering::Relaxed);
+            return Ok(result);
+        }
+
+        let packed = items.len() <= self.config.list_max_packed_size
+            && items
+                .iter()
+                .all(|item| item.len() <= self.config.max_packed_value);
+        let value = if packed {
+            encode_packed_list(&items)
+        } else {
+            encode_list(&items)?
+        };
+        let new_size = Shard::estimate_size(key, &value);
+        if new_size > old_size {
+            self.check_memory_limit(new_size - old_size)?;
+        }
+
+        match guard.get_mut(key) {
+            Some(entry) => {
+                entry.value = Arc::new(value);
+                entry.version = self.next_version();
+                entry.touch();
+            }
+            None => {
+                let ttl = self.config.default_ttl.map(Duration::from_secs);
+                let expires_at = ttl.map(|ttl| now_millis().saturating_add(ttl.as_millis() as u64));
+                self.reserve_key(expires_at)?;
+                shard.remember(key);
+                guard.insert(
+                    key.to_string(),
+                    Entry::new(value, expires_at, self.next_version()),
+                );
             }
         }
 
-        debug!("Scan found {} keys with prefix '{}'", results.len(), prefix);
-        Ok(results)
+        self.update_memory(new_size as isize - old_size as isize);
+        shard.size.fetch_add(new_size, Ordering::Relaxed);
+        shard.size.fetch_sub(old_size, Ordering::Relaxed);
+
+        Ok(result)
     }
 
-    async fn stats(&self) -> StorageResult<StorageStats> {
-        let mut total_keys = 0;
+    // Counting policy for total_operations: every keyspace call counts once per key it
+    // addresses, whether it hits, misses, fails or changes nothing. Administrative calls
+    // (stats, iter_all, health_check) are not counted. Only `get` feeds hit_count/miss_count.
+    fn record_operations(&self, keys: usize) {
+        self.total_operations
+            .fetch_add(keys as u64, Ordering::Relaxed);
+    }
+
+    // Every shard that may hold keys, with migration held off until the guard is dropped
+    fn scan_shards(&self) -> (Vec<Arc<Shard>>, ScanGuard) {
+        let layout = self.layout();
+        (layout.all().cloned().collect(), ScanGuard::new(&self.scans))
+    }
+
+    // Live entries with prefix, one shard per chunk so no lock is held for the whole keyspace
+    fn entries(&self, prefix: String) -> EntryStream {
+        Box::pin(
+            self.entries_with_expiry(prefix)
+                .map_ok(|(key, value, _)| (key, value)),
+        )
+    }
 
-        // Count keys across all shards
-        for shard in &self.shards {
+    fn entries_with_expiry(&self, prefix: String) -> ExpiringEntryStream {
+        let (shards, scan) = self.scan_shards();
+        let entries = stream::iter(shards).flat_map(move |shard| {
+            let _scan = &scan;
+            let now = now_millis();
             let guard = shard.data.read();
-            total_keys += guard.len();
+            let entries: Vec<(String, Arc<Vec<u8>>, Option<u64>)> = guard
+                .iter()
+                .filter(|(key, entry)| key.starts_with(&prefix) && !entry.is_expired(now))
+                .map(|(key, entry)| (key.clone(), Arc::clone(&entry.value), entry.expires_at))
+                .collect();
+            drop(guard);
+
+            // Values are copied once the shard is unlocked
+            stream::iter(
+                entries
+                    .into_iter()
+                    .map(|(key, value, expires_at)| Ok((key, value.as_ref().clone(), expires_at))),
+            )
+        });
+
+        Box::pin(entries)
+    }
+
+    // Remove a key if it has expired (lazy expiry on access)
+    fn purge_if_expired(&self, key: &str) {
+        let layout = self.layout();
+        let shard = layout.shard(key);
+        let mut guard = shard.data.write();
+
+        if guard.get(key).is_some_and(|e| e.is_expired(now_millis()))
+            && let Some(old) = guard.remove(key)
+        {
+            let size = Shard::estimate_size(key, &old.value);
+            self.update_memory(-(size as isize));
+            shard.size.fetch_sub(size, Ordering::Relaxed);
+            self.release_key(&old);
+            drop(guard);
+
+            self.expired_keys.fetch_add(1, Ordering::Relaxed);
+            self.notify("expired", key);
+            debug!("Expired key purged");
         }
+    }
+}
 
-        let total_ops = self.total_operations.load(Ordering::Relaxed);
-        let hits = self.hit_count.load(Ordering::Relaxed);
-        let misses = self.miss_count.load(Ordering::Relaxed);
+// Uniformly-ish random index below `bound` (> 0), good enough for picking eviction samples
+fn random_index(bound: usize) -> usize {
+    (RandomState::new().hash_one(now_millis()) as usize) % bound
+}
 
-        // Hit rate should only consider hits and misses
-        // because other operations (set, delete) do not contribute to hit/miss
-        let total_gets = hits + misses;
-        let hit_rate = if total_gets > 0 {
-            hits as f64 / total_gets as f64
-        } else {
-            0.0
-        };
+// Uniformly-ish random number in [0, 1), for the LFU counter's increments
+fn random_fraction() -> f64 {
+    const STEPS: usize = 1 << 24;
+    random_index(STEPS) as f64 / STEPS as f64
+}
 
-        Ok(StorageStats {
-            total_keys,
-            memory_usage: self.total_memory.load(Ordering::Relaxed),
-            hit_rate,
-            total_operations: total_ops,
-        })
+// One step of a background resize: None while a scan stream holds migration off,
+// otherwise whether the resize is complete
+fn migrate_step(layout: &RwLock<ShardLayout>, scans: &AtomicUsize) -> Option<bool> {
+    let mut layout = layout.write();
+    // Streams register under the read lock, so none can appear while this is held
+    if scans.load(Ordering::Relaxed) > 0 {
+        return None;
+    }
+    Some(layout.migrate_next())
+}
+
+// Shard owning `key` among `shard_count` shards
+fn shard_index(key: &str, shard_count: usize) -> usize {
+    let mut hasher = DefaultHasher::new();
+    key.hash(&mut hasher);
+    (hasher.finish() as usize) % shard_count
+}
+
+// Keys each shard's bloom filter is sized for, 0 when the filters are off
+fn bloom_capacity(config: &StorageConfig, shard_count: usize) -> usize {
+    if !config.bloom_filter {
+        return 0;
+    }
+    config.bloom_filter_capacity.div_ceil(shard_count).max(1)
+}
+
+// Write guards for a set of shards, held in ascending lock order
+struct LockedShards<'a> {
+    engine: &'a MemoryEngine,
+    layout: &'a ShardLayout,
+    guards: Vec<(usize, RwLockWriteGuard<'a, HashMap<String, Entry>>)>,
+}
+
+impl LockedShards<'_> {
+    // Map of the locked shard owning `key`
+    fn map_for(&mut self, key: &str) -> &mut HashMap<String, Entry> {
+        let position = self.layout.position(key);
+        let index = self
+            .guards
+            .binary_search_by_key(&position, |(p, _)| *p)
+            .expect("shard for key was not locked");
+        &mut self.guards[index].1
+    }
+
+    // Remove a key, keeping memory accounting in sync
+    fn remove(&mut self, key: &str) -> Option<Entry> {
+        let entry = self.map_for(key).remove(key)?;
+        let size = Shard::estimate_size(key, &entry.value);
+        self.engine.update_memory(-(size as isize));
+        self.engine.release_key(&entry);
+        self.layout
+            .shard(key)
+            .size
+            .fetch_sub(size, Ordering::Relaxed);
+        Some(entry)
+    }
+
+    // Insert an entry, keeping memory accounting in sync
+    fn insert(&mut self, key: &str, entry: Entry) {
+        if let Some(old) = self.remove(key) {
+            debug!(
+                "Replacing existing value, expired: {}",
+                old.is_expired(now_millis())
+            );
+        }
+
+        // Entries only move between keys here, so the slot freed by their removal is reused
+        self.engine.key_count.fetch_add(1, Ordering::Relaxed);
+        self.engine.track_ttl(None, entry.expires_at);
+
+        let size = Shard::estimate_size(key, &entry.value);
+        self.layout.shard(key).remember(key);
+        self.map_for(key).insert(key.to_string(), entry);
+        self.engine.update_memory(size as isize);
+        self.layout
+            .shard(key)
+            .size
+            .fetch_add(size, Ordering::Relaxed);
+    }
+}
+
+#[async_trait::async_trait]
+impl StorageEngine for MemoryEngine {
+    #[instrument(skip(self), fields(key = %key))]
+    async fn get(&self, key: &str) -> StorageResult<Option<Vec<u8>>> {
+        debug!("getting key from memory engine");
+
+        self.record_operations(1);
+
+        let layout = self.layout();
+        let shard = layout.shard(key);
+        let (found, expired) = if !shard.may_contain(key) {
+            (None, false)
+        } else {
+            let guard = shard.data.read();
+
+            match guard.get(key) {
+                Some(entry) if !entry.is_expired(now_millis()) => {
+                    entry.touch();
+                    (Some(Arc::clone(&entry.value)), false)
+                }
+                Some(_) => (None, true),
+                None => (None, false),
+            }
+        };
+
+        // The copy happens outside the shard lock, so large hot values don't stall writers
+        if let Some(value) = found {
+            self.hit_count.fetch_add(1, Ordering::Relaxed);
+            debug!("Key found in memory");
+            return Ok(Some(value.as_ref().clone()));
+        }
+
+        if expired {
+            self.purge_if_expired(key);
+        }
+
+        self.miss_count.fetch_add(1, Ordering::Relaxed);
+        debug!("Key not found in memory");
+        Ok(None)
+    }
+
+    #[instrument(skip(self, keys), fields(keys = keys.len()))]
+    async fn get_many(&self, keys: &[&str]) -> StorageResult<HashMap<String, Vec<u8>>> {
+        debug!("getting keys from memory engine");
+
+        self.record_operations(keys.len());
+
+        // Sort by shard in lock order, so each shard is read-locked once for its run of keys
+        let layout = self.layout();
+        let mut by_shard: Vec<(usize, &str)> = keys
+            .iter()
+            .map(|&key| (layout.position(key), key))
+            .collect();
+        by_shard.sort_unstable_by_key(|(position, _)| *position);
+
+        let now = now_millis();
+        let mut found = Vec::with_capacity(keys.len());
+        let mut expired = Vec::new();
+        for run in by_shard.chunk_by(|a, b| a.0 == b.0) {
+            let shard = layout.shard_at(run[0].0);
+            let run: Vec<&str> = run
+                .iter()
+                .map(|&(_, key)| key)
+                .filter(|key| shard.may_contain(key))
+                .collect();
+            if run.is_empty() {
+                continue;
+            }
+
+            let guard = shard.data.read();
+            for key in run {
+                match guard.get(key) {
+                    Some(entry) if !entry.is_expired(now) => {
+                        entry.touch();
+                        found.push((key, Arc::clone(&entry.value)));
+                    }
+                    Some(_) => expired.push(key),
+                    None => {}
+                }
+            }
+        }
+        drop(layout);
+
+        self.hit_count
+            .fetch_add(found.len() as u64, Ordering::Relaxed);
+        self.miss_count
+            .fetch_add((keys.len() - found.len()) as u64, Ordering::Relaxed);
+        for key in expired {
+            self.purge_if_expired(key);
+        }
+
+        // Like get, values are copied once every shard lock is released
+        let mut values = HashMap::with_capacity(found.len());
+        for (key, value) in found {
+            values.insert(key.to_string(), value.as_ref().clone());
+        }
+        Ok(values)
+    }
+
+    #[instrument(skip(self, value), fields(key = %key, size = value.len()))]
+    async fn set(&self, key: &str, value: Vec<u8>) -> StorageResult<()> {
+        debug!("Setting key in memory engine");
+
+        self.record_operations(1);
+
+        let ttl = self.config.default_ttl.map(Duration::from_secs);
+        self.insert(key, value, ttl, None).map(|_| ())
+    }
+
+    #[instrument(skip(self, value), fields(key = %key, size = value.len(), ttl = ?ttl))]
+    async fn set_with_ttl(&self, key: &str, value: Vec<u8>, ttl: Duration) -> StorageResult<()> {
+        debug!("Setting key with TTL in memory engine");
+
+        self.record_operations(1);
+
+        let ttl = self.apply_ttl_policy(ttl)?;
+        self.insert(key, value, Some(ttl), None).map(|_| ())
+    }
+
+    #[instrument(skip(self), fields(key = %key))]
+    async fn get_versioned(&self, key: &str) -> StorageResult<Option<(Vec<u8>, u64)>> {
+        debug!("getting key with its version from memory engine");
+
+        self.record_operations(1);
+
+        let layout = self.layout();
+        let shard = layout.shard(key);
+        let (found, expired) = {
+            let guard = shard.data.read();
+
+            match guard.get(key) {
+                Some(entry) if !entry.is_expired(now_millis()) => {
+                    entry.touch();
+                    (Some((Arc::clone(&entry.value), entry.version)), false)
+                }
+                Some(_) => (None, true),
+                None => (None, false),
+            }
+        };
+
+        if let Some((value, version)) = found {
+            self.hit_count.fetch_add(1, Ordering::Relaxed);
+            return Ok(Some((value.as_ref().clone(), version)));
+        }
+
+        if expired {
+            self.purge_if_expired(key);
+        }
+
+        self.miss_count.fetch_add(1, Ordering::Relaxed);
+        Ok(None)
+    }
+
+    #[instrument(skip(self, value), fields(key = %key, size = value.len(), expected))]
+    async fn set_if_version(&self, key: &str, value: Vec<u8>, expected: u64) -> StorageResult<u64> {
+        debug!("Setting key at an expected version in memory engine");
+
+        self.record_operations(1);
+
+        let ttl = self.config.default_ttl.map(Duration::from_secs);
+        self.insert(key, value, ttl, Some(expected))
+    }
+
+    #[instrument(skip(self), fields(key = %key, start, end))]
+    async fn get_range(&self, key: &str, start: i64, end: i64) -> StorageResult<Vec<u8>> {
+        debug!("Getting range from memory engine");
+
+        self.record_operations(1);
+
+        let layout = self.layout();
+        let shard = layout.shard(key);
+        let value = {
+            let guard = shard.data.read();
+
+            match guard.get(key) {
+                Some(entry) if !entry.is_expired(now_millis()) => {
+                    entry.touch();
+                    Arc::clone(&entry.value)
+                }
+                _ => return Ok(Vec::new()),
+            }
+        };
+        if is_typed(&value) {
+            return Err(StorageError::WrongType);
+        }
+
+        // Same index rules as Redis GETRANGE
+        let len = value.len() as i64;
+        let start = if start < 0 { len + start } else { start }.max(0);
+        let end = if end < 0 { len + end } else { end }.max(0).min(len - 1);
+
+        if len == 0 || start > end {
+            return Ok(Vec::new());
+        }
+
+        Ok(value[start as usize..=end as usize].to_vec())
+    }
+
+    #[instrument(skip(self, bytes), fields(key = %key, offset, size = bytes.len()))]
+    async fn set_range(&self, key: &str, offset: usize, bytes: &[u8]) -> StorageResult<usize> {
+        debug!("Setting range in memory engine");
+
+        self.record_operations(1);
+
+        self.purge_if_expired(key);
+
+        let layout = self.layout();
+        let shard = layout.shard(key);
+        let mut guard = shard.data.write();
+
+        if guard.get(key).is_some_and(|entry| is_typed(&entry.value)) {
+            return Err(StorageError::WrongType);
+        }
+
+        let old_len = guard.get(key).map(|entry| entry.value.len());
+
+        // Nothing to write: report the current length without creating the key
+        if bytes.is_empty() {
+            return Ok(old_len.unwrap_or(0));
+        }
+
+        let write_end = offset
+            .checked_add(bytes.len())
+            .ok_or_else(|| StorageError::Persistence("SETRANGE offset out of range".to_string()))?;
+        let new_len = write_end.max(old_len.unwrap_or(0));
+
+        // A string is never turned into something that reads as a set or list
+        let current = guard.get(key).map(|entry| entry.value.as_slice());
+        if typed_after_write(current, new_len, offset, bytes) {
+            return Err(StorageError::ReservedTypeTag);
+        }
+
+        let growth = match old_len {
+            Some(old_len) => new_len - old_len,
+            None => Shard::estimate_size(key, &[]) + new_len,
+        };
+        self.check_memory_limit(growth)?;
+
+        let ttl = self.config.default_ttl.map(Duration::from_secs);
+        let expires_at = ttl.map(|ttl| now_millis().saturating_add(ttl.as_millis() as u64));
+        if old_len.is_none() {
+            self.reserve_key(expires_at)?;
+            shard.remember(key);
+        }
+
+        let entry = guard
+            .entry(key.to_string())
+            .or_insert_with(|| Entry::new(Vec::new(), expires_at, self.next_version()));
+
+        // Zero-pad up to the offset, then overwrite in place
+        let value = Arc::make_mut(&mut entry.value);
+        if value.len() < new_len {
+            value.resize(new_len, 0);
+        }
+        value[offset..write_end].copy_from_slice(bytes);
+        entry.version = self.next_version();
+        entry.touch();
+
+        self.update_memory(growth as isize);
+        shard.size.fetch_add(growth, Ordering::Relaxed);
+
+        Ok(new_len)
+    }
+
+    #[instrument(skip(self), fields(key = %key, offset, bit))]
+    async fn set_bit(&self, key: &str, offset: usize, bit: bool) -> StorageResult<bool> {
+        debug!("Setting bit in memory engine");
+
+        self.record_operations(1);
+
+        self.purge_if_expired(key);
+
+        let layout = self.layout();
+        let shard = layout.shard(key);
+        let mut guard = shard.data.write();
+
+        if guard.get(key).is_some_and(|entry| is_typed(&entry.value)) {
+            return Err(StorageError::WrongType);
+        }
+
+        let byte = offset / 8;
+        let mask = 0x80u8 >> (offset % 8);
+        let old_len = guard.get(key).map(|entry| entry.value.len());
+        let new_len = (byte + 1).max(old_len.unwrap_or(0));
+
+        let current = guard.get(key).map(|entry| entry.value.as_slice());
+        let old_byte = current
+            .and_then(|value| value.get(byte))
+            .copied()
+            .unwrap_or(0);
+        let new_byte = if bit {
+            old_byte | mask
+        } else {
+            old_byte & !mask
+        };
+        if typed_after_write(current, new_len, byte, &[new_byte]) {
+            return Err(StorageError::ReservedTypeTag);
+        }
+
+        let growth = match old_len {
+            Some(old_len) => new_len - old_len,
+            None => Shard::estimate_size(key, &[]) + new_len,
+        };
+        self.check_memory_limit(growth)?;
+
+        let ttl = self.config.default_ttl.map(Duration::from_secs);
+        let expires_at = ttl.map(|ttl| now_millis().saturating_add(ttl.as_millis() as u64));
+        if old_len.is_none() {
+            self.reserve_key(expires_at)?;
+            shard.remember(key);
+        }
+
+        let entry = guard
+            .entry(key.to_string())
+            .or_insert_with(|| Entry::new(Vec::new(), expires_at, self.next_version()));
+
+        let value = Arc::make_mut(&mut entry.value);
+        if value.len() < new_len {
+            value.resize(new_len, 0);
+        }
+
+        let previous = value[byte] & mask != 0;
+        if bit {
+            value[byte] |= mask;
+        } else {
+            value[byte] &= !mask;
+        }
+        entry.version = self.next_version();
+        entry.touch();
+
+        self.update_memory(growth as isize);
+        shard.size.fetch_add(growth, Ordering::Relaxed);
+
+        Ok(previous)
+    }
+
+    #[instrument(skip(self), fields(key = %key, delta))]
+    async fn incr_by_float(&self, key: &str, delta: f64) -> StorageResult<f64> {
+        debug!("Incrementing float in memory engine");
+
+        self.record_operations(1);
+
+        self.purge_if_expired(key);
+
+        let layout = self.layout();
+        let shard = layout.shard(key);
+        let mut guard = shard.data.write();
+
+        let current = match guard.get(key) {
+            Some(entry) if is_typed(&entry.value) => return Err(StorageError::WrongType),
+            Some(entry) => parse_float(&entry.value).ok_or(StorageError::NotAFloat)?,
+            None => 0.0,
+        };
+
+        let result = current + delta;
+        if !result.is_finite() {
+            return Err(StorageError::FloatOverflow);
+        }
+
+        let value = format_float(result).into_bytes();
+        let old_size = guard
+            .get(key)
+            .map_or(0, |entry| Shard::estimate_size(key, &entry.value));
+        let new_size = Shard::estimate_size(key, &value);
+        if new_size > old_size {
+            self.check_memory_limit(new_size - old_size)?;
+        }
+
+        match guard.get_mut(key) {
+            Some(entry) => {
+                entry.value = Arc::new(value);
+                entry.version = self.next_version();
+                entry.touch();
+            }
+            None => {
+                let ttl = self.config.default_ttl.map(Duration::from_secs);
+                let expires_at = ttl.map(|ttl| now_millis().saturating_add(ttl.as_millis() as u64));
+                self.reserve_key(expires_at)?;
+                shard.remember(key);
+                guard.insert(
+                    key.to_string(),
+                    Entry::new(value, expires_at, self.next_version()),
+                );
+            }
+        }
+
+        self.update_memory(new_size as isize - old_size as isize);
+        shard.size.fetch_add(new_size, Ordering::Relaxed);
+        shard.size.fetch_sub(old_size, Ordering::Relaxed);
+
+        Ok(result)
+    }
+
+    #[instrument(skip(self, update), fields(key = %key))]
+    async fn update(&self, key: &str, update: UpdateFn<'_>) -> StorageResult<bool> {
+        debug!("Updating value in memory engine");
+
+        self.record_operations(1);
+
+        self.purge_if_expired(key);
+
+        let layout = self.layout();
+        let shard = layout.shard(key);
+        let mut guard = shard.data.write();
+
+        let current = guard.get(key).map(|entry| Arc::clone(&entry.value));
+        if current.as_deref().is_some_and(|value| is_typed(value)) {
+            return Err(StorageError::WrongType);
+        }
+
+        let Some(value) = update(current.as_deref().map(Vec::as_slice))? else {
+            return Ok(false);
+        };
+
+        let old_size = current.map_or(0, |value| Shard::estimate_size(key, &value));
+        let new_size = Shard::estimate_size(key, &value);
+        if new_size > old_size {
+            self.check_memory_limit(new_size - old_size)?;
+        }
+
+        // An existing key keeps its TTL
+        match guard.get_mut(key) {
+            Some(entry) => {
+                entry.value = Arc::new(value);
+                entry.version = self.next_version();
+                entry.touch();
+            }
+            None => {
+                let ttl = self.config.default_ttl.map(Duration::from_secs);
+                let expires_at = ttl.map(|ttl| now_millis().saturating_add(ttl.as_millis() as u64));
+                self.reserve_key(expires_at)?;
+                shard.remember(key);
+                guard.insert(
+                    key.to_string(),
+                    Entry::new(value, expires_at, self.next_version()),
+                );
+            }
+        }
+
+        self.update_memory(new_size as isize - old_size as isize);
+        shard.size.fetch_add(new_size, Ordering::Relaxed);
+        shard.size.fetch_sub(old_size, Ordering::Relaxed);
+
+        Ok(true)
+    }
+
+    #[instrument(skip(self, members), fields(key = %key, count = members.len()))]
+    async fn add_members(&self, key: &str, members: &[Vec<u8>]) -> StorageResult<usize> {
+        debug!("Adding set members in memory engine");
+
+        self.record_operations(1);
+
+        self.update_set(key, |set| {
+            members
+                .iter()
+                .filter(|member| set.insert(member.to_vec()))
+                .count()
+        })
+    }
+
+    #[instrument(skip(self, members), fields(key = %key, count = members.len()))]
+    async fn remove_members(&self, key: &str, members: &[Vec<u8>]) -> StorageResult<usize> {
+        debug!("Removing set members in memory engine");
+
+        self.record_operations(1);
+
+        self.update_set(key, |set| {
+            members
+                .iter()
+                .filter(|member| set.remove(member.as_slice()))
+                .count()
+        })
+    }
+
+    #[instrument(skip(self, items), fields(key = %key, items = items.len(), front))]
+    async fn push_items(&self, key: &str, items: &[Vec<u8>], front: bool) -> StorageResult<usize> {
+        debug!("Pushing list items in memory engine");
+
+        self.record_operations(1);
+
+        let len = self.update_list(key, |list| {
+            for item in items {
+                if front {
+                    list.push_front(item.clone());
+                } else {
+                    list.push_back(item.clone());
+                }
+            }
+            list.len()
+        })?;
+
+        // Woken poppers retry and race for the items, losers go back to waiting
+        let waiter = self.list_waiters.lock().get(key).and_then(Weak::upgrade);
+        if let Some(waiter) = waiter {
+            waiter.notify_waiters();
+        }
+
+        Ok(len)
+    }
+
+    #[instrument(skip(self), fields(key = %key))]
+    async fn pop_front(&self, key: &str) -> StorageResult<Option<Vec<u8>>> {
+        debug!("Popping list item in memory engine");
+
+        self.record_operations(1);
+
+        self.update_list(key, |list| list.pop_front())
+    }
+
+    fn list_waiter(&self, key: &str) -> Arc<Notify> {
+        let mut waiters = self.list_waiters.lock();
+        if let Some(waiter) = waiters.get(key).and_then(Weak::upgrade) {
+            return waiter;
+        }
+
+        waiters.retain(|_, waiter| waiter.strong_count() > 0);
+        let waiter = Arc::new(Notify::new());
+        waiters.insert(key.to_string(), Arc::downgrade(&waiter));
+        waiter
+    }
+
+    #[instrument(skip(self), fields(key = %key))]
+    async fn delete(&self, key: &str) -> StorageResult<bool> {
+        debug!("Deleting key from memory engine");
+
+        self.record_operations(1);
+
+        let layout = self.layout();
+        let shard = layout.shard(key);
+        let mut guard = shard.data.write();
+
+        match guard.remove(key) {
+            Some(old) => {
+                let size = Shard::estimate_size(key, &old.value);
+                self.update_memory(-(size as isize));
+                shard.size.fetch_sub(size, Ordering::Relaxed);
+                self.release_key(&old);
+
+                drop(guard);
+
+                debug!("Key deleted from memory");
+
+                // An expired key was already logically gone
+                let expired = old.is_expired(now_millis());
+                if expired {
+                    self.expired_keys.fetch_add(1, Ordering::Relaxed);
+                }
+                self.notify(if expired { "expired" } else { "del" }, key);
+                Ok(!expired)
+            }
+            None => {
+                debug!("Key not found in memory");
+                Ok(false)
+            }
+        }
+    }
+
+    async fn delete_prefix(&self, prefix: &str) -> StorageResult<Vec<String>> {
+        debug!("Deleting keys by prefix from memory engine");
+
+        self.record_operations(1);
+
+        let now = now_millis();
+        let mut deleted = Vec::new();
+        let mut expired = Vec::new();
+
+        // Internal metadata only goes when the prefix names the reserved namespace itself
+        let reserved = self
+            .config
+            .reserved_prefix
+            .as_deref()
+            .filter(|reserved| !prefix.starts_with(reserved));
+
+        let layout = self.layout();
+        for shard in layout.all() {
+            let mut guard = shard.data.write();
+            let matching: Vec<String> = guard
+                .keys()
+                .filter(|key| {
+                    key.starts_with(prefix)
+                        && !reserved.is_some_and(|reserved| key.starts_with(reserved))
+                })
+                .cloned()
+                .collect();
+
+            for key in matching {
+                let Some(old) = guard.remove(&key) else {
+                    continue;
+                };
+                let size = Shard::estimate_size(&key, &old.value);
+                self.update_memory(-(size as isize));
+                shard.size.fetch_sub(size, Ordering::Relaxed);
+                self.release_key(&old);
+
+                if old.is_expired(now) {
+                    expired.push(key);
+                } else {
+                    deleted.push(key);
+                }
+            }
+        }
+        drop(layout);
+
+        // Notified once every lock is released, like a plain delete
+        self.expired_keys
+            .fetch_add(expired.len() as u64, Ordering::Relaxed);
+        for key in &expired {
+            self.notify("expired", key);
+        }
+        for key in &deleted {
+            self.notify("del", key);
+        }
+
+        debug!("Deleted {} keys by prefix", deleted.len());
+        Ok(deleted)
+    }
+
+    async fn exists(&self, key: &str) -> StorageResult<bool> {
+        self.record_operations(1);
+
+        let layout = self.layout();
+        let shard = layout.shard(key);
+        if !shard.may_contain(key) {
+            return Ok(false);
+        }
+
+        let guard = shard.data.read();
+        Ok(guard
+            .get(key)
+            .is_some_and(|entry| !entry.is_expired(now_millis())))
+    }
+
+    #[instrument(skip(self), fields(key = %key, at_millis))]
+    async fn expire_at(&self, key: &str, at_millis: u64) -> StorageResult<Option<u64>> {
+        debug!("Setting expiry in memory engine");
+
+        self.record_operations(1);
+
+        let now = now_millis();
+        let at_millis = match at_millis.checked_sub(now) {
+            Some(ttl) if ttl > 0 => {
+                let ttl = self.apply_ttl_policy(Duration::from_millis(ttl))?;
+                now.saturating_add(ttl.as_millis() as u64)
+            }
+            _ => at_millis,
+        };
+
+        self.purge_if_expired(key);
+
+        let layout = self.layout();
+        let shard = layout.shard(key);
+        let mut guard = shard.data.write();
+
+        let Some(entry) = guard.get_mut(key) else {
+            return Ok(None);
+        };
+        self.track_ttl(entry.expires_at, Some(at_millis));
+        entry.expires_at = Some(at_millis);
+        drop(guard);
+
+        // Already due: gone now rather than at the next lookup or sweep
+        if at_millis <= now {
+            self.purge_if_expired(key);
+        }
+
+        Ok(Some(at_millis))
+    }
+
+    async fn expires_at(&self, key: &str) -> StorageResult<Option<Option<u64>>> {
+        self.record_operations(1);
+
+        let layout = self.layout();
+        let shard = layout.shard(key);
+        let guard = shard.data.read();
+
+        Ok(guard
+            .get(key)
+            .filter(|entry| !entry.is_expired(now_millis()))
+            .map(|entry| entry.expires_at))
+    }
+
+    async fn idle_time(&self, key: &str) -> StorageResult<Option<u64>> {
+        self.record_operations(1);
+
+        let layout = self.layout();
+        let shard = layout.shard(key);
+        let guard = shard.data.read();
+
+        Ok(guard
+            .get(key)
+            .filter(|entry| !entry.is_expired(now_millis()))
+            .map(|entry| {
+                let last_access = entry.last_access.load(Ordering::Relaxed);
+                (now_millis() / 1000).saturating_sub(last_access)
+            }))
+    }
+
+    async fn encoding(&self, key: &str) -> StorageResult<Option<&'static str>> {
+        self.record_operations(1);
+
+        let layout = self.layout();
+        let shard = layout.shard(key);
+        let guard = shard.data.read();
+
+        Ok(guard
+            .get(key)
+            .filter(|entry| !entry.is_expired(now_millis()))
+            .map(|entry| encoding(&entry.value)))
+    }
+
+    async fn memory_usage(&self, key: &str) -> StorageResult<Option<usize>> {
+        self.record_operations(1);
+
+        let layout = self.layout();
+        let shard = layout.shard(key);
+        let guard = shard.data.read();
+
+        Ok(guard
+            .get(key)
+            .filter(|entry| !entry.is_expired(now_millis()))
+            .map(|entry| Shard::estimate_size(key, &entry.value)))
+    }
+
+    async fn memory_stats(&self) -> StorageResult<MemoryStats> {
+        self.record_operations(1);
+
+        // Shard sizes are kept up to date on every write, so this never walks the keys
+        let layout = self.layout();
+        let mut stats = MemoryStats::default();
+        for shard in layout.all() {
+            let keys = shard.data.read().len();
+            let bytes = shard.size.load(Ordering::Relaxed);
+            stats.keys += keys;
+            stats.overhead_bytes += keys * ENTRY_OVERHEAD;
+            stats.keyspace_bytes += bytes.saturating_sub(keys * ENTRY_OVERHEAD);
+            stats.shard_bytes.push(bytes);
+        }
+
+        Ok(stats)
+    }
+
+    async fn access_frequency(&self, key: &str) -> StorageResult<Option<u64>> {
+        self.record_operations(1);
+
+        let layout = self.layout();
+        let shard = layout.shard(key);
+        let guard = shard.data.read();
+
+        let now = now_millis();
+        Ok(guard
+            .get(key)
+            .filter(|entry| !entry.is_expired(now))
+            .map(|entry| entry.frequency_at(now)))
+    }
+
+    #[instrument(skip(self, keys), fields(keys = keys.len()))]
+    async fn touch(&self, keys: &[String]) -> StorageResult<usize> {
+        debug!("Touching keys in memory engine");
+
+        self.record_operations(keys.len());
+
+        let now = now_millis();
+        let mut touched = 0;
+
+        let layout = self.layout();
+        for key in keys {
+            let guard = layout.shard(key).data.read();
+            if let Some(entry) = guard.get(key.as_str())
+                && !entry.is_expired(now)
+            {
+                entry.touch();
+                touched += 1;
+            }
+        }
+
+        Ok(touched)
+    }
+
+    #[instrument(skip(self), fields(from = %from, to = %to))]
+    async fn rename(&self, from: &str, to: &str) -> StorageResult<bool> {
+        debug!("Renaming key in memory engine");
+
+        self.record_operations(1);
+
+        let layout = self.layout();
+        let mut locked = self.lock_shards(&layout, &[from, to]);
+
+        let entry = match locked.remove(from) {
+            Some(entry) if !entry.is_expired(now_millis()) => entry,
+            _ => return Ok(false),
+        };
+
+        entry.touch();
+        locked.insert(to, entry);
+
+        Ok(true)
+    }
+
+    #[instrument(skip(self), fields(prefix = %prefix))]
+    async fn scan(&self, prefix: &str) -> StorageResult<KeyStream> {
+        debug!("Scanning keys with prefix");
+
+        self.record_operations(1);
+
+        let prefix = prefix.to_string();
+
+        // Walk one shard at a time, only holding its read lock while copying matching keys
+        let (shards, scan) = self.scan_shards();
+        let keys = stream::iter(shards).flat_map(move |shard| {
+            let _scan = &scan;
+            let now = now_millis();
+            let guard = shard.data.read();
+            let matching: Vec<StorageResult<String>> = guard
+                .iter()
+                .filter(|(key, entry)| key.starts_with(&prefix) && !entry.is_expired(now))
+                .map(|(key, _)| Ok(key.clone()))
+                .collect();
+            stream::iter(matching)
+        });
+
+        Ok(Box::pin(keys))
+    }
+
+    async fn scan_entries(&self, prefix: &str) -> StorageResult<EntryStream> {
+        self.record_operations(1);
+
+        Ok(self.entries(prefix.to_string()))
+    }
+
+    async fn iter_all(&self) -> StorageResult<EntryStream> {
+        Ok(self.entries(String::new()))
+    }
+
+    async fn iter_with_expiry(&self) -> StorageResult<ExpiringEntryStream> {
+        Ok(self.entries_with_expiry(String::new()))
+    }
+
+    async fn purge_expired(&self) -> StorageResult<usize> {
+        let layout = self.layout();
+        let index = self.expire_cursor.fetch_add(1, Ordering::Relaxed) % layout.len();
+        let shard = layout.shard_at(index);
+
+        let now = now_millis();
+        let mut guard = shard.data.write();
+        let expired: Vec<String> = guard
+            .iter()
+            .filter(|(_, entry)| entry.is_expired(now))
+            .map(|(key, _)| key.clone())
+            .collect();
+
+        for key in &expired {
+            if let Some(old) = guard.remove(key) {
+                let size = Shard::estimate_size(key, &old.value);
+                self.update_memory(-(size as isize));
+                shard.size.fetch_sub(size, Ordering::Relaxed);
+                self.release_key(&old);
+            }
+        }
+        // The sweep visits every shard in turn, so it also keeps their filters from drifting
+        shard.refresh_filter(&guard, false);
+        drop(guard);
+
+        self.expired_keys
+            .fetch_add(expired.len() as u64, Ordering::Relaxed);
+        for key in &expired {
+            self.notify("expired", key);
+        }
+        if !expired.is_empty() {
+            debug!("Purged {} expired keys from shard {}", expired.len(), index);
+        }
+
+        Ok(expired.len())
+    }
+
+    async fn resize_shards(&self, shard_count: usize) -> StorageResult<()> {
+        if shard_count == 0 {
+            return Err(StorageError::Resize("shard_count must be > 0".to_string()));
+        }
+
+        let mut layout = self.layout.write();
+        if layout.resize.is_some() {
+            return Err(StorageError::Resize(
+                "a shard resize is already in progress".to_string(),
+            ));
+        }
+        if layout.shards.len() == shard_count {
+            return Ok(());
+        }
+
+        let new_layout = ShardLayout::new(shard_count, bloom_capacity(&self.config, shard_count));
+        let from = std::mem::replace(&mut layout.shards, new_layout.shards);
+        info!("Resizing from {} to {} shards", from.len(), shard_count);
+        layout.resize = Some(Resize { from, migrated: 0 });
+        drop(layout);
+
+        let layout = self.layout.clone();
+        let scans = self.scans.clone();
+        tokio::spawn(async move {
+            loop {
+                match migrate_step(&layout, &scans) {
+                    Some(true) => break,
+                    Some(false) => tokio::task::yield_now().await,
+                    None => tokio::time::sleep(Duration::from_millis(10)).await,
+                }
+            }
+            info!("Shard resize to {} shards complete", shard_count);
+        });
+
+        Ok(())
+    }
+
+    // Reads counters only, so it is cheap enough to call on every scrape. Like Redis' DBSIZE,
+    // key counts include expired keys until a lookup or the sweeper purges them
+    async fn stats(&self) -> StorageResult<StorageStats> {
+        let total_ops = self.total_operations.load(Ordering::Relaxed);
+        let hits = self.hit_count.load(Ordering::Relaxed);
+        let misses = self.miss_count.load(Ordering::Relaxed);
+
+        // Hit rate should only consider hits and misses
+        // because other operations (set, delete) do not contribute to hit/miss
+        let total_gets = hits + misses;
+        let hit_rate = if total_gets > 0 {
+            hits as f64 / total_gets as f64
+        } else {
+            0.0
+        };
+
+        Ok(StorageStats {
+            total_keys: self.key_count.load(Ordering::Relaxed),
+            memory_usage: self.total_memory.load(Ordering::Relaxed),
+            hit_rate,
+            total_operations: total_ops,
+            keyspace_hits: hits,
+            keyspace_misses: misses,
+            evicted_keys: self.evicted_keys.load(Ordering::Relaxed),
+            expires: self.volatile_count.load(Ordering::Relaxed),
+            expired_keys: self.expired_keys.load(Ordering::Relaxed),
+            max_keys: self.config.max_keys,
+        })
+    }
+
+    async fn clear(&self) -> StorageResult<usize> {
+        debug!("Clearing memory engine");
+
+        self.record_operations(1);
+
+        // Every shard is locked (in ascending order) before any is emptied, so no
+        // command observes a half-flushed keyspace
+        let layout = self.layout();
+        let mut guards: Vec<_> = layout.all().map(|shard| shard.data.write()).collect();
+
+        let now = now_millis();
+        let mut removed = 0;
+        for (shard, guard) in layout.all().zip(guards.iter_mut()) {
+            removed += guard
+                .values()
+                .filter(|entry| !entry.is_expired(now))
+                .count();
+            for entry in guard.values() {
+                self.release_key(entry);
+            }
+            guard.clear();
+            shard.refresh_filter(guard, true);
+
+            let size = shard.size.swap(0, Ordering::Relaxed);
+            self.update_memory(-(size as isize));
+        }
+
+        info!("Cleared {} keys", removed);
+        Ok(removed)
     }
 
     async fn health_check(&self) -> StorageResult<()> {
         // Simple health check - try to access first shard
-        let _guard = self.shards[0].data.read();
+        let layout = self.layout();
+        let _guard = layout.shard_at(0).data.read();
         Ok(())
     }
 }
@@ -1 +1,2 @@
+pub mod bloom;
 pub mod memory;
@@ -1,10 +1,13 @@
-use std::u64;
+use std::{collections::HashMap, pin::Pin, sync::Arc, time::Duration};
 
+use futures_util::Stream;
 use serde::{Deserialize, Serialize};
 use thiserror::Error;
+use tokio::sync::Notify;
 
 pub mod engine;
 pub mod persistence;
+pub mod value;
 
 #[derive(Debug, Error)]
 pub enum StorageError {
@@ -22,26 +25,172 @@ pub enum StorageError {
 
     #[error("Persistence error: {0}")]
     Persistence(String),
+
+    #[error("Invalid TTL: {0}")]
+    InvalidTtl(String),
+
+    #[error("WRONGTYPE Operation against a key holding the wrong kind of value")]
+    WrongType,
+
+    #[error("Value would start with a reserved type tag")]
+    ReservedTypeTag,
+
+    #[error("Value is not a valid float")]
+    NotAFloat,
+
+    #[error("Increment would produce NaN or Infinity")]
+    FloatOverflow,
+
+    #[error("Key limit reached (max_keys {0}), only existing keys can be written")]
+    KeyLimit(usize),
+
+    #[error("VERSIONMISMATCH Expected version {expected}, key is at {actual}")]
+    VersionMismatch { expected: u64, actual: u64 },
+
+    #[error("Resize error: {0}")]
+    Resize(String),
+
+    #[error("Corrupt value: {0}")]
+    CorruptValue(String),
 }
 
 pub type StorageResult<T> = Result<T, StorageError>;
 
+// Lazily produced keys, so large keyspaces never need to be held in RAM at once
+pub type KeyStream = Pin<Box<dyn Stream<Item = StorageResult<String>> + Send>>;
+
+// Lazily produced key-value pairs (for snapshots, compaction, ...)
+pub type EntryStream = Pin<Box<dyn Stream<Item = StorageResult<(String, Vec<u8>)>> + Send>>;
+
+// Key-value pairs with each key's absolute expiry in unix millis (for snapshots)
+pub type ExpiringEntryStream =
+    Pin<Box<dyn Stream<Item = StorageResult<(String, Vec<u8>, Option<u64>)>> + Send>>;
+
+// Called with each key eviction drops, while the key's shard is still locked
+pub type EvictionListener = Arc<dyn Fn(&str) + Send + Sync>;
+
+// Read-modify-write step run under a key's lock: gets the current value (None when the key
+// is missing) and returns the value to store, or None to leave the key as it is
+pub type UpdateFn<'a> =
+    Box<dyn FnOnce(Option<&[u8]>) -> StorageResult<Option<Vec<u8>>> + Send + 'a>;
+
 #[async_trait::async_trait]
 pub trait StorageEngine: Send + Sync {
     // Get value by key
     async fn get(&self, key: &str) -> StorageResult<Option<Vec<u8>>>;
 
-    // Set key-value pair
+    // Get several keys at once, locking each shard only once; missing keys are left out
+    async fn get_many(&self, keys: &[&str]) -> StorageResult<HashMap<String, Vec<u8>>>;
+
+    // Set key-value pair (uses the configured default TTL, if any)
     async fn set(&self, key: &str, value: Vec<u8>) -> StorageResult<()>;
 
+    // Set key-value pair that expires after ttl (subject to max_ttl policy)
+    async fn set_with_ttl(&self, key: &str, value: Vec<u8>, ttl: Duration) -> StorageResult<()>;
+
+    // Get value by key along with its version, which every write to the key changes
+    async fn get_versioned(&self, key: &str) -> StorageResult<Option<(Vec<u8>, u64)>>;
+
+    // Set like `set`, only while the key is still at version `expected` (0 = key must not
+    // exist); returns the new version
+    async fn set_if_version(&self, key: &str, value: Vec<u8>, expected: u64) -> StorageResult<u64>;
+
+    // Bytes between start and end inclusive, negative offsets count from the end (empty if missing)
+    async fn get_range(&self, key: &str, start: i64, end: i64) -> StorageResult<Vec<u8>>;
+
+    // Overwrite bytes at offset, zero-padding past the end, returns the new value length
+    async fn set_range(&self, key: &str, offset: usize, bytes: &[u8]) -> StorageResult<usize>;
+
+    // Set or clear one bit (bit 0 = most significant bit of byte 0), zero-growing the value
+    // as needed, returns the bit's previous value
+    async fn set_bit(&self, key: &str, offset: usize, bit: bool) -> StorageResult<bool>;
+
+    // Add delta to the float stored at key (0 if missing), keeping its TTL, returns the new value
+    async fn incr_by_float(&self, key: &str, delta: f64) -> StorageResult<f64>;
+
+    // Atomically replace a string value with what `update` computes from it, sets and
+    // lists are refused. Returns whether a value was written
+    async fn update(&self, key: &str, update: UpdateFn<'_>) -> StorageResult<bool>;
+
+    // Add members to the set at key, creating it if missing, returns how many were new
+    async fn add_members(&self, key: &str, members: &[Vec<u8>]) -> StorageResult<usize>;
+
+    // Remove members from the set at key, dropping the key once empty, returns how many were removed
+    async fn remove_members(&self, key: &str, members: &[Vec<u8>]) -> StorageResult<usize>;
+
+    // Push items one by one onto the head (front) or tail of the list at key, creating it
+    // if missing, returns the new length. Wakes clients waiting on the key
+    async fn push_items(&self, key: &str, items: &[Vec<u8>], front: bool) -> StorageResult<usize>;
+
+    // Remove and return the first item of the list at key, dropping the key once empty
+    async fn pop_front(&self, key: &str) -> StorageResult<Option<Vec<u8>>>;
+
+    // Notified after every push onto the list at key, for blocking pops
+    fn list_waiter(&self, key: &str) -> Arc<Notify>;
+
     // Delete key-value pair
     async fn delete(&self, key: &str) -> StorageResult<bool>;
 
+    // Remove every key at once, returns how many live keys were dropped
+    async fn clear(&self) -> StorageResult<usize>;
+
+    // Remove every key starting with prefix, each shard atomically under its write lock
+    // Returns the live keys removed, expired ones are dropped without being listed
+    // Keys under the reserved prefix are kept unless the prefix itself lies inside it
+    async fn delete_prefix(&self, prefix: &str) -> StorageResult<Vec<String>>;
+
     // Check if key exists
     async fn exists(&self, key: &str) -> StorageResult<bool>;
 
-    // Get all keys with prefix
-    async fn scan(&self, prefix: &str) -> StorageResult<Vec<String>>;
+    // Make the key expire at an absolute unix time in millis (subject to max_ttl policy),
+    // a time in the past expires it right away. Returns the expiry actually set, which the
+    // policy may have brought forward, or None if the key is missing
+    async fn expire_at(&self, key: &str, at_millis: u64) -> StorageResult<Option<u64>>;
+
+    // Absolute expiry of the key in unix millis: None if missing, Some(None) if it never
+    // expires
+    async fn expires_at(&self, key: &str) -> StorageResult<Option<Option<u64>>>;
+
+    // Seconds since the key was last read or written (None if missing)
+    async fn idle_time(&self, key: &str) -> StorageResult<Option<u64>>;
+
+    // Decaying access-frequency counter of the key, 0-255 (None if missing)
+    async fn access_frequency(&self, key: &str) -> StorageResult<Option<u64>>;
+
+    // Internal encoding of the key's value, as reported by OBJECT ENCODING (None if missing)
+    async fn encoding(&self, key: &str) -> StorageResult<Option<&'static str>>;
+
+    // Bytes the key counts for against max_memory (None if missing)
+    async fn memory_usage(&self, key: &str) -> StorageResult<Option<usize>>;
+
+    // Where the bytes counted against max_memory go
+    async fn memory_stats(&self) -> StorageResult<MemoryStats>;
+
+    // Refresh last-access time without reading values, returns how many keys exist
+    async fn touch(&self, keys: &[String]) -> StorageResult<usize>;
+
+    // Atomically move a value to a new key, replacing any existing value (false if source missing)
+    async fn rename(&self, from: &str, to: &str) -> StorageResult<bool>;
+
+    // Stream all keys with prefix
+    async fn scan(&self, prefix: &str) -> StorageResult<KeyStream>;
+
+    // Stream key-value pairs with prefix, without materializing the whole result
+    async fn scan_entries(&self, prefix: &str) -> StorageResult<EntryStream>;
+
+    // Stream all key-value pairs
+    async fn iter_all(&self) -> StorageResult<EntryStream>;
+
+    // Stream all key-value pairs along with their expiry
+    async fn iter_with_expiry(&self) -> StorageResult<ExpiringEntryStream>;
+
+    // Actively remove expired keys from part of the keyspace (one shard per call for the
+    // memory engine), returns how many were removed
+    async fn purge_expired(&self) -> StorageResult<usize>;
+
+    // Spread keys over a different number of shards while serving; returns once the new
+    // layout is in place, keys are then migrated to it in the background
+    async fn resize_shards(&self, shard_count: usize) -> StorageResult<()>;
 
     // Get storage statistics
     async fn stats(&self) -> StorageResult<StorageStats>;
@@ -53,10 +202,25 @@ pub trait StorageEngine: Send + Sync {
 // Storage statistics structure
 #[derive(Debug, Clone)]
 pub struct StorageStats {
-    pub total_keys: usize,
+    pub total_keys: usize, // Keys held, including expired ones not purged yet
     pub memory_usage: usize,
-    pub hit_rate: f64,
-    pub total_operations: u64,
+    pub hit_rate: f64, // GET hits / (hits + misses); other commands don't affect it
+    pub total_operations: u64, // Keyspace operations, one per key a command addresses
+    pub keyspace_hits: u64, // GETs that found a live key
+    pub keyspace_misses: u64, // GETs that found nothing (or an expired key)
+    pub evicted_keys: u64, // Keys dropped to stay under max_memory
+    pub expires: usize, // Keys with a TTL, counted like total_keys
+    pub expired_keys: u64, // Keys removed because their TTL ran out, lazily or by the sweeper
+    pub max_keys: Option<usize>, // Cap on the key count (None = unlimited)
+}
+
+// Breakdown of memory_usage, in the same accounting as max_memory
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct MemoryStats {
+    pub keys: usize,
+    pub keyspace_bytes: usize,   // Key and value bytes
+    pub overhead_bytes: usize,   // Fixed per-key estimate on top of them
+    pub shard_bytes: Vec<usize>, // Keyspace plus overhead of each shard, in shard order
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -66,6 +230,120 @@ pub struct StorageConfig {
     pub aof_path: String,          // AOF file path
     pub snapshot_interval: u64,    // Snapshot interval in seconds
     pub shard_count: usize,        // Number of shards for HashMap}
+
+    #[serde(default)]
+    pub default_ttl: Option<u64>, // TTL in seconds for keys set without one (None = no expiry)
+
+    #[serde(default)]
+    pub max_ttl: Option<u64>, // Max TTL in seconds a client may request (None = unlimited)
+
+    #[serde(default)]
+    pub max_keys: Option<usize>, // Max keys per database, new keys are refused past it (None = unlimited)
+
+    #[serde(default)]
+    pub ttl_overflow: TtlOverflowPolicy, // What to do with a TTL above max_ttl
+
+    #[serde(default = "default_max_key_size")]
+    pub max_key_size: usize, // Max key length in bytes
+
+    #[serde(default = "default_max_value_size")]
+    pub max_value_size: usize, // Max value size in bytes
+
+    #[serde(default = "default_reserved_prefix")]
+    pub reserved_prefix: Option<String>, // Key namespace reserved for internal metadata
+
+    #[serde(default = "default_databases")]
+    pub databases: usize, // Logical databases selectable with SELECT, each with its own shards
+
+    #[serde(default)]
+    pub maxmemory_policy: MaxMemoryPolicy, // Which keys to evict when max_memory is reached
+
+    #[serde(default = "default_eviction_sample_size")]
+    pub eviction_sample_size: usize, // Keys compared per eviction by the LRU and TTL policies
+
+    #[serde(default = "default_active_expire_interval_ms")]
+    pub active_expire_interval_ms: u64, // How often the sweeper purges expired keys (0 = lazy expiry only)
+
+    #[serde(default = "default_max_packed_entries")]
+    pub set_max_packed_entries: usize, // Sets with more members are stored as a hashtable
+
+    #[serde(default = "default_max_packed_entries")]
+    pub list_max_packed_size: usize, // Lists with more items are stored as a quicklist
+
+    #[serde(default = "default_max_packed_value")]
+    pub max_packed_value: usize, // Collections holding a longer element are never packed
+
+    #[serde(default)]
+    pub bloom_filter: bool, // Per-shard bloom filter so GET/EXISTS of absent keys skip the shard lock
+
+    #[serde(default = "default_bloom_filter_capacity")]
+    pub bloom_filter_capacity: usize, // Keys the filters are sized for across all shards, 10 bits each
+}
+
+fn default_max_packed_entries() -> usize {
+    128
+}
+
+fn default_max_packed_value() -> usize {
+    64
+}
+
+fn default_bloom_filter_capacity() -> usize {
+    1_000_000
+}
+
+fn default_eviction_sample_size() -> usize {
+    5
+}
+
+fn default_max_key_size() -> usize {
+    512
+}
+
+fn default_max_value_size() -> usize {
+    10 * 1024 * 1024 // 10 MB
+}
+
+fn default_reserved_prefix() -> Option<String> {
+    Some("__blaze:".to_string())
+}
+
+fn default_databases() -> usize {
+    16
+}
+
+fn default_active_expire_interval_ms() -> u64 {
+    100
+}
+
+// Policy for client TTLs exceeding max_ttl
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TtlOverflowPolicy {
+    #[default]
+    Clamp, // Silently cap to max_ttl
+    Reject, // Fail the write
+}
+
+// Policy for writes that would take memory past max_memory
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum MaxMemoryPolicy {
+    #[default]
+    #[serde(rename = "noeviction")]
+    NoEviction, // Fail the write
+    AllkeysLru,    // Evict the least recently accessed of a sample of keys
+    AllkeysLfu,    // Evict the least frequently accessed of a sample of keys
+    AllkeysRandom, // Evict any key
+    VolatileLru,   // Like allkeys-lru, among keys with a TTL only
+    VolatileTtl,   // Evict the key closest to expiring among a sample of keys with a TTL
+}
+
+impl MaxMemoryPolicy {
+    // Whether only keys with a TTL may be evicted
+    pub fn is_volatile(&self) -> bool {
+        matches!(self, Self::VolatileLru | Self::VolatileTtl)
+    }
 }
 
 impl Default for StorageConfig {
@@ -76,6 +354,27 @@ impl Default for StorageConfig {
             aof_path: "resplite.aof".to_string(),
             snapshot_interval: 3600, // 5 minutes
             shard_count: 16,         // 16 shards
+            default_ttl: None,
+            max_ttl: None,
+            max_keys: None,
+            ttl_overflow: TtlOverflowPolicy::Clamp,
+            max_key_size: default_max_key_size(),
+            max_value_size: default_max_value_size(),
+            reserved_prefix: default_reserved_prefix(),
+            databases: default_databases(),
+            maxmemory_policy: MaxMemoryPolicy::NoEviction,
+            eviction_sample_size: default_eviction_sample_size(),
+            active_expire_interval_ms: default_active_expire_interval_ms(),
+            set_max_packed_entries: default_max_packed_entries(),
+            list_max_packed_size: default_max_packed_entries(),
+            max_packed_value: default_max_packed_value(),
+            bloom_filter: false,
+            bloom_filter_capacity: default_bloom_filter_capacity(),
         }
     }
 }
+
+// Current wall-clock time as Unix millis (used for expiry timestamps)
+pub fn now_millis() -> u64 {
+    chrono::Utc::now().timestamp_millis().max(0) as u64
+}
@@ -1,30 +1,111 @@
 use std::{
+    borrow::Cow,
+    io::SeekFrom,
     path::{Path, PathBuf},
+    pin::Pin,
     sync::{
         Arc,
-        atomic::{AtomicU64, Ordering},
+        atomic::{AtomicBool, AtomicU64, Ordering},
     },
+    time::{Duration, Instant},
 };
 
+use chrono::{DateTime, Utc};
 use flume::{Receiver, Sender};
+use futures_util::{Stream, TryStreamExt, stream};
 use serde::{Deserialize, Serialize};
 use tokio::{
     fs::{File, OpenOptions},
-    io::{AsyncBufReadExt, AsyncWriteExt, BufReader, BufWriter},
+    io::{AsyncBufReadExt, AsyncReadExt, AsyncSeekExt, AsyncWriteExt, BufReader, BufWriter},
 };
-use tracing::{debug, info, instrument, warn};
+use tracing::{debug, error, info, instrument, warn};
 
-use crate::storage::{StorageError, StorageResult};
+use crate::storage::{EntryStream, ExpiringEntryStream, StorageError, StorageResult};
+
+// Logged operations, each paired with the byte offset just past its entry
+pub type OperationStream = Pin<Box<dyn Stream<Item = StorageResult<(Operation, u64)>> + Send>>;
 
 // Operations that can be logged to AOF (Append-Only File)
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub enum Operation {
-    Put { key: String, value: Vec<u8> },
-    Delete { key: String },
-    // Future: Expire, Increment, etc.
+    Put {
+        key: String,
+        value: Vec<u8>,
+    },
+    // Put with its absolute expiry in the same entry, so no replay sees one without the other
+    PutExpiring {
+        key: String,
+        value: Vec<u8>,
+        at_millis: u64,
+    },
+    // Like Put, but an existing key keeps its expiry (INCRBYFLOAT, EVAL results)
+    Update {
+        key: String,
+        value: Vec<u8>,
+    },
+    Delete {
+        key: String,
+    },
+    SetRange {
+        key: String,
+        offset: usize,
+        value: Vec<u8>,
+    },
+    SetBit {
+        key: String,
+        offset: usize,
+        bit: bool,
+    },
+    SetAdd {
+        key: String,
+        members: Vec<Vec<u8>>,
+    },
+    SetRemove {
+        key: String,
+        members: Vec<Vec<u8>>,
+    },
+    ListPush {
+        key: String,
+        items: Vec<Vec<u8>>,
+        front: bool,
+    },
+    ListPop {
+        key: String,
+    },
+    ExpireAt {
+        key: String,
+        at_millis: u64, // Absolute, so replay doesn't push expiry back
+    },
+    Flush,
+    // An operation on a database other than 0; every entry carries its own database so
+    // the file can be read from any offset without tracking a selected database
+    Select {
+        db: usize,
+        operation: Box<Operation>,
+    },
 }
 
 impl Operation {
+    // Tag the operation with the database it applies to (database 0 stays untagged)
+    pub fn in_database(self, db: usize) -> Self {
+        match self {
+            _ if db == 0 => self,
+            Operation::Select { operation, .. } => Operation::Select { db, operation },
+            operation => Operation::Select {
+                db,
+                operation: Box::new(operation),
+            },
+        }
+    }
+
+    // Split into the target database and the untagged operation
+    pub fn into_database(self) -> (usize, Operation) {
+        match self {
+            Operation::Select { db, operation } => (db, *operation),
+            operation => (0, operation),
+        }
+    }
+
     // Serialize operation to AOF format
     pub fn to_aof_entry(&self) -> StorageResult<String> {
         match self {
@@ -32,12 +113,106 @@ impl Operation {
                 // Format: Set key value_base64
                 let value_b64 =
                     base64::Engine::encode(&base64::engine::general_purpose::STANDARD, value);
-                Ok(format!("SET {} {}\n", key, value_b64))
+                Ok(format!("SET {} {}\n", encode_key(key), value_b64))
+            }
+
+            Operation::PutExpiring {
+                key,
+                value,
+                at_millis,
+            } => {
+                // Format: SETPXAT key unix_millis value_base64
+                let value_b64 =
+                    base64::Engine::encode(&base64::engine::general_purpose::STANDARD, value);
+                Ok(format!(
+                    "SETPXAT {} {} {}\n",
+                    encode_key(key),
+                    at_millis,
+                    value_b64
+                ))
+            }
+
+            Operation::Update { key, value } => {
+                // Format: SETKEEPTTL key value_base64
+                let value_b64 =
+                    base64::Engine::encode(&base64::engine::general_purpose::STANDARD, value);
+                Ok(format!("SETKEEPTTL {} {}\n", encode_key(key), value_b64))
             }
 
             Operation::Delete { key } => {
                 // Format: Del key
-                Ok(format!("DEL {}\n", key))
+                Ok(format!("DEL {}\n", encode_key(key)))
+            }
+
+            Operation::SetRange { key, offset, value } => {
+                // Format: SETRANGE key offset value_base64
+                let value_b64 =
+                    base64::Engine::encode(&base64::engine::general_purpose::STANDARD, value);
+                Ok(format!(
+                    "SETRANGE {} {} {}\n",
+                    encode_key(key),
+                    offset,
+                    value_b64
+                ))
+            }
+
+            Operation::SetBit { key, offset, bit } => {
+                // Format: SETBIT key offset 0|1
+                Ok(format!(
+                    "SETBIT {} {} {}\n",
+                    encode_key(key),
+                    offset,
+                    u8::from(*bit)
+                ))
+            }
+
+            Operation::SetAdd { key, members } => {
+                // Format: SADD key member_base64 [member_base64 ...]
+                Ok(format!(
+                    "SADD {} {}\n",
+                    encode_key(key),
+                    encode_members(members)
+                ))
+            }
+
+            Operation::SetRemove { key, members } => {
+                // Format: SREM key member_base64 [member_base64 ...]
+                Ok(format!(
+                    "SREM {} {}\n",
+                    encode_key(key),
+                    encode_members(members)
+                ))
+            }
+
+            Operation::ListPush { key, items, front } => {
+                // Format: LPUSH|RPUSH key item_base64 [item_base64 ...]
+                let name = if *front { "LPUSH" } else { "RPUSH" };
+                Ok(format!(
+                    "{} {} {}\n",
+                    name,
+                    encode_key(key),
+                    encode_members(items)
+                ))
+            }
+
+            // Format: LPOP key
+            Operation::ListPop { key } => Ok(format!("LPOP {}\n", encode_key(key))),
+
+            // Format: PEXPIREAT key unix_millis
+            Operation::ExpireAt { key, at_millis } => {
+                Ok(format!("PEXPIREAT {} {}\n", encode_key(key), at_millis))
+            }
+
+            Operation::Flush => Ok("FLUSHDB\n".to_string()),
+
+            Operation::Select { db, operation } => {
+                // Format: SELECT db <entry>
+                if matches!(**operation, Operation::Select { .. }) {
+                    return Err(StorageError::Persistence(
+                        "Nested SELECT in AOF entry".to_string(),
+                    ));
+                }
+                Ok(format!("SELECT {} {}", db, operation.to_aof_entry()?))
             }
         }
     }
@@ -46,9 +221,9 @@ impl Operation {
     pub fn from_aof_entry(line: &str) -> StorageResult<Self> {
         let parts: Vec<&str> = line.trim().split(' ').collect();
 
-        match parts.get(0) {
+        match parts.first() {
             Some(&"SET") if parts.len() >= 3 => {
-                let key = parts[1].to_string();
+                let key = decode_key(parts[1])?;
                 let value_b64 = parts[2..].join(" "); // handle spaces in value
                 let value =
                     base64::Engine::decode(&base64::engine::general_purpose::STANDARD, &value_b64)
@@ -57,10 +232,101 @@ impl Operation {
                         })?;
                 Ok(Operation::Put { key, value })
             }
+            Some(&"SETPXAT") if parts.len() == 4 => {
+                let at_millis = parts[2].parse::<u64>().map_err(|e| {
+                    StorageError::Persistence(format!("Invalid SETPXAT time: {}", e))
+                })?;
+                let value =
+                    base64::Engine::decode(&base64::engine::general_purpose::STANDARD, parts[3])
+                        .map_err(|e| {
+                            StorageError::Persistence(format!("Base64 decode error: {}", e))
+                        })?;
+                Ok(Operation::PutExpiring {
+                    key: decode_key(parts[1])?,
+                    value,
+                    at_millis,
+                })
+            }
+            Some(&"SETKEEPTTL") if parts.len() == 3 => {
+                let value =
+                    base64::Engine::decode(&base64::engine::general_purpose::STANDARD, parts[2])
+                        .map_err(|e| {
+                            StorageError::Persistence(format!("Base64 decode error: {}", e))
+                        })?;
+                Ok(Operation::Update {
+                    key: decode_key(parts[1])?,
+                    value,
+                })
+            }
             Some(&"DEL") if parts.len() == 2 => {
-                let key = parts[1].to_string();
+                let key = decode_key(parts[1])?;
                 Ok(Operation::Delete { key })
             }
+            Some(&"SETRANGE") if parts.len() == 4 => {
+                let key = decode_key(parts[1])?;
+                let offset = parts[2].parse::<usize>().map_err(|e| {
+                    StorageError::Persistence(format!("Invalid SETRANGE offset: {}", e))
+                })?;
+                let value =
+                    base64::Engine::decode(&base64::engine::general_purpose::STANDARD, parts[3])
+                        .map_err(|e| {
+                            StorageError::Persistence(format!("Base64 decode error: {}", e))
+                        })?;
+                Ok(Operation::SetRange { key, offset, value })
+            }
+            Some(&"SETBIT") if parts.len() == 4 => {
+                let offset = parts[2].parse::<usize>().map_err(|e| {
+                    StorageError::Persistence(format!("Invalid SETBIT offset: {}", e))
+                })?;
+                let bit = match parts[3] {
+                    "0" => false,
+                    "1" => true,
+                    other => {
+                        return Err(StorageError::Persistence(format!(
+                            "Invalid SETBIT value: {}",
+                            other
+                        )));
+                    }
+                };
+                Ok(Operation::SetBit {
+                    key: decode_key(parts[1])?,
+                    offset,
+                    bit,
+                })
+            }
+            Some(&"SADD") if parts.len() >= 3 => Ok(Operation::SetAdd {
+                key: decode_key(parts[1])?,
+                members: decode_members(&parts[2..])?,
+            }),
+            Some(&"SREM") if parts.len() >= 3 => Ok(Operation::SetRemove {
+                key: decode_key(parts[1])?,
+                members: decode_members(&parts[2..])?,
+            }),
+            Some(&name @ ("LPUSH" | "RPUSH")) if parts.len() >= 3 => Ok(Operation::ListPush {
+                key: decode_key(parts[1])?,
+                items: decode_members(&parts[2..])?,
+                front: name == "LPUSH",
+            }),
+            Some(&"LPOP") if parts.len() == 2 => Ok(Operation::ListPop {
+                key: decode_key(parts[1])?,
+            }),
+            Some(&"PEXPIREAT") if parts.len() == 3 => {
+                let at_millis = parts[2].parse::<u64>().map_err(|e| {
+                    StorageError::Persistence(format!("Invalid PEXPIREAT time: {}", e))
+                })?;
+                Ok(Operation::ExpireAt {
+                    key: decode_key(parts[1])?,
+                    at_millis,
+                })
+            }
+            Some(&"FLUSHDB") if parts.len() == 1 => Ok(Operation::Flush),
+            Some(&"SELECT") if parts.len() >= 3 && parts[2] != "SELECT" => {
+                let db = parts[1].parse::<usize>().map_err(|e| {
+                    StorageError::Persistence(format!("Invalid SELECT database: {}", e))
+                })?;
+                let operation = Self::from_aof_entry(&parts[2..].join(" "))?;
+                Ok(operation.in_database(db))
+            }
             _ => Err(StorageError::Persistence(format!(
                 "Invalid AOF entry: {}",
                 line
@@ -69,6 +335,226 @@ impl Operation {
     }
 }
 
+// Prefix of a base64-encoded key. Keys that are empty, contain whitespace or control
+// characters, or already start with the prefix are written encoded, so a key can never
+// split an entry into extra fields or lines; every other key stays readable as is.
+const ENCODED_KEY_PREFIX: &str = "b64:";
+
+fn encode_key(key: &str) -> Cow<'_, str> {
+    if key.is_empty()
+        || key.starts_with(ENCODED_KEY_PREFIX)
+        || key.chars().any(|c| c.is_whitespace() || c.is_control())
+    {
+        Cow::Owned(format!(
+            "{}{}",
+            ENCODED_KEY_PREFIX,
+            base64::Engine::encode(&base64::engine::general_purpose::STANDARD, key)
+        ))
+    } else {
+        Cow::Borrowed(key)
+    }
+}
+
+fn decode_key(field: &str) -> StorageResult<String> {
+    let Some(encoded) = field.strip_prefix(ENCODED_KEY_PREFIX) else {
+        return Ok(field.to_string());
+    };
+    let bytes = base64::Engine::decode(&base64::engine::general_purpose::STANDARD, encoded)
+        .map_err(|e| StorageError::Persistence(format!("Base64 decode error: {}", e)))?;
+    String::from_utf8(bytes)
+        .map_err(|e| StorageError::Persistence(format!("Invalid key encoding: {}", e)))
+}
+
+// Space-separated base64 set members
+fn encode_members(members: &[Vec<u8>]) -> String {
+    members
+        .iter()
+        .map(|member| base64::Engine::encode(&base64::engine::general_purpose::STANDARD, member))
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+fn decode_members(parts: &[&str]) -> StorageResult<Vec<Vec<u8>>> {
+    parts
+        .iter()
+        .map(|part| {
+            base64::Engine::decode(&base64::engine::general_purpose::STANDARD, part)
+                .map_err(|e| StorageError::Persistence(format!("Base64 decode error: {}", e)))
+        })
+        .collect()
+}
+
+// Messages consumed by the background writer
+enum AofMessage {
+    Write(Operation),
+    Sync(tokio::sync::oneshot::Sender<Result<(), String>>), // flush + fsync, then ack
+    Flush(tokio::sync::oneshot::Sender<Result<(), String>>), // flush to the OS only, then ack
+
+    // Compaction: buffer writes from here on (still appending them to the live file)
+    BeginRewrite(tokio::sync::oneshot::Sender<()>),
+
+    // Compaction: append the buffered writes to the rewritten file and switch to it
+    FinishRewrite {
+        temp_path: PathBuf,
+        ack: tokio::sync::oneshot::Sender<Result<(), String>>,
+    },
+
+    // Compaction failed before FinishRewrite: drop the buffer, keep the live file
+    AbortRewrite,
+
+    // Move to a new file, carrying the live file and its segments over when `copy` is set
+    Relocate {
+        new_path: PathBuf,
+        copy: bool,
+        ack: tokio::sync::oneshot::Sender<Result<(), String>>,
+    },
+}
+
+// Max queued write-failure notifications
+const ERROR_CHANNEL_CAPACITY: usize = 64;
+
+// Rotations not yet picked up coalesce, one pending notice is enough to trigger a compaction
+const ROTATION_CHANNEL_CAPACITY: usize = 1;
+
+// Longest a dropped AOF blocks waiting for the background writer to flush
+const DROP_FLUSH_TIMEOUT: Duration = Duration::from_secs(2);
+
+// Most writes one group commit gathers before it fsyncs, even with the window still open
+const GROUP_COMMIT_MAX_OPS: usize = 1024;
+
+// How often the writer looks for its file on disk, a removed file (or directory) would
+// otherwise swallow every write into an unlinked inode
+const FILE_CHECK_INTERVAL: Duration = Duration::from_secs(1);
+
+// Writes and sync requests waiting for the background writer's next flush + fsync
+#[derive(Default)]
+struct PendingCommit {
+    acks: Vec<tokio::sync::oneshot::Sender<Result<(), String>>>,
+    last_write: Option<Operation>, // Latest write the fsync policy wants on disk
+    opened_at: Option<tokio::time::Instant>,
+    ops: usize,             // Writes since the commit was opened
+    fsyncs: Arc<AtomicU64>, // Shared with AofStats
+}
+
+impl PendingCommit {
+    fn is_empty(&self) -> bool {
+        self.acks.is_empty() && self.last_write.is_none()
+    }
+
+    fn add_ack(&mut self, ack: tokio::sync::oneshot::Sender<Result<(), String>>) {
+        self.acks.push(ack);
+        self.opened_at.get_or_insert_with(tokio::time::Instant::now);
+    }
+
+    fn add_write(&mut self, operation: Operation, needs_fsync: bool) {
+        if needs_fsync {
+            self.last_write = Some(operation);
+        }
+        if !self.is_empty() {
+            self.opened_at.get_or_insert_with(tokio::time::Instant::now);
+            self.ops += 1;
+        }
+    }
+
+    // Next message arriving before the window (counted from the first waiter) closes,
+    // None once it has closed or the commit is full
+    async fn extend(&self, rx: &Receiver<AofMessage>, window: Duration) -> Option<AofMessage> {
+        let opened_at = self.opened_at?;
+        if window.is_zero() || self.ops >= GROUP_COMMIT_MAX_OPS {
+            return None;
+        }
+
+        tokio::time::timeout_at(opened_at + window, rx.recv_async())
+            .await
+            .ok()?
+            .ok()
+    }
+
+    // Flush and fsync once for everything gathered, then ack every waiter with the outcome
+    async fn commit(
+        &mut self,
+        writer: Option<&mut BufWriter<File>>,
+        report: &impl Fn(&Operation, &str, &std::io::Error),
+        failed: &AtomicBool,
+    ) {
+        if self.is_empty() {
+            return;
+        }
+
+        let result = match writer {
+            Some(w) => {
+                self.fsyncs.fetch_add(1, Ordering::Relaxed);
+                match w.flush().await {
+                    Ok(_) => w.get_mut().sync_all().await.map_err(|e| ("fsync", e)),
+                    Err(e) => Err(("flush", e)),
+                }
+            }
+            None => Ok(()),
+        };
+
+        if let Err((stage, ref e)) = result {
+            match self.last_write {
+                Some(ref operation) => report(operation, stage, e),
+                None => {
+                    error!("AOF sync failed, persistence is now degraded: {}", e);
+                    failed.store(true, Ordering::Release);
+                }
+            }
+        }
+
+        let result = result.map_err(|(_, e)| e.to_string());
+        for ack in self.acks.drain(..) {
+            let _ = ack.send(result.clone());
+        }
+        if self.ops > 1 {
+            debug!("AOF group commit fsynced {} operations", self.ops);
+        }
+
+        self.last_write = None;
+        self.opened_at = None;
+        self.ops = 0;
+    }
+}
+
+// Set while the background writer task is alive, cleared even if the runtime drops the task
+struct WriterRunning(Arc<AtomicBool>);
+
+impl Drop for WriterRunning {
+    fn drop(&mut self) {
+        self.0.store(false, Ordering::Release);
+    }
+}
+
+// Background write failure, emitted on the AOF error channel
+#[derive(Debug, Clone)]
+pub struct AofWriteError {
+    pub operation: Operation,
+    pub error: String,
+    pub timestamp: DateTime<Utc>,
+}
+
+// Queues operations for an AOF's background writer without awaiting, see AppendOnlyFile::queue
+#[derive(Clone)]
+pub struct OperationQueue {
+    operation_tx: Sender<AofMessage>,
+    failed: Arc<AtomicBool>,
+}
+
+impl OperationQueue {
+    // Same as AppendOnlyFile::log_operation; never blocks, the channel is unbounded
+    pub fn push(&self, operation: Operation) -> StorageResult<()> {
+        if self.failed.load(Ordering::Acquire) {
+            return Err(StorageError::Persistence(
+                "AOF writer failed, refusing writes".to_string(),
+            ));
+        }
+
+        self.operation_tx
+            .send(AofMessage::Write(operation))
+            .map_err(|e| StorageError::Persistence(format!("Failed to queue operation: {}", e)))
+    }
+}
+
 // Append-Only File for persistence
 // Logs all write operations for crash recovery
 pub struct AppendOnlyFile {
@@ -76,15 +562,41 @@ pub struct AppendOnlyFile {
     file_path: PathBuf,
 
     // background writer
-    operation_tx: Sender<Operation>,
-    operation_rx: Receiver<Operation>,
+    operation_tx: Sender<AofMessage>,
+    operation_rx: Receiver<AofMessage>,
 
     // stats
     operation_logged: Arc<AtomicU64>,
+    fsyncs: Arc<AtomicU64>,
     file_size: Arc<AtomicU64>,
 
+    // Rotated segments (`<file>.1` oldest .. `<file>.N` newest) ahead of the live file
+    current_segment: Arc<AtomicU64>,
+    segments_size: Arc<AtomicU64>,
+    rotation_tx: Sender<u64>,
+    rotation_rx: Receiver<u64>,
+
+    // Total size right after the last rewrite (or at startup), growth is measured from it
+    rewrite_base: Arc<AtomicU64>,
+    growth_tx: Sender<u64>,
+    growth_rx: Receiver<u64>,
+
+    // failure state
+    failed: Arc<AtomicBool>,
+    error_tx: Sender<AofWriteError>,
+    error_rx: Receiver<AofWriteError>,
+
     // config
     pub fsync_every: u64, // fsync after N operations (0 = every operation)
+    pub max_size: u64,    // rotate the live file once it reaches N bytes (0 = never)
+    pub group_commit_window: Duration, // hold an fsync this long for more writes to share it
+    pub rewrite_percentage: u64, // ask for a rewrite once grown this % past the base (0 = never)
+    pub rewrite_min_size: u64, // ... and at least this many bytes in total
+
+    // Policy the background writer reads per write, seeded from fsync_every on start
+    live_fsync_every: Arc<AtomicU64>,
+
+    writer_running: Arc<AtomicBool>,
 }
 
 impl AppendOnlyFile {
@@ -105,6 +617,16 @@ impl AppendOnlyFile {
         // Create channel for background writing
         let (op_tx, op_rx) = flume::unbounded();
 
+        // Bounded so an absent consumer can't grow memory
+        let (error_tx, error_rx) = flume::bounded(ERROR_CHANNEL_CAPACITY);
+        let (rotation_tx, rotation_rx) = flume::bounded(ROTATION_CHANNEL_CAPACITY);
+        let (growth_tx, growth_rx) = flume::bounded(ROTATION_CHANNEL_CAPACITY);
+
+        // Pick up segments rotated out before a restart
+        let segments = Self::rotated_segments(&file_path).await?;
+        let current_segment = segments.last().map(|(index, _)| *index).unwrap_or(0);
+        let segments_size = Self::segments_size(&segments).await?;
+
         info!("AOF initialized at {}", file_path.display());
 
         let mut aof = Self {
@@ -113,8 +635,25 @@ impl AppendOnlyFile {
             operation_rx: op_rx,
             operation_tx: op_tx,
             operation_logged: Arc::new(AtomicU64::new(0)),
+            fsyncs: Arc::new(AtomicU64::new(0)),
             file_size: Arc::new(AtomicU64::new(0)),
+            current_segment: Arc::new(AtomicU64::new(current_segment)),
+            segments_size: Arc::new(AtomicU64::new(segments_size)),
+            rotation_tx,
+            rotation_rx,
+            rewrite_base: Arc::new(AtomicU64::new(0)),
+            growth_tx,
+            growth_rx,
+            failed: Arc::new(AtomicBool::new(false)),
+            error_tx,
+            error_rx,
             fsync_every: 1, // sync after every 1 operations by default
+            max_size: 0,
+            group_commit_window: Duration::ZERO,
+            rewrite_percentage: 0,
+            rewrite_min_size: 0,
+            live_fsync_every: Arc::new(AtomicU64::new(1)),
+            writer_running: Arc::new(AtomicBool::new(false)),
         };
 
         aof.open_writer().await?;
@@ -143,14 +682,119 @@ impl AppendOnlyFile {
     pub async fn log_operation(&self, operation: Operation) -> StorageResult<()> {
         debug!("Queuing operation for AOF logging");
 
+        // Refuse to ack writes once the background writer can't persist them
+        if self.is_failed() {
+            return Err(StorageError::Persistence(
+                "AOF writer failed, refusing writes".to_string(),
+            ));
+        }
+
         self.operation_tx
-            .send_async(operation)
+            .send_async(AofMessage::Write(operation))
             .await
             .map_err(|e| StorageError::Persistence(format!("Failed to queue operation: {}", e)))?;
 
         Ok(())
     }
 
+    // Handle for queueing operations from synchronous code, e.g. under an engine lock
+    pub fn queue(&self) -> OperationQueue {
+        OperationQueue {
+            operation_tx: self.operation_tx.clone(),
+            failed: self.failed.clone(),
+        }
+    }
+
+    // Wait until every operation queued before this call is flushed and fsynced
+    // The sync request travels the same FIFO channel, so its ack implies all prior writes are durable
+    pub async fn sync(&self) -> StorageResult<()> {
+        if self.is_failed() {
+            return Err(StorageError::Persistence(
+                "AOF writer failed, cannot sync".to_string(),
+            ));
+        }
+
+        let (ack_tx, ack_rx) = tokio::sync::oneshot::channel();
+
+        self.operation_tx
+            .send_async(AofMessage::Sync(ack_tx))
+            .await
+            .map_err(|e| StorageError::Persistence(format!("Failed to queue sync: {}", e)))?;
+
+        ack_rx
+            .await
+            .map_err(|_| StorageError::Persistence("AOF writer stopped".to_string()))?
+            .map_err(|e| StorageError::Persistence(format!("AOF sync failed: {}", e)))
+    }
+
+    // Like sync(), but only hands the buffered writes to the OS: they survive the process
+    // exiting, not a power loss. Writes the fsync policy asked to make durable still are
+    pub async fn flush(&self) -> StorageResult<()> {
+        if self.is_failed() {
+            return Err(StorageError::Persistence(
+                "AOF writer failed, cannot flush".to_string(),
+            ));
+        }
+
+        let (ack_tx, ack_rx) = tokio::sync::oneshot::channel();
+
+        self.operation_tx
+            .send_async(AofMessage::Flush(ack_tx))
+            .await
+            .map_err(|e| StorageError::Persistence(format!("Failed to queue flush: {}", e)))?;
+
+        ack_rx
+            .await
+            .map_err(|_| StorageError::Persistence("AOF writer stopped".to_string()))?
+            .map_err(|e| StorageError::Persistence(format!("AOF flush failed: {}", e)))
+    }
+
+    // sync() for synchronous code (Drop, panic hooks), waiting at most `timeout`
+    // Gives up at once when the writer can't run meanwhile: not started, already gone,
+    // or sharing this thread on a current-thread runtime
+    pub fn flush_blocking(&self, timeout: Duration) -> StorageResult<()> {
+        if !self.writer_running.load(Ordering::Acquire) {
+            return Err(StorageError::Persistence(
+                "AOF writer is not running".to_string(),
+            ));
+        }
+
+        if let Ok(handle) = tokio::runtime::Handle::try_current()
+            && handle.runtime_flavor() == tokio::runtime::RuntimeFlavor::CurrentThread
+        {
+            return Err(StorageError::Persistence(
+                "Cannot wait for the AOF writer on a current-thread runtime".to_string(),
+            ));
+        }
+
+        let (ack_tx, mut ack_rx) = tokio::sync::oneshot::channel();
+        self.operation_tx
+            .send(AofMessage::Sync(ack_tx))
+            .map_err(|e| StorageError::Persistence(format!("Failed to queue sync: {}", e)))?;
+
+        let deadline = Instant::now() + timeout;
+        loop {
+            match ack_rx.try_recv() {
+                Ok(result) => {
+                    return result
+                        .map_err(|e| StorageError::Persistence(format!("AOF sync failed: {}", e)));
+                }
+                Err(tokio::sync::oneshot::error::TryRecvError::Closed) => {
+                    return Err(StorageError::Persistence("AOF writer stopped".to_string()));
+                }
+                Err(tokio::sync::oneshot::error::TryRecvError::Empty) => {
+                    if Instant::now() >= deadline {
+                        return Err(StorageError::Persistence(format!(
+                            "AOF flush timed out after {:?}",
+                            timeout
+                        )));
+                    }
+                    std::thread::sleep(Duration::from_millis(1));
+                }
+            }
+        }
+    }
+
     // Log operation sychronously (blocking)
     pub async fn log_operation_sync(&mut self, operation: Operation) -> StorageResult<()> {
         self.write_operation(&operation).await
@@ -170,9 +814,10 @@ impl AppendOnlyFile {
 
         // Fsync based on policy
         let ops_count = self.operation_logged.fetch_add(1, Ordering::Relaxed) + 1;
-        if self.fsync_every == 0 || ops_count % self.fsync_every == 0 {
+        if self.fsync_every == 0 || ops_count.is_multiple_of(self.fsync_every) {
             writer.flush().await?;
             writer.get_mut().sync_all().await?;
+            self.fsyncs.fetch_add(1, Ordering::Relaxed);
             debug!("AOF fsynced after {} operations", ops_count);
         }
 
@@ -182,58 +827,593 @@ impl AppendOnlyFile {
         Ok(())
     }
 
+    // Change the fsync policy of a running background writer
+    pub fn set_fsync_every(&self, fsync_every: u64) {
+        self.live_fsync_every.store(fsync_every, Ordering::Relaxed);
+    }
+
     // Start background writer task
     pub async fn start_background_writer(&mut self) {
         let mut writer = self.writer.take();
+        let mut file_path = self.file_path.clone();
         let rx = self.operation_rx.clone();
         let file_size = self.file_size.clone();
         let operation_logged = self.operation_logged.clone();
-        let fsync_every = self.fsync_every;
+        let current_segment = self.current_segment.clone();
+        let segments_size = self.segments_size.clone();
+        let rotation_tx = self.rotation_tx.clone();
+        let max_size = self.max_size;
+        let rewrite_base = self.rewrite_base.clone();
+        let growth_tx = self.growth_tx.clone();
+        let (rewrite_percentage, rewrite_min_size) =
+            (self.rewrite_percentage, self.rewrite_min_size);
+        let group_commit_window = self.group_commit_window;
+        let fsyncs = self.fsyncs.clone();
+        self.live_fsync_every
+            .store(self.fsync_every, Ordering::Relaxed);
+        let fsync_every = self.live_fsync_every.clone();
+        let failed = self.failed.clone();
+        let error_tx = self.error_tx.clone();
+        self.writer_running.store(true, Ordering::Release);
+        let running = WriterRunning(self.writer_running.clone());
 
         tokio::spawn(async move {
+            let _running = running;
             info!("AOF background writer started");
 
-            while let Ok(operation) = rx.recv_async().await {
-                if let Some(ref mut w) = writer {
-                    if let Ok(entry) = operation.to_aof_entry() {
-                        if let Err(e) = w.write_all(entry.as_bytes()).await {
-                            warn!("Failed to write AOF entry: {}", e);
+            // Writes logged while a compaction is in progress, replayed into the new file
+            let mut rewrite_buffer: Option<Vec<Operation>> = None;
+
+            // Live file size that triggers a rotation. A compacted file starts out holding
+            // the whole dataset, so it may grow by max_size past that before rotating;
+            // otherwise a dataset larger than max_size would rotate and compact on every write
+            let mut rotate_at = max_size;
+
+            // Total size that asks for a growth-triggered rewrite, measured from a base that
+            // is reset by every rewrite; u64::MAX while one has been asked for
+            let growth_at = |base: u64| {
+                if rewrite_percentage == 0 {
+                    return u64::MAX;
+                }
+                let grown = base.saturating_add(base.saturating_mul(rewrite_percentage) / 100);
+                grown.max(rewrite_min_size)
+            };
+            let total_size =
+                || segments_size.load(Ordering::Relaxed) + file_size.load(Ordering::Relaxed);
+            rewrite_base.store(total_size(), Ordering::Relaxed);
+            let mut rewrite_at = growth_at(total_size());
+
+            // Flip the failure flag and notify subscribers; the operation is lost
+            let report = |operation: &Operation, stage: &str, e: &std::io::Error| {
+                error!("AOF {} failed, persistence is now degraded: {}", stage, e);
+                failed.store(true, Ordering::Release);
+
+                // Never block the writer on a slow alert consumer
+                let _ = error_tx.try_send(AofWriteError {
+                    operation: operation.clone(),
+                    error: format!("{}: {}", stage, e),
+                    timestamp: Utc::now(),
+                });
+            };
+
+            // Writes and sync requests waiting for the next flush + fsync
+            let mut pending = PendingCommit {
+                fsyncs,
+                ..Default::default()
+            };
+            // Message that arrived while a group commit window was open
+            let mut next = None;
+            let mut last_file_check = Instant::now();
+
+            loop {
+                let message = match next.take() {
+                    Some(message) => message,
+                    None => match rx.recv_async().await {
+                        Ok(message) => message,
+                        Err(_) => break,
+                    },
+                };
+
+                let operation = match message {
+                    AofMessage::Write(operation) => Some(operation),
+                    AofMessage::Sync(ack) => {
+                        pending.add_ack(ack);
+                        None
+                    }
+                    AofMessage::Flush(ack) => {
+                        pending.commit(writer.as_mut(), &report, &failed).await;
+                        let result = match writer {
+                            Some(ref mut w) => w.flush().await.map_err(|e| e.to_string()),
+                            None => Ok(()),
+                        };
+                        let _ = ack.send(result);
+                        continue;
+                    }
+                    // Compaction steps never overtake a pending commit
+                    AofMessage::BeginRewrite(ack) => {
+                        pending.commit(writer.as_mut(), &report, &failed).await;
+                        rewrite_buffer = Some(Vec::new());
+                        let _ = ack.send(());
+                        continue;
+                    }
+                    AofMessage::FinishRewrite { temp_path, ack } => {
+                        pending.commit(writer.as_mut(), &report, &failed).await;
+                        let delta = rewrite_buffer.take().unwrap_or_default();
+                        let result = Self::swap_in_rewrite(&temp_path, &file_path, &delta).await;
+
+                        match result {
+                            Ok((new_writer, size)) => {
+                                // Flush the old handle's tail before dropping it; its content
+                                // is already covered by the rewritten file
+                                if let Some(ref mut old) = writer {
+                                    let _ = old.flush().await;
+                                }
+                                writer = Some(new_writer);
+                                file_size.store(size, Ordering::Relaxed);
+                                rotate_at = size.saturating_add(max_size);
+                                debug!("AOF switched to rewritten file, {} delta ops", delta.len());
+
+                                // The rewritten file covers every rotated segment
+                                let (last, size) = Self::remove_segments(&file_path).await;
+                                current_segment.store(last, Ordering::Relaxed);
+                                segments_size.store(size, Ordering::Relaxed);
+
+                                rewrite_base.store(total_size(), Ordering::Relaxed);
+                                rewrite_at = growth_at(total_size());
+
+                                let _ = ack.send(Ok(()));
+                            }
+                            Err(e) => {
+                                // Live file still has every operation, nothing is lost
+                                warn!("AOF rewrite could not be installed: {}", e);
+                                let _ = tokio::fs::remove_file(&temp_path).await;
+                                let _ = ack.send(Err(e.to_string()));
+
+                                // Wait for the file to grow by the percentage again
+                                rewrite_at = growth_at(total_size());
+                            }
+                        }
+                        continue;
+                    }
+                    AofMessage::AbortRewrite => {
+                        rewrite_buffer = None;
+                        rewrite_at = growth_at(total_size());
+                        continue;
+                    }
+                    AofMessage::Relocate {
+                        new_path,
+                        copy,
+                        ack,
+                    } => {
+                        // Everything logged so far is on disk before anything is copied
+                        pending.commit(writer.as_mut(), &report, &failed).await;
+                        if rewrite_buffer.is_some() {
+                            let _ = ack.send(Err("AOF rewrite in progress".to_string()));
                             continue;
                         }
 
-                        // Fsync policy
-                        let ops_count = operation_logged.fetch_add(1, Ordering::Relaxed) + 1;
-                        if fsync_every == 0 || ops_count % fsync_every == 0 {
-                            if let Err(e) = w.flush().await {
-                                warn!("Failed to flush AOF writer: {}", e);
+                        let segments = if copy {
+                            Self::rotated_segments(&file_path).await.unwrap_or_default()
+                        } else {
+                            Vec::new()
+                        };
+                        match Self::open_relocated(&file_path, &new_path, &segments, copy).await {
+                            Ok((new_writer, size)) => {
+                                if let Some(ref mut old) = writer {
+                                    let _ = old.flush().await;
+                                }
+                                writer = Some(new_writer);
+                                file_size.store(size, Ordering::Relaxed);
+                                rotate_at = size.saturating_add(max_size);
+
+                                let last = segments.last().map(|(index, _)| *index).unwrap_or(0);
+                                current_segment.store(last, Ordering::Relaxed);
+                                let copied = Self::segments_size(&segments).await.unwrap_or(0);
+                                segments_size.store(copied, Ordering::Relaxed);
+                                rewrite_base.store(total_size(), Ordering::Relaxed);
+                                rewrite_at = growth_at(total_size());
+
+                                info!(
+                                    "AOF moved from {} to {}",
+                                    file_path.display(),
+                                    new_path.display()
+                                );
+                                file_path = new_path;
+                                let _ = ack.send(Ok(()));
+                            }
+                            Err(e) => {
+                                // Nothing switched, writes keep going to the current file
+                                warn!("AOF could not be moved to {}: {}", new_path.display(), e);
+                                let _ = ack.send(Err(e.to_string()));
+                            }
+                        }
+                        continue;
+                    }
+                };
+
+                if let Some(operation) = operation {
+                    if let Some(ref mut buffer) = rewrite_buffer {
+                        buffer.push(operation.clone());
+                    }
+
+                    if let Some(ref mut w) = writer
+                        && let Ok(entry) = operation.to_aof_entry()
+                    {
+                        let mut removed = false;
+                        if last_file_check.elapsed() >= FILE_CHECK_INTERVAL {
+                            last_file_check = Instant::now();
+                            // A file where its directory was counts as removed too
+                            removed = match tokio::fs::try_exists(&file_path).await {
+                                Ok(exists) => !exists,
+                                Err(e) => e.kind() == std::io::ErrorKind::NotADirectory,
+                            };
+                        }
+
+                        let mut stage = "write";
+                        let mut result = if removed {
+                            Err(std::io::ErrorKind::NotFound.into())
+                        } else {
+                            w.write_all(entry.as_bytes()).await
+                        };
+
+                        // The file or its directory was removed: start a new one and ask for
+                        // a rewrite, since everything before it is only in the unlinked file
+                        if let Err(ref e) = result
+                            && e.kind() == std::io::ErrorKind::NotFound
+                        {
+                            match Self::recreate(&file_path).await {
+                                Ok(new_writer) => {
+                                    warn!(
+                                        "AOF file {} was removed, recreated it and asking for a rewrite",
+                                        file_path.display()
+                                    );
+                                    *w = new_writer;
+                                    file_size.store(0, Ordering::Relaxed);
+                                    rotate_at = max_size;
+                                    let _ = growth_tx.try_send(total_size());
+                                    rewrite_at = u64::MAX;
+                                    result = w.write_all(entry.as_bytes()).await;
+                                }
+                                Err(e) => {
+                                    stage = "reopen";
+                                    result = Err(e);
+                                }
                             }
-                            if let Err(e) = w.get_mut().sync_all().await {
-                                warn!("Failed to fsync AOF file: {}", e);
+                        }
+
+                        if let Err(e) = result {
+                            report(&operation, stage, &e);
+                            continue;
+                        }
+
+                        let size = file_size.fetch_add(entry.len() as u64, Ordering::Relaxed)
+                            + entry.len() as u64;
+
+                        if max_size > 0 && size >= rotate_at {
+                            let index = current_segment.load(Ordering::Relaxed) + 1;
+                            match Self::rotate(&file_path, w, index).await {
+                                Ok(new_writer) => {
+                                    *w = new_writer;
+                                    current_segment.store(index, Ordering::Relaxed);
+                                    segments_size.fetch_add(size, Ordering::Relaxed);
+                                    file_size.store(0, Ordering::Relaxed);
+                                    rotate_at = max_size;
+                                    info!("AOF rotated to segment {} at {} bytes", index, size);
+                                    let _ = rotation_tx.try_send(index);
+                                }
+                                Err(e) => {
+                                    // Nothing is lost, writes keep going to the oversized file
+                                    // and the next attempt waits for another max_size bytes
+                                    warn!("AOF rotation failed: {}", e);
+                                    rotate_at = size.saturating_add(max_size);
+                                }
                             }
                         }
 
-                        file_size.fetch_add(entry.len() as u64, Ordering::Relaxed);
+                        let total = total_size();
+                        if total >= rewrite_at {
+                            info!(
+                                "AOF grew to {} bytes from {}, asking for a rewrite",
+                                total,
+                                rewrite_base.load(Ordering::Relaxed)
+                            );
+                            let _ = growth_tx.try_send(total);
+                            rewrite_at = u64::MAX;
+                        }
+
+                        // Fsync policy
+                        let ops_count = operation_logged.fetch_add(1, Ordering::Relaxed) + 1;
+                        let fsync_every = fsync_every.load(Ordering::Relaxed);
+                        pending.add_write(
+                            operation,
+                            fsync_every == 0 || ops_count.is_multiple_of(fsync_every),
+                        );
                     }
                 }
+
+                if pending.is_empty() {
+                    continue;
+                }
+
+                // Group commit: everything arriving within the window shares one fsync
+                if let Some(message) = pending.extend(&rx, group_commit_window).await {
+                    next = Some(message);
+                    continue;
+                }
+                pending.commit(writer.as_mut(), &report, &failed).await;
+            }
+
+            // Every sender is gone (the AOF was dropped): don't leave buffered writes behind
+            pending.commit(writer.as_mut(), &report, &failed).await;
+            if let Some(ref mut w) = writer {
+                let flushed = match w.flush().await {
+                    Ok(_) => w.get_mut().sync_all().await,
+                    Err(e) => Err(e),
+                };
+                if let Err(e) = flushed {
+                    error!("Final AOF flush failed: {}", e);
+                }
             }
 
             info!("AOF background writer stopped");
         });
     }
 
-    // Read all operations from AOF file
+    // Append the compaction delta to the rewritten file, move it over the live AOF
+    // and open a writer on it
+    async fn swap_in_rewrite(
+        temp_path: &Path,
+        file_path: &Path,
+        delta: &[Operation],
+    ) -> std::io::Result<(BufWriter<File>, u64)> {
+        let file = OpenOptions::new().append(true).open(temp_path).await?;
+        let mut temp_writer = BufWriter::new(file);
+
+        for operation in delta {
+            let entry = operation
+                .to_aof_entry()
+                .map_err(|e| std::io::Error::other(e.to_string()))?;
+            temp_writer.write_all(entry.as_bytes()).await?;
+        }
+
+        temp_writer.flush().await?;
+        temp_writer.get_mut().sync_all().await?;
+        drop(temp_writer);
+
+        tokio::fs::rename(temp_path, file_path).await?;
+
+        let file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(file_path)
+            .await?;
+        let size = file.metadata().await?.len();
+
+        Ok((BufWriter::new(file), size))
+    }
+
+    // Move the live file aside as segment `index` and open a fresh one in its place
+    async fn rotate(
+        file_path: &Path,
+        writer: &mut BufWriter<File>,
+        index: u64,
+    ) -> std::io::Result<BufWriter<File>> {
+        writer.flush().await?;
+        writer.get_mut().sync_all().await?;
+
+        tokio::fs::rename(file_path, Self::segment_path(file_path, index)).await?;
+
+        let file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(file_path)
+            .await?;
+
+        Ok(BufWriter::new(file))
+    }
+
+    // Create the AOF at `new_path`, copying the live file and its `segments` over when
+    // `copy` is set. Copies left behind by a failure are removed, the current file is
+    // never touched
+    async fn open_relocated(
+        file_path: &Path,
+        new_path: &Path,
+        segments: &[(u64, PathBuf)],
+        copy: bool,
+    ) -> std::io::Result<(BufWriter<File>, u64)> {
+        let mut created = Vec::new();
+        let result = Self::copy_relocated(file_path, new_path, segments, copy, &mut created).await;
+        if result.is_err() {
+            for path in created {
+                let _ = tokio::fs::remove_file(path).await;
+            }
+        }
+        result
+    }
+
+    async fn copy_relocated(
+        file_path: &Path,
+        new_path: &Path,
+        segments: &[(u64, PathBuf)],
+        copy: bool,
+        created: &mut Vec<PathBuf>,
+    ) -> std::io::Result<(BufWriter<File>, u64)> {
+        if let Some(dir) = new_path.parent()
+            && !dir.as_os_str().is_empty()
+        {
+            tokio::fs::create_dir_all(dir).await?;
+        }
+
+        // Never append to or overwrite a file already there
+        let mut targets = vec![new_path.to_path_buf()];
+        targets.extend(
+            segments
+                .iter()
+                .map(|(index, _)| Self::segment_path(new_path, *index)),
+        );
+        for target in &targets {
+            if tokio::fs::try_exists(target).await? {
+                return Err(std::io::Error::new(
+                    std::io::ErrorKind::AlreadyExists,
+                    format!("{} already exists", target.display()),
+                ));
+            }
+        }
+
+        for ((_, source), target) in segments.iter().zip(&targets[1..]) {
+            created.push(target.clone());
+            tokio::fs::copy(source, target).await?;
+            File::open(target).await?.sync_all().await?;
+        }
+
+        created.push(new_path.to_path_buf());
+        if copy {
+            tokio::fs::copy(file_path, new_path).await?;
+        }
+
+        let file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(new_path)
+            .await?;
+        file.sync_all().await?;
+        let size = file.metadata().await?.len();
+
+        Ok((BufWriter::new(file), size))
+    }
+
+    // Open a fresh live file in place of a removed one, recreating its directory
+    async fn recreate(file_path: &Path) -> std::io::Result<BufWriter<File>> {
+        if let Some(dir) = file_path.parent()
+            && !dir.as_os_str().is_empty()
+        {
+            tokio::fs::create_dir_all(dir).await?;
+        }
+
+        let file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(file_path)
+            .await?;
+
+        Ok(BufWriter::new(file))
+    }
+
+    fn segment_path(file_path: &Path, index: u64) -> PathBuf {
+        let mut path = file_path.as_os_str().to_owned();
+        path.push(format!(".{}", index));
+        PathBuf::from(path)
+    }
+
+    // Rotated segments of `file_path`, oldest first
+    async fn rotated_segments(file_path: &Path) -> std::io::Result<Vec<(u64, PathBuf)>> {
+        let (Some(dir), Some(name)) = (file_path.parent(), file_path.file_name()) else {
+            return Ok(Vec::new());
+        };
+        let dir = if dir.as_os_str().is_empty() {
+            Path::new(".")
+        } else {
+            dir
+        };
+        let prefix = format!("{}.", name.to_string_lossy());
+
+        let mut segments = Vec::new();
+        let mut entries = match tokio::fs::read_dir(dir).await {
+            Ok(entries) => entries,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(segments),
+            Err(e) => return Err(e),
+        };
+        while let Some(entry) = entries.next_entry().await? {
+            let file_name = entry.file_name();
+            let index = file_name
+                .to_str()
+                .and_then(|name| name.strip_prefix(&prefix))
+                .and_then(|suffix| suffix.parse::<u64>().ok());
+
+            if let Some(index) = index.filter(|index| *index > 0) {
+                segments.push((index, entry.path()));
+            }
+        }
+
+        segments.sort_unstable_by_key(|(index, _)| *index);
+        Ok(segments)
+    }
+
+    async fn segments_size(segments: &[(u64, PathBuf)]) -> std::io::Result<u64> {
+        let mut size = 0;
+        for (_, path) in segments {
+            size += tokio::fs::metadata(path).await?.len();
+        }
+        Ok(size)
+    }
+
+    // Delete rotated segments after a rewrite, returning the last index and total size of
+    // any left behind. Leftovers still replay ahead of the live file, so they can't undo it
+    async fn remove_segments(file_path: &Path) -> (u64, u64) {
+        let segments = match Self::rotated_segments(file_path).await {
+            Ok(segments) => segments,
+            Err(e) => {
+                warn!("Could not list rotated AOF segments: {}", e);
+                return (0, 0);
+            }
+        };
+
+        let mut remaining = Vec::new();
+        for (index, path) in segments {
+            if let Err(e) = tokio::fs::remove_file(&path).await {
+                warn!("Could not remove AOF segment {}: {}", path.display(), e);
+                remaining.push((index, path));
+            }
+        }
+
+        let last = remaining.last().map(|(index, _)| *index).unwrap_or(0);
+        let size = Self::segments_size(&remaining).await.unwrap_or(0);
+        (last, size)
+    }
+
+    // Whether a background write has failed (sticky until restart)
+    pub fn is_failed(&self) -> bool {
+        self.failed.load(Ordering::Acquire)
+    }
+
+    // Subscribe to background write failures (for alerting)
+    pub fn errors(&self) -> Receiver<AofWriteError> {
+        self.error_rx.clone()
+    }
+
+    // Subscribe to rotations, each carrying the index of the segment just rotated out
+    pub fn rotations(&self) -> Receiver<u64> {
+        self.rotation_rx.clone()
+    }
+
+    // Subscribe to rewrite requests, each carrying the total size reached: growth past
+    // rewrite_percentage, or a live file recreated after it was removed
+    pub fn growth(&self) -> Receiver<u64> {
+        self.growth_rx.clone()
+    }
+
+    // Read all operations: rotated segments oldest first, then the live file
     pub async fn read_operations(&self) -> StorageResult<Vec<Operation>> {
-        let file = File::open(&self.file_path).await?;
-        let reader = BufReader::new(file);
-        let mut lines = reader.lines();
+        let mut paths: Vec<PathBuf> = Self::rotated_segments(&self.file_path)
+            .await?
+            .into_iter()
+            .map(|(_, path)| path)
+            .collect();
+        paths.push(self.file_path.clone());
+
         let mut operations = Vec::new();
+        for path in paths {
+            let file = File::open(&path).await?;
+            let reader = BufReader::new(file);
+            let mut lines = reader.lines();
 
-        while let Some(line) = lines.next_line().await? {
-            if !line.trim().is_empty() {
-                match Operation::from_aof_entry(&line) {
-                    Ok(op) => operations.push(op),
-                    Err(e) => {
-                        warn!("Skipping invalid AOF entry: {}: {}", line, e);
+            while let Some(line) = lines.next_line().await? {
+                if !line.trim().is_empty() {
+                    match Operation::from_aof_entry(&line) {
+                        Ok(op) => operations.push(op),
+                        Err(e) => {
+                            warn!("Skipping invalid AOF entry: {}: {}", line, e);
+                        }
                     }
                 }
             }
@@ -243,54 +1423,257 @@ impl AppendOnlyFile {
         Ok(operations)
     }
 
+    // Stream operations starting at a byte offset (0 or an offset previously yielded)
+    // The stream ends at the last complete entry; a line still being written is left for
+    // the next call, resumed from the last yielded offset. Offsets are into the live file only:
+    // a rewrite or rotation replaces it, after which old offsets no longer apply and an open
+    // stream keeps reading the replaced file
+    pub async fn read_operations_from(&self, offset: u64) -> StorageResult<OperationStream> {
+        let mut file = File::open(&self.file_path).await?;
+        let len = file.metadata().await?.len();
+
+        if offset > len {
+            return Err(StorageError::Persistence(format!(
+                "AOF offset {} is past the end of the file ({} bytes), it was likely rewritten or rotated",
+                offset, len
+            )));
+        }
+
+        // Offsets must sit on an entry boundary
+        if offset > 0 {
+            file.seek(SeekFrom::Start(offset - 1)).await?;
+            if file.read_u8().await? != b'\n' {
+                return Err(StorageError::Persistence(format!(
+                    "AOF offset {} is not at the start of an entry",
+                    offset
+                )));
+            }
+        }
+
+        let reader = BufReader::new(file);
+        let operations =
+            stream::try_unfold((reader, offset), |(mut reader, mut offset)| async move {
+                let mut line = Vec::new();
+                loop {
+                    line.clear();
+                    let read = reader.read_until(b'\n', &mut line).await?;
+
+                    // End of file, or an entry the writer hasn't finished yet
+                    if read == 0 || line.last() != Some(&b'\n') {
+                        return Ok(None);
+                    }
+                    offset += read as u64;
+
+                    let entry = String::from_utf8_lossy(&line);
+                    if entry.trim().is_empty() {
+                        continue;
+                    }
+
+                    match Operation::from_aof_entry(entry.trim_end()) {
+                        Ok(operation) => return Ok(Some(((operation, offset), (reader, offset)))),
+                        Err(e) => warn!("Skipping invalid AOF entry: {}: {}", entry.trim_end(), e),
+                    }
+                }
+            });
+
+        Ok(Box::pin(operations))
+    }
+
     // Get AOF statistics
     pub fn stats(&self) -> AofStats {
         AofStats {
+            failed: self.is_failed(),
             operations_logged: self.operation_logged.load(Ordering::Relaxed),
+            fsyncs: self.fsyncs.load(Ordering::Relaxed),
             file_size_bytes: self.file_size.load(Ordering::Relaxed),
+            current_segment: self.current_segment.load(Ordering::Relaxed),
+            total_size_bytes: self.segments_size.load(Ordering::Relaxed)
+                + self.file_size.load(Ordering::Relaxed),
+            last_rewrite_size_bytes: self.rewrite_base.load(Ordering::Relaxed),
             file_path: self.file_path.clone(),
         }
     }
 
-    // Compact AOF by rewriting with current state
-    pub async fn compact(
-        &mut self,
-        current_keys: impl Iterator<Item = (String, Vec<u8>)>,
+    pub fn file_path(&self) -> &Path {
+        &self.file_path
+    }
+
+    // Switch the background writer to `new_path` once everything logged before is fsynced,
+    // starting it from a copy of the current file and segments when `copy` is set
+    // The old files stay where they are; a failure leaves the writer on them
+    pub async fn relocate(&mut self, new_path: &Path, copy: bool) -> StorageResult<()> {
+        if self.is_failed() {
+            return Err(StorageError::Persistence(
+                "AOF writer failed, cannot relocate".to_string(),
+            ));
+        }
+
+        let (ack_tx, ack_rx) = tokio::sync::oneshot::channel();
+        self.operation_tx
+            .send_async(AofMessage::Relocate {
+                new_path: new_path.to_path_buf(),
+                copy,
+                ack: ack_tx,
+            })
+            .await
+            .map_err(|e| StorageError::Persistence(format!("Failed to queue relocation: {}", e)))?;
+
+        ack_rx
+            .await
+            .map_err(|_| StorageError::Persistence("AOF writer stopped".to_string()))?
+            .map_err(|e| StorageError::Persistence(format!("AOF relocation failed: {}", e)))?;
+
+        self.file_path = new_path.to_path_buf();
+        Ok(())
+    }
+
+    // Start a compaction: from the ack on, the background writer keeps a copy of every
+    // logged operation so it can be replayed into the rewritten file
+    // Requires the background writer; callers must stop writes racing ahead of the marker
+    pub async fn begin_rewrite(&self) -> StorageResult<()> {
+        let (ack_tx, ack_rx) = tokio::sync::oneshot::channel();
+
+        self.operation_tx
+            .send_async(AofMessage::BeginRewrite(ack_tx))
+            .await
+            .map_err(|e| StorageError::Persistence(format!("Failed to start rewrite: {}", e)))?;
+
+        ack_rx
+            .await
+            .map_err(|_| StorageError::Persistence("AOF writer stopped".to_string()))
+    }
+
+    // Give up on a compaction started with begin_rewrite, the live file stays as is
+    pub async fn abort_rewrite(&self) {
+        let _ = self.operation_tx.send_async(AofMessage::AbortRewrite).await;
+    }
+
+    // Finish a compaction started with begin_rewrite
+    // `current_keys` must be read after begin_rewrite returned
+    pub async fn complete_rewrite(&self, current_keys: EntryStream) -> StorageResult<()> {
+        let current_keys: ExpiringEntryStream =
+            Box::pin(current_keys.map_ok(|(key, value)| (key, value, None)));
+        self.complete_rewrite_databases(vec![current_keys]).await
+    }
+
+    // Same as complete_rewrite with one stream per database, indexed by database number,
+    // and each key's expiry written after it
+    pub async fn complete_rewrite_databases(
+        &self,
+        databases: Vec<ExpiringEntryStream>,
     ) -> StorageResult<()> {
         info!("Starting AOF compaction");
 
         let temp_path = self.file_path.with_extension("aof.tmp");
-        let temp_file = File::create(&temp_path).await?;
+
+        let compacted = match Self::write_rewrite(&temp_path, databases).await {
+            Ok(compacted) => compacted,
+            Err(e) => {
+                self.abort_rewrite().await;
+                let _ = tokio::fs::remove_file(&temp_path).await;
+                return Err(e);
+            }
+        };
+
+        // The writer appends the delta and swaps files between two queued writes
+        let (ack_tx, ack_rx) = tokio::sync::oneshot::channel();
+        self.operation_tx
+            .send_async(AofMessage::FinishRewrite {
+                temp_path,
+                ack: ack_tx,
+            })
+            .await
+            .map_err(|e| StorageError::Persistence(format!("Failed to finish rewrite: {}", e)))?;
+
+        ack_rx
+            .await
+            .map_err(|_| StorageError::Persistence("AOF writer stopped".to_string()))?
+            .map_err(|e| StorageError::Persistence(format!("AOF rewrite failed: {}", e)))?;
+
+        info!("AOF compaction completed, {} entries written", compacted);
+        Ok(())
+    }
+
+    // Write the current state to a temporary AOF
+    async fn write_rewrite(
+        temp_path: &Path,
+        databases: Vec<ExpiringEntryStream>,
+    ) -> StorageResult<u64> {
+        let temp_file = File::create(temp_path).await?;
         let mut temp_writer = BufWriter::new(temp_file);
 
-        let mut compacted_opt = 0;
+        let mut compacted = 0;
 
-        // Write current state
-        for (k, v) in current_keys {
-            let op = Operation::Put { key: k, value: v };
-            let entry = op.to_aof_entry()?;
-            temp_writer.write_all(entry.as_bytes()).await?;
-            compacted_opt += 1;
+        for (db, mut current_keys) in databases.into_iter().enumerate() {
+            // The file holds each database's whole state, so recovery must drop whatever an
+            // older snapshot loaded first; keys deleted since would otherwise come back
+            let flush = Operation::Flush.in_database(db).to_aof_entry()?;
+            temp_writer.write_all(flush.as_bytes()).await?;
+
+            while let Some((k, v, expires_at)) = current_keys.try_next().await? {
+                let expire = expires_at.map(|at_millis| Operation::ExpireAt {
+                    key: k.clone(),
+                    at_millis,
+                });
+                let op = Operation::Put { key: k, value: v }.in_database(db);
+                let entry = op.to_aof_entry()?;
+                temp_writer.write_all(entry.as_bytes()).await?;
+                if let Some(expire) = expire {
+                    let entry = expire.in_database(db).to_aof_entry()?;
+                    temp_writer.write_all(entry.as_bytes()).await?;
+                }
+                compacted += 1;
+            }
         }
 
         temp_writer.flush().await?;
         temp_writer.into_inner().sync_all().await?;
 
-        // Replace old AOF with compacted
-        tokio::fs::rename(&temp_path, &self.file_path).await?;
-
-        self.open_writer().await?;
-        info!(
-            "AOF compaction completed, {} entries written",
-            compacted_opt
-        );
-        Ok(())
+        Ok(compacted)
     }
 }
 
 #[derive(Debug)]
 pub struct AofStats {
+    pub failed: bool,
     pub operations_logged: u64,
-    pub file_size_bytes: u64,
+    pub fsyncs: u64, // Flush + fsync rounds, a group commit covers many operations
+    pub file_size_bytes: u64, // Live file only
+    pub current_segment: u64, // Segments rotated out since the last compaction
+    pub total_size_bytes: u64, // Live file plus rotated segments
+    pub last_rewrite_size_bytes: u64, // Total size after the last rewrite, or at startup
     pub file_path: PathBuf,
 }
+
+// Best effort for abnormal exits; an orderly PersistenceManager::stop() is still the
+// reliable path, this only narrows what a panic or a forgotten stop() can lose
+impl Drop for AppendOnlyFile {
+    fn drop(&mut self) {
+        // Writer never started: push out what log_operation_sync left buffered
+        if let Some(writer) = self.writer.take() {
+            if writer.buffer().is_empty() {
+                return;
+            }
+
+            let buffered = writer.buffer().to_vec();
+            let flushed = match writer.into_inner().try_into_std() {
+                Ok(mut file) => std::io::Write::write_all(&mut file, &buffered)
+                    .and_then(|_| file.sync_all())
+                    .map_err(|e| e.to_string()),
+                Err(_) => Err("file busy".to_string()),
+            };
+            if let Err(e) = flushed {
+                warn!("AOF buffer could not be flushed on drop: {}", e);
+            }
+            return;
+        }
+
+        // The writer also flushes once the channel closes; waiting here makes sure that
+        // happened before a process about to exit moves on
+        if self.writer_running.load(Ordering::Acquire)
+            && let Err(e) = self.flush_blocking(DROP_FLUSH_TIMEOUT)
+        {
+            debug!("AOF not flushed on drop: {}", e);
+        }
+    }
+}
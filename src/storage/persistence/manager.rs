@@ -1,26 +1,106 @@
-use std::{collections::HashMap, sync::Arc, time::Duration};
+use std::{
+    hash::{BuildHasher, RandomState},
+    path::{Path, PathBuf},
+    sync::{
+        Arc, Weak,
+        atomic::{AtomicBool, AtomicU64, Ordering},
+    },
+    time::Duration,
+};
 
-use tokio::sync::RwLock;
-use tracing::{error, info, instrument};
+use futures_util::TryStreamExt;
+use tokio::{
+    sync::{Mutex, MutexGuard, OwnedRwLockReadGuard, RwLock, watch},
+    task::JoinHandle,
+    time::MissedTickBehavior,
+};
+use tokio_util::sync::CancellationToken;
+use tracing::{debug, error, info, instrument, warn};
 
 use crate::{
     config::{FsyncPolicy, PersistenceConfig},
     storage::{
-        StorageEngine, StorageError, StorageResult,
+        ExpiringEntryStream, StorageConfig, StorageEngine, StorageError, StorageResult,
+        engine::memory::MemoryEngine,
         persistence::{
-            aof::{AppendOnlyFile, Operation},
+            aof::{AppendOnlyFile, Operation, OperationQueue},
             recovery::{RecoveryManager, RecoveryStats},
-            snapshot::Snapshotter,
+            snapshot::{SnapshotData, Snapshotter, snapshot_format},
         },
     },
 };
 
+// Stripes of the per-key write order; keys hashing to the same stripe simply share it
+const WRITE_ORDER_STRIPES: usize = 64;
+
+// Left by relocate() next to the old AOF and in the old snapshot directory, holding the
+// new location. The config file still names the old one, and starting from those stale
+// files would silently lose every write since the move, so recovery refuses to
+const RELOCATED_AOF_SUFFIX: &str = ".relocated";
+const RELOCATED_SNAPSHOTS_MARKER: &str = "RELOCATED";
+
+// Held by a write from before it runs until its AOF entry is queued, see order_writes()
+pub struct WriteOrder<'a> {
+    _gate: OwnedRwLockReadGuard<()>,
+    _keys: Vec<MutexGuard<'a, ()>>,
+}
+
 // Manages all persistence operations (AOF + Snapshots)
 pub struct PersistenceManager {
     pub aof: Option<Arc<RwLock<AppendOnlyFile>>>,
-    pub snapshotter: Option<Snapshotter>,
+    pub snapshotter: Option<parking_lot::RwLock<Snapshotter>>, // Swapped by relocate()
     config: PersistenceConfig,
-    storage: Arc<dyn StorageEngine>,
+    databases: Vec<Arc<dyn StorageEngine>>, // Indexed by database number
+
+    // Writes hold a read guard from AOF logging until applied to storage;
+    // compaction takes it exclusively to place its marker between whole writes
+    write_gate: Arc<RwLock<()>>,
+
+    // Writes to one key are applied and logged one at a time, so replay sees their order
+    key_order: Vec<Mutex<()>>,
+    key_hasher: RandomState,
+
+    // Held while a snapshot is written, and by relocate() from copying the snapshots until
+    // it switches directories, so no snapshot ends up only in the old one
+    snapshot_lock: Mutex<()>,
+
+    // Snapshot period in seconds, changeable at runtime (CONFIG SET)
+    snapshot_interval: watch::Sender<u64>,
+
+    // Background tasks, stopped by stop(); they only hold a Weak to the manager
+    shutdown: CancellationToken,
+    background: parking_lot::Mutex<Vec<JoinHandle<()>>>,
+
+    // At most one AOF rewrite at a time, whether manual or after a snapshot
+    rewrite_in_progress: Arc<AtomicBool>,
+    rewrites_completed: AtomicU64,
+    last_rewrite_error: parking_lot::Mutex<Option<String>>,
+}
+
+// Held for the duration of an AOF rewrite, releases the slot on drop (even on panic)
+struct RewriteClaim(Arc<AtomicBool>);
+
+impl Drop for RewriteClaim {
+    fn drop(&mut self) {
+        self.0.store(false, Ordering::Release);
+    }
+}
+
+// Result of a successful SNAPSHOT VERIFY
+#[derive(Debug, Clone)]
+pub struct SnapshotVerification {
+    pub path: PathBuf,
+    pub format_version: u32,
+    pub total_keys: usize,
+    pub total_size: usize,
+    pub checksum_verified: bool, // false for snapshots written before checksums existed
+}
+
+// Where persistence writes after a successful RELOCATE
+#[derive(Debug, Clone)]
+pub struct Relocation {
+    pub aof_path: Option<PathBuf>,
+    pub snapshot_dir: Option<PathBuf>,
 }
 
 /// Persistence statistics
@@ -30,6 +110,9 @@ pub struct PersistenceStats {
     pub aof_stats: Option<super::aof::AofStats>,
     pub snapshot_enabled: bool,
     pub snapshot_count: usize,
+    pub aof_rewrite_in_progress: bool,
+    pub aof_rewrites_completed: u64,
+    pub aof_last_rewrite_error: Option<String>, // Cleared by the next successful rewrite
 }
 
 impl PersistenceManager {
@@ -47,11 +130,11 @@ impl PersistenceManager {
             let mut aof = AppendOnlyFile::new(&config.aof_path).await?;
 
             // Set Fsync policy
-            aof.fsync_every = match config.fsync_policy {
-                FsyncPolicy::Always => 0,
-                FsyncPolicy::EveryN(n) => n,
-                FsyncPolicy::Never => u64::MAX,
-            };
+            aof.fsync_every = config.fsync_policy.fsync_every();
+            aof.max_size = config.max_aof_size;
+            aof.group_commit_window = Duration::from_micros(config.group_commit_window_us);
+            aof.rewrite_percentage = config.aof_rewrite_percentage;
+            aof.rewrite_min_size = config.aof_rewrite_min_size;
 
             // Start background writer
             aof.start_background_writer().await;
@@ -68,8 +151,9 @@ impl PersistenceManager {
                 "Initializing snapshotter at: {}",
                 config.snapshot_dir.display()
             );
-            let snapshotter = Snapshotter::new(&config.snapshot_dir)?;
-            Some(snapshotter)
+            let snapshotter = Snapshotter::new(&config.snapshot_dir)?
+                .with_format(snapshot_format(config.snapshot_format));
+            Some(parking_lot::RwLock::new(snapshotter))
         } else {
             info!("Snapshots disabled");
             None
@@ -78,22 +162,102 @@ impl PersistenceManager {
         Ok(Self {
             aof,
             snapshotter,
+            databases: vec![storage],
+            write_gate: Arc::new(RwLock::new(())),
+            key_order: (0..WRITE_ORDER_STRIPES).map(|_| Mutex::new(())).collect(),
+            key_hasher: RandomState::new(),
+            snapshot_lock: Mutex::new(()),
+            snapshot_interval: watch::Sender::new(config.snapshot_interval),
+            shutdown: CancellationToken::new(),
+            background: parking_lot::Mutex::new(Vec::new()),
+            rewrite_in_progress: Arc::new(AtomicBool::new(false)),
+            rewrites_completed: AtomicU64::new(0),
+            last_rewrite_error: parking_lot::Mutex::new(None),
             config,
-            storage,
         })
     }
 
+    // Persist every logical database instead of only the engine given to new()
+    // `databases[0]` takes that engine's place; an empty list is ignored
+    pub fn with_databases(mut self, databases: Vec<Arc<dyn StorageEngine>>) -> Self {
+        if !databases.is_empty() {
+            self.databases = databases;
+        }
+        self
+    }
+
+    // Held by a write from before it runs until its AOF entry is queued: writes to the same
+    // key reach the log in the order they were applied, and compaction never splits a write
+    // from its entry. `keys` None orders against every other write (FLUSHDB, DELPREFIX)
+    pub async fn order_writes(&self, keys: Option<&[&str]>) -> WriteOrder<'_> {
+        let gate = self.write_gate.clone().read_owned().await;
+
+        let mut stripes: Vec<usize> = match keys {
+            Some(keys) => keys
+                .iter()
+                .map(|key| self.key_hasher.hash_one(key) as usize % WRITE_ORDER_STRIPES)
+                .collect(),
+            None => (0..WRITE_ORDER_STRIPES).collect(),
+        };
+        // Ascending, so two multi-key writes never wait on each other in opposite order
+        stripes.sort_unstable();
+        stripes.dedup();
+
+        let mut guards = Vec::with_capacity(stripes.len());
+        for stripe in stripes {
+            guards.push(self.key_order[stripe].lock().await);
+        }
+        WriteOrder {
+            _gate: gate,
+            _keys: guards,
+        }
+    }
+
     // Recover database from persistence
     #[instrument(skip(self))]
     pub async fn recover(&self) -> StorageResult<RecoveryStats> {
+        let Some(ref restore_from) = self.config.restore_from else {
+            return self.recover_from(None, true).await;
+        };
+
+        let stats = self
+            .recover_from(
+                Some(restore_from.clone()),
+                !self.config.restore_snapshot_only,
+            )
+            .await?;
+
+        // Persist the restored dataset over what is on disk, so the next plain restart comes
+        // back to it rather than to the latest snapshot and the old AOF
+        if self.snapshotter.is_some() {
+            self.create_snapshot().await?;
+        } else if self.aof.is_some() {
+            self.compact_aof().await?;
+        }
+        info!("Restore from {} persisted", restore_from.display());
+
+        Ok(stats)
+    }
+
+    // Recover from `restore_from` instead of the latest snapshot when given, replaying the
+    // AOF on top unless `replay_aof` is false
+    async fn recover_from(
+        &self,
+        restore_from: Option<PathBuf>,
+        replay_aof: bool,
+    ) -> StorageResult<RecoveryStats> {
         info!("Starting database recovery...");
+        self.check_not_relocated().await?;
 
-        let aof_for_recovery = if let Some(ref _aof_lock) = self.aof {
+        let aof_for_recovery = if let Some(ref aof_lock) = self.aof
+            && replay_aof
+        {
             // Create new AOF instance for reading (don't interfere with writer)
-            let aof_path = &self.config.aof_path;
+            // The live path, which RELOCATE may have moved away from the configured one
+            let aof_path = aof_lock.read().await.file_path().to_path_buf();
 
             if aof_path.exists() {
-                Some(AppendOnlyFile::new(aof_path).await?)
+                Some(AppendOnlyFile::new(&aof_path).await?)
             } else {
                 None
             }
@@ -101,9 +265,16 @@ impl PersistenceManager {
             None
         };
 
-        let recovery_manager = RecoveryManager::new(aof_for_recovery, self.snapshotter.clone());
+        let recovery_manager = RecoveryManager::new(aof_for_recovery, self.current_snapshotter())
+            .with_snapshot_required(self.config.snapshot_required)
+            .with_restore_from(restore_from);
 
-        let stats = recovery_manager.recover(self.storage.as_ref()).await?;
+        let databases: Vec<&dyn StorageEngine> = self
+            .databases
+            .iter()
+            .map(|storage| storage.as_ref())
+            .collect();
+        let stats = recovery_manager.recover_databases(&databases).await?;
 
         stats.print_summary();
 
@@ -120,30 +291,63 @@ impl PersistenceManager {
         Ok(())
     }
 
+    // Queue for logging from synchronous code, None without an AOF
+    pub async fn operation_queue(&self) -> Option<OperationQueue> {
+        match self.aof {
+            Some(ref aof) => Some(aof.read().await.queue()),
+            None => None,
+        }
+    }
+
+    // Wait until all logged operations are fsynced to the AOF
+    pub async fn sync_aof(&self) -> StorageResult<()> {
+        match self.aof {
+            Some(ref aof) => aof.read().await.sync().await,
+            None => Err(StorageError::Persistence("AOF not enabled".to_string())),
+        }
+    }
+
+    // Push queued writes to the OS without forcing an fsync the policy didn't ask for
+    pub async fn flush_aof(&self) -> StorageResult<()> {
+        match self.aof {
+            Some(ref aof) => aof.read().await.flush().await,
+            None => Err(StorageError::Persistence("AOF not enabled".to_string())),
+        }
+    }
+
     // Create snapshot manually
     #[instrument(skip(self))]
     pub async fn create_snapshot(&self) -> StorageResult<()> {
-        if let Some(ref snapshotter) = self.snapshotter {
+        let saving = self.snapshot_lock.lock().await;
+        if let Some(snapshotter) = self.current_snapshotter() {
             info!("Creating manual snapshot...");
 
             // Get all data from storage
-            let all_keys = self.storage.scan("").await?;
-            let mut data = HashMap::new();
-
-            for key in all_keys {
-                if let Some(value) = self.storage.get(&key).await? {
-                    data.insert(key, value);
-                }
+            let mut databases = Vec::with_capacity(self.databases.len());
+            for storage in &self.databases {
+                let data: SnapshotData = storage
+                    .iter_with_expiry()
+                    .await?
+                    .map_ok(|(key, value, expires_at)| (key, (value, expires_at)))
+                    .try_collect()
+                    .await?;
+                databases.push(data);
             }
 
-            let snapshot_path = snapshotter.create_snapshot(data).await?;
+            let snapshot_path = snapshotter.create_snapshot_databases(databases).await?;
+            drop(saving);
 
             info!("Snapshot created: {}", snapshot_path.display());
 
-            // Optionally compact AOF after snapshot
+            // Optionally compact AOF after snapshot, unless a rewrite is already running
             if self.config.enabled {
-                info!("Compacting AOF after snapshot...");
-                self.compact_aof().await?;
+                match self.claim_rewrite() {
+                    Ok(claim) => {
+                        info!("Compacting AOF after snapshot...");
+                        self.rewrite_aof(claim).await?;
+                    }
+                    Err(_) => info!("AOF rewrite already in progress, skipping compaction"),
+                }
             }
 
             Ok(())
@@ -154,49 +358,446 @@ impl PersistenceManager {
         }
     }
 
-    // Compact AOF (remove redundant operations)
-    async fn compact_aof(&self) -> StorageResult<()> {
-        if let Some(ref aof_lock) = self.aof {
-            let all_keys = self.storage.scan("").await?;
-            let mut current_state = Vec::new();
+    // Snapshot, wipe every database and recover it from the snapshot and AOF, like a restart
+    // Writes wait at the write gate meanwhile, reads may see the dataset half-loaded
+    // Returns the live key count before and after
+    #[instrument(skip(self))]
+    pub async fn reload(&self) -> StorageResult<(usize, usize)> {
+        self.create_snapshot().await?;
+
+        let _gate = self.write_gate.write().await;
+        if self.aof.is_some() {
+            self.sync_aof().await?;
+        }
 
-            for key in all_keys {
-                if let Some(value) = self.storage.get(&key).await? {
-                    current_state.push((key, value));
+        let before = self.live_key_count().await?;
+        for storage in &self.databases {
+            storage.clear().await?;
+        }
+        // Always from the latest snapshot, a startup restore has been persisted since
+        self.recover_from(None, true).await?;
+        let after = self.live_key_count().await?;
+
+        info!("Reloaded dataset: {} keys before, {} after", before, after);
+        Ok((before, after))
+    }
+
+    // Cloned out of the lock, which is never held across an await
+    fn current_snapshotter(&self) -> Option<Snapshotter> {
+        self.snapshotter
+            .as_ref()
+            .map(|snapshotter| snapshotter.read().clone())
+    }
+
+    // Move the AOF and/or the snapshot directory at runtime, e.g. onto a new volume; only
+    // with persistence.allow_relocate, and never alongside another relocation or a rewrite
+    // Writes wait at the write gate while the AOF and snapshot directory switch, so each one
+    // lands in exactly one of the old and new file. With `copy` the old files are copied
+    // over first, and removed again if the switch fails,
+    // otherwise the new AOF is seeded by a rewrite of the current dataset. Either way the
+    // old files are left in place for the operator to remove, marked so that a restart on
+    // a config still naming them fails instead of loading them
+    #[instrument(skip(self))]
+    pub async fn relocate(
+        &self,
+        aof_path: Option<PathBuf>,
+        snapshot_dir: Option<PathBuf>,
+        copy: bool,
+    ) -> StorageResult<Relocation> {
+        if !self.config.allow_relocate {
+            return Err(StorageError::Persistence(
+                "Relocation is disabled, set persistence.allow_relocate".to_string(),
+            ));
+        }
+        if aof_path.is_none() && snapshot_dir.is_none() {
+            return Err(StorageError::Persistence("Nothing to relocate".to_string()));
+        }
+        if aof_path.is_some() && self.aof.is_none() {
+            return Err(StorageError::Persistence("AOF not enabled".to_string()));
+        }
+        if snapshot_dir.is_some() && self.snapshotter.is_none() {
+            return Err(StorageError::Persistence(
+                "Snapshots not enabled".to_string(),
+            ));
+        }
+        if self.shutdown.is_cancelled() {
+            return Err(StorageError::Persistence(
+                "Persistence is shutting down".to_string(),
+            ));
+        }
+
+        let claim = self.claim_rewrite().map_err(|_| {
+            StorageError::Persistence(
+                "A relocation or AOF rewrite is already in progress".to_string(),
+            )
+        })?;
+
+        // No snapshot is written from the copy until the switch, so each one lands in the
+        // new directory or gets copied there
+        let saving = self.snapshot_lock.lock().await;
+
+        // Copy snapshots first, the step most likely to fail, before anything switches
+        let snapshotter = match (snapshot_dir, self.current_snapshotter()) {
+            (Some(dir), Some(current)) => Some(current.relocate(&dir, copy).await?),
+            _ => None,
+        };
+
+        // Switch the AOF and the snapshot directory between the same two writes
+        let old_aof_path = match self.aof {
+            Some(ref aof_lock) => Some(aof_lock.read().await.file_path().to_path_buf()),
+            None => None,
+        };
+        let old_snapshotter = {
+            let _gate = self.write_gate.write().await;
+            if let (Some(path), Some(aof_lock)) = (&aof_path, &self.aof)
+                && let Err(e) = aof_lock.write().await.relocate(path, copy).await
+            {
+                // Nothing switched, leave the new directory as empty as it was
+                if let Some(ref new) = snapshotter {
+                    new.discard_relocated().await;
                 }
+                return Err(e);
+            }
+            match (&snapshotter, &self.snapshotter) {
+                (Some(new), Some(lock)) => Some(std::mem::replace(&mut *lock.write(), new.clone())),
+                _ => None,
+            }
+        };
+        drop(saving);
+
+        if let (Some(path), Some(old_path)) = (&aof_path, &old_aof_path) {
+            Self::mark_relocated(&Self::relocated_aof_marker(old_path), path)
+                .await
+                .map_err(|e| {
+                    StorageError::Persistence(format!(
+                        "AOF moved to {} but marking the old file failed: {}",
+                        path.display(),
+                        e
+                    ))
+                })?;
+            let _ = tokio::fs::remove_file(Self::relocated_aof_marker(path)).await;
+        }
+
+        if let (Some(new), Some(old)) = (&snapshotter, &old_snapshotter) {
+            info!("Snapshots moved to {}", new.snapshot_dir().display());
+
+            let marker = old.snapshot_dir().join(RELOCATED_SNAPSHOTS_MARKER);
+            Self::mark_relocated(&marker, new.snapshot_dir())
+                .await
+                .map_err(|e| {
+                    StorageError::Persistence(format!(
+                        "Snapshots moved to {} but marking the old directory failed: {}",
+                        new.snapshot_dir().display(),
+                        e
+                    ))
+                })?;
+            let _ =
+                tokio::fs::remove_file(new.snapshot_dir().join(RELOCATED_SNAPSHOTS_MARKER)).await;
+        }
+
+        // Everything has switched by now, so a failed seeding is fixed by a rewrite of the
+        // new file rather than by relocating again
+        if let Some(ref path) = aof_path
+            && !copy
+        {
+            self.rewrite_aof(claim).await.map_err(|e| {
+                StorageError::Persistence(format!(
+                    "AOF moved to {} but seeding it failed, run BGREWRITEAOF before \
+                     restarting: {}",
+                    path.display(),
+                    e
+                ))
+            })?;
+        }
+
+        Ok(Relocation {
+            aof_path,
+            snapshot_dir: snapshotter.map(|s| s.snapshot_dir().to_path_buf()),
+        })
+    }
+
+    fn relocated_aof_marker(aof_path: &Path) -> PathBuf {
+        let mut marker = aof_path.as_os_str().to_owned();
+        marker.push(RELOCATED_AOF_SUFFIX);
+        PathBuf::from(marker)
+    }
+
+    async fn mark_relocated(marker: &Path, new_location: &Path) -> std::io::Result<()> {
+        tokio::fs::write(marker, new_location.display().to_string()).await?;
+        tokio::fs::File::open(marker).await?.sync_all().await
+    }
+
+    // Fail on files relocate() moved away from, naming where they went
+    async fn check_not_relocated(&self) -> StorageResult<()> {
+        let mut markers = Vec::new();
+        if let Some(ref aof_lock) = self.aof {
+            markers.push(Self::relocated_aof_marker(
+                aof_lock.read().await.file_path(),
+            ));
+        }
+        if let Some(snapshotter) = self.current_snapshotter() {
+            markers.push(snapshotter.snapshot_dir().join(RELOCATED_SNAPSHOTS_MARKER));
+        }
+
+        for marker in markers {
+            if let Ok(moved_to) = tokio::fs::read_to_string(&marker).await {
+                return Err(StorageError::Persistence(format!(
+                    "Persistence files were moved to {} by RELOCATE: point the config there, \
+                     or delete {} to start from the old files anyway",
+                    moved_to,
+                    marker.display()
+                )));
+            }
+        }
+        Ok(())
+    }
+
+    // Unexpired keys over every database, counted by walking them
+    async fn live_key_count(&self) -> StorageResult<usize> {
+        let mut count = 0;
+        for entries in self.iter_databases().await? {
+            count += entries.try_fold(0, |n, _| async move { Ok(n + 1) }).await?;
+        }
+        Ok(count)
+    }
+
+    // Load a snapshot (None = latest) into a throwaway engine without touching the live store
+    // Catches corruption in files that would otherwise only be read on the next restart
+    #[instrument(skip(self))]
+    pub async fn verify_snapshot(&self, name: Option<&str>) -> StorageResult<SnapshotVerification> {
+        let snapshotter = self
+            .current_snapshotter()
+            .ok_or_else(|| StorageError::Persistence("Snapshots not enabled".to_string()))?;
+
+        let path = snapshotter.snapshot_path(name)?;
+        let snapshot = snapshotter.load_snapshot(&path).await?;
+        let checksum_verified = snapshot.verify()?;
+
+        // Replay into scratch engines, exercising the same path recovery takes
+        let mut total_keys = 0;
+        for data in std::iter::once(&snapshot.data).chain(snapshot.databases.values()) {
+            let scratch = MemoryEngine::new(StorageConfig {
+                max_memory: usize::MAX,
+                default_ttl: None,
+                ..StorageConfig::default()
+            });
+            for (key, (value, _)) in data {
+                scratch.set(key, value.clone()).await?;
+            }
+            total_keys += scratch.stats().await?.total_keys;
+        }
+
+        if total_keys != snapshot.metadata.total_keys {
+            return Err(StorageError::Persistence(format!(
+                "snapshot loaded {} keys, metadata records {}",
+                total_keys, snapshot.metadata.total_keys
+            )));
+        }
+
+        info!(
+            "Snapshot verified: {} ({} keys, {} bytes)",
+            path.display(),
+            total_keys,
+            snapshot.metadata.total_size
+        );
+
+        Ok(SnapshotVerification {
+            path,
+            format_version: snapshot.metadata.format_version,
+            total_keys,
+            total_size: snapshot.metadata.total_size,
+            checksum_verified,
+        })
+    }
+
+    // Compact AOF (remove redundant operations), failing if a rewrite is already running
+    pub async fn compact_aof(&self) -> StorageResult<()> {
+        let claim = self.claim_rewrite()?;
+        self.rewrite_aof(claim).await
+    }
+
+    // BGREWRITEAOF: compact in the background and return once the rewrite is claimed
+    pub fn start_aof_rewrite(self: &Arc<Self>) -> StorageResult<()> {
+        if self.aof.is_none() {
+            return Err(StorageError::Persistence("AOF not enabled".to_string()));
+        }
+        if self.shutdown.is_cancelled() {
+            return Err(StorageError::Persistence(
+                "Persistence is shutting down".to_string(),
+            ));
+        }
+
+        let claim = self.claim_rewrite()?;
+        let manager = self.clone();
+
+        // Tracked with the other background tasks, so stop() waits for it
+        let handle = tokio::spawn(async move {
+            if let Err(e) = manager.rewrite_aof(claim).await {
+                error!("Background AOF rewrite failed: {}", e);
+            }
+        });
+        self.background.lock().push(handle);
+
+        info!("Background AOF rewrite started");
+        Ok(())
+    }
+
+    pub fn is_aof_rewrite_in_progress(&self) -> bool {
+        self.rewrite_in_progress.load(Ordering::Acquire)
+    }
+
+    pub fn aof_rewrites_completed(&self) -> u64 {
+        self.rewrites_completed.load(Ordering::Relaxed)
+    }
+
+    pub fn aof_last_rewrite_error(&self) -> Option<String> {
+        self.last_rewrite_error.lock().clone()
+    }
+
+    fn claim_rewrite(&self) -> StorageResult<RewriteClaim> {
+        self.rewrite_in_progress
+            .compare_exchange(false, true, Ordering::AcqRel, Ordering::Acquire)
+            .map(|_| RewriteClaim(self.rewrite_in_progress.clone()))
+            .map_err(|_| StorageError::Persistence("AOF rewrite already in progress".to_string()))
+    }
+
+    // Writes keep flowing: anything logged after the rewrite marker is buffered by the
+    // AOF writer and appended to the compacted file before it replaces the old one
+    // On failure the live AOF is left untouched
+    async fn rewrite_aof(&self, _claim: RewriteClaim) -> StorageResult<()> {
+        let Some(ref aof_lock) = self.aof else {
+            return Ok(());
+        };
+
+        let aof = aof_lock.read().await;
+
+        // Place the marker between whole writes, so each one is either
+        // already in storage or in the buffered delta
+        {
+            let _gate = self.write_gate.write().await;
+            aof.begin_rewrite().await?;
+        }
+
+        let result = match self.iter_databases().await {
+            Ok(current_state) => aof.complete_rewrite_databases(current_state).await,
+            Err(e) => {
+                aof.abort_rewrite().await;
+                Err(e)
             }
+        };
 
-            let mut aof = aof_lock.write().await;
-            aof.compact(current_state.into_iter()).await?;
+        match result {
+            Ok(()) => {
+                self.rewrites_completed.fetch_add(1, Ordering::Relaxed);
+                *self.last_rewrite_error.lock() = None;
+                info!("AOF compaction completed");
+                Ok(())
+            }
+            Err(e) => {
+                warn!("AOF compaction failed, keeping the current file: {}", e);
+                *self.last_rewrite_error.lock() = Some(e.to_string());
+                Err(e)
+            }
+        }
+    }
 
-            info!("AOF compaction completed");
+    async fn iter_databases(&self) -> StorageResult<Vec<ExpiringEntryStream>> {
+        let mut streams = Vec::with_capacity(self.databases.len());
+        for storage in &self.databases {
+            streams.push(storage.iter_with_expiry().await?);
         }
+        Ok(streams)
+    }
 
+    // Apply a new fsync policy to the running AOF writer
+    pub async fn set_fsync_policy(&self, policy: &FsyncPolicy) -> StorageResult<()> {
+        match self.aof {
+            Some(ref aof) => {
+                aof.read().await.set_fsync_every(policy.fsync_every());
+                info!("AOF fsync policy changed to {}", policy);
+                Ok(())
+            }
+            None => Err(StorageError::Persistence("AOF not enabled".to_string())),
+        }
+    }
+
+    // Reschedule background snapshots; the next one runs a full new interval from now
+    pub fn set_snapshot_interval(&self, seconds: u64) -> StorageResult<()> {
+        if !self.config.snapshot_enabled {
+            return Err(StorageError::Persistence(
+                "Snapshots not enabled".to_string(),
+            ));
+        }
+
+        if seconds == 0 || seconds <= self.config.snapshot_jitter {
+            return Err(StorageError::Persistence(
+                "snapshot_interval must be > 0 and > snapshot_jitter".to_string(),
+            ));
+        }
+
+        self.snapshot_interval.send_replace(seconds);
+        info!("Snapshot interval changed to {}s", seconds);
         Ok(())
     }
 
     /// Start background snapshot task
+    /// The task holds only a Weak reference, so it never keeps the manager (and storage) alive
     pub fn start_background_snapshots(self: Arc<Self>) {
-        if !self.config.snapshot_enabled {
+        if !self.config.snapshot_enabled || self.shutdown.is_cancelled() {
             return;
         }
 
-        let interval = Duration::from_secs(self.config.snapshot_interval);
+        let mut interval_rx = self.snapshot_interval.subscribe();
+        let interval = Duration::from_secs(*interval_rx.borrow_and_update());
+        let jitter = Duration::from_secs(self.config.snapshot_jitter);
 
         info!(
-            "Starting background snapshot task (interval: {:?})",
-            interval
+            "Starting background snapshot task (interval: {:?}, jitter: {:?})",
+            interval, jitter
         );
 
-        tokio::spawn(async move {
+        let manager = Arc::downgrade(&self);
+        let shutdown = self.shutdown.clone();
+
+        let handle = tokio::spawn(async move {
             let mut interval_timer = tokio::time::interval(interval);
+            // A snapshot that overruns the interval must not cause a burst of catch-up ticks
+            interval_timer.set_missed_tick_behavior(MissedTickBehavior::Skip);
 
             loop {
-                interval_timer.tick().await;
+                tokio::select! {
+                    _ = shutdown.cancelled() => return,
+                    _ = interval_timer.tick() => {}
+                    changed = interval_rx.changed() => {
+                        if changed.is_err() {
+                            return; // Sender gone
+                        }
+
+                        let interval = Duration::from_secs(*interval_rx.borrow_and_update());
+                        interval_timer = tokio::time::interval_at(
+                            tokio::time::Instant::now() + interval,
+                            interval,
+                        );
+                        interval_timer.set_missed_tick_behavior(MissedTickBehavior::Skip);
+                        continue;
+                    }
+                }
+
+                // Spread snapshots across instances sharing the same schedule
+                if !jitter.is_zero() {
+                    tokio::select! {
+                        _ = shutdown.cancelled() => return,
+                        _ = tokio::time::sleep(random_delay(jitter)) => {}
+                    }
+                }
+
+                let Some(manager) = Weak::upgrade(&manager) else {
+                    return; // Manager dropped
+                };
 
                 info!("Background snapshot triggered");
 
-                match self.create_snapshot().await {
+                match manager.create_snapshot().await {
                     Ok(_) => {
                         info!("Background snapshot completed successfully");
                     }
@@ -206,6 +807,142 @@ impl PersistenceManager {
                 }
             }
         });
+
+        self.background.lock().push(handle);
+    }
+
+    /// Compact the AOF whenever the writer rotates it, folding the segments back into one file
+    /// Like the snapshot task it only holds a Weak reference to the manager
+    pub async fn start_rotation_compaction(self: Arc<Self>) {
+        let Some(ref aof) = self.aof else {
+            return;
+        };
+        if self.config.max_aof_size == 0 || self.shutdown.is_cancelled() {
+            return;
+        }
+
+        let rotations = aof.read().await.rotations();
+        let manager = Arc::downgrade(&self);
+        let shutdown = self.shutdown.clone();
+
+        let handle = tokio::spawn(async move {
+            loop {
+                let segment = tokio::select! {
+                    _ = shutdown.cancelled() => return,
+                    rotated = rotations.recv_async() => match rotated {
+                        Ok(segment) => segment,
+                        Err(_) => return, // AOF dropped
+                    },
+                };
+
+                let Some(manager) = Weak::upgrade(&manager) else {
+                    return; // Manager dropped
+                };
+
+                info!("AOF rotated to segment {}, compacting", segment);
+
+                // A rewrite already running covers this rotation as well
+                if let Err(e) = manager.start_aof_rewrite() {
+                    debug!("Compaction after rotation not started: {}", e);
+                }
+            }
+        });
+
+        self.background.lock().push(handle);
+    }
+
+    /// Compact the AOF once it has grown aof_rewrite_percentage past its size after the last
+    /// rewrite, the way rotations trigger one, or once its file had to be recreated
+    pub async fn start_growth_compaction(self: Arc<Self>) {
+        let Some(ref aof) = self.aof else {
+            return;
+        };
+        // Runs even with aof_rewrite_percentage at 0, a recreated file needs the rewrite
+        if self.shutdown.is_cancelled() {
+            return;
+        }
+
+        let growth = aof.read().await.growth();
+        let manager = Arc::downgrade(&self);
+        let shutdown = self.shutdown.clone();
+
+        let handle = tokio::spawn(async move {
+            loop {
+                let size = tokio::select! {
+                    _ = shutdown.cancelled() => return,
+                    grown = growth.recv_async() => match grown {
+                        Ok(size) => size,
+                        Err(_) => return, // AOF dropped
+                    },
+                };
+
+                let Some(manager) = Weak::upgrade(&manager) else {
+                    return; // Manager dropped
+                };
+
+                info!("AOF asked for a rewrite at {} bytes, compacting", size);
+
+                if let Err(e) = manager.start_aof_rewrite() {
+                    debug!("Compaction after growth not started: {}", e);
+                }
+            }
+        });
+
+        self.background.lock().push(handle);
+    }
+
+    // Stop background tasks and fsync the AOF; an in-progress snapshot is allowed to finish
+    // so neither the snapshot nor the AOF rewrite it triggers is left half done
+    pub async fn stop(&self) -> StorageResult<()> {
+        info!("Stopping persistence background tasks...");
+        self.shutdown.cancel();
+
+        let handles: Vec<_> = std::mem::take(&mut *self.background.lock());
+        for handle in handles {
+            if let Err(e) = handle.await
+                && !e.is_cancelled()
+            {
+                error!("Persistence background task failed: {}", e);
+            }
+        }
+
+        // Whatever the running fsync policy, the last writes reach the disk before exit
+        // unless fsync_on_shutdown was turned off
+        match self.aof {
+            Some(_) if self.config.fsync_on_shutdown => self.sync_aof().await,
+            Some(_) => self.flush_aof().await,
+            None => Ok(()),
+        }
+    }
+
+    // Last-resort AOF flush from synchronous code, e.g. a panic hook
+    // Skipped when the AOF is locked (a rewrite is swapping it), waiting could deadlock
+    pub fn flush_blocking(&self, timeout: Duration) -> StorageResult<()> {
+        match self.aof {
+            Some(ref aof) => match aof.try_read() {
+                Ok(aof) => aof.flush_blocking(timeout),
+                Err(_) => Err(StorageError::Persistence("AOF is busy".to_string())),
+            },
+            None => Ok(()),
+        }
+    }
+
+    // Whether stop() has been called
+    pub fn is_stopped(&self) -> bool {
+        self.shutdown.is_cancelled()
+    }
+
+    /// Report unhealthy once the AOF writer has lost writes
+    pub async fn health_check(&self) -> StorageResult<()> {
+        if let Some(ref aof) = self.aof
+            && aof.read().await.is_failed()
+        {
+            return Err(StorageError::Persistence(
+                "AOF writer failed, writes are not being persisted".to_string(),
+            ));
+        }
+
+        Ok(())
     }
 
     /// Get persistence statistics
@@ -217,7 +954,7 @@ impl PersistenceManager {
             None
         };
 
-        let snapshot_count = if let Some(ref snapshotter) = self.snapshotter {
+        let snapshot_count = if let Some(snapshotter) = self.current_snapshotter() {
             snapshotter
                 .list_snapshots()
                 .await
@@ -233,6 +970,22 @@ impl PersistenceManager {
             aof_stats,
             snapshot_enabled: self.snapshotter.is_some(),
             snapshot_count,
+            aof_rewrite_in_progress: self.is_aof_rewrite_in_progress(),
+            aof_rewrites_completed: self.aof_rewrites_completed(),
+            aof_last_rewrite_error: self.aof_last_rewrite_error(),
         }
     }
 }
+
+// Dropping without stop() still ends the background tasks; the AOF flushes on its own drop
+impl Drop for PersistenceManager {
+    fn drop(&mut self) {
+        self.shutdown.cancel();
+    }
+}
+
+// Uniform delay in [0, max], seeded from the std hasher's per-process random keys
+fn random_delay(max: Duration) -> Duration {
+    let seed = RandomState::new().hash_one(std::time::SystemTime::now());
+    Duration::from_millis(seed % (max.as_millis() as u64 + 1))
+}
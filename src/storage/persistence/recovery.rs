@@ -1,13 +1,14 @@
-use std::collections::HashMap;
+use std::path::PathBuf;
 
 use chrono::{DateTime, Utc};
+use futures_util::TryStreamExt;
 use tracing::{error, info, instrument, warn};
 
 use crate::storage::{
-    StorageEngine, StorageResult,
+    StorageEngine, StorageError, StorageResult, now_millis,
     persistence::{
         aof::{AppendOnlyFile, Operation},
-        snapshot::Snapshotter,
+        snapshot::{Snapshot, SnapshotData, Snapshotter},
     },
 };
 
@@ -15,36 +16,81 @@ use crate::storage::{
 pub struct RecoveryManager {
     aof: Option<AppendOnlyFile>,
     snapshotter: Option<Snapshotter>,
+    snapshot_required: bool, // Fail instead of falling back to the AOF alone
+    restore_from: Option<PathBuf>, // Snapshot file loaded instead of the latest one
 }
 
 #[derive(Debug, Default, Clone)]
 pub struct RecoveryStats {
     pub snapshot_loaded: bool,
+    pub snapshot_error: Option<String>, // Why the snapshot was skipped, if it failed to load
     pub snapshot_timestamp: Option<DateTime<Utc>>,
     pub keys_from_snapshot: usize,
+    pub keys_expired_in_snapshot: usize, // Skipped: their expiry passed before the restart
     pub aof_operations_total: usize,
     pub aof_operations_replayed: usize,
+    pub aof_operations_skipped: usize, // Entries that failed to apply and were left out
     pub final_key_count: usize,
 }
 
 impl RecoveryManager {
     pub fn new(aof: Option<AppendOnlyFile>, snapshotter: Option<Snapshotter>) -> Self {
-        Self { aof, snapshotter }
+        Self {
+            aof,
+            snapshotter,
+            snapshot_required: false,
+            restore_from: None,
+        }
+    }
+
+    // Load this snapshot file instead of the latest one, whether or not a snapshotter is
+    // configured; it is verified first and any failure to load it is fatal
+    pub fn with_restore_from(mut self, path: Option<PathBuf>) -> Self {
+        self.restore_from = path;
+        self
+    }
+
+    // Treat a snapshot that fails to load as fatal
+    pub fn with_snapshot_required(mut self, required: bool) -> Self {
+        self.snapshot_required = required;
+        self
     }
 
     // Recover database state
     // Strategy: Load snapshot (if exists) + replay AOF from snapshot timestamp
+    // A snapshot that fails to load is skipped and the AOF replayed on its own, unless
+    // the snapshot is required or named by restore_from
     #[instrument(skip(self, storage))]
     pub async fn recover(&self, storage: &dyn StorageEngine) -> StorageResult<RecoveryStats> {
+        self.recover_databases(&[storage]).await
+    }
+
+    // Same as recover, with one engine per database indexed by database number
+    // Fails if the persisted data references a database that isn't configured
+    #[instrument(skip(self, databases), fields(databases = databases.len()))]
+    pub async fn recover_databases(
+        &self,
+        databases: &[&dyn StorageEngine],
+    ) -> StorageResult<RecoveryStats> {
         info!("Starting database recovery...");
 
         let mut stats = RecoveryStats::default();
 
-        // Step 1: Load snapshot if available
-        if let Some(ref snapshotter) = self.snapshotter {
-            info!("Snapshotter is available, attempting to load...");
+        // Step 1: Load the snapshot to restore, or the latest one if available
+        let loaded = match (&self.restore_from, &self.snapshotter) {
+            (Some(path), _) => {
+                info!("Restoring from snapshot file {}", path.display());
+                Some(Self::load_restore(path).await)
+            }
+            (None, Some(snapshotter)) => {
+                info!("Snapshotter is available, attempting to load...");
+                Some(snapshotter.load_latest_snapshot().await)
+            }
+            (None, None) => None,
+        };
 
-            match snapshotter.load_latest_snapshot().await {
+        if let Some(loaded) = loaded {
+            match loaded {
                 Ok(Some(snapshot)) => {
                     info!(
                         "Restoring from snapshot: {} keys",
@@ -53,22 +99,30 @@ impl RecoveryManager {
 
                     let snapshot_timestamp = snapshot.metadata.timestamp;
                     stats.snapshot_loaded = true;
-                    stats.keys_from_snapshot = snapshot.data.len();
 
                     // Restore data from snapshot
-                    for (key, value) in snapshot.data {
-                        storage.set(&key, value).await?;
+                    Self::restore(databases[0], snapshot.data, &mut stats).await?;
+                    for (db, entries) in snapshot.databases {
+                        let storage = Self::database(databases, db)?;
+                        Self::restore(storage, entries, &mut stats).await?;
                     }
-                    info!("Snapshot restored: {} keys", stats.keys_from_snapshot);
+                    info!(
+                        "Snapshot restored: {} keys, {} already expired",
+                        stats.keys_from_snapshot, stats.keys_expired_in_snapshot
+                    );
                     stats.snapshot_timestamp = Some(snapshot_timestamp);
                 }
                 Ok(None) => {
                     info!("⚠️ No snapshot found, will replay full AOF");
                 }
-                Err(e) => {
+                Err(e) if self.snapshot_required || self.restore_from.is_some() => {
                     error!("❌ Failed to load snapshot: {:?}", e);
                     return Err(e);
                 }
+                Err(e) => {
+                    warn!("⚠️ Failed to load snapshot, will replay full AOF: {}", e);
+                    stats.snapshot_error = Some(e.to_string());
+                }
             }
         } else {
             info!("⚠️ No snapshotter configured")
@@ -81,46 +135,142 @@ impl RecoveryManager {
             stats.aof_operations_total = operations.len();
 
             for operation in operations {
-                match operation {
-                    Operation::Put { key, value } => {
-                        storage.set(&key, value).await?;
-                        stats.aof_operations_replayed += 1;
+                let (db, operation) = operation.into_database();
+                let storage = Self::database(databases, db)?;
+
+                // An entry that fails to apply is skipped rather than failing startup; only
+                // writes that succeeded are logged, so this is an AOF from before that held
+                let result = match operation {
+                    Operation::Put { key, value } => storage.set(&key, value).await,
+                    Operation::PutExpiring {
+                        key,
+                        value,
+                        at_millis,
+                    } => match storage.set(&key, value).await {
+                        Ok(()) => storage.expire_at(&key, at_millis).await.map(drop),
+                        Err(e) => Err(e),
+                    },
+                    Operation::Update { key, value } => storage
+                        .update(&key, Box::new(move |_| Ok(Some(value))))
+                        .await
+                        .map(drop),
+                    Operation::Delete { key } => storage.delete(&key).await.map(drop),
+                    Operation::SetRange { key, offset, value } => {
+                        storage.set_range(&key, offset, &value).await.map(drop)
+                    }
+                    Operation::SetBit { key, offset, bit } => {
+                        storage.set_bit(&key, offset, bit).await.map(drop)
+                    }
+                    Operation::SetAdd { key, members } => {
+                        storage.add_members(&key, &members).await.map(drop)
+                    }
+                    Operation::SetRemove { key, members } => {
+                        storage.remove_members(&key, &members).await.map(drop)
+                    }
+                    Operation::ListPush { key, items, front } => {
+                        storage.push_items(&key, &items, front).await.map(drop)
+                    }
+                    Operation::ListPop { key } => storage.pop_front(&key).await.map(drop),
+                    Operation::ExpireAt { key, at_millis } => {
+                        storage.expire_at(&key, at_millis).await.map(drop)
                     }
-                    Operation::Delete { key } => {
-                        storage.delete(&key).await?;
-                        stats.aof_operations_replayed += 1
+                    Operation::Flush => storage.clear().await.map(drop),
+                    Operation::Select { .. } => {
+                        return Err(StorageError::Persistence(
+                            "Nested SELECT in AOF entry".to_string(),
+                        ));
+                    }
+                };
+
+                match result {
+                    Ok(()) => stats.aof_operations_replayed += 1,
+                    Err(e) => {
+                        warn!(
+                            "Skipping AOF entry for database {} that failed to apply: {}",
+                            db, e
+                        );
+                        stats.aof_operations_skipped += 1;
                     }
                 }
             }
 
             info!(
-                "AOF replay complete: {} operations",
-                stats.aof_operations_replayed
+                "AOF replay complete: {} operations, {} skipped",
+                stats.aof_operations_replayed, stats.aof_operations_skipped
             );
         }
         // Final stats
-        let final_stats = storage.stats().await?;
-        stats.final_key_count = final_stats.total_keys;
+        for storage in databases {
+            stats.final_key_count += storage.stats().await?.total_keys;
+        }
 
         info!("Recovery complete: {} total keys", stats.final_key_count);
 
         Ok(stats)
     }
 
+    // Snapshot named by restore_from, checked against its checksum before anything is loaded
+    async fn load_restore(path: &std::path::Path) -> StorageResult<Option<Snapshot>> {
+        let snapshot = Snapshotter::load_file(path).await?;
+        if !snapshot.verify()? {
+            warn!(
+                "Snapshot {} predates checksums, only its counts were checked",
+                path.display()
+            );
+        }
+        Ok(Some(snapshot))
+    }
+
+    // Load one database's snapshot entries with their expiry, dropping keys that expired
+    // while the server was down
+    async fn restore(
+        storage: &dyn StorageEngine,
+        entries: SnapshotData,
+        stats: &mut RecoveryStats,
+    ) -> StorageResult<()> {
+        let now = now_millis();
+
+        for (key, (value, expires_at)) in entries {
+            if expires_at.is_some_and(|at| at <= now) {
+                stats.keys_expired_in_snapshot += 1;
+                continue;
+            }
+
+            storage.set(&key, value).await?;
+            if let Some(at) = expires_at {
+                storage.expire_at(&key, at).await?;
+            }
+            stats.keys_from_snapshot += 1;
+        }
+
+        Ok(())
+    }
+
+    fn database<'a>(
+        databases: &[&'a dyn StorageEngine],
+        db: usize,
+    ) -> StorageResult<&'a dyn StorageEngine> {
+        databases.get(db).copied().ok_or_else(|| {
+            StorageError::Persistence(format!(
+                "persisted data references database {} but only {} are configured",
+                db,
+                databases.len()
+            ))
+        })
+    }
+
     // Create snapshot from current state
     pub async fn create_snapshot(&self, storage: &dyn StorageEngine) -> StorageResult<()> {
         if let Some(ref snapshotter) = self.snapshotter {
             info!("Creating manual snapshot...");
 
             // Get all data from storage
-            let all_keys = storage.scan("").await?;
-            let mut data = HashMap::new();
-
-            for key in all_keys {
-                if let Some(value) = storage.get(&key).await? {
-                    data.insert(key, value);
-                }
-            }
+            let data: SnapshotData = storage
+                .iter_with_expiry()
+                .await?
+                .map_ok(|(key, value, expires_at)| (key, (value, expires_at)))
+                .try_collect()
+                .await?;
 
             snapshotter.create_snapshot(data).await?;
 
@@ -143,6 +293,9 @@ impl RecoveryStats {
                 info!("    • Timestamp: {}", ts);
             }
             info!("    • Keys restored: {}", self.keys_from_snapshot);
+            info!("    • Already expired: {}", self.keys_expired_in_snapshot);
+        } else if let Some(ref e) = self.snapshot_error {
+            warn!("  Snapshot: Failed to load ({}), rebuilt from AOF only", e);
         } else {
             info!("  Snapshot: Not loaded");
         }
@@ -1,6 +1,10 @@
 use std::{
-    collections::HashMap,
+    collections::{BTreeMap, HashMap},
     path::{Path, PathBuf},
+    sync::{
+        Arc,
+        atomic::{AtomicU64, Ordering},
+    },
 };
 
 use chrono::{DateTime, Utc};
@@ -11,38 +15,430 @@ use tokio::{
 };
 use tracing::{debug, error, info, instrument, warn};
 
-use crate::storage::{StorageError, StorageResult};
+use crate::{
+    config::SnapshotEncoding,
+    storage::{StorageError, StorageResult},
+};
+
+// On-disk snapshot layout version, bumped on any incompatible change
+// Independent of the crate version so releases that keep the layout stay compatible
+pub const SNAPSHOT_FORMAT_VERSION: u32 = 4;
+
+// Files start with MAGIC followed by the format version (u32 LE); older files have no header
+const SNAPSHOT_MAGIC: &[u8; 4] = b"BLZS";
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct SnapshotMetadata {
-    pub version: String,
-    pub timestamp: DateTime<Utc>,
+    pub format_version: u32,
+    pub version: String,          // Crate version that wrote the snapshot
+    pub timestamp: DateTime<Utc>, // Informational only, the wall clock can jump backward
+    #[serde(default)]
+    pub sequence: u64, // Orders snapshots within a directory, 0 for ones migrated from format 3 and older
     pub total_keys: usize,
     pub total_size: usize,
     pub checksum: Option<String>,
 }
 
+// One database's keys, each with its value and absolute expiry in unix millis
+pub type SnapshotData = HashMap<String, (Vec<u8>, Option<u64>)>;
+
 // Complete database snapshot
 #[derive(Debug, Serialize, Deserialize)]
 pub struct Snapshot {
     pub metadata: SnapshotMetadata,
-    pub data: HashMap<String, Vec<u8>>,
+    pub data: SnapshotData,                       // Database 0
+    pub databases: BTreeMap<usize, SnapshotData>, // Other non-empty databases
 }
 
 impl Snapshot {
-    pub fn new(data: HashMap<String, Vec<u8>>) -> Self {
-        let total_size: usize = data.iter().map(|(k, v)| k.len() + v.len()).sum();
+    pub fn new(data: SnapshotData) -> Self {
+        Self::with_databases(data, BTreeMap::new())
+    }
+
+    // Snapshot of every database, indexed by database number
+    pub fn from_databases(databases: Vec<SnapshotData>) -> Self {
+        let mut databases = databases.into_iter();
+        let data = databases.next().unwrap_or_default();
+        let others = databases
+            .enumerate()
+            .filter(|(_, entries)| !entries.is_empty())
+            .map(|(index, entries)| (index + 1, entries))
+            .collect();
+
+        Self::with_databases(data, others)
+    }
+
+    fn with_databases(data: SnapshotData, databases: BTreeMap<usize, SnapshotData>) -> Self {
+        let (total_keys, total_size) = Self::totals(&data, &databases);
 
         Self {
             metadata: SnapshotMetadata {
+                format_version: SNAPSHOT_FORMAT_VERSION,
                 version: env!("CARGO_PKG_VERSION").to_string(),
                 timestamp: Utc::now(),
-                total_keys: data.len(),
+                sequence: 0, // Assigned when saved
+                total_keys,
                 total_size,
-                checksum: None, // TODO: Implement checksum
+                checksum: Some(Self::compute_checksum_databases(&data, &databases)),
             },
             data,
+            databases,
+        }
+    }
+
+    // Keys and bytes across every database
+    fn totals(data: &SnapshotData, databases: &BTreeMap<usize, SnapshotData>) -> (usize, usize) {
+        std::iter::once(data)
+            .chain(databases.values())
+            .flat_map(|entries| entries.iter())
+            .fold((0, 0), |(keys, size), (k, (v, _))| {
+                (keys + 1, size + k.len() + v.len())
+            })
+    }
+
+    // CRC32 over entries in key order, so it doesn't depend on HashMap iteration order
+    pub fn compute_checksum(data: &SnapshotData) -> String {
+        Self::compute_checksum_databases(data, &BTreeMap::new())
+    }
+
+    // Database 0 is hashed exactly like compute_checksum, other databases follow in order
+    // prefixed by their number, so single-database checksums are unchanged
+    // Only keys with an expiry hash it, so snapshots migrated from older formats still verify
+    pub fn compute_checksum_databases(
+        data: &SnapshotData,
+        databases: &BTreeMap<usize, SnapshotData>,
+    ) -> String {
+        fn update(crc: &mut flate2::Crc, data: &SnapshotData) {
+            let mut keys: Vec<&String> = data.keys().collect();
+            keys.sort();
+
+            for key in keys {
+                let (value, expires_at) = &data[key];
+                crc.update(&(key.len() as u64).to_le_bytes());
+                crc.update(key.as_bytes());
+                crc.update(&(value.len() as u64).to_le_bytes());
+                crc.update(value);
+                if let Some(at) = expires_at {
+                    crc.update(&at.to_le_bytes());
+                }
+            }
+        }
+
+        let mut crc = flate2::Crc::new();
+        update(&mut crc, data);
+        for (db, entries) in databases {
+            crc.update(&(*db as u64).to_le_bytes());
+            update(&mut crc, entries);
+        }
+
+        format!("crc32:{:08x}", crc.sum())
+    }
+
+    // Check the data against the recorded checksum and counts
+    // Returns false when the snapshot predates checksums and only counts could be checked
+    pub fn verify(&self) -> StorageResult<bool> {
+        let (total_keys, total_size) = Self::totals(&self.data, &self.databases);
+        if total_keys != self.metadata.total_keys || total_size != self.metadata.total_size {
+            return Err(StorageError::Persistence(format!(
+                "snapshot metadata mismatch: {} keys/{} bytes recorded, {} keys/{} bytes found",
+                self.metadata.total_keys, self.metadata.total_size, total_keys, total_size
+            )));
+        }
+
+        let Some(ref expected) = self.metadata.checksum else {
+            return Ok(false);
+        };
+
+        let actual = Self::compute_checksum_databases(&self.data, &self.databases);
+        if *expected != actual {
+            return Err(StorageError::Persistence(format!(
+                "snapshot checksum mismatch: expected {}, got {}",
+                expected, actual
+            )));
+        }
+
+        Ok(true)
+    }
+}
+
+// Values without expiry, as stored by formats 0 to 2
+type LegacyData = HashMap<String, Vec<u8>>;
+
+fn without_expiry(data: LegacyData) -> SnapshotData {
+    data.into_iter()
+        .map(|(key, value)| (key, (value, None)))
+        .collect()
+}
+
+// Format 0: headerless files written before format versioning, metadata without format_version
+#[derive(Deserialize)]
+struct SnapshotMetadataV0 {
+    version: String,
+    timestamp: DateTime<Utc>,
+    total_keys: usize,
+    total_size: usize,
+    checksum: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct SnapshotV0 {
+    metadata: SnapshotMetadataV0,
+    data: LegacyData,
+}
+
+// Format 1: single database
+#[derive(Deserialize)]
+struct SnapshotV1 {
+    metadata: SnapshotMetadataV3,
+    data: LegacyData,
+}
+
+// Formats 1 to 3: metadata without a sequence number
+#[derive(Deserialize)]
+struct SnapshotMetadataV3 {
+    version: String,
+    timestamp: DateTime<Utc>,
+    total_keys: usize,
+    total_size: usize,
+    checksum: Option<String>,
+}
+
+impl From<SnapshotMetadataV3> for SnapshotMetadata {
+    fn from(old: SnapshotMetadataV3) -> Self {
+        Self {
+            format_version: SNAPSHOT_FORMAT_VERSION,
+            version: old.version,
+            timestamp: old.timestamp,
+            sequence: 0,
+            total_keys: old.total_keys,
+            total_size: old.total_size,
+            checksum: old.checksum,
+        }
+    }
+}
+
+// Format 3: every database with expiry, no sequence number
+#[derive(Deserialize)]
+struct SnapshotV3 {
+    metadata: SnapshotMetadataV3,
+    data: SnapshotData,
+    databases: BTreeMap<usize, SnapshotData>,
+}
+
+impl From<SnapshotV3> for Snapshot {
+    fn from(old: SnapshotV3) -> Self {
+        Self {
+            metadata: old.metadata.into(),
+            data: old.data,
+            databases: old.databases,
+        }
+    }
+}
+
+// Format 2: every database, no expiry
+#[derive(Deserialize)]
+struct SnapshotV2 {
+    metadata: SnapshotMetadataV3,
+    data: LegacyData,
+    databases: BTreeMap<usize, LegacyData>,
+}
+
+impl From<SnapshotV2> for Snapshot {
+    fn from(old: SnapshotV2) -> Self {
+        Self {
+            metadata: old.metadata.into(),
+            data: without_expiry(old.data),
+            databases: old
+                .databases
+                .into_iter()
+                .map(|(db, data)| (db, without_expiry(data)))
+                .collect(),
+        }
+    }
+}
+
+impl From<SnapshotV1> for Snapshot {
+    fn from(old: SnapshotV1) -> Self {
+        Self {
+            metadata: old.metadata.into(),
+            data: without_expiry(old.data),
+            databases: BTreeMap::new(),
+        }
+    }
+}
+
+impl From<SnapshotV0> for Snapshot {
+    fn from(old: SnapshotV0) -> Self {
+        Self {
+            metadata: SnapshotMetadata {
+                format_version: SNAPSHOT_FORMAT_VERSION,
+                version: old.metadata.version,
+                timestamp: old.metadata.timestamp,
+                sequence: 0,
+                total_keys: old.metadata.total_keys,
+                total_size: old.metadata.total_size,
+                checksum: old.metadata.checksum,
+            },
+            data: without_expiry(old.data),
+            databases: BTreeMap::new(),
+        }
+    }
+}
+
+// Split a snapshot file into its format version and bincode payload
+fn split_header(buffer: &[u8]) -> (u32, &[u8]) {
+    match buffer.strip_prefix(SNAPSHOT_MAGIC.as_slice()) {
+        Some(rest) if rest.len() >= 4 => {
+            let (version, payload) = rest.split_at(4);
+            let version = u32::from_le_bytes(version.try_into().expect("4-byte slice"));
+            (version, payload)
+        }
+        _ => (0, buffer),
+    }
+}
+
+// Migration hook: decode any supported format and upgrade it to the current Snapshot
+// When bumping SNAPSHOT_FORMAT_VERSION, keep the old layout as a private `SnapshotVN`
+// struct with a `From<SnapshotVN> for Snapshot` impl and add an arm for it here
+fn decode_snapshot(version: u32, payload: &[u8]) -> StorageResult<Snapshot> {
+    let config = bincode::config::standard();
+
+    match version {
+        SNAPSHOT_FORMAT_VERSION => {
+            let (snapshot, _) = bincode::serde::decode_from_slice::<Snapshot, _>(payload, config)
+                .map_err(StorageError::Deserialization)?;
+            Ok(snapshot)
+        }
+        3 => {
+            let (snapshot, _) = bincode::serde::decode_from_slice::<SnapshotV3, _>(payload, config)
+                .map_err(StorageError::Deserialization)?;
+            info!("Migrated snapshot from format version 3");
+            Ok(snapshot.into())
+        }
+        2 => {
+            let (snapshot, _) = bincode::serde::decode_from_slice::<SnapshotV2, _>(payload, config)
+                .map_err(StorageError::Deserialization)?;
+            info!("Migrated snapshot from format version 2");
+            Ok(snapshot.into())
+        }
+        1 => {
+            let (snapshot, _) = bincode::serde::decode_from_slice::<SnapshotV1, _>(payload, config)
+                .map_err(StorageError::Deserialization)?;
+            info!("Migrated snapshot from format version 1");
+            Ok(snapshot.into())
+        }
+        0 => {
+            let (snapshot, _) = bincode::serde::decode_from_slice::<SnapshotV0, _>(payload, config)
+                .map_err(StorageError::Deserialization)?;
+            info!("Migrated snapshot from format version 0");
+            Ok(snapshot.into())
+        }
+        other => Err(StorageError::Persistence(format!(
+            "unsupported snapshot format version {}",
+            other
+        ))),
+    }
+}
+
+// How a Snapshot is written to and read back from a file
+// Loading tries every format's `detect`, so files of any format can be mixed in one directory
+pub trait SnapshotFormat: Send + Sync + std::fmt::Debug {
+    fn name(&self) -> &'static str;
+
+    // Whether a file starting with these bytes was written in this format
+    fn detect(&self, buffer: &[u8]) -> bool;
+
+    fn encode(&self, snapshot: &Snapshot) -> StorageResult<Vec<u8>>;
+
+    fn decode(&self, buffer: &[u8]) -> StorageResult<Snapshot>;
+}
+
+// MAGIC and version header followed by a bincode payload, the default
+// Headerless files from before format versioning are bincode too, so this is the fallback
+#[derive(Debug, Clone, Copy)]
+pub struct BincodeFormat;
+
+impl SnapshotFormat for BincodeFormat {
+    fn name(&self) -> &'static str {
+        "bincode"
+    }
+
+    fn detect(&self, buffer: &[u8]) -> bool {
+        buffer.starts_with(SNAPSHOT_MAGIC)
+    }
+
+    fn encode(&self, snapshot: &Snapshot) -> StorageResult<Vec<u8>> {
+        // Serialize snapshot using the serde adapter so serde::Serialize is sufficient
+        let payload = bincode::serde::encode_to_vec(snapshot, bincode::config::standard())
+            .map_err(StorageError::Serialization)?;
+
+        let mut serialized = Vec::with_capacity(SNAPSHOT_MAGIC.len() + 4 + payload.len());
+        serialized.extend_from_slice(SNAPSHOT_MAGIC);
+        serialized.extend_from_slice(&SNAPSHOT_FORMAT_VERSION.to_le_bytes());
+        serialized.extend_from_slice(&payload);
+        Ok(serialized)
+    }
+
+    fn decode(&self, buffer: &[u8]) -> StorageResult<Snapshot> {
+        let (format_version, payload) = split_header(buffer);
+        decode_snapshot(format_version, payload)
+    }
+}
+
+// A plain JSON document of the Snapshot, readable with any JSON tool
+// Introduced at format version 3, which only lacks the sequence number (defaulted to 0)
+#[derive(Debug, Clone, Copy)]
+pub struct JsonFormat;
+
+impl SnapshotFormat for JsonFormat {
+    fn name(&self) -> &'static str {
+        "json"
+    }
+
+    fn detect(&self, buffer: &[u8]) -> bool {
+        buffer.trim_ascii_start().starts_with(b"{")
+    }
+
+    fn encode(&self, snapshot: &Snapshot) -> StorageResult<Vec<u8>> {
+        serde_json::to_vec_pretty(snapshot)
+            .map_err(|e| StorageError::Persistence(format!("JSON snapshot encoding: {}", e)))
+    }
+
+    fn decode(&self, buffer: &[u8]) -> StorageResult<Snapshot> {
+        let mut snapshot: Snapshot = serde_json::from_slice(buffer)
+            .map_err(|e| StorageError::Persistence(format!("invalid JSON snapshot: {}", e)))?;
+
+        match snapshot.metadata.format_version {
+            SNAPSHOT_FORMAT_VERSION => {}
+            3 => snapshot.metadata.format_version = SNAPSHOT_FORMAT_VERSION,
+            other => {
+                return Err(StorageError::Persistence(format!(
+                    "unsupported snapshot format version {}",
+                    other
+                )));
+            }
         }
+
+        Ok(snapshot)
+    }
+}
+
+// Checked in order, the last one takes whatever no other format claims
+const SNAPSHOT_FORMATS: &[&dyn SnapshotFormat] = &[&JsonFormat, &BincodeFormat];
+
+fn detect_format(buffer: &[u8]) -> &'static dyn SnapshotFormat {
+    SNAPSHOT_FORMATS
+        .iter()
+        .copied()
+        .find(|format| format.detect(buffer))
+        .unwrap_or(&BincodeFormat)
+}
+
+// The format new snapshots are written in for a configured encoding
+pub fn snapshot_format(encoding: SnapshotEncoding) -> &'static dyn SnapshotFormat {
+    match encoding {
+        SnapshotEncoding::Bincode => &BincodeFormat,
+        SnapshotEncoding::Json => &JsonFormat,
     }
 }
 
@@ -50,6 +446,8 @@ impl Snapshot {
 #[derive(Debug, Clone)]
 pub struct Snapshotter {
     snapshot_dir: PathBuf,
+    format: &'static dyn SnapshotFormat, // Used for writing only
+    last_sequence: Arc<AtomicU64>,       // Highest sequence handed out so far
 }
 
 impl Snapshotter {
@@ -62,29 +460,123 @@ impl Snapshotter {
 
         info!("Snapshotter initialized at: {}", snapshot_dir.display());
 
-        Ok(Self { snapshot_dir })
+        Ok(Self {
+            snapshot_dir,
+            format: &BincodeFormat,
+            last_sequence: Arc::new(AtomicU64::new(0)),
+        })
+    }
+
+    // Write new snapshots in this format instead of bincode
+    pub fn with_format(mut self, format: &'static dyn SnapshotFormat) -> Self {
+        self.format = format;
+        self
+    }
+
+    pub fn snapshot_dir(&self) -> &Path {
+        &self.snapshot_dir
+    }
+
+    // A snapshotter writing to `new_dir` in the same format, seeded with a copy of every
+    // snapshot here when `copy` is set. Refuses a directory that already holds snapshots,
+    // and removes what it copied when the copy fails
+    pub async fn relocate(&self, new_dir: &Path, copy: bool) -> StorageResult<Self> {
+        let relocated = Self::new(new_dir)?.with_format(self.format);
+        if !relocated.list_snapshots().await?.is_empty()
+            || tokio::fs::try_exists(new_dir.join("snapshot-latest.rdb")).await?
+        {
+            return Err(StorageError::Persistence(format!(
+                "{} already holds snapshots",
+                new_dir.display()
+            )));
+        }
+
+        if copy {
+            let mut copied = Vec::new();
+            if let Err(e) = self.copy_snapshots(new_dir, &mut copied).await {
+                for path in copied {
+                    let _ = tokio::fs::remove_file(path).await;
+                }
+                return Err(e);
+            }
+        }
+
+        Ok(relocated)
+    }
+
+    async fn copy_snapshots(&self, new_dir: &Path, copied: &mut Vec<PathBuf>) -> StorageResult<()> {
+        let mut entries = tokio::fs::read_dir(&self.snapshot_dir).await?;
+        while let Some(entry) = entries.next_entry().await? {
+            let path = entry.path();
+            if path.extension().and_then(|s| s.to_str()) != Some("rdb") {
+                continue;
+            }
+
+            let target = new_dir.join(entry.file_name());
+            copied.push(target.clone());
+            tokio::fs::copy(&path, &target).await?;
+            File::open(&target).await?.sync_all().await?;
+        }
+        Ok(())
+    }
+
+    // Undo relocate() for a snapshotter nothing was switched to: its directory held no
+    // snapshots before, so every one in it now is a copy
+    pub async fn discard_relocated(&self) {
+        let Ok(mut entries) = tokio::fs::read_dir(&self.snapshot_dir).await else {
+            return;
+        };
+        while let Ok(Some(entry)) = entries.next_entry().await {
+            let path = entry.path();
+            if path.extension().and_then(|s| s.to_str()) == Some("rdb") {
+                let _ = tokio::fs::remove_file(&path).await;
+            }
+        }
     }
 
     // Create snapshot from current data
     #[instrument(skip(self, data))]
-    pub async fn create_snapshot(&self, data: HashMap<String, Vec<u8>>) -> StorageResult<PathBuf> {
-        let snapshot = Snapshot::new(data);
+    pub async fn create_snapshot(&self, data: SnapshotData) -> StorageResult<PathBuf> {
+        self.save(Snapshot::new(data)).await
+    }
 
+    // Create snapshot of every database, indexed by database number
+    #[instrument(skip(self, databases))]
+    pub async fn create_snapshot_databases(
+        &self,
+        databases: Vec<SnapshotData>,
+    ) -> StorageResult<PathBuf> {
+        self.save(Snapshot::from_databases(databases)).await
+    }
+
+    async fn save(&self, mut snapshot: Snapshot) -> StorageResult<PathBuf> {
         info!(
             "Creating snapshot: {} keys, {} bytes",
             snapshot.metadata.total_keys, snapshot.metadata.total_size
         );
 
-        // Generate filename with timestamp
+        // Order by a sequence that only grows, never by the wall clock, so a clock jumping
+        // back can neither misorder retention nor reuse an older snapshot's file name
+        let on_disk = self.max_sequence_on_disk().await?;
+        self.last_sequence.fetch_max(on_disk, Ordering::SeqCst);
+        snapshot.metadata.sequence = self.last_sequence.fetch_add(1, Ordering::SeqCst) + 1;
+
+        // Generate filename with sequence and timestamp, the same for every format since
+        // loading detects it. The zero-padded sequence keeps name order equal to sequence order
         let timestamp = snapshot.metadata.timestamp.format("%Y%m%d-%H%M%S");
-        let filename = format!("snapshot-{}.rdb", timestamp);
+        let filename = format!(
+            "snapshot-{:010}-{}.rdb",
+            snapshot.metadata.sequence, timestamp
+        );
         let filepath = self.snapshot_dir.join(&filename);
 
-        // Serialize snapshot using the serde adapter so serde::Serialize is sufficient
-        let serialized = bincode::serde::encode_to_vec(&snapshot, bincode::config::standard())
-            .map_err(|e| StorageError::Serialization(e))?;
+        let serialized = self.format.encode(&snapshot)?;
 
-        debug!("Snapshot serialized: {} bytes", serialized.len());
+        debug!(
+            "Snapshot serialized as {}: {} bytes",
+            self.format.name(),
+            serialized.len()
+        );
 
         // Write to temporary file first (atomic write)
         let temp_path = filepath.with_extension("tmp");
@@ -105,6 +597,34 @@ impl Snapshotter {
         Ok(filepath)
     }
 
+    // Highest sequence in the snapshot file names here, 0 when there are none
+    // Read from names rather than files, so it stays cheap however large snapshots get
+    async fn max_sequence_on_disk(&self) -> StorageResult<u64> {
+        let mut entries = tokio::fs::read_dir(&self.snapshot_dir).await?;
+        let mut max = 0;
+
+        while let Some(entry) = entries.next_entry().await? {
+            let name = entry.file_name();
+            let Some(sequence) = name.to_str().and_then(Self::sequence_from_name) else {
+                continue;
+            };
+            max = max.max(sequence);
+        }
+
+        Ok(max)
+    }
+
+    // `snapshot-<sequence>-<date>-<time>.rdb`, older `snapshot-<date>-<time>.rdb` have none
+    fn sequence_from_name(name: &str) -> Option<u64> {
+        let stem = name.strip_prefix("snapshot-")?.strip_suffix(".rdb")?;
+        let mut parts = stem.split('-');
+        let sequence = parts.next()?;
+        if parts.count() != 2 || sequence.len() != 10 {
+            return None;
+        }
+        sequence.parse().ok()
+    }
+
     // Update symlink to latest snapshot
     async fn update_latest_symlink(&self, filepath: &Path) -> StorageResult<()> {
         let latest_path = self.snapshot_dir.join("snapshot-latest.rdb");
@@ -121,6 +641,27 @@ impl Snapshotter {
         Ok(())
     }
 
+    // Resolve a snapshot file name inside the snapshot directory (None = latest)
+    // Paths escaping the directory are refused, clients only get to pick among snapshots
+    pub fn snapshot_path(&self, name: Option<&str>) -> StorageResult<PathBuf> {
+        let Some(name) = name else {
+            return Ok(self.snapshot_dir.join("snapshot-latest.rdb"));
+        };
+
+        let relative = Path::new(name);
+        if !relative
+            .components()
+            .all(|component| matches!(component, std::path::Component::Normal(_)))
+        {
+            return Err(StorageError::Persistence(format!(
+                "snapshot path must be a file name inside the snapshot directory: {}",
+                name
+            )));
+        }
+
+        Ok(self.snapshot_dir.join(relative))
+    }
+
     // Load latest snapshot
     #[instrument(skip(self))]
     pub async fn load_latest_snapshot(&self) -> StorageResult<Option<Snapshot>> {
@@ -146,8 +687,11 @@ impl Snapshotter {
         &self,
         path: P,
     ) -> StorageResult<Snapshot> {
-        let path = path.as_ref();
+        Self::load_file(path.as_ref()).await
+    }
 
+    // Load a snapshot file from anywhere, without a snapshot directory (restores)
+    pub async fn load_file(path: &Path) -> StorageResult<Snapshot> {
         info!("Loading snapshot from: {}", path.display());
 
         let mut file = File::open(path).await?;
@@ -156,9 +700,9 @@ impl Snapshotter {
 
         debug!("Snapshot file read: {} bytes", buffer.len());
 
-        let (snapshot, _) =
-            bincode::serde::decode_from_slice::<Snapshot, _>(&buffer, bincode::config::standard())
-                .map_err(|e| StorageError::Deserialization(e))?;
+        let format = detect_format(&buffer);
+        debug!("Snapshot format detected: {}", format.name());
+        let snapshot = format.decode(&buffer)?;
 
         info!(
             "Snapshot loaded: {} keys from {}",
@@ -172,24 +716,18 @@ impl Snapshotter {
     async fn cleanup_old_snapshots(&self) -> StorageResult<()> {
         const KEEP_SNAPSHOTS: usize = 5;
 
-        let mut snapshots = self.list_snapshots().await?;
+        let mut snapshots = self.snapshot_files().await?;
 
         if snapshots.len() <= KEEP_SNAPSHOTS {
             return Ok(());
         }
 
-        // Sort by timestamp (oldest first)
-        snapshots.sort_by(|a, b| a.timestamp.cmp(&b.timestamp));
+        // Sort by sequence (oldest first), the timestamp only orders files that predate it
+        snapshots.sort_by_key(|(_, metadata)| (metadata.sequence, metadata.timestamp));
 
         // Remove oldest snapshots
         let to_remove = snapshots.len() - KEEP_SNAPSHOTS;
-        for i in 0..to_remove {
-            let filename = format!(
-                "snapshot-{}.rdb",
-                snapshots[i].timestamp.format("%Y%m%d-%H%M%S")
-            );
-            let filepath = self.snapshot_dir.join(filename);
-
+        for (filepath, _) in snapshots.iter().take(to_remove) {
             if let Err(e) = tokio::fs::remove_file(&filepath).await {
                 warn!(
                     "Failed to remove old snapshot {}: {}",
@@ -206,6 +744,16 @@ impl Snapshotter {
 
     // List all available snapshots
     pub async fn list_snapshots(&self) -> StorageResult<Vec<SnapshotMetadata>> {
+        Ok(self
+            .snapshot_files()
+            .await?
+            .into_iter()
+            .map(|(_, metadata)| metadata)
+            .collect())
+    }
+
+    // Every readable snapshot file with its metadata
+    async fn snapshot_files(&self) -> StorageResult<Vec<(PathBuf, SnapshotMetadata)>> {
         let mut entries = tokio::fs::read_dir(&self.snapshot_dir).await?;
         let mut snapshots = Vec::new();
 
@@ -214,10 +762,9 @@ impl Snapshotter {
 
             if path.extension().and_then(|s| s.to_str()) == Some("rdb")
                 && path.file_name().and_then(|s| s.to_str()) != Some("snapshot-latest.rdb")
+                && let Ok(snapshot) = self.load_snapshot(&path).await
             {
-                if let Ok(snapshot) = self.load_snapshot(&path).await {
-                    snapshots.push(snapshot.metadata);
-                }
+                snapshots.push((path, snapshot.metadata));
             }
         }
 
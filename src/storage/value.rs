@@ -0,0 +1,215 @@
+use std::collections::{HashSet, VecDeque};
+
+use crate::storage::{StorageError, StorageResult};
+
+// Typed values are stored as raw bytes behind a type tag, so they survive
+// snapshots and AOF compaction, which only ever see key/value bytes
+// Untagged values are plain strings
+pub const SET_TAG: &[u8] = b"\x00blz:set\x00";
+pub const LIST_TAG: &[u8] = b"\x00blz:list\x00";
+
+// Small collections use a flat length-prefixed layout instead, which reads
+// in place without building a HashSet or VecDeque
+pub const PACKED_SET_TAG: &[u8] = b"\x00blz:pset\x00";
+pub const PACKED_LIST_TAG: &[u8] = b"\x00blz:plist\x00";
+
+// Strings up to this many bytes report the embstr encoding
+const EMBSTR_MAX_LEN: usize = 44;
+
+// Whether a stored value holds a set
+pub fn is_set(value: &[u8]) -> bool {
+    value.starts_with(SET_TAG) || value.starts_with(PACKED_SET_TAG)
+}
+
+// Whether a stored value holds a list
+pub fn is_list(value: &[u8]) -> bool {
+    value.starts_with(LIST_TAG) || value.starts_with(PACKED_LIST_TAG)
+}
+
+// Whether a stored value is typed rather than a plain string
+pub fn is_typed(value: &[u8]) -> bool {
+    is_set(value) || is_list(value)
+}
+
+// Reject a plain string that would read back as typed
+// Typed values are only created through their own commands
+pub fn check_untyped(value: &[u8]) -> StorageResult<()> {
+    if is_typed(value) {
+        return Err(StorageError::ReservedTypeTag);
+    }
+    Ok(())
+}
+
+// Whether a string of `len` bytes would read as typed once `bytes` is written at `offset`
+// Only the first bytes can form a tag, so the rest of the value is never copied
+pub fn typed_after_write(current: Option<&[u8]>, len: usize, offset: usize, bytes: &[u8]) -> bool {
+    // PACKED_LIST_TAG is the longest tag
+    let head_len = len.min(PACKED_LIST_TAG.len());
+    let mut head = vec![0; head_len];
+
+    if let Some(current) = current {
+        let kept = current.len().min(head_len);
+        head[..kept].copy_from_slice(&current[..kept]);
+    }
+    if offset < head_len {
+        let end = (offset + bytes.len()).min(head_len);
+        head[offset..end].copy_from_slice(&bytes[..end - offset]);
+    }
+
+    is_typed(&head)
+}
+
+// Name of the encoding a stored value uses, as reported by OBJECT ENCODING
+pub fn encoding(value: &[u8]) -> &'static str {
+    if value.starts_with(PACKED_SET_TAG) || value.starts_with(PACKED_LIST_TAG) {
+        "listpack"
+    } else if value.starts_with(SET_TAG) {
+        "hashtable"
+    } else if value.starts_with(LIST_TAG) {
+        "quicklist"
+    } else if std::str::from_utf8(value).is_ok_and(|text| text.parse::<i64>().is_ok()) {
+        "int"
+    } else if value.len() <= EMBSTR_MAX_LEN {
+        "embstr"
+    } else {
+        "raw"
+    }
+}
+
+// Write entries behind tag, each prefixed by its LEB128 length
+fn encode_packed<'a>(tag: &[u8], entries: impl Iterator<Item = &'a Vec<u8>>) -> Vec<u8> {
+    let mut encoded = tag.to_vec();
+    for entry in entries {
+        let mut len = entry.len();
+        while len >= 0x80 {
+            encoded.push((len as u8) | 0x80);
+            len >>= 7;
+        }
+        encoded.push(len as u8);
+        encoded.extend_from_slice(entry);
+    }
+    encoded
+}
+
+// Split a packed payload back into its entries without copying them
+fn packed_entries(mut payload: &[u8]) -> StorageResult<Vec<&[u8]>> {
+    let corrupt = || StorageError::CorruptValue("truncated packed collection".to_string());
+
+    let mut entries = Vec::new();
+    while !payload.is_empty() {
+        let mut len = 0usize;
+        let mut shift = 0;
+        loop {
+            let (&byte, rest) = payload.split_first().ok_or_else(corrupt)?;
+            payload = rest;
+            if shift >= usize::BITS {
+                return Err(corrupt());
+            }
+            len |= ((byte & 0x7f) as usize) << shift;
+            shift += 7;
+            if byte & 0x80 == 0 {
+                break;
+            }
+        }
+
+        if len > payload.len() {
+            return Err(corrupt());
+        }
+        let (entry, rest) = payload.split_at(len);
+        entries.push(entry);
+        payload = rest;
+    }
+    Ok(entries)
+}
+
+// Encode set members in the packed layout
+pub fn encode_packed_set(members: &HashSet<Vec<u8>>) -> Vec<u8> {
+    encode_packed(PACKED_SET_TAG, members.iter())
+}
+
+// Encode list items, in order, in the packed layout
+pub fn encode_packed_list(items: &VecDeque<Vec<u8>>) -> Vec<u8> {
+    encode_packed(PACKED_LIST_TAG, items.iter())
+}
+
+// Whether member is in the stored set, scanning packed sets in place
+pub fn set_contains(value: &[u8], member: &[u8]) -> StorageResult<bool> {
+    match value.strip_prefix(PACKED_SET_TAG) {
+        Some(payload) => Ok(packed_entries(payload)?.contains(&member)),
+        None => Ok(decode_set(value)?.contains(member)),
+    }
+}
+
+// Number of members in the stored set, counting packed sets in place
+pub fn set_len(value: &[u8]) -> StorageResult<usize> {
+    match value.strip_prefix(PACKED_SET_TAG) {
+        Some(payload) => Ok(packed_entries(payload)?.len()),
+        None => Ok(decode_set(value)?.len()),
+    }
+}
+
+// Encode set members behind the set tag
+pub fn encode_set(members: &HashSet<Vec<u8>>) -> StorageResult<Vec<u8>> {
+    let mut encoded = SET_TAG.to_vec();
+    bincode::serde::encode_into_std_write(members, &mut encoded, bincode::config::standard())
+        .map_err(StorageError::Serialization)?;
+    Ok(encoded)
+}
+
+// Parse a stored string as a finite float (missing keys count as 0 at the call site)
+pub fn parse_float(value: &[u8]) -> Option<f64> {
+    std::str::from_utf8(value)
+        .ok()?
+        .parse::<f64>()
+        .ok()
+        .filter(|value| value.is_finite())
+}
+
+// Shortest text that parses back to the same float, no exponent and no trailing zeros
+pub fn format_float(value: f64) -> String {
+    // -0 would otherwise be stored as "-0"
+    if value == 0.0 {
+        return "0".to_string();
+    }
+    value.to_string()
+}
+
+// Decode a stored set, failing with WrongType for any other value
+pub fn decode_set(value: &[u8]) -> StorageResult<HashSet<Vec<u8>>> {
+    if let Some(payload) = value.strip_prefix(PACKED_SET_TAG) {
+        return Ok(packed_entries(payload)?
+            .into_iter()
+            .map(<[u8]>::to_vec)
+            .collect());
+    }
+
+    let payload = value.strip_prefix(SET_TAG).ok_or(StorageError::WrongType)?;
+    let (members, _) = bincode::serde::decode_from_slice(payload, bincode::config::standard())
+        .map_err(StorageError::Deserialization)?;
+    Ok(members)
+}
+
+// Encode list items, in order, behind the list tag
+pub fn encode_list(items: &VecDeque<Vec<u8>>) -> StorageResult<Vec<u8>> {
+    let mut encoded = LIST_TAG.to_vec();
+    bincode::serde::encode_into_std_write(items, &mut encoded, bincode::config::standard())
+        .map_err(StorageError::Serialization)?;
+    Ok(encoded)
+}
+
+// Decode a stored list, failing with WrongType for any other value
+pub fn decode_list(value: &[u8]) -> StorageResult<VecDeque<Vec<u8>>> {
+    if let Some(payload) = value.strip_prefix(PACKED_LIST_TAG) {
+        return Ok(packed_entries(payload)?
+            .into_iter()
+            .map(<[u8]>::to_vec)
+            .collect());
+    }
+
+    let payload = value
+        .strip_prefix(LIST_TAG)
+        .ok_or(StorageError::WrongType)?;
+    let (items, _) = bincode::serde::decode_from_slice(payload, bincode::config::standard())
+        .map_err(StorageError::Deserialization)?;
+    Ok(items)
+}
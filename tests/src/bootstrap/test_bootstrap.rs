@@ -0,0 +1,573 @@
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use async_trait::async_trait;
+use blazekvdb::{
+    bootstrap::BlazeKVDB,
+    commands::{Command, CommandResponse, debug::DebugCommand, get::GetCommand, set::SetCommand},
+    config::BlazeServerConfig,
+    storage::{
+        EntryStream, ExpiringEntryStream, KeyStream, MaxMemoryPolicy, MemoryStats, StorageConfig,
+        StorageEngine, StorageResult, StorageStats, UpdateFn, engine::memory::MemoryEngine,
+    },
+};
+use futures_util::{StreamExt, TryStreamExt};
+use tokio::sync::Notify;
+
+// Delegates to MemoryEngine while recording which trait methods were called
+struct RecordingEngine {
+    inner: MemoryEngine,
+    calls: Mutex<Vec<&'static str>>,
+}
+
+impl RecordingEngine {
+    fn new() -> Self {
+        Self {
+            inner: MemoryEngine::new(StorageConfig::default()),
+            calls: Mutex::new(Vec::new()),
+        }
+    }
+
+    fn record(&self, call: &'static str) {
+        self.calls.lock().unwrap().push(call);
+    }
+
+    fn calls(&self) -> Vec<&'static str> {
+        self.calls.lock().unwrap().clone()
+    }
+}
+
+#[async_trait]
+impl StorageEngine for RecordingEngine {
+    async fn get(&self, key: &str) -> StorageResult<Option<Vec<u8>>> {
+        self.record("get");
+        self.inner.get(key).await
+    }
+
+    async fn get_many(&self, keys: &[&str]) -> StorageResult<HashMap<String, Vec<u8>>> {
+        self.record("get_many");
+        self.inner.get_many(keys).await
+    }
+
+    async fn set(&self, key: &str, value: Vec<u8>) -> StorageResult<()> {
+        self.record("set");
+        self.inner.set(key, value).await
+    }
+
+    async fn set_with_ttl(&self, key: &str, value: Vec<u8>, ttl: Duration) -> StorageResult<()> {
+        self.record("set_with_ttl");
+        self.inner.set_with_ttl(key, value, ttl).await
+    }
+
+    async fn get_versioned(&self, key: &str) -> StorageResult<Option<(Vec<u8>, u64)>> {
+        self.record("get_versioned");
+        self.inner.get_versioned(key).await
+    }
+
+    async fn set_if_version(&self, key: &str, value: Vec<u8>, expected: u64) -> StorageResult<u64> {
+        self.record("set_if_version");
+        self.inner.set_if_version(key, value, expected).await
+    }
+
+    async fn get_range(&self, key: &str, start: i64, end: i64) -> StorageResult<Vec<u8>> {
+        self.record("get_range");
+        self.inner.get_range(key, start, end).await
+    }
+
+    async fn set_range(&self, key: &str, offset: usize, bytes: &[u8]) -> StorageResult<usize> {
+        self.record("set_range");
+        self.inner.set_range(key, offset, bytes).await
+    }
+
+    async fn set_bit(&self, key: &str, offset: usize, bit: bool) -> StorageResult<bool> {
+        self.record("set_bit");
+        self.inner.set_bit(key, offset, bit).await
+    }
+
+    async fn incr_by_float(&self, key: &str, delta: f64) -> StorageResult<f64> {
+        self.record("incr_by_float");
+        self.inner.incr_by_float(key, delta).await
+    }
+
+    async fn update(&self, key: &str, update: UpdateFn<'_>) -> StorageResult<bool> {
+        self.record("update");
+        self.inner.update(key, update).await
+    }
+
+    async fn add_members(&self, key: &str, members: &[Vec<u8>]) -> StorageResult<usize> {
+        self.record("add_members");
+        self.inner.add_members(key, members).await
+    }
+
+    async fn remove_members(&self, key: &str, members: &[Vec<u8>]) -> StorageResult<usize> {
+        self.record("remove_members");
+        self.inner.remove_members(key, members).await
+    }
+
+    async fn push_items(&self, key: &str, items: &[Vec<u8>], front: bool) -> StorageResult<usize> {
+        self.record("push_items");
+        self.inner.push_items(key, items, front).await
+    }
+
+    async fn pop_front(&self, key: &str) -> StorageResult<Option<Vec<u8>>> {
+        self.record("pop_front");
+        self.inner.pop_front(key).await
+    }
+
+    fn list_waiter(&self, key: &str) -> Arc<Notify> {
+        self.inner.list_waiter(key)
+    }
+
+    async fn delete(&self, key: &str) -> StorageResult<bool> {
+        self.record("delete");
+        self.inner.delete(key).await
+    }
+
+    async fn clear(&self) -> StorageResult<usize> {
+        self.record("clear");
+        self.inner.clear().await
+    }
+
+    async fn delete_prefix(&self, prefix: &str) -> StorageResult<Vec<String>> {
+        self.record("delete_prefix");
+        self.inner.delete_prefix(prefix).await
+    }
+
+    async fn exists(&self, key: &str) -> StorageResult<bool> {
+        self.record("exists");
+        self.inner.exists(key).await
+    }
+
+    async fn expire_at(&self, key: &str, at_millis: u64) -> StorageResult<Option<u64>> {
+        self.record("expire_at");
+        self.inner.expire_at(key, at_millis).await
+    }
+
+    async fn expires_at(&self, key: &str) -> StorageResult<Option<Option<u64>>> {
+        self.record("expires_at");
+        self.inner.expires_at(key).await
+    }
+
+    async fn idle_time(&self, key: &str) -> StorageResult<Option<u64>> {
+        self.record("idle_time");
+        self.inner.idle_time(key).await
+    }
+
+    async fn access_frequency(&self, key: &str) -> StorageResult<Option<u64>> {
+        self.record("access_frequency");
+        self.inner.access_frequency(key).await
+    }
+
+    async fn encoding(&self, key: &str) -> StorageResult<Option<&'static str>> {
+        self.record("encoding");
+        self.inner.encoding(key).await
+    }
+
+    async fn memory_usage(&self, key: &str) -> StorageResult<Option<usize>> {
+        self.record("memory_usage");
+        self.inner.memory_usage(key).await
+    }
+
+    async fn memory_stats(&self) -> StorageResult<MemoryStats> {
+        self.record("memory_stats");
+        self.inner.memory_stats().await
+    }
+
+    async fn touch(&self, keys: &[String]) -> StorageResult<usize> {
+        self.record("touch");
+        self.inner.touch(keys).await
+    }
+
+    async fn rename(&self, from: &str, to: &str) -> StorageResult<bool> {
+        self.record("rename");
+        self.inner.rename(from, to).await
+    }
+
+    async fn scan(&self, prefix: &str) -> StorageResult<KeyStream> {
+        self.record("scan");
+        self.inner.scan(prefix).await
+    }
+
+    async fn scan_entries(&self, prefix: &str) -> StorageResult<EntryStream> {
+        self.record("scan_entries");
+        self.inner.scan_entries(prefix).await
+    }
+
+    async fn iter_all(&self) -> StorageResult<EntryStream> {
+        self.record("iter_all");
+        self.inner.iter_all().await
+    }
+
+    async fn iter_with_expiry(&self) -> StorageResult<ExpiringEntryStream> {
+        self.record("iter_with_expiry");
+        self.inner.iter_with_expiry().await
+    }
+
+    async fn purge_expired(&self) -> StorageResult<usize> {
+        self.record("purge_expired");
+        self.inner.purge_expired().await
+    }
+
+    async fn resize_shards(&self, shard_count: usize) -> StorageResult<()> {
+        self.record("resize_shards");
+        self.inner.resize_shards(shard_count).await
+    }
+
+    async fn stats(&self) -> StorageResult<StorageStats> {
+        self.record("stats");
+        self.inner.stats().await
+    }
+
+    async fn health_check(&self) -> StorageResult<()> {
+        self.record("health_check");
+        self.inner.health_check().await
+    }
+}
+
+#[tokio::test]
+async fn test_with_storage_uses_injected_engine() {
+    let mut config = BlazeServerConfig::default();
+    config.persistence.enabled = false;
+    config.storage.active_expire_interval_ms = 0; // Keep the sweeper out of the recorded calls
+
+    let engine = Arc::new(RecordingEngine::new());
+    let kvdb = BlazeKVDB::with_storage(config, engine.clone())
+        .await
+        .unwrap();
+
+    let response = kvdb
+        .execute(Command::Set(SetCommand::new(
+            "key1".to_string(),
+            b"value1".to_vec(),
+        )))
+        .await;
+    assert_eq!(response, CommandResponse::Ok);
+
+    let response = kvdb
+        .execute(Command::Get(GetCommand::new("key1".to_string())))
+        .await;
+    assert_eq!(response, CommandResponse::Value(b"value1".to_vec()));
+
+    kvdb.health_check().await.unwrap();
+
+    assert_eq!(engine.calls(), vec!["set", "get", "health_check"]);
+}
+
+#[tokio::test]
+async fn test_shutdown_releases_storage() {
+    let temp_dir = tempfile::tempdir().unwrap();
+
+    let mut config = BlazeServerConfig::default();
+    config.persistence.aof_path = temp_dir.path().join("test.aof");
+    config.persistence.snapshot_dir = temp_dir.path().join("snapshots");
+
+    let engine = Arc::new(RecordingEngine::new());
+    let kvdb = BlazeKVDB::with_storage(config, engine.clone())
+        .await
+        .unwrap();
+
+    let response = kvdb
+        .execute(Command::Set(SetCommand::new(
+            "key1".to_string(),
+            b"value1".to_vec(),
+        )))
+        .await;
+    assert_eq!(response, CommandResponse::Ok);
+
+    kvdb.shutdown().await.unwrap();
+    drop(kvdb);
+
+    assert_eq!(Arc::strong_count(&engine), 1);
+}
+
+#[tokio::test]
+async fn test_recovery_completes_before_serving() {
+    let temp_dir = tempfile::tempdir().unwrap();
+
+    let mut config = BlazeServerConfig::default();
+    config.persistence.aof_path = temp_dir.path().join("test.aof");
+    config.persistence.snapshot_dir = temp_dir.path().join("snapshots");
+
+    let kvdb = BlazeKVDB::new(config.clone()).await.unwrap();
+    let response = kvdb
+        .execute(Command::Set(SetCommand::new(
+            "key1".to_string(),
+            b"value1".to_vec(),
+        )))
+        .await;
+    assert_eq!(response, CommandResponse::Ok);
+    kvdb.shutdown().await.unwrap();
+    drop(kvdb);
+
+    // The instance is handed out with the AOF replayed and the gate lifted
+    let kvdb = BlazeKVDB::new(config).await.unwrap();
+    assert!(!kvdb.is_recovering());
+    assert!(!kvdb.dispatcher().is_recovering());
+
+    let response = kvdb
+        .execute(Command::Get(GetCommand::new("key1".to_string())))
+        .await;
+    assert_eq!(response, CommandResponse::Value(b"value1".to_vec()));
+    kvdb.shutdown().await.unwrap();
+}
+
+#[tokio::test]
+async fn test_scan_stream_is_lazy() {
+    let mut config = BlazeServerConfig::default();
+    config.persistence.enabled = false;
+    config.storage.active_expire_interval_ms = 0;
+
+    let engine = Arc::new(RecordingEngine::new());
+    let kvdb = BlazeKVDB::with_storage(config, engine.clone())
+        .await
+        .unwrap();
+
+    for i in 0..1000 {
+        engine
+            .inner
+            .set(&format!("export:{:04}", i), vec![i as u8])
+            .await
+            .unwrap();
+    }
+    engine.inner.set("skip", b"value".to_vec()).await.unwrap();
+
+    // Nothing is read until the stream is polled
+    let stream = kvdb.scan_stream("export:");
+    assert!(engine.calls().is_empty());
+
+    let mut entries: Vec<(String, Vec<u8>)> = stream.try_collect().await.unwrap();
+    entries.sort();
+    assert_eq!(entries.len(), 1000);
+    assert_eq!(entries[7], ("export:0007".to_string(), vec![7]));
+    assert_eq!(engine.calls(), vec!["scan_entries"]);
+
+    // Consumers can stop early
+    let first: Vec<_> = kvdb.scan_stream("").take(3).collect().await;
+    assert_eq!(first.len(), 3);
+}
+
+#[tokio::test]
+async fn test_get_many_is_one_storage_call() {
+    let mut config = BlazeServerConfig::default();
+    config.persistence.enabled = false;
+    config.storage.active_expire_interval_ms = 0;
+
+    let engine = Arc::new(RecordingEngine::new());
+    let kvdb = BlazeKVDB::with_storage(config, engine.clone())
+        .await
+        .unwrap();
+
+    engine.inner.set("a", b"1".to_vec()).await.unwrap();
+    engine.inner.set("b", b"2".to_vec()).await.unwrap();
+
+    let values = kvdb.get_many(&["a", "b", "c"]).await.unwrap();
+    assert_eq!(
+        values,
+        HashMap::from([
+            ("a".to_string(), b"1".to_vec()),
+            ("b".to_string(), b"2".to_vec())
+        ])
+    );
+    assert_eq!(engine.calls(), vec!["get_many"]);
+}
+
+#[tokio::test]
+async fn test_active_expire_purges_unread_keys() {
+    let mut config = BlazeServerConfig::default();
+    config.persistence.enabled = false;
+    config.storage.databases = 1;
+    config.storage.active_expire_interval_ms = 5;
+
+    let kvdb = BlazeKVDB::new(config).await.unwrap();
+    let expired_keys = || async { kvdb.storage_stats().await.unwrap().expired_keys };
+
+    // Paused: the expired key lingers until something reads it
+    assert_eq!(
+        kvdb.execute(Command::Debug(DebugCommand::SetActiveExpire(false)))
+            .await,
+        CommandResponse::Ok
+    );
+    assert!(!kvdb.dispatcher().is_active_expire());
+    kvdb.storage()
+        .set_with_ttl("key1", b"value1".to_vec(), Duration::from_millis(1))
+        .await
+        .unwrap();
+    tokio::time::sleep(Duration::from_millis(200)).await;
+    assert_eq!(expired_keys().await, 0);
+
+    // Resumed: the sweeper removes it without any access
+    kvdb.execute(Command::Debug(DebugCommand::SetActiveExpire(true)))
+        .await;
+    for _ in 0..200 {
+        if expired_keys().await == 1 {
+            break;
+        }
+        tokio::time::sleep(Duration::from_millis(10)).await;
+    }
+    assert_eq!(expired_keys().await, 1);
+    assert_eq!(kvdb.storage_stats().await.unwrap().memory_usage, 0);
+
+    kvdb.shutdown().await.unwrap();
+}
+
+#[tokio::test]
+async fn test_run_script_reports_failed_lines() {
+    let mut config = BlazeServerConfig::default();
+    config.persistence.enabled = false;
+    let kvdb = BlazeKVDB::new(config).await.unwrap();
+
+    let script = "# seed data\nSET greeting hello\n\nSET counter 1\nINCRBYFLOAT greeting 1\n";
+    let report = kvdb.run_script(script).await;
+    assert_eq!(report.executed, 3);
+    assert_eq!(report.errors.len(), 1);
+    assert_eq!(report.errors[0].line, 5);
+    assert_eq!(
+        kvdb.execute(Command::Get(GetCommand::new("greeting".to_string())))
+            .await,
+        CommandResponse::Value(b"hello".to_vec())
+    );
+
+    // A line that doesn't parse keeps every other line from running
+    let report = kvdb.run_script("SET fresh 1\nBOGUS fresh\n").await;
+    assert_eq!(report.executed, 0);
+    assert_eq!(report.errors.len(), 1);
+    assert_eq!(report.errors[0].line, 2);
+    assert_eq!(
+        kvdb.execute(Command::Get(GetCommand::new("fresh".to_string())))
+            .await,
+        CommandResponse::Nil
+    );
+
+    kvdb.shutdown().await.unwrap();
+}
+
+#[tokio::test]
+async fn test_debug_reload_round_trips_dataset() {
+    let temp_dir = tempfile::tempdir().unwrap();
+
+    let mut config = BlazeServerConfig::default();
+    config.persistence.aof_path = temp_dir.path().join("test.aof");
+    config.persistence.snapshot_dir = temp_dir.path().join("snapshots");
+    let kvdb = BlazeKVDB::new(config).await.unwrap();
+
+    for (key, value) in [("plain", "value"), ("spaced", "hello big world")] {
+        let response = kvdb
+            .execute(Command::Set(SetCommand::new(
+                key.to_string(),
+                value.as_bytes().to_vec(),
+            )))
+            .await;
+        assert_eq!(response, CommandResponse::Ok);
+    }
+
+    // Wipes the live dataset, so it stays off unless explicitly allowed
+    let reload = || kvdb.execute(Command::Debug(DebugCommand::Reload));
+    assert!(matches!(reload().await, CommandResponse::Error(e) if e.contains("debug_commands")));
+
+    kvdb.config().write().server.debug_commands = true;
+    assert_eq!(reload().await, CommandResponse::Ok);
+
+    let response = kvdb
+        .execute(Command::Get(GetCommand::new("spaced".to_string())))
+        .await;
+    assert_eq!(
+        response,
+        CommandResponse::Value(b"hello big world".to_vec())
+    );
+    assert_eq!(kvdb.storage_stats().await.unwrap().total_keys, 2);
+
+    kvdb.shutdown().await.unwrap();
+}
+
+#[tokio::test]
+async fn test_databases_share_max_memory() {
+    let mut config = BlazeServerConfig::default();
+    config.persistence.enabled = false;
+    config.storage.databases = 2;
+    config.storage.max_memory = 4096;
+
+    let kvdb = BlazeKVDB::new(config).await.unwrap();
+    let db0 = kvdb.database(0).unwrap();
+    let db1 = kvdb.database(1).unwrap();
+
+    // Each fits on its own, not both together
+    db0.set("a", vec![0; 3000]).await.unwrap();
+    assert!(db1.set("b", vec![0; 3000]).await.is_err());
+    assert_eq!(db1.get("b").await.unwrap(), None);
+
+    // Freed in one database, usable by the other
+    db0.delete("a").await.unwrap();
+    db1.set("b", vec![0; 3000]).await.unwrap();
+
+    kvdb.shutdown().await.unwrap();
+}
+
+#[tokio::test]
+async fn test_databases_evict_from_each_other() {
+    let mut config = BlazeServerConfig::default();
+    config.persistence.enabled = false;
+    config.storage.databases = 2;
+    config.storage.max_memory = 4096;
+    config.storage.maxmemory_policy = MaxMemoryPolicy::AllkeysLru;
+
+    let kvdb = BlazeKVDB::new(config).await.unwrap();
+    let db0 = kvdb.database(0).unwrap();
+    let db1 = kvdb.database(1).unwrap();
+
+    // The empty database makes room by evicting from the full one
+    db0.set("a", vec![0; 3000]).await.unwrap();
+    db1.set("b", vec![0; 3000]).await.unwrap();
+    assert_eq!(db0.get("a").await.unwrap(), None);
+    assert!(db1.exists("b").await.unwrap());
+    assert_eq!(db0.stats().await.unwrap().evicted_keys, 1);
+
+    kvdb.shutdown().await.unwrap();
+}
+
+#[tokio::test]
+async fn test_evicted_keys_stay_gone_after_recovery() {
+    let temp_dir = tempfile::tempdir().unwrap();
+
+    let mut config = BlazeServerConfig::default();
+    config.persistence.aof_path = temp_dir.path().join("test.aof");
+    config.persistence.snapshot_enabled = false;
+    config.storage.databases = 2;
+    config.storage.max_memory = 4096;
+    config.storage.maxmemory_policy = MaxMemoryPolicy::AllkeysRandom;
+
+    let keys = |kvdb: &BlazeKVDB, db: usize| {
+        let storage = kvdb.database(db).unwrap();
+        async move {
+            let mut keys: Vec<String> =
+                storage.scan("").await.unwrap().try_collect().await.unwrap();
+            keys.sort();
+            keys
+        }
+    };
+
+    let kvdb = BlazeKVDB::new(config.clone()).await.unwrap();
+    let dispatcher = kvdb.dispatcher();
+    for db in 0..2 {
+        for i in 0..40 {
+            let set = SetCommand::new(format!("key:{}", i), vec![0; 100]);
+            let response = dispatcher.execute_on(db, Command::Set(set)).await;
+            assert_eq!(response, CommandResponse::Ok);
+        }
+    }
+    let before = [keys(&kvdb, 0).await, keys(&kvdb, 1).await];
+    assert!(
+        before[0].len() + before[1].len() < 80,
+        "nothing was evicted"
+    );
+    kvdb.shutdown().await.unwrap();
+    drop(kvdb);
+
+    // Room for everything, so only the logged evictions can keep keys out
+    config.storage.max_memory = 1024 * 1024;
+    let kvdb = BlazeKVDB::new(config).await.unwrap();
+    assert_eq!([keys(&kvdb, 0).await, keys(&kvdb, 1).await], before);
+    kvdb.shutdown().await.unwrap();
+}
@@ -0,0 +1,301 @@
+use std::{net::SocketAddr, sync::Arc, time::Duration};
+
+use blazekvdb::{
+    client::{BlazeClient, ClientConfig, ClientError},
+    commands::{
+        Command, CommandDispatcher, CommandResponse, get::GetCommand, sadd::SAddCommand,
+        set::SetCommand, smembers::SMembersCommand,
+    },
+    repl::format_response,
+    server::tcp::TcpServer,
+    storage::{StorageConfig, StorageEngine, engine::memory::MemoryEngine},
+};
+use tokio::{io::AsyncBufReadExt, net::TcpListener};
+
+fn create_test_server(addr: SocketAddr) -> TcpServer {
+    let storage = Arc::new(MemoryEngine::new(StorageConfig::default())) as Arc<dyn StorageEngine>;
+    let dispatcher = Arc::new(CommandDispatcher::new(storage));
+    TcpServer::new(dispatcher, addr)
+}
+
+async fn start_server() -> SocketAddr {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    let server = create_test_server(addr);
+
+    tokio::spawn(async move {
+        server.accept_connections(listener).await.ok();
+    });
+
+    addr
+}
+
+#[tokio::test]
+async fn test_client_typed_commands() {
+    let addr = start_server().await;
+    let client = BlazeClient::connect(addr).await.unwrap();
+
+    client.ping().await.unwrap();
+    assert_eq!(
+        client.echo(b"conn-1 \n\0".to_vec()).await.unwrap(),
+        b"conn-1 \n\0"
+    );
+
+    let hello = client.hello().await.unwrap();
+    assert_eq!(hello[0], ("server".to_string(), "blazekvdb".to_string()));
+    assert!(hello.contains(&("proto".to_string(), "1".to_string())));
+
+    client.set("user:1", "alice").await.unwrap();
+    client
+        .set("user:2", vec![0u8, 255, b' ', b'\n'])
+        .await
+        .unwrap();
+    client
+        .set_ex("session", "token", Duration::from_secs(60))
+        .await
+        .unwrap();
+
+    assert_eq!(client.get("user:1").await.unwrap(), Some(b"alice".to_vec()));
+    assert_eq!(
+        client.get("user:2").await.unwrap(),
+        Some(vec![0u8, 255, b' ', b'\n'])
+    );
+    assert_eq!(client.get("missing").await.unwrap(), None);
+
+    assert!(client.exists("user:1").await.unwrap());
+    assert!(!client.exists("missing").await.unwrap());
+
+    let mut keys = client.scan("user:").await.unwrap();
+    keys.sort();
+    assert_eq!(keys, vec!["user:1".to_string(), "user:2".to_string()]);
+
+    assert!(client.delete("user:1").await.unwrap());
+    assert!(!client.delete("user:1").await.unwrap());
+    assert_eq!(client.get("user:1").await.unwrap(), None);
+
+    // Server-side failures surface as errors, not as protocol trouble
+    client
+        .execute(Command::SAdd(SAddCommand::new(
+            "set".to_string(),
+            vec![b"a".to_vec()],
+        )))
+        .await
+        .unwrap();
+    assert!(matches!(
+        client.get("set").await,
+        Err(ClientError::Server(_))
+    ));
+}
+
+#[tokio::test]
+async fn test_client_pipeline() {
+    let addr = start_server().await;
+    let client = BlazeClient::connect(addr).await.unwrap();
+
+    let commands = vec![
+        Command::Set(SetCommand::new("k1".to_string(), b"v1".to_vec())),
+        Command::SAdd(SAddCommand::new(
+            "members".to_string(),
+            vec![b"x".to_vec(), b"y".to_vec()],
+        )),
+        Command::Get(GetCommand::new("k1".to_string())),
+        Command::SMembers(SMembersCommand::new("members".to_string())),
+        Command::Get(GetCommand::new("missing".to_string())),
+        Command::Ping,
+    ];
+
+    let responses = client.pipeline(&commands).await.unwrap();
+    assert_eq!(responses.len(), commands.len());
+    assert_eq!(responses[0], CommandResponse::Ok);
+    assert_eq!(responses[1], CommandResponse::Integer(2));
+    assert_eq!(responses[2], CommandResponse::Value(b"v1".to_vec()));
+    match &responses[3] {
+        CommandResponse::Members(members) => {
+            let mut members = members.clone();
+            members.sort();
+            assert_eq!(members, vec![b"x".to_vec(), b"y".to_vec()]);
+        }
+        other => panic!("Expected members, got {:?}", other),
+    }
+    assert_eq!(responses[4], CommandResponse::Nil);
+    assert_eq!(responses[5], CommandResponse::Pong);
+
+    // The connection stays usable after a pipeline
+    client.ping().await.unwrap();
+}
+
+#[tokio::test]
+async fn test_client_rejects_unencodable_commands() {
+    let addr = start_server().await;
+    let client = BlazeClient::connect(addr).await.unwrap();
+
+    assert!(matches!(
+        client.set("two words", "value").await,
+        Err(ClientError::Protocol(_))
+    ));
+
+    // Nothing was sent, the connection is still in sync
+    client.ping().await.unwrap();
+}
+
+#[tokio::test]
+async fn test_client_reconnects_after_connection_loss() {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    let server = create_test_server(addr);
+
+    // The first connection is dropped without a reply, later ones reach the server
+    tokio::spawn(async move {
+        let (stream, _) = listener.accept().await.unwrap();
+        drop(stream);
+        server.accept_connections(listener).await.ok();
+    });
+
+    let config = ClientConfig {
+        reconnect_delay: Duration::from_millis(10),
+        ..Default::default()
+    };
+    let client = BlazeClient::connect_with(addr, config).await.unwrap();
+
+    client.ping().await.unwrap();
+    client.set("key", "value").await.unwrap();
+    assert_eq!(client.get("key").await.unwrap(), Some(b"value".to_vec()));
+}
+
+#[tokio::test]
+async fn test_client_does_not_resend_writes_after_connection_loss() {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    let server = create_test_server(addr);
+
+    // The first connection reads the request and goes away without a reply: the write may
+    // or may not have run, so it must not be sent again
+    tokio::spawn(async move {
+        let (stream, _) = listener.accept().await.unwrap();
+        let mut line = String::new();
+        tokio::io::BufReader::new(stream)
+            .read_line(&mut line)
+            .await
+            .unwrap();
+        server.accept_connections(listener).await.ok();
+    });
+
+    let config = ClientConfig {
+        reconnect_delay: Duration::from_millis(10),
+        ..Default::default()
+    };
+    let client = BlazeClient::connect_with(addr, config).await.unwrap();
+
+    assert!(matches!(
+        client.set("key", "value").await,
+        Err(ClientError::ConnectionClosed)
+    ));
+    // The next request gets a fresh connection, and the lost SET never reached the server
+    assert_eq!(client.get("key").await.unwrap(), None);
+}
+
+#[tokio::test]
+async fn test_client_connect_fails_without_server() {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    drop(listener);
+
+    assert!(matches!(
+        BlazeClient::connect(addr).await,
+        Err(ClientError::Io(_))
+    ));
+}
+
+#[tokio::test]
+async fn test_client_execute_line() {
+    let addr = start_server().await;
+    let client = BlazeClient::connect(addr).await.unwrap();
+
+    // Values go out exactly as typed, base64 included, and come back decoded
+    assert_eq!(
+        client.execute_line("SET user YWxpY2U=").await.unwrap(),
+        CommandResponse::Ok
+    );
+    assert_eq!(
+        client.execute_line("  GET user  ").await.unwrap(),
+        CommandResponse::Value(b"alice".to_vec())
+    );
+
+    // Unknown commands are the server's call, it may know them under a new name
+    assert!(matches!(
+        client.execute_line("FROBNICATE user").await.unwrap(),
+        CommandResponse::Error(_)
+    ));
+
+    for line in [
+        "",
+        "GET a\nGET b",
+        "PROTO JSON",
+        "SUBSCRIBE news",
+        "SETCHUNK big 10",
+    ] {
+        assert!(
+            matches!(
+                client.execute_line(line).await,
+                Err(ClientError::Protocol(_))
+            ),
+            "{:?} should be refused",
+            line
+        );
+    }
+
+    // The connection is still usable after a refused line
+    assert_eq!(
+        client.execute_line("PING").await.unwrap(),
+        CommandResponse::Pong
+    );
+}
+
+#[test]
+fn test_format_response() {
+    assert_eq!(
+        format_response(&CommandResponse::Value(b"say \"hi\"\n\xff".to_vec())),
+        "\"say \\\"hi\\\"\\n\\xff\""
+    );
+    assert_eq!(
+        format_response(&CommandResponse::Integer(-3)),
+        "(integer) -3"
+    );
+    assert_eq!(format_response(&CommandResponse::Bool(true)), "(integer) 1");
+    assert_eq!(format_response(&CommandResponse::Nil), "(nil)");
+    assert_eq!(
+        format_response(&CommandResponse::Error("ERR nope".to_string())),
+        "(error) ERR nope"
+    );
+
+    let keys: Vec<String> = (1..=10).map(|i| format!("k{}", i)).collect();
+    let rendered = format_response(&CommandResponse::Keys(keys));
+    let lines: Vec<&str> = rendered.lines().collect();
+    assert_eq!(lines[0], " 1) \"k1\"");
+    assert_eq!(lines[9], "10) \"k10\"");
+    assert_eq!(
+        format_response(&CommandResponse::Members(Vec::new())),
+        "(empty list)"
+    );
+
+    assert_eq!(
+        format_response(&CommandResponse::Map(vec![
+            ("server".to_string(), "blazekvdb".to_string()),
+            ("id".to_string(), "7".to_string()),
+        ])),
+        "server:  blazekvdb\nid:      7"
+    );
+
+    let stats = format_response(&CommandResponse::Stats {
+        total_keys: 2,
+        memory_usage: 128,
+        hit_rate: 0.5,
+        total_operations: 4,
+        keyspace_hits: 1,
+        keyspace_misses: 1,
+        evicted_keys: 0,
+    });
+    assert_eq!(stats.lines().count(), 7);
+    assert!(stats.contains("total_keys:        2"));
+    assert!(stats.contains("hit_rate:          50.00%"));
+}
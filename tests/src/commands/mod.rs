@@ -1,8 +1,28 @@
+pub mod test_acl;
+pub mod test_bgrewriteaof;
+pub mod test_bitmap;
+pub mod test_config_command;
 pub mod test_delete;
 pub mod test_dispatcher;
+pub mod test_eval;
 pub mod test_exist;
+pub mod test_expire;
 pub mod test_get;
+pub mod test_hello;
+pub mod test_incrbyfloat;
+pub mod test_info;
+pub mod test_lists;
+pub mod test_memory;
+pub mod test_metrics;
+pub mod test_object;
 pub mod test_ping;
+pub mod test_range;
+pub mod test_relocate;
 pub mod test_scan;
+pub mod test_select;
 pub mod test_set;
+pub mod test_sets;
 pub mod test_stats;
+pub mod test_touch;
+pub mod test_version;
+pub mod test_wait;
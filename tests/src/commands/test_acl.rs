@@ -0,0 +1,217 @@
+use std::sync::Arc;
+
+use blazekvdb::{
+    acl::{Acl, DEFAULT_USER},
+    commands::{
+        Command, CommandDispatcher, CommandResponse, auth::AuthCommand, delete::DeleteCommand,
+        get::GetCommand, hello::HelloCommand, readonly::ReadOnlyCommand, set::SetCommand,
+    },
+    config::{BlazeServerConfig, SecurityConfig, UserConfig},
+    storage::{StorageConfig, StorageEngine, engine::memory::MemoryEngine},
+};
+
+fn user(name: &str, password: &str, commands: &[&str]) -> UserConfig {
+    UserConfig {
+        name: name.to_string(),
+        password: password.to_string(),
+        commands: commands.iter().map(|c| c.to_string()).collect(),
+    }
+}
+
+fn dispatcher(security: &SecurityConfig) -> CommandDispatcher {
+    let storage = Arc::new(MemoryEngine::new(StorageConfig::default())) as Arc<dyn StorageEngine>;
+    CommandDispatcher::new(storage).with_acl(Acl::from_config(security))
+}
+
+fn set(key: &str) -> Command {
+    Command::Set(SetCommand::new(key.to_string(), b"value".to_vec()))
+}
+
+fn get(key: &str) -> Command {
+    Command::Get(GetCommand::new(key.to_string()))
+}
+
+fn is_noperm(response: &CommandResponse) -> bool {
+    matches!(response, CommandResponse::Error(msg) if msg.starts_with("NOPERM"))
+}
+
+#[tokio::test]
+async fn test_read_only_user() {
+    let security = SecurityConfig {
+        users: vec![user("reader", "secret", &["@read"])],
+        ..Default::default()
+    };
+    let dispatcher = dispatcher(&security);
+    let reader = dispatcher.acl().user("reader").unwrap();
+
+    assert_eq!(dispatcher.execute(set("key")).await, CommandResponse::Ok);
+    assert_eq!(
+        dispatcher.execute_as(0, Some(&reader), get("key")).await,
+        CommandResponse::Value(b"value".to_vec())
+    );
+
+    let response = dispatcher.execute_as(0, Some(&reader), set("key")).await;
+    assert!(is_noperm(&response), "{:?}", response);
+
+    // Server management isn't part of @read even though it doesn't write data
+    let response = dispatcher
+        .execute_as(
+            0,
+            Some(&reader),
+            Command::ReadOnly(ReadOnlyCommand::new(true)),
+        )
+        .await;
+    assert!(is_noperm(&response), "{:?}", response);
+    assert!(!dispatcher.is_read_only());
+}
+
+#[tokio::test]
+async fn test_command_allow_list() {
+    let security = SecurityConfig {
+        users: vec![user("writer", "secret", &["set", "PING"])],
+        ..Default::default()
+    };
+    let dispatcher = dispatcher(&security);
+    let writer = dispatcher.acl().user("writer").unwrap();
+
+    assert_eq!(
+        dispatcher.execute_as(0, Some(&writer), set("key")).await,
+        CommandResponse::Ok
+    );
+    assert_eq!(
+        dispatcher.execute_as(0, Some(&writer), Command::Ping).await,
+        CommandResponse::Pong
+    );
+
+    let response = dispatcher.execute_as(0, Some(&writer), get("key")).await;
+    assert!(is_noperm(&response), "{:?}", response);
+
+    // Users can always switch to another account
+    let response = dispatcher
+        .execute_as(
+            0,
+            Some(&writer),
+            Command::Auth(AuthCommand::with_username(
+                "writer".to_string(),
+                "secret".to_string(),
+            )),
+        )
+        .await;
+    assert_eq!(response, CommandResponse::Ok);
+}
+
+#[tokio::test]
+async fn test_alias_grants_the_command_it_runs() {
+    let security = SecurityConfig {
+        users: vec![user("cleaner", "secret", &["del"])],
+        ..Default::default()
+    };
+    let dispatcher = dispatcher(&security);
+    let cleaner = dispatcher.acl().user("cleaner").unwrap();
+
+    let del = Command::Delete(DeleteCommand::new("key".to_string()));
+    assert_eq!(
+        dispatcher.execute_as(0, Some(&cleaner), del).await,
+        CommandResponse::Integer(0)
+    );
+}
+
+#[tokio::test]
+async fn test_unauthenticated_connections_need_auth() {
+    let security = SecurityConfig {
+        require_auth: true,
+        auth_password: Some("hunter2".to_string()),
+        ..Default::default()
+    };
+    let dispatcher = dispatcher(&security);
+    assert!(dispatcher.acl().initial_user().is_none());
+
+    assert_eq!(
+        dispatcher.execute_as(0, None, Command::Ping).await,
+        CommandResponse::Error("NOAUTH Authentication required".to_string())
+    );
+    // Clients may identify the server before logging in
+    let response = dispatcher
+        .execute_as(0, None, Command::Hello(HelloCommand::new(None)))
+        .await;
+    assert!(
+        matches!(response, CommandResponse::Map(_)),
+        "{:?}",
+        response
+    );
+
+    let response = dispatcher
+        .execute_as(
+            0,
+            None,
+            Command::Auth(AuthCommand::new("wrong".to_string())),
+        )
+        .await;
+    assert!(
+        matches!(response, CommandResponse::Error(ref msg) if msg.starts_with("WRONGPASS")),
+        "{:?}",
+        response
+    );
+
+    let response = dispatcher
+        .execute_as(
+            0,
+            None,
+            Command::Auth(AuthCommand::new("hunter2".to_string())),
+        )
+        .await;
+    assert_eq!(response, CommandResponse::Ok);
+
+    // The password belongs to the unrestricted default user
+    let default = dispatcher.acl().user(DEFAULT_USER).unwrap();
+    assert_eq!(
+        dispatcher.execute_as(0, Some(&default), set("key")).await,
+        CommandResponse::Ok
+    );
+}
+
+#[tokio::test]
+async fn test_auth_without_passwords() {
+    let dispatcher = dispatcher(&SecurityConfig::default());
+    let default = dispatcher.acl().initial_user().unwrap();
+
+    assert_eq!(
+        dispatcher.execute_as(0, Some(&default), set("key")).await,
+        CommandResponse::Ok
+    );
+    assert_eq!(
+        dispatcher
+            .execute_as(
+                0,
+                Some(&default),
+                Command::Auth(AuthCommand::new("anything".to_string())),
+            )
+            .await,
+        CommandResponse::Error("AUTH called without any password configured".to_string())
+    );
+}
+
+#[test]
+fn test_users_are_validated() {
+    let mut config = BlazeServerConfig::default();
+    config.security.users = vec![user("a", "x", &["@all"]), user("a", "y", &["@read"])];
+    assert!(config.validate().is_err());
+
+    config.security.users = vec![user("a", "x", &["@reads"])];
+    assert!(config.validate().is_err());
+
+    config.security.users = vec![user("a", "x", &["@read", "set"])];
+    assert!(config.validate().is_ok());
+
+    // Typos would silently grant nothing
+    config.security.users = vec![user("a", "x", &["sett"])];
+    assert!(config.validate().is_err());
+
+    config.security.users = vec![user("a", "x", &["DEL", "exists", "setex"])];
+    assert!(config.validate().is_ok());
+
+    // Nobody could log in
+    config.security.users.clear();
+    config.security.require_auth = true;
+    assert!(config.validate().is_err());
+}
@@ -0,0 +1,239 @@
+use std::{path::Path, sync::Arc, time::Duration};
+
+use blazekvdb::{
+    commands::{
+        Command, CommandDispatcher, CommandHandler, CommandResponse,
+        bgrewriteaof::BgRewriteAofCommand, delete::DeleteCommand, info::InfoCommand,
+        set::SetCommand,
+    },
+    config::{FsyncPolicy, PersistenceConfig},
+    storage::{
+        StorageConfig, StorageEngine,
+        engine::memory::MemoryEngine,
+        persistence::{
+            aof::{AppendOnlyFile, Operation},
+            manager::PersistenceManager,
+        },
+    },
+};
+use tempfile::tempdir;
+
+fn persistence_config(dir: &Path) -> PersistenceConfig {
+    PersistenceConfig {
+        aof_path: dir.join("test.aof"),
+        fsync_policy: FsyncPolicy::Always,
+        aof_rewrite_percentage: 0,
+        aof_rewrite_min_size: 0,
+        snapshot_enabled: false,
+        snapshot_dir: dir.join("snapshots"),
+        ..Default::default()
+    }
+}
+
+async fn setup(dir: &Path) -> (CommandDispatcher, Arc<PersistenceManager>) {
+    setup_with(persistence_config(dir)).await
+}
+
+async fn setup_with(config: PersistenceConfig) -> (CommandDispatcher, Arc<PersistenceManager>) {
+    let storage = Arc::new(MemoryEngine::new(StorageConfig::default())) as Arc<dyn StorageEngine>;
+    let persistence = Arc::new(
+        PersistenceManager::new(config, storage.clone())
+            .await
+            .unwrap(),
+    );
+    let dispatcher = CommandDispatcher::new(storage).with_persistence(persistence.clone());
+
+    (dispatcher, persistence)
+}
+
+async fn wait_for_rewrite(persistence: &PersistenceManager) {
+    for _ in 0..200 {
+        if !persistence.is_aof_rewrite_in_progress() {
+            return;
+        }
+        tokio::time::sleep(Duration::from_millis(10)).await;
+    }
+    panic!("AOF rewrite did not finish");
+}
+
+async fn set(dispatcher: &CommandDispatcher, key: &str, value: &[u8]) {
+    let cmd = Command::Set(SetCommand::new(key.to_string(), value.to_vec()));
+    assert_eq!(dispatcher.execute(cmd).await, CommandResponse::Ok);
+}
+
+async fn info_persistence(dispatcher: &CommandDispatcher) -> String {
+    let command = Command::Info(InfoCommand::new(Some("persistence".to_string())));
+    match dispatcher.execute(command).await {
+        CommandResponse::Value(info) => String::from_utf8(info).unwrap(),
+        other => panic!("Expected INFO text, got {:?}", other),
+    }
+}
+
+async fn logged_operations(path: &Path) -> Vec<Operation> {
+    AppendOnlyFile::new(path)
+        .await
+        .unwrap()
+        .read_operations()
+        .await
+        .unwrap()
+}
+
+#[tokio::test]
+async fn test_bgrewriteaof_without_persistence() {
+    let engine = Arc::new(MemoryEngine::new(StorageConfig::default())) as Arc<dyn StorageEngine>;
+
+    let response = BgRewriteAofCommand.execute(&*engine).await;
+    assert!(matches!(response, CommandResponse::Error(_)));
+}
+
+#[tokio::test]
+async fn test_bgrewriteaof_compacts_log() {
+    let temp_dir = tempdir().unwrap();
+    let (dispatcher, persistence) = setup(temp_dir.path()).await;
+
+    for i in 0..5 {
+        set(&dispatcher, "key1", format!("value{}", i).as_bytes()).await;
+    }
+    persistence.sync_aof().await.unwrap();
+    let aof_path = temp_dir.path().join("test.aof");
+    assert_eq!(logged_operations(&aof_path).await.len(), 5);
+
+    let response = dispatcher.execute(Command::BgRewriteAof).await;
+    assert_eq!(response, CommandResponse::Ok);
+    wait_for_rewrite(&persistence).await;
+
+    match logged_operations(&aof_path).await.as_slice() {
+        [Operation::Flush, Operation::Put { key, value }] => {
+            assert_eq!(key, "key1");
+            assert_eq!(value, b"value4");
+        }
+        other => panic!("Expected a flush and a single Put, got {:?}", other),
+    }
+
+    let stats = persistence.stats().await;
+    assert!(!stats.aof_rewrite_in_progress);
+    assert_eq!(stats.aof_rewrites_completed, 1);
+    assert_eq!(stats.aof_last_rewrite_error, None);
+}
+
+#[tokio::test]
+async fn test_bgrewriteaof_runs_one_at_a_time() {
+    let temp_dir = tempdir().unwrap();
+    let (dispatcher, persistence) = setup(temp_dir.path()).await;
+    set(&dispatcher, "key1", b"value1").await;
+
+    // Holding the AOF exclusively parks the first rewrite right after it is claimed
+    let aof = persistence.aof.clone().unwrap();
+    let hold = aof.write().await;
+
+    assert_eq!(
+        dispatcher.execute(Command::BgRewriteAof).await,
+        CommandResponse::Ok
+    );
+    assert!(persistence.is_aof_rewrite_in_progress());
+    assert!(
+        info_persistence(&dispatcher)
+            .await
+            .contains("aof_rewrite_in_progress:1\r\n")
+    );
+    assert!(matches!(
+        dispatcher.execute(Command::BgRewriteAof).await,
+        CommandResponse::Error(e) if e.contains("already in progress")
+    ));
+    assert!(persistence.compact_aof().await.is_err());
+
+    drop(hold);
+    wait_for_rewrite(&persistence).await;
+    assert_eq!(persistence.stats().await.aof_rewrites_completed, 1);
+    let info = info_persistence(&dispatcher).await;
+    assert!(info.starts_with("# Persistence\r\n"));
+    assert!(info.contains("aof_rewrite_in_progress:0\r\n"));
+    assert!(info.contains("aof_rewrites_completed:1\r\n"));
+
+    // The slot is free again
+    persistence.compact_aof().await.unwrap();
+    assert_eq!(persistence.stats().await.aof_rewrites_completed, 2);
+}
+
+#[tokio::test]
+async fn test_failed_rewrite_keeps_original_aof() {
+    let temp_dir = tempdir().unwrap();
+    let (dispatcher, persistence) = setup(temp_dir.path()).await;
+    let aof_path = temp_dir.path().join("test.aof");
+
+    set(&dispatcher, "key1", b"a").await;
+    set(&dispatcher, "key1", b"b").await;
+
+    // A directory in the way of the temporary file makes the rewrite fail
+    let temp_path = aof_path.with_extension("aof.tmp");
+    std::fs::create_dir(&temp_path).unwrap();
+
+    assert_eq!(
+        dispatcher.execute(Command::BgRewriteAof).await,
+        CommandResponse::Ok
+    );
+    wait_for_rewrite(&persistence).await;
+
+    let stats = persistence.stats().await;
+    assert_eq!(stats.aof_rewrites_completed, 0);
+    assert!(stats.aof_last_rewrite_error.is_some());
+    assert!(
+        info_persistence(&dispatcher)
+            .await
+            .contains("aof_last_rewrite_status:err\r\n")
+    );
+
+    // Every operation is still in the live file and new writes keep landing there
+    set(&dispatcher, "key2", b"c").await;
+    persistence.sync_aof().await.unwrap();
+    assert_eq!(logged_operations(&aof_path).await.len(), 3);
+
+    std::fs::remove_dir(&temp_path).unwrap();
+    persistence.compact_aof().await.unwrap();
+    // Flush marker plus the two keys
+    assert_eq!(logged_operations(&aof_path).await.len(), 3);
+    assert_eq!(persistence.stats().await.aof_last_rewrite_error, None);
+    assert!(
+        info_persistence(&dispatcher)
+            .await
+            .contains("aof_last_rewrite_status:ok\r\n")
+    );
+}
+
+#[tokio::test]
+async fn test_rewrite_supersedes_older_snapshot() {
+    let temp_dir = tempdir().unwrap();
+    let config = PersistenceConfig {
+        snapshot_enabled: true,
+        ..persistence_config(temp_dir.path())
+    };
+    let (dispatcher, persistence) = setup_with(config.clone()).await;
+
+    set(&dispatcher, "kept", b"a").await;
+    set(&dispatcher, "deleted", b"b").await;
+    persistence.create_snapshot().await.unwrap();
+
+    // Gone from the rewritten AOF but still in the snapshot
+    let response = dispatcher
+        .execute(Command::Delete(DeleteCommand::new("deleted".to_string())))
+        .await;
+    assert_eq!(response, CommandResponse::Integer(1));
+    assert_eq!(
+        dispatcher.execute(Command::BgRewriteAof).await,
+        CommandResponse::Ok
+    );
+    wait_for_rewrite(&persistence).await;
+    persistence.stop().await.unwrap();
+
+    let storage = Arc::new(MemoryEngine::new(StorageConfig::default())) as Arc<dyn StorageEngine>;
+    let stats = PersistenceManager::new(config, storage.clone())
+        .await
+        .unwrap()
+        .recover()
+        .await
+        .unwrap();
+
+    assert!(stats.snapshot_loaded);
+    assert_eq!(storage.get("kept").await.unwrap(), Some(b"a".to_vec()));
+    assert_eq!(storage.get("deleted").await.unwrap(), None);
+}
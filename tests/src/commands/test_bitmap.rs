@@ -0,0 +1,112 @@
+use std::sync::Arc;
+
+use blazekvdb::{
+    commands::{
+        CommandHandler, CommandResponse, KeyLimits, bitcount::BitCountCommand,
+        getbit::GetBitCommand, sadd::SAddCommand, setbit::SetBitCommand,
+    },
+    storage::{StorageConfig, StorageEngine, engine::memory::MemoryEngine},
+};
+
+#[test]
+fn test_setbit_validation() {
+    let limits = KeyLimits {
+        max_value_size: 2,
+        ..Default::default()
+    };
+
+    // The addressed byte must fit within the value limit
+    let cmd = SetBitCommand::new("key".to_string(), 15, true);
+    assert!(cmd.validate_with(&limits).is_ok());
+
+    let cmd = SetBitCommand::new("key".to_string(), 16, true);
+    assert!(cmd.validate_with(&limits).is_err());
+
+    let cmd = SetBitCommand::new("__blaze:key".to_string(), 0, true);
+    assert!(cmd.validate().is_err());
+}
+
+#[tokio::test]
+async fn test_setbit_getbit_bitcount() {
+    let engine = Arc::new(MemoryEngine::new(StorageConfig::default())) as Arc<dyn StorageEngine>;
+
+    // Bit 0 is the most significant bit of the first byte
+    for (offset, expected) in [(1, 0), (7, 0), (1, 1)] {
+        let cmd = SetBitCommand::new("bits".to_string(), offset, true);
+        assert_eq!(
+            cmd.execute(&*engine).await,
+            CommandResponse::Integer(expected)
+        );
+    }
+    assert_eq!(engine.get("bits").await.unwrap(), Some(vec![0b0100_0001]));
+
+    let cmd = SetBitCommand::new("bits".to_string(), 7, false);
+    assert_eq!(cmd.execute(&*engine).await, CommandResponse::Integer(1));
+
+    for (offset, expected) in [(0, 0), (1, 1), (7, 0), (1000, 0)] {
+        let cmd = GetBitCommand::new("bits".to_string(), offset);
+        assert_eq!(
+            cmd.execute(&*engine).await,
+            CommandResponse::Integer(expected),
+            "GETBIT bits {}",
+            offset
+        );
+    }
+
+    engine.set("text", b"foobar".to_vec()).await.unwrap();
+    let cmd = BitCountCommand::new("text".to_string());
+    assert_eq!(cmd.execute(&*engine).await, CommandResponse::Integer(26));
+
+    let cmd = BitCountCommand::new("missing".to_string());
+    assert_eq!(cmd.execute(&*engine).await, CommandResponse::Integer(0));
+    let cmd = GetBitCommand::new("missing".to_string(), 3);
+    assert_eq!(cmd.execute(&*engine).await, CommandResponse::Integer(0));
+}
+
+#[tokio::test]
+async fn test_setbit_grows_value() {
+    let engine = Arc::new(MemoryEngine::new(StorageConfig::default())) as Arc<dyn StorageEngine>;
+
+    let cmd = SetBitCommand::new("bits".to_string(), 0, true);
+    cmd.execute(&*engine).await;
+
+    // Growth is zero-filled and tracked
+    let before = engine.stats().await.unwrap().memory_usage;
+    let cmd = SetBitCommand::new("bits".to_string(), 8 * 9 + 7, true);
+    assert_eq!(cmd.execute(&*engine).await, CommandResponse::Integer(0));
+    assert_eq!(engine.stats().await.unwrap().memory_usage, before + 9);
+
+    let mut expected = vec![0u8; 10];
+    expected[0] = 0x80;
+    expected[9] = 0x01;
+    assert_eq!(engine.get("bits").await.unwrap(), Some(expected));
+
+    // Addressing an existing byte does not grow the value
+    let cmd = SetBitCommand::new("bits".to_string(), 12, true);
+    cmd.execute(&*engine).await;
+    assert_eq!(engine.stats().await.unwrap().memory_usage, before + 9);
+}
+
+#[tokio::test]
+async fn test_bit_commands_reject_sets() {
+    let engine = Arc::new(MemoryEngine::new(StorageConfig::default())) as Arc<dyn StorageEngine>;
+
+    let cmd = SAddCommand::new("set".to_string(), vec![b"a".to_vec()]);
+    cmd.execute(&*engine).await;
+
+    let cmd = SetBitCommand::new("set".to_string(), 0, true);
+    assert!(matches!(
+        cmd.execute(&*engine).await,
+        CommandResponse::Error(_)
+    ));
+    let cmd = GetBitCommand::new("set".to_string(), 0);
+    assert!(matches!(
+        cmd.execute(&*engine).await,
+        CommandResponse::Error(_)
+    ));
+    let cmd = BitCountCommand::new("set".to_string());
+    assert!(matches!(
+        cmd.execute(&*engine).await,
+        CommandResponse::Error(_)
+    ));
+}
@@ -0,0 +1,148 @@
+use blazekvdb::{
+    bootstrap::BlazeKVDB,
+    commands::{Command, CommandResponse, config::ConfigCommand, get::GetCommand, set::SetCommand},
+    config::{BlazeServerConfig, FsyncPolicy},
+};
+use tempfile::tempdir;
+
+fn get(param: &str) -> Command {
+    Command::Config(ConfigCommand::get(param.to_string()))
+}
+
+fn set(param: &str, value: &str) -> Command {
+    Command::Config(ConfigCommand::set(param.to_string(), value.to_string()))
+}
+
+#[tokio::test]
+async fn test_config_get_set() {
+    let temp_dir = tempdir().unwrap();
+
+    let mut config = BlazeServerConfig::default();
+    config.storage.max_memory = 1024 * 1024;
+    config.persistence.aof_path = temp_dir.path().join("test.aof");
+    config.persistence.snapshot_dir = temp_dir.path().join("snapshots");
+    config.persistence.fsync_policy = FsyncPolicy::Always;
+
+    let kvdb = BlazeKVDB::new(config).await.unwrap();
+
+    assert_eq!(
+        kvdb.execute(get("max_memory")).await,
+        CommandResponse::Value(b"1048576".to_vec())
+    );
+    assert_eq!(
+        kvdb.execute(get("fsync_policy")).await,
+        CommandResponse::Value(b"always".to_vec())
+    );
+
+    // Live-tunable parameters update the shared config
+    assert_eq!(
+        kvdb.execute(set("fsync_policy", "everyn:10")).await,
+        CommandResponse::Ok
+    );
+    assert_eq!(
+        kvdb.execute(get("fsync_policy")).await,
+        CommandResponse::Value(b"everyn:10".to_vec())
+    );
+    assert_eq!(
+        kvdb.config().read().persistence.fsync_policy,
+        FsyncPolicy::EveryN(10)
+    );
+
+    assert_eq!(
+        kvdb.execute(set("snapshot_interval", "120")).await,
+        CommandResponse::Ok
+    );
+    assert_eq!(kvdb.config().read().persistence.snapshot_interval, 120);
+
+    // Invalid values and startup-only parameters are refused without side effects
+    assert!(matches!(
+        kvdb.execute(set("fsync_policy", "sometimes")).await,
+        CommandResponse::Error(_)
+    ));
+    assert!(matches!(
+        kvdb.execute(set("max_memory", "1")).await,
+        CommandResponse::Error(_)
+    ));
+    assert_eq!(kvdb.config().read().storage.max_memory, 1024 * 1024);
+    assert!(matches!(
+        kvdb.execute(get("bind_addr")).await,
+        CommandResponse::Error(_)
+    ));
+}
+
+#[tokio::test]
+async fn test_config_set_readonly() {
+    let mut config = BlazeServerConfig::default();
+    config.persistence.enabled = false;
+    config.persistence.snapshot_enabled = false;
+
+    let kvdb = BlazeKVDB::new(config).await.unwrap();
+
+    assert_eq!(
+        kvdb.execute(set("readonly", "yes")).await,
+        CommandResponse::Ok
+    );
+    assert!(kvdb.is_read_only());
+    assert_eq!(
+        kvdb.execute(get("readonly")).await,
+        CommandResponse::Value(b"yes".to_vec())
+    );
+
+    let write = Command::Set(SetCommand::new("key".to_string(), b"value".to_vec()));
+    assert!(matches!(
+        kvdb.execute(write.clone()).await,
+        CommandResponse::Error(e) if e.starts_with("READONLY")
+    ));
+
+    // Snapshot settings need persistence
+    assert!(matches!(
+        kvdb.execute(set("snapshot_interval", "60")).await,
+        CommandResponse::Error(_)
+    ));
+
+    assert_eq!(
+        kvdb.execute(set("readonly", "no")).await,
+        CommandResponse::Ok
+    );
+    assert_eq!(kvdb.execute(write).await, CommandResponse::Ok);
+}
+
+#[tokio::test]
+async fn test_config_set_shard_count() {
+    let mut config = BlazeServerConfig::default();
+    config.persistence.enabled = false;
+    config.persistence.snapshot_enabled = false;
+
+    let kvdb = BlazeKVDB::new(config).await.unwrap();
+    for i in 0..100 {
+        let write = Command::Set(SetCommand::new(format!("key{}", i), b"value".to_vec()));
+        assert_eq!(kvdb.execute(write).await, CommandResponse::Ok);
+    }
+
+    assert_eq!(
+        kvdb.execute(set("shard_count", "64")).await,
+        CommandResponse::Ok
+    );
+    assert_eq!(
+        kvdb.execute(get("shard_count")).await,
+        CommandResponse::Value(b"64".to_vec())
+    );
+
+    // Keys stay reachable while and after they are migrated
+    for _ in 0..2 {
+        for i in 0..100 {
+            let read = Command::Get(GetCommand::new(format!("key{}", i)));
+            assert_eq!(
+                kvdb.execute(read).await,
+                CommandResponse::Value(b"value".to_vec())
+            );
+        }
+        tokio::time::sleep(std::time::Duration::from_millis(50)).await;
+    }
+
+    assert!(matches!(
+        kvdb.execute(set("shard_count", "0")).await,
+        CommandResponse::Error(_)
+    ));
+    assert_eq!(kvdb.config().read().storage.shard_count, 64);
+}
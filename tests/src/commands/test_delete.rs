@@ -1,9 +1,21 @@
-use std::sync::Arc;
+use std::{sync::Arc, time::Duration};
 
 use blazekvdb::{
-    commands::{CommandHandler, CommandResponse, delete::DeleteCommand},
-    storage::{StorageConfig, StorageEngine, engine::memory::MemoryEngine},
+    commands::{
+        Command, CommandDispatcher, CommandHandler, CommandResponse, delete::DeleteCommand,
+        delprefix::DelPrefixCommand, set::SetCommand, wait::WaitCommand,
+    },
+    config::{FsyncPolicy, PersistenceConfig},
+    storage::{
+        StorageConfig, StorageEngine,
+        engine::memory::MemoryEngine,
+        persistence::{
+            aof::{AppendOnlyFile, Operation},
+            manager::PersistenceManager,
+        },
+    },
 };
+use tempfile::tempdir;
 
 #[test]
 fn test_delete_validation() {
@@ -20,5 +32,213 @@ async fn test_delete_execute() {
 
     let cmd = DeleteCommand::new("key".to_string());
     let response = cmd.execute(&*engine).await;
-    assert_eq!(response, CommandResponse::Bool(true))
+    assert_eq!(response, CommandResponse::Integer(1))
+}
+
+#[tokio::test]
+async fn test_delete_many_execute() {
+    let config = StorageConfig::default();
+    let engine = Arc::new(MemoryEngine::new(config)) as Arc<dyn StorageEngine>;
+
+    engine.set("key1", b"value1".to_vec()).await.unwrap();
+    engine.set("key2", b"value2".to_vec()).await.unwrap();
+
+    // Missing and repeated keys don't count
+    let cmd = DeleteCommand::many(vec![
+        "key1".to_string(),
+        "missing".to_string(),
+        "key2".to_string(),
+        "key1".to_string(),
+    ]);
+    assert_eq!(cmd.execute(&*engine).await, CommandResponse::Integer(2));
+    assert_eq!(engine.stats().await.unwrap().total_keys, 0);
+}
+
+#[tokio::test]
+async fn test_delete_logs_only_existing_keys() {
+    let temp_dir = tempdir().unwrap();
+    let aof_path = temp_dir.path().join("test.aof");
+
+    let config = PersistenceConfig {
+        aof_path: aof_path.clone(),
+        fsync_policy: FsyncPolicy::Always,
+        aof_rewrite_percentage: 0,
+        aof_rewrite_min_size: 0,
+        snapshot_enabled: false,
+        snapshot_dir: temp_dir.path().join("snapshots"),
+        ..Default::default()
+    };
+
+    let storage = Arc::new(MemoryEngine::new(StorageConfig::default())) as Arc<dyn StorageEngine>;
+    let persistence = Arc::new(
+        PersistenceManager::new(config, storage.clone())
+            .await
+            .unwrap(),
+    );
+    let dispatcher = CommandDispatcher::new(storage).with_persistence(persistence);
+
+    let set = SetCommand::new("key1".to_string(), b"value1".to_vec());
+    dispatcher.execute(Command::Set(set)).await;
+
+    let delete = DeleteCommand::many(vec!["key1".to_string(), "missing".to_string()]);
+    assert_eq!(
+        dispatcher.execute(Command::Delete(delete)).await,
+        CommandResponse::Integer(1)
+    );
+    dispatcher
+        .execute(Command::Wait(WaitCommand::new(0, 1000)))
+        .await;
+
+    let ops = AppendOnlyFile::new(&aof_path)
+        .await
+        .unwrap()
+        .read_operations()
+        .await
+        .unwrap();
+    assert_eq!(ops.len(), 2);
+    assert!(matches!(&ops[1], Operation::Delete { key } if key == "key1"));
+}
+
+#[test]
+fn test_delprefix_validation() {
+    assert!(
+        DelPrefixCommand::new("user:".to_string())
+            .validate()
+            .is_ok()
+    );
+    assert!(DelPrefixCommand::all().validate().is_ok());
+
+    // Nothing wipes the database by accident, or touches internal metadata
+    assert!(DelPrefixCommand::new("".to_string()).validate().is_err());
+    assert!(
+        DelPrefixCommand::new("__blaze:x".to_string())
+            .validate()
+            .is_err()
+    );
+    // Nor a prefix the reserved namespace starts with
+    for prefix in ["_", "__bl", "__blaze:"] {
+        assert!(
+            DelPrefixCommand::new(prefix.to_string())
+                .validate()
+                .is_err()
+        );
+    }
+    let cmd = DelPrefixCommand {
+        prefix: "user:".to_string(),
+        all: true,
+    };
+    assert!(cmd.validate().is_err());
+}
+
+#[tokio::test]
+async fn test_delprefix_execute() {
+    let engine = Arc::new(MemoryEngine::new(StorageConfig::default())) as Arc<dyn StorageEngine>;
+
+    for key in ["user:1", "user:2", "user:3", "users", "session:1"] {
+        engine.set(key, b"value".to_vec()).await.unwrap();
+    }
+    engine
+        .set_with_ttl("user:gone", b"value".to_vec(), Duration::from_millis(1))
+        .await
+        .unwrap();
+    tokio::time::sleep(Duration::from_millis(5)).await;
+    let memory = engine.stats().await.unwrap().memory_usage;
+
+    // The expired key is removed but not counted
+    let cmd = DelPrefixCommand::new("user:".to_string());
+    assert_eq!(cmd.execute(&*engine).await, CommandResponse::Integer(3));
+    assert!(!engine.exists("user:1").await.unwrap());
+    assert!(engine.exists("users").await.unwrap());
+
+    let stats = engine.stats().await.unwrap();
+    assert_eq!(stats.total_keys, 2);
+    assert!(stats.memory_usage < memory);
+
+    assert_eq!(
+        DelPrefixCommand::all().execute(&*engine).await,
+        CommandResponse::Integer(2)
+    );
+    assert_eq!(engine.stats().await.unwrap().total_keys, 0);
+    assert_eq!(engine.stats().await.unwrap().memory_usage, 0);
+}
+
+#[tokio::test]
+async fn test_delprefix_keeps_internal_keys() {
+    let engine = Arc::new(MemoryEngine::new(StorageConfig::default())) as Arc<dyn StorageEngine>;
+    let dispatcher = CommandDispatcher::new(engine.clone());
+
+    for key in ["__blaze:meta", "_user", "user:1"] {
+        engine.set(key, b"value".to_vec()).await.unwrap();
+    }
+
+    // Refused before it reaches the engine
+    let response = dispatcher
+        .execute(Command::DelPrefix(DelPrefixCommand::new("_".to_string())))
+        .await;
+    assert!(matches!(response, CommandResponse::Error(_)));
+    assert!(engine.exists("_user").await.unwrap());
+
+    // --all removes every user key and nothing else
+    assert_eq!(
+        dispatcher
+            .execute(Command::DelPrefix(DelPrefixCommand::all()))
+            .await,
+        CommandResponse::Integer(2)
+    );
+    assert!(engine.exists("__blaze:meta").await.unwrap());
+    assert_eq!(engine.stats().await.unwrap().total_keys, 1);
+}
+
+#[tokio::test]
+async fn test_delprefix_logs_each_deleted_key() {
+    let temp_dir = tempdir().unwrap();
+    let aof_path = temp_dir.path().join("test.aof");
+
+    let config = PersistenceConfig {
+        aof_path: aof_path.clone(),
+        fsync_policy: FsyncPolicy::Always,
+        aof_rewrite_percentage: 0,
+        aof_rewrite_min_size: 0,
+        snapshot_enabled: false,
+        snapshot_dir: temp_dir.path().join("snapshots"),
+        ..Default::default()
+    };
+
+    let storage = Arc::new(MemoryEngine::new(StorageConfig::default())) as Arc<dyn StorageEngine>;
+    let persistence = Arc::new(
+        PersistenceManager::new(config, storage.clone())
+            .await
+            .unwrap(),
+    );
+    let dispatcher = CommandDispatcher::new(storage).with_persistence(persistence);
+
+    for key in ["tmp:a", "tmp:b", "keep"] {
+        let set = SetCommand::new(key.to_string(), b"value".to_vec());
+        dispatcher.execute(Command::Set(set)).await;
+    }
+
+    let delete = DelPrefixCommand::new("tmp:".to_string());
+    assert_eq!(
+        dispatcher.execute(Command::DelPrefix(delete)).await,
+        CommandResponse::Integer(2)
+    );
+    dispatcher
+        .execute(Command::Wait(WaitCommand::new(0, 1000)))
+        .await;
+
+    let ops = AppendOnlyFile::new(&aof_path)
+        .await
+        .unwrap()
+        .read_operations()
+        .await
+        .unwrap();
+    let mut deleted: Vec<&str> = ops[3..]
+        .iter()
+        .map(|op| match op {
+            Operation::Delete { key } => key.as_str(),
+            other => panic!("Expected a Delete, got {:?}", other),
+        })
+        .collect();
+    deleted.sort();
+    assert_eq!(deleted, ["tmp:a", "tmp:b"]);
 }
@@ -1,12 +1,26 @@
-use std::sync::Arc;
+use std::sync::{
+    Arc,
+    atomic::{AtomicU64, Ordering},
+};
 
 use blazekvdb::{
     commands::{
-        Command, CommandDispatcher, CommandResponse, delete::DeleteCommand, exist::ExistCommand,
-        get::GetCommand, scan::ScanCommand, set::SetCommand,
+        Command, CommandDispatcher, CommandResponse, KeyLimits, SampledSpans, TraceSampler,
+        delete::DeleteCommand, exist::ExistCommand, get::GetCommand, readonly::ReadOnlyCommand,
+        scan::ScanCommand, set::SetCommand,
     },
     storage::{StorageConfig, StorageEngine, engine::memory::MemoryEngine},
 };
+use tracing::{
+    Event, Subscriber,
+    instrument::WithSubscriber,
+    span::{Attributes, Id},
+};
+use tracing_subscriber::{
+    Layer,
+    filter::LevelFilter,
+    layer::{Context, SubscriberExt},
+};
 
 #[tokio::test]
 async fn test_command_dispatcher() {
@@ -42,19 +56,24 @@ async fn test_command_dispatcher() {
         memory_usage,
         hit_rate,
         total_operations,
+        keyspace_hits,
+        keyspace_misses,
+        evicted_keys,
     } = stats_response
     {
         assert!(total_keys != 0);
         assert!(total_operations != 0);
         assert!(hit_rate.is_finite());
-        assert!(memory_usage != 0)
+        assert!(memory_usage != 0);
+        assert_eq!((keyspace_hits, keyspace_misses), (1, 0));
+        assert_eq!(evicted_keys, 0);
     } else {
         panic!("Expected Stats response");
     }
 
     let delete_command = DeleteCommand::new("key1".to_string());
     let delete_response = dispatcher.execute(Command::Delete(delete_command)).await;
-    assert_eq!(delete_response, CommandResponse::Bool(true))
+    assert_eq!(delete_response, CommandResponse::Integer(1))
 }
 
 #[tokio::test]
@@ -78,5 +97,239 @@ async fn test_batch_commands() {
     assert_eq!(responses[1], CommandResponse::Ok);
     assert_eq!(responses[2], CommandResponse::Value(b"value1".to_vec()));
     assert_eq!(responses[3], CommandResponse::Bool(true));
-    assert_eq!(responses[4], CommandResponse::Bool(true));
+    assert_eq!(responses[4], CommandResponse::Integer(1));
+}
+
+#[tokio::test]
+async fn test_batch_concurrent_reads_preserve_order() {
+    let config = StorageConfig::default();
+    let engine = Arc::new(MemoryEngine::new(config)) as Arc<dyn StorageEngine>;
+    let dispatcher = CommandDispatcher::new(engine);
+
+    for i in 0..100 {
+        let cmd = SetCommand::new(format!("key{}", i), format!("value{}", i).into_bytes());
+        dispatcher.execute(Command::Set(cmd)).await;
+    }
+
+    // 100-GET pipeline runs as a single concurrent read run
+    let commands = (0..100)
+        .map(|i| Command::Get(GetCommand::new(format!("key{}", i))))
+        .collect();
+
+    let responses = dispatcher.execute_batch(commands).await;
+
+    assert_eq!(responses.len(), 100);
+    for (i, response) in responses.into_iter().enumerate() {
+        assert_eq!(
+            response,
+            CommandResponse::Value(format!("value{}", i).into_bytes())
+        );
+    }
+}
+
+#[tokio::test]
+async fn test_batch_write_flushes_reads() {
+    let config = StorageConfig::default();
+    let engine = Arc::new(MemoryEngine::new(config)) as Arc<dyn StorageEngine>;
+    let dispatcher = CommandDispatcher::new(engine);
+
+    let commands = vec![
+        Command::Exist(ExistCommand::new("key1".to_string())),
+        Command::Set(SetCommand::new("key1".to_string(), b"value1".to_vec())),
+        Command::Exist(ExistCommand::new("key1".to_string())),
+        Command::Delete(DeleteCommand::new("key1".to_string())),
+        Command::Exist(ExistCommand::new("key1".to_string())),
+    ];
+
+    let responses = dispatcher.execute_batch(commands).await;
+
+    assert_eq!(
+        responses,
+        vec![
+            CommandResponse::Bool(false),
+            CommandResponse::Ok,
+            CommandResponse::Bool(true),
+            CommandResponse::Integer(1),
+            CommandResponse::Bool(false),
+        ]
+    );
+}
+
+#[tokio::test]
+async fn test_dispatcher_key_limits() {
+    let engine = Arc::new(MemoryEngine::new(StorageConfig::default())) as Arc<dyn StorageEngine>;
+    let dispatcher = CommandDispatcher::new(engine).with_limits(KeyLimits {
+        max_key_size: 12,
+        max_value_size: 4,
+        reserved_prefix: Some("__blaze:".to_string()),
+    });
+
+    let too_long = SetCommand::new("longer-than-twelve".to_string(), b"v".to_vec());
+    assert!(matches!(
+        dispatcher.execute(Command::Set(too_long)).await,
+        CommandResponse::Error(_)
+    ));
+
+    let too_big = SetCommand::new("k".to_string(), b"12345".to_vec());
+    assert!(matches!(
+        dispatcher.execute(Command::Set(too_big)).await,
+        CommandResponse::Error(_)
+    ));
+
+    // User writes into the reserved namespace are rejected, internal ones pass
+    let user = SetCommand::new("__blaze:x".to_string(), b"v".to_vec());
+    let response = dispatcher.execute(Command::Set(user)).await;
+    assert!(matches!(response, CommandResponse::Error(ref e) if e.contains("reserved")));
+
+    let internal = SetCommand::internal("__blaze:x".to_string(), b"v".to_vec());
+    assert_eq!(
+        dispatcher.execute(Command::Set(internal)).await,
+        CommandResponse::Ok
+    );
+
+    let delete = DeleteCommand::new("__blaze:x".to_string());
+    assert!(matches!(
+        dispatcher.execute(Command::Delete(delete)).await,
+        CommandResponse::Error(_)
+    ));
+}
+
+#[tokio::test]
+async fn test_dispatcher_read_only_mode() {
+    let engine = Arc::new(MemoryEngine::new(StorageConfig::default())) as Arc<dyn StorageEngine>;
+    let dispatcher = CommandDispatcher::new(engine);
+
+    let set = |key: &str| Command::Set(SetCommand::new(key.to_string(), b"value".to_vec()));
+    assert_eq!(dispatcher.execute(set("key1")).await, CommandResponse::Ok);
+
+    let response = dispatcher
+        .execute(Command::ReadOnly(ReadOnlyCommand::new(true)))
+        .await;
+    assert_eq!(response, CommandResponse::Ok);
+    assert!(dispatcher.is_read_only());
+
+    // Writes are refused, reads still served
+    assert!(matches!(
+        dispatcher.execute(set("key2")).await,
+        CommandResponse::Error(e) if e.starts_with("READONLY")
+    ));
+    let delete = Command::Delete(DeleteCommand::new("key1".to_string()));
+    assert!(matches!(
+        dispatcher.execute(delete).await,
+        CommandResponse::Error(e) if e.starts_with("READONLY")
+    ));
+    let get = Command::Get(GetCommand::new("key1".to_string()));
+    assert_eq!(
+        dispatcher.execute(get).await,
+        CommandResponse::Value(b"value".to_vec())
+    );
+
+    dispatcher
+        .execute(Command::ReadOnly(ReadOnlyCommand::new(false)))
+        .await;
+    assert_eq!(dispatcher.execute(set("key2")).await, CommandResponse::Ok);
+}
+
+#[tokio::test]
+async fn test_dispatcher_refuses_commands_while_recovering() {
+    let engine = Arc::new(MemoryEngine::new(StorageConfig::default())) as Arc<dyn StorageEngine>;
+    engine.set("key1", b"value".to_vec()).await.unwrap();
+    let dispatcher = CommandDispatcher::new(engine);
+
+    dispatcher.set_recovering(true);
+    assert!(dispatcher.is_recovering());
+
+    // Reads are refused too, they could observe a half-replayed dataset
+    let get = Command::Get(GetCommand::new("key1".to_string()));
+    assert!(matches!(
+        dispatcher.execute(get.clone()).await,
+        CommandResponse::Error(e) if e.starts_with("LOADING")
+    ));
+    let set = Command::Set(SetCommand::new("key2".to_string(), b"value".to_vec()));
+    assert!(matches!(
+        dispatcher.execute(set).await,
+        CommandResponse::Error(e) if e.starts_with("LOADING")
+    ));
+    assert_eq!(
+        dispatcher.execute(Command::Ping).await,
+        CommandResponse::Pong
+    );
+
+    dispatcher.set_recovering(false);
+    assert_eq!(
+        dispatcher.execute(get).await,
+        CommandResponse::Value(b"value".to_vec())
+    );
+}
+
+// Counts the spans and events reaching a layer filtered like the server's log output
+#[derive(Clone, Default)]
+struct TraceCounter {
+    spans: Arc<AtomicU64>,
+    events: Arc<AtomicU64>,
+}
+
+impl<S: Subscriber> Layer<S> for TraceCounter {
+    fn on_new_span(&self, _: &Attributes<'_>, _: &Id, _: Context<'_, S>) {
+        self.spans.fetch_add(1, Ordering::Relaxed);
+    }
+
+    fn on_event(&self, _: &Event<'_>, _: Context<'_, S>) {
+        self.events.fetch_add(1, Ordering::Relaxed);
+    }
+}
+
+#[test]
+fn test_trace_sampler_rate() {
+    let sampler = TraceSampler::new(0.25);
+    let sampled = (0..100).filter(|_| sampler.sample()).count();
+    assert_eq!(sampled, 25);
+
+    assert!((0..10).all(|_| TraceSampler::new(1.0).sample()));
+    assert!(!(0..10).any(|_| TraceSampler::new(0.0).sample()));
+
+    // Out-of-range rates are clamped
+    assert_eq!(TraceSampler::new(7.0).rate(), 1.0);
+}
+
+#[tokio::test]
+async fn test_dispatcher_trace_sampling() {
+    async fn traced_with(rate: f64) -> (u64, u64) {
+        let engine =
+            Arc::new(MemoryEngine::new(StorageConfig::default())) as Arc<dyn StorageEngine>;
+        let dispatcher = CommandDispatcher::new(engine).with_trace_sample_rate(rate);
+        let counter = TraceCounter::default();
+        let subscriber = tracing_subscriber::registry()
+            .with(counter.clone().with_filter(SampledSpans))
+            .with(LevelFilter::DEBUG);
+
+        async {
+            for i in 0..10 {
+                let response = dispatcher
+                    .execute(Command::Set(SetCommand::new(
+                        format!("key{}", i),
+                        b"v".to_vec(),
+                    )))
+                    .await;
+                assert_eq!(response, CommandResponse::Ok);
+            }
+        }
+        .with_subscriber(subscriber)
+        .await;
+
+        (
+            counter.spans.load(Ordering::Relaxed),
+            counter.events.load(Ordering::Relaxed),
+        )
+    }
+
+    let (full, events) = traced_with(1.0).await;
+    assert!(full >= 10, "every command should emit spans, got {}", full);
+    assert!(events >= 10, "every command should log, got {}", events);
+
+    // Commands still execute and log, they just don't trace
+    assert_eq!(traced_with(0.0).await, (0, events));
+
+    let (half, _) = traced_with(0.5).await;
+    assert_eq!(half, full / 2);
 }
@@ -0,0 +1,176 @@
+use std::sync::Arc;
+
+use blazekvdb::{
+    commands::{
+        Command, CommandDispatcher, CommandHandler, CommandResponse,
+        eval::{AtomicOp, EvalCommand},
+        sadd::SAddCommand,
+        wait::WaitCommand,
+    },
+    config::{FsyncPolicy, PersistenceConfig},
+    storage::{
+        StorageConfig, StorageEngine,
+        engine::memory::MemoryEngine,
+        persistence::{
+            aof::{AppendOnlyFile, Operation},
+            manager::PersistenceManager,
+        },
+    },
+};
+use tempfile::tempdir;
+
+fn cas(key: &str, expected: &[u8], new: &[u8]) -> EvalCommand {
+    EvalCommand::new(
+        key.to_string(),
+        AtomicOp::Cas {
+            expected: expected.to_vec(),
+            new: new.to_vec(),
+        },
+    )
+}
+
+fn addmax(key: &str, n: f64) -> EvalCommand {
+    EvalCommand::new(key.to_string(), AtomicOp::AddMax(n))
+}
+
+#[test]
+fn test_eval_validation() {
+    assert!(cas("key", b"a", b"b").validate().is_ok());
+    assert!(cas("", b"a", b"b").validate().is_err());
+    assert!(cas("__blaze:key", b"a", b"b").validate().is_err());
+    assert!(addmax("key", 1.0).validate().is_ok());
+    assert!(addmax("key", f64::NAN).validate().is_err());
+}
+
+#[tokio::test]
+async fn test_eval_cas() {
+    let engine = Arc::new(MemoryEngine::new(StorageConfig::default())) as Arc<dyn StorageEngine>;
+
+    // A missing key never matches
+    assert_eq!(
+        cas("lock", b"free", b"taken").execute(&*engine).await,
+        CommandResponse::Bool(false)
+    );
+    assert_eq!(engine.get("lock").await.unwrap(), None);
+
+    engine.set("lock", b"free".to_vec()).await.unwrap();
+    assert_eq!(
+        cas("lock", b"free", b"taken").execute(&*engine).await,
+        CommandResponse::Bool(true)
+    );
+    assert_eq!(
+        cas("lock", b"free", b"taken").execute(&*engine).await,
+        CommandResponse::Bool(false)
+    );
+    assert_eq!(engine.get("lock").await.unwrap(), Some(b"taken".to_vec()));
+
+    SAddCommand::new("set".to_string(), vec![b"a".to_vec()])
+        .execute(&*engine)
+        .await;
+    assert!(matches!(
+        cas("set", b"a", b"b").execute(&*engine).await,
+        CommandResponse::Error(msg) if msg.starts_with("WRONGTYPE")
+    ));
+}
+
+#[tokio::test]
+async fn test_eval_addmax() {
+    let engine = Arc::new(MemoryEngine::new(StorageConfig::default())) as Arc<dyn StorageEngine>;
+
+    assert_eq!(
+        addmax("peak", 10.0).execute(&*engine).await,
+        CommandResponse::Value(b"10".to_vec())
+    );
+    assert_eq!(
+        addmax("peak", 3.5).execute(&*engine).await,
+        CommandResponse::Value(b"10".to_vec())
+    );
+    assert_eq!(
+        addmax("peak", 12.25).execute(&*engine).await,
+        CommandResponse::Value(b"12.25".to_vec())
+    );
+    assert_eq!(engine.get("peak").await.unwrap(), Some(b"12.25".to_vec()));
+
+    engine.set("text", b"abc".to_vec()).await.unwrap();
+    assert_eq!(
+        addmax("text", 1.0).execute(&*engine).await,
+        CommandResponse::Error("Value is not a valid float".to_string())
+    );
+}
+
+#[tokio::test]
+async fn test_eval_is_atomic() {
+    let engine = Arc::new(MemoryEngine::new(StorageConfig::default())) as Arc<dyn StorageEngine>;
+    engine.set("lock", b"free".to_vec()).await.unwrap();
+
+    // Exactly one of many racing swaps wins
+    let tasks: Vec<_> = (0..32)
+        .map(|i| {
+            let engine = engine.clone();
+            tokio::spawn(async move {
+                cas("lock", b"free", format!("owner{}", i).as_bytes())
+                    .execute(&*engine)
+                    .await
+            })
+        })
+        .collect();
+
+    let mut won = 0;
+    for task in tasks {
+        if task.await.unwrap() == CommandResponse::Bool(true) {
+            won += 1;
+        }
+    }
+    assert_eq!(won, 1);
+}
+
+#[tokio::test]
+async fn test_eval_logs_writes_only() {
+    let temp_dir = tempdir().unwrap();
+    let aof_path = temp_dir.path().join("test.aof");
+
+    let config = PersistenceConfig {
+        aof_path: aof_path.clone(),
+        fsync_policy: FsyncPolicy::Always,
+        aof_rewrite_percentage: 0,
+        aof_rewrite_min_size: 0,
+        snapshot_enabled: false,
+        snapshot_dir: temp_dir.path().join("snapshots"),
+        ..Default::default()
+    };
+
+    let storage = Arc::new(MemoryEngine::new(StorageConfig::default())) as Arc<dyn StorageEngine>;
+    let persistence = Arc::new(
+        PersistenceManager::new(config, storage.clone())
+            .await
+            .unwrap(),
+    );
+    let dispatcher = CommandDispatcher::new(storage).with_persistence(persistence);
+
+    for (command, expected) in [
+        (addmax("peak", 5.0), CommandResponse::Value(b"5".to_vec())),
+        (addmax("peak", 1.0), CommandResponse::Value(b"5".to_vec())),
+        (cas("peak", b"5", b"0"), CommandResponse::Bool(true)),
+        (cas("peak", b"5", b"1"), CommandResponse::Bool(false)),
+    ] {
+        assert_eq!(dispatcher.execute(Command::Eval(command)).await, expected);
+    }
+    dispatcher
+        .execute(Command::Wait(WaitCommand::new(0, 1000)))
+        .await;
+
+    let ops = AppendOnlyFile::new(&aof_path)
+        .await
+        .unwrap()
+        .read_operations()
+        .await
+        .unwrap();
+    let values: Vec<Vec<u8>> = ops
+        .into_iter()
+        .map(|op| match op {
+            Operation::Update { key, value } if key == "peak" => value,
+            other => panic!("unexpected operation {:?}", other),
+        })
+        .collect();
+    assert_eq!(values, vec![b"5".to_vec(), b"0".to_vec()]);
+}
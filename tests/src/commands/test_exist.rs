@@ -22,3 +22,23 @@ async fn test_exist_execute() {
     let response = cmd.execute(&*engine).await;
     assert_eq!(response, CommandResponse::Bool(true));
 }
+
+#[tokio::test]
+async fn test_exists_counts_keys() {
+    let engine = Arc::new(MemoryEngine::new(StorageConfig::default())) as Arc<dyn StorageEngine>;
+
+    engine.set("key1", b"value1".to_vec()).await.unwrap();
+    engine.set("key2", b"value2".to_vec()).await.unwrap();
+
+    // Duplicates count once per occurrence
+    let cmd = ExistCommand::many(vec![
+        "key1".to_string(),
+        "missing".to_string(),
+        "key2".to_string(),
+        "key1".to_string(),
+    ]);
+    assert_eq!(cmd.execute(&*engine).await, CommandResponse::Integer(3));
+
+    let cmd = ExistCommand::many(vec!["missing".to_string()]);
+    assert_eq!(cmd.execute(&*engine).await, CommandResponse::Integer(0));
+}
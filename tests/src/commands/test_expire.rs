@@ -0,0 +1,89 @@
+use std::{sync::Arc, time::Duration};
+
+use blazekvdb::{
+    commands::{CommandHandler, CommandResponse, expire::ExpireCommand, ttl::TtlCommand},
+    storage::{StorageConfig, StorageEngine, engine::memory::MemoryEngine, now_millis},
+};
+
+fn engine() -> Arc<dyn StorageEngine> {
+    Arc::new(MemoryEngine::new(StorageConfig::default()))
+}
+
+async fn pttl(engine: &dyn StorageEngine, key: &str) -> i64 {
+    match TtlCommand::new(key.to_string(), true).execute(engine).await {
+        CommandResponse::Integer(ttl) => ttl,
+        other => panic!("Expected PTTL integer, got {:?}", other),
+    }
+}
+
+#[test]
+fn test_expire_names() {
+    let name = |millis, absolute| ExpireCommand::new("k".to_string(), 1, millis, absolute).name();
+    assert_eq!(name(false, false), "EXPIRE");
+    assert_eq!(name(true, false), "PEXPIRE");
+    assert_eq!(name(false, true), "EXPIREAT");
+    assert_eq!(name(true, true), "PEXPIREAT");
+    assert!(
+        ExpireCommand::new(String::new(), 1, false, false)
+            .validate()
+            .is_err()
+    );
+}
+
+#[tokio::test]
+async fn test_pexpire_has_sub_second_precision() {
+    let engine = engine();
+    engine.set("key", b"value".to_vec()).await.unwrap();
+
+    let cmd = ExpireCommand::new("key".to_string(), 100, true, false);
+    assert_eq!(cmd.execute(&*engine).await, CommandResponse::Bool(true));
+
+    let ttl = pttl(&*engine, "key").await;
+    assert!((1..=100).contains(&ttl), "{}", ttl);
+    assert_eq!(
+        TtlCommand::new("key".to_string(), false)
+            .execute(&*engine)
+            .await,
+        CommandResponse::Integer(0)
+    );
+
+    tokio::time::sleep(Duration::from_millis(150)).await;
+    assert_eq!(engine.get("key").await.unwrap(), None);
+    assert_eq!(pttl(&*engine, "key").await, -2);
+}
+
+#[tokio::test]
+async fn test_expireat_uses_absolute_time() {
+    let engine = engine();
+    engine.set("later", b"v".to_vec()).await.unwrap();
+    engine.set("past", b"v".to_vec()).await.unwrap();
+
+    let at = now_millis() / 1000 + 100;
+    let cmd = ExpireCommand::new("later".to_string(), at, false, true);
+    assert_eq!(cmd.execute(&*engine).await, CommandResponse::Bool(true));
+    let ttl = pttl(&*engine, "later").await;
+    assert!((99_000..=100_000).contains(&ttl), "{}", ttl);
+
+    // A time already gone removes the key right away
+    let cmd = ExpireCommand::new("past".to_string(), 1, true, true);
+    assert_eq!(cmd.execute(&*engine).await, CommandResponse::Bool(true));
+    assert!(!engine.exists("past").await.unwrap());
+}
+
+#[tokio::test]
+async fn test_ttl_of_missing_and_persistent_keys() {
+    let engine = engine();
+    engine.set("forever", b"v".to_vec()).await.unwrap();
+
+    assert_eq!(pttl(&*engine, "forever").await, -1);
+    assert_eq!(pttl(&*engine, "missing").await, -2);
+
+    let cmd = ExpireCommand::new("missing".to_string(), 10, false, false);
+    assert_eq!(cmd.execute(&*engine).await, CommandResponse::Bool(false));
+
+    let cmd = ExpireCommand::new("forever".to_string(), u64::MAX, false, false);
+    assert!(matches!(
+        cmd.execute(&*engine).await,
+        CommandResponse::Error(_)
+    ));
+}
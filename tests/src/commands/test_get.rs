@@ -1,7 +1,9 @@
 use std::sync::Arc;
 
 use blazekvdb::{
-    commands::{CommandHandler, CommandResponse, get::GetCommand},
+    bootstrap::BlazeKVDB,
+    commands::{Command, CommandHandler, CommandResponse, get::GetCommand},
+    config::{BlazeServerConfig, GetMissingBehavior, ProtocolConfig},
     storage::{StorageConfig, StorageEngine, engine::memory::MemoryEngine},
 };
 
@@ -22,3 +24,33 @@ async fn test_get_execute() {
     let response = cmd.execute(&*engine).await;
     assert_eq!(response, CommandResponse::Value(b"value1".to_vec()));
 }
+
+#[tokio::test]
+async fn test_get_missing_behavior() {
+    let mut config = BlazeServerConfig::default();
+    config.persistence.enabled = false;
+    let kvdb = BlazeKVDB::new(config).await.unwrap();
+    let get = || Command::Get(GetCommand::new("missing".to_string()));
+
+    // Nil by default
+    assert_eq!(kvdb.execute(get()).await, CommandResponse::Nil);
+
+    kvdb.config().write().protocol.get_missing_behavior = GetMissingBehavior::Error;
+    assert_eq!(
+        kvdb.execute(get()).await,
+        CommandResponse::Error("Key not found".to_string())
+    );
+
+    kvdb.config().write().protocol.get_missing_behavior = GetMissingBehavior::Empty;
+    assert_eq!(
+        kvdb.execute(get()).await,
+        CommandResponse::Value(Vec::new())
+    );
+
+    kvdb.shutdown().await.unwrap();
+
+    // Configured by its lowercase name
+    let parsed: ProtocolConfig =
+        serde_json::from_str(r#"{ "get_missing_behavior": "error" }"#).unwrap();
+    assert_eq!(parsed.get_missing_behavior, GetMissingBehavior::Error);
+}
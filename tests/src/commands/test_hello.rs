@@ -0,0 +1,43 @@
+use std::sync::Arc;
+
+use blazekvdb::{
+    commands::{
+        CommandHandler, CommandResponse,
+        hello::{HelloCommand, PROTOCOL_VERSION},
+    },
+    storage::{StorageConfig, StorageEngine, engine::memory::MemoryEngine},
+};
+
+#[tokio::test]
+async fn test_hello_command() {
+    let engine = Arc::new(MemoryEngine::new(StorageConfig::default())) as Arc<dyn StorageEngine>;
+
+    let response = HelloCommand::new(None).execute(&*engine).await;
+    let CommandResponse::Map(fields) = response else {
+        panic!("Expected a map, got {:?}", response);
+    };
+    let field = |name: &str| {
+        fields
+            .iter()
+            .find(|(field, _)| field == name)
+            .map(|(_, value)| value.as_str())
+    };
+    assert_eq!(field("server"), Some("blazekvdb"));
+    assert_eq!(field("version"), Some(env!("CARGO_PKG_VERSION")));
+    assert_eq!(field("proto"), Some("1"));
+    assert_eq!(field("mode"), Some("standalone"));
+    assert_eq!(field("protocols"), Some("text,json"));
+
+    // Asking for the supported version is the same as not asking
+    assert_eq!(
+        HelloCommand::new(Some(PROTOCOL_VERSION))
+            .execute(&*engine)
+            .await,
+        CommandResponse::Map(fields)
+    );
+
+    assert_eq!(
+        HelloCommand::new(Some(3)).execute(&*engine).await,
+        CommandResponse::Error("NOPROTO unsupported protocol version 3".to_string())
+    );
+}
@@ -0,0 +1,170 @@
+use std::{sync::Arc, time::Duration};
+
+use blazekvdb::{
+    commands::{
+        Command, CommandDispatcher, CommandHandler, CommandResponse,
+        incrbyfloat::IncrByFloatCommand, sadd::SAddCommand, wait::WaitCommand,
+    },
+    config::{FsyncPolicy, PersistenceConfig},
+    storage::{
+        StorageConfig, StorageEngine,
+        engine::memory::MemoryEngine,
+        persistence::{
+            aof::{AppendOnlyFile, Operation},
+            manager::PersistenceManager,
+        },
+    },
+};
+use tempfile::tempdir;
+
+fn incr(key: &str, delta: f64) -> IncrByFloatCommand {
+    IncrByFloatCommand::new(key.to_string(), delta)
+}
+
+#[test]
+fn test_incrbyfloat_validation() {
+    assert!(incr("key", 1.5).validate().is_ok());
+    assert!(incr("", 1.5).validate().is_err());
+    assert!(incr("__blaze:key", 1.5).validate().is_err());
+    assert!(incr("key", f64::NAN).validate().is_err());
+    assert!(incr("key", f64::INFINITY).validate().is_err());
+}
+
+#[tokio::test]
+async fn test_incrbyfloat_accumulates() {
+    let engine = Arc::new(MemoryEngine::new(StorageConfig::default())) as Arc<dyn StorageEngine>;
+
+    // Missing keys start at 0
+    assert_eq!(
+        incr("rate", 10.5).execute(&*engine).await,
+        CommandResponse::Value(b"10.5".to_vec())
+    );
+    assert_eq!(
+        incr("rate", 0.1).execute(&*engine).await,
+        CommandResponse::Value(b"10.6".to_vec())
+    );
+
+    // Integers come back without a trailing ".0", and -0 is plain 0
+    assert_eq!(
+        incr("rate", -5.6).execute(&*engine).await,
+        CommandResponse::Value(b"5".to_vec())
+    );
+    assert_eq!(
+        incr("rate", -5.0).execute(&*engine).await,
+        CommandResponse::Value(b"0".to_vec())
+    );
+
+    // Existing string values are parsed, exponents included
+    engine.set("text", b"5.0e3".to_vec()).await.unwrap();
+    assert_eq!(
+        incr("text", 200.15).execute(&*engine).await,
+        CommandResponse::Value(b"5200.15".to_vec())
+    );
+}
+
+#[tokio::test]
+async fn test_incrbyfloat_rejects_bad_values() {
+    let engine = Arc::new(MemoryEngine::new(StorageConfig::default())) as Arc<dyn StorageEngine>;
+
+    for stored in [&b"abc"[..], b"1.5 ", b"inf", b"NaN", b""] {
+        engine.set("key", stored.to_vec()).await.unwrap();
+        assert_eq!(
+            incr("key", 1.0).execute(&*engine).await,
+            CommandResponse::Error("Value is not a valid float".to_string()),
+            "stored {:?}",
+            stored
+        );
+        assert_eq!(engine.get("key").await.unwrap(), Some(stored.to_vec()));
+    }
+
+    engine
+        .set("big", f64::MAX.to_string().into_bytes())
+        .await
+        .unwrap();
+    assert_eq!(
+        incr("big", f64::MAX).execute(&*engine).await,
+        CommandResponse::Error("Increment would produce NaN or Infinity".to_string())
+    );
+
+    SAddCommand::new("set".to_string(), vec![b"a".to_vec()])
+        .execute(&*engine)
+        .await;
+    assert!(matches!(
+        incr("set", 1.0).execute(&*engine).await,
+        CommandResponse::Error(msg) if msg.starts_with("WRONGTYPE")
+    ));
+}
+
+#[tokio::test]
+async fn test_incrbyfloat_keeps_ttl() {
+    let engine = Arc::new(MemoryEngine::new(StorageConfig::default())) as Arc<dyn StorageEngine>;
+
+    engine
+        .set_with_ttl("key", b"1".to_vec(), Duration::from_millis(100))
+        .await
+        .unwrap();
+    incr("key", 1.0).execute(&*engine).await;
+
+    tokio::time::sleep(Duration::from_millis(150)).await;
+    assert_eq!(engine.get("key").await.unwrap(), None);
+}
+
+#[tokio::test]
+async fn test_incrbyfloat_logs_result() {
+    let temp_dir = tempdir().unwrap();
+    let aof_path = temp_dir.path().join("test.aof");
+
+    let config = PersistenceConfig {
+        aof_path: aof_path.clone(),
+        fsync_policy: FsyncPolicy::Always,
+        aof_rewrite_percentage: 0,
+        aof_rewrite_min_size: 0,
+        snapshot_enabled: false,
+        snapshot_dir: temp_dir.path().join("snapshots"),
+        ..Default::default()
+    };
+
+    let storage = Arc::new(MemoryEngine::new(StorageConfig::default())) as Arc<dyn StorageEngine>;
+    let persistence = Arc::new(
+        PersistenceManager::new(config, storage.clone())
+            .await
+            .unwrap(),
+    );
+    let dispatcher = CommandDispatcher::new(storage).with_persistence(persistence);
+
+    dispatcher
+        .execute(Command::IncrByFloat(incr("rate", 0.1)))
+        .await;
+    dispatcher
+        .execute(Command::IncrByFloat(incr("rate", 0.2)))
+        .await;
+
+    // Failed increments log nothing
+    assert!(matches!(
+        dispatcher
+            .execute(Command::IncrByFloat(incr("rate", f64::NAN)))
+            .await,
+        CommandResponse::Error(_)
+    ));
+    dispatcher
+        .execute(Command::Wait(WaitCommand::new(0, 1000)))
+        .await;
+
+    let ops = AppendOnlyFile::new(&aof_path)
+        .await
+        .unwrap()
+        .read_operations()
+        .await
+        .unwrap();
+    let values: Vec<Vec<u8>> = ops
+        .into_iter()
+        .map(|op| match op {
+            Operation::Update { key, value } if key == "rate" => value,
+            other => panic!("unexpected operation {:?}", other),
+        })
+        .collect();
+    assert_eq!(
+        values,
+        vec![b"0.1".to_vec(), b"0.30000000000000004".to_vec()]
+    );
+}
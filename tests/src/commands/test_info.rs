@@ -0,0 +1,84 @@
+use std::{sync::Arc, time::Duration};
+
+use blazekvdb::{
+    commands::{
+        Command, CommandDispatcher, CommandResponse, debug::DebugCommand, get::GetCommand,
+        info::InfoCommand,
+    },
+    storage::{StorageConfig, StorageEngine, engine::memory::MemoryEngine},
+};
+
+async fn info(dispatcher: &CommandDispatcher, section: Option<&str>) -> String {
+    let command = Command::Info(InfoCommand::new(section.map(str::to_string)));
+    match dispatcher.execute(command).await {
+        CommandResponse::Value(info) => String::from_utf8(info).unwrap(),
+        other => panic!("Expected INFO text, got {:?}", other),
+    }
+}
+
+#[tokio::test]
+async fn test_info_reports_keyspace_and_expiry() {
+    let engines: Vec<Arc<dyn StorageEngine>> = (0..3)
+        .map(|_| Arc::new(MemoryEngine::new(StorageConfig::default())) as Arc<dyn StorageEngine>)
+        .collect();
+    let dispatcher = CommandDispatcher::new(engines[0].clone()).with_databases(engines.clone());
+
+    engines[0].set("a", b"1".to_vec()).await.unwrap();
+    engines[0]
+        .set_with_ttl("b", b"2".to_vec(), Duration::from_secs(60))
+        .await
+        .unwrap();
+    engines[2]
+        .set_with_ttl("c", b"3".to_vec(), Duration::from_millis(1))
+        .await
+        .unwrap();
+    engines[2].set("d", b"4".to_vec()).await.unwrap();
+    tokio::time::sleep(Duration::from_millis(10)).await;
+
+    // Reading the expired key removes it lazily
+    assert_eq!(
+        dispatcher
+            .execute_on(2, Command::Get(GetCommand::new("c".to_string())))
+            .await,
+        CommandResponse::Nil
+    );
+
+    assert_eq!(
+        info(&dispatcher, Some("keyspace")).await,
+        "# Keyspace\r\ndb0:keys=2,expires=1\r\ndb2:keys=1,expires=0\r\n"
+    );
+
+    let all = info(&dispatcher, None).await;
+    assert!(all.starts_with("# Stats\r\n"));
+    assert!(all.contains("\r\nexpired_keys:1\r\n"));
+    assert!(all.contains("\r\nevicted_keys:0\r\n"));
+    assert!(all.contains("\r\nmax_keys:0\r\n"));
+    assert!(all.contains("\r\n# Keyspace\r\ndb0:keys=2,expires=1\r\n"));
+    assert!(!info(&dispatcher, Some("stats")).await.contains("Keyspace"));
+
+    let response = dispatcher
+        .execute(Command::Info(InfoCommand::new(Some("memory".to_string()))))
+        .await;
+    assert!(matches!(response, CommandResponse::Error(_)));
+}
+
+#[tokio::test]
+async fn test_debug_set_active_expire() {
+    let dispatcher = CommandDispatcher::new(Arc::new(MemoryEngine::new(StorageConfig::default())));
+    assert!(dispatcher.is_active_expire());
+
+    assert_eq!(
+        dispatcher
+            .execute(Command::Debug(DebugCommand::SetActiveExpire(false)))
+            .await,
+        CommandResponse::Ok
+    );
+    assert!(!dispatcher.is_active_expire());
+
+    // Still allowed in read-only mode, it doesn't touch data
+    dispatcher.set_read_only(true);
+    dispatcher
+        .execute(Command::Debug(DebugCommand::SetActiveExpire(true)))
+        .await;
+    assert!(dispatcher.is_active_expire());
+}
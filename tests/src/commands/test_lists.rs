@@ -0,0 +1,175 @@
+use std::{sync::Arc, time::Duration};
+
+use blazekvdb::{
+    commands::{
+        Command, CommandDispatcher, CommandHandler, CommandResponse, blpop::BLPopCommand,
+        get::GetCommand, push::PushCommand,
+    },
+    server::tcp::TcpServer,
+    storage::{StorageConfig, StorageEngine, StorageError, engine::memory::MemoryEngine},
+};
+use tokio::{
+    io::{AsyncReadExt, AsyncWriteExt},
+    net::TcpStream,
+};
+
+fn push(key: &str, items: &[&[u8]], front: bool) -> Command {
+    Command::Push(PushCommand::new(
+        key.to_string(),
+        items.iter().map(|item| item.to_vec()).collect(),
+        front,
+    ))
+}
+
+fn blpop(key: &str, timeout: Duration) -> Command {
+    Command::BLPop(BLPopCommand::new(key.to_string(), timeout))
+}
+
+#[tokio::test]
+async fn test_push_and_pop_order() {
+    let storage = MemoryEngine::new(StorageConfig::default());
+
+    assert_eq!(
+        storage
+            .push_items("jobs", &[b"b".to_vec(), b"c".to_vec()], false)
+            .await
+            .unwrap(),
+        2
+    );
+    // LPUSH a1 a2 puts a2 first
+    assert_eq!(
+        storage
+            .push_items("jobs", &[b"a1".to_vec(), b"a2".to_vec()], true)
+            .await
+            .unwrap(),
+        4
+    );
+
+    let mut popped = Vec::new();
+    while let Some(item) = storage.pop_front("jobs").await.unwrap() {
+        popped.push(item);
+    }
+    assert_eq!(popped, [&b"a2"[..], b"a1", b"b", b"c"]);
+
+    // The emptied list is gone, like an emptied set
+    assert!(!storage.exists("jobs").await.unwrap());
+    assert_eq!(storage.stats().await.unwrap().memory_usage, 0);
+}
+
+#[tokio::test]
+async fn test_lists_and_strings_dont_mix() {
+    let storage = MemoryEngine::new(StorageConfig::default());
+    storage.set("plain", b"value".to_vec()).await.unwrap();
+    storage
+        .push_items("jobs", &[b"a".to_vec()], false)
+        .await
+        .unwrap();
+
+    assert!(matches!(
+        storage.push_items("plain", &[b"a".to_vec()], false).await,
+        Err(StorageError::WrongType)
+    ));
+    assert!(matches!(
+        storage.add_members("jobs", &[b"a".to_vec()]).await,
+        Err(StorageError::WrongType)
+    ));
+    assert!(matches!(
+        storage.set_bit("jobs", 0, true).await,
+        Err(StorageError::WrongType)
+    ));
+
+    let response = GetCommand::new("jobs".to_string()).execute(&storage).await;
+    assert!(matches!(response, CommandResponse::Error(ref e) if e.contains("WRONGTYPE")));
+}
+
+#[tokio::test]
+async fn test_blpop_returns_available_item() {
+    let storage = Arc::new(MemoryEngine::new(StorageConfig::default())) as Arc<dyn StorageEngine>;
+    let dispatcher = CommandDispatcher::new(storage);
+
+    dispatcher.execute(push("jobs", &[b"a"], false)).await;
+    assert_eq!(
+        dispatcher.execute(blpop("jobs", Duration::ZERO)).await,
+        CommandResponse::Value(b"a".to_vec())
+    );
+}
+
+#[tokio::test]
+async fn test_blpop_wakes_on_push() {
+    let storage = Arc::new(MemoryEngine::new(StorageConfig::default())) as Arc<dyn StorageEngine>;
+    let dispatcher = Arc::new(CommandDispatcher::new(storage));
+
+    let popping = dispatcher.clone();
+    let popper = tokio::spawn(async move { popping.execute(blpop("jobs", Duration::ZERO)).await });
+
+    tokio::time::sleep(Duration::from_millis(50)).await;
+    assert!(!popper.is_finished());
+
+    assert_eq!(
+        dispatcher.execute(push("jobs", &[b"a", b"b"], false)).await,
+        CommandResponse::Integer(2)
+    );
+    let response = tokio::time::timeout(Duration::from_secs(1), popper)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(response, CommandResponse::Value(b"a".to_vec()));
+
+    // Only the woken popper took an item
+    assert_eq!(
+        dispatcher.execute(blpop("jobs", Duration::ZERO)).await,
+        CommandResponse::Value(b"b".to_vec())
+    );
+}
+
+#[tokio::test]
+async fn test_blpop_times_out_with_nil() {
+    let storage = Arc::new(MemoryEngine::new(StorageConfig::default())) as Arc<dyn StorageEngine>;
+    let dispatcher = CommandDispatcher::new(storage);
+
+    let started = tokio::time::Instant::now();
+    assert_eq!(
+        dispatcher
+            .execute(blpop("jobs", Duration::from_millis(100)))
+            .await,
+        CommandResponse::Nil
+    );
+    assert!(started.elapsed() >= Duration::from_millis(100));
+
+    // A push to another key doesn't count
+    dispatcher.execute(push("other", &[b"a"], false)).await;
+    assert_eq!(
+        dispatcher
+            .execute(blpop("jobs", Duration::from_millis(50)))
+            .await,
+        CommandResponse::Nil
+    );
+}
+
+#[tokio::test]
+async fn test_blpop_gives_up_on_shutdown() {
+    let storage = Arc::new(MemoryEngine::new(StorageConfig::default())) as Arc<dyn StorageEngine>;
+    let dispatcher = Arc::new(CommandDispatcher::new(storage));
+    let server = Arc::new(TcpServer::new(dispatcher, "127.0.0.1:0".parse().unwrap()));
+    let listener = server.bind().unwrap();
+    let addr = listener.local_addr().unwrap();
+
+    let accepting = server.clone();
+    let accept_loop = tokio::spawn(async move {
+        accepting.accept_connections(listener).await.ok();
+    });
+
+    let mut client = TcpStream::connect(addr).await.unwrap();
+    client.write_all(b"BLPOP jobs 0\n").await.unwrap();
+    tokio::time::sleep(Duration::from_millis(50)).await;
+
+    // Draining doesn't wait for a push that will never come
+    tokio::time::timeout(Duration::from_secs(1), server.drain())
+        .await
+        .unwrap();
+    accept_loop.await.unwrap();
+
+    let mut reply = Vec::new();
+    client.read_to_end(&mut reply).await.unwrap();
+    assert_eq!(reply, b"NIL\n");
+}
@@ -0,0 +1,96 @@
+use std::sync::Arc;
+
+use blazekvdb::{
+    commands::{CommandHandler, CommandResponse, memory::MemoryCommand},
+    storage::{StorageConfig, StorageEngine, engine::memory::MemoryEngine},
+};
+
+#[test]
+fn test_memory_validation() {
+    let cmd = MemoryCommand::Usage { key: String::new() };
+    assert!(cmd.validate().is_err());
+    assert!(MemoryCommand::Stats.validate().is_ok());
+}
+
+#[tokio::test]
+async fn test_memory_usage_execute() {
+    let engine = Arc::new(MemoryEngine::new(StorageConfig::default())) as Arc<dyn StorageEngine>;
+    let usage = |key: &str| MemoryCommand::Usage {
+        key: key.to_string(),
+    };
+
+    engine.set("small", b"v".to_vec()).await.unwrap();
+    engine.set("big", vec![0; 10_000]).await.unwrap();
+    engine
+        .add_members("set", &[b"a".to_vec(), b"b".to_vec()])
+        .await
+        .unwrap();
+
+    // Key and value bytes plus the fixed per-key overhead
+    assert_eq!(
+        usage("small").execute(&*engine).await,
+        CommandResponse::Integer(5 + 1 + 64)
+    );
+    assert_eq!(
+        usage("big").execute(&*engine).await,
+        CommandResponse::Integer(3 + 10_000 + 64)
+    );
+    let CommandResponse::Integer(set) = usage("set").execute(&*engine).await else {
+        panic!("Expected an integer");
+    };
+    assert!(set > 3 + 2 + 64, "{}", set);
+
+    assert_eq!(
+        usage("missing").execute(&*engine).await,
+        CommandResponse::Nil
+    );
+
+    // The per-key sizes add up to what max_memory is checked against
+    let memory_usage = engine.stats().await.unwrap().memory_usage as i64;
+    assert_eq!(memory_usage, (5 + 1 + 64) + (3 + 10_000 + 64) + set);
+}
+
+#[tokio::test]
+async fn test_memory_stats_execute() {
+    let config = StorageConfig {
+        shard_count: 4,
+        ..Default::default()
+    };
+    let engine = Arc::new(MemoryEngine::new(config)) as Arc<dyn StorageEngine>;
+
+    for i in 0..20 {
+        engine
+            .set(&format!("key{}", i), b"value".to_vec())
+            .await
+            .unwrap();
+    }
+
+    let CommandResponse::Map(fields) = MemoryCommand::Stats.execute(&*engine).await else {
+        panic!("Expected a map");
+    };
+    let field = |name: &str| -> usize {
+        fields
+            .iter()
+            .find(|(field, _)| field == name)
+            .unwrap_or_else(|| panic!("missing {}", name))
+            .1
+            .parse()
+            .unwrap()
+    };
+
+    let keyspace: usize = (0..20).map(|i| format!("key{}", i).len() + 5).sum();
+    assert_eq!(field("keys.count"), 20);
+    assert_eq!(field("keyspace.bytes"), keyspace);
+    assert_eq!(field("overhead.bytes"), 20 * 64);
+    assert_eq!(field("total.bytes"), keyspace + 20 * 64);
+    assert_eq!(
+        field("total.bytes"),
+        engine.stats().await.unwrap().memory_usage
+    );
+
+    let shards: usize = (0..4)
+        .map(|shard| field(&format!("shard.{}.bytes", shard)))
+        .sum();
+    assert_eq!(shards, field("total.bytes"));
+    assert!(!fields.iter().any(|(field, _)| field == "shard.4.bytes"));
+}
@@ -0,0 +1,84 @@
+use std::sync::Arc;
+
+use blazekvdb::{
+    commands::{
+        Command, CommandDispatcher, CommandHandler, CommandResponse, get::GetCommand,
+        metrics::MetricsCommand,
+    },
+    server::tcp::TcpServer,
+    storage::{StorageConfig, StorageEngine, engine::memory::MemoryEngine},
+};
+use tokio::{
+    io::{AsyncReadExt, AsyncWriteExt},
+    net::{TcpListener, TcpStream},
+};
+
+async fn metrics(dispatcher: &CommandDispatcher) -> serde_json::Value {
+    match dispatcher.execute(Command::Metrics).await {
+        CommandResponse::Value(json) => serde_json::from_slice(&json).unwrap(),
+        other => panic!("Expected METRICS json, got {:?}", other),
+    }
+}
+
+#[tokio::test]
+async fn test_metrics_sums_every_database() {
+    let engines: Vec<Arc<dyn StorageEngine>> = (0..2)
+        .map(|_| Arc::new(MemoryEngine::new(StorageConfig::default())) as Arc<dyn StorageEngine>)
+        .collect();
+    let dispatcher = CommandDispatcher::new(engines[0].clone()).with_databases(engines.clone());
+
+    engines[0].set("a", b"1".to_vec()).await.unwrap();
+    engines[1].set("b", b"2".to_vec()).await.unwrap();
+    engines[1].set("c", b"3".to_vec()).await.unwrap();
+
+    for (database, key) in [(0, "a"), (1, "b"), (1, "b"), (1, "missing")] {
+        dispatcher
+            .execute_on(database, Command::Get(GetCommand::new(key.to_string())))
+            .await;
+    }
+
+    let metrics = metrics(&dispatcher).await;
+    assert_eq!(metrics["total_keys"], 3);
+    assert_eq!(metrics["databases"], 2);
+    assert_eq!(metrics["keyspace_hits"], 3);
+    assert_eq!(metrics["keyspace_misses"], 1);
+    assert_eq!(metrics["hit_rate"], 0.75);
+    assert_eq!(metrics["evicted_keys"], 0);
+    assert_eq!(metrics["connected_clients"], 0);
+    assert!(metrics["memory_usage_bytes"].as_u64().unwrap() > 0);
+    assert!(metrics["persistence"].is_null());
+}
+
+#[tokio::test]
+async fn test_metrics_counts_connected_clients() {
+    let storage = Arc::new(MemoryEngine::new(StorageConfig::default())) as Arc<dyn StorageEngine>;
+    let dispatcher = Arc::new(CommandDispatcher::new(storage));
+    let server = TcpServer::new(dispatcher.clone(), "127.0.0.1:0".parse().unwrap());
+
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(async move {
+        server.accept_connections(listener).await.ok();
+    });
+
+    // A reply means the connection was accepted and counted
+    let mut buffer = [0; 64];
+    let mut clients = Vec::new();
+    for _ in 0..2 {
+        let mut client = TcpStream::connect(addr).await.unwrap();
+        client.write_all(b"PING\n").await.unwrap();
+        assert!(client.read(&mut buffer).await.unwrap() > 0);
+        clients.push(client);
+    }
+    assert_eq!(metrics(&dispatcher).await["connected_clients"], 2);
+
+    // Without a dispatcher there is nothing to count
+    let storage = MemoryEngine::new(StorageConfig::default());
+    match MetricsCommand.execute(&storage).await {
+        CommandResponse::Value(json) => {
+            let metrics: serde_json::Value = serde_json::from_slice(&json).unwrap();
+            assert!(metrics["connected_clients"].is_null());
+        }
+        other => panic!("Expected METRICS json, got {:?}", other),
+    }
+}
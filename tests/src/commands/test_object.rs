@@ -0,0 +1,158 @@
+use std::sync::Arc;
+
+use blazekvdb::{
+    commands::{
+        CommandHandler, CommandResponse,
+        object::{ObjectCommand, ObjectSubcommand},
+    },
+    storage::{
+        StorageConfig, StorageEngine,
+        engine::memory::MemoryEngine,
+        value::{decode_list, decode_set},
+    },
+};
+
+#[test]
+fn test_object_validation() {
+    let cmd = ObjectCommand::new(ObjectSubcommand::IdleTime, "".to_string());
+    assert!(cmd.validate().is_err());
+}
+
+#[tokio::test]
+async fn test_object_idletime_execute() {
+    let config = StorageConfig::default();
+    let engine = Arc::new(MemoryEngine::new(config)) as Arc<dyn StorageEngine>;
+
+    engine.set("key1", b"value1".to_vec()).await.unwrap();
+
+    let cmd = ObjectCommand::new(ObjectSubcommand::IdleTime, "key1".to_string());
+    let response = cmd.execute(&*engine).await;
+    assert_eq!(response, CommandResponse::Integer(0));
+
+    let cmd = ObjectCommand::new(ObjectSubcommand::IdleTime, "missing".to_string());
+    let response = cmd.execute(&*engine).await;
+    assert!(matches!(response, CommandResponse::Error(_)));
+}
+
+#[tokio::test]
+async fn test_object_freq_execute() {
+    let config = StorageConfig::default();
+    let engine = Arc::new(MemoryEngine::new(config)) as Arc<dyn StorageEngine>;
+
+    engine.set("key1", b"value1".to_vec()).await.unwrap();
+
+    // New keys start at 5, the first read always counts, later ones ever more rarely
+    let freq = |key: &str| ObjectCommand::new(ObjectSubcommand::Freq, key.to_string());
+    assert_eq!(
+        freq("key1").execute(&*engine).await,
+        CommandResponse::Integer(5)
+    );
+
+    engine.get("key1").await.unwrap();
+    assert_eq!(
+        freq("key1").execute(&*engine).await,
+        CommandResponse::Integer(6)
+    );
+
+    for _ in 0..1000 {
+        engine.get("key1").await.unwrap();
+    }
+    let CommandResponse::Integer(hot) = freq("key1").execute(&*engine).await else {
+        panic!("Expected an integer");
+    };
+    assert!((7..50).contains(&hot), "{}", hot);
+
+    // Asking doesn't count as an access
+    assert_eq!(
+        freq("key1").execute(&*engine).await,
+        CommandResponse::Integer(hot)
+    );
+
+    let response = freq("missing").execute(&*engine).await;
+    assert!(matches!(response, CommandResponse::Error(_)));
+}
+
+#[tokio::test]
+async fn test_object_encoding_strings() {
+    let engine = Arc::new(MemoryEngine::new(StorageConfig::default())) as Arc<dyn StorageEngine>;
+    let encoding = |key: &str| ObjectCommand::new(ObjectSubcommand::Encoding, key.to_string());
+
+    engine.set("int", b"-42".to_vec()).await.unwrap();
+    engine.set("short", b"hello".to_vec()).await.unwrap();
+    engine.set("long", vec![b'x'; 45]).await.unwrap();
+
+    for (key, expected) in [("int", "int"), ("short", "embstr"), ("long", "raw")] {
+        assert_eq!(
+            encoding(key).execute(&*engine).await,
+            CommandResponse::Value(expected.as_bytes().to_vec())
+        );
+    }
+
+    let response = encoding("missing").execute(&*engine).await;
+    assert!(matches!(response, CommandResponse::Error(_)));
+}
+
+#[tokio::test]
+async fn test_object_encoding_set_converts_at_threshold() {
+    let config = StorageConfig {
+        set_max_packed_entries: 3,
+        max_packed_value: 8,
+        ..Default::default()
+    };
+    let engine = Arc::new(MemoryEngine::new(config)) as Arc<dyn StorageEngine>;
+    let encoding = || ObjectCommand::new(ObjectSubcommand::Encoding, "s".to_string());
+    let listpack = CommandResponse::Value(b"listpack".to_vec());
+    let hashtable = CommandResponse::Value(b"hashtable".to_vec());
+
+    let members: Vec<Vec<u8>> = ["a", "b", "c", "d"].map(|m| m.as_bytes().to_vec()).into();
+    engine.add_members("s", &members[..3]).await.unwrap();
+    assert_eq!(encoding().execute(&*engine).await, listpack);
+
+    // One past the entry threshold converts, dropping back under it converts back
+    engine.add_members("s", &members[3..]).await.unwrap();
+    assert_eq!(encoding().execute(&*engine).await, hashtable);
+
+    engine.remove_members("s", &members[3..]).await.unwrap();
+    assert_eq!(encoding().execute(&*engine).await, listpack);
+
+    // A single oversized member converts regardless of the count
+    engine.add_members("s", &[vec![b'x'; 9]]).await.unwrap();
+    assert_eq!(encoding().execute(&*engine).await, hashtable);
+
+    // Both encodings decode to the same members
+    let mut stored: Vec<Vec<u8>> = decode_set(&engine.get("s").await.unwrap().unwrap())
+        .unwrap()
+        .into_iter()
+        .collect();
+    stored.sort();
+    assert_eq!(
+        stored,
+        [b"a".to_vec(), b"b".to_vec(), b"c".to_vec(), vec![b'x'; 9]]
+    );
+}
+
+#[tokio::test]
+async fn test_object_encoding_list_converts_at_threshold() {
+    let config = StorageConfig {
+        list_max_packed_size: 2,
+        ..Default::default()
+    };
+    let engine = Arc::new(MemoryEngine::new(config)) as Arc<dyn StorageEngine>;
+    let encoding = || ObjectCommand::new(ObjectSubcommand::Encoding, "l".to_string());
+    let listpack = CommandResponse::Value(b"listpack".to_vec());
+    let quicklist = CommandResponse::Value(b"quicklist".to_vec());
+
+    let items: Vec<Vec<u8>> = ["a", "b", "c"].map(|i| i.as_bytes().to_vec()).into();
+    engine.push_items("l", &items[..2], false).await.unwrap();
+    assert_eq!(encoding().execute(&*engine).await, listpack);
+
+    engine.push_items("l", &items[2..], false).await.unwrap();
+    assert_eq!(encoding().execute(&*engine).await, quicklist);
+
+    assert_eq!(engine.pop_front("l").await.unwrap(), Some(b"a".to_vec()));
+    assert_eq!(encoding().execute(&*engine).await, listpack);
+    assert_eq!(
+        decode_list(&engine.get("l").await.unwrap().unwrap()).unwrap(),
+        [b"b".to_vec(), b"c".to_vec()]
+    );
+}
@@ -1,7 +1,7 @@
 use std::sync::Arc;
 
 use blazekvdb::{
-    commands::{CommandHandler, CommandResponse, ping::PingCommand},
+    commands::{CommandHandler, CommandResponse, KeyLimits, echo::EchoCommand, ping::PingCommand},
     storage::{StorageConfig, StorageEngine, engine::memory::MemoryEngine},
 };
 
@@ -10,6 +10,48 @@ async fn test_ping_command() {
     let config = StorageConfig::default();
     let engine = Arc::new(MemoryEngine::new(config)) as Arc<dyn StorageEngine>;
 
-    let response = PingCommand.execute(&*engine).await;
+    let response = PingCommand::default().execute(&*engine).await;
     assert_eq!(response, CommandResponse::Pong)
 }
+
+#[tokio::test]
+async fn test_ping_with_message() {
+    let config = StorageConfig::default();
+    let engine = Arc::new(MemoryEngine::new(config)) as Arc<dyn StorageEngine>;
+
+    let response = PingCommand::new(Some(b"health".to_vec()))
+        .execute(&*engine)
+        .await;
+    assert_eq!(response, CommandResponse::Value(b"health".to_vec()));
+
+    let limits = KeyLimits {
+        max_value_size: 4,
+        ..KeyLimits::default()
+    };
+    assert!(
+        PingCommand::new(Some(b"12345".to_vec()))
+            .validate_with(&limits)
+            .is_err()
+    );
+    assert!(PingCommand::default().validate_with(&limits).is_ok());
+}
+
+#[tokio::test]
+async fn test_echo_is_binary_safe() {
+    let config = StorageConfig::default();
+    let engine = Arc::new(MemoryEngine::new(config)) as Arc<dyn StorageEngine>;
+
+    let message = b"pool-7\n\0\xff".to_vec();
+    let response = EchoCommand::new(message.clone()).execute(&*engine).await;
+    assert_eq!(response, CommandResponse::Value(message));
+
+    let limits = KeyLimits {
+        max_value_size: 4,
+        ..KeyLimits::default()
+    };
+    assert!(
+        EchoCommand::new(b"12345".to_vec())
+            .validate_with(&limits)
+            .is_err()
+    );
+}
@@ -0,0 +1,82 @@
+use std::sync::Arc;
+
+use blazekvdb::{
+    commands::{
+        CommandHandler, CommandResponse, KeyLimits, getrange::GetRangeCommand,
+        setrange::SetRangeCommand,
+    },
+    storage::{StorageConfig, StorageEngine, engine::memory::MemoryEngine},
+};
+
+#[test]
+fn test_setrange_validation() {
+    let limits = KeyLimits {
+        max_value_size: 8,
+        ..Default::default()
+    };
+
+    // Padding counts towards the value limit
+    let cmd = SetRangeCommand::new("key".to_string(), 6, b"abc".to_vec());
+    assert!(cmd.validate_with(&limits).is_err());
+
+    let cmd = SetRangeCommand::new("key".to_string(), 5, b"abc".to_vec());
+    assert!(cmd.validate_with(&limits).is_ok());
+
+    let cmd = SetRangeCommand::new("__blaze:key".to_string(), 0, b"abc".to_vec());
+    assert!(cmd.validate().is_err());
+}
+
+#[tokio::test]
+async fn test_getrange_execute() {
+    let engine = Arc::new(MemoryEngine::new(StorageConfig::default())) as Arc<dyn StorageEngine>;
+    engine
+        .set("key", b"This is a string".to_vec())
+        .await
+        .unwrap();
+
+    let cases: [(i64, i64, &[u8]); 5] = [
+        (0, 3, b"This"),
+        (-3, -1, b"ing"),
+        (0, -1, b"This is a string"),
+        (10, 100, b"string"),
+        (5, 2, b""),
+    ];
+
+    for (start, end, expected) in cases {
+        let cmd = GetRangeCommand::new("key".to_string(), start, end);
+        assert_eq!(
+            cmd.execute(&*engine).await,
+            CommandResponse::Value(expected.to_vec()),
+            "GETRANGE key {} {}",
+            start,
+            end
+        );
+    }
+
+    let cmd = GetRangeCommand::new("missing".to_string(), 0, -1);
+    assert_eq!(cmd.execute(&*engine).await, CommandResponse::Value(vec![]));
+}
+
+#[tokio::test]
+async fn test_setrange_execute() {
+    let engine = Arc::new(MemoryEngine::new(StorageConfig::default())) as Arc<dyn StorageEngine>;
+    engine.set("key", b"Hello World".to_vec()).await.unwrap();
+
+    let cmd = SetRangeCommand::new("key".to_string(), 6, b"Redis".to_vec());
+    assert_eq!(cmd.execute(&*engine).await, CommandResponse::Integer(11));
+    assert_eq!(
+        engine.get("key").await.unwrap(),
+        Some(b"Hello Redis".to_vec())
+    );
+
+    // Writing past the end of a missing key zero-pads
+    let cmd = SetRangeCommand::new("new".to_string(), 3, b"ab".to_vec());
+    assert_eq!(cmd.execute(&*engine).await, CommandResponse::Integer(5));
+    assert_eq!(engine.get("new").await.unwrap(), Some(b"\0\0\0ab".to_vec()));
+
+    // Memory tracking follows the growth
+    let before = engine.stats().await.unwrap().memory_usage;
+    let cmd = SetRangeCommand::new("new".to_string(), 10, b"x".to_vec());
+    assert_eq!(cmd.execute(&*engine).await, CommandResponse::Integer(11));
+    assert_eq!(engine.stats().await.unwrap().memory_usage, before + 6);
+}
@@ -0,0 +1,312 @@
+use std::{path::Path, sync::Arc, time::Duration};
+
+use blazekvdb::{
+    commands::{
+        Command, CommandDispatcher, CommandResponse, get::GetCommand, relocate::RelocateCommand,
+        set::SetCommand,
+    },
+    config::{FsyncPolicy, PersistenceConfig},
+    storage::{
+        StorageConfig, StorageEngine,
+        engine::memory::MemoryEngine,
+        persistence::{
+            aof::{AppendOnlyFile, Operation},
+            manager::PersistenceManager,
+        },
+    },
+};
+use tempfile::tempdir;
+
+fn config(dir: &Path, allow_relocate: bool) -> PersistenceConfig {
+    PersistenceConfig {
+        aof_path: dir.join("test.aof"),
+        fsync_policy: FsyncPolicy::Always,
+        aof_rewrite_percentage: 0,
+        aof_rewrite_min_size: 0,
+        snapshot_dir: dir.join("snapshots"),
+        allow_relocate,
+        ..Default::default()
+    }
+}
+
+async fn setup(config: PersistenceConfig) -> (CommandDispatcher, Arc<PersistenceManager>) {
+    let storage = Arc::new(MemoryEngine::new(StorageConfig::default())) as Arc<dyn StorageEngine>;
+    let persistence = Arc::new(
+        PersistenceManager::new(config, storage.clone())
+            .await
+            .unwrap(),
+    );
+    let dispatcher = CommandDispatcher::new(storage).with_persistence(persistence.clone());
+
+    (dispatcher, persistence)
+}
+
+async fn set(dispatcher: &CommandDispatcher, key: &str, value: &[u8]) {
+    let cmd = Command::Set(SetCommand::new(key.to_string(), value.to_vec()));
+    assert_eq!(dispatcher.execute(cmd).await, CommandResponse::Ok);
+}
+
+async fn logged_operations(path: &Path) -> Vec<Operation> {
+    AppendOnlyFile::new(path)
+        .await
+        .unwrap()
+        .read_operations()
+        .await
+        .unwrap()
+}
+
+fn relocate(aof_path: Option<&Path>, snapshot_dir: Option<&Path>, copy: bool) -> Command {
+    Command::Relocate(RelocateCommand {
+        aof_path: aof_path.map(|path| path.display().to_string()),
+        snapshot_dir: snapshot_dir.map(|dir| dir.display().to_string()),
+        copy,
+    })
+}
+
+// A fresh server started on `config` sees every key in `keys`
+async fn assert_recovers(config: PersistenceConfig, keys: &[(&str, &[u8])]) {
+    let (dispatcher, persistence) = setup(config).await;
+    persistence.recover().await.unwrap();
+
+    for (key, value) in keys {
+        let response = dispatcher
+            .execute(Command::Get(GetCommand::new(key.to_string())))
+            .await;
+        assert_eq!(response, CommandResponse::Value(value.to_vec()), "{}", key);
+    }
+}
+
+#[tokio::test]
+async fn test_relocate_disabled_by_default() {
+    let temp_dir = tempdir().unwrap();
+    let (dispatcher, _persistence) = setup(config(temp_dir.path(), false)).await;
+
+    let new_path = temp_dir.path().join("moved/test.aof");
+    let response = dispatcher
+        .execute(relocate(Some(&new_path), None, false))
+        .await;
+    assert!(
+        matches!(response, CommandResponse::Error(ref e) if e.contains("allow_relocate")),
+        "{:?}",
+        response
+    );
+    assert!(!new_path.exists());
+}
+
+#[tokio::test]
+async fn test_relocate_aof_seeds_new_file() {
+    let temp_dir = tempdir().unwrap();
+    let (dispatcher, persistence) = setup(config(temp_dir.path(), true)).await;
+    let old_path = temp_dir.path().join("test.aof");
+    let new_path = temp_dir.path().join("volume2/moved.aof");
+
+    set(&dispatcher, "key1", b"a").await;
+    set(&dispatcher, "key1", b"b").await;
+
+    let response = dispatcher
+        .execute(relocate(Some(&new_path), None, false))
+        .await;
+    let CommandResponse::Value(reply) = response else {
+        panic!("{:?}", response);
+    };
+    let reply = String::from_utf8(reply).unwrap();
+    assert!(
+        reply.starts_with(&format!("aof={} ", new_path.display())),
+        "{}",
+        reply
+    );
+    assert!(reply.contains("config file not updated"), "{}", reply);
+
+    set(&dispatcher, "key2", b"c").await;
+    persistence.sync_aof().await.unwrap();
+
+    // The old file stops at the switch, the new one holds the dataset plus later writes
+    assert_eq!(logged_operations(&old_path).await.len(), 2);
+    assert_eq!(logged_operations(&new_path).await.len(), 3);
+    assert_eq!(
+        persistence.stats().await.aof_stats.unwrap().file_path,
+        new_path
+    );
+
+    let mut moved = config(temp_dir.path(), true);
+    moved.aof_path = new_path;
+    moved.snapshot_enabled = false;
+    assert_recovers(moved, &[("key1", b"b"), ("key2", b"c")]).await;
+}
+
+#[tokio::test]
+async fn test_relocate_copy_moves_aof_and_snapshots() {
+    let temp_dir = tempdir().unwrap();
+    let (dispatcher, persistence) = setup(config(temp_dir.path(), true)).await;
+    let new_path = temp_dir.path().join("volume2/test.aof");
+    let new_dir = temp_dir.path().join("volume2/snapshots");
+
+    set(&dispatcher, "key1", b"a").await;
+    persistence.create_snapshot().await.unwrap();
+    set(&dispatcher, "key2", b"b").await;
+
+    let response = dispatcher
+        .execute(relocate(Some(&new_path), Some(&new_dir), true))
+        .await;
+    assert!(
+        matches!(response, CommandResponse::Value(_)),
+        "{:?}",
+        response
+    );
+    assert!(new_dir.join("snapshot-latest.rdb").exists());
+
+    // History is copied as is (key1 compacted by the snapshot), later writes follow it
+    set(&dispatcher, "key3", b"c").await;
+    persistence.sync_aof().await.unwrap();
+    assert_eq!(logged_operations(&new_path).await.len(), 4);
+
+    // The new location holds everything on its own
+    std::fs::remove_file(temp_dir.path().join("test.aof")).unwrap();
+    std::fs::remove_dir_all(temp_dir.path().join("snapshots")).unwrap();
+    persistence.create_snapshot().await.unwrap();
+    assert!(!temp_dir.path().join("snapshots").exists());
+
+    let mut moved = config(temp_dir.path(), true);
+    moved.aof_path = new_path;
+    moved.snapshot_dir = new_dir;
+    assert_recovers(moved, &[("key1", b"a"), ("key2", b"b"), ("key3", b"c")]).await;
+}
+
+#[tokio::test]
+async fn test_restart_on_old_paths_after_relocate_fails() {
+    let temp_dir = tempdir().unwrap();
+    let (dispatcher, _persistence) = setup(config(temp_dir.path(), true)).await;
+    let new_path = temp_dir.path().join("volume2/test.aof");
+    let new_dir = temp_dir.path().join("volume2/snapshots");
+
+    set(&dispatcher, "key1", b"a").await;
+    let response = dispatcher
+        .execute(relocate(Some(&new_path), Some(&new_dir), false))
+        .await;
+    assert!(
+        matches!(response, CommandResponse::Value(_)),
+        "{:?}",
+        response
+    );
+    set(&dispatcher, "key2", b"b").await;
+
+    // The config file still names the old files, which miss key2
+    let (_, restarted) = setup(config(temp_dir.path(), true)).await;
+    let error = restarted.recover().await.unwrap_err().to_string();
+    assert!(error.contains(&new_path.display().to_string()), "{}", error);
+
+    // Only the AOF marker removed, the snapshot directory still refuses
+    std::fs::remove_file(temp_dir.path().join("test.aof.relocated")).unwrap();
+    let error = restarted.recover().await.unwrap_err().to_string();
+    assert!(error.contains(&new_dir.display().to_string()), "{}", error);
+
+    // The operator can still choose the old files explicitly
+    std::fs::remove_file(temp_dir.path().join("snapshots/RELOCATED")).unwrap();
+    restarted.recover().await.unwrap();
+}
+
+#[tokio::test]
+async fn test_relocate_refuses_existing_file() {
+    let temp_dir = tempdir().unwrap();
+    let (dispatcher, persistence) = setup(config(temp_dir.path(), true)).await;
+    let old_path = temp_dir.path().join("test.aof");
+    let new_path = temp_dir.path().join("other.aof");
+    std::fs::write(&new_path, b"not ours\n").unwrap();
+
+    set(&dispatcher, "key1", b"a").await;
+    let response = dispatcher
+        .execute(relocate(Some(&new_path), None, true))
+        .await;
+    assert!(matches!(response, CommandResponse::Error(ref e) if e.contains("already exists")));
+
+    // Nothing switched and the other file is untouched
+    set(&dispatcher, "key2", b"b").await;
+    persistence.sync_aof().await.unwrap();
+    assert_eq!(logged_operations(&old_path).await.len(), 2);
+    assert_eq!(std::fs::read(&new_path).unwrap(), b"not ours\n");
+}
+
+#[tokio::test]
+async fn test_failed_relocate_can_be_retried() {
+    let temp_dir = tempdir().unwrap();
+    let (dispatcher, persistence) = setup(config(temp_dir.path(), true)).await;
+    let taken = temp_dir.path().join("taken.aof");
+    let new_path = temp_dir.path().join("volume2/test.aof");
+    let new_dir = temp_dir.path().join("volume2/snapshots");
+    std::fs::write(&taken, b"not ours\n").unwrap();
+
+    set(&dispatcher, "key1", b"a").await;
+    persistence.create_snapshot().await.unwrap();
+
+    // The snapshots are copied before the AOF step fails, and removed again
+    let response = dispatcher
+        .execute(relocate(Some(&taken), Some(&new_dir), true))
+        .await;
+    assert!(matches!(response, CommandResponse::Error(ref e) if e.contains("already exists")));
+    assert_eq!(std::fs::read_dir(&new_dir).unwrap().count(), 0);
+
+    let response = dispatcher
+        .execute(relocate(Some(&new_path), Some(&new_dir), true))
+        .await;
+    assert!(
+        matches!(response, CommandResponse::Value(_)),
+        "{:?}",
+        response
+    );
+    assert!(new_dir.join("snapshot-latest.rdb").exists());
+}
+
+#[tokio::test]
+async fn test_relocate_in_read_only_mode() {
+    let temp_dir = tempdir().unwrap();
+    let (dispatcher, _persistence) = setup(config(temp_dir.path(), true)).await;
+    let new_path = temp_dir.path().join("volume2/test.aof");
+
+    // Never retried after a lost connection, yet allowed while writes are refused
+    let command = relocate(Some(&new_path), None, true);
+    assert!(!command.clone().into_handler().is_read_only());
+
+    set(&dispatcher, "key1", b"a").await;
+    dispatcher.set_read_only(true);
+    let response = dispatcher.execute(command).await;
+    assert!(
+        matches!(response, CommandResponse::Value(_)),
+        "{:?}",
+        response
+    );
+    assert!(new_path.exists());
+}
+
+#[tokio::test]
+async fn test_relocate_runs_one_at_a_time() {
+    let temp_dir = tempdir().unwrap();
+    let (dispatcher, persistence) = setup(config(temp_dir.path(), true)).await;
+    set(&dispatcher, "key1", b"a").await;
+
+    // Holding the AOF exclusively parks the first relocation right before it switches
+    let aof = persistence.aof.clone().unwrap();
+    let hold = aof.write().await;
+
+    let first = {
+        let persistence = persistence.clone();
+        let path = temp_dir.path().join("first.aof");
+        tokio::spawn(async move { persistence.relocate(Some(path), None, true).await })
+    };
+    for _ in 0..200 {
+        if persistence.is_aof_rewrite_in_progress() {
+            break;
+        }
+        tokio::time::sleep(Duration::from_millis(10)).await;
+    }
+
+    let second = persistence
+        .relocate(Some(temp_dir.path().join("second.aof")), None, true)
+        .await;
+    assert!(matches!(second, Err(ref e) if e.to_string().contains("already in progress")));
+    assert!(persistence.compact_aof().await.is_err());
+
+    drop(hold);
+    first.await.unwrap().unwrap();
+    assert!(!temp_dir.path().join("second.aof").exists());
+    assert!(!persistence.is_aof_rewrite_in_progress());
+}
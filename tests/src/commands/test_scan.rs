@@ -27,3 +27,47 @@ async fn test_scan_execute() {
         })
     );
 }
+
+#[tokio::test]
+async fn test_sorted_scan_returns_smallest_keys_in_order() {
+    let config = StorageConfig::default();
+    let engine = Arc::new(MemoryEngine::new(config)) as Arc<dyn StorageEngine>;
+
+    for i in (0..50).rev() {
+        engine
+            .set(&format!("key{:02}", i), b"v".to_vec())
+            .await
+            .unwrap();
+    }
+    engine.set("other", b"v".to_vec()).await.unwrap();
+
+    let cmd = ScanCommand::new("key".to_string()).sorted().with_count(3);
+    assert_eq!(
+        cmd.execute(&*engine).await,
+        CommandResponse::Keys(vec![
+            "key00".to_string(),
+            "key01".to_string(),
+            "key02".to_string()
+        ])
+    );
+
+    let cmd = ScanCommand::new(String::new()).sorted();
+    match cmd.execute(&*engine).await {
+        CommandResponse::Keys(keys) => {
+            assert_eq!(keys.len(), 51);
+            assert!(keys.is_sorted());
+            assert_eq!(keys.last().map(String::as_str), Some("other"));
+        }
+        other => panic!("Expected keys, got {:?}", other),
+    }
+
+    // COUNT alone just caps an unordered scan
+    let cmd = ScanCommand::new("key".to_string()).with_count(5);
+    assert!(matches!(cmd.execute(&*engine).await, CommandResponse::Keys(keys) if keys.len() == 5));
+    assert!(
+        ScanCommand::new(String::new())
+            .with_count(0)
+            .validate()
+            .is_err()
+    );
+}
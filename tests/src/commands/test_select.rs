@@ -0,0 +1,216 @@
+use std::sync::Arc;
+
+use blazekvdb::{
+    bootstrap::BlazeKVDB,
+    commands::{
+        Command, CommandDispatcher, CommandResponse, delete::DeleteCommand, get::GetCommand,
+        select::SelectCommand, set::SetCommand,
+    },
+    config::BlazeServerConfig,
+    storage::{StorageConfig, StorageEngine, engine::memory::MemoryEngine},
+};
+use tempfile::tempdir;
+
+fn dispatcher(databases: usize) -> CommandDispatcher {
+    let engines: Vec<Arc<dyn StorageEngine>> = (0..databases)
+        .map(|_| Arc::new(MemoryEngine::new(StorageConfig::default())) as Arc<dyn StorageEngine>)
+        .collect();
+
+    CommandDispatcher::new(engines[0].clone()).with_databases(engines)
+}
+
+fn set(key: &str, value: &[u8]) -> Command {
+    Command::Set(SetCommand::new(key.to_string(), value.to_vec()))
+}
+
+fn get(key: &str) -> Command {
+    Command::Get(GetCommand::new(key.to_string()))
+}
+
+#[tokio::test]
+async fn test_databases_are_isolated() {
+    let dispatcher = dispatcher(3);
+    assert_eq!(dispatcher.database_count(), 3);
+
+    assert_eq!(
+        dispatcher.execute_on(0, set("key", b"zero")).await,
+        CommandResponse::Ok
+    );
+    assert_eq!(
+        dispatcher.execute_on(1, set("key", b"one")).await,
+        CommandResponse::Ok
+    );
+
+    assert_eq!(
+        dispatcher.execute(get("key")).await,
+        CommandResponse::Value(b"zero".to_vec())
+    );
+    assert_eq!(
+        dispatcher.execute_on(1, get("key")).await,
+        CommandResponse::Value(b"one".to_vec())
+    );
+    assert_eq!(
+        dispatcher.execute_on(2, get("key")).await,
+        CommandResponse::Nil
+    );
+}
+
+#[tokio::test]
+async fn test_select_out_of_range() {
+    let dispatcher = dispatcher(2);
+
+    assert_eq!(
+        dispatcher
+            .execute(Command::Select(SelectCommand::new(1)))
+            .await,
+        CommandResponse::Ok
+    );
+    assert_eq!(
+        dispatcher
+            .execute(Command::Select(SelectCommand::new(2)))
+            .await,
+        CommandResponse::Error("DB index is out of range".to_string())
+    );
+    assert_eq!(
+        dispatcher.execute_on(2, get("key")).await,
+        CommandResponse::Error("DB index is out of range".to_string())
+    );
+}
+
+#[tokio::test]
+async fn test_flushdb_only_clears_selected_database() {
+    let dispatcher = dispatcher(2);
+
+    for db in 0..2 {
+        dispatcher.execute_on(db, set("a", b"1")).await;
+        dispatcher.execute_on(db, set("b", b"2")).await;
+    }
+
+    assert_eq!(
+        dispatcher.execute_on(1, Command::FlushDb).await,
+        CommandResponse::Ok
+    );
+
+    assert_eq!(
+        dispatcher.execute_on(1, get("a")).await,
+        CommandResponse::Nil
+    );
+    assert_eq!(
+        dispatcher.execute(get("a")).await,
+        CommandResponse::Value(b"1".to_vec())
+    );
+    assert_eq!(
+        dispatcher.execute(get("b")).await,
+        CommandResponse::Value(b"2".to_vec())
+    );
+}
+
+fn persistent_config(dir: &std::path::Path) -> BlazeServerConfig {
+    let mut config = BlazeServerConfig::default();
+    config.storage.databases = 4;
+    config.persistence.aof_path = dir.join("test.aof");
+    config.persistence.snapshot_dir = dir.join("snapshots");
+    config
+}
+
+#[tokio::test]
+async fn test_databases_survive_aof_replay() {
+    let temp_dir = tempdir().unwrap();
+    let mut config = persistent_config(temp_dir.path());
+    config.persistence.snapshot_enabled = false;
+
+    let kvdb = BlazeKVDB::new(config.clone()).await.unwrap();
+    let dispatcher = kvdb.dispatcher();
+
+    dispatcher.execute_on(0, set("key", b"zero")).await;
+    dispatcher.execute_on(2, set("key", b"two")).await;
+    dispatcher.execute_on(2, set("gone", b"x")).await;
+    dispatcher.execute_on(1, set("flushed", b"x")).await;
+    dispatcher
+        .execute_on(2, Command::Delete(DeleteCommand::new("gone".to_string())))
+        .await;
+    dispatcher.execute_on(1, Command::FlushDb).await;
+
+    kvdb.shutdown().await.unwrap();
+    drop(dispatcher);
+    drop(kvdb);
+
+    let kvdb = BlazeKVDB::new(config).await.unwrap();
+    let dispatcher = kvdb.dispatcher();
+
+    assert_eq!(
+        dispatcher.execute(get("key")).await,
+        CommandResponse::Value(b"zero".to_vec())
+    );
+    assert_eq!(
+        dispatcher.execute_on(2, get("key")).await,
+        CommandResponse::Value(b"two".to_vec())
+    );
+    assert_eq!(
+        dispatcher.execute_on(2, get("gone")).await,
+        CommandResponse::Nil
+    );
+    assert_eq!(
+        kvdb.database(1).unwrap().stats().await.unwrap().total_keys,
+        0
+    );
+
+    kvdb.shutdown().await.unwrap();
+}
+
+#[tokio::test]
+async fn test_databases_survive_snapshot() {
+    let temp_dir = tempdir().unwrap();
+    let config = persistent_config(temp_dir.path());
+
+    let kvdb = BlazeKVDB::new(config.clone()).await.unwrap();
+    let dispatcher = kvdb.dispatcher();
+
+    dispatcher.execute_on(0, set("key", b"zero")).await;
+    dispatcher.execute_on(2, set("key", b"two")).await;
+
+    // Snapshot (and the AOF compaction after it) carries database 2
+    kvdb.snapshot().await.unwrap();
+
+    // Only in the AOF
+    dispatcher.execute_on(3, set("key", b"three")).await;
+
+    kvdb.shutdown().await.unwrap();
+    drop(dispatcher);
+    drop(kvdb);
+
+    let kvdb = BlazeKVDB::new(config).await.unwrap();
+    let dispatcher = kvdb.dispatcher();
+
+    for (db, expected) in [(0, &b"zero"[..]), (2, b"two"), (3, b"three")] {
+        assert_eq!(
+            dispatcher.execute_on(db, get("key")).await,
+            CommandResponse::Value(expected.to_vec()),
+            "database {}",
+            db
+        );
+    }
+    assert_eq!(
+        dispatcher.execute_on(1, get("key")).await,
+        CommandResponse::Nil
+    );
+
+    kvdb.shutdown().await.unwrap();
+}
+
+#[tokio::test]
+async fn test_recovery_rejects_unconfigured_database() {
+    let temp_dir = tempdir().unwrap();
+    let mut config = persistent_config(temp_dir.path());
+    config.persistence.snapshot_enabled = false;
+
+    let kvdb = BlazeKVDB::new(config.clone()).await.unwrap();
+    kvdb.dispatcher().execute_on(3, set("key", b"three")).await;
+    kvdb.shutdown().await.unwrap();
+    drop(kvdb);
+
+    // Silently dropping database 3 would lose data
+    config.storage.databases = 2;
+    let err = BlazeKVDB::new(config).await.err().unwrap();
+    assert!(err.to_string().contains("database 3"));
+}
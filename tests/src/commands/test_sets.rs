@@ -0,0 +1,110 @@
+use std::sync::Arc;
+
+use blazekvdb::{
+    commands::{
+        CommandHandler, CommandResponse, get::GetCommand, sadd::SAddCommand, scard::SCardCommand,
+        set::SetCommand, sismember::SIsMemberCommand, smembers::SMembersCommand, srem::SRemCommand,
+    },
+    storage::{StorageConfig, StorageEngine, engine::memory::MemoryEngine, value::encode_set},
+};
+
+fn members(items: &[&str]) -> Vec<Vec<u8>> {
+    items.iter().map(|m| m.as_bytes().to_vec()).collect()
+}
+
+#[test]
+fn test_set_commands_validation() {
+    assert!(
+        SAddCommand::new("key".to_string(), vec![])
+            .validate()
+            .is_err()
+    );
+    assert!(
+        SAddCommand::new("".to_string(), members(&["a"]))
+            .validate()
+            .is_err()
+    );
+    assert!(
+        SRemCommand::new("__blaze:key".to_string(), members(&["a"]))
+            .validate()
+            .is_err()
+    );
+
+    // Plain SET cannot forge a typed value
+    let forged = encode_set(&Default::default()).unwrap();
+    assert!(
+        SetCommand::new("key".to_string(), forged)
+            .validate()
+            .is_err()
+    );
+}
+
+#[tokio::test]
+async fn test_set_commands_execute() {
+    let engine = Arc::new(MemoryEngine::new(StorageConfig::default())) as Arc<dyn StorageEngine>;
+
+    // Duplicates in one call are only added once
+    let cmd = SAddCommand::new("set".to_string(), members(&["a", "b", "a"]));
+    assert_eq!(cmd.execute(&*engine).await, CommandResponse::Integer(2));
+
+    let cmd = SAddCommand::new("set".to_string(), members(&["b", "c"]));
+    assert_eq!(cmd.execute(&*engine).await, CommandResponse::Integer(1));
+
+    let cmd = SCardCommand::new("set".to_string());
+    assert_eq!(cmd.execute(&*engine).await, CommandResponse::Integer(3));
+
+    let cmd = SIsMemberCommand::new("set".to_string(), b"c".to_vec());
+    assert_eq!(cmd.execute(&*engine).await, CommandResponse::Bool(true));
+
+    let cmd = SIsMemberCommand::new("set".to_string(), b"z".to_vec());
+    assert_eq!(cmd.execute(&*engine).await, CommandResponse::Bool(false));
+
+    let cmd = SMembersCommand::new("set".to_string());
+    assert_eq!(
+        cmd.execute(&*engine).await,
+        CommandResponse::Members(members(&["a", "b", "c"]))
+    );
+
+    let cmd = SRemCommand::new("set".to_string(), members(&["a", "z"]));
+    assert_eq!(cmd.execute(&*engine).await, CommandResponse::Integer(1));
+
+    // Removing the last member drops the key
+    let cmd = SRemCommand::new("set".to_string(), members(&["b", "c"]));
+    assert_eq!(cmd.execute(&*engine).await, CommandResponse::Integer(2));
+    assert!(!engine.exists("set").await.unwrap());
+
+    // Missing keys behave like empty sets
+    let cmd = SMembersCommand::new("missing".to_string());
+    assert_eq!(
+        cmd.execute(&*engine).await,
+        CommandResponse::Members(vec![])
+    );
+    let cmd = SCardCommand::new("missing".to_string());
+    assert_eq!(cmd.execute(&*engine).await, CommandResponse::Integer(0));
+}
+
+#[tokio::test]
+async fn test_set_commands_wrong_type() {
+    let engine = Arc::new(MemoryEngine::new(StorageConfig::default())) as Arc<dyn StorageEngine>;
+
+    engine.set("string", b"value".to_vec()).await.unwrap();
+    let cmd = SAddCommand::new("string".to_string(), members(&["a"]));
+    assert!(matches!(
+        cmd.execute(&*engine).await,
+        CommandResponse::Error(e) if e.starts_with("WRONGTYPE")
+    ));
+    assert_eq!(engine.get("string").await.unwrap(), Some(b"value".to_vec()));
+
+    let cmd = SMembersCommand::new("string".to_string());
+    assert!(
+        matches!(cmd.execute(&*engine).await, CommandResponse::Error(e) if e.starts_with("WRONGTYPE"))
+    );
+
+    engine.add_members("set", &members(&["a"])).await.unwrap();
+    let cmd = GetCommand::new("set".to_string());
+    assert!(
+        matches!(cmd.execute(&*engine).await, CommandResponse::Error(e) if e.starts_with("WRONGTYPE"))
+    );
+    assert!(engine.get_range("set", 0, -1).await.is_err());
+    assert!(engine.set_range("set", 0, b"x").await.is_err());
+}
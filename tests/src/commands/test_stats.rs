@@ -14,6 +14,9 @@ async fn test_stats_execute() {
     engine.set("key1", b"value1".to_vec()).await.unwrap();
     engine.set("key2", b"value2".to_vec()).await.unwrap();
     engine.set("key3", b"value3".to_vec()).await.unwrap();
+    engine.get("key1").await.unwrap();
+    engine.get("key2").await.unwrap();
+    engine.get("missing").await.unwrap();
 
     let response = StatsCommand.execute(&*engine).await;
 
@@ -23,12 +26,18 @@ async fn test_stats_execute() {
         total_operations,
         hit_rate,
         memory_usage,
+        keyspace_hits,
+        keyspace_misses,
+        evicted_keys,
     } = response
     {
         assert!(total_keys != 0);
         assert!(total_operations != 0);
-        assert!(hit_rate.is_finite());
-        assert!(memory_usage != 0)
+        assert!((hit_rate - 2.0 / 3.0).abs() < f64::EPSILON);
+        assert!(memory_usage != 0);
+        assert_eq!(keyspace_hits, 2);
+        assert_eq!(keyspace_misses, 1);
+        assert_eq!(evicted_keys, 0);
     } else {
         panic!("Expected Stats response");
     }
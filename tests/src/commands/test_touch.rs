@@ -0,0 +1,30 @@
+use std::sync::Arc;
+
+use blazekvdb::{
+    commands::{CommandHandler, CommandResponse, touch::TouchCommand},
+    storage::{StorageConfig, StorageEngine, engine::memory::MemoryEngine},
+};
+
+#[test]
+fn test_touch_validation() {
+    assert!(TouchCommand::new(vec![]).validate().is_err());
+    assert!(TouchCommand::new(vec!["".to_string()]).validate().is_err());
+}
+
+#[tokio::test]
+async fn test_touch_execute() {
+    let config = StorageConfig::default();
+    let engine = Arc::new(MemoryEngine::new(config)) as Arc<dyn StorageEngine>;
+
+    engine.set("key1", b"value1".to_vec()).await.unwrap();
+    engine.set("key2", b"value2".to_vec()).await.unwrap();
+
+    let cmd = TouchCommand::new(vec![
+        "key1".to_string(),
+        "missing".to_string(),
+        "key2".to_string(),
+    ]);
+    let response = cmd.execute(&*engine).await;
+    assert_eq!(response, CommandResponse::Integer(2));
+    assert_eq!(engine.idle_time("key1").await.unwrap(), Some(0));
+}
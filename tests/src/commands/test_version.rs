@@ -0,0 +1,155 @@
+use std::sync::Arc;
+
+use blazekvdb::{
+    commands::{
+        Command, CommandDispatcher, CommandHandler, CommandResponse, getver::GetVerCommand,
+        setver::SetVerCommand, wait::WaitCommand,
+    },
+    config::{FsyncPolicy, PersistenceConfig},
+    storage::{
+        StorageConfig, StorageEngine,
+        engine::memory::MemoryEngine,
+        persistence::{
+            aof::{AppendOnlyFile, Operation},
+            manager::PersistenceManager,
+        },
+    },
+};
+use tempfile::tempdir;
+
+fn getver(key: &str) -> GetVerCommand {
+    GetVerCommand::new(key.to_string())
+}
+
+fn setver(key: &str, value: &[u8], expected: u64) -> SetVerCommand {
+    SetVerCommand::new(key.to_string(), value.to_vec(), expected)
+}
+
+async fn version_of(engine: &dyn StorageEngine, key: &str) -> u64 {
+    match getver(key).execute(engine).await {
+        CommandResponse::Versioned { version, .. } => version,
+        other => panic!("expected a versioned value, got {:?}", other),
+    }
+}
+
+#[test]
+fn test_setver_validation() {
+    assert!(setver("key", b"value", 0).validate().is_ok());
+    assert!(setver("", b"value", 0).validate().is_err());
+    assert!(setver("__blaze:key", b"value", 0).validate().is_err());
+    assert!(getver("").validate().is_err());
+}
+
+#[tokio::test]
+async fn test_versions_change_on_every_write() {
+    let engine = Arc::new(MemoryEngine::new(StorageConfig::default())) as Arc<dyn StorageEngine>;
+
+    assert!(matches!(
+        getver("doc").execute(&*engine).await,
+        CommandResponse::Error(_)
+    ));
+
+    engine.set("doc", b"v1".to_vec()).await.unwrap();
+    let first = version_of(&*engine, "doc").await;
+    assert!(first > 0);
+
+    // Reads leave the version alone, in-place writes move it on
+    assert_eq!(version_of(&*engine, "doc").await, first);
+    engine.set_range("doc", 2, b"!").await.unwrap();
+    let second = version_of(&*engine, "doc").await;
+    assert!(second > first);
+    assert_eq!(
+        getver("doc").execute(&*engine).await,
+        CommandResponse::Versioned {
+            value: b"v1!".to_vec(),
+            version: second
+        }
+    );
+
+    // A deleted and recreated key never comes back at an old version
+    engine.delete("doc").await.unwrap();
+    engine.set("doc", b"v1!".to_vec()).await.unwrap();
+    assert!(version_of(&*engine, "doc").await > second);
+
+    // Sets and lists are not plain values
+    engine.add_members("tags", &[b"a".to_vec()]).await.unwrap();
+    assert!(matches!(
+        getver("tags").execute(&*engine).await,
+        CommandResponse::Error(e) if e.starts_with("WRONGTYPE")
+    ));
+}
+
+#[tokio::test]
+async fn test_setver_only_writes_at_expected_version() {
+    let engine = Arc::new(MemoryEngine::new(StorageConfig::default())) as Arc<dyn StorageEngine>;
+
+    // 0 creates a missing key, and only a missing one
+    let CommandResponse::Integer(created) = setver("doc", b"v1", 0).execute(&*engine).await else {
+        panic!("expected the new version");
+    };
+    assert_eq!(created as u64, version_of(&*engine, "doc").await);
+    assert!(matches!(
+        setver("doc", b"other", 0).execute(&*engine).await,
+        CommandResponse::Error(e) if e.starts_with("VERSIONMISMATCH")
+    ));
+
+    let CommandResponse::Integer(updated) =
+        setver("doc", b"v2", created as u64).execute(&*engine).await
+    else {
+        panic!("expected the new version");
+    };
+    assert!(updated > created);
+
+    // A stale version loses, and leaves the value alone
+    assert!(matches!(
+        setver("doc", b"stale", created as u64).execute(&*engine).await,
+        CommandResponse::Error(e) if e.starts_with("VERSIONMISMATCH")
+    ));
+    assert_eq!(engine.get("doc").await.unwrap(), Some(b"v2".to_vec()));
+}
+
+#[tokio::test]
+async fn test_setver_logs_only_accepted_writes() {
+    let temp_dir = tempdir().unwrap();
+    let aof_path = temp_dir.path().join("test.aof");
+
+    let config = PersistenceConfig {
+        aof_path: aof_path.clone(),
+        fsync_policy: FsyncPolicy::Always,
+        aof_rewrite_percentage: 0,
+        aof_rewrite_min_size: 0,
+        snapshot_enabled: false,
+        snapshot_dir: temp_dir.path().join("snapshots"),
+        ..Default::default()
+    };
+
+    let storage = Arc::new(MemoryEngine::new(StorageConfig::default())) as Arc<dyn StorageEngine>;
+    let persistence = Arc::new(
+        PersistenceManager::new(config, storage.clone())
+            .await
+            .unwrap(),
+    );
+    let dispatcher = CommandDispatcher::new(storage).with_persistence(persistence);
+
+    dispatcher
+        .execute(Command::SetVer(setver("doc", b"v1", 0)))
+        .await;
+    assert!(matches!(
+        dispatcher
+            .execute(Command::SetVer(setver("doc", b"lost", 0)))
+            .await,
+        CommandResponse::Error(_)
+    ));
+    dispatcher
+        .execute(Command::Wait(WaitCommand::new(0, 1000)))
+        .await;
+
+    let ops = AppendOnlyFile::new(&aof_path)
+        .await
+        .unwrap()
+        .read_operations()
+        .await
+        .unwrap();
+    assert_eq!(ops.len(), 1);
+    assert!(matches!(&ops[0], Operation::Put { key, value } if key == "doc" && value == b"v1"));
+}
@@ -0,0 +1,85 @@
+use std::sync::Arc;
+
+use blazekvdb::{
+    commands::{
+        Command, CommandDispatcher, CommandHandler, CommandResponse, set::SetCommand,
+        wait::WaitCommand,
+    },
+    config::{FsyncPolicy, PersistenceConfig},
+    storage::{
+        StorageConfig, StorageEngine,
+        engine::memory::MemoryEngine,
+        persistence::{aof::AppendOnlyFile, manager::PersistenceManager},
+    },
+};
+use tempfile::tempdir;
+
+#[tokio::test]
+async fn test_wait_without_persistence() {
+    let config = StorageConfig::default();
+    let engine = Arc::new(MemoryEngine::new(config)) as Arc<dyn StorageEngine>;
+
+    let response = WaitCommand::new(0, 100).execute(&*engine).await;
+    assert!(matches!(response, CommandResponse::Error(_)));
+}
+
+#[test]
+fn test_wait_validation() {
+    assert!(WaitCommand::new(0, 0).validate().is_ok());
+    assert!(WaitCommand::new(1, 0).validate().is_ok());
+
+    // Only the local fsync can ack, more replicas could never be reached
+    assert!(WaitCommand::new(3, 0).validate().is_err());
+}
+
+#[tokio::test]
+async fn test_wait_acks_local_fsync() {
+    let temp_dir = tempdir().unwrap();
+    let aof_path = temp_dir.path().join("test.aof");
+
+    let config = PersistenceConfig {
+        aof_path: aof_path.clone(),
+        // Never fsync on its own, so only WAIT makes the write durable
+        fsync_policy: FsyncPolicy::Never,
+        aof_rewrite_percentage: 0,
+        aof_rewrite_min_size: 0,
+        snapshot_enabled: false,
+        snapshot_dir: temp_dir.path().join("snapshots"),
+        ..Default::default()
+    };
+
+    let storage = Arc::new(MemoryEngine::new(StorageConfig::default())) as Arc<dyn StorageEngine>;
+    let persistence = Arc::new(
+        PersistenceManager::new(config, storage.clone())
+            .await
+            .unwrap(),
+    );
+    let dispatcher = CommandDispatcher::new(storage).with_persistence(persistence);
+
+    let set = SetCommand::new("key1".to_string(), b"value1".to_vec());
+    assert_eq!(
+        dispatcher.execute(Command::Set(set)).await,
+        CommandResponse::Ok
+    );
+
+    let response = dispatcher
+        .execute(Command::Wait(WaitCommand::new(0, 1000)))
+        .await;
+    assert_eq!(response, CommandResponse::Integer(1));
+
+    // Not a write, so READONLY mode lets it through
+    dispatcher.set_read_only(true);
+    let response = dispatcher
+        .execute(Command::Wait(WaitCommand::new(0, 1000)))
+        .await;
+    assert_eq!(response, CommandResponse::Integer(1));
+
+    // The write is on disk once WAIT returns
+    let ops = AppendOnlyFile::new(&aof_path)
+        .await
+        .unwrap()
+        .read_operations()
+        .await
+        .unwrap();
+    assert_eq!(ops.len(), 1);
+}
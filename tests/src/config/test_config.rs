@@ -0,0 +1,444 @@
+use blazekvdb::{
+    config::{BlazeServerConfig, CliOverrides, ConfigError, ConfigSource, interpolate_env},
+    storage::MaxMemoryPolicy,
+};
+use tempfile::tempdir;
+
+#[test]
+fn test_builder_defaults() {
+    let layered = BlazeServerConfig::builder().build().unwrap();
+
+    assert_eq!(layered.base, ConfigSource::Default);
+    assert!(layered.overrides.is_empty());
+    assert_eq!(
+        layered.source_of("server.bind_addr"),
+        &ConfigSource::Default
+    );
+}
+
+#[test]
+fn test_builder_file_then_cli() {
+    let temp_dir = tempdir().unwrap();
+    let path = temp_dir.path().join("blaze.toml");
+
+    let mut file_config = BlazeServerConfig::default();
+    file_config.server.bind_addr = "127.0.0.1:7000".parse().unwrap();
+    file_config.to_toml_file(&path).unwrap();
+
+    // CLI is applied last even when added before the file
+    let layered = BlazeServerConfig::builder()
+        .with_cli(CliOverrides {
+            bind: Some("127.0.0.1:7001".to_string()),
+            debug: true,
+            ..Default::default()
+        })
+        .with_file(&path)
+        .build()
+        .unwrap();
+
+    assert_eq!(layered.config.server.bind_addr.port(), 7001);
+    assert_eq!(layered.config.observability.log_level, "debug");
+    assert_eq!(
+        layered.source_of("server.bind_addr"),
+        &ConfigSource::Cli("--bind")
+    );
+    assert_eq!(
+        layered.source_of("server.max_connections"),
+        &ConfigSource::File(path)
+    );
+}
+
+#[test]
+fn test_builder_cli_exec_sets_startup_script() {
+    let layered = BlazeServerConfig::builder()
+        .with_cli(CliOverrides {
+            exec: Some("seed.txt".into()),
+            ..Default::default()
+        })
+        .build()
+        .unwrap();
+
+    assert_eq!(
+        layered.config.server.startup_script.as_deref(),
+        Some(std::path::Path::new("seed.txt"))
+    );
+    assert_eq!(
+        layered.source_of("server.startup_script"),
+        &ConfigSource::Cli("--exec")
+    );
+}
+
+#[test]
+fn test_builder_cli_restore() {
+    let layered = BlazeServerConfig::builder()
+        .with_cli(CliOverrides {
+            restore_from: Some("backup.rdb".into()),
+            restore_snapshot_only: true,
+            ..Default::default()
+        })
+        .build()
+        .unwrap();
+
+    assert_eq!(
+        layered.config.persistence.restore_from.as_deref(),
+        Some(std::path::Path::new("backup.rdb"))
+    );
+    assert_eq!(
+        layered.source_of("persistence.restore_snapshot_only"),
+        &ConfigSource::Cli("--restore-snapshot-only")
+    );
+
+    // Skipping the AOF only makes sense for a restore, and a restore needs persistence
+    let result = BlazeServerConfig::builder()
+        .with_cli(CliOverrides {
+            restore_snapshot_only: true,
+            ..Default::default()
+        })
+        .build();
+    assert!(result.is_err());
+
+    let result = BlazeServerConfig::builder()
+        .with_cli(CliOverrides {
+            restore_from: Some("backup.rdb".into()),
+            no_persistence: true,
+            ..Default::default()
+        })
+        .build();
+    assert!(result.is_err());
+}
+
+#[test]
+fn test_builder_invalid_cli_bind() {
+    let result = BlazeServerConfig::builder()
+        .with_cli(CliOverrides {
+            bind: Some("not-an-addr".to_string()),
+            ..Default::default()
+        })
+        .build();
+
+    assert!(result.is_err());
+}
+
+#[test]
+fn test_builder_missing_file() {
+    let result = BlazeServerConfig::builder()
+        .with_file("does-not-exist.toml")
+        .build();
+
+    assert!(result.is_err());
+}
+
+#[test]
+fn test_from_json_file_roundtrip() {
+    let temp_dir = tempdir().unwrap();
+    let path = temp_dir.path().join("blaze.conf");
+
+    let mut config = BlazeServerConfig::default();
+    config.server.max_connections = 42;
+    config.to_json_file(&path).unwrap();
+
+    let loaded = BlazeServerConfig::from_json_file(&path).unwrap();
+    assert_eq!(loaded.server.max_connections, 42);
+}
+
+#[test]
+fn test_validate_bind_addr_collision() {
+    let mut config = BlazeServerConfig::default();
+    config.observability.metrics_addr = Some(config.server.bind_addr);
+
+    let err = config.validate().unwrap_err().to_string();
+    assert!(err.contains("server.bind_addr"));
+    assert!(err.contains("observability.metrics_addr"));
+
+    // Disabled endpoints are not checked
+    config.observability.metrics_enabled = false;
+    assert!(config.validate().is_ok());
+}
+
+#[test]
+fn test_validate_unspecified_ip_collision() {
+    let mut config = BlazeServerConfig::default();
+    config.server.bind_addr = "0.0.0.0:8080".parse().unwrap();
+
+    let err = config.validate().unwrap_err().to_string();
+    assert!(err.contains("observability.health_check_addr"));
+}
+
+#[test]
+fn test_validate_default_ttl_above_max() {
+    let mut config = BlazeServerConfig::default();
+    config.storage.default_ttl = Some(120);
+    config.storage.max_ttl = Some(60);
+
+    assert!(config.validate().is_err());
+
+    config.storage.default_ttl = Some(30);
+    assert!(config.validate().is_ok());
+}
+
+#[test]
+fn test_validate_key_limits() {
+    let mut config = BlazeServerConfig::default();
+    config.storage.reserved_prefix = Some(String::new());
+    assert!(config.validate().is_err());
+
+    // None disables the reserved namespace
+    config.storage.reserved_prefix = None;
+    assert!(config.validate().is_ok());
+
+    config.storage.max_key_size = 0;
+    assert!(config.validate().is_err());
+}
+
+#[test]
+fn test_validate_bloom_filter() {
+    let mut config = BlazeServerConfig::default();
+    assert!(!config.storage.bloom_filter);
+
+    // Only matters once the filters are on
+    config.storage.bloom_filter_capacity = 0;
+    assert!(config.validate().is_ok());
+
+    config.storage.bloom_filter = true;
+    assert!(config.validate().is_err());
+
+    config.storage.bloom_filter_capacity = 1000;
+    assert!(config.validate().is_ok());
+}
+
+#[test]
+fn test_validate_maxmemory_policy() {
+    let mut config = BlazeServerConfig::default();
+    assert_eq!(config.storage.maxmemory_policy, MaxMemoryPolicy::NoEviction);
+
+    // Only matters once keys are evicted
+    config.storage.eviction_sample_size = 0;
+    assert!(config.validate().is_ok());
+
+    config.storage.maxmemory_policy = MaxMemoryPolicy::VolatileLru;
+    assert!(config.validate().is_err());
+
+    config.storage.eviction_sample_size = 10;
+    assert!(config.validate().is_ok());
+}
+
+#[test]
+fn test_maxmemory_policy_names() {
+    let temp_dir = tempdir().unwrap();
+    let path = temp_dir.path().join("blaze.toml");
+    BlazeServerConfig::default().to_toml_file(&path).unwrap();
+    let defaults = std::fs::read_to_string(&path).unwrap();
+    assert!(defaults.contains("maxmemory_policy = \"noeviction\""));
+
+    for (name, policy) in [
+        ("noeviction", MaxMemoryPolicy::NoEviction),
+        ("allkeys-lru", MaxMemoryPolicy::AllkeysLru),
+        ("allkeys-lfu", MaxMemoryPolicy::AllkeysLfu),
+        ("allkeys-random", MaxMemoryPolicy::AllkeysRandom),
+        ("volatile-lru", MaxMemoryPolicy::VolatileLru),
+        ("volatile-ttl", MaxMemoryPolicy::VolatileTtl),
+    ] {
+        std::fs::write(
+            &path,
+            defaults.replace(
+                "maxmemory_policy = \"noeviction\"",
+                &format!("maxmemory_policy = \"{}\"", name),
+            ),
+        )
+        .unwrap();
+        let config = BlazeServerConfig::from_toml_file(&path).unwrap();
+        assert_eq!(config.storage.maxmemory_policy, policy);
+    }
+}
+
+#[test]
+fn test_legacy_eviction_sample_size_needs_a_policy() {
+    let temp_dir = tempdir().unwrap();
+    let path = temp_dir.path().join("blaze.toml");
+    BlazeServerConfig::default().to_toml_file(&path).unwrap();
+    let defaults = std::fs::read_to_string(&path).unwrap();
+    let legacy = defaults.replace("maxmemory_policy = \"noeviction\"\n", "");
+
+    // A sample size alone used to mean LRU eviction, it must not silently turn into none
+    std::fs::write(&path, &legacy).unwrap();
+    let error = BlazeServerConfig::from_toml_file(&path).unwrap_err();
+    assert!(error.to_string().contains("maxmemory_policy"), "{}", error);
+
+    // Zero never evicted, so it still means noeviction
+    std::fs::write(
+        &path,
+        legacy.replace("eviction_sample_size = 5", "eviction_sample_size = 0"),
+    )
+    .unwrap();
+    let config = BlazeServerConfig::from_toml_file(&path).unwrap();
+    assert_eq!(config.storage.maxmemory_policy, MaxMemoryPolicy::NoEviction);
+
+    let path = temp_dir.path().join("blaze.json");
+    std::fs::write(
+        &path,
+        r#"{"storage": {"max_memory": 1048576, "eviction_sample_size": 5}}"#,
+    )
+    .unwrap();
+    let error = BlazeServerConfig::from_json_file(&path).unwrap_err();
+    assert!(error.to_string().contains("maxmemory_policy"), "{}", error);
+}
+
+#[test]
+fn test_validate_snapshot_jitter() {
+    let mut config = BlazeServerConfig::default();
+    config.persistence.snapshot_interval = 60;
+    config.persistence.snapshot_jitter = 60;
+    assert!(config.validate().is_err());
+
+    config.persistence.snapshot_jitter = 15;
+    assert!(config.validate().is_ok());
+
+    config.persistence.snapshot_interval = 0;
+    assert!(config.validate().is_err());
+}
+
+#[test]
+fn test_validate_listen_options() {
+    let mut config = BlazeServerConfig::default();
+    assert_eq!(config.server.listen_backlog, 1024);
+    assert_eq!(config.server.accept_tasks, 1);
+    assert!(config.server.tcp_nodelay);
+
+    config.server.listen_backlog = 0;
+    assert!(config.validate().is_err());
+
+    config.server.listen_backlog = 4096;
+    config.server.accept_tasks = 0;
+    assert!(config.validate().is_err());
+
+    config.server.accept_tasks = 4;
+    assert!(config.validate().is_ok());
+}
+
+#[test]
+fn test_interpolate_env() {
+    // SAFETY: variable names are unique to this test
+    unsafe {
+        std::env::set_var("BLAZE_TEST_INTERP_DIR", "/var/lib/blaze");
+        std::env::set_var("BLAZE_TEST_INTERP_EMPTY", "");
+    }
+
+    assert_eq!(
+        interpolate_env("${BLAZE_TEST_INTERP_DIR}/blazekvdb.aof").unwrap(),
+        "/var/lib/blaze/blazekvdb.aof"
+    );
+    assert_eq!(
+        interpolate_env("${BLAZE_TEST_INTERP_UNSET:-data}/snapshots").unwrap(),
+        "data/snapshots"
+    );
+    assert_eq!(
+        interpolate_env("${BLAZE_TEST_INTERP_EMPTY:-fallback}").unwrap(),
+        "fallback"
+    );
+    assert_eq!(
+        interpolate_env("pa$$word $${literal}").unwrap(),
+        "pa$$word ${literal}"
+    );
+
+    assert!(interpolate_env("${BLAZE_TEST_INTERP_UNSET}").is_err());
+    assert!(interpolate_env("${BLAZE_TEST_INTERP_DIR").is_err());
+    assert!(interpolate_env("${}").is_err());
+}
+
+#[test]
+fn test_config_file_interpolation() {
+    // SAFETY: variable name is unique to this test
+    unsafe {
+        std::env::set_var("BLAZE_TEST_FILE_DATA_DIR", "/srv/blaze");
+    }
+
+    let temp_dir = tempdir().unwrap();
+    let path = temp_dir.path().join("blaze.toml");
+    std::fs::write(
+        &path,
+        r#"
+[server]
+bind_addr = "127.0.0.1:6380"
+
+[storage]
+max_memory = 1048576
+persistence_enabled = true
+aof_path = "resplite.aof"
+snapshot_interval = 3600
+shard_count = 16
+
+[persistence]
+aof_path = "${BLAZE_TEST_FILE_DATA_DIR}/blazekvdb.aof"
+snapshot_dir = "${BLAZE_TEST_FILE_SNAPSHOTS:-/tmp/snapshots}"
+
+[observability]
+"#,
+    )
+    .unwrap();
+
+    let config = BlazeServerConfig::from_toml_file(&path).unwrap();
+    assert_eq!(
+        config.persistence.aof_path,
+        std::path::PathBuf::from("/srv/blaze/blazekvdb.aof")
+    );
+    assert_eq!(
+        config.persistence.snapshot_dir,
+        std::path::PathBuf::from("/tmp/snapshots")
+    );
+
+    // Missing variables without a default fail the load
+    std::fs::write(
+        temp_dir.path().join("broken.json"),
+        r#"{"persistence": {"aof_path": "${BLAZE_TEST_FILE_MISSING}/x.aof"}}"#,
+    )
+    .unwrap();
+    assert!(matches!(
+        BlazeServerConfig::from_json_file(temp_dir.path().join("broken.json")),
+        Err(ConfigError::Interpolation(_))
+    ));
+}
+
+#[test]
+fn test_validate_trace_sample_rate() {
+    let mut config = BlazeServerConfig::default();
+    assert_eq!(config.observability.trace_sample_rate, 1.0);
+
+    config.observability.trace_sample_rate = 0.1;
+    assert!(config.validate().is_ok());
+
+    config.observability.trace_sample_rate = 1.5;
+    assert!(config.validate().is_err());
+
+    config.observability.trace_sample_rate = f64::NAN;
+    assert!(config.validate().is_err());
+}
+
+#[test]
+fn test_validate_renamed_commands() {
+    let mut config = BlazeServerConfig::default();
+    let renames = |pairs: &[(&str, &str)]| {
+        pairs
+            .iter()
+            .map(|(from, to)| (from.to_string(), to.to_string()))
+            .collect()
+    };
+
+    config.security.renamed_commands = renames(&[("FLUSHDB", ""), ("config", "cfg-9f2a")]);
+    assert!(config.validate().is_ok());
+
+    config.security.renamed_commands = renames(&[("NOSUCHCMD", "")]);
+    let err = config.validate().unwrap_err().to_string();
+    assert!(err.contains("unknown command 'NOSUCHCMD'"), "{}", err);
+
+    // A new name may not take over a command that still answers to it
+    config.security.renamed_commands = renames(&[("FLUSHDB", "get")]);
+    assert!(config.validate().is_err());
+    config.security.renamed_commands = renames(&[("FLUSHDB", "GET"), ("GET", "fetch")]);
+    assert!(config.validate().is_ok());
+
+    config.security.renamed_commands = renames(&[("FLUSHDB", "same"), ("DEBUG", "SAME")]);
+    assert!(config.validate().is_err());
+
+    config.security.renamed_commands = renames(&[("FLUSHDB", "flush db")]);
+    assert!(config.validate().is_err());
+}
@@ -1,6 +1,15 @@
+#[cfg(test)]
+mod bootstrap;
+
+#[cfg(test)]
+mod client;
+
 #[cfg(test)]
 mod commands;
 
+#[cfg(test)]
+mod config;
+
 #[cfg(test)]
 mod storage;
 
@@ -1 +1,2 @@
+pub mod test_parser_properties;
 pub mod test_protocol_parser;
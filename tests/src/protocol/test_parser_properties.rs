@@ -0,0 +1,399 @@
+use std::time::Duration;
+
+use base64::{Engine, engine::general_purpose::STANDARD};
+use blazekvdb::{
+    commands::{
+        Command, CommandResponse, KeyLimits,
+        auth::AuthCommand,
+        blpop::BLPopCommand,
+        debug::DebugCommand,
+        delete::DeleteCommand,
+        delprefix::DelPrefixCommand,
+        echo::EchoCommand,
+        eval::{AtomicOp, EvalCommand},
+        exist::ExistCommand,
+        expire::ExpireCommand,
+        get::GetCommand,
+        getrange::GetRangeCommand,
+        getver::GetVerCommand,
+        hello::HelloCommand,
+        incrbyfloat::IncrByFloatCommand,
+        info::InfoCommand,
+        ping::PingCommand,
+        proto::ProtocolMode,
+        push::PushCommand,
+        relocate::RelocateCommand,
+        sadd::SAddCommand,
+        scan::ScanCommand,
+        select::SelectCommand,
+        set::SetCommand,
+        setbit::SetBitCommand,
+        setchunk::SetChunkCommand,
+        setver::SetVerCommand,
+        subscribe::{SubscribeCommand, UnsubscribeCommand},
+        ttl::TtlCommand,
+    },
+    protocol::parser::{CommandRenames, MAX_ARGUMENTS, ProtocolError, ProtocolParser},
+};
+use proptest::prelude::*;
+
+const COMMANDS: &[&str] = &[
+    "GET",
+    "SET",
+    "SETEX",
+    "SETCHUNK",
+    "GETVER",
+    "SETVER",
+    "GETRANGE",
+    "SETRANGE",
+    "SETBIT",
+    "INCRBYFLOAT",
+    "EVAL",
+    "GETBIT",
+    "BITCOUNT",
+    "DEL",
+    "DELETE",
+    "DELPREFIX",
+    "EXIST",
+    "EXISTS",
+    "SADD",
+    "SREM",
+    "SISMEMBER",
+    "SMEMBERS",
+    "SCARD",
+    "LPUSH",
+    "RPUSH",
+    "BLPOP",
+    "SCAN",
+    "OBJECT",
+    "MEMORY",
+    "TOUCH",
+    "EXPIRE",
+    "PEXPIRE",
+    "EXPIREAT",
+    "PEXPIREAT",
+    "TTL",
+    "PTTL",
+    "COMPRESS",
+    "WAIT",
+    "PROTO",
+    "READONLY",
+    "CONFIG",
+    "SELECT",
+    "AUTH",
+    "SUBSCRIBE",
+    "UNSUBSCRIBE",
+    "INFO",
+    "METRICS",
+    "DEBUG",
+    "HELLO",
+    "RESET",
+    "FLUSHDB",
+    "BGREWRITEAOF",
+    "RELOCATE",
+    "STATS",
+    "ECHO",
+    "PING",
+];
+
+// Words that tend to reach deeper parser branches than random bytes do
+fn argument() -> impl Strategy<Value = String> {
+    prop_oneof![
+        any::<String>(),
+        "[a-zA-Z0-9+/=]{0,16}",
+        any::<i64>().prop_map(|n| n.to_string()),
+        Just("\0".to_string()),
+        Just("ON".to_string()),
+        Just("GET".to_string()),
+        Just("IDLETIME".to_string()),
+        Just("FREQ".to_string()),
+        Just("ENCODING".to_string()),
+        Just("USAGE".to_string()),
+        Just("SORTED".to_string()),
+        Just("COUNT".to_string()),
+        Just("CAS".to_string()),
+        Just("18446744073709551616".to_string()),
+    ]
+}
+
+// A known command name, in random case, followed by arbitrary arguments
+fn command_line() -> impl Strategy<Value = String> {
+    (
+        prop::sample::select(COMMANDS),
+        any::<bool>(),
+        prop::collection::vec(argument(), 0..8),
+        "[ \t\u{a0}\u{3000}]{1,3}",
+    )
+        .prop_map(|(name, lower, args, sep)| {
+            let name = if lower {
+                name.to_lowercase()
+            } else {
+                name.to_string()
+            };
+            std::iter::once(name)
+                .chain(args)
+                .collect::<Vec<_>>()
+                .join(&sep)
+        })
+}
+
+// Commands whose arguments fit the text framing
+fn encodable_command() -> impl Strategy<Value = Command> {
+    let key = "[a-zA-Z0-9:_.-]{1,32}";
+    let value = prop::collection::vec(any::<u8>(), 1..64);
+
+    prop_oneof![
+        key.prop_map(|key| Command::Get(GetCommand::new(key))),
+        (key, value.clone(), prop::option::of(1u64..100_000)).prop_map(|(key, value, ttl)| {
+            let cmd = SetCommand::new(key, value);
+            Command::Set(match ttl {
+                Some(ttl) => cmd.with_ttl(ttl),
+                None => cmd,
+            })
+        }),
+        key.prop_map(|key| Command::GetVer(GetVerCommand::new(key))),
+        (key, value.clone(), any::<u64>()).prop_map(|(key, value, expected)| {
+            Command::SetVer(SetVerCommand::new(key, value, expected))
+        }),
+        (key, any::<usize>())
+            .prop_map(|(key, total_len)| Command::SetChunk(SetChunkCommand::new(key, total_len))),
+        (key, any::<i64>(), any::<i64>())
+            .prop_map(|(key, start, end)| Command::GetRange(GetRangeCommand::new(key, start, end))),
+        (key, any::<usize>(), any::<bool>())
+            .prop_map(|(key, offset, bit)| Command::SetBit(SetBitCommand::new(key, offset, bit))),
+        (
+            key,
+            prop::num::f64::NORMAL | prop::num::f64::SUBNORMAL | prop::num::f64::ZERO
+        )
+            .prop_map(|(key, delta)| Command::IncrByFloat(IncrByFloatCommand::new(key, delta))),
+        (key, value.clone(), value.clone()).prop_map(|(key, expected, new)| {
+            Command::Eval(EvalCommand::new(key, AtomicOp::Cas { expected, new }))
+        }),
+        (
+            key,
+            prop::num::f64::NORMAL | prop::num::f64::SUBNORMAL | prop::num::f64::ZERO
+        )
+            .prop_map(|(key, n)| Command::Eval(EvalCommand::new(key, AtomicOp::AddMax(n)))),
+        prop::collection::vec(key, 1..4)
+            .prop_map(|keys| Command::Delete(DeleteCommand::many(keys))),
+        key.prop_map(|prefix| Command::DelPrefix(DelPrefixCommand::new(prefix))),
+        Just(Command::DelPrefix(DelPrefixCommand::all())),
+        prop::collection::vec(key, 1..4).prop_map(|keys| Command::Exist(ExistCommand::many(keys))),
+        key.prop_map(|key| Command::Exist(ExistCommand::new(key))),
+        (key, prop::collection::vec(value.clone(), 1..4))
+            .prop_map(|(key, members)| Command::SAdd(SAddCommand::new(key, members))),
+        (key, prop::collection::vec(value, 1..4), any::<bool>())
+            .prop_map(|(key, items, front)| { Command::Push(PushCommand::new(key, items, front)) }),
+        (key, 0u64..10_000_000).prop_map(|(key, timeout)| {
+            Command::BLPop(BLPopCommand::new(key, Duration::from_millis(timeout)))
+        }),
+        (key, any::<u64>(), any::<bool>(), any::<bool>()).prop_map(
+            |(key, amount, millis, absolute)| {
+                Command::Expire(ExpireCommand::new(key, amount, millis, absolute))
+            }
+        ),
+        (key, any::<bool>()).prop_map(|(key, millis)| Command::Ttl(TtlCommand::new(key, millis))),
+        (
+            "[a-zA-Z0-9:_]{0,8}",
+            any::<bool>(),
+            prop::option::of(1usize..10_000)
+        )
+            .prop_map(|(prefix, sorted, count)| {
+                let cmd = ScanCommand::new(prefix);
+                let cmd = if sorted { cmd.sorted() } else { cmd };
+                Command::Scan(match count {
+                    Some(count) => cmd.with_count(count),
+                    None => cmd,
+                })
+            }),
+        any::<usize>().prop_map(|index| Command::Select(SelectCommand::new(index))),
+        (prop::option::of(key), "[^\r\n]{0,16}")
+            .prop_map(|(username, password)| { Command::Auth(AuthCommand { username, password }) }),
+        prop::collection::vec(key, 1..4)
+            .prop_map(|channels| Command::Subscribe(SubscribeCommand::new(channels))),
+        prop::collection::vec(key, 0..4)
+            .prop_map(|channels| Command::Unsubscribe(UnsubscribeCommand::new(channels))),
+        prop::option::of(prop::sample::select(vec![
+            "stats",
+            "persistence",
+            "keyspace"
+        ]))
+        .prop_map(|section| Command::Info(InfoCommand::new(section.map(str::to_string)))),
+        any::<bool>().prop_map(|enabled| Command::Debug(DebugCommand::SetActiveExpire(enabled))),
+        Just(Command::Debug(DebugCommand::Reload)),
+        (
+            prop::option::of("[^\r\n]{1,16}"),
+            prop::option::of("[^\r\n]{1,16}"),
+            any::<bool>()
+        )
+            .prop_filter("AOF or SNAPSHOTS", |(aof, dir, _)| aof.is_some()
+                || dir.is_some())
+            .prop_map(|(aof_path, snapshot_dir, copy)| {
+                Command::Relocate(RelocateCommand {
+                    aof_path,
+                    snapshot_dir,
+                    copy,
+                })
+            }),
+        prop::option::of(any::<u32>())
+            .prop_map(|protover| Command::Hello(HelloCommand::new(protover))),
+        prop::collection::vec(any::<u8>(), 1..64)
+            .prop_map(|message| Command::Echo(EchoCommand::new(message))),
+        Just(Command::Reset),
+        Just(Command::FlushDb),
+        Just(Command::Metrics),
+        Just(Command::Stats),
+        Just(Command::Ping),
+        prop::collection::vec(any::<u8>(), 1..64)
+            .prop_map(|message| Command::PingMessage(PingCommand::new(Some(message)))),
+    ]
+}
+
+fn text_response() -> impl Strategy<Value = CommandResponse> {
+    prop_oneof![
+        prop::collection::vec(any::<u8>(), 0..64).prop_map(CommandResponse::Value),
+        Just(CommandResponse::Ok),
+        any::<bool>().prop_map(CommandResponse::Bool),
+        any::<i64>().prop_map(CommandResponse::Integer),
+        prop::collection::vec("[a-zA-Z0-9:_]{1,16}", 0..4).prop_map(CommandResponse::Keys),
+        prop::collection::vec(prop::collection::vec(any::<u8>(), 1..16), 0..4)
+            .prop_map(CommandResponse::Members),
+        prop::collection::vec(("[a-z_]{1,12}", "[a-zA-Z0-9.,: ]{0,16}"), 0..4)
+            .prop_map(CommandResponse::Map),
+        Just(CommandResponse::Pong),
+        Just(CommandResponse::Nil),
+        (prop::collection::vec(any::<u8>(), 0..64), any::<u64>())
+            .prop_map(|(value, version)| CommandResponse::Versioned { value, version }),
+        "[a-zA-Z0-9 ]{0,32}".prop_map(CommandResponse::Error),
+        (
+            "[a-zA-Z0-9:_@]{1,24}",
+            prop::collection::vec(any::<u8>(), 0..32)
+        )
+            .prop_map(|(channel, payload)| CommandResponse::Message { channel, payload }),
+    ]
+}
+
+proptest! {
+    #[test]
+    fn prop_serialized_commands_parse_back(command in encodable_command()) {
+        let line = ProtocolParser::serialize_command(&command).unwrap();
+        prop_assert!(line.ends_with('\n'));
+        prop_assert_eq!(ProtocolParser::parse_command(&line).unwrap(), command);
+    }
+
+    #[test]
+    fn prop_serialized_responses_parse_back(response in text_response()) {
+        let reply = ProtocolParser::serialize_response(&response).unwrap();
+        let header = reply.lines().next().unwrap();
+        prop_assert_eq!(
+            ProtocolParser::response_body_lines(header).unwrap(),
+            reply.lines().count() - 1
+        );
+        prop_assert_eq!(ProtocolParser::parse_response(&reply).unwrap(), response);
+    }
+
+    #[test]
+    fn prop_arbitrary_bytes_never_panic(bytes in prop::collection::vec(any::<u8>(), 0..512)) {
+        let message = String::from_utf8_lossy(&bytes);
+
+        let _ = ProtocolParser::parse_command(&message);
+        let _ = ProtocolParser::parse_command_in(&message, ProtocolMode::Json, &KeyLimits::default(), &CommandRenames::default());
+        let _ = ProtocolParser::parse_commands(&message);
+    }
+
+    #[test]
+    fn prop_command_lines_parse_or_error(line in command_line()) {
+        // Either outcome is fine, as long as the parser returns instead of panicking
+        match ProtocolParser::parse_command(&line) {
+            Ok(_) | Err(ProtocolError::InvalidFormat(_))
+            | Err(ProtocolError::MissingArguments(_))
+            | Err(ProtocolError::UnknownCommand(_))
+            | Err(ProtocolError::UnsupportedOption(_)) => {}
+            Err(e) => prop_assert!(false, "unexpected error for {:?}: {}", line, e),
+        }
+    }
+
+    #[test]
+    fn prop_set_roundtrips_base64_values(
+        key in "[a-zA-Z0-9:_]{1,32}",
+        value in prop::collection::vec(any::<u8>(), 1..256),
+    ) {
+        let line = format!("SET {} {}", key, STANDARD.encode(&value));
+        let cmd = ProtocolParser::parse_command(&line).unwrap();
+        prop_assert_eq!(cmd, Command::Set(SetCommand::new(key, value)));
+    }
+}
+
+#[test]
+fn test_parse_embedded_nul_and_odd_unicode() {
+    let cmd = ProtocolParser::parse_command("GET a\0b").unwrap();
+    assert!(matches!(cmd, Command::Get(ref get) if get.key == "a\0b"));
+
+    // Unicode whitespace separates words like ASCII whitespace
+    assert!(ProtocolParser::parse_command("GET\u{3000}key").is_ok());
+    assert!(ProtocolParser::parse_command("\u{a0}\u{2028}").is_err());
+
+    // Case folding that changes length must not confuse command matching
+    assert!(ProtocolParser::parse_command("ß key").is_err());
+    assert!(ProtocolParser::parse_command("\u{fffd}\u{fffd} key").is_err());
+}
+
+#[test]
+fn test_parse_rejects_huge_argument_count() {
+    let line = format!("DEL{}", " k".repeat(MAX_ARGUMENTS));
+    assert!(matches!(
+        ProtocolParser::parse_command(&line),
+        Err(ProtocolError::InvalidFormat(_))
+    ));
+
+    let line = format!("DEL{}", " k".repeat(MAX_ARGUMENTS - 1));
+    assert!(ProtocolParser::parse_command(&line).is_ok());
+}
+
+#[test]
+fn test_serialize_command_rejects_unframeable_arguments() {
+    let cmd = Command::Get(GetCommand::new("two words".to_string()));
+    assert!(ProtocolParser::serialize_command(&cmd).is_err());
+
+    // Would be read back as the start of a quoted argument
+    let cmd = Command::Get(GetCommand::new("\"key".to_string()));
+    assert!(ProtocolParser::serialize_command(&cmd).is_err());
+
+    let cmd = Command::Set(SetCommand::new("key".to_string(), Vec::new()));
+    assert!(ProtocolParser::serialize_command(&cmd).is_err());
+
+    let cmd = Command::Exist(ExistCommand {
+        keys: vec!["a".to_string(), "b".to_string()],
+        count: false,
+    });
+    assert!(ProtocolParser::serialize_command(&cmd).is_err());
+}
+
+#[test]
+fn test_parse_stats_response() {
+    let response = CommandResponse::Stats {
+        total_keys: 3,
+        memory_usage: 1024,
+        hit_rate: 0.5,
+        total_operations: 42,
+        keyspace_hits: 7,
+        keyspace_misses: 3,
+        evicted_keys: 2,
+    };
+    let reply = ProtocolParser::serialize_response(&response).unwrap();
+    assert_eq!(
+        reply,
+        "STATS total_keys=3 memory_usage=1024 hit_rate=0.500 total_operations=42 \
+         keyspace_hits=7 keyspace_misses=3 evicted_keys=2\n"
+    );
+    assert_eq!(ProtocolParser::parse_response(&reply).unwrap(), response);
+
+    // Every field splits the same way
+    let fields: Vec<(&str, &str)> = reply
+        .trim_end()
+        .split(' ')
+        .skip(1)
+        .map(|field| field.split_once('=').unwrap())
+        .collect();
+    assert_eq!(fields.len(), 7);
+}
@@ -1,9 +1,54 @@
+use std::{collections::HashMap, io::Read, time::Duration};
+
 use blazekvdb::{
     commands::{
-        Command, CommandResponse, delete::DeleteCommand, exist::ExistCommand, get::GetCommand,
-        scan::ScanCommand, set::SetCommand,
+        Command, CommandResponse, KeyLimits,
+        auth::AuthCommand,
+        bitcount::BitCountCommand,
+        blpop::BLPopCommand,
+        compress::{CompressCommand, CompressionAlgorithm},
+        config::ConfigCommand,
+        debug::DebugCommand,
+        delete::DeleteCommand,
+        delprefix::DelPrefixCommand,
+        echo::EchoCommand,
+        encoding::{EncodingCommand, ValueEncoding},
+        eval::{AtomicOp, EvalCommand},
+        exist::ExistCommand,
+        expire::ExpireCommand,
+        get::GetCommand,
+        getbit::GetBitCommand,
+        getrange::GetRangeCommand,
+        getver::GetVerCommand,
+        hello::HelloCommand,
+        incrbyfloat::IncrByFloatCommand,
+        info::InfoCommand,
+        memory::MemoryCommand,
+        object::{ObjectCommand, ObjectSubcommand},
+        ping::PingCommand,
+        proto::{ProtoCommand, ProtocolMode},
+        push::PushCommand,
+        readonly::ReadOnlyCommand,
+        relocate::RelocateCommand,
+        sadd::SAddCommand,
+        scan::ScanCommand,
+        select::SelectCommand,
+        set::SetCommand,
+        setbit::SetBitCommand,
+        setchunk::SetChunkCommand,
+        setrange::SetRangeCommand,
+        setver::SetVerCommand,
+        sismember::SIsMemberCommand,
+        snapshot::SnapshotCommand,
+        subscribe::{SubscribeCommand, UnsubscribeCommand},
+        touch::TouchCommand,
+        ttl::TtlCommand,
+        wait::WaitCommand,
+    },
+    protocol::parser::{
+        COMMAND_NAMES, CommandRenames, MAX_CORRELATION_ID_LEN, ProtocolError, ProtocolParser,
+        ResponseOptions,
     },
-    protocol::parser::ProtocolParser,
 };
 
 #[test]
@@ -34,6 +79,91 @@ fn test_parse_set_command_base64() {
     )
 }
 
+#[test]
+fn test_parse_setex_command() {
+    let cmd = ProtocolParser::parse_command("SETEX mykey 30 hello world").unwrap();
+    assert_eq!(
+        cmd,
+        Command::Set(SetCommand::new("mykey".to_string(), b"hello world".to_vec()).with_ttl(30))
+    );
+
+    assert!(ProtocolParser::parse_command("SETEX mykey soon hello").is_err());
+    assert!(ProtocolParser::parse_command("SETEX mykey 30").is_err());
+}
+
+#[test]
+fn test_parse_set_rejects_unsupported_options() {
+    for option in [
+        "EX", "PX", "EXAT", "PXAT", "NX", "XX", "KEEPTTL", "GET", "IDLE",
+    ] {
+        for line in [
+            format!("SET mykey hello {}", option),
+            format!("SET mykey hello {} 10", option.to_lowercase()),
+            format!("SETEX mykey 30 hello world {}", option),
+        ] {
+            match ProtocolParser::parse_command(&line) {
+                Err(ProtocolError::UnsupportedOption(name)) => assert_eq!(name, option),
+                other => panic!("Expected {} to be rejected, got {:?}", line, other),
+            }
+        }
+    }
+
+    let e = ProtocolParser::parse_command("SET mykey v KEEPTTL").unwrap_err();
+    assert_eq!(e.to_string(), "syntax error, unsupported option KEEPTTL");
+
+    // The first word is always the value, and quoted words are never options
+    assert_eq!(
+        ProtocolParser::parse_command("SET mykey GET").unwrap(),
+        Command::Set(SetCommand::new("mykey".to_string(), b"GET".to_vec()))
+    );
+    assert_eq!(
+        ProtocolParser::parse_command(r#"SET mykey ready "GET" set"#).unwrap(),
+        Command::Set(SetCommand::new(
+            "mykey".to_string(),
+            b"ready GET set".to_vec()
+        ))
+    );
+}
+
+#[test]
+fn test_parse_range_commands() {
+    assert_eq!(
+        ProtocolParser::parse_command("GETRANGE mykey 0 -1").unwrap(),
+        Command::GetRange(GetRangeCommand::new("mykey".to_string(), 0, -1))
+    );
+    assert_eq!(
+        ProtocolParser::parse_command("SETRANGE mykey 6 hello").unwrap(),
+        Command::SetRange(SetRangeCommand::new(
+            "mykey".to_string(),
+            6,
+            b"hello".to_vec()
+        ))
+    );
+
+    assert!(ProtocolParser::parse_command("GETRANGE mykey 0").is_err());
+    assert!(ProtocolParser::parse_command("SETRANGE mykey -1 x").is_err());
+}
+
+#[test]
+fn test_parse_bit_commands() {
+    assert_eq!(
+        ProtocolParser::parse_command("SETBIT mykey 7 1").unwrap(),
+        Command::SetBit(SetBitCommand::new("mykey".to_string(), 7, true))
+    );
+    assert_eq!(
+        ProtocolParser::parse_command("GETBIT mykey 7").unwrap(),
+        Command::GetBit(GetBitCommand::new("mykey".to_string(), 7))
+    );
+    assert_eq!(
+        ProtocolParser::parse_command("BITCOUNT mykey").unwrap(),
+        Command::BitCount(BitCountCommand::new("mykey".to_string()))
+    );
+
+    assert!(ProtocolParser::parse_command("SETBIT mykey 7 2").is_err());
+    assert!(ProtocolParser::parse_command("SETBIT mykey -1 1").is_err());
+    assert!(ProtocolParser::parse_command("GETBIT mykey").is_err());
+}
+
 #[test]
 fn test_parse_delete_command() {
     let cmd = ProtocolParser::parse_command("DELETE mykey").unwrap();
@@ -48,12 +178,82 @@ fn test_parse_delete_command() {
         cmd,
         Command::Delete(DeleteCommand::new("mykey".to_string()))
     );
+
+    let cmd = ProtocolParser::parse_command("DEL a b").unwrap();
+    assert_eq!(
+        cmd,
+        Command::Delete(DeleteCommand::many(vec!["a".to_string(), "b".to_string()]))
+    );
+}
+
+#[test]
+fn test_split_correlation_id() {
+    assert_eq!(
+        ProtocolParser::split_correlation_id("ID req-42 GET key").unwrap(),
+        (Some("req-42"), "GET key")
+    );
+    assert_eq!(
+        ProtocolParser::split_correlation_id("id abc {\"Get\":{\"key\":\"k\"}}").unwrap(),
+        (Some("abc"), "{\"Get\":{\"key\":\"k\"}}")
+    );
+    assert_eq!(
+        ProtocolParser::split_correlation_id("GET ID").unwrap(),
+        (None, "GET ID")
+    );
+    assert_eq!(
+        ProtocolParser::split_correlation_id("IDLE x").unwrap(),
+        (None, "IDLE x")
+    );
+
+    for line in ["ID", "ID req-42", "ID req-42   "] {
+        assert!(matches!(
+            ProtocolParser::split_correlation_id(line),
+            Err(ProtocolError::MissingArguments(_))
+        ));
+    }
+    let long = format!("ID {} PING", "x".repeat(MAX_CORRELATION_ID_LEN + 1));
+    for line in [long.as_str(), "ID \"quoted\" PING", "ID caf\u{e9} PING"] {
+        assert!(matches!(
+            ProtocolParser::split_correlation_id(line),
+            Err(ProtocolError::InvalidFormat(_))
+        ));
+    }
+}
+
+#[test]
+fn test_parse_delprefix_command() {
+    assert_eq!(
+        ProtocolParser::parse_command("DELPREFIX user:").unwrap(),
+        Command::DelPrefix(DelPrefixCommand::new("user:".to_string()))
+    );
+    assert_eq!(
+        ProtocolParser::parse_command("DELPREFIX --all").unwrap(),
+        Command::DelPrefix(DelPrefixCommand::all())
+    );
+    assert_eq!(
+        ProtocolParser::parse_command(r#"DELPREFIX "--all""#).unwrap(),
+        Command::DelPrefix(DelPrefixCommand::new("--all".to_string()))
+    );
+
+    assert!(ProtocolParser::parse_command("DELPREFIX").is_err());
+    assert!(ProtocolParser::parse_command("DELPREFIX a b").is_err());
 }
 
 #[test]
 fn test_parse_exist_command() {
     let cmd = ProtocolParser::parse_command("EXIST mykey").unwrap();
     assert_eq!(cmd, Command::Exist(ExistCommand::new("mykey".to_string())));
+
+    let cmd = ProtocolParser::parse_command("EXISTS a b a").unwrap();
+    assert_eq!(
+        cmd,
+        Command::Exist(ExistCommand::many(vec![
+            "a".to_string(),
+            "b".to_string(),
+            "a".to_string()
+        ]))
+    );
+    assert!(ProtocolParser::parse_command("EXISTS").is_err());
 }
 
 #[test]
@@ -63,7 +263,745 @@ fn test_parse_scan_command() {
 
     // Test scan all
     let cmd = ProtocolParser::parse_command("SCAN").unwrap();
-    assert_eq!(cmd, Command::Scan(ScanCommand::new(String::new())))
+    assert_eq!(cmd, Command::Scan(ScanCommand::new(String::new())));
+
+    let cmd = ProtocolParser::parse_command("SCAN user: sorted COUNT 10").unwrap();
+    assert_eq!(
+        cmd,
+        Command::Scan(
+            ScanCommand::new("user:".to_string())
+                .sorted()
+                .with_count(10)
+        )
+    );
+    let cmd = ProtocolParser::parse_command(r#"SCAN "" SORTED"#).unwrap();
+    assert_eq!(cmd, Command::Scan(ScanCommand::new(String::new()).sorted()));
+    assert_eq!(
+        ProtocolParser::serialize_command(&cmd).unwrap(),
+        "SCAN \"\" SORTED\n"
+    );
+
+    for line in [
+        "SCAN user: COUNT",
+        "SCAN user: COUNT -1",
+        "SCAN user: REVERSED",
+    ] {
+        assert!(ProtocolParser::parse_command(line).is_err(), "{}", line);
+    }
+}
+
+#[test]
+fn test_parse_object_command() {
+    let cmd = ProtocolParser::parse_command("OBJECT idletime mykey").unwrap();
+    assert_eq!(
+        cmd,
+        Command::Object(ObjectCommand::new(
+            ObjectSubcommand::IdleTime,
+            "mykey".to_string()
+        ))
+    );
+
+    assert_eq!(
+        ProtocolParser::parse_command("object FREQ mykey").unwrap(),
+        Command::Object(ObjectCommand::new(
+            ObjectSubcommand::Freq,
+            "mykey".to_string()
+        ))
+    );
+
+    assert_eq!(
+        ProtocolParser::parse_command("OBJECT ENCODING mykey").unwrap(),
+        Command::Object(ObjectCommand::new(
+            ObjectSubcommand::Encoding,
+            "mykey".to_string()
+        ))
+    );
+
+    assert!(ProtocolParser::parse_command("OBJECT REFCOUNT mykey").is_err());
+    assert!(ProtocolParser::parse_command("OBJECT IDLETIME").is_err());
+}
+
+#[test]
+fn test_parse_memory_command() {
+    assert_eq!(
+        ProtocolParser::parse_command("MEMORY usage mykey").unwrap(),
+        Command::Memory(MemoryCommand::Usage {
+            key: "mykey".to_string()
+        })
+    );
+    assert_eq!(
+        ProtocolParser::parse_command("memory STATS").unwrap(),
+        Command::Memory(MemoryCommand::Stats)
+    );
+
+    assert!(ProtocolParser::parse_command("MEMORY").is_err());
+    assert!(ProtocolParser::parse_command("MEMORY USAGE").is_err());
+    assert!(ProtocolParser::parse_command("MEMORY USAGE a b").is_err());
+    assert!(ProtocolParser::parse_command("MEMORY DOCTOR").is_err());
+}
+
+#[test]
+fn test_parse_touch_command() {
+    let cmd = ProtocolParser::parse_command("TOUCH a b").unwrap();
+    assert_eq!(
+        cmd,
+        Command::Touch(TouchCommand::new(vec!["a".to_string(), "b".to_string()]))
+    );
+    assert!(ProtocolParser::parse_command("TOUCH").is_err());
+}
+
+#[test]
+fn test_parse_echo() {
+    assert_eq!(
+        ProtocolParser::parse_command("ECHO aGk=").unwrap(),
+        Command::Echo(EchoCommand::new(b"hi".to_vec()))
+    );
+    assert_eq!(
+        ProtocolParser::parse_command(r#"echo "are you there""#).unwrap(),
+        Command::Echo(EchoCommand::new(b"are you there".to_vec()))
+    );
+    assert!(ProtocolParser::parse_command("ECHO").is_err());
+}
+
+#[test]
+fn test_parse_ping() {
+    assert_eq!(
+        ProtocolParser::parse_command("PING").unwrap(),
+        Command::Ping
+    );
+    assert_eq!(
+        ProtocolParser::parse_command(r#"ping "health check""#).unwrap(),
+        Command::PingMessage(PingCommand::new(Some(b"health check".to_vec())))
+    );
+    assert_eq!(
+        ProtocolParser::parse_command("PING aGk=").unwrap(),
+        Command::PingMessage(PingCommand::new(Some(b"hi".to_vec())))
+    );
+    // Words that aren't base64 are taken as they are, like a Redis client would send them
+    assert_eq!(
+        ProtocolParser::parse_command("PING hello").unwrap(),
+        Command::PingMessage(PingCommand::new(Some(b"hello".to_vec())))
+    );
+}
+
+#[test]
+fn test_parse_setchunk() {
+    assert_eq!(
+        ProtocolParser::parse_command("SETCHUNK upload 10485760").unwrap(),
+        Command::SetChunk(SetChunkCommand::new("upload".to_string(), 10_485_760))
+    );
+
+    for line in [
+        "SETCHUNK upload",
+        "SETCHUNK upload -1",
+        "SETCHUNK upload 10 extra",
+    ] {
+        assert!(ProtocolParser::parse_command(line).is_err(), "{}", line);
+    }
+}
+
+#[test]
+fn test_parse_expire_and_ttl() {
+    assert_eq!(
+        ProtocolParser::parse_command("PEXPIRE session 1500").unwrap(),
+        Command::Expire(ExpireCommand::new("session".to_string(), 1500, true, false))
+    );
+    assert_eq!(
+        ProtocolParser::parse_command("expireat session 1700000000").unwrap(),
+        Command::Expire(ExpireCommand::new(
+            "session".to_string(),
+            1_700_000_000,
+            false,
+            true
+        ))
+    );
+    assert_eq!(
+        ProtocolParser::parse_command("PTTL session").unwrap(),
+        Command::Ttl(TtlCommand::new("session".to_string(), true))
+    );
+    assert_eq!(
+        ProtocolParser::parse_command("TTL session").unwrap(),
+        Command::Ttl(TtlCommand::new("session".to_string(), false))
+    );
+
+    for line in [
+        "EXPIRE session",
+        "PEXPIRE session -1",
+        "PEXPIREAT session 1.5",
+        "TTL",
+    ] {
+        assert!(ProtocolParser::parse_command(line).is_err(), "{}", line);
+    }
+}
+
+#[test]
+fn test_parse_compress_command() {
+    assert_eq!(
+        ProtocolParser::parse_command("COMPRESS ON gzip 2048").unwrap(),
+        Command::Compress(CompressCommand::on(CompressionAlgorithm::Gzip, 2048))
+    );
+    assert_eq!(
+        ProtocolParser::parse_command("compress off").unwrap(),
+        Command::Compress(CompressCommand::off())
+    );
+
+    assert!(ProtocolParser::parse_command("COMPRESS ON brotli").is_err());
+    assert!(ProtocolParser::parse_command("COMPRESS").is_err());
+}
+
+#[test]
+fn test_serialize_compressed_value() {
+    let options = ResponseOptions {
+        compression: Some((CompressionAlgorithm::Gzip, 8)),
+        ..Default::default()
+    };
+
+    // Below threshold stays a plain VALUE
+    let small = CommandResponse::Value(b"hi".to_vec());
+    let serialized = ProtocolParser::serialize_response_with(&small, &options).unwrap();
+    assert_eq!(serialized, "VALUE aGk=\n");
+
+    let payload = vec![b'a'; 4096];
+    let large = CommandResponse::Value(payload.clone());
+    let serialized = ProtocolParser::serialize_response_with(&large, &options).unwrap();
+
+    let encoded = serialized
+        .trim_end()
+        .strip_prefix("VALUEZ gzip ")
+        .expect("expected compressed frame");
+    let compressed =
+        base64::Engine::decode(&base64::engine::general_purpose::STANDARD, encoded).unwrap();
+
+    let mut decoded = Vec::new();
+    flate2::read::GzDecoder::new(&compressed[..])
+        .read_to_end(&mut decoded)
+        .unwrap();
+    assert_eq!(decoded, payload);
+}
+
+#[test]
+fn test_parse_wait_command() {
+    assert_eq!(
+        ProtocolParser::parse_command("WAIT 0 500").unwrap(),
+        Command::Wait(WaitCommand::new(0, 500))
+    );
+
+    assert!(ProtocolParser::parse_command("WAIT 0").is_err());
+    assert!(ProtocolParser::parse_command("WAIT x 500").is_err());
+}
+
+#[test]
+fn test_parse_set_type_commands() {
+    assert_eq!(
+        ProtocolParser::parse_command("SADD tags red green").unwrap(),
+        Command::SAdd(SAddCommand::new(
+            "tags".to_string(),
+            vec![b"red".to_vec(), b"green".to_vec()]
+        ))
+    );
+    assert_eq!(
+        ProtocolParser::parse_command("sismember tags red").unwrap(),
+        Command::SIsMember(SIsMemberCommand::new("tags".to_string(), b"red".to_vec()))
+    );
+    assert!(ProtocolParser::parse_command("SADD tags").is_err());
+    assert!(ProtocolParser::parse_command("SCARD").is_err());
+
+    let response = CommandResponse::Members(vec![b"a".to_vec(), b"b".to_vec()]);
+    assert_eq!(
+        ProtocolParser::serialize_response(&response).unwrap(),
+        "MEMBERS 2\nYQ==\nYg==\n"
+    );
+}
+
+#[test]
+fn test_parse_proto_command() {
+    assert_eq!(
+        ProtocolParser::parse_command("PROTO json").unwrap(),
+        Command::Proto(ProtoCommand::new(ProtocolMode::Json))
+    );
+    assert!(ProtocolParser::parse_command("PROTO xml").is_err());
+}
+
+#[test]
+fn test_parse_encoding_command() {
+    assert_eq!(
+        ProtocolParser::parse_command("ENCODING raw").unwrap(),
+        Command::Encoding(EncodingCommand::new(ValueEncoding::Raw))
+    );
+    assert_eq!(
+        ProtocolParser::parse_command("ENCODING BASE64").unwrap(),
+        Command::Encoding(EncodingCommand::new(ValueEncoding::Base64))
+    );
+    assert!(ProtocolParser::parse_command("ENCODING hex").is_err());
+    assert!(ProtocolParser::parse_command("ENCODING").is_err());
+}
+
+#[test]
+fn test_serialize_raw_values() {
+    let options = ResponseOptions {
+        encoding: ValueEncoding::Raw,
+        ..Default::default()
+    };
+
+    // Binary payloads go out verbatim after a length header
+    let serialized = ProtocolParser::serialize_response_bytes(
+        &CommandResponse::Value(b"a\r\nb\0".to_vec()),
+        &options,
+    )
+    .unwrap();
+    assert_eq!(serialized, b"VALUE 5\r\na\r\nb\0");
+
+    // Other replies are unaffected
+    let serialized =
+        ProtocolParser::serialize_response_bytes(&CommandResponse::Ok, &options).unwrap();
+    assert_eq!(serialized, b"OK\n");
+
+    // Compressed values keep their base64 VALUEZ framing
+    let options = ResponseOptions {
+        compression: Some((CompressionAlgorithm::Gzip, 4)),
+        ..options
+    };
+    let serialized = ProtocolParser::serialize_response_bytes(
+        &CommandResponse::Value(b"compress me".to_vec()),
+        &options,
+    )
+    .unwrap();
+    assert!(serialized.starts_with(b"VALUEZ gzip "));
+
+    // JSON mode always uses base64
+    let options = ResponseOptions {
+        mode: ProtocolMode::Json,
+        encoding: ValueEncoding::Raw,
+        ..Default::default()
+    };
+    let serialized = ProtocolParser::serialize_response_bytes(
+        &CommandResponse::Value(b"hello".to_vec()),
+        &options,
+    )
+    .unwrap();
+    assert_eq!(serialized, b"{\"status\":\"ok\",\"value\":\"aGVsbG8=\"}\n");
+}
+
+#[test]
+fn test_parse_snapshot_command() {
+    assert_eq!(
+        ProtocolParser::parse_command("SNAPSHOT verify").unwrap(),
+        Command::Snapshot(SnapshotCommand::verify(None))
+    );
+    assert_eq!(
+        ProtocolParser::parse_command("SNAPSHOT VERIFY snapshot-20250101-000000.rdb").unwrap(),
+        Command::Snapshot(SnapshotCommand::verify(Some(
+            "snapshot-20250101-000000.rdb".to_string()
+        )))
+    );
+    assert!(ProtocolParser::parse_command("SNAPSHOT").is_err());
+    assert!(ProtocolParser::parse_command("SNAPSHOT RESTORE").is_err());
+}
+
+#[test]
+fn test_parse_relocate_command() {
+    assert_eq!(
+        ProtocolParser::parse_command("RELOCATE AOF /mnt/new/app.aof").unwrap(),
+        Command::Relocate(RelocateCommand {
+            aof_path: Some("/mnt/new/app.aof".to_string()),
+            snapshot_dir: None,
+            copy: false,
+        })
+    );
+    assert_eq!(
+        ProtocolParser::parse_command("relocate snapshots \"/mnt/new disk\" copy aof x.aof")
+            .unwrap(),
+        Command::Relocate(RelocateCommand {
+            aof_path: Some("x.aof".to_string()),
+            snapshot_dir: Some("/mnt/new disk".to_string()),
+            copy: true,
+        })
+    );
+
+    for line in [
+        "RELOCATE",
+        "RELOCATE COPY",
+        "RELOCATE AOF",
+        "RELOCATE AOF a.aof MOVE",
+    ] {
+        assert!(ProtocolParser::parse_command(line).is_err(), "{}", line);
+    }
+}
+
+#[test]
+fn test_parse_versioned_commands() {
+    assert_eq!(
+        ProtocolParser::parse_command("GETVER doc").unwrap(),
+        Command::GetVer(GetVerCommand::new("doc".to_string()))
+    );
+    assert_eq!(
+        ProtocolParser::parse_command("SETVER doc aGVsbG8= 7").unwrap(),
+        Command::SetVer(SetVerCommand::new("doc".to_string(), b"hello".to_vec(), 7))
+    );
+
+    for line in [
+        "GETVER",
+        "SETVER doc aGVsbG8=",
+        "SETVER doc aGVsbG8= -1",
+        "SETVER doc aGVsbG8= x",
+        "SETVER doc aGVsbG8= aGVsbG8= 7",
+    ] {
+        assert!(ProtocolParser::parse_command(line).is_err(), "{}", line);
+    }
+}
+
+#[test]
+fn test_parse_incrbyfloat() {
+    assert_eq!(
+        ProtocolParser::parse_command("INCRBYFLOAT rate 0.1").unwrap(),
+        Command::IncrByFloat(IncrByFloatCommand::new("rate".to_string(), 0.1))
+    );
+    assert_eq!(
+        ProtocolParser::parse_command("incrbyfloat rate -5e3").unwrap(),
+        Command::IncrByFloat(IncrByFloatCommand::new("rate".to_string(), -5000.0))
+    );
+
+    for line in [
+        "INCRBYFLOAT rate",
+        "INCRBYFLOAT rate abc",
+        "INCRBYFLOAT rate inf",
+        "INCRBYFLOAT rate NaN",
+    ] {
+        assert!(ProtocolParser::parse_command(line).is_err(), "{}", line);
+    }
+}
+
+#[test]
+fn test_parse_eval() {
+    assert_eq!(
+        ProtocolParser::parse_command(r#"EVAL cas lock "free" "taken""#).unwrap(),
+        Command::Eval(EvalCommand::new(
+            "lock".to_string(),
+            AtomicOp::Cas {
+                expected: b"free".to_vec(),
+                new: b"taken".to_vec(),
+            }
+        ))
+    );
+    assert_eq!(
+        ProtocolParser::parse_command("eval ADDMAX peak 2.5").unwrap(),
+        Command::Eval(EvalCommand::new("peak".to_string(), AtomicOp::AddMax(2.5)))
+    );
+
+    for line in [
+        "EVAL",
+        "EVAL cas lock free",
+        "EVAL addmax peak",
+        "EVAL addmax peak inf",
+        "EVAL incr key 1",
+    ] {
+        assert!(ProtocolParser::parse_command(line).is_err(), "{}", line);
+    }
+}
+
+#[test]
+fn test_parse_list_commands() {
+    assert_eq!(
+        ProtocolParser::parse_command(r#"LPUSH jobs "a" "b""#).unwrap(),
+        Command::Push(PushCommand::new(
+            "jobs".to_string(),
+            vec![b"a".to_vec(), b"b".to_vec()],
+            true
+        ))
+    );
+    assert_eq!(
+        ProtocolParser::parse_command(r#"rpush jobs "c""#).unwrap(),
+        Command::Push(PushCommand::new(
+            "jobs".to_string(),
+            vec![b"c".to_vec()],
+            false
+        ))
+    );
+    assert_eq!(
+        ProtocolParser::parse_command("BLPOP jobs 1.5").unwrap(),
+        Command::BLPop(BLPopCommand::new(
+            "jobs".to_string(),
+            Duration::from_millis(1500)
+        ))
+    );
+    assert_eq!(
+        ProtocolParser::parse_command("blpop jobs 0").unwrap(),
+        Command::BLPop(BLPopCommand::new("jobs".to_string(), Duration::ZERO))
+    );
+
+    for line in [
+        "LPUSH jobs",
+        "BLPOP jobs",
+        "BLPOP jobs -1",
+        "BLPOP jobs inf",
+    ] {
+        assert!(ProtocolParser::parse_command(line).is_err(), "{}", line);
+    }
+
+    assert_eq!(
+        ProtocolParser::parse_response("NIL\n").unwrap(),
+        CommandResponse::Nil
+    );
+}
+
+#[test]
+fn test_parse_select_and_flushdb() {
+    assert_eq!(
+        ProtocolParser::parse_command("select 3").unwrap(),
+        Command::Select(SelectCommand::new(3))
+    );
+    assert_eq!(
+        ProtocolParser::parse_command("FLUSHDB").unwrap(),
+        Command::FlushDb
+    );
+    assert!(ProtocolParser::parse_command("SELECT").is_err());
+    assert!(ProtocolParser::parse_command("SELECT -1").is_err());
+}
+
+#[test]
+fn test_parse_auth() {
+    assert_eq!(
+        ProtocolParser::parse_command("auth hunter2").unwrap(),
+        Command::Auth(AuthCommand::new("hunter2".to_string()))
+    );
+    assert_eq!(
+        ProtocolParser::parse_command(r#"AUTH reader "two \"quoted\" words""#).unwrap(),
+        Command::Auth(AuthCommand::with_username(
+            "reader".to_string(),
+            r#"two "quoted" words"#.to_string()
+        ))
+    );
+    assert!(ProtocolParser::parse_command("AUTH").is_err());
+    assert!(ProtocolParser::parse_command("AUTH a b c").is_err());
+
+    let command = Command::Auth(AuthCommand::new(r#"a \ "b""#.to_string()));
+    let line = ProtocolParser::serialize_command(&command).unwrap();
+    assert_eq!(ProtocolParser::parse_command(&line).unwrap(), command);
+
+    // Parsed commands are logged, the password must not be
+    let command = Command::Auth(AuthCommand::new("hunter2".to_string()));
+    assert!(!format!("{:?}", command).contains("hunter2"));
+}
+
+#[test]
+fn test_parse_subscribe() {
+    assert_eq!(
+        ProtocolParser::parse_command("subscribe __keyevent@0__:set news").unwrap(),
+        Command::Subscribe(SubscribeCommand::new(vec![
+            "__keyevent@0__:set".to_string(),
+            "news".to_string()
+        ]))
+    );
+    assert!(ProtocolParser::parse_command("SUBSCRIBE").is_err());
+    assert_eq!(
+        ProtocolParser::parse_command("UNSUBSCRIBE").unwrap(),
+        Command::Unsubscribe(UnsubscribeCommand::new(vec![]))
+    );
+
+    let message = CommandResponse::Message {
+        channel: "__keyevent@0__:del".to_string(),
+        payload: b"key".to_vec(),
+    };
+    let reply = ProtocolParser::serialize_response(&message).unwrap();
+    assert_eq!(reply, "MESSAGE __keyevent@0__:del a2V5\n");
+    assert_eq!(ProtocolParser::parse_response(&reply).unwrap(), message);
+}
+
+#[test]
+fn test_parse_info() {
+    assert_eq!(
+        ProtocolParser::parse_command("info").unwrap(),
+        Command::Info(InfoCommand::new(None))
+    );
+    assert_eq!(
+        ProtocolParser::parse_command("INFO Keyspace").unwrap(),
+        Command::Info(InfoCommand::new(Some("keyspace".to_string())))
+    );
+}
+
+#[test]
+fn test_parse_value_too_large_is_refused_before_decoding() {
+    let limits = KeyLimits {
+        max_value_size: 6,
+        ..KeyLimits::default()
+    };
+
+    // 6 bytes encode to 8 base64 characters, the most a value may take on the wire
+    let fits = base64::Engine::encode(&base64::engine::general_purpose::STANDARD, b"123456");
+    assert_eq!(
+        ProtocolParser::parse_command_with(&format!("SET k {}", fits), &limits).unwrap(),
+        Command::Set(SetCommand::new("k".to_string(), b"123456".to_vec()))
+    );
+
+    let oversized = base64::Engine::encode(&base64::engine::general_purpose::STANDARD, b"1234567");
+    for line in [
+        format!("SET k {}", oversized),
+        format!("SETEX k 10 {}", oversized),
+        format!("RPUSH k a {}", oversized),
+        "SET k hello big world".to_string(),
+    ] {
+        assert!(matches!(
+            ProtocolParser::parse_command_with(&line, &limits),
+            Err(ProtocolError::ValueTooLarge(6))
+        ));
+    }
+
+    // A 100 MB upload is refused from its length alone under the default 10 MB limit
+    let upload = format!("SET k {}", "A".repeat(100 * 1024 * 1024));
+    assert!(matches!(
+        ProtocolParser::parse_command(&upload),
+        Err(ProtocolError::ValueTooLarge(_))
+    ));
+}
+
+#[test]
+fn test_parse_debug() {
+    assert_eq!(
+        ProtocolParser::parse_command("debug set-active-expire 0").unwrap(),
+        Command::Debug(DebugCommand::SetActiveExpire(false))
+    );
+    assert_eq!(
+        ProtocolParser::parse_command("DEBUG SET-ACTIVE-EXPIRE 1").unwrap(),
+        Command::Debug(DebugCommand::SetActiveExpire(true))
+    );
+    assert_eq!(
+        ProtocolParser::parse_command("debug reload").unwrap(),
+        Command::Debug(DebugCommand::Reload)
+    );
+    assert!(ProtocolParser::parse_command("DEBUG SET-ACTIVE-EXPIRE on").is_err());
+    assert!(ProtocolParser::parse_command("DEBUG SEGFAULT").is_err());
+    assert!(ProtocolParser::parse_command("DEBUG").is_err());
+}
+
+#[test]
+fn test_parse_hello() {
+    assert_eq!(
+        ProtocolParser::parse_command("hello").unwrap(),
+        Command::Hello(HelloCommand::new(None))
+    );
+    assert_eq!(
+        ProtocolParser::parse_command("HELLO 1").unwrap(),
+        Command::Hello(HelloCommand::new(Some(1)))
+    );
+    assert!(ProtocolParser::parse_command("HELLO three").is_err());
+
+    let response = CommandResponse::Map(vec![
+        ("server".to_string(), "blazekvdb".to_string()),
+        ("protocols".to_string(), "text,json".to_string()),
+    ]);
+    let reply = ProtocolParser::serialize_response(&response).unwrap();
+    assert_eq!(reply, "MAP 2\nserver blazekvdb\nprotocols text,json\n");
+    assert_eq!(ProtocolParser::response_body_lines("MAP 2\n").unwrap(), 2);
+    assert_eq!(ProtocolParser::parse_response(&reply).unwrap(), response);
+}
+
+#[test]
+fn test_parse_reset() {
+    assert_eq!(
+        ProtocolParser::parse_command("reset").unwrap(),
+        Command::Reset
+    );
+}
+
+#[test]
+fn test_parse_bgrewriteaof_command() {
+    assert_eq!(
+        ProtocolParser::parse_command("bgrewriteaof").unwrap(),
+        Command::BgRewriteAof
+    );
+}
+
+#[test]
+fn test_parse_readonly_command() {
+    assert_eq!(
+        ProtocolParser::parse_command("READONLY on").unwrap(),
+        Command::ReadOnly(ReadOnlyCommand::new(true))
+    );
+    assert_eq!(
+        ProtocolParser::parse_command("READONLY OFF").unwrap(),
+        Command::ReadOnly(ReadOnlyCommand::new(false))
+    );
+    assert!(ProtocolParser::parse_command("READONLY").is_err());
+}
+
+#[test]
+fn test_parse_config_command() {
+    assert_eq!(
+        ProtocolParser::parse_command("CONFIG GET MAX_MEMORY").unwrap(),
+        Command::Config(ConfigCommand::get("max_memory".to_string()))
+    );
+    assert_eq!(
+        ProtocolParser::parse_command("config set fsync_policy everyn:100").unwrap(),
+        Command::Config(ConfigCommand::set(
+            "fsync_policy".to_string(),
+            "everyn:100".to_string()
+        ))
+    );
+    assert!(ProtocolParser::parse_command("CONFIG SET readonly").is_err());
+    assert!(ProtocolParser::parse_command("CONFIG RESETSTAT").is_err());
+}
+
+#[test]
+fn test_parse_json_command() {
+    let cmd = ProtocolParser::parse_command_in(
+        r#"{"Get":{"key":"k"}}"#,
+        ProtocolMode::Json,
+        &KeyLimits::default(),
+        &CommandRenames::default(),
+    );
+    assert_eq!(cmd.unwrap(), Command::Get(GetCommand::new("k".to_string())));
+
+    let cmd = ProtocolParser::parse_command_in(
+        r#""Ping""#,
+        ProtocolMode::Json,
+        &KeyLimits::default(),
+        &CommandRenames::default(),
+    );
+    assert_eq!(cmd.unwrap(), Command::Ping);
+
+    // Clients cannot mark a SET as internal
+    let cmd = ProtocolParser::parse_json_command(
+        r#"{"Set":{"key":"__blaze:x","value":[1],"ttl":null,"internal":true}}"#,
+    )
+    .unwrap();
+    assert!(matches!(cmd, Command::Set(set) if !set.internal));
+
+    // Text mode never interprets JSON
+    assert!(
+        ProtocolParser::parse_command_in(
+            r#""Ping""#,
+            ProtocolMode::Text,
+            &KeyLimits::default(),
+            &CommandRenames::default()
+        )
+        .is_err()
+    );
+}
+
+#[test]
+fn test_serialize_json_responses() {
+    let options = ResponseOptions {
+        mode: ProtocolMode::Json,
+        ..Default::default()
+    };
+
+    let serialized = ProtocolParser::serialize_response_with(
+        &CommandResponse::Value(b"hello".to_vec()),
+        &options,
+    )
+    .unwrap();
+    assert_eq!(serialized, "{\"status\":\"ok\",\"value\":\"aGVsbG8=\"}\n");
+
+    let serialized =
+        ProtocolParser::serialize_response_with(&CommandResponse::Integer(3), &options).unwrap();
+    assert_eq!(serialized, "{\"status\":\"ok\",\"value\":3}\n");
+
+    let serialized = ProtocolParser::serialize_response_with(
+        &CommandResponse::Error("boom".to_string()),
+        &options,
+    )
+    .unwrap();
+    assert_eq!(serialized, "{\"error\":\"boom\",\"status\":\"error\"}\n");
 }
 
 #[test]
@@ -76,6 +1014,81 @@ fn test_parse_simple_commands() {
         ProtocolParser::parse_command("STATS").unwrap(),
         Command::Stats
     );
+    assert_eq!(
+        ProtocolParser::parse_command("metrics").unwrap(),
+        Command::Metrics
+    );
+}
+
+#[test]
+fn test_parse_quoted_arguments() {
+    // Quoted values keep their spaces and are never base64 decoded
+    assert_eq!(
+        ProtocolParser::parse_command(r#"SET greeting "hello world""#).unwrap(),
+        Command::Set(SetCommand::new(
+            "greeting".to_string(),
+            b"hello world".to_vec()
+        ))
+    );
+    assert_eq!(
+        ProtocolParser::parse_command(r#"SET key "aGVsbG8=""#).unwrap(),
+        Command::Set(SetCommand::new("key".to_string(), b"aGVsbG8=".to_vec()))
+    );
+
+    // Keys may be quoted too
+    assert_eq!(
+        ProtocolParser::parse_command(r#"GET "my key""#).unwrap(),
+        Command::Get(GetCommand::new("my key".to_string()))
+    );
+
+    // \" and \\ are escapes, other backslashes are kept
+    assert_eq!(
+        ProtocolParser::parse_command(r#"SET key "say \"hi\" \\ \n""#).unwrap(),
+        Command::Set(SetCommand::new(
+            "key".to_string(),
+            br#"say "hi" \ \n"#.to_vec()
+        ))
+    );
+
+    // Each quoted member is one argument
+    assert_eq!(
+        ProtocolParser::parse_command(r#"SADD set "a b" "" c"#).unwrap(),
+        Command::SAdd(SAddCommand::new(
+            "set".to_string(),
+            vec![b"a b".to_vec(), Vec::new(), b"c".to_vec()]
+        ))
+    );
+
+    // Quotes inside a bare word are ordinary characters
+    assert_eq!(
+        ProtocolParser::parse_command(r#"GET a"b"#).unwrap(),
+        Command::Get(GetCommand::new(r#"a"b"#.to_string()))
+    );
+
+    // Unquoted multi-word values still join with single spaces
+    assert_eq!(
+        ProtocolParser::parse_command("SET key hello   world").unwrap(),
+        Command::Set(SetCommand::new("key".to_string(), b"hello world".to_vec()))
+    );
+}
+
+#[test]
+fn test_parse_malformed_quotes() {
+    for line in [
+        r#"SET key "hello"#,
+        r#"SET key "hello\""#,
+        r#"GET ""#,
+        r#"SET key "a"b"#,
+    ] {
+        assert!(
+            matches!(
+                ProtocolParser::parse_command(line),
+                Err(ProtocolError::InvalidFormat(_))
+            ),
+            "{}",
+            line
+        );
+    }
 }
 
 #[test]
@@ -118,6 +1131,11 @@ fn test_serialize_responses() {
     let serialized = ProtocolParser::serialize_response(&response).unwrap();
     assert_eq!(serialized, "KEYS 2\nkey1\nkey2\n");
 
+    // Integer response
+    let response = CommandResponse::Integer(42);
+    let serialized = ProtocolParser::serialize_response(&response).unwrap();
+    assert_eq!(serialized, "INTEGER 42\n");
+
     // Error response
     let response = CommandResponse::Error("Something went wrong".to_string());
     let serialized = ProtocolParser::serialize_response(&response).unwrap();
@@ -145,3 +1163,54 @@ fn test_parse_multiple_commands() {
     );
     assert_eq!(commands[2].as_ref().unwrap(), &Command::Ping);
 }
+
+#[test]
+fn test_command_names_are_all_parsed() {
+    for name in COMMAND_NAMES {
+        let parsed = ProtocolParser::parse_command(name);
+        assert!(
+            !matches!(parsed, Err(ProtocolError::UnknownCommand(_))),
+            "{} is unknown",
+            name
+        );
+    }
+}
+
+#[test]
+fn test_parse_renamed_commands() {
+    let renames = CommandRenames::new(&HashMap::from([
+        ("flushdb".to_string(), "".to_string()),
+        ("CONFIG".to_string(), "cfg-9f2a".to_string()),
+    ]));
+    let parse = |line: &str, mode| {
+        ProtocolParser::parse_command_in(line, mode, &KeyLimits::default(), &renames)
+    };
+
+    // Disabled and renamed-away names are unknown, the new name runs the command
+    assert!(matches!(
+        parse("FLUSHDB", ProtocolMode::Text),
+        Err(ProtocolError::UnknownCommand(_))
+    ));
+    assert!(matches!(
+        parse("config get max_memory", ProtocolMode::Text),
+        Err(ProtocolError::UnknownCommand(_))
+    ));
+    assert!(matches!(
+        parse("CFG-9F2A GET max_memory", ProtocolMode::Text),
+        Ok(Command::Config(_))
+    ));
+    assert_eq!(
+        parse("GET k", ProtocolMode::Text).unwrap(),
+        Command::Get(GetCommand::new("k".to_string()))
+    );
+
+    // Nor can JSON mode reach them
+    assert!(matches!(
+        parse(r#""FlushDb""#, ProtocolMode::Json),
+        Err(ProtocolError::UnknownCommand(_))
+    ));
+    assert_eq!(
+        parse(r#""Ping""#, ProtocolMode::Json).unwrap(),
+        Command::Ping
+    );
+}
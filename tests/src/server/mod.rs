@@ -1 +1,4 @@
+pub mod test_audit;
 pub mod test_connection;
+pub mod test_correlation;
+pub mod test_pubsub;
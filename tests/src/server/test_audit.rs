@@ -0,0 +1,186 @@
+use std::sync::{Arc, Mutex};
+
+use blazekvdb::{
+    acl::Acl,
+    commands::CommandDispatcher,
+    config::{SecurityConfig, UserConfig},
+    server::{
+        audit::AUDIT_TARGET,
+        connection::{ConnectionHandler, ConnectionLimits},
+    },
+    storage::{StorageConfig, StorageEngine, engine::memory::MemoryEngine},
+};
+use tokio::{
+    io::{AsyncBufReadExt, AsyncWriteExt, BufReader},
+    net::{TcpListener, TcpStream},
+};
+use tracing::{
+    Event, Metadata, Subscriber,
+    field::{Field, Visit},
+    instrument::WithSubscriber,
+    span::{Attributes, Id, Record},
+};
+
+// An audit event's fields, as (name, value) pairs
+type AuditEvent = Vec<(String, String)>;
+
+// Keeps every event logged under the audit target
+#[derive(Clone, Default)]
+struct AuditCollector {
+    events: Arc<Mutex<Vec<AuditEvent>>>,
+}
+
+impl AuditCollector {
+    // (command, reason) of each event, and whether every one names the client
+    fn recorded(&self, client: &str) -> Vec<(String, String)> {
+        let events = self.events.lock().unwrap();
+        events
+            .iter()
+            .map(|event| {
+                let field = |name: &str| {
+                    event
+                        .iter()
+                        .find(|(field, _)| field == name)
+                        .map(|(_, value)| value.clone())
+                        .unwrap_or_default()
+                };
+                assert_eq!(field("client"), client);
+                (field("command"), field("reason"))
+            })
+            .collect()
+    }
+}
+
+struct FieldVisitor<'a>(&'a mut AuditEvent);
+
+impl Visit for FieldVisitor<'_> {
+    fn record_str(&mut self, field: &Field, value: &str) {
+        self.0.push((field.name().to_string(), value.to_string()));
+    }
+
+    fn record_debug(&mut self, field: &Field, value: &dyn std::fmt::Debug) {
+        self.0
+            .push((field.name().to_string(), format!("{:?}", value)));
+    }
+}
+
+impl Subscriber for AuditCollector {
+    fn enabled(&self, metadata: &Metadata<'_>) -> bool {
+        metadata.target() == AUDIT_TARGET
+    }
+
+    fn new_span(&self, _: &Attributes<'_>) -> Id {
+        Id::from_u64(1)
+    }
+
+    fn record(&self, _: &Id, _: &Record<'_>) {}
+
+    fn record_follows_from(&self, _: &Id, _: &Id) {}
+
+    fn event(&self, event: &Event<'_>) {
+        let mut fields = Vec::new();
+        event.record(&mut FieldVisitor(&mut fields));
+        self.events.lock().unwrap().push(fields);
+    }
+
+    fn enter(&self, _: &Id) {}
+
+    fn exit(&self, _: &Id) {}
+}
+
+// Send each line over one connection served with the given audit setting, returns the
+// replies' first lines and what was audited
+async fn run_session(audit_log: bool, lines: &[&str]) -> (Vec<String>, Vec<(String, String)>) {
+    let storage = Arc::new(MemoryEngine::new(StorageConfig::default())) as Arc<dyn StorageEngine>;
+    let security = SecurityConfig {
+        users: vec![UserConfig {
+            name: "reader".to_string(),
+            password: "secret".to_string(),
+            commands: vec!["@read".to_string()],
+        }],
+        ..Default::default()
+    };
+    let dispatcher =
+        Arc::new(CommandDispatcher::new(storage).with_acl(Acl::from_config(&security)));
+    let handler = ConnectionHandler::new(dispatcher)
+        .with_limits(ConnectionLimits {
+            max_commands_per_sec: lines.len() as u64 - 1,
+            ..Default::default()
+        })
+        .with_audit_log(audit_log);
+
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    let client = TcpStream::connect(addr).await.unwrap();
+    let client_addr = client.local_addr().unwrap().to_string();
+    let (stream, peer) = listener.accept().await.unwrap();
+
+    let collector = AuditCollector::default();
+    let serving = handler
+        .handle_connection(stream, peer)
+        .with_subscriber(collector.clone());
+
+    let talking = async move {
+        let (read_half, mut write_half) = client.into_split();
+        let mut reader = BufReader::new(read_half);
+        let mut replies = Vec::new();
+        for line in lines {
+            write_half
+                .write_all(format!("{}\n", line).as_bytes())
+                .await
+                .unwrap();
+            let mut reply = String::new();
+            reader.read_line(&mut reply).await.unwrap();
+            replies.push(reply.trim_end().to_string());
+        }
+        replies
+    };
+
+    let (_, replies) = tokio::join!(serving, talking);
+    let recorded = collector.recorded(&client_addr);
+    (replies, recorded)
+}
+
+#[tokio::test]
+async fn test_audit_log_records_rejections() {
+    let long_key = "k".repeat(1024);
+    let lines = [
+        "AUTH reader wrong".to_string(),
+        "auth nobody secret".to_string(),
+        "AUTH reader secret".to_string(),
+        "SET key \"value\"".to_string(),
+        format!("GET {}", long_key),
+        "FROBNICATE".to_string(),
+        "GET missing".to_string(),
+        "PING".to_string(),
+    ];
+    let lines: Vec<&str> = lines.iter().map(String::as_str).collect();
+
+    let (replies, recorded) = run_session(true, &lines).await;
+    assert!(replies[0].starts_with("ERROR WRONGPASS"));
+    assert_eq!(replies[2], "OK");
+    assert!(replies[3].starts_with("ERROR NOPERM"));
+    assert!(replies[7].starts_with("ERROR Command rate"));
+
+    // A missing key is no security event, and passwords never reach the log
+    assert_eq!(
+        recorded,
+        [
+            ("AUTH", "auth_failed"),
+            ("AUTH", "auth_failed"),
+            ("SET", "permission_denied"),
+            ("GET", "oversized"),
+            ("FROBNICATE", "invalid_command"),
+            ("PING", "rate_limited"),
+        ]
+        .map(|(command, reason)| (command.to_string(), reason.to_string()))
+    );
+    assert!(!format!("{:?}", recorded).contains("secret"));
+}
+
+#[tokio::test]
+async fn test_audit_log_is_off_by_default() {
+    let (replies, recorded) = run_session(false, &["AUTH reader wrong", "FROBNICATE"]).await;
+    assert!(replies.iter().all(|reply| reply.starts_with("ERROR")));
+    assert!(recorded.is_empty());
+}
@@ -1,12 +1,20 @@
 use std::{net::SocketAddr, sync::Arc};
 
 use blazekvdb::{
-    commands::CommandDispatcher,
-    server::{connection::ConnectionHandler, tcp::TcpServer},
+    acl::Acl,
+    commands::{
+        Command, CommandDispatcher, CommandHandler, CommandMiddleware, CommandResponse, KeyLimits,
+        info::InfoCommand,
+    },
+    config::{SecurityConfig, UserConfig},
+    server::{
+        connection::{ConnectionHandler, ConnectionLimits},
+        tcp::TcpServer,
+    },
     storage::{StorageConfig, StorageEngine, engine::memory::MemoryEngine},
 };
 use tokio::{
-    io::{AsyncReadExt, AsyncWriteExt},
+    io::{AsyncBufReadExt, AsyncReadExt, AsyncWriteExt},
     net::{TcpListener, TcpStream},
 };
 
@@ -68,3 +76,1029 @@ async fn test_connection_stats() {
     assert_eq!(stats.bytes_received, 0);
     assert_eq!(stats.bytes_sent, 0);
 }
+
+#[tokio::test]
+async fn test_connection_stats_track_last_command() {
+    let storage = Arc::new(MemoryEngine::new(StorageConfig::default())) as Arc<dyn StorageEngine>;
+    let dispatcher = Arc::new(CommandDispatcher::new(storage));
+
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    let mut client = TcpStream::connect(addr).await.unwrap();
+    let (stream, peer) = listener.accept().await.unwrap();
+
+    let handler = Arc::new(ConnectionHandler::new(dispatcher));
+    assert!(handler.stats().last_command_time.is_none());
+
+    let serving = handler.clone();
+    let connection = tokio::spawn(async move { serving.handle_connection(stream, peer).await });
+
+    client.write_all(b"PING\n").await.unwrap();
+    let mut buffer = [0; 64];
+    let n = client.read(&mut buffer).await.unwrap();
+    assert_eq!(&buffer[..n], b"PONG\n");
+
+    tokio::time::sleep(std::time::Duration::from_millis(100)).await;
+
+    // The timestamp stays at the command instead of following the clock
+    let stats = handler.stats();
+    let last_command = stats.last_command_time.unwrap();
+    assert!(last_command.elapsed() >= std::time::Duration::from_millis(100));
+    assert!(stats.idle_time >= std::time::Duration::from_millis(100));
+    assert_eq!(stats.commands_processed, 1);
+
+    client.write_all(b"PING\n").await.unwrap();
+    let n = client.read(&mut buffer).await.unwrap();
+    assert_eq!(&buffer[..n], b"PONG\n");
+    assert!(handler.stats().last_command_time.unwrap() > last_command);
+    assert!(handler.idle_time() < std::time::Duration::from_millis(100));
+
+    drop(client);
+    connection.await.unwrap();
+}
+
+#[tokio::test]
+async fn test_select_is_per_connection() {
+    let databases: Vec<Arc<dyn StorageEngine>> = (0..2)
+        .map(|_| Arc::new(MemoryEngine::new(StorageConfig::default())) as Arc<dyn StorageEngine>)
+        .collect();
+    let dispatcher =
+        Arc::new(CommandDispatcher::new(databases[0].clone()).with_databases(databases.clone()));
+    let server = TcpServer::new(dispatcher, "127.0.0.1:0".parse().unwrap());
+
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(async move {
+        server.accept_connections(listener).await.ok();
+    });
+
+    let mut buffer = [0; 128];
+    let mut selected = TcpStream::connect(addr).await.unwrap();
+    for line in ["SELECT 1\n", "SET key b25l\n"] {
+        selected.write_all(line.as_bytes()).await.unwrap();
+        let n = selected.read(&mut buffer).await.unwrap();
+        assert_eq!(&buffer[..n], b"OK\n");
+    }
+
+    selected.write_all(b"SELECT 2\n").await.unwrap();
+    let n = selected.read(&mut buffer).await.unwrap();
+    assert!(String::from_utf8_lossy(&buffer[..n]).contains("DB index is out of range"));
+
+    // A fresh connection starts on database 0
+    let mut other = TcpStream::connect(addr).await.unwrap();
+    other.write_all(b"SET key emVybw==\n").await.unwrap();
+    let n = other.read(&mut buffer).await.unwrap();
+    assert_eq!(&buffer[..n], b"OK\n");
+
+    assert_eq!(
+        databases[0].get("key").await.unwrap(),
+        Some(b"zero".to_vec())
+    );
+    assert_eq!(
+        databases[1].get("key").await.unwrap(),
+        Some(b"one".to_vec())
+    );
+}
+
+#[tokio::test]
+async fn test_auth_is_per_connection() {
+    let security = SecurityConfig {
+        require_auth: true,
+        users: vec![UserConfig {
+            name: "reader".to_string(),
+            password: "two words".to_string(),
+            commands: vec!["@read".to_string()],
+        }],
+        ..Default::default()
+    };
+    let storage = Arc::new(MemoryEngine::new(StorageConfig::default())) as Arc<dyn StorageEngine>;
+    let dispatcher =
+        Arc::new(CommandDispatcher::new(storage).with_acl(Acl::from_config(&security)));
+    let server = TcpServer::new(dispatcher, "127.0.0.1:0".parse().unwrap());
+
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(async move {
+        server.accept_connections(listener).await.ok();
+    });
+
+    let mut buffer = [0; 256];
+    let mut client = TcpStream::connect(addr).await.unwrap();
+    for (line, expected) in [
+        ("PING\n", "NOAUTH"),
+        ("AUTH reader wrong\n", "WRONGPASS"),
+        ("AUTH reader \"two words\"\n", "OK"),
+        ("PING\n", "PONG"),
+        ("SET key b25l\n", "NOPERM"),
+    ] {
+        client.write_all(line.as_bytes()).await.unwrap();
+        let n = client.read(&mut buffer).await.unwrap();
+        let reply = String::from_utf8_lossy(&buffer[..n]);
+        assert!(reply.contains(expected), "{} -> {}", line.trim(), reply);
+    }
+
+    // Another connection still has to authenticate
+    let mut other = TcpStream::connect(addr).await.unwrap();
+    other.write_all(b"PING\n").await.unwrap();
+    let n = other.read(&mut buffer).await.unwrap();
+    assert!(String::from_utf8_lossy(&buffer[..n]).contains("NOAUTH"));
+}
+
+#[tokio::test]
+async fn test_reset_restores_initial_state() {
+    let security = SecurityConfig {
+        require_auth: true,
+        auth_password: Some("secret".to_string()),
+        ..Default::default()
+    };
+    let databases: Vec<Arc<dyn StorageEngine>> = (0..2)
+        .map(|_| Arc::new(MemoryEngine::new(StorageConfig::default())) as Arc<dyn StorageEngine>)
+        .collect();
+    let dispatcher = Arc::new(
+        CommandDispatcher::new(databases[0].clone())
+            .with_databases(databases.clone())
+            .with_acl(Acl::from_config(&security)),
+    );
+    let server = TcpServer::new(dispatcher, "127.0.0.1:0".parse().unwrap());
+
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(async move {
+        server.accept_connections(listener).await.ok();
+    });
+
+    let mut buffer = [0; 256];
+    let mut client = TcpStream::connect(addr).await.unwrap();
+    for (line, expected) in [
+        ("AUTH secret\n", "OK"),
+        ("SELECT 1\n", "OK"),
+        ("SET key b25l\n", "OK"),
+        ("SUBSCRIBE news\n", "INTEGER 1"),
+        ("PROTO JSON\n", "ok"),
+        // Replies to RESET already use the initial text framing
+        ("RESET\n", "OK\n"),
+        ("PING\n", "NOAUTH"),
+        ("AUTH secret\n", "OK"),
+        ("GET key\n", "NIL"),
+        ("UNSUBSCRIBE\n", "INTEGER 0"),
+    ] {
+        client.write_all(line.as_bytes()).await.unwrap();
+        let n = client.read(&mut buffer).await.unwrap();
+        let reply = String::from_utf8_lossy(&buffer[..n]);
+        assert!(reply.contains(expected), "{} -> {}", line.trim(), reply);
+    }
+
+    assert_eq!(
+        databases[1].get("key").await.unwrap(),
+        Some(b"one".to_vec())
+    );
+}
+
+#[tokio::test]
+async fn test_connection_compression() {
+    let (server, _) = create_test_server().await;
+
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let actual_addr = listener.local_addr().unwrap();
+
+    tokio::spawn(async move {
+        server.accept_connections(listener).await.ok();
+    });
+
+    let mut stream = TcpStream::connect(actual_addr).await.unwrap();
+    let mut buffer = [0; 1024];
+
+    stream.write_all(b"COMPRESS ON gzip 4\n").await.unwrap();
+    let n = stream.read(&mut buffer).await.unwrap();
+    assert_eq!(String::from_utf8_lossy(&buffer[..n]), "OK\n");
+
+    stream.write_all(b"SET key1 hello world\n").await.unwrap();
+    stream.read_exact(&mut buffer[..3]).await.unwrap(); // OK
+
+    stream.write_all(b"GET key1\n").await.unwrap();
+    let n = stream.read(&mut buffer).await.unwrap();
+    assert!(String::from_utf8_lossy(&buffer[..n]).starts_with("VALUEZ gzip "));
+
+    stream.write_all(b"COMPRESS OFF\n").await.unwrap();
+    stream.read_exact(&mut buffer[..3]).await.unwrap(); // OK
+
+    stream.write_all(b"GET key1\n").await.unwrap();
+    let n = stream.read(&mut buffer).await.unwrap();
+    assert!(String::from_utf8_lossy(&buffer[..n]).starts_with("VALUE "));
+}
+
+#[tokio::test]
+async fn test_connection_raw_encoding() {
+    let (server, _) = create_test_server().await;
+
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let actual_addr = listener.local_addr().unwrap();
+
+    tokio::spawn(async move {
+        server.accept_connections(listener).await.ok();
+    });
+
+    let mut stream = TcpStream::connect(actual_addr).await.unwrap();
+    let mut buffer = [0; 1024];
+
+    // Newlines and NULs inside the value must survive raw framing
+    let value = b"line1\nline2\0\xff";
+    let encoded = base64::Engine::encode(&base64::engine::general_purpose::STANDARD, value);
+    stream
+        .write_all(format!("SET bin {}\nENCODING RAW\n", encoded).as_bytes())
+        .await
+        .unwrap();
+    stream.read_exact(&mut buffer[..6]).await.unwrap();
+    assert_eq!(&buffer[..6], b"OK\nOK\n");
+
+    stream.write_all(b"GET bin\nPING\n").await.unwrap();
+    let expected = [b"VALUE 13\r\n".as_slice(), value, b"PONG\n"].concat();
+    let mut received = vec![0; expected.len()];
+    stream.read_exact(&mut received).await.unwrap();
+    assert_eq!(received, expected);
+
+    // Back to base64
+    stream
+        .write_all(b"ENCODING BASE64\nGET bin\n")
+        .await
+        .unwrap();
+    let expected = format!("OK\nVALUE {}\n", encoded);
+    let mut received = vec![0; expected.len()];
+    stream.read_exact(&mut received).await.unwrap();
+    assert_eq!(String::from_utf8_lossy(&received), expected);
+}
+
+#[tokio::test]
+async fn test_connection_setchunk() {
+    let storage = Arc::new(MemoryEngine::new(StorageConfig::default())) as Arc<dyn StorageEngine>;
+    let dispatcher = Arc::new(
+        CommandDispatcher::new(storage.clone()).with_limits(KeyLimits {
+            max_value_size: 16,
+            ..KeyLimits::default()
+        }),
+    );
+    let server = TcpServer::new(dispatcher, "127.0.0.1:0".parse().unwrap());
+
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(async move {
+        server.accept_connections(listener).await.ok();
+    });
+
+    let mut stream = TcpStream::connect(addr).await.unwrap();
+    let mut buffer = [0; 3];
+
+    // Raw bytes, newlines included, split over several writes
+    stream.write_all(b"SETCHUNK big 12\nline1\n").await.unwrap();
+    stream.flush().await.unwrap();
+    tokio::time::sleep(std::time::Duration::from_millis(10)).await;
+    stream.write_all(b"\0line2").await.unwrap();
+    stream.read_exact(&mut buffer).await.unwrap();
+    assert_eq!(&buffer, b"OK\n");
+    assert_eq!(
+        storage.get("big").await.unwrap(),
+        Some(b"line1\n\0line2".to_vec())
+    );
+
+    // Refused from the header alone, and the payload doesn't leak into the next command
+    stream
+        .write_all(b"SETCHUNK huge 20\nPING PING PING PING\nPING\n")
+        .await
+        .unwrap();
+    let expected = "ERROR Invalid parameter: Value too large (max 16 bytes)\nPONG\n";
+    let mut received = vec![0; expected.len()];
+    stream.read_exact(&mut received).await.unwrap();
+    assert_eq!(String::from_utf8_lossy(&received), expected);
+    assert_eq!(storage.get("huge").await.unwrap(), None);
+}
+
+#[tokio::test]
+async fn test_connection_unsupported_set_option() {
+    let storage = Arc::new(MemoryEngine::new(StorageConfig::default())) as Arc<dyn StorageEngine>;
+    let dispatcher = Arc::new(CommandDispatcher::new(storage.clone()));
+    let server = TcpServer::new(dispatcher, "127.0.0.1:0".parse().unwrap());
+
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(async move {
+        server.accept_connections(listener).await.ok();
+    });
+
+    let mut stream = TcpStream::connect(addr).await.unwrap();
+    stream.write_all(b"SET k v KEEPTTL\nPING\n").await.unwrap();
+
+    let expected = "ERROR syntax error, unsupported option KEEPTTL\nPONG\n";
+    let mut received = vec![0; expected.len()];
+    stream.read_exact(&mut received).await.unwrap();
+    assert_eq!(String::from_utf8_lossy(&received), expected);
+    assert_eq!(storage.get("k").await.unwrap(), None);
+}
+
+#[tokio::test]
+async fn test_connection_json_mode() {
+    let (server, _) = create_test_server().await;
+
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let actual_addr = listener.local_addr().unwrap();
+
+    tokio::spawn(async move {
+        server.accept_connections(listener).await.ok();
+    });
+
+    let mut stream = TcpStream::connect(actual_addr).await.unwrap();
+    let mut buffer = [0; 1024];
+
+    // The switch is acknowledged in the new framing
+    stream.write_all(b"PROTO JSON\n").await.unwrap();
+    let n = stream.read(&mut buffer).await.unwrap();
+    assert_eq!(
+        String::from_utf8_lossy(&buffer[..n]),
+        "{\"status\":\"ok\"}\n"
+    );
+
+    stream
+        .write_all(b"{\"Set\":{\"key\":\"key1\",\"value\":[104,105],\"ttl\":null}}\n")
+        .await
+        .unwrap();
+    let n = stream.read(&mut buffer).await.unwrap();
+    assert_eq!(
+        String::from_utf8_lossy(&buffer[..n]),
+        "{\"status\":\"ok\"}\n"
+    );
+
+    stream
+        .write_all(b"{\"Get\":{\"key\":\"key1\"}}\n")
+        .await
+        .unwrap();
+    let n = stream.read(&mut buffer).await.unwrap();
+    assert_eq!(
+        String::from_utf8_lossy(&buffer[..n]),
+        "{\"status\":\"ok\",\"value\":\"aGk=\"}\n" // "hi" in base64
+    );
+
+    // Plain text still works in JSON mode so clients can switch back
+    stream.write_all(b"PROTO TEXT\n").await.unwrap();
+    let n = stream.read(&mut buffer).await.unwrap();
+    assert_eq!(String::from_utf8_lossy(&buffer[..n]), "OK\n");
+
+    stream.write_all(b"PING\n").await.unwrap();
+    let n = stream.read(&mut buffer).await.unwrap();
+    assert_eq!(String::from_utf8_lossy(&buffer[..n]), "PONG\n");
+}
+
+async fn start_server_with_limits(limits: ConnectionLimits) -> SocketAddr {
+    let (server, _) = create_test_server().await;
+    let server = server.with_connection_limits(limits);
+
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+
+    tokio::spawn(async move {
+        server.accept_connections(listener).await.ok();
+    });
+
+    addr
+}
+
+#[tokio::test]
+async fn test_connection_read_buffer_grows_past_initial_size() {
+    let addr = start_server_with_limits(ConnectionLimits {
+        read_buffer_size: 1,
+        ..Default::default()
+    })
+    .await;
+
+    let stream = TcpStream::connect(addr).await.unwrap();
+    let (read_half, mut write_half) = stream.into_split();
+    let mut reader = tokio::io::BufReader::new(read_half);
+
+    let value = "v".repeat(10_000);
+    write_half
+        .write_all(format!("SET long \"{}\"\nGET long\n", value).as_bytes())
+        .await
+        .unwrap();
+
+    let mut reply = String::new();
+    reader.read_line(&mut reply).await.unwrap();
+    assert_eq!(reply, "OK\n");
+
+    reply.clear();
+    reader.read_line(&mut reply).await.unwrap();
+    let encoded = base64::Engine::encode(&base64::engine::general_purpose::STANDARD, &value);
+    assert_eq!(reply, format!("VALUE {}\n", encoded));
+}
+
+#[tokio::test]
+async fn test_connection_client_buffer_limit() {
+    let addr = start_server_with_limits(ConnectionLimits {
+        max_client_buffer_bytes: 64 * 1024,
+        ..Default::default()
+    })
+    .await;
+
+    let mut small = TcpStream::connect(addr).await.unwrap();
+    let mut buffer = [0; 1024];
+    small.write_all(b"PING\n").await.unwrap();
+    let n = small.read(&mut buffer).await.unwrap();
+    assert_eq!(String::from_utf8_lossy(&buffer[..n]), "PONG\n");
+
+    // A single command larger than the whole cap is over any fair share
+    let mut large = TcpStream::connect(addr).await.unwrap();
+    let value = "v".repeat(100_000);
+    large
+        .write_all(format!("SET big \"{}\"\n", value).as_bytes())
+        .await
+        .unwrap();
+
+    let mut reply = Vec::new();
+    large.read_to_end(&mut reply).await.unwrap();
+    let reply = String::from_utf8_lossy(&reply);
+    assert!(reply.starts_with("ERROR Client buffers"), "{}", reply);
+
+    // The other client is untouched, and the command never ran
+    small.write_all(b"EXISTS big\n").await.unwrap();
+    let n = small.read(&mut buffer).await.unwrap();
+    assert_eq!(String::from_utf8_lossy(&buffer[..n]), "INTEGER 0\n");
+}
+
+#[tokio::test]
+async fn test_setchunk_payload_counts_toward_client_buffer_limit() {
+    let addr = start_server_with_limits(ConnectionLimits {
+        max_client_buffer_bytes: 16 * 1024,
+        ..Default::default()
+    })
+    .await;
+
+    let mut stream = TcpStream::connect(addr).await.unwrap();
+    stream.write_all(b"SETCHUNK big 40000\n").await.unwrap();
+    // The server may hang up before taking all of it
+    let _ = stream.write_all(&[b'v'; 40_000]).await;
+
+    // Refused once the payload read so far is over the cap; unread bytes may reset
+    let mut reply = Vec::new();
+    let read = stream.read_to_end(&mut reply).await;
+    let reply = String::from_utf8_lossy(&reply);
+    assert!(
+        read.is_err() || reply.starts_with("ERROR Client buffers"),
+        "{}",
+        reply
+    );
+
+    let mut other = TcpStream::connect(addr).await.unwrap();
+    let mut buffer = [0; 64];
+    other.write_all(b"EXISTS big\n").await.unwrap();
+    let n = other.read(&mut buffer).await.unwrap();
+    assert_eq!(String::from_utf8_lossy(&buffer[..n]), "INTEGER 0\n");
+}
+
+#[tokio::test]
+async fn test_stalled_setchunk_payload_times_out() {
+    let addr = start_server_with_limits(ConnectionLimits {
+        write_timeout: std::time::Duration::from_millis(200),
+        ..Default::default()
+    })
+    .await;
+
+    // Part of the payload, then nothing
+    let mut stream = TcpStream::connect(addr).await.unwrap();
+    stream.write_all(b"SETCHUNK big 12\nline1").await.unwrap();
+
+    let mut reply = Vec::new();
+    tokio::time::timeout(
+        std::time::Duration::from_secs(2),
+        stream.read_to_end(&mut reply),
+    )
+    .await
+    .unwrap()
+    .unwrap();
+    assert_eq!(
+        String::from_utf8_lossy(&reply),
+        "ERROR Timed out reading SETCHUNK payload\n"
+    );
+}
+
+#[tokio::test]
+async fn test_connection_command_rate_quota() {
+    let addr = start_server_with_limits(ConnectionLimits {
+        max_commands_per_sec: 2,
+        ..Default::default()
+    })
+    .await;
+
+    let mut stream = TcpStream::connect(addr).await.unwrap();
+    let mut buffer = [0; 1024];
+
+    for _ in 0..2 {
+        stream.write_all(b"PING\n").await.unwrap();
+        let n = stream.read(&mut buffer).await.unwrap();
+        assert_eq!(String::from_utf8_lossy(&buffer[..n]), "PONG\n");
+    }
+
+    // Budget exhausted, but the connection stays open
+    stream.write_all(b"PING\n").await.unwrap();
+    let n = stream.read(&mut buffer).await.unwrap();
+    assert!(String::from_utf8_lossy(&buffer[..n]).starts_with("ERROR Command rate"));
+
+    // A new window restores the budget
+    tokio::time::sleep(tokio::time::Duration::from_millis(1100)).await;
+    stream.write_all(b"PING\n").await.unwrap();
+    let n = stream.read(&mut buffer).await.unwrap();
+    assert_eq!(String::from_utf8_lossy(&buffer[..n]), "PONG\n");
+}
+
+#[tokio::test]
+async fn test_connection_quota_disconnect() {
+    let addr = start_server_with_limits(ConnectionLimits {
+        max_commands_per_sec: 1,
+        disconnect_on_quota: true,
+        ..Default::default()
+    })
+    .await;
+
+    let mut stream = TcpStream::connect(addr).await.unwrap();
+    let mut buffer = [0; 1024];
+
+    stream.write_all(b"PING\n").await.unwrap();
+    stream.read_exact(&mut buffer[..5]).await.unwrap(); // PONG
+
+    stream.write_all(b"PING\n").await.unwrap();
+    let n = stream.read(&mut buffer).await.unwrap();
+    assert!(String::from_utf8_lossy(&buffer[..n]).starts_with("ERROR"));
+
+    // Server closed the connection
+    assert_eq!(stream.read(&mut buffer).await.unwrap(), 0);
+}
+
+#[tokio::test]
+async fn test_connection_pipeline_depth() {
+    let addr = start_server_with_limits(ConnectionLimits {
+        max_pipeline_depth: 2,
+        ..Default::default()
+    })
+    .await;
+
+    let mut stream = TcpStream::connect(addr).await.unwrap();
+
+    // Send the whole pipeline in one write so it lands in the read buffer together
+    stream.write_all(&b"PING\n".repeat(8)).await.unwrap();
+
+    let mut replies = String::new();
+    let mut buffer = [0; 1024];
+    while replies.lines().count() < 8 {
+        let n = stream.read(&mut buffer).await.unwrap();
+        assert!(n > 0);
+        replies.push_str(&String::from_utf8_lossy(&buffer[..n]));
+    }
+
+    // Commands queued deeper than the limit are rejected, the tail still runs
+    assert!(
+        replies
+            .lines()
+            .any(|line| line.starts_with("ERROR Pipeline depth"))
+    );
+    assert_eq!(replies.lines().last(), Some("PONG"));
+}
+
+#[tokio::test]
+async fn test_concurrent_accept_loops() {
+    let config = StorageConfig::default();
+    let storage = Arc::new(MemoryEngine::new(config)) as Arc<dyn StorageEngine>;
+    let dispatcher = Arc::new(CommandDispatcher::new(storage));
+
+    let server = Arc::new(
+        TcpServer::new(dispatcher.clone(), "127.0.0.1:0".parse().unwrap())
+            .with_listen_backlog(64)
+            .with_accept_tasks(4),
+    );
+
+    let listener = server.bind().unwrap();
+    let actual_addr = listener.local_addr().unwrap();
+
+    let accepting = server.clone();
+    tokio::spawn(async move {
+        accepting.accept_connections(listener).await.ok();
+    });
+
+    // A burst of clients connecting at once
+    let clients = (0..32).map(|_| async move {
+        let mut stream = TcpStream::connect(actual_addr).await.unwrap();
+        stream.write_all(b"PING\n").await.unwrap();
+
+        let mut buffer = [0; 16];
+        let n = stream.read(&mut buffer).await.unwrap();
+        assert_eq!(&buffer[..n], b"PONG\n");
+    });
+    futures_util::future::join_all(clients).await;
+
+    let stats = server.stats();
+    assert_eq!(stats.total_connections, 32);
+    assert_eq!(stats.accept_errors, 0);
+    assert_eq!(stats.accept_resource_errors, 0);
+
+    // Reported by INFO and METRICS as well
+    let response = dispatcher
+        .execute(Command::Info(InfoCommand::new(Some("stats".to_string()))))
+        .await;
+    let CommandResponse::Value(info) = response else {
+        panic!("unexpected response: {:?}", response);
+    };
+    let info = String::from_utf8(info).unwrap();
+    assert!(info.contains("accept_errors:0\r\n"), "{}", info);
+    assert!(info.contains("accept_resource_errors:0\r\n"), "{}", info);
+
+    let response = dispatcher.execute(Command::Metrics).await;
+    let CommandResponse::Value(metrics) = response else {
+        panic!("unexpected response: {:?}", response);
+    };
+    let metrics: serde_json::Value = serde_json::from_slice(&metrics).unwrap();
+    assert_eq!(metrics["accept_errors"], 0);
+    assert_eq!(metrics["accept_resource_errors"], 0);
+}
+
+// Holds every command long enough for shutdown to arrive mid-execution
+struct SlowMiddleware;
+
+#[async_trait::async_trait]
+impl CommandMiddleware for SlowMiddleware {
+    async fn before_execute(&self, _command: &dyn CommandHandler) -> Result<(), CommandResponse> {
+        tokio::time::sleep(std::time::Duration::from_millis(200)).await;
+        Ok(())
+    }
+
+    async fn after_execute(&self, _command: &dyn CommandHandler, _response: &CommandResponse) {}
+}
+
+#[tokio::test]
+async fn test_shutdown_drains_in_flight_command() {
+    let config = StorageConfig::default();
+    let storage = Arc::new(MemoryEngine::new(config)) as Arc<dyn StorageEngine>;
+    let dispatcher =
+        Arc::new(CommandDispatcher::new(storage).with_middleware(Box::new(SlowMiddleware)));
+
+    let server = Arc::new(
+        TcpServer::new(dispatcher, "127.0.0.1:0".parse().unwrap())
+            .with_shutdown_timeout(std::time::Duration::from_secs(5)),
+    );
+    let listener = server.bind().unwrap();
+    let actual_addr = listener.local_addr().unwrap();
+
+    let accepting = server.clone();
+    let accept_loop = tokio::spawn(async move {
+        accepting.accept_connections(listener).await.ok();
+    });
+
+    let mut stream = TcpStream::connect(actual_addr).await.unwrap();
+    stream.write_all(b"PING\nPING\n").await.unwrap();
+
+    // Shutdown while the first PING is executing
+    tokio::time::sleep(std::time::Duration::from_millis(50)).await;
+    let draining = server.clone();
+    let drain = tokio::spawn(async move { draining.drain().await });
+
+    // The in-flight command still gets its reply, the pipelined one is dropped
+    let mut response = Vec::new();
+    stream.read_to_end(&mut response).await.unwrap();
+    assert_eq!(response, b"PONG\n");
+
+    drain.await.unwrap();
+    accept_loop.await.unwrap();
+    assert_eq!(server.stats().active_connections, 0);
+
+    // No longer accepting
+    assert!(TcpStream::connect(actual_addr).await.is_err());
+}
+
+#[tokio::test]
+async fn test_pipelined_replies_are_batched() {
+    let storage = Arc::new(MemoryEngine::new(StorageConfig::default())) as Arc<dyn StorageEngine>;
+    let dispatcher = Arc::new(CommandDispatcher::new(storage));
+    let handler = Arc::new(ConnectionHandler::new(dispatcher));
+
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+
+    let server_handler = handler.clone();
+    let server = tokio::spawn(async move {
+        let (stream, peer) = listener.accept().await.unwrap();
+        stream.set_nodelay(true).unwrap();
+        server_handler.handle_connection(stream, peer).await;
+    });
+
+    let mut stream = TcpStream::connect(addr).await.unwrap();
+    stream.write_all(&b"PING\n".repeat(100)).await.unwrap();
+
+    let expected = b"PONG\n".repeat(100);
+    let mut received = vec![0; expected.len()];
+    stream.read_exact(&mut received).await.unwrap();
+    assert_eq!(received, expected);
+
+    drop(stream);
+    server.await.unwrap();
+
+    let stats = handler.stats();
+    assert_eq!(stats.commands_processed, 100);
+    assert_eq!(stats.bytes_sent, expected.len() as u64);
+    // One write per chunk the client's pipeline arrived in, not one per reply
+    assert!(
+        stats.write_batches < 10,
+        "expected batched writes, got {}",
+        stats.write_batches
+    );
+}
+
+#[tokio::test]
+async fn test_replies_before_a_blocking_command_are_not_held_back() {
+    let (server, _) = create_test_server().await;
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(async move {
+        server.accept_connections(listener).await.ok();
+    });
+
+    let mut stream = TcpStream::connect(addr).await.unwrap();
+    stream
+        .write_all(b"PING\nBLPOP queue 5\nPING\n")
+        .await
+        .unwrap();
+
+    // The first PONG arrives while BLPOP is still waiting
+    let mut reply = [0; 5];
+    tokio::time::timeout(
+        std::time::Duration::from_secs(2),
+        stream.read_exact(&mut reply),
+    )
+    .await
+    .unwrap()
+    .unwrap();
+    assert_eq!(&reply, b"PONG\n");
+
+    // A push from another client wakes BLPOP, and the pipeline carries on
+    let mut other = TcpStream::connect(addr).await.unwrap();
+    other.write_all(b"LPUSH queue v\n").await.unwrap();
+
+    let mut rest = Vec::new();
+    while !rest.ends_with(b"PONG\n") {
+        let mut buffer = [0; 64];
+        let n = stream.read(&mut buffer).await.unwrap();
+        assert!(n > 0);
+        rest.extend_from_slice(&buffer[..n]);
+    }
+    assert!(
+        rest.starts_with(b"VALUE "),
+        "{}",
+        String::from_utf8_lossy(&rest)
+    );
+}
+
+// Server holding a value large enough that its reply can't fit in socket buffers
+async fn start_server_with_large_value(
+    limits: ConnectionLimits,
+) -> (Arc<TcpServer>, SocketAddr, tokio::task::JoinHandle<()>) {
+    let storage = Arc::new(MemoryEngine::new(StorageConfig::default())) as Arc<dyn StorageEngine>;
+    storage.set("big", vec![7; 16 * 1024 * 1024]).await.unwrap();
+    let dispatcher = Arc::new(CommandDispatcher::new(storage));
+
+    let server = Arc::new(
+        TcpServer::new(dispatcher, "127.0.0.1:0".parse().unwrap())
+            .with_connection_limits(limits)
+            .with_shutdown_timeout(std::time::Duration::from_secs(5)),
+    );
+    let listener = server.bind().unwrap();
+    let addr = listener.local_addr().unwrap();
+
+    let accepting = server.clone();
+    let accept_loop = tokio::spawn(async move {
+        accepting.accept_connections(listener).await.ok();
+    });
+
+    (server, addr, accept_loop)
+}
+
+async fn wait_for_no_active_connections(server: &TcpServer) {
+    for _ in 0..100 {
+        let stats = server.stats();
+        if stats.total_connections > 0 && stats.active_connections == 0 {
+            return;
+        }
+        tokio::time::sleep(std::time::Duration::from_millis(50)).await;
+    }
+    panic!(
+        "connection was not cleaned up: {:?}",
+        server.stats().active_connections
+    );
+}
+
+#[tokio::test]
+async fn test_client_closing_mid_response_is_cleaned_up() {
+    let (server, addr, accept_loop) =
+        start_server_with_large_value(ConnectionLimits::default()).await;
+
+    let mut stream = TcpStream::connect(addr).await.unwrap();
+    stream.write_all(b"GET big\n").await.unwrap();
+
+    // Read the start of the reply, then vanish while the server is still writing
+    let mut buffer = [0; 1024];
+    stream.read_exact(&mut buffer).await.unwrap();
+    assert!(buffer.starts_with(b"VALUE "));
+    drop(stream);
+
+    wait_for_no_active_connections(&server).await;
+    assert_eq!(server.stats().total_connections, 1);
+
+    // Nothing left for shutdown to wait on
+    tokio::time::timeout(std::time::Duration::from_secs(1), server.drain())
+        .await
+        .unwrap();
+    accept_loop.await.unwrap();
+    assert_eq!(server.stats().active_connections, 0);
+}
+
+#[tokio::test]
+async fn test_client_not_reading_times_out_write() {
+    let (server, addr, accept_loop) = start_server_with_large_value(ConnectionLimits {
+        write_timeout: std::time::Duration::from_millis(200),
+        ..Default::default()
+    })
+    .await;
+
+    // Half-open: the request goes out, but the client never reads the reply
+    let mut stream = TcpStream::connect(addr).await.unwrap();
+    stream.write_all(b"GET big\n").await.unwrap();
+
+    wait_for_no_active_connections(&server).await;
+    assert_eq!(server.stats().total_connections, 1);
+
+    // The server hung up: draining what was buffered ends in EOF or a reset
+    let mut sink = Vec::new();
+    let read = stream.read_to_end(&mut sink).await;
+    assert!(read.is_err() || sink.len() < 16 * 1024 * 1024 * 4 / 3);
+
+    tokio::time::timeout(std::time::Duration::from_secs(1), server.drain())
+        .await
+        .unwrap();
+    accept_loop.await.unwrap();
+}
+
+#[tokio::test]
+async fn test_idle_connections_are_reaped() {
+    let storage = Arc::new(MemoryEngine::new(StorageConfig::default())) as Arc<dyn StorageEngine>;
+    let dispatcher = Arc::new(CommandDispatcher::new(storage));
+
+    let server = Arc::new(
+        TcpServer::new(dispatcher.clone(), "127.0.0.1:0".parse().unwrap())
+            .with_idle_timeout(std::time::Duration::from_millis(300))
+            .with_idle_check_interval(std::time::Duration::from_millis(50)),
+    );
+    let listener = server.bind().unwrap();
+    let addr = listener.local_addr().unwrap();
+
+    let accepting = server.clone();
+    let accept_loop = tokio::spawn(async move {
+        accepting.accept_connections(listener).await.ok();
+    });
+
+    let mut idle = TcpStream::connect(addr).await.unwrap();
+    let mut busy = TcpStream::connect(addr).await.unwrap();
+
+    idle.write_all(b"PING\n").await.unwrap();
+    let mut buffer = [0; 64];
+    let n = idle.read(&mut buffer).await.unwrap();
+    assert_eq!(&buffer[..n], b"PONG\n");
+
+    // The busy client keeps sending commands past the idle timeout
+    for _ in 0..10 {
+        busy.write_all(b"PING\n").await.unwrap();
+        let n = busy.read(&mut buffer).await.unwrap();
+        assert_eq!(&buffer[..n], b"PONG\n");
+        tokio::time::sleep(std::time::Duration::from_millis(60)).await;
+    }
+
+    // The idle one has been hung up on by now
+    let n = tokio::time::timeout(std::time::Duration::from_secs(1), idle.read(&mut buffer))
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(n, 0);
+
+    // The slot is released just after the socket closes
+    for _ in 0..20 {
+        if server.stats().active_connections == 1 {
+            break;
+        }
+        tokio::time::sleep(std::time::Duration::from_millis(50)).await;
+    }
+    let stats = server.stats();
+    assert_eq!(stats.reaped_connections, 1);
+    assert_eq!(stats.active_connections, 1);
+
+    // Reported by INFO and METRICS as well
+    let response = dispatcher
+        .execute(Command::Info(InfoCommand::new(Some("stats".to_string()))))
+        .await;
+    let CommandResponse::Value(info) = response else {
+        panic!("unexpected response: {:?}", response);
+    };
+    let info = String::from_utf8(info).unwrap();
+    assert!(info.contains("reaped_connections:1\r\n"), "{}", info);
+
+    let response = dispatcher.execute(Command::Metrics).await;
+    let CommandResponse::Value(metrics) = response else {
+        panic!("unexpected response: {:?}", response);
+    };
+    let metrics: serde_json::Value = serde_json::from_slice(&metrics).unwrap();
+    assert_eq!(metrics["reaped_connections"], 1);
+
+    busy.write_all(b"PING\n").await.unwrap();
+    let n = busy.read(&mut buffer).await.unwrap();
+    assert_eq!(&buffer[..n], b"PONG\n");
+
+    drop(busy);
+    tokio::time::timeout(std::time::Duration::from_secs(1), server.drain())
+        .await
+        .unwrap();
+    accept_loop.await.unwrap();
+}
+
+#[tokio::test]
+async fn test_server_keepalive_probes_silent_connections() {
+    let addr = start_server_with_limits(ConnectionLimits {
+        keepalive_interval: Some(std::time::Duration::from_millis(100)),
+        keepalive_timeout: std::time::Duration::from_millis(200),
+        ..ConnectionLimits::default()
+    })
+    .await;
+
+    let mut stream = TcpStream::connect(addr).await.unwrap();
+    let mut buffer = [0; 64];
+
+    // Answered probes keep the connection open, and the answer itself gets no reply
+    for _ in 0..3 {
+        let n = tokio::time::timeout(std::time::Duration::from_secs(1), stream.read(&mut buffer))
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(&buffer[..n], b"KEEPALIVE\n");
+        stream.write_all(b"KEEPALIVE\n").await.unwrap();
+    }
+
+    stream.write_all(b"PING\n").await.unwrap();
+    let n = stream.read(&mut buffer).await.unwrap();
+    assert_eq!(&buffer[..n], b"PONG\n");
+
+    // An unanswered probe closes the connection
+    let n = stream.read(&mut buffer).await.unwrap();
+    assert_eq!(&buffer[..n], b"KEEPALIVE\n");
+    let n = tokio::time::timeout(std::time::Duration::from_secs(1), stream.read(&mut buffer))
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(n, 0);
+}
+
+#[tokio::test]
+async fn test_server_keeps_traffic_of_closed_connections() {
+    let storage = Arc::new(MemoryEngine::new(StorageConfig::default())) as Arc<dyn StorageEngine>;
+    let dispatcher = Arc::new(CommandDispatcher::new(storage));
+    let server = Arc::new(TcpServer::new(
+        dispatcher.clone(),
+        "127.0.0.1:0".parse().unwrap(),
+    ));
+    let listener = server.bind().unwrap();
+    let addr = listener.local_addr().unwrap();
+    let accepting = server.clone();
+    tokio::spawn(async move {
+        accepting.accept_connections(listener).await.ok();
+    });
+
+    let mut stream = tokio::io::BufReader::new(TcpStream::connect(addr).await.unwrap());
+    stream.get_mut().write_all(b"PING\nPING\n").await.unwrap();
+    let mut reply = String::new();
+    for _ in 0..2 {
+        stream.read_line(&mut reply).await.unwrap();
+    }
+
+    // Open connections count towards the server totals
+    let stats = server.stats();
+    assert_eq!(stats.total_commands_processed, 2);
+    assert_eq!(stats.total_bytes_received, 10);
+    assert_eq!(stats.total_bytes_sent, reply.len() as u64);
+
+    // And are still counted once closed
+    drop(stream);
+    wait_for_no_active_connections(&server).await;
+    let stats = server.stats();
+    assert_eq!(stats.total_commands_processed, 2);
+    assert_eq!(stats.total_bytes_received, 10);
+    assert_eq!(stats.total_bytes_sent, reply.len() as u64);
+
+    let response = dispatcher
+        .execute(Command::Info(InfoCommand::new(Some("stats".to_string()))))
+        .await;
+    let CommandResponse::Value(info) = response else {
+        panic!("unexpected response: {:?}", response);
+    };
+    let info = String::from_utf8(info).unwrap();
+    assert!(info.contains("total_connections_closed:1\r\n"), "{}", info);
+    assert!(info.contains("total_commands_processed:2\r\n"), "{}", info);
+    assert!(info.contains("total_net_input_bytes:10\r\n"), "{}", info);
+}
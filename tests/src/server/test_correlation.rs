@@ -0,0 +1,156 @@
+use std::sync::{Arc, Mutex};
+
+use blazekvdb::{
+    commands::CommandDispatcher,
+    server::connection::ConnectionHandler,
+    storage::{StorageConfig, StorageEngine, engine::memory::MemoryEngine},
+};
+use tokio::{
+    io::{AsyncBufReadExt, AsyncWriteExt, BufReader},
+    net::{TcpListener, TcpStream},
+};
+use tracing::{
+    Event, Metadata, Subscriber,
+    field::{Field, Visit},
+    instrument::WithSubscriber,
+    span::{Attributes, Id, Record},
+};
+
+// A span's name, its fields as (name, value) pairs and its parent's index
+struct SpanRecord {
+    name: &'static str,
+    fields: Vec<(String, String)>,
+    parent: Option<usize>,
+}
+
+// Keeps every span created, with the parent each was opened under
+#[derive(Clone, Default)]
+struct SpanCollector {
+    spans: Arc<Mutex<Vec<SpanRecord>>>, // Span ids are index + 1
+    entered: Arc<Mutex<Vec<usize>>>,
+}
+
+impl SpanCollector {
+    fn field(span: &SpanRecord, name: &str) -> Option<String> {
+        span.fields
+            .iter()
+            .find(|(field, _)| field == name)
+            .map(|(_, value)| value.clone())
+    }
+
+    // Correlation id of the request span enclosing the command span for key, if any
+    fn correlation_id_of(&self, key: &str) -> Option<String> {
+        let spans = self.spans.lock().unwrap();
+        let mut index = spans
+            .iter()
+            .position(|span| Self::field(span, "key").as_deref() == Some(key))
+            .unwrap_or_else(|| panic!("No span for key {}", key));
+
+        while let Some(parent) = spans[index].parent {
+            index = parent;
+            if spans[index].name == "request" {
+                return Self::field(&spans[index], "correlation_id");
+            }
+        }
+        None
+    }
+
+    fn count(&self, name: &str) -> usize {
+        let spans = self.spans.lock().unwrap();
+        spans.iter().filter(|span| span.name == name).count()
+    }
+}
+
+struct FieldVisitor<'a>(&'a mut Vec<(String, String)>);
+
+impl Visit for FieldVisitor<'_> {
+    fn record_debug(&mut self, field: &Field, value: &dyn std::fmt::Debug) {
+        self.0
+            .push((field.name().to_string(), format!("{:?}", value)));
+    }
+}
+
+impl Subscriber for SpanCollector {
+    fn enabled(&self, _: &Metadata<'_>) -> bool {
+        true
+    }
+
+    fn new_span(&self, attributes: &Attributes<'_>) -> Id {
+        let mut fields = Vec::new();
+        attributes.record(&mut FieldVisitor(&mut fields));
+
+        let parent = match attributes.parent() {
+            Some(parent) => Some(parent.into_u64() as usize - 1),
+            None if attributes.is_contextual() => self.entered.lock().unwrap().last().copied(),
+            None => None,
+        };
+
+        let mut spans = self.spans.lock().unwrap();
+        spans.push(SpanRecord {
+            name: attributes.metadata().name(),
+            fields,
+            parent,
+        });
+        Id::from_u64(spans.len() as u64)
+    }
+
+    fn record(&self, _: &Id, _: &Record<'_>) {}
+
+    fn record_follows_from(&self, _: &Id, _: &Id) {}
+
+    fn event(&self, _: &Event<'_>) {}
+
+    fn enter(&self, span: &Id) {
+        let index = span.into_u64() as usize - 1;
+        self.entered.lock().unwrap().push(index);
+    }
+
+    fn exit(&self, _: &Id) {
+        self.entered.lock().unwrap().pop();
+    }
+}
+
+#[tokio::test]
+async fn test_correlation_id_tags_request_spans() {
+    let storage = Arc::new(MemoryEngine::new(StorageConfig::default())) as Arc<dyn StorageEngine>;
+    let handler = ConnectionHandler::new(Arc::new(CommandDispatcher::new(storage)));
+
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let client = TcpStream::connect(listener.local_addr().unwrap())
+        .await
+        .unwrap();
+    let (stream, peer) = listener.accept().await.unwrap();
+
+    let collector = SpanCollector::default();
+    let serving = handler
+        .handle_connection(stream, peer)
+        .with_subscriber(collector.clone());
+
+    let talking = async move {
+        let (read_half, mut write_half) = client.into_split();
+        let mut reader = BufReader::new(read_half);
+        let mut replies = Vec::new();
+        for line in ["ID req-42 GET tagged", "GET untagged", "ID req-42"] {
+            write_half
+                .write_all(format!("{}\n", line).as_bytes())
+                .await
+                .unwrap();
+            let mut reply = String::new();
+            reader.read_line(&mut reply).await.unwrap();
+            replies.push(reply.trim_end().to_string());
+        }
+        replies
+    };
+
+    let (_, replies) = tokio::join!(serving, talking);
+    assert_eq!(replies[..2], ["NIL", "NIL"]);
+    assert!(replies[2].starts_with("ERROR Parse error"));
+
+    // Spans opened while running the command inherit the id, untagged requests get no span
+    assert_eq!(
+        collector.correlation_id_of("tagged").as_deref(),
+        Some("req-42")
+    );
+    assert_eq!(collector.correlation_id_of("untagged"), None);
+    assert_eq!(collector.count("request"), 1);
+}
@@ -0,0 +1,131 @@
+use std::time::Duration;
+
+use blazekvdb::{
+    bootstrap::BlazeKVDB,
+    config::BlazeServerConfig,
+    pubsub::{KeyspaceNotifier, PubSub},
+    server::tcp::TcpServer,
+    storage::{StorageConfig, StorageEngine, engine::memory::MemoryEngine},
+};
+use tokio::{
+    io::{AsyncBufReadExt, AsyncWriteExt, BufReader},
+    net::{TcpListener, TcpStream, tcp::OwnedReadHalf},
+};
+
+async fn read_line(reader: &mut BufReader<OwnedReadHalf>) -> String {
+    let mut line = String::new();
+    tokio::time::timeout(Duration::from_secs(5), reader.read_line(&mut line))
+        .await
+        .expect("timed out waiting for a reply")
+        .unwrap();
+    line
+}
+
+async fn start_server(notify: bool) -> (BlazeKVDB, std::net::SocketAddr) {
+    let mut config = BlazeServerConfig::default();
+    config.persistence.enabled = false;
+    config.storage.databases = 2;
+    config.server.notify_keyspace_events = notify;
+
+    let kvdb = BlazeKVDB::new(config).await.unwrap();
+    let server = TcpServer::new(kvdb.dispatcher(), "127.0.0.1:0".parse().unwrap());
+
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(async move {
+        server.accept_connections(listener).await.ok();
+    });
+
+    (kvdb, addr)
+}
+
+#[tokio::test]
+async fn test_engine_publishes_key_events() {
+    let pubsub = PubSub::new();
+    let engine = MemoryEngine::new(StorageConfig::default())
+        .with_notifier(KeyspaceNotifier::new(3, pubsub.clone()));
+    let mut messages = pubsub.subscribe();
+
+    engine.set("key", b"value".to_vec()).await.unwrap();
+    engine.delete("key").await.unwrap();
+    engine.delete("missing").await.unwrap();
+    engine
+        .set_with_ttl("temp", b"value".to_vec(), Duration::from_millis(10))
+        .await
+        .unwrap();
+    tokio::time::sleep(Duration::from_millis(30)).await;
+    assert_eq!(engine.get("temp").await.unwrap(), None);
+
+    let mut events = Vec::new();
+    while let Ok(message) = messages.try_recv() {
+        events.push((
+            message.channel.clone(),
+            String::from_utf8(message.payload.clone()).unwrap(),
+        ));
+    }
+
+    let event = |name: &str, key: &str| (KeyspaceNotifier::channel(3, name), key.to_string());
+    assert_eq!(
+        events,
+        vec![
+            event("set", "key"),
+            event("del", "key"),
+            event("set", "temp"),
+            event("expired", "temp"),
+        ]
+    );
+}
+
+#[tokio::test]
+async fn test_subscribers_receive_keyspace_events() {
+    let (_kvdb, addr) = start_server(true).await;
+
+    let (read_half, mut subscriber) = TcpStream::connect(addr).await.unwrap().into_split();
+    let mut subscriber_reader = BufReader::new(read_half);
+    subscriber
+        .write_all(b"SUBSCRIBE __keyevent@0__:set __keyevent@0__:del\n")
+        .await
+        .unwrap();
+    assert_eq!(read_line(&mut subscriber_reader).await, "INTEGER 2\n");
+
+    let (read_half, mut writer) = TcpStream::connect(addr).await.unwrap().into_split();
+    let mut writer_reader = BufReader::new(read_half);
+    for line in [
+        "SET key b25l\n",
+        "DELETE key\n",
+        "SELECT 1\n",
+        "SET key b25l\n",
+    ] {
+        writer.write_all(line.as_bytes()).await.unwrap();
+        read_line(&mut writer_reader).await;
+    }
+
+    // Database 1 publishes to its own channels, which nobody subscribed to
+    assert_eq!(
+        read_line(&mut subscriber_reader).await,
+        "MESSAGE __keyevent@0__:set a2V5\n"
+    );
+    assert_eq!(
+        read_line(&mut subscriber_reader).await,
+        "MESSAGE __keyevent@0__:del a2V5\n"
+    );
+
+    // Subscribed connections still run commands
+    subscriber.write_all(b"UNSUBSCRIBE\n").await.unwrap();
+    assert_eq!(read_line(&mut subscriber_reader).await, "INTEGER 0\n");
+    subscriber.write_all(b"PING\n").await.unwrap();
+    assert_eq!(read_line(&mut subscriber_reader).await, "PONG\n");
+}
+
+#[tokio::test]
+async fn test_keyspace_events_are_off_by_default() {
+    let (kvdb, addr) = start_server(false).await;
+    let mut messages = kvdb.dispatcher().pubsub().subscribe();
+
+    let (read_half, mut writer) = TcpStream::connect(addr).await.unwrap().into_split();
+    let mut reader = BufReader::new(read_half);
+    writer.write_all(b"SET key b25l\n").await.unwrap();
+    assert_eq!(read_line(&mut reader).await, "OK\n");
+
+    assert!(messages.try_recv().is_err());
+}
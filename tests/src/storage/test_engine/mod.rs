@@ -1 +1,2 @@
+pub mod test_bloom;
 pub mod test_memory;
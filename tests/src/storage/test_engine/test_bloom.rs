@@ -0,0 +1,188 @@
+use std::time::Duration;
+
+use blazekvdb::storage::{
+    StorageConfig, StorageEngine,
+    engine::{bloom::BloomFilter, memory::MemoryEngine},
+};
+
+fn bloom_engine(shard_count: usize, capacity: usize) -> MemoryEngine {
+    MemoryEngine::new(StorageConfig {
+        shard_count,
+        bloom_filter: true,
+        bloom_filter_capacity: capacity,
+        ..Default::default()
+    })
+}
+
+#[test]
+fn test_bloom_filter_has_no_false_negatives() {
+    let bloom = BloomFilter::new(10_000);
+    for i in 0..10_000 {
+        bloom.insert(&format!("key:{}", i));
+    }
+    for i in 0..10_000 {
+        assert!(bloom.may_contain(&format!("key:{}", i)));
+    }
+
+    // Sized for 1%, leave room for hash luck
+    let false_positives = (0..10_000)
+        .filter(|i| bloom.may_contain(&format!("absent:{}", i)))
+        .count();
+    assert!(false_positives < 300, "{} false positives", false_positives);
+}
+
+#[test]
+fn test_bloom_filter_rebuild_forgets_deleted_keys() {
+    let bloom = BloomFilter::new(100);
+    let live: Vec<String> = (0..50).map(|i| format!("live:{}", i)).collect();
+    for key in &live {
+        bloom.insert(key);
+    }
+    for i in 0..5000 {
+        bloom.insert(&format!("deleted:{}", i));
+    }
+
+    // Saturated by keys that are gone
+    assert!(bloom.is_stale(live.len()));
+    assert!(bloom.may_contain("never-inserted"));
+
+    bloom.rebuild(live.iter());
+    assert!(!bloom.is_stale(live.len()));
+    assert!(live.iter().all(|key| bloom.may_contain(key)));
+    let false_positives = (0..5000)
+        .filter(|i| bloom.may_contain(&format!("deleted:{}", i)))
+        .count();
+    assert!(false_positives < 250, "{} false positives", false_positives);
+}
+
+#[tokio::test]
+async fn test_bloom_filter_engine_lookups() {
+    let engine = bloom_engine(4, 1000);
+
+    for i in 0..1000 {
+        engine
+            .set(&format!("key:{}", i), b"value".to_vec())
+            .await
+            .unwrap();
+    }
+    for i in 0..1000 {
+        let key = format!("key:{}", i);
+        assert_eq!(engine.get(&key).await.unwrap(), Some(b"value".to_vec()));
+        assert!(engine.exists(&key).await.unwrap());
+    }
+
+    // Filtered misses still count as misses
+    let before = engine.stats().await.unwrap().keyspace_misses;
+    for i in 0..100 {
+        let key = format!("absent:{}", i);
+        assert_eq!(engine.get(&key).await.unwrap(), None);
+        assert!(!engine.exists(&key).await.unwrap());
+    }
+    assert_eq!(engine.stats().await.unwrap().keyspace_misses, before + 100);
+
+    let values = engine
+        .get_many(&["key:1", "absent:1", "key:999"])
+        .await
+        .unwrap();
+    assert_eq!(values.len(), 2);
+    assert!(values.contains_key("key:999"));
+
+    assert!(engine.delete("key:0").await.unwrap());
+    assert_eq!(engine.get("key:0").await.unwrap(), None);
+}
+
+#[tokio::test]
+async fn test_bloom_filter_sees_keys_from_every_write_path() {
+    // Nearly empty filter, so a key that skipped it is reported missing
+    let engine = bloom_engine(4, 1000);
+
+    engine.set("set", b"1".to_vec()).await.unwrap();
+    engine
+        .set_with_ttl("set_with_ttl", b"1".to_vec(), Duration::from_secs(60))
+        .await
+        .unwrap();
+    engine
+        .set_if_version("set_if_version", b"1".to_vec(), 0)
+        .await
+        .unwrap();
+    engine.set_range("set_range", 2, b"1").await.unwrap();
+    engine.set_bit("set_bit", 9, true).await.unwrap();
+    engine.incr_by_float("incr_by_float", 1.5).await.unwrap();
+    engine
+        .update("update", Box::new(|_| Ok(Some(b"1".to_vec()))))
+        .await
+        .unwrap();
+    engine
+        .add_members("add_members", &[b"a".to_vec()])
+        .await
+        .unwrap();
+    engine
+        .push_items("push_items", &[b"a".to_vec()], false)
+        .await
+        .unwrap();
+    engine.set("from", b"1".to_vec()).await.unwrap();
+    assert!(engine.rename("from", "rename").await.unwrap());
+
+    let keys = [
+        "set",
+        "set_with_ttl",
+        "set_if_version",
+        "set_range",
+        "set_bit",
+        "incr_by_float",
+        "update",
+        "add_members",
+        "push_items",
+        "rename",
+    ];
+    for key in keys {
+        assert!(engine.exists(key).await.unwrap(), "{} not found", key);
+        assert!(
+            engine.get(key).await.unwrap().is_some(),
+            "{} not found",
+            key
+        );
+    }
+    assert_eq!(engine.get_many(&keys).await.unwrap().len(), keys.len());
+    assert!(!engine.exists("from").await.unwrap());
+}
+
+#[tokio::test]
+async fn test_bloom_filter_survives_resize_sweep_and_clear() {
+    let engine = bloom_engine(1, 100);
+
+    for i in 0..3000 {
+        engine
+            .set(&format!("key:{}", i), b"value".to_vec())
+            .await
+            .unwrap();
+    }
+    for i in 100..3000 {
+        engine.delete(&format!("key:{}", i)).await.unwrap();
+    }
+
+    // The sweep rebuilds the stale filter from the remaining keys
+    engine.purge_expired().await.unwrap();
+    for i in 0..100 {
+        assert!(engine.exists(&format!("key:{}", i)).await.unwrap());
+    }
+
+    engine.resize_shards(4).await.unwrap();
+    engine.set("during", b"1".to_vec()).await.unwrap();
+    for _ in 0..500 {
+        if !engine.is_resizing() {
+            break;
+        }
+        tokio::time::sleep(Duration::from_millis(5)).await;
+    }
+    assert!(!engine.is_resizing());
+    for i in 0..100 {
+        assert!(engine.exists(&format!("key:{}", i)).await.unwrap());
+    }
+    assert!(engine.exists("during").await.unwrap());
+
+    engine.clear().await.unwrap();
+    assert!(!engine.exists("key:1").await.unwrap());
+    engine.set("key:1", b"again".to_vec()).await.unwrap();
+    assert_eq!(engine.get("key:1").await.unwrap(), Some(b"again".to_vec()));
+}
@@ -1,6 +1,10 @@
-use std::sync::atomic::Ordering;
+use std::{collections::HashMap, sync::atomic::Ordering, time::Duration};
 
-use blazekvdb::storage::{StorageConfig, StorageEngine, engine::memory::MemoryEngine};
+use blazekvdb::storage::{
+    MaxMemoryPolicy, StorageConfig, StorageEngine, StorageError, TtlOverflowPolicy,
+    engine::memory::MemoryEngine, now_millis,
+};
+use futures_util::TryStreamExt;
 
 #[tokio::test]
 async fn test_basic_operations() {
@@ -64,6 +68,207 @@ async fn test_memory_limit() {
     assert!(stats.memory_usage <= 1024);
 }
 
+#[tokio::test]
+async fn test_purge_expired_sweeps_one_shard_per_call() {
+    let config = StorageConfig {
+        shard_count: 4,
+        ..Default::default()
+    };
+    let engine = MemoryEngine::new(config);
+
+    for i in 0..20 {
+        engine
+            .set_with_ttl(
+                &format!("temp{}", i),
+                b"v".to_vec(),
+                Duration::from_millis(1),
+            )
+            .await
+            .unwrap();
+    }
+    engine
+        .set_with_ttl("kept", b"v".to_vec(), Duration::from_secs(60))
+        .await
+        .unwrap();
+    engine.set("plain", b"v".to_vec()).await.unwrap();
+    tokio::time::sleep(Duration::from_millis(10)).await;
+
+    let mut purged = 0;
+    for _ in 0..4 {
+        purged += engine.purge_expired().await.unwrap();
+    }
+    assert_eq!(purged, 20);
+    assert_eq!(engine.purge_expired().await.unwrap(), 0);
+
+    let stats = engine.stats().await.unwrap();
+    assert_eq!(stats.expired_keys, 20);
+    assert_eq!(stats.total_keys, 2);
+    assert_eq!(stats.expires, 1);
+    assert_eq!(
+        stats.memory_usage,
+        ["kept", "plain"]
+            .iter()
+            .map(|key| key.len() + 1 + 64)
+            .sum::<usize>()
+    );
+}
+
+#[tokio::test]
+async fn test_eviction_drops_least_recently_used() {
+    // One shard and a sample covering it, so eviction is exact LRU
+    let config = StorageConfig {
+        max_memory: 1200, // Ten 119-byte keys
+        shard_count: 1,
+        maxmemory_policy: MaxMemoryPolicy::AllkeysLru,
+        eviction_sample_size: 16,
+        ..Default::default()
+    };
+    let engine = MemoryEngine::new(config);
+
+    for i in 0..5 {
+        engine
+            .set(&format!("cold{}", i), vec![b'a'; 50])
+            .await
+            .unwrap();
+    }
+    // Access times have one-second resolution
+    tokio::time::sleep(Duration::from_millis(1100)).await;
+    for i in 0..5 {
+        engine
+            .set(&format!("warm{}", i), vec![b'a'; 50])
+            .await
+            .unwrap();
+    }
+
+    // Writes past the limit succeed by evicting the older keys
+    for i in 0..5 {
+        engine
+            .set(&format!("new{}", i), vec![b'a'; 50])
+            .await
+            .unwrap();
+    }
+
+    for i in 0..5 {
+        assert!(!engine.exists(&format!("cold{}", i)).await.unwrap());
+        assert!(engine.exists(&format!("warm{}", i)).await.unwrap());
+        assert!(engine.exists(&format!("new{}", i)).await.unwrap());
+    }
+
+    let stats = engine.stats().await.unwrap();
+    assert!(stats.memory_usage <= 1200);
+    assert_eq!(stats.evicted_keys, 5);
+
+    // A value that can't fit even in an empty engine fails without evicting anything
+    assert!(engine.set("huge", vec![b'a'; 2000]).await.is_err());
+    assert_eq!(engine.stats().await.unwrap().evicted_keys, 5);
+}
+
+#[tokio::test]
+async fn test_eviction_drops_least_frequently_used() {
+    let config = StorageConfig {
+        max_memory: 1200, // Ten 119-byte keys
+        shard_count: 1,
+        maxmemory_policy: MaxMemoryPolicy::AllkeysLfu,
+        eviction_sample_size: 16,
+        ..Default::default()
+    };
+    let engine = MemoryEngine::new(config);
+
+    // Read often, but before the others were written, so LRU would pick it
+    engine.set("hot", vec![b'a'; 50]).await.unwrap();
+    for _ in 0..1000 {
+        engine.get("hot").await.unwrap();
+    }
+    for i in 0..9 {
+        let key = format!("once{}", i);
+        engine.set(&key, vec![b'a'; 50]).await.unwrap();
+        engine.get(&key).await.unwrap();
+    }
+
+    for i in 0..5 {
+        engine
+            .set(&format!("new{}", i), vec![b'a'; 50])
+            .await
+            .unwrap();
+    }
+
+    assert!(engine.exists("hot").await.unwrap());
+    assert_eq!(engine.stats().await.unwrap().evicted_keys, 5);
+}
+
+#[tokio::test]
+async fn test_eviction_policies() {
+    // Fills the engine with five plain keys and five expiring at different times
+    async fn filled(policy: MaxMemoryPolicy) -> MemoryEngine {
+        let engine = MemoryEngine::new(StorageConfig {
+            max_memory: 1200, // Ten 119-byte keys
+            shard_count: 1,
+            maxmemory_policy: policy,
+            eviction_sample_size: 16,
+            ..Default::default()
+        });
+        for i in 0..5 {
+            engine
+                .set(&format!("keep{}", i), vec![b'a'; 50])
+                .await
+                .unwrap();
+            engine
+                .set_with_ttl(
+                    &format!("ttl_{}", i),
+                    vec![b'a'; 50],
+                    Duration::from_secs(100 * (i + 1)),
+                )
+                .await
+                .unwrap();
+        }
+        engine
+    }
+
+    let engine = filled(MaxMemoryPolicy::NoEviction).await;
+    assert!(engine.set("new_0", vec![b'a'; 50]).await.is_err());
+    assert_eq!(engine.stats().await.unwrap().evicted_keys, 0);
+
+    // Keys closest to expiring go first, keys without a TTL are never evicted
+    let engine = filled(MaxMemoryPolicy::VolatileTtl).await;
+    for i in 0..5 {
+        engine
+            .set(&format!("new_{}", i), vec![b'a'; 50])
+            .await
+            .unwrap();
+        assert!(!engine.exists(&format!("ttl_{}", i)).await.unwrap());
+        if i < 4 {
+            assert!(engine.exists(&format!("ttl_{}", i + 1)).await.unwrap());
+        }
+    }
+    assert!(engine.set("new_5", vec![b'a'; 50]).await.is_err());
+    for i in 0..5 {
+        assert!(engine.exists(&format!("keep{}", i)).await.unwrap());
+    }
+
+    let engine = filled(MaxMemoryPolicy::VolatileLru).await;
+    for i in 0..5 {
+        engine
+            .set(&format!("new_{}", i), vec![b'a'; 50])
+            .await
+            .unwrap();
+    }
+    assert!(engine.set("new_5", vec![b'a'; 50]).await.is_err());
+    let stats = engine.stats().await.unwrap();
+    assert_eq!(stats.evicted_keys, 5);
+    assert_eq!(stats.expires, 0);
+
+    let engine = filled(MaxMemoryPolicy::AllkeysRandom).await;
+    for i in 0..10 {
+        engine
+            .set(&format!("new_{}", i), vec![b'a'; 50])
+            .await
+            .unwrap();
+    }
+    let stats = engine.stats().await.unwrap();
+    assert_eq!(stats.evicted_keys, 10);
+    assert_eq!(stats.total_keys, 10);
+}
+
 #[tokio::test]
 async fn test_memory_stats() {
     let config = StorageConfig::default();
@@ -146,3 +351,530 @@ async fn test_memory_stats_debug() {
         final_stats.hit_rate
     );
 }
+
+#[tokio::test]
+async fn test_scan_stream() {
+    let engine = MemoryEngine::new(StorageConfig::default());
+
+    for i in 0..20 {
+        engine
+            .set(&format!("user:{}", i), b"v".to_vec())
+            .await
+            .unwrap();
+    }
+    engine.set("other", b"v".to_vec()).await.unwrap();
+
+    let stream = engine.scan("user:").await.unwrap();
+    let mut keys: Vec<String> = stream.try_collect().await.unwrap();
+    keys.sort();
+
+    assert_eq!(keys.len(), 20);
+    assert!(keys.iter().all(|k| k.starts_with("user:")));
+}
+
+#[tokio::test]
+async fn test_iter_all_stream() {
+    let engine = MemoryEngine::new(StorageConfig::default());
+
+    engine.set("key1", b"value1".to_vec()).await.unwrap();
+    engine.set("key2", b"value2".to_vec()).await.unwrap();
+
+    let entries: HashMap<String, Vec<u8>> = engine
+        .iter_all()
+        .await
+        .unwrap()
+        .try_collect()
+        .await
+        .unwrap();
+
+    assert_eq!(entries.len(), 2);
+    assert_eq!(entries.get("key2"), Some(&b"value2".to_vec()));
+}
+
+#[tokio::test]
+async fn test_scan_entries_stream() {
+    let engine = MemoryEngine::new(StorageConfig::default());
+
+    for i in 0..100 {
+        engine
+            .set(&format!("user:{}", i), i.to_string().into_bytes())
+            .await
+            .unwrap();
+    }
+    engine.set("other", b"value".to_vec()).await.unwrap();
+    engine
+        .set_with_ttl(
+            "user:expiring",
+            b"value".to_vec(),
+            Duration::from_millis(10),
+        )
+        .await
+        .unwrap();
+    tokio::time::sleep(Duration::from_millis(30)).await;
+
+    let entries: HashMap<String, Vec<u8>> = engine
+        .scan_entries("user:")
+        .await
+        .unwrap()
+        .try_collect()
+        .await
+        .unwrap();
+
+    assert_eq!(entries.len(), 100);
+    assert_eq!(entries.get("user:42"), Some(&b"42".to_vec()));
+    assert!(!entries.contains_key("user:expiring"));
+}
+
+#[tokio::test]
+async fn test_ttl_expiry() {
+    let engine = MemoryEngine::new(StorageConfig::default());
+
+    engine
+        .set_with_ttl("temp", b"value".to_vec(), Duration::from_millis(20))
+        .await
+        .unwrap();
+    assert!(engine.exists("temp").await.unwrap());
+
+    tokio::time::sleep(Duration::from_millis(40)).await;
+
+    assert!(!engine.exists("temp").await.unwrap());
+    assert_eq!(engine.get("temp").await.unwrap(), None);
+    assert_eq!(engine.stats().await.unwrap().total_keys, 0);
+    assert_eq!(engine.stats().await.unwrap().memory_usage, 0);
+}
+
+#[tokio::test]
+async fn test_get_many() {
+    let engine = MemoryEngine::new(StorageConfig {
+        shard_count: 4,
+        ..Default::default()
+    });
+
+    for i in 0..20 {
+        engine
+            .set(&format!("key{}", i), format!("value{}", i).into_bytes())
+            .await
+            .unwrap();
+    }
+    engine
+        .set_with_ttl("temp", b"value".to_vec(), Duration::from_millis(20))
+        .await
+        .unwrap();
+    tokio::time::sleep(Duration::from_millis(40)).await;
+
+    let keys: Vec<String> = (0..20).map(|i| format!("key{}", i)).collect();
+    let mut requested: Vec<&str> = keys.iter().map(String::as_str).collect();
+    requested.extend(["missing", "temp", "key3"]);
+
+    let values = engine.get_many(&requested).await.unwrap();
+    assert_eq!(values.len(), 20);
+    for i in 0..20 {
+        assert_eq!(
+            values[&format!("key{}", i)],
+            format!("value{}", i).into_bytes()
+        );
+    }
+
+    // Counted like the equivalent GETs, and the expired key is purged on the way
+    let stats = engine.stats().await.unwrap();
+    assert_eq!(stats.keyspace_hits, 21);
+    assert_eq!(stats.keyspace_misses, 2);
+    assert_eq!(stats.total_keys, 20);
+
+    assert!(engine.get_many(&[]).await.unwrap().is_empty());
+}
+
+#[tokio::test]
+async fn test_default_ttl() {
+    let config = StorageConfig {
+        default_ttl: Some(1),
+        ..Default::default()
+    };
+    let engine = MemoryEngine::new(config);
+
+    // A plain SET picks up the one-second default
+    let before = now_millis();
+    engine.set("key1", b"value1".to_vec()).await.unwrap();
+    let expires_at = engine.expires_at("key1").await.unwrap().unwrap().unwrap();
+    assert!((before + 1000..=now_millis() + 1000).contains(&expires_at));
+
+    tokio::time::sleep(Duration::from_millis(1100)).await;
+    assert_eq!(engine.get("key1").await.unwrap(), None);
+}
+
+#[tokio::test]
+async fn test_max_ttl_policy() {
+    let config = StorageConfig {
+        max_ttl: Some(60),
+        ttl_overflow: TtlOverflowPolicy::Reject,
+        ..Default::default()
+    };
+    let engine = MemoryEngine::new(config);
+
+    let result = engine
+        .set_with_ttl("key1", b"v".to_vec(), Duration::from_secs(3600))
+        .await;
+    assert!(result.is_err());
+
+    let config = StorageConfig {
+        max_ttl: Some(60),
+        ttl_overflow: TtlOverflowPolicy::Clamp,
+        ..Default::default()
+    };
+    let engine = MemoryEngine::new(config);
+
+    engine
+        .set_with_ttl("key1", b"v".to_vec(), Duration::from_secs(3600))
+        .await
+        .unwrap();
+    assert!(engine.exists("key1").await.unwrap());
+}
+
+#[tokio::test]
+async fn test_max_keys_refuses_new_keys_only() {
+    let config = StorageConfig {
+        max_keys: Some(2),
+        ..Default::default()
+    };
+    let engine = MemoryEngine::new(config);
+
+    engine.set("a", b"1".to_vec()).await.unwrap();
+    engine.set("b", b"2".to_vec()).await.unwrap();
+    assert_eq!(engine.stats().await.unwrap().max_keys, Some(2));
+
+    // Every way of creating a key is refused at the cap
+    assert!(matches!(
+        engine.set("c", b"3".to_vec()).await,
+        Err(StorageError::KeyLimit(2))
+    ));
+    assert!(matches!(
+        engine.add_members("c", &[b"m".to_vec()]).await,
+        Err(StorageError::KeyLimit(2))
+    ));
+    assert!(matches!(
+        engine.set_range("c", 0, b"x").await,
+        Err(StorageError::KeyLimit(2))
+    ));
+    assert!(matches!(
+        engine.incr_by_float("c", 1.0).await,
+        Err(StorageError::KeyLimit(2))
+    ));
+    assert!(!engine.exists("c").await.unwrap());
+
+    // Existing keys can still be overwritten and renamed
+    engine.set("a", b"updated".to_vec()).await.unwrap();
+    assert!(engine.rename("b", "c").await.unwrap());
+    assert_eq!(engine.get("a").await.unwrap(), Some(b"updated".to_vec()));
+
+    // A deleted or expired key frees its slot
+    engine.delete("a").await.unwrap();
+    engine.set("d", b"4".to_vec()).await.unwrap();
+    engine
+        .set_with_ttl("d", b"4".to_vec(), Duration::from_millis(10))
+        .await
+        .unwrap();
+    tokio::time::sleep(Duration::from_millis(20)).await;
+    assert_eq!(engine.get("d").await.unwrap(), None);
+    engine.set("e", b"5".to_vec()).await.unwrap();
+
+    assert_eq!(engine.clear().await.unwrap(), 2);
+    engine.set("f", b"6".to_vec()).await.unwrap();
+    engine.set("g", b"7".to_vec()).await.unwrap();
+}
+
+#[tokio::test]
+async fn test_rename() {
+    let engine = MemoryEngine::new(StorageConfig::default());
+
+    engine.set("src", b"value".to_vec()).await.unwrap();
+    engine.set("dst", b"old".to_vec()).await.unwrap();
+
+    assert!(engine.rename("src", "dst").await.unwrap());
+    assert_eq!(engine.get("src").await.unwrap(), None);
+    assert_eq!(engine.get("dst").await.unwrap(), Some(b"value".to_vec()));
+
+    // Missing source leaves the keyspace untouched
+    assert!(!engine.rename("missing", "dst").await.unwrap());
+    assert_eq!(engine.stats().await.unwrap().total_keys, 1);
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 8)]
+async fn test_concurrent_rename_no_deadlock() {
+    let engine = std::sync::Arc::new(MemoryEngine::new(StorageConfig::default()));
+    let key = |i: usize| format!("key{:02}", i % 16);
+
+    for i in 0..16 {
+        engine.set(&key(i), b"v".to_vec()).await.unwrap();
+    }
+
+    // Neighbouring tasks rename the same pairs in opposite directions,
+    // which deadlocks unless shards are always locked in the same order
+    let tasks: Vec<_> = (0..64)
+        .map(|t| {
+            let engine = engine.clone();
+            tokio::spawn(async move {
+                for n in 0..500 {
+                    let (a, b) = (key(t + n), key(t + n + 1));
+                    let (from, to) = if t % 2 == 0 { (a, b) } else { (b, a) };
+                    engine.rename(&from, &to).await.unwrap();
+                }
+            })
+        })
+        .collect();
+
+    tokio::time::timeout(Duration::from_secs(30), async {
+        for task in tasks {
+            task.await.unwrap();
+        }
+    })
+    .await
+    .expect("concurrent renames deadlocked");
+
+    // Every surviving entry is 5-byte key + 1-byte value + 64 bytes overhead
+    let stats = engine.stats().await.unwrap();
+    assert!(stats.total_keys >= 1);
+    assert_eq!(stats.memory_usage, stats.total_keys * 70);
+}
+
+#[tokio::test]
+async fn test_total_operations_counts_mixed_workload() {
+    let engine = MemoryEngine::new(StorageConfig::default());
+
+    for key in ["a", "b", "c"] {
+        engine.set(key, b"v".to_vec()).await.unwrap(); // 3
+    }
+    engine
+        .set_with_ttl("d", b"v".to_vec(), Duration::from_secs(60))
+        .await
+        .unwrap(); // 4
+
+    engine.get("a").await.unwrap(); // 5, hit
+    engine.get("missing").await.unwrap(); // 6, miss
+    engine.exists("a").await.unwrap(); // 7
+    engine.exists("missing").await.unwrap(); // 8
+
+    let keys: Vec<String> = engine.scan("").await.unwrap().try_collect().await.unwrap(); // 9, however many keys it streams
+    assert_eq!(keys.len(), 4);
+
+    engine.delete("c").await.unwrap(); // 10
+    engine.delete("missing").await.unwrap(); // 11, misses count too
+    engine
+        .touch(&["a".to_string(), "b".to_string(), "missing".to_string()])
+        .await
+        .unwrap(); // 14, one per key
+    engine.add_members("s", &[b"m".to_vec()]).await.unwrap(); // 15
+    engine.remove_members("s", &[b"x".to_vec()]).await.unwrap(); // 16, no-op
+    engine.rename("b", "e").await.unwrap(); // 17
+    engine.get_range("a", 0, -1).await.unwrap(); // 18
+    engine.set_range("a", 0, b"w").await.unwrap(); // 19
+    engine.idle_time("a").await.unwrap(); // 20
+
+    // Administrative calls are not traffic
+    let entries: Vec<(String, Vec<u8>)> = engine
+        .iter_all()
+        .await
+        .unwrap()
+        .try_collect()
+        .await
+        .unwrap();
+    assert_eq!(entries.len(), 4); // a, d, e and the set s
+    engine.health_check().await.unwrap();
+    engine.stats().await.unwrap();
+
+    let stats = engine.stats().await.unwrap();
+    assert_eq!(stats.total_operations, 20);
+
+    // Only GET affects the hit rate
+    assert_eq!(engine.hit_count.load(Ordering::Relaxed), 1);
+    assert_eq!(engine.miss_count.load(Ordering::Relaxed), 1);
+    assert_eq!(stats.hit_rate, 0.5);
+}
+
+// Polls until a background shard resize has moved every key
+async fn wait_for_resize(engine: &MemoryEngine) {
+    for _ in 0..500 {
+        if !engine.is_resizing() {
+            return;
+        }
+        tokio::time::sleep(Duration::from_millis(5)).await;
+    }
+    panic!("shard resize did not complete");
+}
+
+#[tokio::test]
+async fn test_resize_shards_keeps_every_key() {
+    let engine = MemoryEngine::new(StorageConfig {
+        shard_count: 4,
+        ..Default::default()
+    });
+
+    for i in 0..1000 {
+        let key = format!("key{}", i);
+        if i % 10 == 0 {
+            engine
+                .set_with_ttl(&key, vec![b'a'; 10], Duration::from_secs(60))
+                .await
+                .unwrap();
+        } else {
+            engine.set(&key, vec![b'a'; 10]).await.unwrap();
+        }
+    }
+    let before = engine.stats().await.unwrap();
+
+    for shard_count in [16, 3] {
+        engine.resize_shards(shard_count).await.unwrap();
+        assert_eq!(engine.shard_count(), shard_count);
+
+        // Commands keep working mid-migration, routed to wherever their key lives now
+        engine.set("moving", b"1".to_vec()).await.unwrap();
+        assert_eq!(engine.get("key7").await.unwrap(), Some(vec![b'a'; 10]));
+        assert!(engine.rename("moving", "moved").await.unwrap());
+        assert!(engine.delete("moved").await.unwrap());
+
+        wait_for_resize(&engine).await;
+
+        let stats = engine.stats().await.unwrap();
+        assert_eq!(stats.total_keys, before.total_keys);
+        assert_eq!(stats.expires, before.expires);
+        assert_eq!(stats.memory_usage, before.memory_usage);
+        for i in 0..1000 {
+            assert!(engine.exists(&format!("key{}", i)).await.unwrap());
+        }
+    }
+
+    assert!(engine.resize_shards(0).await.is_err());
+}
+
+#[tokio::test]
+async fn test_resize_waits_for_scans() {
+    let engine = MemoryEngine::new(StorageConfig {
+        shard_count: 2,
+        ..Default::default()
+    });
+    for i in 0..100 {
+        engine
+            .set(&format!("key{}", i), b"value".to_vec())
+            .await
+            .unwrap();
+    }
+
+    // An open stream holds migration off, so it sees each key exactly once
+    let stream = engine.iter_all().await.unwrap();
+    engine.resize_shards(8).await.unwrap();
+    assert!(engine.resize_shards(4).await.is_err());
+
+    tokio::time::sleep(Duration::from_millis(50)).await;
+    assert!(engine.is_resizing());
+    assert_eq!(engine.get("key42").await.unwrap(), Some(b"value".to_vec()));
+
+    let entries: Vec<(String, Vec<u8>)> = stream.try_collect().await.unwrap();
+    assert_eq!(entries.len(), 100);
+
+    wait_for_resize(&engine).await;
+    let keys: Vec<String> = engine
+        .scan("key")
+        .await
+        .unwrap()
+        .try_collect()
+        .await
+        .unwrap();
+    assert_eq!(keys.len(), 100);
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 8)]
+async fn test_key_counters_match_shards_after_mixed_workload() {
+    let engine = std::sync::Arc::new(MemoryEngine::new(StorageConfig::default()));
+    let key = |i: usize| format!("key{:02}", i % 32);
+
+    // Keys migrate between shard sets while the workload runs
+    engine.resize_shards(7).await.unwrap();
+
+    // Tasks race on a small keyspace, so inserts, overwrites and removals of the same key
+    // interleave; type errors from mixing strings, sets and lists are expected
+    let tasks: Vec<_> = (0..16)
+        .map(|t| {
+            let engine = engine.clone();
+            tokio::spawn(async move {
+                for n in 0..400 {
+                    let k = key(t * 7 + n);
+                    let member = vec![b'm', (n % 3) as u8];
+                    let _ = match (t + n) % 10 {
+                        0 => engine.set(&k, b"v".to_vec()).await,
+                        1 => {
+                            engine
+                                .set_with_ttl(&k, b"v".to_vec(), Duration::from_secs(60))
+                                .await
+                        }
+                        2 => engine.delete(&k).await.map(|_| ()),
+                        3 => engine.rename(&k, &key(n)).await.map(|_| ()),
+                        4 => engine.add_members(&k, &[member]).await.map(|_| ()),
+                        5 => engine.remove_members(&k, &[member]).await.map(|_| ()),
+                        6 => engine.incr_by_float(&k, 1.0).await.map(|_| ()),
+                        7 => {
+                            let at = blazekvdb::storage::now_millis() + 60_000;
+                            engine.expire_at(&k, at).await.map(|_| ())
+                        }
+                        8 => engine.push_items(&k, &[member], false).await.map(|_| ()),
+                        _ => engine.pop_front(&k).await.map(|_| ()),
+                    };
+                }
+                engine.delete_prefix(&key(t)).await.unwrap();
+            })
+        })
+        .collect();
+
+    for task in tasks {
+        task.await.unwrap();
+    }
+    wait_for_resize(&engine).await;
+
+    // Nothing has expired, so a full walk of the shards sees every counted key
+    let entries: Vec<_> = engine
+        .iter_with_expiry()
+        .await
+        .unwrap()
+        .try_collect()
+        .await
+        .unwrap();
+    let volatile = entries.iter().filter(|(_, _, at)| at.is_some()).count();
+
+    let stats = engine.stats().await.unwrap();
+    assert!(stats.total_keys > 0);
+    assert_eq!(stats.total_keys, entries.len());
+    assert_eq!(stats.expires, volatile);
+}
+
+#[tokio::test]
+async fn test_set_range_and_set_bit_refuse_type_tags() {
+    let engine = MemoryEngine::new(StorageConfig::default());
+
+    // A new key can't start out as a tag
+    assert!(matches!(
+        engine.set_range("new", 0, b"\x00blz:set\x00rest").await,
+        Err(StorageError::ReservedTypeTag)
+    ));
+    assert!(!engine.exists("new").await.unwrap());
+
+    // Nor can a string be completed into one byte by byte
+    engine
+        .set_range("string", 0, b"\x00blz:list")
+        .await
+        .unwrap();
+    assert!(matches!(
+        engine.set_range("string", 9, b"\x00").await,
+        Err(StorageError::ReservedTypeTag)
+    ));
+    engine.set_range("string", 9, b"\x01").await.unwrap();
+    assert!(matches!(
+        engine.set_bit("string", 9 * 8 + 7, false).await,
+        Err(StorageError::ReservedTypeTag)
+    ));
+    assert_eq!(
+        engine.get("string").await.unwrap(),
+        Some(b"\x00blz:list\x01".to_vec())
+    );
+
+    // Writes past the tag region are unaffected
+    engine.set_range("string", 20, b"\x00").await.unwrap();
+    engine.set_bit("string", 200, true).await.unwrap();
+}
@@ -1,18 +1,27 @@
-use std::{collections::HashMap, sync::Arc};
+use std::{collections::HashMap, sync::Arc, time::Duration};
 
 use blazekvdb::{
+    commands::{
+        Command, CommandDispatcher, CommandResponse, blpop::BLPopCommand, expire::ExpireCommand,
+        push::PushCommand, sadd::SAddCommand, set::SetCommand, snapshot::SnapshotCommand,
+        srem::SRemCommand,
+    },
     config::{FsyncPolicy, PersistenceConfig},
+    protocol::parser::ProtocolParser,
     storage::{
-        StorageConfig, StorageEngine,
+        EntryStream, StorageConfig, StorageEngine,
         engine::memory::MemoryEngine,
+        now_millis,
         persistence::{
             aof::{AppendOnlyFile, Operation},
             manager::PersistenceManager,
             recovery::RecoveryManager,
-            snapshot::Snapshotter,
+            snapshot::{JsonFormat, SNAPSHOT_FORMAT_VERSION, Snapshotter},
         },
+        value::{decode_list, decode_set},
     },
 };
+use futures_util::TryStreamExt;
 use tempfile::tempdir;
 
 #[tokio::test]
@@ -56,140 +65,2001 @@ async fn test_aof_operations() {
 }
 
 #[tokio::test]
-async fn test_snapshot_creation_and_loading() {
+async fn test_aof_setrange_replay() {
     let temp_dir = tempdir().unwrap();
-    let snapshotter = Snapshotter::new(temp_dir.path()).unwrap();
+    let aof_path = temp_dir.path().join("test.aof");
 
-    // Create test data
-    let mut data = HashMap::new();
-    data.insert("key1".to_string(), b"value1".to_vec());
-    data.insert("key2".to_string(), b"value2".to_vec());
+    let mut aof = AppendOnlyFile::new(&aof_path).await.unwrap();
+    aof.log_operation_sync(Operation::Put {
+        key: "key1".to_string(),
+        value: b"Hello World".to_vec(),
+    })
+    .await
+    .unwrap();
+    aof.log_operation_sync(Operation::SetRange {
+        key: "key1".to_string(),
+        offset: 6,
+        value: b"Redis".to_vec(),
+    })
+    .await
+    .unwrap();
 
-    // Create snapshot
-    let snapshot_path = snapshotter.create_snapshot(data.clone()).await.unwrap();
-    assert!(snapshot_path.exists());
+    let storage = MemoryEngine::new(StorageConfig::default());
+    let recovery = RecoveryManager::new(Some(aof), None);
+    recovery.recover(&storage).await.unwrap();
 
-    // Load snapshot
-    let loaded = snapshotter.load_snapshot(&snapshot_path).await.unwrap();
-    assert_eq!(loaded.data.len(), 2);
-    assert_eq!(loaded.data.get("key1").unwrap(), b"value1");
+    assert_eq!(
+        storage.get("key1").await.unwrap(),
+        Some(b"Hello Redis".to_vec())
+    );
 }
 
 #[tokio::test]
-async fn test_recovery_with_snapshot_and_aof() {
+async fn test_aof_setbit_replay() {
     let temp_dir = tempdir().unwrap();
     let aof_path = temp_dir.path().join("test.aof");
-    let snapshot_dir = temp_dir.path().join("snapshots");
 
-    // Setup
-    let config = StorageConfig::default();
-    let storage = Arc::new(MemoryEngine::new(config));
-    let aof = AppendOnlyFile::new(&aof_path).await.unwrap();
+    let mut aof = AppendOnlyFile::new(&aof_path).await.unwrap();
+    for (offset, bit) in [(1, true), (7, true), (17, true), (7, false)] {
+        aof.log_operation_sync(Operation::SetBit {
+            key: "bits".to_string(),
+            offset,
+            bit,
+        })
+        .await
+        .unwrap();
+    }
 
-    // Create snapshot with initial data
-    storage.set("key1", b"value1".to_vec()).await.unwrap();
+    let storage = MemoryEngine::new(StorageConfig::default());
+    let recovery = RecoveryManager::new(Some(aof), None);
+    recovery.recover(&storage).await.unwrap();
 
-    let snapshotter = Snapshotter::new(&snapshot_dir).unwrap();
-    let mut data = HashMap::new();
-    data.insert("key1".to_string(), b"value1".to_vec());
+    assert_eq!(
+        storage.get("bits").await.unwrap(),
+        Some(vec![0b0100_0000, 0, 0b0100_0000])
+    );
+}
 
-    snapshotter.create_snapshot(data).await.unwrap();
+#[tokio::test]
+async fn test_aof_database_tags_replay() {
+    let temp_dir = tempdir().unwrap();
+    let aof_path = temp_dir.path().join("test.aof");
 
-    // Clear storage
-    storage.delete("key1").await.unwrap();
+    let put = |key: &str, value: &[u8]| Operation::Put {
+        key: key.to_string(),
+        value: value.to_vec(),
+    };
 
-    // Recovery should restore from snapshot
-    let recovery = RecoveryManager::new(Some(aof), Some(snapshotter));
-    let stats = recovery.recover(storage.as_ref()).await.unwrap();
+    let mut aof = AppendOnlyFile::new(&aof_path).await.unwrap();
+    for operation in [
+        put("key", b"zero").in_database(0),
+        put("key", b"one").in_database(1),
+        put("other", b"one").in_database(1),
+        Operation::Flush.in_database(1),
+        put("key", b"one again").in_database(1),
+    ] {
+        aof.log_operation_sync(operation).await.unwrap();
+    }
 
-    println!("stats: {:?}", stats);
-    assert!(stats.snapshot_loaded);
-    assert_eq!(stats.keys_from_snapshot, 1);
+    // Database 0 entries keep the untagged format older files use
+    let contents = std::fs::read_to_string(&aof_path).unwrap();
+    assert!(contents.starts_with("SET key "));
+    assert!(contents.contains("SELECT 1 FLUSHDB"));
 
-    // Verify data restored
-    let result = storage.get("key1").await.unwrap();
-    assert_eq!(result, Some(b"value1".to_vec()))
+    let db0 = MemoryEngine::new(StorageConfig::default());
+    let db1 = MemoryEngine::new(StorageConfig::default());
+    let recovery = RecoveryManager::new(Some(aof), None);
+    recovery.recover_databases(&[&db0, &db1]).await.unwrap();
+
+    assert_eq!(db0.get("key").await.unwrap(), Some(b"zero".to_vec()));
+    assert_eq!(db1.get("key").await.unwrap(), Some(b"one again".to_vec()));
+    assert_eq!(db1.get("other").await.unwrap(), None);
+
+    // Replaying into fewer databases than the file references is refused
+    let err = recovery.recover_databases(&[&db0]).await.unwrap_err();
+    assert!(err.to_string().contains("database 1"));
 }
 
 #[tokio::test]
-async fn test_persistence_manager_initialization() {
+async fn test_set_operations_survive_compaction() {
     let temp_dir = tempdir().unwrap();
 
     let config = PersistenceConfig {
-        enabled: true,
         aof_path: temp_dir.path().join("test.aof"),
-        fsync_policy: FsyncPolicy::EveryN(100),
-        snapshot_enabled: true,
-        snapshot_interval: 3600,
+        fsync_policy: FsyncPolicy::Always,
+        aof_rewrite_percentage: 0,
+        aof_rewrite_min_size: 0,
         snapshot_dir: temp_dir.path().join("snapshots"),
+        ..Default::default()
     };
 
-    let storage_config = StorageConfig::default();
-    let storage = Arc::new(MemoryEngine::new(storage_config)) as Arc<dyn StorageEngine>;
+    let storage = Arc::new(MemoryEngine::new(StorageConfig::default())) as Arc<dyn StorageEngine>;
+    let manager = Arc::new(
+        PersistenceManager::new(config.clone(), storage.clone())
+            .await
+            .unwrap(),
+    );
+    let dispatcher = CommandDispatcher::new(storage.clone()).with_persistence(manager.clone());
 
-    let manager = PersistenceManager::new(config, storage).await.unwrap();
+    let members =
+        |items: &[&str]| -> Vec<Vec<u8>> { items.iter().map(|m| m.as_bytes().to_vec()).collect() };
 
-    assert!(manager.aof.is_some());
-    assert!(manager.snapshotter.is_some());
+    dispatcher
+        .execute(Command::SAdd(SAddCommand::new(
+            "set".to_string(),
+            members(&["a", "b"]),
+        )))
+        .await;
+
+    // Compaction rewrites the set as its tagged value
+    manager.create_snapshot().await.unwrap();
+
+    dispatcher
+        .execute(Command::SAdd(SAddCommand::new(
+            "set".to_string(),
+            members(&["c"]),
+        )))
+        .await;
+    dispatcher
+        .execute(Command::SRem(SRemCommand::new(
+            "set".to_string(),
+            members(&["a"]),
+        )))
+        .await;
+    manager.sync_aof().await.unwrap();
+
+    std::fs::remove_dir_all(temp_dir.path().join("snapshots")).unwrap();
+    let recovery_config = PersistenceConfig {
+        snapshot_enabled: false,
+        ..config
+    };
+
+    let new_storage =
+        Arc::new(MemoryEngine::new(StorageConfig::default())) as Arc<dyn StorageEngine>;
+    PersistenceManager::new(recovery_config, new_storage.clone())
+        .await
+        .unwrap()
+        .recover()
+        .await
+        .unwrap();
+
+    let value = new_storage.get("set").await.unwrap().unwrap();
+    let mut recovered: Vec<Vec<u8>> = decode_set(&value).unwrap().into_iter().collect();
+    recovered.sort();
+    assert_eq!(recovered, members(&["b", "c"]));
 }
 
 #[tokio::test]
-async fn test_full_persistence_cycle() {
+async fn test_restore_from_archived_snapshot() {
     let temp_dir = tempdir().unwrap();
 
     let config = PersistenceConfig {
-        enabled: true,
         aof_path: temp_dir.path().join("test.aof"),
         fsync_policy: FsyncPolicy::Always,
-        snapshot_enabled: true,
-        snapshot_interval: 3600,
+        aof_rewrite_percentage: 0,
+        aof_rewrite_min_size: 0,
         snapshot_dir: temp_dir.path().join("snapshots"),
+        ..Default::default()
     };
 
-    let storage_config = StorageConfig::default();
-    let storage = Arc::new(MemoryEngine::new(storage_config)) as Arc<dyn StorageEngine>;
+    let storage = Arc::new(MemoryEngine::new(StorageConfig::default())) as Arc<dyn StorageEngine>;
+    let manager = Arc::new(
+        PersistenceManager::new(config.clone(), storage.clone())
+            .await
+            .unwrap(),
+    );
+    let dispatcher = CommandDispatcher::new(storage).with_persistence(manager.clone());
+
+    dispatcher
+        .execute(Command::Set(SetCommand::new(
+            "key".to_string(),
+            b"old".to_vec(),
+        )))
+        .await;
+    manager.create_snapshot().await.unwrap();
+    let archive = temp_dir.path().join("archive.rdb");
+    std::fs::copy(
+        temp_dir.path().join("snapshots/snapshot-latest.rdb"),
+        &archive,
+    )
+    .unwrap();
+
+    dispatcher
+        .execute(Command::Set(SetCommand::new(
+            "key".to_string(),
+            b"new".to_vec(),
+        )))
+        .await;
+    dispatcher
+        .execute(Command::Set(SetCommand::new(
+            "later".to_string(),
+            b"x".to_vec(),
+        )))
+        .await;
+    manager.create_snapshot().await.unwrap();
+    manager.stop().await.unwrap();
+
+    // A missing restore file is fatal rather than a silent fallback to the latest snapshot
+    let missing = PersistenceConfig {
+        restore_from: Some(temp_dir.path().join("missing.rdb")),
+        ..config.clone()
+    };
+    let storage = Arc::new(MemoryEngine::new(StorageConfig::default())) as Arc<dyn StorageEngine>;
+    let manager = PersistenceManager::new(missing, storage).await.unwrap();
+    assert!(manager.recover().await.is_err());
+    manager.stop().await.unwrap();
+
+    let restore = PersistenceConfig {
+        restore_from: Some(archive),
+        restore_snapshot_only: true,
+        allow_relocate: false,
+        ..config.clone()
+    };
+    let storage = Arc::new(MemoryEngine::new(StorageConfig::default())) as Arc<dyn StorageEngine>;
+    let manager = PersistenceManager::new(restore, storage.clone())
+        .await
+        .unwrap();
+    let stats = manager.recover().await.unwrap();
+    manager.stop().await.unwrap();
+
+    assert_eq!(stats.aof_operations_total, 0);
+    assert_eq!(storage.get("key").await.unwrap(), Some(b"old".to_vec()));
+    assert_eq!(storage.get("later").await.unwrap(), None);
 
+    // The restored state replaced the persisted one, a plain restart comes back to it
+    let storage = Arc::new(MemoryEngine::new(StorageConfig::default())) as Arc<dyn StorageEngine>;
+    let manager = PersistenceManager::new(config, storage.clone())
+        .await
+        .unwrap();
+    manager.recover().await.unwrap();
+    manager.stop().await.unwrap();
+
+    assert_eq!(storage.get("key").await.unwrap(), Some(b"old".to_vec()));
+    assert_eq!(storage.get("later").await.unwrap(), None);
+}
+
+#[tokio::test]
+async fn test_list_pops_are_replayed() {
+    let temp_dir = tempdir().unwrap();
+
+    let config = PersistenceConfig {
+        aof_path: temp_dir.path().join("test.aof"),
+        fsync_policy: FsyncPolicy::Always,
+        aof_rewrite_percentage: 0,
+        aof_rewrite_min_size: 0,
+        snapshot_enabled: false,
+        snapshot_dir: temp_dir.path().join("snapshots"),
+        ..Default::default()
+    };
+
+    let storage = Arc::new(MemoryEngine::new(StorageConfig::default())) as Arc<dyn StorageEngine>;
     let manager = Arc::new(
         PersistenceManager::new(config.clone(), storage.clone())
             .await
             .unwrap(),
     );
+    let dispatcher = CommandDispatcher::new(storage.clone()).with_persistence(manager.clone());
 
-    storage.set("key1", b"value1".to_vec()).await.unwrap();
-    manager
-        .log_operation(Operation::Put {
-            key: "key1".to_string(),
-            value: b"value1".to_vec(),
-        })
+    let items = vec![b"a".to_vec(), b"b".to_vec(), b"c".to_vec()];
+    dispatcher
+        .execute(Command::Push(PushCommand::new(
+            "jobs".to_string(),
+            items,
+            false,
+        )))
+        .await;
+    let pop = Command::BLPop(BLPopCommand::new("jobs".to_string(), Duration::ZERO));
+    assert_eq!(
+        dispatcher.execute(pop).await,
+        CommandResponse::Value(b"a".to_vec())
+    );
+
+    // A pop that finds nothing isn't logged
+    let pop = Command::BLPop(BLPopCommand::new(
+        "empty".to_string(),
+        Duration::from_millis(10),
+    ));
+    assert_eq!(dispatcher.execute(pop).await, CommandResponse::Nil);
+    manager.sync_aof().await.unwrap();
+
+    let contents = std::fs::read_to_string(temp_dir.path().join("test.aof")).unwrap();
+    assert_eq!(
+        contents.lines().filter(|l| l.starts_with("LPOP")).count(),
+        1
+    );
+
+    let new_storage =
+        Arc::new(MemoryEngine::new(StorageConfig::default())) as Arc<dyn StorageEngine>;
+    PersistenceManager::new(config, new_storage.clone())
+        .await
+        .unwrap()
+        .recover()
         .await
         .unwrap();
 
-    storage.set("key2", b"value2".to_vec()).await.unwrap();
-    manager
-        .log_operation(Operation::Put {
-            key: "key2".to_string(),
-            value: b"value2".to_vec(),
-        })
+    let value = new_storage.get("jobs").await.unwrap().unwrap();
+    assert_eq!(decode_list(&value).unwrap(), [b"b".to_vec(), b"c".to_vec()]);
+}
+
+#[tokio::test]
+async fn test_expiry_is_replayed_as_absolute_time() {
+    let temp_dir = tempdir().unwrap();
+
+    let config = PersistenceConfig {
+        aof_path: temp_dir.path().join("test.aof"),
+        fsync_policy: FsyncPolicy::Always,
+        aof_rewrite_percentage: 0,
+        aof_rewrite_min_size: 0,
+        snapshot_enabled: false,
+        snapshot_dir: temp_dir.path().join("snapshots"),
+        ..Default::default()
+    };
+
+    let storage = Arc::new(MemoryEngine::new(StorageConfig::default())) as Arc<dyn StorageEngine>;
+    let manager = Arc::new(
+        PersistenceManager::new(config.clone(), storage.clone())
+            .await
+            .unwrap(),
+    );
+    let dispatcher = CommandDispatcher::new(storage.clone()).with_persistence(manager.clone());
+
+    for key in ["short", "long"] {
+        dispatcher
+            .execute(Command::Set(SetCommand::new(
+                key.to_string(),
+                b"v".to_vec(),
+            )))
+            .await;
+    }
+    let expire = |key: &str, millis| {
+        Command::Expire(ExpireCommand::new(key.to_string(), millis, true, false))
+    };
+    assert_eq!(
+        dispatcher.execute(expire("short", 50)).await,
+        CommandResponse::Bool(true)
+    );
+    assert_eq!(
+        dispatcher.execute(expire("long", 60_000)).await,
+        CommandResponse::Bool(true)
+    );
+    let deadline = storage.expires_at("long").await.unwrap().unwrap().unwrap();
+    manager.sync_aof().await.unwrap();
+
+    // Replaying after the short expiry has passed must not bring it back to life
+    tokio::time::sleep(Duration::from_millis(100)).await;
+    let new_storage =
+        Arc::new(MemoryEngine::new(StorageConfig::default())) as Arc<dyn StorageEngine>;
+    PersistenceManager::new(config, new_storage.clone())
+        .await
+        .unwrap()
+        .recover()
         .await
         .unwrap();
 
-    manager.create_snapshot().await.unwrap();
+    assert_eq!(new_storage.get("short").await.unwrap(), None);
+    assert_eq!(
+        new_storage.expires_at("long").await.unwrap(),
+        Some(Some(deadline))
+    );
+    assert!(deadline > now_millis());
+}
 
-    storage.delete("key1").await.unwrap();
-    storage.delete("key2").await.unwrap();
+#[tokio::test]
+async fn test_snapshot_creation_and_loading() {
+    let temp_dir = tempdir().unwrap();
+    let snapshotter = Snapshotter::new(temp_dir.path()).unwrap();
 
-    // Recover
+    // Create test data
+    let mut data = HashMap::new();
+    data.insert("key1".to_string(), (b"value1".to_vec(), None));
+    data.insert("key2".to_string(), (b"value2".to_vec(), None));
+
+    // Create snapshot
+    let snapshot_path = snapshotter.create_snapshot(data.clone()).await.unwrap();
+    assert!(snapshot_path.exists());
+
+    // Load snapshot
+    let loaded = snapshotter.load_snapshot(&snapshot_path).await.unwrap();
+    assert_eq!(loaded.data.len(), 2);
+    assert_eq!(loaded.data["key1"], (b"value1".to_vec(), None));
+    assert_eq!(loaded.metadata.format_version, SNAPSHOT_FORMAT_VERSION);
+}
+
+#[tokio::test]
+async fn test_snapshot_keeps_expiry() {
+    let temp_dir = tempdir().unwrap();
+
+    // Snapshot only, so nothing can come back from the AOF
+    let config = PersistenceConfig {
+        enabled: false,
+        aof_path: temp_dir.path().join("test.aof"),
+        fsync_policy: FsyncPolicy::Always,
+        aof_rewrite_percentage: 0,
+        aof_rewrite_min_size: 0,
+        snapshot_dir: temp_dir.path().join("snapshots"),
+        snapshot_required: true,
+        ..Default::default()
+    };
+
+    let storage = Arc::new(MemoryEngine::new(StorageConfig::default())) as Arc<dyn StorageEngine>;
+    let manager = PersistenceManager::new(config.clone(), storage.clone())
+        .await
+        .unwrap();
+
+    for key in ["short", "long", "plain"] {
+        storage.set(key, b"v".to_vec()).await.unwrap();
+    }
+    let now = now_millis();
+    storage.expire_at("short", now + 50).await.unwrap();
+    storage.expire_at("long", now + 60_000).await.unwrap();
+    manager.create_snapshot().await.unwrap();
+
+    // Restarting after the short expiry passed drops it instead of making it permanent
+    tokio::time::sleep(Duration::from_millis(100)).await;
     let new_storage =
         Arc::new(MemoryEngine::new(StorageConfig::default())) as Arc<dyn StorageEngine>;
-    let new_manager = PersistenceManager::new(config, new_storage.clone())
+    let stats = PersistenceManager::new(config, new_storage.clone())
+        .await
+        .unwrap()
+        .recover()
         .await
         .unwrap();
 
-    let stats = new_manager.recover().await.unwrap();
+    assert_eq!(stats.keys_from_snapshot, 2);
+    assert_eq!(stats.keys_expired_in_snapshot, 1);
+    assert_eq!(new_storage.expires_at("short").await.unwrap(), None);
+    assert_eq!(
+        new_storage.expires_at("long").await.unwrap(),
+        Some(Some(now + 60_000))
+    );
+    assert_eq!(new_storage.expires_at("plain").await.unwrap(), Some(None));
+}
 
-    // Verify recovery
-    assert!(stats.snapshot_loaded || stats.aof_operations_replayed > 0);
+#[tokio::test]
+async fn test_snapshot_formats_are_detected_on_load() {
+    let temp_dir = tempdir().unwrap();
+    let bincode = Snapshotter::new(temp_dir.path().join("bincode")).unwrap();
+    let json = Snapshotter::new(temp_dir.path().join("json"))
+        .unwrap()
+        .with_format(&JsonFormat);
 
-    let value1 = new_storage.get("key1").await.unwrap();
-    assert_eq!(value1, Some(b"value1".to_vec()));
+    let mut data = HashMap::new();
+    data.insert("plain".to_string(), (b"value".to_vec(), None));
+    data.insert(
+        "expiring".to_string(),
+        (vec![0, 255], Some(now_millis() + 60_000)),
+    );
+
+    let json_path = json.create_snapshot(data.clone()).await.unwrap();
+    let text = std::fs::read_to_string(&json_path).unwrap();
+    let document: serde_json::Value = serde_json::from_str(&text).unwrap();
+    assert_eq!(document["metadata"]["total_keys"], 2);
+
+    // Either snapshotter reads both formats, whichever it writes
+    let bincode_path = bincode.create_snapshot(data.clone()).await.unwrap();
+    for path in [&json_path, &bincode_path] {
+        for snapshotter in [&bincode, &json] {
+            let snapshot = snapshotter.load_snapshot(path).await.unwrap();
+            assert_eq!(snapshot.data, data);
+            assert!(snapshot.verify().unwrap());
+        }
+    }
+
+    // A JSON file claiming another layout isn't guessed at
+    std::fs::write(
+        &json_path,
+        text.replace(
+            &format!("\"format_version\": {}", SNAPSHOT_FORMAT_VERSION),
+            "\"format_version\": 99",
+        ),
+    )
+    .unwrap();
+    let err = json.load_snapshot(&json_path).await.unwrap_err();
+    assert!(
+        err.to_string()
+            .contains("unsupported snapshot format version 99")
+    );
+}
+
+#[tokio::test]
+async fn test_snapshot_retention_ignores_clock_skew() {
+    let temp_dir = tempdir().unwrap();
+    let snapshotter = Snapshotter::new(temp_dir.path())
+        .unwrap()
+        .with_format(&JsonFormat);
+
+    // Pretend the clock has since jumped back: every snapshot so far looks newer than the next
+    let mut paths = Vec::new();
+    for i in 0..5 {
+        let mut data = HashMap::new();
+        data.insert(format!("key{}", i), (b"value".to_vec(), None));
+        let path = snapshotter.create_snapshot(data).await.unwrap();
+
+        let mut document: serde_json::Value =
+            serde_json::from_slice(&std::fs::read(&path).unwrap()).unwrap();
+        assert_eq!(document["metadata"]["sequence"], i + 1);
+        document["metadata"]["timestamp"] = "2099-01-01T00:00:00Z".into();
+        std::fs::write(&path, serde_json::to_vec(&document).unwrap()).unwrap();
+        paths.push(path);
+    }
+
+    // Retention drops the lowest sequence, not the snapshot with the earliest timestamp
+    let newest = snapshotter.create_snapshot(HashMap::new()).await.unwrap();
+    assert!(newest.exists());
+    assert!(!paths[0].exists());
+
+    let mut sequences: Vec<u64> = snapshotter
+        .list_snapshots()
+        .await
+        .unwrap()
+        .iter()
+        .map(|metadata| metadata.sequence)
+        .collect();
+    sequences.sort();
+    assert_eq!(sequences, [2, 3, 4, 5, 6]);
+
+    // A fresh snapshotter on the same directory carries on from the files
+    let reopened = Snapshotter::new(temp_dir.path()).unwrap();
+    let path = reopened.create_snapshot(HashMap::new()).await.unwrap();
+    assert_eq!(
+        reopened
+            .load_snapshot(&path)
+            .await
+            .unwrap()
+            .metadata
+            .sequence,
+        7
+    );
+
+    // Format 3 files have no sequence and order before every newer snapshot
+    let mut document: serde_json::Value =
+        serde_json::from_slice(&std::fs::read(&newest).unwrap()).unwrap();
+    document["metadata"]["format_version"] = 3.into();
+    document["metadata"]
+        .as_object_mut()
+        .unwrap()
+        .remove("sequence");
+    std::fs::write(&newest, serde_json::to_vec(&document).unwrap()).unwrap();
+    let legacy = snapshotter.load_snapshot(&newest).await.unwrap();
+    assert_eq!(legacy.metadata.sequence, 0);
+    assert_eq!(legacy.metadata.format_version, SNAPSHOT_FORMAT_VERSION);
+}
+
+#[tokio::test]
+async fn test_snapshot_unsupported_format_version() {
+    let temp_dir = tempdir().unwrap();
+    let snapshotter = Snapshotter::new(temp_dir.path()).unwrap();
+
+    let snapshot_path = snapshotter.create_snapshot(HashMap::new()).await.unwrap();
+
+    // Pretend a newer release wrote the file: the version follows the 4-byte magic
+    let mut bytes = std::fs::read(&snapshot_path).unwrap();
+    bytes[4..8].copy_from_slice(&99u32.to_le_bytes());
+    std::fs::write(&snapshot_path, bytes).unwrap();
+
+    let err = snapshotter.load_snapshot(&snapshot_path).await.unwrap_err();
+    assert!(
+        err.to_string()
+            .contains("unsupported snapshot format version 99")
+    );
+}
+
+#[tokio::test]
+async fn test_snapshot_verify_command() {
+    let temp_dir = tempdir().unwrap();
+    let snapshot_dir = temp_dir.path().join("snapshots");
+
+    let config = PersistenceConfig {
+        enabled: false,
+        aof_path: temp_dir.path().join("test.aof"),
+        fsync_policy: FsyncPolicy::Always,
+        aof_rewrite_percentage: 0,
+        aof_rewrite_min_size: 0,
+        snapshot_dir: snapshot_dir.clone(),
+        ..Default::default()
+    };
+
+    let storage = Arc::new(MemoryEngine::new(StorageConfig::default())) as Arc<dyn StorageEngine>;
+    let manager = Arc::new(
+        PersistenceManager::new(config, storage.clone())
+            .await
+            .unwrap(),
+    );
+    let dispatcher = CommandDispatcher::new(storage.clone()).with_persistence(manager.clone());
+
+    storage.set("key1", b"value1".to_vec()).await.unwrap();
+    storage.set("key2", b"value2".to_vec()).await.unwrap();
+    manager.create_snapshot().await.unwrap();
+
+    // Latest snapshot
+    let response = dispatcher
+        .execute(Command::Snapshot(SnapshotCommand::verify(None)))
+        .await;
+    let CommandResponse::Value(report) = response else {
+        panic!("unexpected response: {:?}", response);
+    };
+    let report = String::from_utf8(report).unwrap();
+    assert!(report.contains("keys=2"), "{}", report);
+    assert!(report.contains("size=20"), "{}", report);
+    assert!(report.contains("checksum=ok"), "{}", report);
+
+    // A specific snapshot file, by name
+    let name = std::fs::read_dir(&snapshot_dir)
+        .unwrap()
+        .map(|entry| entry.unwrap().file_name().into_string().unwrap())
+        .find(|name| name != "snapshot-latest.rdb")
+        .unwrap();
+    let response = dispatcher
+        .execute(Command::Snapshot(SnapshotCommand::verify(Some(
+            name.clone(),
+        ))))
+        .await;
+    assert!(matches!(response, CommandResponse::Value(_)));
+
+    // Paths outside the snapshot directory are refused
+    let response = dispatcher
+        .execute(Command::Snapshot(SnapshotCommand::verify(Some(
+            "../test.aof".to_string(),
+        ))))
+        .await;
+    assert!(matches!(response, CommandResponse::Error(_)));
+
+    // Corrupt a value byte: still decodes, but no longer matches the checksum
+    let latest = snapshot_dir.join("snapshot-latest.rdb");
+    let mut bytes = std::fs::read(&latest).unwrap();
+    let at = bytes.windows(6).position(|w| w == b"value1").unwrap();
+    bytes[at + 5] = b'X';
+    std::fs::write(&latest, bytes).unwrap();
+
+    let response = dispatcher
+        .execute(Command::Snapshot(SnapshotCommand::verify(None)))
+        .await;
+    let CommandResponse::Error(message) = response else {
+        panic!("corruption not detected: {:?}", response);
+    };
+    assert!(message.contains("checksum mismatch"), "{}", message);
+
+    // The live store was never touched
+    assert_eq!(storage.stats().await.unwrap().total_keys, 2);
+    assert_eq!(storage.get("key1").await.unwrap(), Some(b"value1".to_vec()));
+}
+
+#[tokio::test]
+async fn test_recovery_with_snapshot_and_aof() {
+    let temp_dir = tempdir().unwrap();
+    let aof_path = temp_dir.path().join("test.aof");
+    let snapshot_dir = temp_dir.path().join("snapshots");
+
+    // Setup
+    let config = StorageConfig::default();
+    let storage = Arc::new(MemoryEngine::new(config));
+    let aof = AppendOnlyFile::new(&aof_path).await.unwrap();
+
+    // Create snapshot with initial data
+    storage.set("key1", b"value1".to_vec()).await.unwrap();
+
+    let snapshotter = Snapshotter::new(&snapshot_dir).unwrap();
+    let mut data = HashMap::new();
+    data.insert("key1".to_string(), (b"value1".to_vec(), None));
+
+    snapshotter.create_snapshot(data).await.unwrap();
+
+    // Clear storage
+    storage.delete("key1").await.unwrap();
+
+    // Recovery should restore from snapshot
+    let recovery = RecoveryManager::new(Some(aof), Some(snapshotter));
+    let stats = recovery.recover(storage.as_ref()).await.unwrap();
+
+    println!("stats: {:?}", stats);
+    assert!(stats.snapshot_loaded);
+    assert_eq!(stats.keys_from_snapshot, 1);
+
+    // Verify data restored
+    let result = storage.get("key1").await.unwrap();
+    assert_eq!(result, Some(b"value1".to_vec()))
+}
+
+#[tokio::test]
+async fn test_recovery_falls_back_to_aof_on_corrupt_snapshot() {
+    let temp_dir = tempdir().unwrap();
+    let aof_path = temp_dir.path().join("test.aof");
+    let snapshot_dir = temp_dir.path().join("snapshots");
+
+    let mut aof = AppendOnlyFile::new(&aof_path).await.unwrap();
+    aof.log_operation_sync(Operation::Put {
+        key: "key1".to_string(),
+        value: b"value1".to_vec(),
+    })
+    .await
+    .unwrap();
+
+    let snapshotter = Snapshotter::new(&snapshot_dir).unwrap();
+    std::fs::write(snapshot_dir.join("snapshot-latest.rdb"), b"not a snapshot").unwrap();
+
+    let storage = MemoryEngine::new(StorageConfig::default());
+    let recovery = RecoveryManager::new(Some(aof), Some(snapshotter.clone()));
+    let stats = recovery.recover(&storage).await.unwrap();
+
+    assert!(!stats.snapshot_loaded);
+    assert!(stats.snapshot_error.is_some());
+    assert_eq!(stats.aof_operations_replayed, 1);
+    assert_eq!(storage.get("key1").await.unwrap(), Some(b"value1".to_vec()));
+
+    // Strict mode keeps the old behaviour
+    let recovery = RecoveryManager::new(
+        Some(AppendOnlyFile::new(&aof_path).await.unwrap()),
+        Some(snapshotter),
+    )
+    .with_snapshot_required(true);
+    let storage = MemoryEngine::new(StorageConfig::default());
+    assert!(recovery.recover(&storage).await.is_err());
+}
+
+#[tokio::test]
+async fn test_persistence_manager_initialization() {
+    let temp_dir = tempdir().unwrap();
+
+    let config = PersistenceConfig {
+        aof_path: temp_dir.path().join("test.aof"),
+        aof_rewrite_percentage: 0,
+        aof_rewrite_min_size: 0,
+        snapshot_dir: temp_dir.path().join("snapshots"),
+        ..Default::default()
+    };
+
+    let storage_config = StorageConfig::default();
+    let storage = Arc::new(MemoryEngine::new(storage_config)) as Arc<dyn StorageEngine>;
+
+    let manager = PersistenceManager::new(config, storage).await.unwrap();
+
+    assert!(manager.aof.is_some());
+    assert!(manager.snapshotter.is_some());
+}
+
+#[tokio::test]
+async fn test_full_persistence_cycle() {
+    let temp_dir = tempdir().unwrap();
+
+    let config = PersistenceConfig {
+        aof_path: temp_dir.path().join("test.aof"),
+        fsync_policy: FsyncPolicy::Always,
+        aof_rewrite_percentage: 0,
+        aof_rewrite_min_size: 0,
+        snapshot_dir: temp_dir.path().join("snapshots"),
+        ..Default::default()
+    };
+
+    let storage_config = StorageConfig::default();
+    let storage = Arc::new(MemoryEngine::new(storage_config)) as Arc<dyn StorageEngine>;
+
+    let manager = Arc::new(
+        PersistenceManager::new(config.clone(), storage.clone())
+            .await
+            .unwrap(),
+    );
+
+    storage.set("key1", b"value1".to_vec()).await.unwrap();
+    manager
+        .log_operation(Operation::Put {
+            key: "key1".to_string(),
+            value: b"value1".to_vec(),
+        })
+        .await
+        .unwrap();
+
+    storage.set("key2", b"value2".to_vec()).await.unwrap();
+    manager
+        .log_operation(Operation::Put {
+            key: "key2".to_string(),
+            value: b"value2".to_vec(),
+        })
+        .await
+        .unwrap();
+
+    manager.create_snapshot().await.unwrap();
+
+    storage.delete("key1").await.unwrap();
+    storage.delete("key2").await.unwrap();
+
+    // Recover
+    let new_storage =
+        Arc::new(MemoryEngine::new(StorageConfig::default())) as Arc<dyn StorageEngine>;
+    let new_manager = PersistenceManager::new(config, new_storage.clone())
+        .await
+        .unwrap();
+
+    let stats = new_manager.recover().await.unwrap();
+
+    // Verify recovery
+    assert!(stats.snapshot_loaded || stats.aof_operations_replayed > 0);
+
+    let value1 = new_storage.get("key1").await.unwrap();
+    assert_eq!(value1, Some(b"value1".to_vec()));
+}
+
+#[cfg(target_os = "linux")]
+#[tokio::test]
+async fn test_aof_write_failure_is_reported() {
+    // Every write to /dev/full fails with ENOSPC
+    let mut aof = AppendOnlyFile::new("/dev/full").await.unwrap();
+    aof.fsync_every = 0;
+    let errors = aof.errors();
+    aof.start_background_writer().await;
+
+    aof.log_operation(Operation::Put {
+        key: "key1".to_string(),
+        value: b"value1".to_vec(),
+    })
+    .await
+    .unwrap();
+
+    let failure = tokio::time::timeout(std::time::Duration::from_secs(1), errors.recv_async())
+        .await
+        .expect("expected a write failure notification")
+        .unwrap();
+
+    assert!(matches!(failure.operation, Operation::Put { ref key, .. } if key == "key1"));
+    assert!(aof.is_failed());
+    assert!(aof.stats().failed);
+
+    // Further writes are refused instead of silently dropped
+    let result = aof
+        .log_operation(Operation::Delete {
+            key: "key1".to_string(),
+        })
+        .await;
+    assert!(result.is_err());
+}
+
+#[tokio::test]
+async fn test_aof_recreates_removed_file() {
+    let temp_dir = tempdir().unwrap();
+    let aof_dir = temp_dir.path().join("aof");
+    let aof_path = aof_dir.join("test.aof");
+    std::fs::create_dir(&aof_dir).unwrap();
+
+    let mut aof = AppendOnlyFile::new(&aof_path).await.unwrap();
+    aof.fsync_every = 0;
+    let growth = aof.growth();
+    aof.start_background_writer().await;
+
+    aof.log_operation(Operation::Delete {
+        key: "before".to_string(),
+    })
+    .await
+    .unwrap();
+    aof.sync().await.unwrap();
+
+    // The writer only looks for its file once a second
+    std::fs::remove_dir_all(&aof_dir).unwrap();
+    tokio::time::sleep(Duration::from_millis(1100)).await;
+
+    aof.log_operation(Operation::Delete {
+        key: "after".to_string(),
+    })
+    .await
+    .unwrap();
+    aof.sync().await.unwrap();
+
+    assert!(!aof.is_failed());
+    let ops = aof.read_operations().await.unwrap();
+    assert_eq!(ops.len(), 1);
+    assert!(matches!(&ops[0], Operation::Delete { key } if key == "after"));
+
+    // Everything before the removal has to come back through a rewrite
+    assert!(growth.try_recv().is_ok());
+}
+
+#[tokio::test]
+async fn test_aof_failed_recreate_is_reported() {
+    let temp_dir = tempdir().unwrap();
+    let aof_dir = temp_dir.path().join("aof");
+    let aof_path = aof_dir.join("test.aof");
+    std::fs::create_dir(&aof_dir).unwrap();
+
+    let mut aof = AppendOnlyFile::new(&aof_path).await.unwrap();
+    aof.fsync_every = 0;
+    aof.start_background_writer().await;
+
+    // A plain file where the directory was keeps it from being recreated
+    std::fs::remove_dir_all(&aof_dir).unwrap();
+    std::fs::write(&aof_dir, b"").unwrap();
+    tokio::time::sleep(Duration::from_millis(1100)).await;
+
+    aof.log_operation(Operation::Delete {
+        key: "lost".to_string(),
+    })
+    .await
+    .unwrap();
+    let _ = aof.sync().await;
+
+    assert!(aof.is_failed());
+}
+
+#[tokio::test]
+async fn test_aof_group_commit_shares_fsyncs() {
+    let temp_dir = tempdir().unwrap();
+
+    // fsync after every operation, with and without a group commit window
+    let mut fsyncs = Vec::new();
+    for (name, window) in [
+        ("single", Duration::ZERO),
+        ("group", Duration::from_millis(50)),
+    ] {
+        let aof_path = temp_dir.path().join(format!("{}.aof", name));
+        let mut aof = AppendOnlyFile::new(&aof_path).await.unwrap();
+        aof.fsync_every = 0;
+        aof.group_commit_window = window;
+        aof.start_background_writer().await;
+
+        for i in 0..200 {
+            aof.log_operation(Operation::Put {
+                key: format!("key{}", i),
+                value: b"value".to_vec(),
+            })
+            .await
+            .unwrap();
+        }
+        aof.sync().await.unwrap();
+
+        assert_eq!(aof.read_operations().await.unwrap().len(), 200);
+        assert_eq!(aof.stats().operations_logged, 200);
+        fsyncs.push(aof.stats().fsyncs);
+    }
+
+    assert!(fsyncs[0] >= 200, "{:?}", fsyncs);
+    assert!(fsyncs[1] < 20, "{:?}", fsyncs);
+}
+
+#[tokio::test]
+async fn test_aof_compaction_keeps_concurrent_writes() {
+    let temp_dir = tempdir().unwrap();
+    let aof_path = temp_dir.path().join("test.aof");
+
+    let mut aof = AppendOnlyFile::new(&aof_path).await.unwrap();
+    aof.fsync_every = 0;
+    aof.start_background_writer().await;
+
+    let put = |key: &str, value: &[u8]| Operation::Put {
+        key: key.to_string(),
+        value: value.to_vec(),
+    };
+
+    aof.log_operation(put("key1", b"old")).await.unwrap();
+    aof.log_operation(put("key1", b"value1")).await.unwrap();
+
+    aof.begin_rewrite().await.unwrap();
+
+    // Logged after the marker but missing from the compacted state
+    aof.log_operation(put("key2", b"value2")).await.unwrap();
+
+    let state: EntryStream = Box::pin(futures_util::stream::iter(vec![Ok((
+        "key1".to_string(),
+        b"value1".to_vec(),
+    ))]));
+    aof.complete_rewrite(state).await.unwrap();
+
+    // Written to the new file, not the replaced one
+    aof.log_operation(put("key3", b"value3")).await.unwrap();
+    aof.sync().await.unwrap();
+
+    let keys: Vec<(String, Vec<u8>)> = aof
+        .read_operations()
+        .await
+        .unwrap()
+        .into_iter()
+        .filter_map(|op| match op {
+            Operation::Put { key, value } => Some((key, value)),
+            Operation::Flush => None,
+            other => panic!("Unexpected operation: {:?}", other),
+        })
+        .collect();
+
+    assert_eq!(
+        keys,
+        vec![
+            ("key1".to_string(), b"value1".to_vec()),
+            ("key2".to_string(), b"value2".to_vec()),
+            ("key3".to_string(), b"value3".to_vec()),
+        ]
+    );
+}
+
+#[tokio::test]
+async fn test_writes_after_compaction_are_recovered() {
+    let temp_dir = tempdir().unwrap();
+
+    let config = PersistenceConfig {
+        aof_path: temp_dir.path().join("test.aof"),
+        fsync_policy: FsyncPolicy::Always,
+        aof_rewrite_percentage: 0,
+        aof_rewrite_min_size: 0,
+        snapshot_dir: temp_dir.path().join("snapshots"),
+        ..Default::default()
+    };
+
+    let storage = Arc::new(MemoryEngine::new(StorageConfig::default())) as Arc<dyn StorageEngine>;
+    let manager = Arc::new(
+        PersistenceManager::new(config.clone(), storage.clone())
+            .await
+            .unwrap(),
+    );
+    let dispatcher = CommandDispatcher::new(storage.clone()).with_persistence(manager.clone());
+
+    dispatcher
+        .execute(Command::Set(SetCommand::new(
+            "key1".to_string(),
+            b"value1".to_vec(),
+        )))
+        .await;
+
+    // Snapshot compacts the AOF
+    manager.create_snapshot().await.unwrap();
+
+    dispatcher
+        .execute(Command::Set(SetCommand::new(
+            "key2".to_string(),
+            b"value2".to_vec(),
+        )))
+        .await;
+    manager.sync_aof().await.unwrap();
+
+    // Recover from the AOF alone
+    std::fs::remove_dir_all(temp_dir.path().join("snapshots")).unwrap();
+    let recovery_config = PersistenceConfig {
+        snapshot_enabled: false,
+        ..config
+    };
+
+    let new_storage =
+        Arc::new(MemoryEngine::new(StorageConfig::default())) as Arc<dyn StorageEngine>;
+    let new_manager = PersistenceManager::new(recovery_config, new_storage.clone())
+        .await
+        .unwrap();
+    new_manager.recover().await.unwrap();
+
+    assert_eq!(
+        new_storage.get("key1").await.unwrap(),
+        Some(b"value1".to_vec())
+    );
+    assert_eq!(
+        new_storage.get("key2").await.unwrap(),
+        Some(b"value2".to_vec())
+    );
+}
+
+#[tokio::test]
+async fn test_persistence_stop_releases_storage() {
+    let temp_dir = tempdir().unwrap();
+
+    let config = PersistenceConfig {
+        aof_path: temp_dir.path().join("test.aof"),
+        aof_rewrite_percentage: 0,
+        aof_rewrite_min_size: 0,
+        snapshot_dir: temp_dir.path().join("snapshots"),
+        ..Default::default()
+    };
+
+    let storage = Arc::new(MemoryEngine::new(StorageConfig::default())) as Arc<dyn StorageEngine>;
+    let manager = Arc::new(
+        PersistenceManager::new(config, storage.clone())
+            .await
+            .unwrap(),
+    );
+
+    manager.clone().start_background_snapshots();
+    manager.stop().await.unwrap();
+    assert!(manager.is_stopped());
+
+    // Restarting after stop is a no-op
+    manager.clone().start_background_snapshots();
+
+    // No background task kept a reference to the manager or the storage
+    assert_eq!(Arc::strong_count(&manager), 1);
+    drop(manager);
+    assert_eq!(Arc::strong_count(&storage), 1);
+}
+
+#[tokio::test]
+async fn test_stop_fsyncs_aof_regardless_of_policy() {
+    for fsync_on_shutdown in [true, false] {
+        let temp_dir = tempdir().unwrap();
+
+        let config = PersistenceConfig {
+            aof_path: temp_dir.path().join("test.aof"),
+            fsync_policy: FsyncPolicy::Never,
+            fsync_on_shutdown,
+            aof_rewrite_percentage: 0,
+            aof_rewrite_min_size: 0,
+            snapshot_enabled: false,
+            snapshot_dir: temp_dir.path().join("snapshots"),
+            ..Default::default()
+        };
+
+        let storage =
+            Arc::new(MemoryEngine::new(StorageConfig::default())) as Arc<dyn StorageEngine>;
+        let manager = Arc::new(
+            PersistenceManager::new(config.clone(), storage.clone())
+                .await
+                .unwrap(),
+        );
+        let dispatcher = CommandDispatcher::new(storage).with_persistence(manager.clone());
+
+        for i in 0..3 {
+            dispatcher
+                .execute(Command::Set(SetCommand::new(
+                    format!("key:{}", i),
+                    b"value".to_vec(),
+                )))
+                .await;
+        }
+        manager.stop().await.unwrap();
+
+        // The policy alone never fsyncs, only the shutdown does
+        let fsyncs = manager.stats().await.aof_stats.unwrap().fsyncs;
+        assert_eq!(fsyncs, u64::from(fsync_on_shutdown));
+
+        // Flushed either way, so the writes outlive the process
+        let new_storage =
+            Arc::new(MemoryEngine::new(StorageConfig::default())) as Arc<dyn StorageEngine>;
+        PersistenceManager::new(config, new_storage.clone())
+            .await
+            .unwrap()
+            .recover()
+            .await
+            .unwrap();
+        assert_eq!(new_storage.stats().await.unwrap().total_keys, 3);
+    }
+}
+
+#[tokio::test]
+async fn test_background_snapshots_do_not_keep_manager_alive() {
+    let temp_dir = tempdir().unwrap();
+
+    let config = PersistenceConfig {
+        enabled: false,
+        aof_path: temp_dir.path().join("test.aof"),
+        fsync_policy: FsyncPolicy::Always,
+        aof_rewrite_percentage: 0,
+        aof_rewrite_min_size: 0,
+        snapshot_dir: temp_dir.path().join("snapshots"),
+        ..Default::default()
+    };
+
+    let storage = Arc::new(MemoryEngine::new(StorageConfig::default())) as Arc<dyn StorageEngine>;
+    let manager = Arc::new(
+        PersistenceManager::new(config, storage.clone())
+            .await
+            .unwrap(),
+    );
+    manager.clone().start_background_snapshots();
+
+    // Dropped without stop(): the task notices and lets go of everything
+    drop(manager);
+    for _ in 0..100 {
+        if Arc::strong_count(&storage) == 1 {
+            return;
+        }
+        tokio::time::sleep(std::time::Duration::from_millis(10)).await;
+    }
+    panic!("storage still referenced: {}", Arc::strong_count(&storage));
+}
+
+#[tokio::test]
+async fn test_aof_read_operations_from_offset() {
+    let temp_dir = tempdir().unwrap();
+    let aof_path = temp_dir.path().join("test.aof");
+
+    let mut aof = AppendOnlyFile::new(&aof_path).await.unwrap();
+    for i in 0..3 {
+        aof.log_operation_sync(Operation::Put {
+            key: format!("key{}", i),
+            value: b"value".to_vec(),
+        })
+        .await
+        .unwrap();
+    }
+
+    let entries: Vec<(Operation, u64)> = aof
+        .read_operations_from(0)
+        .await
+        .unwrap()
+        .try_collect()
+        .await
+        .unwrap();
+    assert_eq!(entries.len(), 3);
+    let end = std::fs::metadata(&aof_path).unwrap().len();
+    assert_eq!(entries[2].1, end);
+
+    // Resuming from a yielded offset skips everything before it
+    let rest: Vec<(Operation, u64)> = aof
+        .read_operations_from(entries[0].1)
+        .await
+        .unwrap()
+        .try_collect()
+        .await
+        .unwrap();
+    let keys: Vec<_> = rest
+        .iter()
+        .map(|(op, _)| match op {
+            Operation::Put { key, .. } => key.clone(),
+            other => panic!("Expected Put, got {:?}", other),
+        })
+        .collect();
+    assert_eq!(keys, vec!["key1".to_string(), "key2".to_string()]);
+
+    assert!(aof.read_operations_from(entries[0].1 - 1).await.is_err());
+    assert!(aof.read_operations_from(end + 1).await.is_err());
+}
+
+#[tokio::test]
+async fn test_aof_read_operations_from_growing_file() {
+    use std::io::Write;
+
+    let temp_dir = tempdir().unwrap();
+    let aof_path = temp_dir.path().join("test.aof");
+
+    let mut aof = AppendOnlyFile::new(&aof_path).await.unwrap();
+    aof.log_operation_sync(Operation::Delete {
+        key: "key0".to_string(),
+    })
+    .await
+    .unwrap();
+
+    // Simulate an entry caught halfway through being written
+    let mut file = std::fs::OpenOptions::new()
+        .append(true)
+        .open(&aof_path)
+        .unwrap();
+    file.write_all(b"DEL ke").unwrap();
+
+    let entries: Vec<(Operation, u64)> = aof
+        .read_operations_from(0)
+        .await
+        .unwrap()
+        .try_collect()
+        .await
+        .unwrap();
+    assert_eq!(entries.len(), 1);
+    let resume_at = entries[0].1;
+
+    file.write_all(b"y1\n").unwrap();
+
+    let entries: Vec<(Operation, u64)> = aof
+        .read_operations_from(resume_at)
+        .await
+        .unwrap()
+        .try_collect()
+        .await
+        .unwrap();
+    match entries.as_slice() {
+        [(Operation::Delete { key }, end)] => {
+            assert_eq!(key, "key1");
+            assert_eq!(*end, std::fs::metadata(&aof_path).unwrap().len());
+        }
+        other => panic!("Expected a single Delete, got {:?}", other),
+    }
+}
+
+#[tokio::test]
+async fn test_aof_drop_flushes_unsynced_writes() {
+    let temp_dir = tempdir().unwrap();
+    let aof_path = temp_dir.path().join("test.aof");
+
+    // A lax fsync policy keeps everything in the userspace buffer
+    let mut aof = AppendOnlyFile::new(&aof_path).await.unwrap();
+    aof.fsync_every = 1000;
+    for i in 0..3 {
+        aof.log_operation_sync(Operation::Delete {
+            key: format!("key{}", i),
+        })
+        .await
+        .unwrap();
+    }
+    drop(aof);
+
+    let aof = AppendOnlyFile::new(&aof_path).await.unwrap();
+    assert_eq!(aof.read_operations().await.unwrap().len(), 3);
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn test_aof_drop_drains_background_writer() {
+    let temp_dir = tempdir().unwrap();
+    let aof_path = temp_dir.path().join("test.aof");
+
+    let mut aof = AppendOnlyFile::new(&aof_path).await.unwrap();
+    aof.fsync_every = 1000;
+    aof.start_background_writer().await;
+    for i in 0..100 {
+        aof.log_operation(Operation::Delete {
+            key: format!("key{}", i),
+        })
+        .await
+        .unwrap();
+    }
+
+    aof.flush_blocking(std::time::Duration::from_secs(5))
+        .unwrap();
+    for i in 100..200 {
+        aof.log_operation(Operation::Delete {
+            key: format!("key{}", i),
+        })
+        .await
+        .unwrap();
+    }
+
+    // Queued but unflushed writes are on disk once drop returns
+    drop(aof);
+
+    let aof = AppendOnlyFile::new(&aof_path).await.unwrap();
+    assert_eq!(aof.read_operations().await.unwrap().len(), 200);
+}
+
+#[tokio::test]
+async fn test_aof_flush_blocking_refuses_current_thread_runtime() {
+    let temp_dir = tempdir().unwrap();
+
+    let mut aof = AppendOnlyFile::new(temp_dir.path().join("test.aof"))
+        .await
+        .unwrap();
+    assert!(
+        aof.flush_blocking(std::time::Duration::from_secs(1))
+            .is_err()
+    );
+
+    // Blocking here would starve the writer sharing this thread
+    aof.start_background_writer().await;
+    assert!(
+        aof.flush_blocking(std::time::Duration::from_secs(1))
+            .is_err()
+    );
+    aof.sync().await.unwrap();
+}
+
+#[tokio::test]
+async fn test_aof_rotates_by_size() {
+    let temp_dir = tempdir().unwrap();
+    let aof_path = temp_dir.path().join("test.aof");
+
+    let mut aof = AppendOnlyFile::new(&aof_path).await.unwrap();
+    aof.max_size = 64;
+    aof.start_background_writer().await;
+    let rotations = aof.rotations();
+
+    let keys: Vec<String> = (0..20).map(|i| format!("key{}", i)).collect();
+    for key in &keys {
+        aof.log_operation(Operation::Put {
+            key: key.clone(),
+            value: b"value".to_vec(),
+        })
+        .await
+        .unwrap();
+    }
+    aof.sync().await.unwrap();
+
+    let stats = aof.stats();
+    assert!(stats.current_segment >= 2);
+    assert!(stats.file_size_bytes < 64);
+    assert!(temp_dir.path().join("test.aof.1").exists());
+
+    let on_disk: u64 = std::fs::read_dir(temp_dir.path())
+        .unwrap()
+        .map(|entry| entry.unwrap().metadata().unwrap().len())
+        .sum();
+    assert_eq!(stats.total_size_bytes, on_disk);
+
+    // Pending notices coalesce, the first one is kept
+    assert_eq!(rotations.try_recv().unwrap(), 1);
+
+    // Segments are read oldest first, ahead of the live file
+    let read = |operations: Vec<Operation>| -> Vec<String> {
+        operations
+            .into_iter()
+            .map(|operation| match operation {
+                Operation::Put { key, .. } => key,
+                other => panic!("unexpected operation {:?}", other),
+            })
+            .collect()
+    };
+    assert_eq!(read(aof.read_operations().await.unwrap()), keys);
+
+    // A reopened AOF picks the segments back up
+    drop(aof);
+    let reopened = AppendOnlyFile::new(&aof_path).await.unwrap();
+    assert_eq!(reopened.stats().current_segment, stats.current_segment);
+    assert_eq!(reopened.stats().total_size_bytes, on_disk);
+    assert_eq!(read(reopened.read_operations().await.unwrap()), keys);
+}
+
+#[tokio::test]
+async fn test_aof_rotation_triggers_compaction() {
+    let temp_dir = tempdir().unwrap();
+    let aof_path = temp_dir.path().join("test.aof");
+
+    let config = PersistenceConfig {
+        aof_path: aof_path.clone(),
+        fsync_policy: FsyncPolicy::Never,
+        max_aof_size: 256,
+        aof_rewrite_percentage: 0,
+        aof_rewrite_min_size: 0,
+        snapshot_enabled: false,
+        snapshot_dir: temp_dir.path().join("snapshots"),
+        ..Default::default()
+    };
+
+    let storage = Arc::new(MemoryEngine::new(StorageConfig::default())) as Arc<dyn StorageEngine>;
+    let manager = Arc::new(
+        PersistenceManager::new(config, storage.clone())
+            .await
+            .unwrap(),
+    );
+    manager.clone().start_rotation_compaction().await;
+
+    // Few keys, many overwrites: compaction shrinks this a lot
+    for i in 0..200 {
+        let key = format!("key{}", i % 5);
+        let value = format!("value{}", i).into_bytes();
+        manager
+            .log_operation(Operation::Put {
+                key: key.clone(),
+                value: value.clone(),
+            })
+            .await
+            .unwrap();
+        storage.set(&key, value).await.unwrap();
+    }
+    manager.sync_aof().await.unwrap();
+
+    let mut compacted = false;
+    for _ in 0..200 {
+        let stats = manager.stats().await;
+        if stats.aof_rewrites_completed > 0 && !stats.aof_rewrite_in_progress {
+            compacted = true;
+            break;
+        }
+        tokio::time::sleep(std::time::Duration::from_millis(10)).await;
+    }
+    assert!(compacted);
+    manager.stop().await.unwrap();
+
+    // Well under the ~4KB that was logged
+    let aof_stats = manager.stats().await.aof_stats.unwrap();
+    assert!(aof_stats.total_size_bytes < 1024);
+
+    let recovered = MemoryEngine::new(StorageConfig::default());
+    let recovery = RecoveryManager::new(Some(AppendOnlyFile::new(&aof_path).await.unwrap()), None);
+    recovery.recover(&recovered).await.unwrap();
+
+    for i in 195..200 {
+        assert_eq!(
+            recovered.get(&format!("key{}", i % 5)).await.unwrap(),
+            Some(format!("value{}", i).into_bytes())
+        );
+    }
+}
+
+#[tokio::test]
+async fn test_aof_growth_triggers_compaction() {
+    let temp_dir = tempdir().unwrap();
+    let aof_path = temp_dir.path().join("test.aof");
+
+    let config = PersistenceConfig {
+        aof_path: aof_path.clone(),
+        fsync_policy: FsyncPolicy::Never,
+        aof_rewrite_min_size: 512,
+        snapshot_enabled: false,
+        snapshot_dir: temp_dir.path().join("snapshots"),
+        ..Default::default()
+    };
+
+    let storage = Arc::new(MemoryEngine::new(StorageConfig::default())) as Arc<dyn StorageEngine>;
+    let manager = Arc::new(
+        PersistenceManager::new(config, storage.clone())
+            .await
+            .unwrap(),
+    );
+    manager.clone().start_growth_compaction().await;
+    assert_eq!(
+        manager
+            .stats()
+            .await
+            .aof_stats
+            .unwrap()
+            .last_rewrite_size_bytes,
+        0
+    );
+
+    // Few keys, many overwrites: well past the minimum size, and compacts to a fraction
+    for i in 0..100 {
+        let key = format!("key{}", i % 5);
+        let value = format!("value{}", i).into_bytes();
+        manager
+            .log_operation(Operation::Put {
+                key: key.clone(),
+                value: value.clone(),
+            })
+            .await
+            .unwrap();
+        storage.set(&key, value).await.unwrap();
+    }
+    manager.sync_aof().await.unwrap();
+
+    let mut compacted = false;
+    for _ in 0..500 {
+        let stats = manager.stats().await;
+        if stats.aof_rewrites_completed > 0 && !stats.aof_rewrite_in_progress {
+            compacted = true;
+            break;
+        }
+        tokio::time::sleep(std::time::Duration::from_millis(10)).await;
+    }
+    assert!(compacted);
+
+    // The rewritten size becomes the new base growth is measured from
+    let aof_stats = manager.stats().await.aof_stats.unwrap();
+    assert!(aof_stats.last_rewrite_size_bytes > 0);
+    assert!(aof_stats.last_rewrite_size_bytes < 512);
+    assert!(aof_stats.total_size_bytes < 1024);
+    manager.stop().await.unwrap();
+}
+
+#[tokio::test]
+async fn test_failed_writes_are_not_logged() {
+    let temp_dir = tempdir().unwrap();
+
+    let config = PersistenceConfig {
+        aof_path: temp_dir.path().join("test.aof"),
+        fsync_policy: FsyncPolicy::Always,
+        aof_rewrite_percentage: 0,
+        aof_rewrite_min_size: 0,
+        snapshot_enabled: false,
+        snapshot_dir: temp_dir.path().join("snapshots"),
+        ..Default::default()
+    };
+
+    let storage = Arc::new(MemoryEngine::new(StorageConfig {
+        max_memory: 1024,
+        ..StorageConfig::default()
+    })) as Arc<dyn StorageEngine>;
+    let manager = Arc::new(
+        PersistenceManager::new(config.clone(), storage.clone())
+            .await
+            .unwrap(),
+    );
+    let dispatcher = CommandDispatcher::new(storage.clone()).with_persistence(manager.clone());
+
+    let response = dispatcher
+        .execute(Command::Set(SetCommand::new(
+            "key".to_string(),
+            b"value".to_vec(),
+        )))
+        .await;
+    assert!(matches!(response, CommandResponse::Ok));
+
+    // Over the memory limit
+    let response = dispatcher
+        .execute(Command::Set(SetCommand::new(
+            "big".to_string(),
+            vec![0; 4096],
+        )))
+        .await;
+    assert!(matches!(response, CommandResponse::Error(_)));
+
+    // WRONGTYPE against a string
+    let response = dispatcher
+        .execute(Command::SAdd(SAddCommand::new(
+            "key".to_string(),
+            vec![b"member".to_vec()],
+        )))
+        .await;
+    assert!(matches!(response, CommandResponse::Error(_)));
+    manager.sync_aof().await.unwrap();
+
+    let operations = AppendOnlyFile::new(temp_dir.path().join("test.aof"))
+        .await
+        .unwrap()
+        .read_operations()
+        .await
+        .unwrap();
+    assert_eq!(operations.len(), 1);
+    assert!(matches!(&operations[0], Operation::Put { key, .. } if key == "key"));
+
+    let new_storage =
+        Arc::new(MemoryEngine::new(StorageConfig::default())) as Arc<dyn StorageEngine>;
+    PersistenceManager::new(config, new_storage.clone())
+        .await
+        .unwrap()
+        .recover()
+        .await
+        .unwrap();
+    assert_eq!(
+        new_storage.get("key").await.unwrap(),
+        Some(b"value".to_vec())
+    );
+    assert_eq!(new_storage.get("big").await.unwrap(), None);
+}
+
+#[tokio::test]
+async fn test_recovery_skips_entries_that_fail_to_apply() {
+    let temp_dir = tempdir().unwrap();
+    let aof_path = temp_dir.path().join("test.aof");
+
+    // As logged by older versions, which wrote entries before executing them
+    let mut aof = AppendOnlyFile::new(&aof_path).await.unwrap();
+    for operation in [
+        Operation::Put {
+            key: "key".to_string(),
+            value: b"value".to_vec(),
+        },
+        Operation::SetAdd {
+            key: "key".to_string(),
+            members: vec![b"member".to_vec()],
+        },
+        Operation::Put {
+            key: "after".to_string(),
+            value: b"value".to_vec(),
+        },
+    ] {
+        aof.log_operation_sync(operation).await.unwrap();
+    }
+
+    let storage = MemoryEngine::new(StorageConfig::default());
+    let stats = RecoveryManager::new(Some(aof), None)
+        .recover(&storage)
+        .await
+        .unwrap();
+
+    assert_eq!(stats.aof_operations_replayed, 2);
+    assert_eq!(stats.aof_operations_skipped, 1);
+    assert_eq!(storage.get("key").await.unwrap(), Some(b"value".to_vec()));
+    assert_eq!(storage.get("after").await.unwrap(), Some(b"value".to_vec()));
+}
+
+#[tokio::test]
+async fn test_setex_ttl_survives_restart_and_rewrite() {
+    let temp_dir = tempdir().unwrap();
+
+    let config = PersistenceConfig {
+        aof_path: temp_dir.path().join("test.aof"),
+        fsync_policy: FsyncPolicy::Always,
+        aof_rewrite_percentage: 0,
+        aof_rewrite_min_size: 0,
+        snapshot_enabled: false,
+        snapshot_dir: temp_dir.path().join("snapshots"),
+        ..Default::default()
+    };
+
+    let storage = Arc::new(MemoryEngine::new(StorageConfig::default())) as Arc<dyn StorageEngine>;
+    let manager = Arc::new(
+        PersistenceManager::new(config.clone(), storage.clone())
+            .await
+            .unwrap(),
+    );
+    let dispatcher = CommandDispatcher::new(storage.clone()).with_persistence(manager.clone());
+
+    let response = dispatcher
+        .execute(Command::Set(
+            SetCommand::new("session".to_string(), b"value".to_vec()).with_ttl(100),
+        ))
+        .await;
+    assert_eq!(response, CommandResponse::Ok);
+    let expires_at = storage.expires_at("session").await.unwrap().unwrap();
+    assert!(expires_at.is_some());
+    manager.sync_aof().await.unwrap();
+
+    let recover = |config: PersistenceConfig| async move {
+        let storage =
+            Arc::new(MemoryEngine::new(StorageConfig::default())) as Arc<dyn StorageEngine>;
+        PersistenceManager::new(config, storage.clone())
+            .await
+            .unwrap()
+            .recover()
+            .await
+            .unwrap();
+        storage
+    };
+
+    // The exact deadline, not a fresh 100 seconds from replay
+    let recovered = recover(config.clone()).await;
+    assert_eq!(
+        recovered.expires_at("session").await.unwrap(),
+        Some(expires_at)
+    );
+
+    // Compaction keeps it too
+    manager.compact_aof().await.unwrap();
+    let recovered = recover(config).await;
+    assert_eq!(
+        recovered.expires_at("session").await.unwrap(),
+        Some(expires_at)
+    );
+}
+
+#[tokio::test]
+async fn test_default_ttl_survives_restart() {
+    let temp_dir = tempdir().unwrap();
+    let aof_path = temp_dir.path().join("test.aof");
+
+    let config = PersistenceConfig {
+        aof_path: aof_path.clone(),
+        fsync_policy: FsyncPolicy::Always,
+        aof_rewrite_percentage: 0,
+        aof_rewrite_min_size: 0,
+        snapshot_enabled: false,
+        snapshot_dir: temp_dir.path().join("snapshots"),
+        ..Default::default()
+    };
+    let storage_config = StorageConfig {
+        default_ttl: Some(100),
+        ..StorageConfig::default()
+    };
+
+    let storage = Arc::new(MemoryEngine::new(storage_config.clone())) as Arc<dyn StorageEngine>;
+    let manager = Arc::new(
+        PersistenceManager::new(config.clone(), storage.clone())
+            .await
+            .unwrap(),
+    );
+    let dispatcher = CommandDispatcher::new(storage.clone()).with_persistence(manager.clone());
+
+    // No TTL of its own, the engine applies default_ttl
+    let response = dispatcher
+        .execute(Command::Set(SetCommand::new(
+            "session".to_string(),
+            b"value".to_vec(),
+        )))
+        .await;
+    assert_eq!(response, CommandResponse::Ok);
+    let expires_at = storage.expires_at("session").await.unwrap().unwrap();
+    assert!(expires_at.is_some());
+    manager.sync_aof().await.unwrap();
+
+    // Value and deadline are one entry
+    let operations = AppendOnlyFile::new(&aof_path)
+        .await
+        .unwrap()
+        .read_operations()
+        .await
+        .unwrap();
+    assert!(
+        matches!(
+            operations.as_slice(),
+            [Operation::PutExpiring { at_millis, .. }] if Some(*at_millis) == expires_at
+        ),
+        "{:?}",
+        operations
+    );
+
+    // Replayed later, the countdown doesn't start over
+    tokio::time::sleep(Duration::from_millis(20)).await;
+    let recovered = Arc::new(MemoryEngine::new(storage_config)) as Arc<dyn StorageEngine>;
+    PersistenceManager::new(config, recovered.clone())
+        .await
+        .unwrap()
+        .recover()
+        .await
+        .unwrap();
+    assert_eq!(
+        recovered.expires_at("session").await.unwrap(),
+        Some(expires_at)
+    );
+}
+
+#[tokio::test]
+async fn test_expire_logs_the_deadline_after_max_ttl() {
+    let temp_dir = tempdir().unwrap();
+    let aof_path = temp_dir.path().join("test.aof");
+
+    let config = PersistenceConfig {
+        aof_path: aof_path.clone(),
+        fsync_policy: FsyncPolicy::Always,
+        aof_rewrite_percentage: 0,
+        aof_rewrite_min_size: 0,
+        snapshot_enabled: false,
+        snapshot_dir: temp_dir.path().join("snapshots"),
+        ..Default::default()
+    };
+
+    // Requests above 10 seconds are clamped to it
+    let storage = Arc::new(MemoryEngine::new(StorageConfig {
+        max_ttl: Some(10),
+        ..StorageConfig::default()
+    })) as Arc<dyn StorageEngine>;
+    let manager = Arc::new(
+        PersistenceManager::new(config, storage.clone())
+            .await
+            .unwrap(),
+    );
+    let dispatcher = CommandDispatcher::new(storage.clone()).with_persistence(manager.clone());
+
+    storage.set("session", b"value".to_vec()).await.unwrap();
+    let response = dispatcher
+        .execute(Command::Expire(ExpireCommand::new(
+            "session".to_string(),
+            3600,
+            false,
+            false,
+        )))
+        .await;
+    assert_eq!(response, CommandResponse::Bool(true));
+    manager.sync_aof().await.unwrap();
+
+    // What storage settled on, so a replay without the policy doesn't extend it
+    let expires_at = storage.expires_at("session").await.unwrap().unwrap();
+    let ops = AppendOnlyFile::new(&aof_path)
+        .await
+        .unwrap()
+        .read_operations()
+        .await
+        .unwrap();
+    assert!(
+        matches!(
+            ops.as_slice(),
+            [Operation::ExpireAt { at_millis, .. }] if Some(*at_millis) == expires_at
+        ),
+        "{:?}",
+        ops
+    );
+    assert!(expires_at.unwrap() <= now_millis() + 10_000);
+}
+
+#[tokio::test]
+async fn test_update_entries_keep_ttl_on_replay() {
+    let temp_dir = tempdir().unwrap();
+    let aof_path = temp_dir.path().join("test.aof");
+    let at_millis = now_millis() + 100_000;
+
+    // An INCRBYFLOAT on a volatile key, as logged
+    let mut aof = AppendOnlyFile::new(&aof_path).await.unwrap();
+    for operation in [
+        Operation::Put {
+            key: "rate".to_string(),
+            value: b"1".to_vec(),
+        },
+        Operation::ExpireAt {
+            key: "rate".to_string(),
+            at_millis,
+        },
+        Operation::Update {
+            key: "rate".to_string(),
+            value: b"1.5".to_vec(),
+        },
+    ] {
+        aof.log_operation_sync(operation).await.unwrap();
+    }
+
+    let storage = MemoryEngine::new(StorageConfig::default());
+    let stats = RecoveryManager::new(Some(aof), None)
+        .recover(&storage)
+        .await
+        .unwrap();
+
+    assert_eq!(stats.aof_operations_replayed, 3);
+    assert_eq!(storage.get("rate").await.unwrap(), Some(b"1.5".to_vec()));
+    assert_eq!(
+        storage.expires_at("rate").await.unwrap(),
+        Some(Some(at_millis))
+    );
+}
+
+#[tokio::test]
+async fn test_keys_cannot_inject_aof_entries() {
+    let temp_dir = tempdir().unwrap();
+
+    let config = PersistenceConfig {
+        aof_path: temp_dir.path().join("test.aof"),
+        fsync_policy: FsyncPolicy::Always,
+        aof_rewrite_percentage: 0,
+        aof_rewrite_min_size: 0,
+        snapshot_enabled: false,
+        snapshot_dir: temp_dir.path().join("snapshots"),
+        ..Default::default()
+    };
+
+    let storage = Arc::new(MemoryEngine::new(StorageConfig::default())) as Arc<dyn StorageEngine>;
+    let manager = Arc::new(
+        PersistenceManager::new(config.clone(), storage.clone())
+            .await
+            .unwrap(),
+    );
+    let dispatcher = CommandDispatcher::new(storage.clone()).with_persistence(manager.clone());
+
+    let response = dispatcher
+        .execute(Command::Set(SetCommand::new(
+            "victim".to_string(),
+            b"safe".to_vec(),
+        )))
+        .await;
+    assert_eq!(response, CommandResponse::Ok);
+
+    // A JSON key that would read as three AOF entries if written raw
+    let injected = "x aGk=\nFLUSHDB\nSET y";
+    let command = ProtocolParser::parse_json_command(
+        r#"{"Set":{"key":"x aGk=\nFLUSHDB\nSET y","value":[104,105],"ttl":null}}"#,
+    )
+    .unwrap();
+    assert_eq!(dispatcher.execute(command).await, CommandResponse::Ok);
+    manager.sync_aof().await.unwrap();
+
+    let contents = std::fs::read_to_string(&config.aof_path).unwrap();
+    assert_eq!(contents.lines().count(), 2);
+    assert!(!contents.lines().any(|line| line == "FLUSHDB"));
+
+    let recover = |config: PersistenceConfig| async move {
+        let storage =
+            Arc::new(MemoryEngine::new(StorageConfig::default())) as Arc<dyn StorageEngine>;
+        PersistenceManager::new(config, storage.clone())
+            .await
+            .unwrap()
+            .recover()
+            .await
+            .unwrap();
+        storage
+    };
+
+    let recovered = recover(config.clone()).await;
+    assert_eq!(
+        recovered.get("victim").await.unwrap(),
+        Some(b"safe".to_vec())
+    );
+    assert_eq!(recovered.get(injected).await.unwrap(), Some(b"hi".to_vec()));
+    assert_eq!(recovered.get("y").await.unwrap(), None);
+
+    // The rewritten file encodes the key the same way
+    manager.compact_aof().await.unwrap();
+    let recovered = recover(config).await;
+    assert_eq!(
+        recovered.get("victim").await.unwrap(),
+        Some(b"safe".to_vec())
+    );
+    assert_eq!(recovered.get(injected).await.unwrap(), Some(b"hi".to_vec()));
+}
+
+#[tokio::test]
+async fn test_quoted_keys_survive_restart() {
+    let temp_dir = tempdir().unwrap();
+
+    let config = PersistenceConfig {
+        aof_path: temp_dir.path().join("test.aof"),
+        fsync_policy: FsyncPolicy::Always,
+        aof_rewrite_percentage: 0,
+        aof_rewrite_min_size: 0,
+        snapshot_enabled: false,
+        snapshot_dir: temp_dir.path().join("snapshots"),
+        ..Default::default()
+    };
+
+    let storage = Arc::new(MemoryEngine::new(StorageConfig::default())) as Arc<dyn StorageEngine>;
+    let manager = Arc::new(
+        PersistenceManager::new(config.clone(), storage.clone())
+            .await
+            .unwrap(),
+    );
+    let dispatcher = CommandDispatcher::new(storage.clone()).with_persistence(manager.clone());
+
+    for (line, expected) in [
+        (r#"SET "my key" hello"#, CommandResponse::Ok),
+        (r#"SADD "my set" a b"#, CommandResponse::Integer(2)),
+    ] {
+        let command = ProtocolParser::parse_command(line).unwrap();
+        assert_eq!(dispatcher.execute(command).await, expected);
+    }
+    manager.sync_aof().await.unwrap();
+
+    let recovered = Arc::new(MemoryEngine::new(StorageConfig::default())) as Arc<dyn StorageEngine>;
+    PersistenceManager::new(config, recovered.clone())
+        .await
+        .unwrap()
+        .recover()
+        .await
+        .unwrap();
+
+    assert_eq!(
+        recovered.get("my key").await.unwrap(),
+        Some(b"hello".to_vec())
+    );
+    assert_eq!(recovered.get("my").await.unwrap(), None);
+    let members = decode_set(&recovered.get("my set").await.unwrap().unwrap()).unwrap();
+    assert_eq!(members.len(), 2);
 }